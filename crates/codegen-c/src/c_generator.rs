@@ -0,0 +1,960 @@
+use std::collections::HashMap;
+
+use colored::Colorize;
+use ecow::EcoString;
+use thiserror::Error;
+use tools::{
+    messages::{localize, text, Arg, Locale, Translate},
+    results::{PhyReport, PhyResult},
+};
+
+use frontend::ast::{
+    expr::{
+        AssignExpr, AwaitExpr, BinaryExpr, CallExpr, CharLiteralExpr, ComprehensionExpr, Expr,
+        GetExpr, GroupingExpr, IdentifierExpr, ImaginaryLiteralExpr, IndexExpr, IntLiteralExpr,
+        ListExpr, LogicalExpr, RealLiteralExpr, SetExpr, SpawnExpr, StrLiteralExpr, UnaryExpr,
+        UnitLiteralExpr, VisitExpr,
+    },
+    stmt::{
+        BlockStmt, ExprStmt, FnDeclStmt, ForRange, ForStmt, IfStmt, ImportStmt, ReturnStmt, Stmt,
+        VarDeclStmt, VisitStmt, WhileStmt,
+    },
+    types::TypeAnn,
+};
+
+#[derive(Error, Debug, PartialEq)]
+pub enum CGenErr {
+    #[error("values of type {0} can't be compiled to C")]
+    UnsupportedValue(&'static str),
+
+    #[error("type {0} can't be compiled to C, only int, real, bool and str can")]
+    UnsupportedType(String),
+
+    #[error("parameter '{0}' of '{1}' must be annotated to be compiled to C")]
+    UntypedParam(EcoString, EcoString),
+
+    #[error("'{0}' returns a value, annotate its return type to compile it to C")]
+    MissingReturnType(EcoString),
+
+    #[error("the type of '{0}' is unknown, annotate it to compile it to C")]
+    UnknownType(EcoString),
+
+    #[error("variable '{0}' must be declared with a value to be compiled to C")]
+    NoValue(EcoString),
+
+    #[error("the types of the operands of '{0}' are unknown or can't be mixed in C")]
+    UnknownOperands(EcoString),
+
+    #[error("'{0}' isn't a function of the file or a native with a C equivalent")]
+    UnknownFunction(String),
+
+    #[error("'print' has no value in C, call it as a statement")]
+    PrintValue,
+
+    #[error("functions must be declared at the top level to be compiled to C")]
+    NestedFn,
+
+    #[error("custom operator '{0}' can't be compiled to C")]
+    CustomOperator(EcoString),
+
+    #[error("only loops over int ranges can be compiled to C")]
+    ForItems,
+
+    #[error("contracts can't be compiled to C")]
+    Contract,
+
+    #[error("imports can't be compiled to C")]
+    Import,
+
+    #[error("tasks can't be compiled to C")]
+    Task,
+
+    #[error("only the members of 'math' can be compiled to C")]
+    Member,
+}
+
+impl PhyReport for CGenErr {
+    fn get_err_msg(&self, locale: &dyn Locale) -> String {
+        format!(
+            "{} {}",
+            text(locale, "label.c", "C codegen error:").red(),
+            localize(locale, self)
+        )
+    }
+}
+
+impl Translate for CGenErr {
+    fn category(&self) -> &'static str {
+        "c"
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            CGenErr::UnsupportedValue(..) => "UnsupportedValue",
+            CGenErr::UnsupportedType(..) => "UnsupportedType",
+            CGenErr::UntypedParam(..) => "UntypedParam",
+            CGenErr::MissingReturnType(..) => "MissingReturnType",
+            CGenErr::UnknownType(..) => "UnknownType",
+            CGenErr::NoValue(..) => "NoValue",
+            CGenErr::UnknownOperands(..) => "UnknownOperands",
+            CGenErr::UnknownFunction(..) => "UnknownFunction",
+            CGenErr::PrintValue => "PrintValue",
+            CGenErr::NestedFn => "NestedFn",
+            CGenErr::CustomOperator(..) => "CustomOperator",
+            CGenErr::ForItems => "ForItems",
+            CGenErr::Contract => "Contract",
+            CGenErr::Import => "Import",
+            CGenErr::Task => "Task",
+            CGenErr::Member => "Member",
+        }
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        match self {
+            CGenErr::UnsupportedValue(v) => vec![Arg::text(v)],
+            CGenErr::UnsupportedType(v) | CGenErr::UnknownFunction(v) => vec![Arg::text(v)],
+            CGenErr::UntypedParam(a, b) => vec![Arg::text(a), Arg::text(b)],
+            CGenErr::MissingReturnType(v)
+            | CGenErr::UnknownType(v)
+            | CGenErr::NoValue(v)
+            | CGenErr::UnknownOperands(v)
+            | CGenErr::CustomOperator(v) => vec![Arg::text(v)],
+            _ => vec![],
+        }
+    }
+}
+
+pub type PhyResCGen = PhyResult<CGenErr>;
+type CGenRes = Result<String, PhyResCGen>;
+
+// Printing follows the interpreter: the values are separated by a space
+// and the reals are written with the fewest digits reading back as the
+// same value, without exponent from 1e-7 to 1e21
+const RUNTIME: &str = r#"#include <inttypes.h>
+#include <math.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+static inline void arc_print_int(int64_t v) {
+    printf("%" PRId64, v);
+}
+
+static inline void arc_print_real(double v) {
+    char buf[64];
+    int digits = 1;
+
+    if (isnan(v) || isinf(v)) {
+        fputs(isnan(v) ? "NaN" : v > 0 ? "inf" : "-inf", stdout);
+        return;
+    }
+
+    for (; digits < 17; digits++) {
+        snprintf(buf, sizeof buf, "%.*e", digits - 1, v);
+        if (strtod(buf, NULL) == v) {
+            break;
+        }
+    }
+
+    snprintf(buf, sizeof buf, "%.*e", digits - 1, v);
+    char *e = strchr(buf, 'e');
+    int exp = atoi(e + 1);
+
+    if (v != 0 && (exp < -6 || exp >= 21)) {
+        *e = '\0';
+    } else {
+        int decimals = digits - 1 - exp;
+        snprintf(buf, sizeof buf, "%.*f", decimals > 0 ? decimals : 0, v);
+        e = NULL;
+    }
+
+    if (strchr(buf, '.')) {
+        char *end = buf + strlen(buf) - 1;
+        while (*end == '0') {
+            *end-- = '\0';
+        }
+        if (*end == '.') {
+            *end = '\0';
+        }
+    }
+
+    fputs(buf, stdout);
+    if (e) {
+        printf("e%d", exp);
+    }
+}
+
+static inline void arc_print_bool(bool v) {
+    fputs(v ? "true" : "false", stdout);
+}
+
+static inline void arc_print_str(const char *v) {
+    fputs(v, stdout);
+}
+"#;
+
+// Members of the native modules with a C equivalent, for real arguments
+const MATH: [(&str, &str); 3] = [("sqrt", "sqrt"), ("abs", "fabs"), ("pow", "pow")];
+
+// Global natives with a C equivalent, returning an int like in arc
+const NATIVES: [(&str, &str); 3] = [("floor", "floor"), ("ceil", "ceil"), ("trunc", "trunc")];
+
+// C keywords and names of the runtime that the scripts may use, followed
+// by a '_' in the generated code
+const RESERVED: [&str; 44] = [
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else",
+    "enum", "extern", "float", "for", "goto", "if", "inline", "int", "long", "register",
+    "restrict", "return", "short", "signed", "sizeof", "static", "struct", "switch", "typedef",
+    "union", "unsigned", "void", "volatile", "while", "main", "printf", "fputs", "fmod", "strcmp",
+    "sqrt", "fabs", "pow", "floor", "ceil",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CType {
+    Int,
+    Real,
+    Bool,
+    Str,
+}
+
+impl CType {
+    fn decl(self, name: &str) -> String {
+        match self {
+            CType::Int => format!("int64_t {}", name),
+            CType::Real => format!("double {}", name),
+            CType::Bool => format!("bool {}", name),
+            CType::Str => format!("const char *{}", name),
+        }
+    }
+
+    fn print_fn(self) -> &'static str {
+        match self {
+            CType::Int => "arc_print_int",
+            CType::Real => "arc_print_real",
+            CType::Bool => "arc_print_bool",
+            CType::Str => "arc_print_str",
+        }
+    }
+}
+
+// Types of the parameters, and of the returned value if there is one
+struct Signature {
+    params: Vec<CType>,
+    ret: Option<CType>,
+}
+
+// Compiles the typed programs to a single C file, with the runtime it
+// needs on top. Only the values with a native C type are supported:
+// ints, reals, bools and strings, the later only to be printed and
+// compared. The functions are declared at the top level, with their
+// parameters annotated, and the other top-level statements make 'main'.
+// The generated code is as fast as the arithmetic it does but doesn't
+// check it: an int overflow or a division by zero is undefined in C
+#[derive(Default)]
+pub struct CGenerator {
+    depth: usize,
+    scopes: Vec<HashMap<EcoString, CType>>,
+    fns: HashMap<EcoString, Signature>,
+    // Function being generated, None for 'main'
+    current: Option<EcoString>,
+}
+
+impl CGenerator {
+    // All the errors are collected before returning, in source order. The
+    // statements of 'main' are generated first, the functions can use the
+    // global variables declared after them
+    pub fn generate(&mut self, stmts: &[Stmt]) -> Result<String, Vec<PhyResCGen>> {
+        let mut errs = vec![];
+        self.scopes = vec![HashMap::new()];
+
+        for stmt in stmts {
+            if let Stmt::FnDecl(f) = stmt {
+                match signature(f) {
+                    Ok(sig) => {
+                        self.fns.insert(f.name.clone(), sig);
+                    }
+                    Err(e) => errs.push(e),
+                }
+            }
+        }
+
+        let (mut globals, mut main) = (String::new(), String::new());
+        self.depth = 1;
+
+        for stmt in stmts.iter().filter(|s| !matches!(s, Stmt::FnDecl(_))) {
+            let res = match stmt {
+                Stmt::VarDecl(v) => self.gen_global(v, &mut globals),
+                s => s.accept(self),
+            };
+
+            match res {
+                Ok(code) => main.push_str(&format!("    {}\n", code)),
+                Err(e) => errs.push(e),
+            }
+        }
+
+        let (mut protos, mut fns) = (String::new(), String::new());
+        self.depth = 0;
+
+        for stmt in stmts {
+            // The functions without a signature are already reported
+            let Stmt::FnDecl(f) = stmt else { continue };
+            if self.fns.contains_key(&f.name) {
+                match self.gen_fn(f) {
+                    Ok((proto, code)) => {
+                        protos.push_str(&format!("{};\n", proto));
+                        fns.push_str(&format!("\n{}\n", code));
+                    }
+                    Err(e) => errs.push(e),
+                }
+            }
+        }
+
+        if !errs.is_empty() {
+            errs.sort_by_key(|e| e.loc.as_ref().map(|l| l.start));
+            return Err(errs);
+        }
+
+        let mut code = String::from(RUNTIME);
+
+        for part in [globals, protos] {
+            if !part.is_empty() {
+                code.push_str(&format!("\n{}", part));
+            }
+        }
+
+        code.push_str(&fns);
+        code.push_str(&format!("\nint main(void) {{\n{}    return 0;\n}}\n", main));
+
+        Ok(code)
+    }
+
+    fn indent(&self) -> String {
+        "    ".repeat(self.depth)
+    }
+
+    fn gen_stmts(&mut self, stmts: &[Stmt]) -> CGenRes {
+        self.depth += 1;
+        self.scopes.push(HashMap::new());
+        let body = stmts
+            .iter()
+            .map(|s| s.accept(self).map(|code| format!("{}{}\n", self.indent(), code)))
+            .collect::<Result<String, PhyResCGen>>();
+        self.scopes.pop();
+        self.depth -= 1;
+
+        Ok(format!("{{\n{}{}}}", body?, self.indent()))
+    }
+
+    fn gen_body(&mut self, stmt: &Stmt) -> CGenRes {
+        match stmt {
+            Stmt::Block(b) => self.gen_stmts(&b.stmts),
+            s => self.gen_stmts(std::slice::from_ref(s)),
+        }
+    }
+
+    fn gen_args(&mut self, args: &[Expr]) -> CGenRes {
+        let args = args
+            .iter()
+            .map(|a| a.accept(self))
+            .collect::<Result<Vec<String>, PhyResCGen>>()?;
+
+        Ok(args.join(", "))
+    }
+
+    // Declared at file scope to be seen by the functions, and set in 'main'
+    fn gen_global(&mut self, stmt: &VarDeclStmt, globals: &mut String) -> CGenRes {
+        let (ty, value) = self.var_value(stmt)?;
+        globals.push_str(&format!("static {};\n", ty.decl(&c_name(&stmt.name))));
+        self.declare(&stmt.name, ty);
+
+        Ok(format!("{} = {};", c_name(&stmt.name), value))
+    }
+
+    // Type and code of the value of the variable. The value is generated
+    // first, to report the values C doesn't have before their type
+    fn var_value(&mut self, stmt: &VarDeclStmt) -> Result<(CType, String), PhyResCGen> {
+        let Some(value) = &stmt.value else {
+            return Err(PhyResult::new(CGenErr::NoValue(stmt.name.clone()), Some(stmt.loc.clone())));
+        };
+
+        let code = value.accept(self)?;
+        let ty = match &stmt.ty {
+            Some(ann) => c_type(ann)?,
+            None => self.type_of(value).ok_or_else(|| {
+                PhyResult::new(CGenErr::UnknownType(stmt.name.clone()), Some(stmt.loc.clone()))
+            })?,
+        };
+
+        Ok((ty, code))
+    }
+
+    fn gen_fn(&mut self, stmt: &FnDeclStmt) -> Result<(String, String), PhyResCGen> {
+        if !stmt.requires.is_empty() || !stmt.ensures.is_empty() {
+            return Err(PhyResult::new(CGenErr::Contract, Some(stmt.name_loc.clone())));
+        }
+
+        let sig = &self.fns[&stmt.name];
+        let params = stmt.params.iter().zip(&sig.params);
+        let scope = params.clone().map(|(p, ty)| (p.clone(), *ty)).collect();
+
+        let params = match stmt.params.is_empty() {
+            true => "void".to_string(),
+            false => params.map(|(p, ty)| ty.decl(&c_name(p))).collect::<Vec<_>>().join(", "),
+        };
+        let proto = match sig.ret {
+            Some(ty) => format!("static {}({})", ty.decl(&c_name(&stmt.name)), params),
+            None => format!("static void {}({})", c_name(&stmt.name), params),
+        };
+
+        self.current = Some(stmt.name.clone());
+        self.scopes.push(scope);
+        let body = self.gen_stmts(&stmt.body);
+        self.scopes.pop();
+        self.current = None;
+
+        let code = format!("{} {}", proto, body?);
+        Ok((proto, code))
+    }
+
+    // The values are printed with the function of their type
+    fn gen_print(&mut self, expr: &CallExpr) -> CGenRes {
+        let mut parts = vec![];
+
+        for (i, arg) in expr.args.iter().enumerate() {
+            if i > 0 {
+                parts.push("putchar(' ')".to_string());
+            }
+
+            let ty = self.type_of(arg).ok_or_else(|| {
+                PhyResult::new(CGenErr::UnknownType(arg.to_string().into()), Some(arg.get_loc()))
+            })?;
+            parts.push(format!("{}({})", ty.print_fn(), arg.accept(self)?));
+        }
+
+        parts.push("putchar('\\n')".into());
+        Ok(format!("{};", parts.join(", ")))
+    }
+
+    fn declare(&mut self, name: &EcoString, ty: CType) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.clone(), ty);
+        }
+    }
+
+    fn type_of(&self, expr: &Expr) -> Option<CType> {
+        match expr {
+            Expr::IntLiteral(_) => Some(CType::Int),
+            Expr::RealLiteral(_) => Some(CType::Real),
+            Expr::StrLiteral(_) => Some(CType::Str),
+            Expr::Grouping(e) => self.type_of(&e.expr),
+            Expr::Unary(e) if e.operator == "!" => Some(CType::Bool),
+            Expr::Unary(e) => self.type_of(&e.right),
+            Expr::Logical(_) => Some(CType::Bool),
+            Expr::Assign(e) => self.var_type(&e.name),
+            Expr::Identifier(e) if e.name == "true" || e.name == "false" => Some(CType::Bool),
+            Expr::Identifier(e) => self.var_type(&e.name),
+            Expr::Binary(e) => {
+                let (left, right) = (self.type_of(&e.left)?, self.type_of(&e.right)?);
+
+                match (left, right, e.operator.as_str()) {
+                    (_, _, "==" | "!=") if left == right || is_number(left) && is_number(right) => {
+                        Some(CType::Bool)
+                    }
+                    (l, r, "<" | "<=" | ">" | ">=") if is_number(l) && is_number(r) => {
+                        Some(CType::Bool)
+                    }
+                    (CType::Int, CType::Int, "+" | "-" | "*" | "/" | "%") => Some(CType::Int),
+                    (l, r, "+" | "-" | "*" | "/" | "%") if is_number(l) && is_number(r) => {
+                        Some(CType::Real)
+                    }
+                    _ => None,
+                }
+            }
+            Expr::Call(e) => match e.callee.as_ref() {
+                Expr::Identifier(f) if NATIVES.iter().any(|(n, _)| *n == f.name) => {
+                    Some(CType::Int)
+                }
+                Expr::Identifier(f) => self.fns.get(&f.name)?.ret,
+                Expr::Get(g) if is_math(&g.object) && g.name == "abs" => {
+                    e.args.first().and_then(|a| self.type_of(a))
+                }
+                Expr::Get(g) if is_math(&g.object) => Some(CType::Real),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn var_type(&self, name: &EcoString) -> Option<CType> {
+        self.scopes.iter().rev().find_map(|s| s.get(name)).copied()
+    }
+}
+
+fn is_number(ty: CType) -> bool {
+    matches!(ty, CType::Int | CType::Real)
+}
+
+fn is_math(expr: &Expr) -> bool {
+    matches!(expr, Expr::Identifier(m) if m.name == "math")
+}
+
+fn c_type(ann: &TypeAnn) -> Result<CType, PhyResCGen> {
+    match ann {
+        TypeAnn::Named(name, _) if name == "int" => Ok(CType::Int),
+        TypeAnn::Named(name, _) if name == "real" => Ok(CType::Real),
+        TypeAnn::Named(name, _) if name == "bool" => Ok(CType::Bool),
+        TypeAnn::Named(name, _) if name == "str" => Ok(CType::Str),
+        ann => Err(PhyResult::new(CGenErr::UnsupportedType(ann.to_string()), Some(ann.get_loc()))),
+    }
+}
+
+fn signature(stmt: &FnDeclStmt) -> Result<Signature, PhyResCGen> {
+    if stmt.name.starts_with('<') {
+        return Err(PhyResult::new(
+            CGenErr::CustomOperator(stmt.name.clone()),
+            Some(stmt.name_loc.clone()),
+        ));
+    }
+
+    let mut params = vec![];
+
+    for (i, (param, ty)) in stmt.params.iter().zip(&stmt.param_types).enumerate() {
+        match ty {
+            Some(ann) => params.push(c_type(ann)?),
+            None => {
+                return Err(PhyResult::new(
+                    CGenErr::UntypedParam(param.clone(), stmt.name.clone()),
+                    Some(stmt.param_locs[i].clone()),
+                ))
+            }
+        }
+    }
+
+    let ret = stmt.return_type.as_ref().map(c_type).transpose()?;
+
+    Ok(Signature { params, ret })
+}
+
+// Macro expansions rename their variables with a '#', like 'i#1'
+fn c_name(name: &str) -> String {
+    let name = name.replace('#', "__");
+
+    match RESERVED.contains(&name.as_str()) {
+        true => format!("{}_", name),
+        false => name,
+    }
+}
+
+impl VisitStmt<String, CGenErr> for CGenerator {
+    fn visit_expr_stmt(&mut self, stmt: &ExprStmt) -> CGenRes {
+        match &stmt.expr {
+            Expr::Call(c) if matches!(c.callee.as_ref(), Expr::Identifier(f) if f.name == "print") => {
+                self.gen_print(c)
+            }
+            e => Ok(format!("{};", e.accept(self)?)),
+        }
+    }
+
+    fn visit_var_decl_stmt(&mut self, stmt: &VarDeclStmt) -> CGenRes {
+        let (ty, value) = self.var_value(stmt)?;
+        self.declare(&stmt.name, ty);
+
+        Ok(format!("{} = {};", ty.decl(&c_name(&stmt.name)), value))
+    }
+
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> CGenRes {
+        self.gen_stmts(&stmt.stmts)
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) -> CGenRes {
+        let mut code = format!("if ({}) ", stmt.condition.accept(self)?);
+
+        match &stmt.then_branch {
+            Some(t) => code.push_str(&self.gen_body(t)?),
+            None => code.push_str("{}"),
+        }
+
+        if let Some(e) = &stmt.else_branch {
+            code.push_str(&format!(" else {}", self.gen_body(e)?));
+        }
+
+        Ok(code)
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) -> CGenRes {
+        Ok(format!(
+            "while ({}) {}",
+            stmt.condition.accept(self)?,
+            self.gen_body(&stmt.body)?
+        ))
+    }
+
+    // The end is evaluated once, in a variable named after the placeholder
+    fn visit_for_stmt(&mut self, stmt: &ForStmt) -> CGenRes {
+        let name = c_name(&stmt.placeholder.name);
+
+        let head = match &stmt.range {
+            ForRange::Ints(start, Some(end)) => {
+                format!("int64_t {name} = {start}; {name} < {end}; {name}++")
+            }
+            ForRange::Ints(end, None) => format!("int64_t {name} = 0; {name} < {end}; {name}++"),
+            ForRange::Bounds(start, end) => format!(
+                "int64_t {name} = {}, {name}__end = {}; {name} < {name}__end; {name}++",
+                start.accept(self)?,
+                end.accept(self)?
+            ),
+            ForRange::Items(_) => {
+                return Err(PhyResult::new(CGenErr::ForItems, Some(stmt.loc.clone())))
+            }
+        };
+
+        self.scopes.push(HashMap::from([(stmt.placeholder.name.clone(), CType::Int)]));
+        let body = self.gen_body(&stmt.body);
+        self.scopes.pop();
+
+        Ok(format!("for ({head}) {}", body?))
+    }
+
+    fn visit_fn_decl_stmt(&mut self, stmt: &FnDeclStmt) -> CGenRes {
+        Err(PhyResult::new(CGenErr::NestedFn, Some(stmt.name_loc.clone())))
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> CGenRes {
+        let Some(value) = &stmt.value else {
+            return Ok("return;".into());
+        };
+
+        match &self.current {
+            Some(f) if self.fns[f].ret.is_none() => Err(PhyResult::new(
+                CGenErr::MissingReturnType(f.clone()),
+                Some(stmt.loc.clone()),
+            )),
+            _ => Ok(format!("return {};", value.accept(self)?)),
+        }
+    }
+
+    fn visit_import_stmt(&mut self, stmt: &ImportStmt) -> CGenRes {
+        Err(PhyResult::new(CGenErr::Import, Some(stmt.loc.clone())))
+    }
+}
+
+impl VisitExpr<String, CGenErr> for CGenerator {
+    // Ints and reals mix like in arc, the C conversions give the same
+    // result. The strings are compared by their content
+    fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> CGenRes {
+        let op = expr.operator.as_str();
+
+        if !matches!(op, "+" | "-" | "*" | "/" | "%" | "==" | "!=" | "<" | "<=" | ">" | ">=") {
+            return Err(PhyResult::new(
+                CGenErr::CustomOperator(expr.operator.clone()),
+                Some(expr.loc.clone()),
+            ));
+        }
+
+        let (left, right) = (expr.left.accept(self)?, expr.right.accept(self)?);
+        let types = (self.type_of(&expr.left), self.type_of(&expr.right));
+
+        if self.type_of(&Expr::Binary(expr.clone())).is_none() {
+            return Err(PhyResult::new(
+                CGenErr::UnknownOperands(expr.operator.clone()),
+                Some(expr.loc.clone()),
+            ));
+        }
+
+        match (op, types) {
+            (_, (Some(CType::Str), _)) => Ok(format!("strcmp({}, {}) {} 0", left, right, op)),
+            ("%", (Some(CType::Real), _) | (_, Some(CType::Real))) => {
+                Ok(format!("fmod({}, {})", left, right))
+            }
+            _ => Ok(format!("{} {} {}", left, op, right)),
+        }
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> CGenRes {
+        Ok(format!("({})", expr.expr.accept(self)?))
+    }
+
+    // The smallest int has no literal in C, it is written as an operation
+    fn visit_int_literal_expr(&mut self, expr: &IntLiteralExpr) -> CGenRes {
+        match expr.value {
+            i64::MIN => Ok("INT64_MIN".into()),
+            v => Ok(format!("INT64_C({})", v)).map(|c| match v.unsigned_abs() <= i32::MAX as u64 {
+                true => v.to_string(),
+                false => c,
+            }),
+        }
+    }
+
+    fn visit_real_literal_expr(&mut self, expr: &RealLiteralExpr) -> CGenRes {
+        Ok(format!("{:?}", expr.value))
+    }
+
+    fn visit_imaginary_literal_expr(&mut self, expr: &ImaginaryLiteralExpr) -> CGenRes {
+        Err(PhyResult::new(CGenErr::UnsupportedValue("complex"), Some(expr.loc.clone())))
+    }
+
+    fn visit_str_literal_expr(&mut self, expr: &StrLiteralExpr) -> CGenRes {
+        let escaped = expr
+            .value
+            .replace("\\", "\\\\")
+            .replace("\"", "\\\"")
+            .replace("\n", "\\n")
+            .replace("\t", "\\t");
+
+        Ok(format!("\"{}\"", escaped))
+    }
+
+    fn visit_char_literal_expr(&mut self, expr: &CharLiteralExpr) -> CGenRes {
+        Err(PhyResult::new(CGenErr::UnsupportedValue("char"), Some(expr.loc.clone())))
+    }
+
+    fn visit_unit_literal_expr(&mut self, expr: &UnitLiteralExpr) -> CGenRes {
+        Err(PhyResult::new(CGenErr::UnsupportedValue("quantity"), Some(expr.loc.clone())))
+    }
+
+    fn visit_identifier_expr(&mut self, expr: &IdentifierExpr) -> CGenRes {
+        match expr.name.as_str() {
+            "true" | "false" => Ok(expr.name.to_string()),
+            "null" => Err(PhyResult::new(CGenErr::UnsupportedValue("null"), Some(expr.loc.clone()))),
+            _ if self.var_type(&expr.name).is_some() => Ok(c_name(&expr.name)),
+            // Functions are only called
+            _ if self.fns.contains_key(&expr.name) => Err(PhyResult::new(
+                CGenErr::UnsupportedValue("fn"),
+                Some(expr.loc.clone()),
+            )),
+            _ => Err(PhyResult::new(CGenErr::UnknownType(expr.name.clone()), Some(expr.loc.clone()))),
+        }
+    }
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> CGenRes {
+        Ok(format!("{}{}", expr.operator, expr.right.accept(self)?))
+    }
+
+    fn visit_assign_expr(&mut self, expr: &AssignExpr) -> CGenRes {
+        Ok(format!("{} = {}", c_name(&expr.name), expr.value.accept(self)?))
+    }
+
+    fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> CGenRes {
+        let operator = match expr.operator.as_str() {
+            "and" => "&&",
+            _ => "||",
+        };
+
+        Ok(format!(
+            "{} {} {}",
+            expr.left.accept(self)?,
+            operator,
+            expr.right.accept(self)?
+        ))
+    }
+
+    // 'floor', 'ceil' and 'trunc' give ints like in arc, the members of
+    // 'math' take reals. 'math.abs' keeps the type of its argument
+    fn visit_call_expr(&mut self, expr: &CallExpr) -> CGenRes {
+        let args = self.gen_args(&expr.args)?;
+
+        match expr.callee.as_ref() {
+            Expr::Identifier(f) if f.name == "print" => {
+                Err(PhyResult::new(CGenErr::PrintValue, Some(expr.loc.clone())))
+            }
+            Expr::Identifier(f) if self.fns.contains_key(&f.name) => {
+                Ok(format!("{}({})", c_name(&f.name), args))
+            }
+            Expr::Identifier(f) => match NATIVES.iter().find(|(n, _)| *n == f.name) {
+                Some((_, c)) => Ok(format!("(int64_t){}({})", c, args)),
+                None => Err(PhyResult::new(
+                    CGenErr::UnknownFunction(f.name.to_string()),
+                    Some(expr.loc.clone()),
+                )),
+            },
+            Expr::Get(g) if is_math(&g.object) && g.name == "abs" => {
+                match expr.args.first().and_then(|a| self.type_of(a)) {
+                    Some(CType::Int) => Ok(format!("llabs({})", args)),
+                    _ => Ok(format!("fabs({})", args)),
+                }
+            }
+            Expr::Get(g) if is_math(&g.object) => {
+                match MATH.iter().find(|(n, _)| *n == g.name) {
+                    Some((_, c)) => Ok(format!("{}({})", c, args)),
+                    None => Err(PhyResult::new(
+                        CGenErr::UnknownFunction(format!("math.{}", g.name)),
+                        Some(expr.loc.clone()),
+                    )),
+                }
+            }
+            callee => Err(PhyResult::new(
+                CGenErr::UnknownFunction(callee.to_string()),
+                Some(expr.loc.clone()),
+            )),
+        }
+    }
+
+    // The members of 'math' are only called
+    fn visit_get_expr(&mut self, expr: &GetExpr) -> CGenRes {
+        Err(PhyResult::new(CGenErr::Member, Some(expr.loc.clone())))
+    }
+
+    fn visit_set_expr(&mut self, expr: &SetExpr) -> CGenRes {
+        Err(PhyResult::new(CGenErr::Member, Some(expr.loc.clone())))
+    }
+
+    fn visit_index_expr(&mut self, expr: &IndexExpr) -> CGenRes {
+        Err(PhyResult::new(CGenErr::UnsupportedValue("list"), Some(expr.loc.clone())))
+    }
+
+    fn visit_list_expr(&mut self, expr: &ListExpr) -> CGenRes {
+        Err(PhyResult::new(CGenErr::UnsupportedValue("list"), Some(expr.loc.clone())))
+    }
+
+    fn visit_comprehension_expr(&mut self, expr: &ComprehensionExpr) -> CGenRes {
+        Err(PhyResult::new(CGenErr::UnsupportedValue("list"), Some(expr.loc.clone())))
+    }
+
+    fn visit_spawn_expr(&mut self, expr: &SpawnExpr) -> CGenRes {
+        Err(PhyResult::new(CGenErr::Task, Some(expr.loc.clone())))
+    }
+
+    fn visit_await_expr(&mut self, expr: &AwaitExpr) -> CGenRes {
+        Err(PhyResult::new(CGenErr::Task, Some(expr.loc.clone())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::lex_parse_gen;
+
+    use super::{CGenErr, RUNTIME};
+
+    // Generated code without the runtime
+    fn gen(code: &str) -> String {
+        let res = lex_parse_gen(code).unwrap();
+        res.strip_prefix(RUNTIME).unwrap().to_string()
+    }
+
+    fn errs(code: &str) -> Vec<CGenErr> {
+        lex_parse_gen(code).unwrap_err().into_iter().map(|e| e.err).collect()
+    }
+
+    #[test]
+    fn statements() {
+        let code = "
+var total: int = 0
+fn add(a: int, b: int) -> int {
+    return a + b
+}
+for i in 1..4 {
+    if i != 2 and true {
+        total = add(total, i)
+    } else {
+        print(\"skip\", i)
+    }
+}
+while total < 10. {
+    total = total * (2 - 1) + 1
+}
+";
+        let expected = "
+static int64_t total;
+
+static int64_t add(int64_t a, int64_t b);
+
+static int64_t add(int64_t a, int64_t b) {
+    return a + b;
+}
+
+int main(void) {
+    total = 0;
+    for (int64_t i = 1; i < 4; i++) {
+        if (i != 2 && true) {
+            total = add(total, i);
+        } else {
+            arc_print_str(\"skip\"), putchar(' '), arc_print_int(i), putchar('\\n');
+        }
+    }
+    while (total < 10.0) {
+        total = total * (2 - 1) + 1;
+    }
+    return 0;
+}
+";
+        assert_eq!(gen(code), expected);
+    }
+
+    #[test]
+    fn operators() {
+        let code = "
+fn f(x: real, s: str) {
+    var r = x % 2
+    var same = s == \"a\"
+    var n = floor(math.sqrt(x)) + math.abs(-2)
+    for j in 0..n + 1 { print(j, r, same) }
+}
+";
+        let expected = "
+static void f(double x, const char *s);
+
+static void f(double x, const char *s) {
+    double r = fmod(x, 2);
+    bool same = strcmp(s, \"a\") == 0;
+    int64_t n = (int64_t)floor(sqrt(x)) + llabs(-2);
+    for (int64_t j = 0, j__end = n + 1; j < j__end; j++) {
+        arc_print_int(j), putchar(' '), arc_print_real(r), putchar(' '), arc_print_bool(same), putchar('\\n');
+    }
+}
+
+int main(void) {
+    return 0;
+}
+";
+        assert_eq!(gen(code), expected);
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(
+            gen("var int = 1\nprint(int)"),
+            "
+static int64_t int_;
+
+int main(void) {
+    int_ = 1;
+    arc_print_int(int_), putchar('\\n');
+    return 0;
+}
+"
+        );
+    }
+
+    #[test]
+    fn unsupported() {
+        assert_eq!(
+            errs("var l = [1]\nimport geo\nspawn f()\nvar c = 'a'"),
+            vec![
+                CGenErr::UnsupportedValue("list"),
+                CGenErr::Import,
+                CGenErr::Task,
+                CGenErr::UnsupportedValue("char"),
+            ]
+        );
+        assert_eq!(
+            errs("fn f(a) { return a }\nfn g() { return 1 }\nfn h(l: [int]) {}"),
+            vec![
+                CGenErr::UntypedParam("a".into(), "f".into()),
+                CGenErr::MissingReturnType("g".into()),
+                CGenErr::UnsupportedType("[int]".into()),
+            ]
+        );
+        assert_eq!(
+            errs("var x\nprint(y)\nfor x in l {}\nvar p = print(1)"),
+            vec![
+                CGenErr::NoValue("x".into()),
+                CGenErr::UnknownType("y".into()),
+                CGenErr::ForItems,
+                CGenErr::PrintValue,
+            ]
+        );
+    }
+}
@@ -0,0 +1,3 @@
+pub mod c_generator;
+#[cfg(test)]
+mod utils;
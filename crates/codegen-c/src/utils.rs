@@ -0,0 +1,10 @@
+use frontend::parser::utils::lex_and_parse;
+use tools::results::PhyResult;
+
+use crate::c_generator::{CGenErr, CGenerator};
+
+pub fn lex_parse_gen(code: &str) -> Result<String, Vec<PhyResult<CGenErr>>> {
+    let nodes = lex_and_parse(code).unwrap();
+    let mut generator = CGenerator::default();
+    generator.generate(&nodes)
+}
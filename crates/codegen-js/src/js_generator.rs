@@ -0,0 +1,857 @@
+use std::collections::{HashMap, HashSet};
+
+use colored::Colorize;
+use ecow::EcoString;
+use thiserror::Error;
+use tools::{
+    messages::{localize, text, Arg, Locale, Translate},
+    results::{PhyReport, PhyResult},
+};
+
+use frontend::ast::{
+    expr::{
+        AssignExpr, AwaitExpr, BinaryExpr, CallExpr, CharLiteralExpr, ComprehensionExpr, Expr,
+        GetExpr, GroupingExpr, IdentifierExpr, ImaginaryLiteralExpr, IndexExpr, IntLiteralExpr,
+        ListExpr, LogicalExpr, RealLiteralExpr, SetExpr, SpawnExpr, StrLiteralExpr, UnaryExpr,
+        UnitLiteralExpr, VisitExpr,
+    },
+    stmt::{
+        BlockStmt, ExprStmt, FnDeclStmt, ForRange, ForStmt, IfStmt, ImportStmt, ReturnStmt, Stmt,
+        VarDeclStmt, VisitStmt, WhileStmt,
+    },
+    types::TypeAnn,
+};
+
+#[derive(Error, Debug, PartialEq)]
+pub enum JsGenErr {
+    #[error("quantities with units can't be translated to JavaScript")]
+    UnitLiteral,
+
+    #[error("complex numbers can't be translated to JavaScript")]
+    ImaginaryLiteral,
+
+    #[error("member '{0}' of module '{1}' has no JavaScript equivalent")]
+    UnsupportedNative(EcoString, EcoString),
+
+    #[error("tasks can't be translated to JavaScript")]
+    Task,
+
+    #[error("the types of the operands of '{0}' are unknown, annotate them to translate it")]
+    UnknownOperands(EcoString),
+}
+
+impl PhyReport for JsGenErr {
+    fn get_err_msg(&self, locale: &dyn Locale) -> String {
+        format!(
+            "{} {}",
+            text(locale, "label.js", "JS codegen error:").red(),
+            localize(locale, self)
+        )
+    }
+}
+
+impl Translate for JsGenErr {
+    fn category(&self) -> &'static str {
+        "js"
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            JsGenErr::UnitLiteral => "UnitLiteral",
+            JsGenErr::ImaginaryLiteral => "ImaginaryLiteral",
+            JsGenErr::UnsupportedNative(..) => "UnsupportedNative",
+            JsGenErr::Task => "Task",
+            JsGenErr::UnknownOperands(..) => "UnknownOperands",
+        }
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        match self {
+            JsGenErr::UnsupportedNative(a, b) => vec![Arg::text(a), Arg::text(b)],
+            JsGenErr::UnknownOperands(v) => vec![Arg::text(v)],
+            _ => vec![],
+        }
+    }
+}
+
+pub type PhyResJsGen = PhyResult<JsGenErr>;
+type JsGenRes = Result<String, PhyResJsGen>;
+
+// Natives modules of the interpreter and the JavaScript equivalent of the
+// members that have one
+const MODULES: [(&str, &[(&str, &str)]); 4] = [
+    ("math", &[("sqrt", "Math.sqrt"), ("abs", "Math.abs"), ("pow", "Math.pow")]),
+    ("str", &[]),
+    ("list", &[]),
+    ("fs", &[]),
+];
+
+// Global natives with a JavaScript equivalent
+const NATIVES: [(&str, &str); 4] = [
+    ("print", "console.log"),
+    ("floor", "Math.floor"),
+    ("ceil", "Math.ceil"),
+    ("trunc", "Math.trunc"),
+];
+
+// Types the generator needs to know to pick the JavaScript of an operator
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum JsType {
+    Int,
+    Real,
+    Str,
+    List,
+}
+
+// Translates the AST to JavaScript, keeping the structure of the source so
+// that the output stays readable. Ints and reals both become JavaScript
+// numbers, so '/' and '*' are only translated when the types of their
+// operands are known, from the literals and the annotations. Constructs
+// without a JavaScript equivalent are reported instead of generating wrong
+// code
+#[derive(Default)]
+pub struct JsGenerator {
+    depth: usize,
+    // Type of the variables in scope, None when it is unknown
+    scopes: Vec<HashMap<EcoString, Option<JsType>>>,
+    // Variables assigned somewhere, whose type is only known when they are
+    // annotated
+    assigned: HashSet<EcoString>,
+}
+
+impl JsGenerator {
+    // All the errors are collected before returning, like the parser does
+    pub fn generate(&mut self, stmts: &[Stmt]) -> Result<String, Vec<PhyResJsGen>> {
+        let mut code = String::new();
+        let mut errs = vec![];
+
+        stmts.iter().for_each(|s| stmt_assigns(s, &mut self.assigned));
+        self.scopes = vec![HashMap::new()];
+
+        for stmt in stmts {
+            match stmt.accept(self) {
+                Ok(s) => {
+                    code.push_str(&s);
+                    code.push('\n');
+                }
+                Err(e) => errs.push(e),
+            }
+        }
+
+        match errs.is_empty() {
+            true => Ok(code),
+            false => Err(errs),
+        }
+    }
+
+    fn indent(&self) -> String {
+        "    ".repeat(self.depth)
+    }
+
+    fn gen_stmts(&mut self, stmts: &[Stmt]) -> JsGenRes {
+        self.depth += 1;
+        self.scopes.push(HashMap::new());
+        let body = stmts
+            .iter()
+            .map(|s| s.accept(self).map(|code| format!("{}{}\n", self.indent(), code)))
+            .collect::<Result<String, PhyResJsGen>>();
+        self.scopes.pop();
+        self.depth -= 1;
+
+        Ok(format!("{{\n{}{}}}", body?, self.indent()))
+    }
+
+    // Bodies are always braced, even when the parser kept a single statement
+    fn gen_body(&mut self, stmt: &Stmt) -> JsGenRes {
+        match stmt {
+            Stmt::Block(b) => self.gen_stmts(&b.stmts),
+            s => self.gen_stmts(std::slice::from_ref(s)),
+        }
+    }
+
+    fn gen_args(&mut self, args: &[Expr]) -> JsGenRes {
+        let args = args
+            .iter()
+            .map(|a| a.accept(self))
+            .collect::<Result<Vec<String>, PhyResJsGen>>()?;
+
+        Ok(args.join(", "))
+    }
+
+    fn declare(&mut self, name: &EcoString, ty: Option<JsType>) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.clone(), ty);
+        }
+    }
+
+    // The annotation wins, the value only tells the type of the variables
+    // that are never assigned
+    fn declare_var(&mut self, name: &EcoString, ann: Option<&TypeAnn>, value: Option<&Expr>) {
+        let ty = match (ann, value) {
+            (Some(ann), _) => ann_type(ann),
+            (None, Some(v)) if !self.assigned.contains(name) => self.type_of(v),
+            _ => None,
+        };
+
+        self.declare(name, ty);
+    }
+
+    fn type_of(&self, expr: &Expr) -> Option<JsType> {
+        match expr {
+            Expr::IntLiteral(_) => Some(JsType::Int),
+            Expr::RealLiteral(_) => Some(JsType::Real),
+            Expr::StrLiteral(_) => Some(JsType::Str),
+            Expr::List(_) | Expr::Comprehension(_) => Some(JsType::List),
+            Expr::Grouping(e) => self.type_of(&e.expr),
+            Expr::Unary(e) if e.operator == "-" => self.type_of(&e.right),
+            Expr::Identifier(e) => self.scopes.iter().rev().find_map(|s| s.get(&e.name)).copied()?,
+            Expr::Binary(e) => {
+                let (left, right) = (self.type_of(&e.left)?, self.type_of(&e.right)?);
+                let arithmetic = matches!(e.operator.as_str(), "+" | "-" | "*" | "/" | "%");
+
+                match (left, right, e.operator.as_str()) {
+                    (JsType::Int, JsType::Int, _) if arithmetic => Some(JsType::Int),
+                    (l, r, _) if arithmetic && is_number(l) && is_number(r) => Some(JsType::Real),
+                    (JsType::Str, JsType::Str, "+")
+                    | (JsType::Str, JsType::Int, "*")
+                    | (JsType::Int, JsType::Str, "*") => Some(JsType::Str),
+                    (JsType::List, JsType::Int, "*") => Some(JsType::List),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+fn is_number(ty: JsType) -> bool {
+    matches!(ty, JsType::Int | JsType::Real)
+}
+
+fn ann_type(ann: &TypeAnn) -> Option<JsType> {
+    match ann {
+        TypeAnn::Named(name, _) => match name.as_str() {
+            "int" => Some(JsType::Int),
+            "real" => Some(JsType::Real),
+            "str" => Some(JsType::Str),
+            _ => None,
+        },
+        TypeAnn::List(..) => Some(JsType::List),
+        _ => None,
+    }
+}
+
+// Names of all the variables assigned in the statement
+fn stmt_assigns(stmt: &Stmt, names: &mut HashSet<EcoString>) {
+    match stmt {
+        Stmt::Expr(s) => expr_assigns(&s.expr, names),
+        Stmt::VarDecl(s) => s.value.iter().for_each(|v| expr_assigns(v, names)),
+        Stmt::Block(s) => s.stmts.iter().for_each(|s| stmt_assigns(s, names)),
+        Stmt::If(s) => {
+            expr_assigns(&s.condition, names);
+            s.then_branch.iter().chain(&s.else_branch).for_each(|b| stmt_assigns(b, names));
+        }
+        Stmt::While(s) => {
+            expr_assigns(&s.condition, names);
+            stmt_assigns(&s.body, names);
+        }
+        Stmt::For(s) => {
+            s.range.exprs().for_each(|e| expr_assigns(e, names));
+            stmt_assigns(&s.body, names);
+        }
+        Stmt::FnDecl(s) => s.body.iter().for_each(|s| stmt_assigns(s, names)),
+        Stmt::Return(s) => s.value.iter().for_each(|v| expr_assigns(v, names)),
+        Stmt::Import(_) => {}
+    }
+}
+
+fn expr_assigns(expr: &Expr, names: &mut HashSet<EcoString>) {
+    match expr {
+        Expr::Assign(a) => {
+            names.insert(a.name.clone());
+            expr_assigns(&a.value, names);
+        }
+        Expr::Binary(b) => {
+            expr_assigns(&b.left, names);
+            expr_assigns(&b.right, names);
+        }
+        Expr::Logical(l) => {
+            expr_assigns(&l.left, names);
+            expr_assigns(&l.right, names);
+        }
+        Expr::Grouping(g) => expr_assigns(&g.expr, names),
+        Expr::Unary(u) => expr_assigns(&u.right, names),
+        Expr::Call(c) => {
+            expr_assigns(&c.callee, names);
+            c.args.iter().for_each(|a| expr_assigns(a, names));
+        }
+        Expr::Get(g) => expr_assigns(&g.object, names),
+        Expr::Set(s) => {
+            expr_assigns(&s.object, names);
+            expr_assigns(&s.value, names);
+        }
+        Expr::Index(i) => {
+            expr_assigns(&i.object, names);
+            i.bounds().for_each(|b| expr_assigns(b, names));
+        }
+        Expr::List(l) => l.items.iter().for_each(|i| expr_assigns(i, names)),
+        Expr::Comprehension(c) => c.sub_exprs().for_each(|e| expr_assigns(e, names)),
+        Expr::Spawn(s) => expr_assigns(&s.call, names),
+        Expr::Await(a) => expr_assigns(&a.task, names),
+        _ => {}
+    }
+}
+
+impl VisitStmt<String, JsGenErr> for JsGenerator {
+    fn visit_expr_stmt(&mut self, stmt: &ExprStmt) -> JsGenRes {
+        Ok(format!("{};", stmt.expr.accept(self)?))
+    }
+
+    fn visit_var_decl_stmt(&mut self, stmt: &VarDeclStmt) -> JsGenRes {
+        let export = export_prefix(stmt.is_pub);
+        self.declare_var(&stmt.name, stmt.ty.as_ref(), stmt.value.as_ref());
+
+        match &stmt.value {
+            Some(v) => Ok(format!("{}let {} = {};", export, stmt.name, v.accept(self)?)),
+            None => Ok(format!("{}let {} = null;", export, stmt.name)),
+        }
+    }
+
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> JsGenRes {
+        self.gen_stmts(&stmt.stmts)
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) -> JsGenRes {
+        let mut code = format!("if ({}) ", stmt.condition.accept(self)?);
+
+        match &stmt.then_branch {
+            Some(t) => code.push_str(&self.gen_body(t)?),
+            None => code.push_str("{}"),
+        }
+
+        if let Some(e) = &stmt.else_branch {
+            code.push_str(&format!(" else {}", self.gen_body(e)?));
+        }
+
+        Ok(code)
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) -> JsGenRes {
+        Ok(format!(
+            "while ({}) {}",
+            stmt.condition.accept(self)?,
+            self.gen_body(&stmt.body)?
+        ))
+    }
+
+    fn visit_for_stmt(&mut self, stmt: &ForStmt) -> JsGenRes {
+        let name = &stmt.placeholder.name;
+
+        // The end is evaluated once, in a variable named after the
+        // placeholder that the scripts can't write
+        let (head, item) = match &stmt.range {
+            ForRange::Ints(start, Some(end)) => {
+                (format!("let {name} = {start}; {name} < {end}; {name}++"), Some(JsType::Int))
+            }
+            ForRange::Ints(end, None) => {
+                (format!("let {name} = 0; {name} < {end}; {name}++"), Some(JsType::Int))
+            }
+            ForRange::Bounds(start, end) => (
+                format!(
+                    "let {name} = {}, {name}$end = {}; {name} < {name}$end; {name}++",
+                    start.accept(self)?,
+                    end.accept(self)?
+                ),
+                Some(JsType::Int),
+            ),
+            ForRange::Items(items) => {
+                let names = stmt.placeholders().map(|p| p.name.as_str()).collect::<Vec<_>>();
+                let target = match stmt.rest.is_empty() {
+                    true => name.to_string(),
+                    false => format!("[{}]", names.join(", ")),
+                };
+
+                (format!("let {target} of {}", items.accept(self)?), None)
+            }
+        };
+
+        let names = stmt.placeholders().map(|p| (p.name.clone(), item)).collect();
+        self.scopes.push(names);
+        let body = self.gen_body(&stmt.body);
+        self.scopes.pop();
+
+        Ok(format!("for ({head}) {}", body?))
+    }
+
+    fn visit_fn_decl_stmt(&mut self, stmt: &FnDeclStmt) -> JsGenRes {
+        self.declare(&stmt.name, None);
+
+        let params = stmt
+            .params
+            .iter()
+            .zip(&stmt.param_types)
+            .map(|(p, ty)| (p.clone(), ty.as_ref().and_then(ann_type)))
+            .collect();
+
+        self.scopes.push(params);
+        let body = self.gen_stmts(&stmt.body);
+        self.scopes.pop();
+
+        Ok(format!(
+            "{}function {}({}) {}",
+            export_prefix(stmt.is_pub),
+            js_name(&stmt.name),
+            stmt.params.join(", "),
+            body?
+        ))
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> JsGenRes {
+        match &stmt.value {
+            Some(v) => Ok(format!("return {};", v.accept(self)?)),
+            None => Ok("return;".into()),
+        }
+    }
+
+    // Each arc module is expected to be generated next to its importer
+    fn visit_import_stmt(&mut self, stmt: &ImportStmt) -> JsGenRes {
+        Ok(format!(
+            "import * as {} from \"./{}.js\";",
+            stmt.name(),
+            stmt.path.join("/")
+        ))
+    }
+}
+
+// Custom operators are functions named after their symbol, like '<+>'
+// becoming 'op_plus'
+fn js_name(name: &str) -> String {
+    let Some(symbol) = name.strip_prefix('<').and_then(|n| n.strip_suffix('>')) else {
+        return name.to_string();
+    };
+
+    let parts: Vec<&str> = symbol
+        .chars()
+        .map(|c| match c {
+            '+' => "plus",
+            '-' => "minus",
+            '*' => "star",
+            '/' => "slash",
+            '%' => "percent",
+            '^' => "caret",
+            '&' => "amp",
+            '|' => "pipe",
+            '~' => "tilde",
+            '!' => "bang",
+            '?' => "question",
+            '=' => "equal",
+            '.' => "dot",
+            _ => "colon",
+        })
+        .collect();
+
+    format!("op_{}", parts.join("_"))
+}
+
+fn export_prefix(is_pub: bool) -> &'static str {
+    match is_pub {
+        true => "export ",
+        false => "",
+    }
+}
+
+impl VisitExpr<String, JsGenErr> for JsGenerator {
+    // The int division is truncated and '*' also repeats the strings and
+    // the lists, which JavaScript only does with the types known here
+    fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> JsGenRes {
+        let (left, right) = (expr.left.accept(self)?, expr.right.accept(self)?);
+        let types = (self.type_of(&expr.left), self.type_of(&expr.right));
+        let unknown = || {
+            Err(PhyResult::new(
+                JsGenErr::UnknownOperands(expr.operator.clone()),
+                Some(expr.loc.clone()),
+            ))
+        };
+
+        match (expr.operator.as_str(), types) {
+            ("/", (Some(JsType::Int), Some(JsType::Int))) => {
+                Ok(format!("Math.trunc({} / {})", left, right))
+            }
+            ("/", (Some(JsType::Real), _) | (_, Some(JsType::Real))) => {
+                Ok(format!("{} / {}", left, right))
+            }
+            ("/", _) => unknown(),
+            ("*", (Some(JsType::Str), _)) => Ok(format!("{}.repeat({})", left, right)),
+            ("*", (_, Some(JsType::Str))) => Ok(format!("{}.repeat({})", right, left)),
+            ("*", (Some(JsType::List), _)) => Ok(format!("Array({}).fill({}).flat()", right, left)),
+            ("*", (Some(l), Some(r))) if is_number(l) && is_number(r) => {
+                Ok(format!("{} * {}", left, right))
+            }
+            ("*", _) => unknown(),
+            ("==", _) => Ok(format!("{} === {}", left, right)),
+            ("!=", _) => Ok(format!("{} !== {}", left, right)),
+            (op, _) => Ok(format!("{} {} {}", left, op, right)),
+        }
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> JsGenRes {
+        Ok(format!("({})", expr.expr.accept(self)?))
+    }
+
+    fn visit_int_literal_expr(&mut self, expr: &IntLiteralExpr) -> JsGenRes {
+        Ok(expr.value.to_string())
+    }
+
+    fn visit_real_literal_expr(&mut self, expr: &RealLiteralExpr) -> JsGenRes {
+        Ok(format!("{:?}", expr.value))
+    }
+
+    fn visit_imaginary_literal_expr(&mut self, expr: &ImaginaryLiteralExpr) -> JsGenRes {
+        Err(PhyResult::new(JsGenErr::ImaginaryLiteral, Some(expr.loc.clone())))
+    }
+
+    fn visit_str_literal_expr(&mut self, expr: &StrLiteralExpr) -> JsGenRes {
+        let escaped = expr.value.replace("\\", "\\\\").replace("\n", "\\n");
+        Ok(format!("\"{}\"", escaped))
+    }
+
+    // JS has no char type, they are strings of length 1
+    fn visit_char_literal_expr(&mut self, expr: &CharLiteralExpr) -> JsGenRes {
+        Ok(format!("{:?}", expr.value.to_string()))
+    }
+
+    fn visit_unit_literal_expr(&mut self, expr: &UnitLiteralExpr) -> JsGenRes {
+        Err(PhyResult::new(JsGenErr::UnitLiteral, Some(expr.loc.clone())))
+    }
+
+    fn visit_identifier_expr(&mut self, expr: &IdentifierExpr) -> JsGenRes {
+        let name = NATIVES
+            .iter()
+            .find(|(n, _)| *n == expr.name)
+            .map_or_else(|| js_name(&expr.name), |(_, js)| js.to_string());
+
+        Ok(name)
+    }
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> JsGenRes {
+        Ok(format!("{}{}", expr.operator, expr.right.accept(self)?))
+    }
+
+    fn visit_assign_expr(&mut self, expr: &AssignExpr) -> JsGenRes {
+        Ok(format!("{} = {}", expr.name, expr.value.accept(self)?))
+    }
+
+    fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> JsGenRes {
+        let operator = match expr.operator.as_str() {
+            "and" => "&&",
+            _ => "||",
+        };
+
+        Ok(format!(
+            "{} {} {}",
+            expr.left.accept(self)?,
+            operator,
+            expr.right.accept(self)?
+        ))
+    }
+
+    fn visit_call_expr(&mut self, expr: &CallExpr) -> JsGenRes {
+        Ok(format!(
+            "{}({})",
+            expr.callee.accept(self)?,
+            self.gen_args(&expr.args)?
+        ))
+    }
+
+    fn visit_get_expr(&mut self, expr: &GetExpr) -> JsGenRes {
+        // Members of the native modules are mapped to their equivalent
+        if let Expr::Identifier(module) = expr.object.as_ref() {
+            if let Some((_, members)) = MODULES.iter().find(|(m, _)| *m == module.name) {
+                return members
+                    .iter()
+                    .find(|(m, _)| *m == expr.name)
+                    .map(|(_, js)| js.to_string())
+                    .ok_or_else(|| {
+                        PhyResult::new(
+                            JsGenErr::UnsupportedNative(expr.name.clone(), module.name.clone()),
+                            Some(expr.loc.clone()),
+                        )
+                    });
+            }
+        }
+
+        Ok(format!("{}.{}", expr.object.accept(self)?, expr.name))
+    }
+
+    fn visit_set_expr(&mut self, expr: &SetExpr) -> JsGenRes {
+        Ok(format!(
+            "{}.{} = {}",
+            expr.object.accept(self)?,
+            expr.name,
+            expr.value.accept(self)?
+        ))
+    }
+
+    // 'at' and 'slice' count the negative indices from the end and clamp
+    // the bounds of the slices, but an item out of range is undefined
+    fn visit_index_expr(&mut self, expr: &IndexExpr) -> JsGenRes {
+        let object = expr.object.accept(self)?;
+        let bounds = expr.bounds().map(|b| b.accept(self)).collect::<Result<Vec<_>, _>>()?;
+
+        match (expr.is_slice, &expr.start) {
+            (false, _) => Ok(format!("{}.at({})", object, bounds[0])),
+            (true, None) if !bounds.is_empty() => Ok(format!("{}.slice(0, {})", object, bounds[0])),
+            (true, _) => Ok(format!("{}.slice({})", object, bounds.join(", "))),
+        }
+    }
+
+    // A range becomes an array of its ints, kept with 'filter' and mapped.
+    // A start that isn't a literal is passed to a function to be evaluated
+    // once
+    fn visit_list_expr(&mut self, expr: &ListExpr) -> JsGenRes {
+        let items = expr.items.iter().map(|i| i.accept(self)).collect::<Result<Vec<_>, _>>()?;
+
+        Ok(format!("[{}]", items.join(", ")))
+    }
+
+    fn visit_comprehension_expr(&mut self, expr: &ComprehensionExpr) -> JsGenRes {
+        let name = &expr.name;
+        let source = expr.source.accept(self)?;
+
+        let mut res = match (&expr.end, expr.source.as_ref()) {
+            (None, _) => source,
+            (Some(end), Expr::IntLiteral(_)) => format!(
+                "Array.from({{ length: {} - {source} }}, (_, i) => i + {source})",
+                end.accept(self)?
+            ),
+            (Some(end), _) => format!(
+                "((s) => Array.from({{ length: {} - s }}, (_, i) => i + s))({source})",
+                end.accept(self)?
+            ),
+        };
+
+        // The items of a range are ints, the ones of a list are unknown
+        let item = expr.end.as_ref().map(|_| JsType::Int);
+        self.scopes.push(HashMap::from([(name.clone(), item)]));
+        let cond = expr.cond.as_ref().map(|c| c.accept(self)).transpose();
+        let value = expr.value.accept(self);
+        self.scopes.pop();
+
+        if let Some(cond) = cond? {
+            res = format!("{res}.filter(({name}) => {cond})");
+        }
+
+        Ok(format!("{res}.map(({name}) => {})", value?))
+    }
+
+    fn visit_spawn_expr(&mut self, expr: &SpawnExpr) -> JsGenRes {
+        Err(PhyResult::new(JsGenErr::Task, Some(expr.loc.clone())))
+    }
+
+    fn visit_await_expr(&mut self, expr: &AwaitExpr) -> JsGenRes {
+        Err(PhyResult::new(JsGenErr::Task, Some(expr.loc.clone())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::lex_parse_gen;
+
+    use super::JsGenErr;
+
+    #[test]
+    fn statements() {
+        let code = "
+var total: int = 0
+fn add(a, b) {
+    return a + b
+}
+for i in 1..4 {
+    if i != 2 and true {
+        total = add(total, i)
+    } else {
+        print(\"skip\", i)
+    }
+}
+while total < 10. {
+    total = total * (2 - 1) + 1
+}
+";
+        let expected = "let total = 0;
+function add(a, b) {
+    return a + b;
+}
+for (let i = 1; i < 4; i++) {
+    if (i !== 2 && true) {
+        total = add(total, i);
+    } else {
+        console.log(\"skip\", i);
+    }
+}
+while (total < 10.0) {
+    total = total * (2 - 1) + 1;
+}
+";
+        assert_eq!(lex_parse_gen(code).unwrap(), expected);
+    }
+
+    #[test]
+    fn natives() {
+        let code = "
+var a = math.sqrt(floor(2.5))
+player.x = -a
+";
+        assert_eq!(
+            lex_parse_gen(code).unwrap(),
+            "let a = Math.sqrt(Math.floor(2.5));\nplayer.x = -a;\n"
+        );
+    }
+
+    #[test]
+    fn indexing() {
+        let code = "
+a[-1]
+a[1..n]
+a[..2]
+a[i..]
+";
+        assert_eq!(
+            lex_parse_gen(code).unwrap(),
+            "a.at(-1);\na.slice(1, n);\na.slice(0, 2);\na.slice(i);\n"
+        );
+    }
+
+    #[test]
+    fn for_loops() {
+        let code = "
+for i in a..n + 1 { print(i / 2) }
+for x in l { print(x) }
+for i, v in enumerate(l) { print(i, v) }
+";
+        assert_eq!(
+            lex_parse_gen(code).unwrap(),
+            "for (let i = a, i$end = n + 1; i < i$end; i++) {
+    console.log(Math.trunc(i / 2));
+}
+for (let x of l) {
+    console.log(x);
+}
+for (let [i, v] of enumerate(l)) {
+    console.log(i, v);
+}
+"
+        );
+    }
+
+    #[test]
+    fn lists() {
+        let code = "
+var l = [1, f(2), [\"a\"]]
+[]
+";
+        assert_eq!(lex_parse_gen(code).unwrap(), "let l = [1, f(2), [\"a\"]];\n[];\n");
+    }
+
+    #[test]
+    fn comprehensions() {
+        let code = "
+[x * x for x in 0..n if x % 2 == 0]
+[x + 1 for x in l]
+[x for x in a..b]
+";
+        assert_eq!(
+            lex_parse_gen(code).unwrap(),
+            "Array.from({ length: n - 0 }, (_, i) => i + 0).filter((x) => x % 2 === 0).map((x) => x * x);
+l.map((x) => x + 1);
+((s) => Array.from({ length: b - s }, (_, i) => i + s))(a).map((x) => x);
+"
+        );
+    }
+
+    #[test]
+    fn custom_operators() {
+        let code = "
+operator <+> (a, b) { return a }
+1 <+> 2
+";
+        assert_eq!(
+            lex_parse_gen(code).unwrap(),
+            "function op_plus(a, b) {\n    return a;\n}\nop_plus(1, 2);\n"
+        );
+    }
+
+    #[test]
+    fn modules() {
+        let code = "
+import geo.shapes
+pub var unit = shapes.square(1)
+pub fn area(x: real) { return x * x }
+";
+        assert_eq!(
+            lex_parse_gen(code).unwrap(),
+            "import * as shapes from \"./geo/shapes.js\";
+export let unit = shapes.square(1);
+export function area(x) {
+    return x * x;
+}
+"
+        );
+    }
+
+    #[test]
+    fn operators() {
+        let code = "
+var a = 7 / 2
+var b = 7 / 2.
+var s = \"ab\" * (a - 1)
+var l = [0] * 3
+fn half(x: int, y: real) { return x / 2 + y * 2. }
+";
+        assert_eq!(
+            lex_parse_gen(code).unwrap(),
+            "let a = Math.trunc(7 / 2);
+let b = 7 / 2.0;
+let s = \"ab\".repeat((a - 1));
+let l = Array(3).fill([0]).flat();
+function half(x, y) {
+    return Math.trunc(x / 2) + y * 2.0;
+}
+"
+        );
+
+        // Unknown types, like the ones of the variables that are assigned
+        let code = "
+var n = 1
+n = f()
+fn g(x) { return x / 2 }
+print(n * 2)
+";
+        let errs: Vec<JsGenErr> =
+            lex_parse_gen(code).err().unwrap().into_iter().map(|e| e.err).collect();
+        assert_eq!(
+            errs,
+            [JsGenErr::UnknownOperands("/".into()), JsGenErr::UnknownOperands("*".into())]
+        );
+    }
+
+    #[test]
+    fn unsupported() {
+        let code = "
+var g = 9.81 m/s^2
+var z = 3 + 4i
+var s = str.upper(\"a\")
+var t = spawn f()
+var ok = 1
+";
+        let errs: Vec<JsGenErr> = lex_parse_gen(code).err().unwrap().into_iter().map(|e| e.err).collect();
+        assert_eq!(
+            errs,
+            [
+                JsGenErr::UnitLiteral,
+                JsGenErr::ImaginaryLiteral,
+                JsGenErr::UnsupportedNative("upper".into(), "str".into()),
+                JsGenErr::Task,
+            ]
+        );
+    }
+}
@@ -0,0 +1,5 @@
+pub mod js_generator;
+#[cfg(test)]
+mod utils;
+
+extern crate frontend;
@@ -0,0 +1,10 @@
+use frontend::parser::utils::lex_and_parse;
+use tools::results::PhyResult;
+
+use crate::js_generator::{JsGenErr, JsGenerator};
+
+pub fn lex_parse_gen(code: &str) -> Result<String, Vec<PhyResult<JsGenErr>>> {
+    let nodes = lex_and_parse(code).unwrap();
+    let mut generator = JsGenerator::default();
+    generator.generate(&nodes)
+}
@@ -1,3 +1,6 @@
 pub mod expr;
 pub mod stmt;
-pub mod ast_pretty_print;
\ No newline at end of file
+pub mod types;
+pub mod node_id;
+pub mod ast_pretty_print;
+pub mod doc_gen;
@@ -1,10 +1,17 @@
+use tools::messages::Locale;
 use tools::results::{PhyReport, PhyResult};
 
 use super::expr::{
-    AssignExpr, BinaryExpr, CallExpr, Expr, GroupingExpr, IdentifierExpr, IntLiteralExpr, LogicalExpr, RealLiteralExpr, StrLiteralExpr, UnaryExpr, VisitExpr
+    AssignExpr, AwaitExpr, BinaryExpr, CallExpr, CharLiteralExpr, ComprehensionExpr, Expr, GetExpr,
+    GroupingExpr, IdentifierExpr, ImaginaryLiteralExpr, IndexExpr, IntLiteralExpr, ListExpr,
+    LogicalExpr, RealLiteralExpr, SetExpr, SpawnExpr, StrLiteralExpr, UnaryExpr, UnitLiteralExpr,
+    VisitExpr,
 };
 
-use super::stmt::{BlockStmt, ExprStmt, FnDeclStmt, ForStmt, IfStmt, PrintStmt, ReturnStmt, Stmt, VarDeclStmt, VisitStmt, WhileStmt};
+use super::stmt::{
+    BlockStmt, ExprStmt, FnDeclStmt, ForRange, ForStmt, IfStmt, ImportStmt, ReturnStmt, Stmt,
+    VarDeclStmt, VisitStmt, WhileStmt,
+};
 
 #[derive(Debug)]
 pub enum AstPrinterErr {}
@@ -12,29 +19,76 @@ pub enum AstPrinterErr {}
 type PhyResAstPrint = PhyResult<AstPrinterErr>;
 
 impl PhyReport for AstPrinterErr {
-    fn get_err_msg(&self) -> String {
+    fn get_err_msg(&self, _: &dyn Locale) -> String {
         String::from("")
     }
 }
 
-pub struct AstPrinter {}
+// S-expressions are compact, like (+ 1 (* 2 3)). The tree puts each node
+// on its own line, indented under its parent
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum AstFormat {
+    #[default]
+    SExpr,
+    Tree,
+}
+
+#[derive(Default)]
+pub struct AstPrinter {
+    pub format: AstFormat,
+}
 
 impl AstPrinter {
+    pub fn new(format: AstFormat) -> Self {
+        Self { format }
+    }
+
     pub fn print(&mut self, expr: &Stmt) -> Result<String, PhyResAstPrint> {
         expr.accept(self)
     }
 
     fn parenthesize(&mut self, name: &str, exprs: &[&Expr]) -> Result<String, PhyResAstPrint> {
-        let mut final_str: String = format!("({}", name);
+        let children = exprs
+            .iter()
+            .map(|e| e.accept(self))
+            .collect::<Result<Vec<String>, PhyResAstPrint>>()?;
 
-        for expr in exprs {
-            final_str.push(' ');
-            final_str.push_str(expr.accept(self)?.as_str());
-        }
+        Ok(self.node(name, children))
+    }
 
-        final_str.push(')');
+    fn parenthesize_stmts(&mut self, name: &str, stmts: &[Stmt]) -> Result<String, PhyResAstPrint> {
+        let children = stmts
+            .iter()
+            .map(|s| s.accept(self))
+            .collect::<Result<Vec<String>, PhyResAstPrint>>()?;
 
-        Ok(final_str)
+        Ok(self.node(name, children))
+    }
+
+    fn node(&self, name: &str, children: Vec<String>) -> String {
+        match self.format {
+            AstFormat::SExpr => {
+                let mut final_str = format!("({}", name);
+
+                for child in children {
+                    final_str.push(' ');
+                    final_str.push_str(&child);
+                }
+
+                final_str.push(')');
+                final_str
+            }
+            AstFormat::Tree => {
+                let mut final_str = name.to_string();
+
+                for line in children.iter().flat_map(|c| c.lines()) {
+                    final_str.push_str("\n  ");
+                    final_str.push_str(line);
+                }
+
+                final_str
+            }
+        }
     }
 }
 
@@ -43,38 +97,92 @@ impl VisitStmt<String, AstPrinterErr> for AstPrinter {
         stmt.expr.accept(self)
     }
 
-    fn visit_print_stmt(&mut self, stmt: &PrintStmt) -> Result<String, PhyResult<AstPrinterErr>> {
-        self.parenthesize("print", &[&stmt.expr])
+    fn visit_var_decl_stmt(&mut self, stmt: &VarDeclStmt) -> Result<String, PhyResult<AstPrinterErr>> {
+        let name = format!("{}var {}", pub_prefix(stmt.is_pub), stmt.name);
+
+        match &stmt.value {
+            Some(v) => self.parenthesize(&name, &[v]),
+            None => self.parenthesize(&name, &[]),
+        }
     }
 
-    fn visit_var_decl_stmt(&mut self, stmt: &VarDeclStmt) -> Result<String, PhyResult<AstPrinterErr>> {
-        let val_str = if let Some(v) = &stmt.value { format!("{}", v) } else { "None".to_string() };
-        let decl_str = format!("decl {} = {}", stmt.name, val_str);
-        self.parenthesize(&decl_str, &[])
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> Result<String, PhyResult<AstPrinterErr>> {
+        self.parenthesize_stmts("block", &stmt.stmts)
     }
 
-    fn visit_block_stmt(&mut self, _stmt: &BlockStmt) -> Result<String, PhyResult<AstPrinterErr>> {
-        self.parenthesize("block", &[])
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) -> Result<String, PhyResult<AstPrinterErr>> {
+        let mut children = vec![stmt.condition.accept(self)?];
+
+        for branch in [&stmt.then_branch, &stmt.else_branch].into_iter().flatten() {
+            children.push(branch.accept(self)?);
+        }
+
+        Ok(self.node("if", children))
     }
 
-    fn visit_if_stmt(&mut self, _stmt: &IfStmt) -> Result<String, PhyResult<AstPrinterErr>> {
-        todo!()
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) -> Result<String, PhyResult<AstPrinterErr>> {
+        let children = vec![stmt.condition.accept(self)?, stmt.body.accept(self)?];
+
+        Ok(self.node("while", children))
     }
 
-    fn visit_while_stmt(&mut self, _stmt: &WhileStmt) -> Result<String, PhyResult<AstPrinterErr>> {
-        todo!()
+    fn visit_for_stmt(&mut self, stmt: &ForStmt) -> Result<String, PhyResult<AstPrinterErr>> {
+        // The bounds and the items that aren't literals are children
+        let range = match &stmt.range {
+            ForRange::Ints(start, Some(end)) => format!(" {}..{}", start, end),
+            ForRange::Ints(start, None) => format!(" {}", start),
+            ForRange::Bounds(..) => " ..".into(),
+            ForRange::Items(_) => String::new(),
+        };
+        let names = stmt.placeholders().map(|p| p.name.as_str()).collect::<Vec<_>>();
+        let name = format!("for {} in{}", names.join(", "), range);
+
+        let mut children = stmt
+            .range
+            .exprs()
+            .map(|e| e.accept(self))
+            .collect::<Result<Vec<String>, PhyResAstPrint>>()?;
+        children.push(stmt.body.accept(self)?);
+
+        Ok(self.node(&name, children))
     }
 
-    fn visit_for_stmt(&mut self, _stmt: &ForStmt) -> Result<String, PhyResult<AstPrinterErr>> {
-        todo!()
+    fn visit_fn_decl_stmt(&mut self, stmt: &FnDeclStmt) -> Result<String, PhyResult<AstPrinterErr>> {
+        let mut name = String::new();
+
+        for attr in &stmt.attributes {
+            let args = attr
+                .args
+                .iter()
+                .map(|a| a.accept(self))
+                .collect::<Result<Vec<String>, PhyResAstPrint>>()?;
+
+            match args.is_empty() {
+                true => name.push_str(&format!("@{} ", attr.name)),
+                false => name.push_str(&format!("@{}({}) ", attr.name, args.join(", "))),
+            }
+        }
+
+        name.push_str(&format!("{}fn {}({})", pub_prefix(stmt.is_pub), stmt.name, stmt.params.join(", ")));
+        self.parenthesize_stmts(&name, &stmt.body)
     }
 
-    fn visit_fn_decl_stmt(&mut self, _stmt: &FnDeclStmt) -> Result<String, PhyResult<AstPrinterErr>> {
-        todo!()
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> Result<String, PhyResult<AstPrinterErr>> {
+        match &stmt.value {
+            Some(v) => self.parenthesize("return", &[v]),
+            None => self.parenthesize("return", &[]),
+        }
     }
 
-    fn visit_return_stmt(&mut self, _stmt: &ReturnStmt) -> Result<String, PhyResult<AstPrinterErr>> {
-        todo!()
+    fn visit_import_stmt(&mut self, stmt: &ImportStmt) -> Result<String, PhyResult<AstPrinterErr>> {
+        Ok(self.node(&format!("import {}", stmt.path.join(".")), vec![]))
+    }
+}
+
+fn pub_prefix(is_pub: bool) -> &'static str {
+    match is_pub {
+        true => "pub ",
+        false => "",
     }
 }
 
@@ -91,6 +199,13 @@ impl VisitExpr<String, AstPrinterErr> for AstPrinter {
         Ok(format!("{}", expr.value))
     }
 
+    fn visit_imaginary_literal_expr(
+        &mut self,
+        expr: &ImaginaryLiteralExpr,
+    ) -> Result<String, PhyResAstPrint> {
+        Ok(format!("{}i", expr.value))
+    }
+
     fn visit_real_literal_expr(&mut self, expr: &RealLiteralExpr) -> Result<String, PhyResAstPrint> {
         Ok(format!("{}", expr.value))
     }
@@ -99,6 +214,14 @@ impl VisitExpr<String, AstPrinterErr> for AstPrinter {
         Ok(format!("\"{}\"", expr.value))
     }
 
+    fn visit_char_literal_expr(&mut self, expr: &CharLiteralExpr) -> Result<String, PhyResAstPrint> {
+        Ok(format!("{:?}", expr.value))
+    }
+
+    fn visit_unit_literal_expr(&mut self, expr: &UnitLiteralExpr) -> Result<String, PhyResAstPrint> {
+        Ok(format!("{}[{}]", expr.value, expr.unit_string()))
+    }
+
     fn visit_identifier_expr(&mut self, expr: &IdentifierExpr) -> Result<String, PhyResAstPrint> {
         Ok(expr.name.to_string())
     }
@@ -108,15 +231,104 @@ impl VisitExpr<String, AstPrinterErr> for AstPrinter {
     }
 
     fn visit_assign_expr(&mut self, expr: &AssignExpr) -> Result<String, PhyResult<AstPrinterErr>> {
-        let assign_str = format!("assign {} to {}", expr.value.accept(self)?, expr.name);
-        self.parenthesize(assign_str.as_str(), &[])
+        self.parenthesize(&format!("assign {}", expr.name), &[&expr.value])
     }
 
     fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> Result<String, PhyResult<AstPrinterErr>> {
         self.parenthesize(expr.operator.as_str(), &[&expr.left, &expr.right])
     }
 
-    fn visit_call_expr(&mut self, _expr: &CallExpr) -> Result<String, PhyResult<AstPrinterErr>> {
-        todo!()
+    fn visit_call_expr(&mut self, expr: &CallExpr) -> Result<String, PhyResult<AstPrinterErr>> {
+        let mut exprs = vec![expr.callee.as_ref()];
+        exprs.extend(expr.args.iter());
+
+        self.parenthesize("call", &exprs)
+    }
+
+    fn visit_get_expr(&mut self, expr: &GetExpr) -> Result<String, PhyResult<AstPrinterErr>> {
+        self.parenthesize(&format!("get {}", expr.name), &[&expr.object])
+    }
+
+    fn visit_set_expr(&mut self, expr: &SetExpr) -> Result<String, PhyResult<AstPrinterErr>> {
+        self.parenthesize(&format!("set {}", expr.name), &[&expr.object, &expr.value])
+    }
+
+    fn visit_index_expr(&mut self, expr: &IndexExpr) -> Result<String, PhyResult<AstPrinterErr>> {
+        let mut exprs = vec![expr.object.as_ref()];
+        exprs.extend(expr.bounds());
+
+        match expr.is_slice {
+            true => self.parenthesize("slice", &exprs),
+            false => self.parenthesize("index", &exprs),
+        }
+    }
+
+    fn visit_list_expr(&mut self, expr: &ListExpr) -> Result<String, PhyResult<AstPrinterErr>> {
+        self.parenthesize("list", &expr.items.iter().collect::<Vec<_>>())
+    }
+
+    fn visit_comprehension_expr(
+        &mut self,
+        expr: &ComprehensionExpr,
+    ) -> Result<String, PhyResult<AstPrinterErr>> {
+        let exprs = expr.sub_exprs().collect::<Vec<_>>();
+        self.parenthesize(&format!("comprehension {}", expr.name), &exprs)
+    }
+
+    fn visit_spawn_expr(&mut self, expr: &SpawnExpr) -> Result<String, PhyResult<AstPrinterErr>> {
+        self.parenthesize("spawn", &[&expr.call])
+    }
+
+    fn visit_await_expr(&mut self, expr: &AwaitExpr) -> Result<String, PhyResult<AstPrinterErr>> {
+        self.parenthesize("await", &[&expr.task])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::utils::{print_sexpr, print_tree};
+
+    #[test]
+    fn sexpr() {
+        let code = "
+var a = 1 + 2 * 3
+a = -(a - 1)
+fn add(x, y) { return x + y }
+for i in 0..3 { print(add(i, 2.5)) }
+if a > 1 and true { a = 0 } else { a = 1 }
+while a < 3 { a = a + 1 }
+var speed = 3 m/s
+var z
+@inline
+@deprecated(\"use g\")
+fn f() {}
+";
+        assert_eq!(
+            print_sexpr(code),
+            [
+                "(var a (+ 1 (* 2 3)))",
+                "(assign a (- (group (- a 1))))",
+                "(fn add(x, y) (return (+ x y)))",
+                "(for i in 0..3 (block (call print (call add i 2.5))))",
+                "(if (and (> a 1) true) (assign a 0) (assign a 1))",
+                "(while (< a 3) (block (assign a (+ a 1))))",
+                "(var speed 3[m*s^-1])",
+                "(var z)",
+                "(@inline @deprecated(\"use g\") fn f())",
+            ]
+        );
+    }
+
+    #[test]
+    fn tree() {
+        let code = "
+fn f(x) {
+    return x * (2 + x)
+}
+";
+        assert_eq!(
+            print_tree(code)[0],
+            "fn f(x)\n  return\n    *\n      x\n      group\n        +\n          2\n          x"
+        );
     }
 }
@@ -0,0 +1,113 @@
+use tools::source_map::SourceMap;
+
+use super::{expr::Expr, stmt::Stmt};
+
+// Markdown documentation of the top level functions and variables of a
+// file: their signature, the text of their '///' comments and a link to
+// the line where they are declared, in the file they come from if they
+// were included. Undocumented declarations are listed too, so that the page
+// shows the whole public surface of the file
+pub fn render_markdown(title: &str, sources: &SourceMap, stmts: &[Stmt]) -> String {
+    let mut doc = format!("# {}\n", title);
+
+    for stmt in stmts {
+        let (signature, text, loc) = match stmt {
+            Stmt::FnDecl(f) => (f.signature(), &f.doc, &f.loc),
+            Stmt::VarDecl(v) => match &v.ty {
+                Some(ty) => (format!("var {}: {}", v.name, ty), &v.doc, &v.loc),
+                None => (format!("var {}", v.name), &v.doc, &v.loc),
+            },
+            _ => continue,
+        };
+
+        doc.push_str(&format!("\n## `{}`\n\n", signature));
+
+        // '@deprecated' with an optional message
+        if let Stmt::FnDecl(f) = stmt {
+            if let Some(attr) = f.get_attribute("deprecated") {
+                match attr.args.first() {
+                    Some(Expr::StrLiteral(msg)) => {
+                        doc.push_str(&format!("**Deprecated**: {}\n\n", msg.value))
+                    }
+                    _ => doc.push_str("**Deprecated**\n\n"),
+                }
+            }
+        }
+
+        if let Some(text) = text {
+            doc.push_str(text);
+            doc.push_str("\n\n");
+        }
+
+        if let Some(file) = sources.lookup(loc) {
+            doc.push_str(&format!("[source]({}#L{})\n", file.name, file.line_of(loc)));
+        }
+    }
+
+    doc
+}
+
+#[cfg(test)]
+mod tests {
+    use tools::source_map::SourceMap;
+
+    use crate::{lexer::Lexer, parser::Parser};
+
+    use super::render_markdown;
+
+    #[test]
+    fn markdown() {
+        let code = "var a = 1
+/// Gravity on earth
+/// in m/s^2
+var g = 9.81
+
+// Not a doc comment
+fn add(x, y) {
+    return x + y
+}
+
+/// Lost, it doesn't document a declaration
+print(g)
+/// Squares a number
+@deprecated(\"use pow\")
+fn square(x: real) -> real {
+    /// Local, not rendered
+    var y = x * x
+    return y
+}
+";
+        let mut sources = SourceMap::default();
+        let offset = sources.add("lib.arc", code);
+        let mut lexer = Lexer::with_offset(offset);
+        let tokens = lexer.tokenize(code).unwrap();
+        let nodes = Parser::default().parse(tokens).unwrap();
+
+        let expected = "# lib.arc
+
+## `var a`
+
+[source](lib.arc#L1)
+
+## `var g`
+
+Gravity on earth
+in m/s^2
+
+[source](lib.arc#L4)
+
+## `fn add(x, y)`
+
+[source](lib.arc#L7)
+
+## `fn square(x: real) -> real`
+
+**Deprecated**: use pow
+
+Squares a number
+
+[source](lib.arc#L15)
+";
+        assert_eq!(render_markdown("lib.arc", &sources, &nodes), expected);
+    }
+}
@@ -3,18 +3,30 @@ use std::fmt::Display;
 use ecow::EcoString;
 use tools::results::{Loc, PhyReport, PhyResult};
 
+use super::node_id::NodeId;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Expr {
     Binary(BinaryExpr),
     Grouping(GroupingExpr),
     IntLiteral(IntLiteralExpr),
     RealLiteral(RealLiteralExpr),
+    ImaginaryLiteral(ImaginaryLiteralExpr),
     StrLiteral(StrLiteralExpr),
+    CharLiteral(CharLiteralExpr),
+    UnitLiteral(UnitLiteralExpr),
     Identifier(IdentifierExpr),
     Unary(UnaryExpr),
     Assign(AssignExpr),
     Logical(LogicalExpr),
     Call(CallExpr),
+    Get(GetExpr),
+    Set(SetExpr),
+    Index(IndexExpr),
+    List(ListExpr),
+    Comprehension(ComprehensionExpr),
+    Spawn(SpawnExpr),
+    Await(AwaitExpr),
 }
 
 impl Display for Expr {
@@ -24,29 +36,96 @@ impl Display for Expr {
             Expr::Grouping(e) => write!(f, "{}", e.expr),
             Expr::IntLiteral(e) => write!(f, "{}", e.value),
             Expr::RealLiteral(e) => write!(f, "{}", e.value),
+            Expr::ImaginaryLiteral(e) => write!(f, "{}i", e.value),
             Expr::StrLiteral(e) => write!(f, "{}", e.value),
+            Expr::CharLiteral(e) => write!(f, "{:?}", e.value),
+            Expr::UnitLiteral(e) => write!(f, "{} {}", e.value, e.unit_string()),
             Expr::Identifier(e) => write!(f, "{}", e.name),
             Expr::Unary(e) => write!(f, "{} {}", e.operator, e.right),
             Expr::Assign(e) => write!(f, "{} {}", e.name, e.value),
             Expr::Logical(e) => write!(f, "{} {} {}", e.left, e.operator, e.right),
-            Expr::Call(e) => write!(f, "{}: {:?}", e.callee, e.args),
+            Expr::Call(e) => {
+                let args = e.args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+                write!(f, "{}({})", e.callee, args.join(", "))
+            }
+            Expr::Get(e) => write!(f, "{}.{}", e.object, e.name),
+            Expr::Set(e) => write!(f, "{}.{} {}", e.object, e.name, e.value),
+            Expr::Index(e) => {
+                let bound = |b: &Option<Box<Expr>>| b.as_ref().map_or(String::new(), |b| b.to_string());
+
+                match e.is_slice {
+                    true => write!(f, "{}[{}..{}]", e.object, bound(&e.start), bound(&e.end)),
+                    false => write!(f, "{}[{}]", e.object, bound(&e.start)),
+                }
+            }
+            Expr::List(e) => {
+                let items = e.items.iter().map(|i| i.to_string()).collect::<Vec<_>>();
+                write!(f, "[{}]", items.join(", "))
+            }
+            Expr::Comprehension(e) => {
+                write!(f, "[{} for {} in {}", e.value, e.name, e.source)?;
+                if let Some(end) = &e.end {
+                    write!(f, "..{}", end)?;
+                }
+                if let Some(cond) = &e.cond {
+                    write!(f, " if {}", cond)?;
+                }
+                write!(f, "]")
+            }
+            Expr::Spawn(e) => write!(f, "spawn {}", e.call),
+            Expr::Await(e) => write!(f, "await {}", e.task),
         }
     }
 }
 
 impl Expr {
+    pub fn get_id(&self) -> NodeId {
+        match self {
+            Self::Binary(b) => b.id,
+            Self::Grouping(g) => g.id,
+            Self::IntLiteral(i) => i.id,
+            Self::RealLiteral(r) => r.id,
+            Self::ImaginaryLiteral(i) => i.id,
+            Self::StrLiteral(s) => s.id,
+            Self::CharLiteral(c) => c.id,
+            Self::UnitLiteral(u) => u.id,
+            Self::Identifier(i) => i.id,
+            Self::Unary(u) => u.id,
+            Self::Assign(a) => a.id,
+            Self::Logical(l) => l.id,
+            Self::Call(c) => c.id,
+            Self::Get(g) => g.id,
+            Self::Set(s) => s.id,
+            Self::Index(i) => i.id,
+            Self::List(l) => l.id,
+            Self::Comprehension(c) => c.id,
+            Self::Spawn(s) => s.id,
+            Self::Await(a) => a.id,
+        }
+    }
+
     pub fn get_loc(&self) -> Loc {
         match self {
             Self::Binary(b) => b.loc.clone(),
             Self::Grouping(g) => g.loc.clone(),
             Self::IntLiteral(i) => i.loc.clone(),
             Self::RealLiteral(r) => r.loc.clone(),
+            Self::ImaginaryLiteral(i) => i.loc.clone(),
             Self::StrLiteral(s) => s.loc.clone(),
+            Self::CharLiteral(c) => c.loc.clone(),
+            Self::UnitLiteral(u) => u.loc.clone(),
             Self::Identifier(i) => i.loc.clone(),
             Self::Unary(u) => u.loc.clone(),
             Self::Assign(a) => a.loc.clone(),
             Self::Logical(l) => l.loc.clone(),
             Self::Call(c) => c.loc.clone(),
+            Self::Get(g) => g.loc.clone(),
+            Self::Set(s) => s.loc.clone(),
+            Self::Index(i) => i.loc.clone(),
+            Self::List(l) => l.loc.clone(),
+            Self::Comprehension(c) => c.loc.clone(),
+            Self::Spawn(s) => s.loc.clone(),
+            Self::Await(a) => a.loc.clone(),
         }
     }
 }
@@ -56,36 +135,81 @@ pub struct BinaryExpr {
     pub left: Box<Expr>,
     pub operator: EcoString,
     pub right: Box<Expr>,
+    pub id: NodeId,
     pub loc: Loc,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct GroupingExpr {
     pub expr: Box<Expr>,
+    pub id: NodeId,
     pub loc: Loc,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct IntLiteralExpr {
     pub value: i64,
+    pub id: NodeId,
     pub loc: Loc,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct RealLiteralExpr {
     pub value: f64,
+    pub id: NodeId,
+    pub loc: Loc,
+}
+
+// Imaginary part of a complex number, like '4i'
+#[derive(Debug, PartialEq, Clone)]
+pub struct ImaginaryLiteralExpr {
+    pub value: f64,
+    pub id: NodeId,
     pub loc: Loc,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct StrLiteralExpr {
     pub value: EcoString,
+    pub id: NodeId,
+    pub loc: Loc,
+}
+
+// Single char, like 'a' or '\n'
+#[derive(Debug, PartialEq, Clone)]
+pub struct CharLiteralExpr {
+    pub value: char,
+    pub id: NodeId,
     pub loc: Loc,
 }
 
+// Number with a unit, like '9.81 m/s^2'. The unit is stored as its
+// symbols with their exponent: [(m, 1), (s, -2)]
+#[derive(Debug, PartialEq, Clone)]
+pub struct UnitLiteralExpr {
+    pub value: f64,
+    pub unit: Vec<(EcoString, i32)>,
+    pub id: NodeId,
+    pub loc: Loc,
+}
+
+impl UnitLiteralExpr {
+    pub fn unit_string(&self) -> String {
+        self.unit
+            .iter()
+            .map(|(s, e)| match e {
+                1 => s.to_string(),
+                e => format!("{}^{}", s, e),
+            })
+            .collect::<Vec<String>>()
+            .join("*")
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct IdentifierExpr {
     pub name: EcoString,
+    pub id: NodeId,
     pub loc: Loc,
 }
 
@@ -93,6 +217,7 @@ pub struct IdentifierExpr {
 pub struct UnaryExpr {
     pub operator: EcoString,
     pub right: Box<Expr>,
+    pub id: NodeId,
     pub loc: Loc,
 }
 
@@ -100,6 +225,8 @@ pub struct UnaryExpr {
 pub struct AssignExpr {
     pub name: EcoString,
     pub value: Box<Expr>,
+    pub name_loc: Loc,
+    pub id: NodeId,
     pub loc: Loc,
 }
 
@@ -108,6 +235,7 @@ pub struct LogicalExpr {
     pub left: Box<Expr>,
     pub operator: EcoString,
     pub right: Box<Expr>,
+    pub id: NodeId,
     pub loc: Loc,
 }
 
@@ -115,6 +243,90 @@ pub struct LogicalExpr {
 pub struct CallExpr {
     pub callee: Box<Expr>,
     pub args: Vec<Expr>,
+    pub id: NodeId,
+    pub loc: Loc,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct GetExpr {
+    pub object: Box<Expr>,
+    pub name: EcoString,
+    pub id: NodeId,
+    pub loc: Loc,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SetExpr {
+    pub object: Box<Expr>,
+    pub name: EcoString,
+    pub value: Box<Expr>,
+    pub id: NodeId,
+    pub loc: Loc,
+}
+
+// 'a[i]', or a slice 'a[i..j]' whose bounds are optional like in 'a[..j]'.
+// For an item, the index is the start
+#[derive(Debug, PartialEq, Clone)]
+pub struct IndexExpr {
+    pub object: Box<Expr>,
+    pub start: Option<Box<Expr>>,
+    pub end: Option<Box<Expr>>,
+    pub is_slice: bool,
+    pub id: NodeId,
+    pub loc: Loc,
+}
+
+impl IndexExpr {
+    pub fn bounds(&self) -> impl Iterator<Item = &Expr> {
+        self.start.iter().chain(&self.end).map(|b| b.as_ref())
+    }
+}
+
+// '[1, 2, 3]', evaluated from left to right
+#[derive(Debug, PartialEq, Clone)]
+pub struct ListExpr {
+    pub items: Vec<Expr>,
+    pub id: NodeId,
+    pub loc: Loc,
+}
+
+// '[x * x for x in 0..10 if x % 2 == 0]'. With an end, the source is the
+// start of an int range, otherwise it is a list whose elements are taken.
+// The placeholder only lives in the value and the condition
+#[derive(Debug, PartialEq, Clone)]
+pub struct ComprehensionExpr {
+    pub value: Box<Expr>,
+    pub name: EcoString,
+    pub name_loc: Loc,
+    pub source: Box<Expr>,
+    pub end: Option<Box<Expr>>,
+    pub cond: Option<Box<Expr>>,
+    pub id: NodeId,
+    pub loc: Loc,
+}
+
+impl ComprehensionExpr {
+    pub fn sub_exprs(&self) -> impl Iterator<Item = &Expr> {
+        [&self.value, &self.source]
+            .into_iter()
+            .chain(&self.end)
+            .chain(&self.cond)
+            .map(|e| e.as_ref())
+    }
+}
+
+// 'spawn f(a)', the call is always an 'Expr::Call'
+#[derive(Debug, PartialEq, Clone)]
+pub struct SpawnExpr {
+    pub call: Box<Expr>,
+    pub id: NodeId,
+    pub loc: Loc,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct AwaitExpr {
+    pub task: Box<Expr>,
+    pub id: NodeId,
     pub loc: Loc,
 }
 
@@ -128,12 +340,22 @@ impl Expr {
             Expr::Grouping(e) => visitor.visit_grouping_expr(e),
             Expr::IntLiteral(e) => visitor.visit_int_literal_expr(e),
             Expr::RealLiteral(e) => visitor.visit_real_literal_expr(e),
+            Expr::ImaginaryLiteral(e) => visitor.visit_imaginary_literal_expr(e),
             Expr::StrLiteral(e) => visitor.visit_str_literal_expr(e),
+            Expr::CharLiteral(e) => visitor.visit_char_literal_expr(e),
+            Expr::UnitLiteral(e) => visitor.visit_unit_literal_expr(e),
             Expr::Identifier(e) => visitor.visit_identifier_expr(e),
             Expr::Unary(e) => visitor.visit_unary_expr(e),
             Expr::Assign(e) => visitor.visit_assign_expr(e),
             Expr::Logical(l) => visitor.visit_logical_expr(l),
             Expr::Call(c) => visitor.visit_call_expr(c),
+            Expr::Get(g) => visitor.visit_get_expr(g),
+            Expr::Index(i) => visitor.visit_index_expr(i),
+            Expr::List(l) => visitor.visit_list_expr(l),
+            Expr::Comprehension(c) => visitor.visit_comprehension_expr(c),
+            Expr::Set(s) => visitor.visit_set_expr(s),
+            Expr::Spawn(s) => visitor.visit_spawn_expr(s),
+            Expr::Await(a) => visitor.visit_await_expr(a),
         }
     }
 }
@@ -143,18 +365,33 @@ pub trait VisitExpr<T, U: PhyReport> {
     fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> Result<T, PhyResult<U>>;
     fn visit_int_literal_expr(&mut self, expr: &IntLiteralExpr) -> Result<T, PhyResult<U>>;
     fn visit_real_literal_expr(&mut self, expr: &RealLiteralExpr) -> Result<T, PhyResult<U>>;
+    fn visit_imaginary_literal_expr(
+        &mut self,
+        expr: &ImaginaryLiteralExpr,
+    ) -> Result<T, PhyResult<U>>;
     fn visit_str_literal_expr(&mut self, expr: &StrLiteralExpr) -> Result<T, PhyResult<U>>;
+    fn visit_char_literal_expr(&mut self, expr: &CharLiteralExpr) -> Result<T, PhyResult<U>>;
+    fn visit_unit_literal_expr(&mut self, expr: &UnitLiteralExpr) -> Result<T, PhyResult<U>>;
     fn visit_identifier_expr(&mut self, expr: &IdentifierExpr) -> Result<T, PhyResult<U>>;
     fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> Result<T, PhyResult<U>>;
     fn visit_assign_expr(&mut self, expr: &AssignExpr) -> Result<T, PhyResult<U>>;
     fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> Result<T, PhyResult<U>>;
     fn visit_call_expr(&mut self, expr: &CallExpr) -> Result<T, PhyResult<U>>;
+    fn visit_get_expr(&mut self, expr: &GetExpr) -> Result<T, PhyResult<U>>;
+    fn visit_index_expr(&mut self, expr: &IndexExpr) -> Result<T, PhyResult<U>>;
+    fn visit_list_expr(&mut self, expr: &ListExpr) -> Result<T, PhyResult<U>>;
+    fn visit_comprehension_expr(&mut self, expr: &ComprehensionExpr)
+        -> Result<T, PhyResult<U>>;
+    fn visit_set_expr(&mut self, expr: &SetExpr) -> Result<T, PhyResult<U>>;
+    fn visit_spawn_expr(&mut self, expr: &SpawnExpr) -> Result<T, PhyResult<U>>;
+    fn visit_await_expr(&mut self, expr: &AwaitExpr) -> Result<T, PhyResult<U>>;
 }
 
 // Into
 impl From<&IdentifierExpr> for Expr {
     fn from(value: &IdentifierExpr) -> Self {
         Self::Identifier(IdentifierExpr {
+            id: NodeId::default(),
             name: value.name.clone(),
             loc: value.loc.clone(),
         })
@@ -164,8 +401,10 @@ impl From<&IdentifierExpr> for Expr {
 impl From<&AssignExpr> for Expr {
     fn from(value: &AssignExpr) -> Self {
         Self::Assign(AssignExpr {
+            id: NodeId::default(),
             name: value.name.clone(),
             value: value.value.clone(),
+            name_loc: value.name_loc.clone(),
             loc: value.loc.clone(),
         })
     }
@@ -0,0 +1,200 @@
+use std::{fmt::Display, rc::Rc};
+
+use super::{expr::Expr, stmt::{ForRange, Stmt}};
+
+// Identifier of a statement or an expression, unique in the tree returned
+// by the parser. Given in the order of the code, they are the same each
+// time the same code is parsed, so data about the nodes like caches or
+// coverage can be keyed by them. The nodes created later, by the
+// optimizations, take the id of the node they come from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(pub u32);
+
+impl Display for NodeId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+// Numbers the nodes in the order of the code, the expanded macros included
+pub fn number_nodes(stmts: &mut [Stmt]) {
+    let mut next = 0;
+    stmts.iter_mut().for_each(|s| number_stmt(s, &mut next));
+}
+
+fn new_id(next: &mut u32) -> NodeId {
+    *next += 1;
+    NodeId(*next - 1)
+}
+
+fn number_stmt(stmt: &mut Stmt, next: &mut u32) {
+    match stmt {
+        Stmt::Expr(s) => {
+            s.id = new_id(next);
+            number_expr(&mut s.expr, next);
+        }
+        Stmt::VarDecl(s) => {
+            s.id = new_id(next);
+            s.value.iter_mut().for_each(|v| number_expr(v, next));
+        }
+        Stmt::Block(s) => {
+            s.id = new_id(next);
+            s.stmts.iter_mut().for_each(|s| number_stmt(s, next));
+        }
+        Stmt::If(s) => {
+            s.id = new_id(next);
+            number_expr(&mut s.condition, next);
+            s.then_branch.iter_mut().for_each(|b| number_stmt(b, next));
+            s.else_branch.iter_mut().for_each(|b| number_stmt(b, next));
+        }
+        Stmt::While(s) => {
+            s.id = new_id(next);
+            number_expr(&mut s.condition, next);
+            number_stmt(&mut s.body, next);
+        }
+        Stmt::For(s) => {
+            s.id = new_id(next);
+            s.placeholder.id = new_id(next);
+            s.rest.iter_mut().for_each(|p| p.id = new_id(next));
+
+            match &mut s.range {
+                ForRange::Ints(..) => {}
+                ForRange::Bounds(start, end) => {
+                    number_expr(start, next);
+                    number_expr(end, next);
+                }
+                ForRange::Items(items) => number_expr(items, next),
+            }
+            number_stmt(&mut s.body, next);
+        }
+        Stmt::FnDecl(s) => {
+            s.id = new_id(next);
+            s.attributes.iter_mut().for_each(|a| a.args.iter_mut().for_each(|e| number_expr(e, next)));
+
+            // Not shared yet, the function values are created when running
+            for conds in [&mut s.requires, &mut s.ensures] {
+                if let Some(conds) = Rc::get_mut(conds) {
+                    conds.iter_mut().for_each(|e| number_expr(e, next));
+                }
+            }
+            if let Some(body) = Rc::get_mut(&mut s.body) {
+                body.iter_mut().for_each(|s| number_stmt(s, next));
+            }
+        }
+        Stmt::Return(s) => {
+            s.id = new_id(next);
+            s.value.iter_mut().for_each(|v| number_expr(v, next));
+        }
+        Stmt::Import(s) => s.id = new_id(next),
+    }
+}
+
+fn number_expr(expr: &mut Expr, next: &mut u32) {
+    match expr {
+        Expr::Binary(e) => {
+            e.id = new_id(next);
+            number_expr(&mut e.left, next);
+            number_expr(&mut e.right, next);
+        }
+        Expr::Grouping(e) => {
+            e.id = new_id(next);
+            number_expr(&mut e.expr, next);
+        }
+        Expr::IntLiteral(e) => e.id = new_id(next),
+        Expr::RealLiteral(e) => e.id = new_id(next),
+        Expr::ImaginaryLiteral(e) => e.id = new_id(next),
+        Expr::StrLiteral(e) => e.id = new_id(next),
+        Expr::CharLiteral(e) => e.id = new_id(next),
+        Expr::UnitLiteral(e) => e.id = new_id(next),
+        Expr::Identifier(e) => e.id = new_id(next),
+        Expr::Unary(e) => {
+            e.id = new_id(next);
+            number_expr(&mut e.right, next);
+        }
+        Expr::Assign(e) => {
+            e.id = new_id(next);
+            number_expr(&mut e.value, next);
+        }
+        Expr::Logical(e) => {
+            e.id = new_id(next);
+            number_expr(&mut e.left, next);
+            number_expr(&mut e.right, next);
+        }
+        Expr::Call(e) => {
+            e.id = new_id(next);
+            number_expr(&mut e.callee, next);
+            e.args.iter_mut().for_each(|a| number_expr(a, next));
+        }
+        Expr::Get(e) => {
+            e.id = new_id(next);
+            number_expr(&mut e.object, next);
+        }
+        Expr::Set(e) => {
+            e.id = new_id(next);
+            number_expr(&mut e.object, next);
+            number_expr(&mut e.value, next);
+        }
+        Expr::Index(e) => {
+            e.id = new_id(next);
+            number_expr(&mut e.object, next);
+            e.start.iter_mut().chain(&mut e.end).for_each(|b| number_expr(b, next));
+        }
+        Expr::List(e) => {
+            e.id = new_id(next);
+            e.items.iter_mut().for_each(|i| number_expr(i, next));
+        }
+        Expr::Comprehension(e) => {
+            e.id = new_id(next);
+            number_expr(&mut e.value, next);
+            number_expr(&mut e.source, next);
+            e.end.iter_mut().chain(&mut e.cond).for_each(|c| number_expr(c, next));
+        }
+        Expr::Spawn(e) => {
+            e.id = new_id(next);
+            number_expr(&mut e.call, next);
+        }
+        Expr::Await(e) => {
+            e.id = new_id(next);
+            number_expr(&mut e.task, next);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::{expr::Expr, stmt::Stmt};
+    use crate::parser::utils::lex_and_parse;
+
+    use super::NodeId;
+
+    #[test]
+    fn numbering() {
+        let code = "
+macro twice(body) {
+    body
+    body
+}
+fn f(a) { return a + 1 }
+twice!() { f(2) }
+";
+        let nodes = lex_and_parse(code).unwrap();
+
+        let Stmt::FnDecl(f) = &nodes[0] else { panic!("expected a function") };
+        let Stmt::Return(ret) = &f.body[0] else { panic!("expected a return") };
+        let Some(Expr::Binary(add)) = &ret.value else { panic!("expected a binary") };
+        assert_eq!(
+            [f.id, ret.id, add.id, add.left.get_id(), add.right.get_id()],
+            [NodeId(0), NodeId(1), NodeId(2), NodeId(3), NodeId(4)]
+        );
+
+        // Each expansion of the macro has its own ids
+        let Stmt::Block(expanded) = &nodes[1] else { panic!("expected a block") };
+        let ids = expanded.stmts.iter().map(|s| s.get_id()).collect::<Vec<NodeId>>();
+        assert_eq!(expanded.id, NodeId(5));
+        assert!(ids[0] != ids[1] && ids.iter().all(|id| id.0 > 5));
+
+        // Same code, same ids
+        let again = lex_and_parse(code).unwrap();
+        assert_eq!(nodes[1].get_id(), again[1].get_id());
+    }
+}
@@ -2,13 +2,12 @@ use std::rc::Rc;
 
 use ecow::EcoString;
 
-use super::expr::Expr;
+use super::{expr::Expr, node_id::NodeId, types::TypeAnn};
 use tools::results::{Loc, PhyReport, PhyResult};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Stmt {
     Expr(ExprStmt),
-    Print(PrintStmt),
     VarDecl(VarDeclStmt),
     Block(BlockStmt),
     If(IfStmt),
@@ -16,84 +15,223 @@ pub enum Stmt {
     For(ForStmt),
     FnDecl(FnDeclStmt),
     Return(ReturnStmt),
+    Import(ImportStmt),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ExprStmt {
     pub expr: Expr,
+    pub id: NodeId,
     pub loc: Loc,
 }
 
-#[derive(Debug)]
-pub struct PrintStmt {
-    pub expr: Expr,
-    pub loc: Loc,
-}
-
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BlockStmt {
     pub stmts: Vec<Stmt>,
+    pub id: NodeId,
     pub loc: Loc,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct VarDeclStmt {
     pub name: EcoString,
     pub value: Option<Expr>,
+    pub ty: Option<TypeAnn>,
+    // Text of the '///' comments above the declaration
+    pub doc: Option<EcoString>,
+    // Exported from its module with 'pub'
+    pub is_pub: bool,
+    pub name_loc: Loc,
+    pub id: NodeId,
     pub loc: Loc,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct IfStmt {
     pub condition: Expr,
     pub then_branch: Option<Box<Stmt>>,
     pub else_branch: Option<Box<Stmt>>,
+    pub id: NodeId,
     pub loc: Loc,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct WhileStmt {
     pub condition: Expr,
     pub body: Box<Stmt>,
+    pub id: NodeId,
     pub loc: Loc,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ForStmt {
     pub placeholder: VarDeclStmt,
+    // Next names of 'for i, v in pairs', each item is a list split between
+    // all the names
+    pub rest: Vec<VarDeclStmt>,
     pub range: ForRange,
     pub body: Box<Stmt>,
+    pub id: NodeId,
     pub loc: Loc,
 }
 
-#[derive(Debug)]
-pub struct ForRange {
-    pub start: i64,
-    pub end: Option<i64>,
+#[derive(Debug, Clone)]
+pub enum ForRange {
+    // Int literals checked by the parser, 'for i in 5' goes from 0 to 4
+    Ints(i64, Option<i64>),
+    // 'for i in a..b', the bounds are evaluated once before the loop
+    Bounds(Box<Expr>, Box<Expr>),
+    // Items of a list or of an iterator
+    Items(Box<Expr>),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FnDeclStmt {
     pub name: EcoString,
     pub params: Rc<Vec<EcoString>>,
+    // Names between '<' and '>' after the name, usable in the annotations
+    pub type_params: Vec<EcoString>,
+    // One per parameter, None when not annotated
+    pub param_types: Vec<Option<TypeAnn>>,
+    pub return_type: Option<TypeAnn>,
+    // Conditions of the 'requires' and 'ensures' clauses, over the
+    // parameters and 'result' for the latter
+    pub requires: Rc<Vec<Expr>>,
+    pub ensures: Rc<Vec<Expr>>,
     pub body: Rc<Vec<Stmt>>,
+    pub doc: Option<EcoString>,
+    pub attributes: Vec<Attribute>,
+    pub is_pub: bool,
+    pub name_loc: Loc,
+    pub param_locs: Vec<Loc>,
+    pub id: NodeId,
     pub loc: Loc,
 }
 
-#[derive(Debug)]
+// '@name' or '@name(args)' above a declaration. The parser accepts any
+// name, each pass reads the ones it knows, like '@deprecated("msg")'
+#[derive(Debug, Clone)]
+pub struct Attribute {
+    pub name: EcoString,
+    pub args: Vec<Expr>,
+    pub loc: Loc,
+}
+
+#[derive(Debug, Clone)]
 pub struct ReturnStmt {
     pub value: Option<Expr>,
+    pub id: NodeId,
+    pub loc: Loc,
+}
+
+// 'import a.b' loads the module 'a/b.arc' and binds it to 'b'
+#[derive(Debug, Clone)]
+pub struct ImportStmt {
+    pub path: Vec<EcoString>,
+    // Location of the last name of the path, the one bound
+    pub name_loc: Loc,
+    pub id: NodeId,
     pub loc: Loc,
 }
 
+impl ImportStmt {
+    pub fn name(&self) -> &EcoString {
+        self.path.last().expect("import path is never empty")
+    }
+}
+
+impl ForStmt {
+    pub fn placeholders(&self) -> impl Iterator<Item = &VarDeclStmt> {
+        std::iter::once(&self.placeholder).chain(&self.rest)
+    }
+}
+
+impl ForRange {
+    pub fn exprs(&self) -> impl Iterator<Item = &Expr> {
+        let exprs = match self {
+            ForRange::Ints(..) => vec![],
+            ForRange::Bounds(start, end) => vec![start, end],
+            ForRange::Items(items) => vec![items],
+        };
+
+        exprs.into_iter().map(|e| e.as_ref())
+    }
+
+    pub fn map(self, mut f: impl FnMut(Expr) -> Expr) -> ForRange {
+        match self {
+            ForRange::Ints(..) => self,
+            ForRange::Bounds(start, end) => {
+                ForRange::Bounds(Box::new(f(*start)), Box::new(f(*end)))
+            }
+            ForRange::Items(items) => ForRange::Items(Box::new(f(*items))),
+        }
+    }
+}
+
+impl FnDeclStmt {
+    pub fn get_attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes.iter().find(|a| a.name == name)
+    }
+
+    // 'fn name<T>(a, b: T) -> T', with the annotations written
+    pub fn signature(&self) -> String {
+        let params = self
+            .params
+            .iter()
+            .zip(&self.param_types)
+            .map(|(p, t)| match t {
+                Some(t) => format!("{}: {}", p, t),
+                None => p.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let name = match self.type_params.is_empty() {
+            true => self.name.to_string(),
+            false => format!("{}<{}>", self.name, self.type_params.join(", ")),
+        };
+
+        match &self.return_type {
+            Some(t) => format!("fn {}({}) -> {}", name, params.join(", "), t),
+            None => format!("fn {}({})", name, params.join(", ")),
+        }
+    }
+}
+
 impl Stmt {
+    pub fn get_id(&self) -> NodeId {
+        match self {
+            Stmt::Expr(s) => s.id,
+            Stmt::VarDecl(s) => s.id,
+            Stmt::Block(s) => s.id,
+            Stmt::If(s) => s.id,
+            Stmt::While(s) => s.id,
+            Stmt::For(s) => s.id,
+            Stmt::FnDecl(s) => s.id,
+            Stmt::Return(s) => s.id,
+            Stmt::Import(s) => s.id,
+        }
+    }
+
+    pub fn get_loc(&self) -> Loc {
+        match self {
+            Stmt::Expr(s) => s.loc.clone(),
+            Stmt::VarDecl(s) => s.loc.clone(),
+            Stmt::Block(s) => s.loc.clone(),
+            Stmt::If(s) => s.loc.clone(),
+            Stmt::While(s) => s.loc.clone(),
+            Stmt::For(s) => s.loc.clone(),
+            Stmt::FnDecl(s) => s.loc.clone(),
+            Stmt::Return(s) => s.loc.clone(),
+            Stmt::Import(s) => s.loc.clone(),
+        }
+    }
+
     pub fn accept<T, U: PhyReport>(
         &self,
         visitor: &mut impl VisitStmt<T, U>,
     ) -> Result<T, PhyResult<U>> {
         match self {
             Stmt::Expr(stmt) => visitor.visit_expr_stmt(stmt),
-            Stmt::Print(stmt) => visitor.visit_print_stmt(stmt),
             Stmt::VarDecl(stmt) => visitor.visit_var_decl_stmt(stmt),
             Stmt::Block(stmt) => visitor.visit_block_stmt(stmt),
             Stmt::If(stmt) => visitor.visit_if_stmt(stmt),
@@ -101,13 +239,13 @@ impl Stmt {
             Stmt::For(stmt) => visitor.visit_for_stmt(stmt),
             Stmt::FnDecl(stmt) => visitor.visit_fn_decl_stmt(stmt),
             Stmt::Return(stmt) => visitor.visit_return_stmt(stmt),
+            Stmt::Import(stmt) => visitor.visit_import_stmt(stmt),
         }
     }
 }
 
 pub trait VisitStmt<T, U: PhyReport> {
     fn visit_expr_stmt(&mut self, stmt: &ExprStmt) -> Result<T, PhyResult<U>>;
-    fn visit_print_stmt(&mut self, stmt: &PrintStmt) -> Result<T, PhyResult<U>>;
     fn visit_var_decl_stmt(&mut self, stmt: &VarDeclStmt) -> Result<T, PhyResult<U>>;
     fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> Result<T, PhyResult<U>>;
     fn visit_if_stmt(&mut self, stmt: &IfStmt) -> Result<T, PhyResult<U>>;
@@ -115,14 +253,20 @@ pub trait VisitStmt<T, U: PhyReport> {
     fn visit_for_stmt(&mut self, stmt: &ForStmt) -> Result<T, PhyResult<U>>;
     fn visit_fn_decl_stmt(&mut self, stmt: &FnDeclStmt) -> Result<T, PhyResult<U>>;
     fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> Result<T, PhyResult<U>>;
+    fn visit_import_stmt(&mut self, stmt: &ImportStmt) -> Result<T, PhyResult<U>>;
 }
 
 // Into
 impl From<&VarDeclStmt> for Stmt {
     fn from(value: &VarDeclStmt) -> Self {
         Self::VarDecl(VarDeclStmt {
+            id: NodeId::default(),
             name: value.name.clone(),
             value: value.value.clone(),
+            ty: value.ty.clone(),
+            doc: value.doc.clone(),
+            is_pub: value.is_pub,
+            name_loc: value.name_loc.clone(),
             loc: value.loc.clone(),
         })
     }
@@ -0,0 +1,45 @@
+use std::fmt::Display;
+
+use ecow::EcoString;
+use tools::results::Loc;
+
+// Type written after a ':' or a '->', like 'int?' in 'var a: int? = null'.
+// The interpreter ignores them, they are only read by the type checker
+#[derive(Debug, PartialEq, Clone)]
+pub enum TypeAnn {
+    Named(EcoString, Loc),
+    Nullable(Box<TypeAnn>, Loc),
+    // '[int]', list of values of the type
+    List(Box<TypeAnn>, Loc),
+    // 'fn(int, int) -> int', returning any value without the '->'
+    Fn(Vec<TypeAnn>, Option<Box<TypeAnn>>, Loc),
+}
+
+impl TypeAnn {
+    pub fn get_loc(&self) -> Loc {
+        match self {
+            TypeAnn::Named(_, loc)
+            | TypeAnn::Nullable(_, loc)
+            | TypeAnn::List(_, loc)
+            | TypeAnn::Fn(_, _, loc) => loc.clone(),
+        }
+    }
+}
+
+impl Display for TypeAnn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeAnn::Named(name, _) => write!(f, "{}", name),
+            TypeAnn::Nullable(ty, _) => write!(f, "{}?", ty),
+            TypeAnn::List(ty, _) => write!(f, "[{}]", ty),
+            TypeAnn::Fn(params, ret, _) => {
+                let params = params.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+
+                match ret {
+                    Some(ret) => write!(f, "fn({}) -> {}", params.join(", "), ret),
+                    None => write!(f, "fn({})", params.join(", ")),
+                }
+            }
+        }
+    }
+}
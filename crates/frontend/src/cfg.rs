@@ -0,0 +1,172 @@
+use std::{collections::HashMap, rc::Rc};
+
+use ecow::EcoString;
+
+use crate::ast::{
+    expr::Expr,
+    node_id::NodeId,
+    stmt::{BlockStmt, FnDeclStmt, ForStmt, IfStmt, Stmt, WhileStmt},
+};
+
+// Compile time constants given by the host, like '-D DEBUG=true' on the
+// command line. The 'if' statements whose condition is one of them, or its
+// negation, are replaced by the branch taken before interpreting, so debug
+// only code has no cost when disabled. A name declared by the script, as a
+// variable or a parameter, isn't a constant anymore in its scope
+pub fn prune_disabled_branches(stmts: Vec<Stmt>, defines: &HashMap<EcoString, bool>) -> Vec<Stmt> {
+    let mut defines = defines.clone();
+    let mut pruned = vec![];
+
+    for stmt in stmts {
+        if let Stmt::VarDecl(v) = &stmt {
+            defines.remove(&v.name);
+        }
+
+        if let Some(s) = prune_stmt(stmt, &defines) {
+            pruned.push(s);
+        }
+    }
+
+    pruned
+}
+
+// None when the statement disappears
+fn prune_stmt(stmt: Stmt, defines: &HashMap<EcoString, bool>) -> Option<Stmt> {
+    match stmt {
+        Stmt::If(s) => match const_condition(&s.condition, defines) {
+            Some(true) => s.then_branch.and_then(|b| prune_stmt(*b, defines)),
+            Some(false) => s.else_branch.and_then(|b| prune_stmt(*b, defines)),
+            None => Some(Stmt::If(IfStmt {
+                then_branch: prune_branch(s.then_branch, defines),
+                else_branch: prune_branch(s.else_branch, defines),
+                ..s
+            })),
+        },
+        Stmt::Block(s) => Some(Stmt::Block(BlockStmt {
+            id: s.id,
+            stmts: prune_disabled_branches(s.stmts, defines),
+            loc: s.loc,
+        })),
+        Stmt::While(s) => Some(Stmt::While(WhileStmt {
+            body: Box::new(prune_stmt(*s.body, defines).unwrap_or_else(|| empty_block(&s.loc))),
+            ..s
+        })),
+        Stmt::For(s) => {
+            let mut defines = defines.clone();
+            s.placeholders().for_each(|p| {
+                defines.remove(&p.name);
+            });
+
+            Some(Stmt::For(ForStmt {
+                body: Box::new(prune_stmt(*s.body, &defines).unwrap_or_else(|| empty_block(&s.loc))),
+                ..s
+            }))
+        }
+        Stmt::FnDecl(s) => {
+            let mut defines = defines.clone();
+            s.params.iter().for_each(|p| {
+                defines.remove(p);
+            });
+
+            // The body is only shared once the function value exists
+            let body = match Rc::try_unwrap(s.body) {
+                Ok(body) => Rc::new(prune_disabled_branches(body, &defines)),
+                Err(body) => body,
+            };
+
+            Some(Stmt::FnDecl(FnDeclStmt { body, ..s }))
+        }
+        s => Some(s),
+    }
+}
+
+fn prune_branch(branch: Option<Box<Stmt>>, defines: &HashMap<EcoString, bool>) -> Option<Box<Stmt>> {
+    branch.and_then(|b| prune_stmt(*b, defines)).map(Box::new)
+}
+
+fn const_condition(cond: &Expr, defines: &HashMap<EcoString, bool>) -> Option<bool> {
+    match cond {
+        Expr::Identifier(i) => defines.get(&i.name).copied(),
+        Expr::Grouping(g) => const_condition(&g.expr, defines),
+        Expr::Unary(u) if u.operator == "!" => const_condition(&u.right, defines).map(|b| !b),
+        _ => None,
+    }
+}
+
+fn empty_block(loc: &tools::results::Loc) -> Stmt {
+    Stmt::Block(BlockStmt {
+        id: NodeId::default(),
+        stmts: vec![],
+        loc: loc.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use ecow::EcoString;
+
+    use crate::{
+        ast::ast_pretty_print::AstPrinter,
+        parser::utils::lex_and_parse,
+    };
+
+    use super::prune_disabled_branches;
+
+    fn prune(code: &str, defines: &[(&str, bool)]) -> Vec<String> {
+        let defines: HashMap<EcoString, bool> =
+            defines.iter().map(|(n, b)| (EcoString::from(*n), *b)).collect();
+        let nodes = prune_disabled_branches(lex_and_parse(code).unwrap(), &defines);
+        let mut printer = AstPrinter::default();
+
+        nodes.iter().map(|n| printer.print(n).unwrap()).collect()
+    }
+
+    #[test]
+    fn prune_branches() {
+        let code = "
+if DEBUG { print(1) } else { print(2) }
+if !DEBUG { print(3) }
+if (DEBUG) { print(4) }
+if DEBUG and a { print(5) }
+if OTHER { print(6) }
+fn f(x) {
+    if DEBUG { print(7) }
+}
+while a { if !DEBUG { print(8) } }
+";
+        assert_eq!(
+            prune(code, &[("DEBUG", true)]),
+            [
+                "(call print 1)",
+                "(call print 4)",
+                "(if (and DEBUG a) (call print 5))",
+                "(if OTHER (call print 6))",
+                "(fn f(x) (call print 7))",
+                "(while a (block))",
+            ]
+        );
+
+        assert_eq!(prune("if DEBUG { print(1) }", &[("DEBUG", false)]).len(), 0);
+    }
+
+    #[test]
+    fn shadowed_constant() {
+        let code = "
+fn f(DEBUG) {
+    if DEBUG { print(1) }
+}
+var DEBUG = false
+if DEBUG { print(2) }
+";
+        assert_eq!(
+            prune(code, &[("DEBUG", true)]),
+            [
+                "(fn f(DEBUG) (if DEBUG (call print 1)))",
+                "(var DEBUG false)",
+                "(if DEBUG (call print 2))",
+            ]
+        );
+    }
+}
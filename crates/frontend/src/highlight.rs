@@ -0,0 +1,139 @@
+use std::collections::HashSet;
+
+use ecow::EcoString;
+use tools::results::Loc;
+
+use crate::lexer::{Lexer, Token, TokenKind};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SemanticKind {
+    Keyword,
+    String,
+    Number,
+    Function,
+    Variable,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SemanticToken {
+    pub kind: SemanticKind,
+    // In characters, like all the locations
+    pub loc: Loc,
+}
+
+// Classified spans of the buffer, in order. Only the tokens are used so
+// that code being edited, which often doesn't parse, is still highlighted.
+// Identifiers are functions when declared with 'fn' or 'actor' anywhere in
+// the buffer or when called, variables otherwise
+pub fn semantic_tokens(code: &str) -> Vec<SemanticToken> {
+    let mut lexer = Lexer::new();
+    let _ = lexer.tokenize(code);
+    let tokens = lexer.get_tokens();
+
+    let functions: HashSet<&EcoString> = tokens
+        .windows(2)
+        .filter(|w| matches!(w[0].kind, TokenKind::Fn | TokenKind::Macro | TokenKind::Actor))
+        .filter(|w| w[1].kind == TokenKind::Identifier)
+        .map(|w| &w[1].value)
+        .collect();
+
+    tokens
+        .iter()
+        .enumerate()
+        .filter_map(|(i, tk)| {
+            let kind = match tk.kind {
+                TokenKind::String | TokenKind::Char => SemanticKind::String,
+                TokenKind::Int | TokenKind::Real | TokenKind::Imaginary => SemanticKind::Number,
+                TokenKind::Identifier if functions.contains(&tk.value) || is_called(tokens, i) => {
+                    SemanticKind::Function
+                }
+                TokenKind::Identifier => SemanticKind::Variable,
+                ref k if is_keyword(k) => SemanticKind::Keyword,
+                _ => return None,
+            };
+
+            Some(SemanticToken { kind, loc: tk.loc.clone() })
+        })
+        .collect()
+}
+
+// Followed by '(' or by the '!' of a macro call
+fn is_called(tokens: &[Token], idx: usize) -> bool {
+    tokens
+        .get(idx + 1)
+        .is_some_and(|next| matches!(next.kind, TokenKind::OpenParen | TokenKind::Bang))
+}
+
+fn is_keyword(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Struct
+            | TokenKind::Fn
+            | TokenKind::Macro
+            | TokenKind::Actor
+            | TokenKind::Include
+            | TokenKind::Import
+            | TokenKind::Pub
+            | TokenKind::SelfKw
+            | TokenKind::Var
+            | TokenKind::Const
+            | TokenKind::Return
+            | TokenKind::If
+            | TokenKind::Else
+            | TokenKind::And
+            | TokenKind::Or
+            | TokenKind::Null
+            | TokenKind::For
+            | TokenKind::While
+            | TokenKind::In
+            | TokenKind::True
+            | TokenKind::False
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{semantic_tokens, SemanticKind};
+
+    fn kinds(code: &str) -> Vec<(String, SemanticKind)> {
+        let chars: Vec<char> = code.chars().collect();
+
+        semantic_tokens(code)
+            .into_iter()
+            .map(|t| (chars[t.loc.start..t.loc.end].iter().collect(), t.kind))
+            .collect()
+    }
+
+    #[test]
+    fn classify() {
+        use SemanticKind::*;
+
+        let code = "
+var f = apply(double, 2.5)
+fn double(x) { return x * 2 }
+print(\"é\", true)
+";
+        assert_eq!(
+            kinds(code),
+            [
+                ("var".into(), Keyword),
+                ("f".into(), Variable),
+                ("apply".into(), Function),
+                ("double".into(), Function),
+                ("2.5".into(), Number),
+                ("fn".into(), Keyword),
+                ("double".into(), Function),
+                ("x".into(), Variable),
+                ("return".into(), Keyword),
+                ("x".into(), Variable),
+                ("2".into(), Number),
+                ("print".into(), Function),
+                ("\"é\"".into(), String),
+                ("true".into(), Keyword),
+            ]
+        );
+
+        // Tokens before and after a lexer error are kept
+        assert_eq!(kinds("a ? 1"), [("a".into(), Variable), ("1".into(), Number)]);
+    }
+}
@@ -0,0 +1,227 @@
+use std::path::Path;
+
+use colored::*;
+use thiserror::Error;
+use tools::{
+    messages::{localize, text, Arg, Locale, Translate},
+    results::{PhyReport, PhyResult},
+    source_map::SourceMap,
+};
+
+use crate::lexer::{Lexer, LexerErr, Token, TokenKind};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum IncludeErr {
+    #[error("{0}")]
+    Lexer(LexerErr),
+
+    #[error("expected a file path string after 'include'")]
+    MissingPath,
+
+    #[error("can't read included file '{0}': {1}")]
+    CantRead(String, String),
+
+    #[error("circular include: {0}")]
+    Circular(String),
+}
+
+impl PhyReport for IncludeErr {
+    fn get_err_msg(&self, locale: &dyn Locale) -> String {
+        match self {
+            IncludeErr::Lexer(e) => e.get_err_msg(locale),
+            e => format!(
+                "{} {}",
+                text(locale, "label.include", "Include error:").red(),
+                localize(locale, e)
+            ),
+        }
+    }
+}
+
+impl Translate for IncludeErr {
+    fn category(&self) -> &'static str {
+        "include"
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            IncludeErr::Lexer(..) => "Lexer",
+            IncludeErr::MissingPath => "MissingPath",
+            IncludeErr::CantRead(..) => "CantRead",
+            IncludeErr::Circular(..) => "Circular",
+        }
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        match self {
+            IncludeErr::Lexer(e) => vec![Arg::message(e)],
+            IncludeErr::CantRead(a, b) => vec![Arg::text(a), Arg::text(b)],
+            IncludeErr::Circular(v) => vec![Arg::text(v)],
+            _ => vec![],
+        }
+    }
+}
+
+pub type PhyResInclude = PhyResult<IncludeErr>;
+
+// Lexes the code and replaces each 'include "file.arc"' line by the tokens
+// of the file, before parsing. Paths are relative to the including file.
+// Each file is registered in the source map with its own locations, so the
+// errors in included code point at the file it comes from
+pub fn lex_with_includes(
+    name: &str,
+    code: &str,
+    sources: &mut SourceMap,
+    loader: &dyn Fn(&str) -> std::io::Result<String>,
+) -> Result<Vec<Token>, Vec<PhyResInclude>> {
+    let mut stack = vec![name.to_string()];
+    let mut tokens = lex_file(name, code, sources, loader, &mut stack)?;
+
+    // Only the end of the main file is kept
+    let eof = tokens.last().cloned();
+    tokens.retain(|t| t.kind != TokenKind::Eof);
+    tokens.extend(eof);
+
+    Ok(tokens)
+}
+
+fn lex_file(
+    name: &str,
+    code: &str,
+    sources: &mut SourceMap,
+    loader: &dyn Fn(&str) -> std::io::Result<String>,
+    stack: &mut Vec<String>,
+) -> Result<Vec<Token>, Vec<PhyResInclude>> {
+    let offset = sources.add(name, code);
+    let mut lexer = Lexer::with_offset(offset);
+
+    let tokens = lexer.tokenize(code).map_err(|errs| {
+        errs.into_iter()
+            .map(|e| PhyResult::new(IncludeErr::Lexer(e.err), e.loc))
+            .collect::<Vec<PhyResInclude>>()
+    })?;
+
+    let mut res = vec![];
+    let mut errs = vec![];
+    let mut tokens = tokens.iter();
+
+    while let Some(tk) = tokens.next() {
+        if tk.kind != TokenKind::Include {
+            res.push(tk.clone());
+            continue;
+        }
+
+        let path = match tokens.next() {
+            Some(p) if p.kind == TokenKind::String => p,
+            _ => {
+                errs.push(PhyResult::new(IncludeErr::MissingPath, Some(tk.loc.clone())));
+                continue;
+            }
+        };
+
+        let file = match Path::new(name).parent() {
+            Some(dir) => dir.join(path.value.as_str()).to_string_lossy().to_string(),
+            None => path.value.to_string(),
+        };
+
+        if stack.contains(&file) {
+            let chain = [stack.as_slice(), &[file]].concat().join(" -> ");
+            errs.push(PhyResult::new(IncludeErr::Circular(chain), Some(path.loc.clone())));
+            continue;
+        }
+
+        let included = match loader(&file) {
+            Ok(code) => code,
+            Err(e) => {
+                errs.push(PhyResult::new(
+                    IncludeErr::CantRead(file, e.to_string()),
+                    Some(path.loc.clone()),
+                ));
+                continue;
+            }
+        };
+
+        stack.push(file.clone());
+
+        match lex_file(&file, &included, sources, loader, stack) {
+            Ok(tks) => {
+                // The included code ends like a line of the including file
+                res.extend(tks.into_iter().filter(|t| t.kind != TokenKind::Eof));
+                res.push(Token {
+                    kind: TokenKind::NewLine,
+                    value: "\n".into(),
+                    loc: path.loc.clone(),
+                });
+            }
+            Err(e) => errs.extend(e),
+        }
+
+        stack.pop();
+    }
+
+    match errs.is_empty() {
+        true => Ok(res),
+        false => Err(errs),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, io};
+
+    use tools::source_map::SourceMap;
+
+    use crate::{ast::ast_pretty_print::AstPrinter, lexer::TokenKind, parser::Parser};
+
+    use super::{lex_with_includes, IncludeErr};
+
+    fn loader(files: HashMap<&'static str, &'static str>) -> impl Fn(&str) -> io::Result<String> {
+        move |path: &str| match files.get(path) {
+            Some(code) => Ok(code.to_string()),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, "not found")),
+        }
+    }
+
+    #[test]
+    fn include() {
+        let files = HashMap::from([
+            ("lib/math.arc", "include \"consts.arc\"\nfn double(x) { return 2 * x }"),
+            ("lib/consts.arc", "var TWO = 2"),
+        ]);
+        let code = "include \"lib/math.arc\"\nprint(double(TWO))";
+        let mut sources = SourceMap::default();
+
+        let tokens = lex_with_includes("main.arc", code, &mut sources, &loader(files)).unwrap();
+        assert_eq!(tokens.iter().filter(|t| t.kind == TokenKind::Eof).count(), 1);
+
+        let nodes = Parser::default().parse(&tokens).unwrap();
+        let mut printer = AstPrinter::default();
+        let printed: Vec<String> = nodes.iter().map(|n| printer.print(n).unwrap()).collect();
+        assert_eq!(
+            printed,
+            ["(var TWO 2)", "(fn double(x) (return (* 2 x)))", "(call print (call double TWO))"]
+        );
+
+        // Locations point in the included files
+        let var_tk = tokens.iter().find(|t| t.value == "TWO").unwrap();
+        assert_eq!(sources.lookup(&var_tk.loc).unwrap().name, "lib/consts.arc");
+    }
+
+    #[test]
+    fn include_errors() {
+        let files = HashMap::from([("a.arc", "include \"b.arc\""), ("b.arc", "include \"a.arc\"")]);
+        let code = "include \"a.arc\"\ninclude \"c.arc\"\ninclude 3";
+        let mut sources = SourceMap::default();
+
+        let errs = lex_with_includes("main.arc", code, &mut sources, &loader(files)).err().unwrap();
+        let errs: Vec<IncludeErr> = errs.into_iter().map(|e| e.err).collect();
+        assert_eq!(
+            errs,
+            [
+                IncludeErr::Circular("main.arc -> a.arc -> b.arc -> a.arc".into()),
+                IncludeErr::CantRead("c.arc".into(), "not found".into()),
+                IncludeErr::MissingPath,
+            ]
+        );
+    }
+}
@@ -0,0 +1,450 @@
+use std::{collections::HashMap, rc::Rc};
+
+use ecow::EcoString;
+
+use crate::ast::{
+    expr::{
+        AssignExpr, AwaitExpr, BinaryExpr, CallExpr, ComprehensionExpr, Expr, GetExpr, GroupingExpr,
+        IndexExpr, ListExpr, LogicalExpr, SetExpr, SpawnExpr, UnaryExpr,
+    },
+    stmt::{BlockStmt, ExprStmt, FnDeclStmt, ForStmt, IfStmt, ReturnStmt, Stmt, VarDeclStmt, WhileStmt},
+};
+
+// Calls of tiny functions are replaced by the expression they return, to
+// save the cost of the call in hot loops. A function is inlined when:
+//  - its body is a single 'return' of an expression only made of
+//    operators, literals and its parameters, each used at least once
+//  - it has no contract nor annotation, checked at each call
+//  - its name is bound nowhere else, not even as a local or a parameter,
+//    and never assigned, so that every use of the name refers to it
+//  - it is marked '@inline' or its expression has at most 'threshold' nodes
+// Only the calls after the declaration whose arguments are literals or
+// names are inlined, those can be evaluated in any order and many times
+pub fn inline_functions(stmts: Vec<Stmt>, threshold: usize) -> Vec<Stmt> {
+    let mut bindings = HashMap::new();
+    stmts.iter().for_each(|s| count_bindings(s, &mut bindings));
+
+    let mut inliner = Inliner {
+        bindings,
+        threshold,
+        candidates: HashMap::new(),
+    };
+
+    stmts
+        .into_iter()
+        .map(|s| {
+            let s = inliner.stmt(s);
+
+            if let Stmt::FnDecl(f) = &s {
+                inliner.register(f);
+            }
+
+            s
+        })
+        .collect()
+}
+
+struct Candidate {
+    params: Rc<Vec<EcoString>>,
+    expr: Expr,
+}
+
+struct Inliner {
+    // Number of declarations and assignments of each name
+    bindings: HashMap<EcoString, usize>,
+    threshold: usize,
+    candidates: HashMap<EcoString, Candidate>,
+}
+
+impl Inliner {
+    fn register(&mut self, f: &FnDeclStmt) {
+        if self.bindings.get(&f.name) != Some(&1)
+            || !f.requires.is_empty()
+            || !f.ensures.is_empty()
+            || !f.type_params.is_empty()
+            || f.return_type.is_some()
+            || f.param_types.iter().any(|t| t.is_some())
+        {
+            return;
+        }
+
+        let [Stmt::Return(ReturnStmt { value: Some(expr), .. })] = f.body.as_slice() else {
+            return;
+        };
+
+        let mut uses = HashMap::new();
+        if !is_pure(expr, &f.params, &mut uses) || uses.len() != f.params.len() {
+            return;
+        }
+
+        if f.get_attribute("inline").is_some() || size(expr) <= self.threshold {
+            self.candidates.insert(
+                f.name.clone(),
+                Candidate {
+                    params: f.params.clone(),
+                    expr: expr.clone(),
+                },
+            );
+        }
+    }
+
+    fn stmt(&self, stmt: Stmt) -> Stmt {
+        match stmt {
+            Stmt::Expr(s) => Stmt::Expr(ExprStmt {
+                expr: self.expr(s.expr),
+                ..s
+            }),
+            Stmt::VarDecl(s) => Stmt::VarDecl(self.var_decl(s)),
+            Stmt::Block(s) => Stmt::Block(BlockStmt {
+                id: s.id,
+                stmts: s.stmts.into_iter().map(|s| self.stmt(s)).collect(),
+                loc: s.loc,
+            }),
+            Stmt::If(s) => Stmt::If(IfStmt {
+                id: s.id,
+                condition: self.expr(s.condition),
+                then_branch: s.then_branch.map(|b| Box::new(self.stmt(*b))),
+                else_branch: s.else_branch.map(|b| Box::new(self.stmt(*b))),
+                loc: s.loc,
+            }),
+            Stmt::While(s) => Stmt::While(WhileStmt {
+                id: s.id,
+                condition: self.expr(s.condition),
+                body: Box::new(self.stmt(*s.body)),
+                loc: s.loc,
+            }),
+            Stmt::For(s) => Stmt::For(ForStmt {
+                range: s.range.map(|e| self.expr(e)),
+                body: Box::new(self.stmt(*s.body)),
+                ..s
+            }),
+            Stmt::FnDecl(s) => {
+                // The body is only shared once the function value exists
+                let body = match Rc::try_unwrap(s.body) {
+                    Ok(body) => Rc::new(body.into_iter().map(|s| self.stmt(s)).collect()),
+                    Err(body) => body,
+                };
+
+                Stmt::FnDecl(FnDeclStmt { body, ..s })
+            }
+            Stmt::Return(s) => Stmt::Return(ReturnStmt {
+                id: s.id,
+                value: s.value.map(|v| self.expr(v)),
+                loc: s.loc,
+            }),
+            s => s,
+        }
+    }
+
+    fn var_decl(&self, stmt: VarDeclStmt) -> VarDeclStmt {
+        VarDeclStmt {
+            value: stmt.value.map(|v| self.expr(v)),
+            ..stmt
+        }
+    }
+
+    fn expr(&self, expr: Expr) -> Expr {
+        match expr {
+            Expr::Call(c) => {
+                let callee = self.expr(*c.callee);
+                let args: Vec<Expr> = c.args.into_iter().map(|a| self.expr(a)).collect();
+
+                match self.inlined(&callee, &args) {
+                    Some(e) => e,
+                    None => Expr::Call(CallExpr {
+                        id: c.id,
+                        callee: Box::new(callee),
+                        args,
+                        loc: c.loc,
+                    }),
+                }
+            }
+            Expr::Binary(b) => Expr::Binary(BinaryExpr {
+                left: Box::new(self.expr(*b.left)),
+                right: Box::new(self.expr(*b.right)),
+                ..b
+            }),
+            Expr::Logical(l) => Expr::Logical(LogicalExpr {
+                left: Box::new(self.expr(*l.left)),
+                right: Box::new(self.expr(*l.right)),
+                ..l
+            }),
+            Expr::Grouping(g) => Expr::Grouping(GroupingExpr {
+                id: g.id,
+                expr: Box::new(self.expr(*g.expr)),
+                loc: g.loc,
+            }),
+            Expr::Unary(u) => Expr::Unary(UnaryExpr {
+                right: Box::new(self.expr(*u.right)),
+                ..u
+            }),
+            Expr::Assign(a) => Expr::Assign(AssignExpr {
+                value: Box::new(self.expr(*a.value)),
+                ..a
+            }),
+            Expr::Get(g) => Expr::Get(GetExpr {
+                object: Box::new(self.expr(*g.object)),
+                ..g
+            }),
+            Expr::Set(s) => Expr::Set(SetExpr {
+                object: Box::new(self.expr(*s.object)),
+                value: Box::new(self.expr(*s.value)),
+                ..s
+            }),
+            Expr::Index(i) => Expr::Index(IndexExpr {
+                object: Box::new(self.expr(*i.object)),
+                start: i.start.map(|s| Box::new(self.expr(*s))),
+                end: i.end.map(|e| Box::new(self.expr(*e))),
+                ..i
+            }),
+            Expr::List(l) => Expr::List(ListExpr {
+                items: l.items.into_iter().map(|i| self.expr(i)).collect(),
+                ..l
+            }),
+            Expr::Comprehension(c) => Expr::Comprehension(ComprehensionExpr {
+                value: Box::new(self.expr(*c.value)),
+                source: Box::new(self.expr(*c.source)),
+                end: c.end.map(|e| Box::new(self.expr(*e))),
+                cond: c.cond.map(|e| Box::new(self.expr(*e))),
+                ..c
+            }),
+            // The spawned call stays a call, only its arguments change
+            Expr::Spawn(s) => {
+                let call = match *s.call {
+                    Expr::Call(c) => Expr::Call(CallExpr {
+                        args: c.args.into_iter().map(|a| self.expr(a)).collect(),
+                        ..c
+                    }),
+                    e => e,
+                };
+
+                Expr::Spawn(SpawnExpr {
+                    id: s.id,
+                    call: Box::new(call),
+                    loc: s.loc,
+                })
+            }
+            Expr::Await(a) => Expr::Await(AwaitExpr {
+                id: a.id,
+                task: Box::new(self.expr(*a.task)),
+                loc: a.loc,
+            }),
+            e => e,
+        }
+    }
+
+    fn inlined(&self, callee: &Expr, args: &[Expr]) -> Option<Expr> {
+        let Expr::Identifier(name) = callee else {
+            return None;
+        };
+        let candidate = self.candidates.get(&name.name)?;
+
+        if args.len() != candidate.params.len() || !args.iter().all(is_trivial) {
+            return None;
+        }
+
+        let values: HashMap<&EcoString, &Expr> = candidate.params.iter().zip(args).collect();
+
+        Some(substitute(&candidate.expr, &values))
+    }
+}
+
+fn is_trivial(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::IntLiteral(_)
+            | Expr::RealLiteral(_)
+            | Expr::ImaginaryLiteral(_)
+            | Expr::StrLiteral(_)
+            | Expr::CharLiteral(_)
+            | Expr::UnitLiteral(_)
+            | Expr::Identifier(_)
+    )
+}
+
+// Fills 'uses' with the parameters read
+fn is_pure<'a>(expr: &'a Expr, params: &[EcoString], uses: &mut HashMap<&'a EcoString, usize>) -> bool {
+    match expr {
+        Expr::Identifier(i) if params.contains(&i.name) => {
+            *uses.entry(&i.name).or_default() += 1;
+            true
+        }
+        Expr::Binary(b) => is_pure(&b.left, params, uses) && is_pure(&b.right, params, uses),
+        Expr::Logical(l) => is_pure(&l.left, params, uses) && is_pure(&l.right, params, uses),
+        Expr::Grouping(g) => is_pure(&g.expr, params, uses),
+        Expr::Unary(u) => is_pure(&u.right, params, uses),
+        e => is_trivial(e) && !matches!(e, Expr::Identifier(_)),
+    }
+}
+
+// Number of nodes of a pure expression
+fn size(expr: &Expr) -> usize {
+    match expr {
+        Expr::Binary(b) => 1 + size(&b.left) + size(&b.right),
+        Expr::Logical(l) => 1 + size(&l.left) + size(&l.right),
+        Expr::Grouping(g) => size(&g.expr),
+        Expr::Unary(u) => 1 + size(&u.right),
+        _ => 1,
+    }
+}
+
+fn substitute(expr: &Expr, values: &HashMap<&EcoString, &Expr>) -> Expr {
+    match expr {
+        Expr::Identifier(i) => values.get(&i.name).map_or_else(|| expr.clone(), |v| (*v).clone()),
+        Expr::Binary(b) => Expr::Binary(BinaryExpr {
+            left: Box::new(substitute(&b.left, values)),
+            right: Box::new(substitute(&b.right, values)),
+            ..b.clone()
+        }),
+        Expr::Logical(l) => Expr::Logical(LogicalExpr {
+            left: Box::new(substitute(&l.left, values)),
+            right: Box::new(substitute(&l.right, values)),
+            ..l.clone()
+        }),
+        Expr::Grouping(g) => Expr::Grouping(GroupingExpr {
+            id: g.id,
+            expr: Box::new(substitute(&g.expr, values)),
+            loc: g.loc.clone(),
+        }),
+        Expr::Unary(u) => Expr::Unary(UnaryExpr {
+            right: Box::new(substitute(&u.right, values)),
+            ..u.clone()
+        }),
+        e => e.clone(),
+    }
+}
+
+fn count_bindings(stmt: &Stmt, bindings: &mut HashMap<EcoString, usize>) {
+    match stmt {
+        Stmt::Expr(s) => count_assigns(&s.expr, bindings),
+        Stmt::VarDecl(s) => {
+            bind(&s.name, bindings);
+            s.value.iter().for_each(|v| count_assigns(v, bindings));
+        }
+        Stmt::Block(s) => s.stmts.iter().for_each(|s| count_bindings(s, bindings)),
+        Stmt::If(s) => {
+            count_assigns(&s.condition, bindings);
+            s.then_branch.iter().chain(&s.else_branch).for_each(|b| count_bindings(b, bindings));
+        }
+        Stmt::While(s) => {
+            count_assigns(&s.condition, bindings);
+            count_bindings(&s.body, bindings);
+        }
+        Stmt::For(s) => {
+            s.range.exprs().for_each(|e| count_assigns(e, bindings));
+            s.placeholders().for_each(|p| bind(&p.name, bindings));
+            count_bindings(&s.body, bindings);
+        }
+        Stmt::FnDecl(s) => {
+            bind(&s.name, bindings);
+            s.params.iter().for_each(|p| bind(p, bindings));
+            s.body.iter().for_each(|s| count_bindings(s, bindings));
+        }
+        Stmt::Return(s) => s.value.iter().for_each(|v| count_assigns(v, bindings)),
+        Stmt::Import(s) => bind(s.name(), bindings),
+    }
+}
+
+fn bind(name: &EcoString, bindings: &mut HashMap<EcoString, usize>) {
+    *bindings.entry(name.clone()).or_default() += 1;
+}
+
+fn count_assigns(expr: &Expr, bindings: &mut HashMap<EcoString, usize>) {
+    match expr {
+        Expr::Assign(a) => {
+            bind(&a.name, bindings);
+            count_assigns(&a.value, bindings);
+        }
+        Expr::Binary(b) => {
+            count_assigns(&b.left, bindings);
+            count_assigns(&b.right, bindings);
+        }
+        Expr::Logical(l) => {
+            count_assigns(&l.left, bindings);
+            count_assigns(&l.right, bindings);
+        }
+        Expr::Grouping(g) => count_assigns(&g.expr, bindings),
+        Expr::Unary(u) => count_assigns(&u.right, bindings),
+        Expr::Call(c) => {
+            count_assigns(&c.callee, bindings);
+            c.args.iter().for_each(|a| count_assigns(a, bindings));
+        }
+        Expr::Get(g) => count_assigns(&g.object, bindings),
+        Expr::Set(s) => {
+            count_assigns(&s.object, bindings);
+            count_assigns(&s.value, bindings);
+        }
+        Expr::Index(i) => {
+            count_assigns(&i.object, bindings);
+            i.bounds().for_each(|b| count_assigns(b, bindings));
+        }
+        Expr::List(l) => l.items.iter().for_each(|i| count_assigns(i, bindings)),
+        Expr::Comprehension(c) => {
+            bind(&c.name, bindings);
+            c.sub_exprs().for_each(|e| count_assigns(e, bindings));
+        }
+        Expr::Spawn(s) => count_assigns(&s.call, bindings),
+        Expr::Await(a) => count_assigns(&a.task, bindings),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ast::ast_pretty_print::AstPrinter, parser::utils::lex_and_parse};
+
+    use super::inline_functions;
+
+    fn inline(code: &str, threshold: usize) -> Vec<String> {
+        let nodes = inline_functions(lex_and_parse(code).unwrap(), threshold);
+        let mut printer = AstPrinter::default();
+
+        nodes.iter().map(|n| printer.print(n).unwrap()).collect()
+    }
+
+    #[test]
+    fn inline_calls() {
+        let code = "
+fn sq(x) { return x * x }
+fn norm(x, y) { return sq(x) + sq(y) }
+var a = norm(3, b)
+print(sq(a + 1))
+";
+        assert_eq!(
+            inline(code, 8)[2..],
+            ["(var a (+ (* 3 3) (* b b)))", "(call print (call sq (+ a 1)))"]
+        );
+
+        // Above the threshold, unless marked
+        assert_eq!(inline(code, 2)[2], "(var a (call norm 3 b))");
+        let code = "
+@inline
+fn norm(x, y) { return x * x + y * y }
+var a = norm(3, b)
+";
+        assert_eq!(inline(code, 0)[1], "(var a (+ (* 3 3) (* b b)))");
+    }
+
+    #[test]
+    fn not_inlined() {
+        let code = "
+sq(1)
+fn sq(x) { return x * x }
+fn first(a, b) { return a }
+fn scaled(x) { return x * scale }
+fn typed(x: int) { return x }
+fn twice(x) { return x * 2 }
+fn shadowed(x) { return x }
+var scale = 2
+twice = 3
+fn f(shadowed) { return shadowed }
+first(1, 2) + scaled(1) + typed(1) + twice(1) + shadowed(1)
+";
+        let nodes = inline(code, 8);
+        assert_eq!(nodes[0], "(call sq 1)");
+        assert_eq!(
+            nodes[10],
+            "(+ (+ (+ (+ (call first 1 2) (call scaled 1)) (call typed 1)) (call twice 1)) (call shadowed 1))"
+        );
+    }
+}
@@ -3,13 +3,15 @@ use ecow::EcoString;
 use thiserror::Error;
 use colored::*;
 
+use tools::messages::{localize, text, Arg, Locale, Translate};
+use tools::real_format::parse_real;
 use tools::results::{PhyReport, PhyResult, Loc};
 
 
 // ----------------
 // Error managment
 // ----------------
-#[derive(Error, Debug)]
+#[derive(Error, Debug, PartialEq)]
 pub enum LexerErr {
     //Tokens
     #[error("unexpected token found: '{0}'")]
@@ -19,17 +21,64 @@ pub enum LexerErr {
     #[error("string literal never closed with '\"'")]
     StringNeverClosed,
 
+    // Chars
+    #[error("char literal must contain one char and be closed with \"'\"")]
+    CharNeverClosed,
+
+    #[error("empty char literal")]
+    EmptyChar,
+
+    #[error("unknown escape sequence: '\\{0}'")]
+    UnknownEscape(char),
+
     // Numbers
     #[error("expected nothing after real number declaration, found: '{0}'")]
     NoSpaceAfterNumber(char),
 
     #[error("expected numbers or nothing after '.' in number literal, found: '{0}'")]
     NonNumericDecimal(char),
+
+    #[error("int literal {0} is larger than the maximum int {}, write it as a real like {0}. instead", i64::MAX)]
+    IntTooLarge(String),
+
+    #[error("real literal {0} is out of the range of reals, from 5e-324 to 1.8e308")]
+    RealOutOfRange(String),
 }
 
 impl PhyReport for LexerErr {
-    fn get_err_msg(&self) -> String {
-        format!("{} {}", "Lexer error:".red(), self)
+    fn get_err_msg(&self, locale: &dyn Locale) -> String {
+        format!("{} {}", text(locale, "label.lexer", "Lexer error:").red(), localize(locale, self))
+    }
+}
+
+impl Translate for LexerErr {
+    fn category(&self) -> &'static str {
+        "lexer"
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            LexerErr::UnexpectedToken(..) => "UnexpectedToken",
+            LexerErr::StringNeverClosed => "StringNeverClosed",
+            LexerErr::CharNeverClosed => "CharNeverClosed",
+            LexerErr::EmptyChar => "EmptyChar",
+            LexerErr::UnknownEscape(..) => "UnknownEscape",
+            LexerErr::NoSpaceAfterNumber(..) => "NoSpaceAfterNumber",
+            LexerErr::NonNumericDecimal(..) => "NonNumericDecimal",
+            LexerErr::IntTooLarge(..) => "IntTooLarge",
+            LexerErr::RealOutOfRange(..) => "RealOutOfRange",
+        }
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        match self {
+            LexerErr::UnexpectedToken(v)
+            | LexerErr::UnknownEscape(v)
+            | LexerErr::NoSpaceAfterNumber(v)
+            | LexerErr::NonNumericDecimal(v) => vec![Arg::text(v)],
+            LexerErr::IntTooLarge(v) | LexerErr::RealOutOfRange(v) => vec![Arg::text(v)],
+            _ => vec![],
+        }
     }
 }
 
@@ -46,6 +95,8 @@ pub enum TokenKind {
     CloseParen,
     OpenBrace,
     CloseBrace,
+    OpenBracket,
+    CloseBracket,
     Comma,
     Dot,
     Minus,
@@ -53,6 +104,10 @@ pub enum TokenKind {
     Slash,
     Star,
     Modulo,
+    Caret,
+    At,
+    Colon,
+    Question,
 
     // One or two characters
     Bang,
@@ -64,16 +119,26 @@ pub enum TokenKind {
     Less,
     LessEqual,
     DotDot,
+    Arrow,
+
+    // Operator declared by the script, like '<+>'
+    CustomOp,
 
     // Literals
     Identifier,
     String,
+    Char,
     Int,
     Real,
+    Imaginary,
 
     // Keywords
     Struct,
     Fn,
+    Macro,
+    Include,
+    Import,
+    Pub,
     SelfKw,
     Var,
     Const,
@@ -83,12 +148,18 @@ pub enum TokenKind {
     And,
     Or,
     Null,
-    Print,
     For,
     While,
     In,
     True,
     False,
+    Spawn,
+    Await,
+    Operator,
+    Actor,
+
+    // Text of a '///' comment starting a line
+    DocComment,
 
     NewLine,
     Eof,
@@ -108,6 +179,66 @@ impl Display for Token {
     }
 }
 
+// Reserved words, never lexed as identifiers
+pub const KEYWORDS: [(&str, TokenKind); 24] = [
+    ("var", TokenKind::Var),
+    ("const", TokenKind::Const),
+    ("true", TokenKind::True),
+    ("false", TokenKind::False),
+    ("struct", TokenKind::Struct),
+    ("self", TokenKind::SelfKw),
+    ("fn", TokenKind::Fn),
+    ("return", TokenKind::Return),
+    ("if", TokenKind::If),
+    ("else", TokenKind::Else),
+    ("and", TokenKind::And),
+    ("or", TokenKind::Or),
+    ("for", TokenKind::For),
+    ("while", TokenKind::While),
+    ("macro", TokenKind::Macro),
+    ("include", TokenKind::Include),
+    ("import", TokenKind::Import),
+    ("pub", TokenKind::Pub),
+    ("in", TokenKind::In),
+    ("null", TokenKind::Null),
+    ("spawn", TokenKind::Spawn),
+    ("await", TokenKind::Await),
+    ("operator", TokenKind::Operator),
+    ("actor", TokenKind::Actor),
+];
+
+impl TokenKind {
+    pub fn is_keyword(&self) -> bool {
+        KEYWORDS.iter().any(|(_, kind)| kind == self)
+    }
+}
+
+// A real literal too large becomes inf and one too small becomes 0,
+// while it has digits other than 0
+fn check_real(literal: &str) -> Option<LexerErr> {
+    let value = parse_real(literal)?;
+    let mantissa = literal.split(['e', 'E']).next().unwrap_or(literal);
+
+    let out_of_range = value.is_infinite()
+        || (value == 0. && mantissa.bytes().any(|b| matches!(b, b'1'..=b'9')));
+
+    out_of_range.then(|| LexerErr::RealOutOfRange(literal.into()))
+}
+
+// Identifiers start with a letter, of any script, or '_' and go on with
+// letters, '_' and the ascii digits. Other digits, like '²', end the name
+// so that names look the same everywhere they're displayed
+// Digits of i64::MIN, one more than the maximum int
+pub const MIN_INT_MAGNITUDE: &str = "9223372036854775808";
+
+pub fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+pub fn is_ident_continue(c: char) -> bool {
+    is_ident_start(c) || c.is_ascii_digit()
+}
+
 #[derive(Default)]
 pub struct Lexer {
     code: Vec<char>,
@@ -115,6 +246,8 @@ pub struct Lexer {
     keywords: HashMap<String, TokenKind>,
     start: usize,
     current: usize,
+    // Location of the code in the source map, added to all token locations
+    offset: usize,
 }
 
 impl Lexer {
@@ -125,28 +258,27 @@ impl Lexer {
         lex
     }
 
+    pub fn with_offset(offset: usize) -> Self {
+        let mut lex = Lexer::new();
+
+        lex.offset = offset;
+        lex
+    }
+
     fn generate_keywords(&mut self) {
-        let mut map: HashMap<String, TokenKind> = HashMap::new();
-
-        map.insert("var".into(), TokenKind::Var);
-        map.insert("const".into(), TokenKind::Const);
-        map.insert("true".into(), TokenKind::True);
-        map.insert("false".into(), TokenKind::False);
-        map.insert("struct".into(), TokenKind::Struct);
-        map.insert("self".into(), TokenKind::SelfKw);
-        map.insert("fn".into(), TokenKind::Fn);
-        map.insert("return".into(), TokenKind::Return);
-        map.insert("if".into(), TokenKind::If);
-        map.insert("else".into(), TokenKind::Else);
-        map.insert("and".into(), TokenKind::And);
-        map.insert("or".into(), TokenKind::Or);
-        map.insert("for".into(), TokenKind::For);
-        map.insert("while".into(), TokenKind::While);
-        map.insert("in".into(), TokenKind::In);
-        map.insert("null".into(), TokenKind::Null);
-        map.insert("print".into(), TokenKind::Print);
-
-        self.keywords = map;
+        self.keywords = KEYWORDS
+            .iter()
+            .map(|(name, kind)| (name.to_string(), kind.clone()))
+            .collect();
+    }
+
+    // Tokens lexed so far, also available after errors
+    pub fn get_tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    pub fn get_keywords(&self) -> Vec<&String> {
+        self.keywords.keys().collect()
     }
 
     pub fn tokenize(&mut self, code: &str) -> Result<&Vec<Token>, Vec<PhyResLex>> {
@@ -168,6 +300,8 @@ impl Lexer {
                 ')' => self.add_token(TokenKind::CloseParen),
                 '{' => self.add_token(TokenKind::OpenBrace),
                 '}' => self.add_token(TokenKind::CloseBrace),
+                '[' => self.add_token(TokenKind::OpenBracket),
+                ']' => self.add_token(TokenKind::CloseBracket),
                 ',' => self.add_token(TokenKind::Comma),
                 '.' => {
                     if self.is_at('.') {
@@ -176,10 +310,22 @@ impl Lexer {
                         self.add_token(TokenKind::Dot);
                     }
                 },
-                '-' => self.add_token(TokenKind::Minus),
+                '-' => {
+                    let tk = if self.is_at('>') {
+                        TokenKind::Arrow
+                    } else {
+                        TokenKind::Minus
+                    };
+
+                    self.add_token(tk);
+                },
                 '+' => self.add_token(TokenKind::Plus),
                 '*' => self.add_token(TokenKind::Star),
                 '%' => self.add_token(TokenKind::Modulo),
+                '^' => self.add_token(TokenKind::Caret),
+                '@' => self.add_token(TokenKind::At),
+                ':' => self.add_token(TokenKind::Colon),
+                '?' => self.add_token(TokenKind::Question),
 
                 // One or two char tokens
                 '!' => {
@@ -201,6 +347,12 @@ impl Lexer {
                     self.add_token(tk);
                 },
                 '<' => {
+                    if let Some(len) = self.custom_op_len() {
+                        self.current += len;
+                        self.add_token(TokenKind::CustomOp);
+                        continue;
+                    }
+
                     let tk = if self.is_at('=') {
                         TokenKind::LessEqual
                     } else {
@@ -231,14 +383,18 @@ impl Lexer {
                     Ok(_) => {},
                     Err(e) => errors.push(e)
                 },
+                '\'' => match self.lex_char() {
+                    Ok(_) => {},
+                    Err(e) => errors.push(e)
+                },
 
                 _ => {
-                    if c.is_numeric() {
+                    if c.is_ascii_digit() {
                         match self.lex_number() {
                             Ok(_) => {},
                             Err(e) => errors.push(e)
                         }
-                    } else if c.is_alphabetic() {
+                    } else if is_ident_start(c) {
                         match self.lex_identifier() {
                             Ok(_) => {},
                             Err(e) => errors.push(e)
@@ -257,7 +413,10 @@ impl Lexer {
             Token {
                 kind: TokenKind::Eof,
                 value: "eof".into(),
-                loc: Loc { start: self.code.len(), end: self.code.len() + 1 }
+                loc: Loc {
+                    start: self.offset + self.code.len(),
+                    end: self.offset + self.code.len() + 1
+                }
             }
         );
 
@@ -268,9 +427,19 @@ impl Lexer {
     }
 
     fn lex_comment(&mut self) {
+        // Only a '///' comment on its own line documents what follows
+        let is_doc = self.is_at('/')
+            && self.tokens.last().is_none_or(|t| t.kind == TokenKind::NewLine);
+
         while !self.eof() && self.at() != '\n' {
             self.eat();
         }
+
+        if is_doc {
+            let text: String = self.code[self.start + 3..self.current].iter().collect();
+            let text = text.strip_prefix(' ').unwrap_or(&text).trim_end();
+            self.add_value_token(TokenKind::DocComment, text.into());
+        }
     }
 
     fn lex_string(&mut self) -> Result<(), PhyResLex> {
@@ -296,14 +465,47 @@ impl Lexer {
         Ok(())
     }
 
+    // Char literal, like 'a' or '\n'. The token value is the char itself
+    fn lex_char(&mut self) -> Result<(), PhyResLex> {
+        let c = match self.at() {
+            '\'' => return Err(self.trigger_error(LexerErr::EmptyChar)),
+            _ if self.eof() || self.at() == '\n' => {
+                return Err(self.trigger_error(LexerErr::CharNeverClosed))
+            }
+            '\\' => {
+                self.eat();
+
+                match self.at() {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    '0' => '\0',
+                    '\\' => '\\',
+                    '\'' => '\'',
+                    c => return Err(self.trigger_error(LexerErr::UnknownEscape(c))),
+                }
+            }
+            c => c,
+        };
+        self.eat();
+
+        if self.at() != '\'' {
+            return Err(self.trigger_error(LexerErr::CharNeverClosed))
+        }
+        self.eat();
+
+        self.add_value_token(TokenKind::Char, c.to_string().into());
+        Ok(())
+    }
+
     fn lex_number(&mut self) -> Result<(), PhyResLex> {
-        while self.at().is_numeric() {
+        while self.at().is_ascii_digit() {
             self.eat();
         }
         
         if self.at() == '.' {
             if self.next() == '.' {
-                self.add_token(TokenKind::Int);
+                self.add_number_token(TokenKind::Int)?;
                 self.eat();
                 self.eat();
                 self.add_token(TokenKind::DotDot);
@@ -313,31 +515,97 @@ impl Lexer {
 
             self.eat();
 
-            if self.eof() || self.is_skippable() || self.at() == '\n' {
-               // Nothing 
-            } else if !self.at().is_numeric() {
-                return Err(self.trigger_error(LexerErr::NonNumericDecimal(self.at())))
-            } else {
-                while self.at().is_numeric() {
+            if self.at().is_ascii_digit() {
+                while self.at().is_ascii_digit() {
                     self.eat();
                 }
+            } else if self.is_in_number() && !self.is_at_exponent() && !self.is_at_imaginary() {
+                return Err(self.trigger_error(LexerErr::NonNumericDecimal(self.at())))
+            }
+        } else if !self.is_at_exponent() {
+            return match self.is_at_imaginary() {
+                true => self.add_imaginary_token(),
+                false => self.add_number_token(TokenKind::Int),
+            }
+        }
 
-                // After all the numbers, we expect a white space
-                if !self.eof() && !self.is_skippable() && self.at() != '\n' {
-                    return Err(self.trigger_error(LexerErr::NoSpaceAfterNumber(self.at())))
-                }
+        // Scientific notation, like '1.5e-7' or '2e10'
+        if self.is_at_exponent() {
+            self.eat();
+
+            if matches!(self.at(), '+' | '-') {
+                self.eat();
             }
-            self.add_token(TokenKind::Real);
+            while self.at().is_ascii_digit() {
+                self.eat();
+            }
+        }
 
-        } else {
-            self.add_token(TokenKind::Int);
+        if self.is_at_imaginary() {
+            return self.add_imaginary_token()
+        }
+
+        // After all the numbers, we expect the end of the literal
+        if self.is_in_number() {
+            return Err(self.trigger_error(LexerErr::NoSpaceAfterNumber(self.at())))
+        }
+
+        self.add_number_token(TokenKind::Real)
+    }
+
+    // Exponent of a real: an 'e' followed by digits, with an optional sign
+    fn is_at_exponent(&self) -> bool {
+        let after = |n: usize| self.code.get(self.current + n).copied().unwrap_or('\0');
+
+        matches!(self.at(), 'e' | 'E')
+            && (after(1).is_ascii_digit() || (matches!(after(1), '+' | '-') && after(2).is_ascii_digit()))
+    }
+
+    // Imaginary literal: a number directly followed by 'i', like '4i'
+    fn is_at_imaginary(&self) -> bool {
+        self.at() == 'i' && !is_ident_continue(self.next())
+    }
+
+    // The token value is the number without the 'i'
+    fn add_imaginary_token(&mut self) -> Result<(), PhyResLex> {
+        let value: String = self.code[self.start..self.current].iter().collect();
+        self.eat();
+
+        if let Some(e) = check_real(&value) {
+            return Err(PhyResult::new(e, Some(self.get_loc())));
         }
 
+        self.add_value_token(TokenKind::Imaginary, value.into());
         Ok(())
     }
 
+    // Literals that can't be represented are reported on their span here,
+    // rather than failing in the parser or being rounded to inf or 0
+    fn add_number_token(&mut self, kind: TokenKind) -> Result<(), PhyResLex> {
+        let value: String = self.code[self.start..self.current].iter().collect();
+
+        // The smallest int is only written negated, the parser makes one
+        // literal of the '-' and its magnitude
+        let negated = self.tokens.last().is_some_and(|t| t.kind == TokenKind::Minus);
+
+        let err = match kind {
+            TokenKind::Int if value == MIN_INT_MAGNITUDE && negated => None,
+            TokenKind::Int if value.parse::<i64>().is_err() => Some(LexerErr::IntTooLarge(value)),
+            TokenKind::Real => check_real(&value),
+            _ => None,
+        };
+
+        match err {
+            Some(e) => Err(PhyResult::new(e, Some(self.get_loc()))),
+            None => {
+                self.add_token(kind);
+                Ok(())
+            }
+        }
+    }
+
     fn lex_identifier(&mut self) -> Result<(), PhyResLex> {
-        while self.at().is_alphanumeric() || self.at() == '_' {
+        while is_ident_continue(self.at()) {
             self.eat();
         }
 
@@ -351,6 +619,15 @@ impl Lexer {
         Ok(())
     }
 
+    // Operator characters between '<' and '>' without spaces, like '<+>' or
+    // '<*>'. Never valid before, so it doesn't clash with comparisons
+    fn custom_op_len(&self) -> Option<usize> {
+        let rest = &self.code[self.current..];
+        let nb = rest.iter().take_while(|c| "+-*/%^&|~!?=.:".contains(**c)).count();
+
+        (nb > 0 && rest.get(nb) == Some(&'>')).then_some(nb + 1)
+    }
+
     fn eof(&self) -> bool {
         self.current >= self.code.len()
     }
@@ -376,6 +653,12 @@ impl Lexer {
         *self.code.get(self.current - 1).unwrap()
     }
 
+    // Chars that can't directly follow a real literal, like in '12.5.' or '12.a'.
+    // Others (white spaces, ')', ',', operators...) end the literal
+    fn is_in_number(&self) -> bool {
+        self.at().is_alphanumeric() || matches!(self.at(), '.' | '_')
+    }
+
     fn is_skippable(&self) -> bool {
         matches!(self.at(), ' ' | '\t' | '\r')
     }
@@ -429,7 +712,7 @@ impl Lexer {
     }
 
     fn get_loc(&self) -> Loc {
-        Loc::new(self.start, self.current)
+        Loc::new(self.offset + self.start, self.offset + self.current)
     }
 }
 
@@ -437,6 +720,8 @@ impl Lexer {
 mod tests {
     use ecow::EcoString;
 
+    use tools::real_format::{format_real, parse_real};
+
     use crate::lexer::{ LexerErr, Loc, TokenKind };
 
     use super::Lexer;
@@ -475,7 +760,7 @@ mod tests {
 
     #[test]
     fn tokenize_double_char() {
-        let code: String = "!= <= >= == ..".into();
+        let code: String = "!= <= >= == .. ->".into();
         let mut lexer = Lexer::new(); 
         let tokens = lexer.tokenize(&code).unwrap();
 
@@ -489,11 +774,39 @@ mod tests {
                 TokenKind::GreaterEqual,
                 TokenKind::EqualEqual,
                 TokenKind::DotDot,
+                TokenKind::Arrow,
                 TokenKind::Eof,
             ]
         );
     }
 
+    #[test]
+    fn tokenize_custom_op() {
+        let code: String = "a <+> b<*>c <= d < -e".into();
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(&code).unwrap();
+
+        let tk_kind: Vec<TokenKind> = tokens.iter().map(|tk| tk.kind.clone()).collect();
+
+        assert_eq!(
+            tk_kind,
+            vec![
+                TokenKind::Identifier,
+                TokenKind::CustomOp,
+                TokenKind::Identifier,
+                TokenKind::CustomOp,
+                TokenKind::Identifier,
+                TokenKind::LessEqual,
+                TokenKind::Identifier,
+                TokenKind::Less,
+                TokenKind::Minus,
+                TokenKind::Identifier,
+                TokenKind::Eof,
+            ]
+        );
+        assert_eq!(tokens[3].value, "<*>");
+    }
+
     #[test]
     fn tokenize_string() {
         let code: String = "\"hello world!\"".into();
@@ -505,9 +818,45 @@ mod tests {
         assert_eq!(tk_kind, vec![TokenKind::String, TokenKind::Eof]);
     }
 
+    #[test]
+    fn tokenize_char() {
+        let code: String = "'a' '\\n' '\\''".into();
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(&code).unwrap();
+
+        let tk_value: Vec<EcoString> = tokens.iter().map(|tk| tk.value.clone()).collect();
+        assert_eq!(tokens[0].kind, TokenKind::Char);
+        assert_eq!(tk_value[..3], ["a", "\n", "'"]);
+
+        for (code, err) in [
+            ("''", LexerErr::EmptyChar),
+            ("'ab'", LexerErr::CharNeverClosed),
+            ("'\\q'", LexerErr::UnknownEscape('q')),
+        ] {
+            let errs = Lexer::new().tokenize(code).err().unwrap();
+            assert_eq!(errs[0].err, err);
+        }
+    }
+
+    #[test]
+    fn tokenize_identifier() {
+        let code: String = "_a1 été vélo_2 if iff".into();
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(&code).unwrap();
+
+        let tk_value: Vec<EcoString> = tokens.iter().map(|tk| tk.value.clone()).collect();
+        assert_eq!(tk_value[..5], ["_a1", "été", "vélo_2", "if", "iff"]);
+        assert_eq!(tokens[3].kind, TokenKind::If);
+        assert_eq!(tokens[4].kind, TokenKind::Identifier);
+
+        // Only ascii digits are part of names
+        let errs = Lexer::new().tokenize("x² = 1").err().unwrap();
+        assert_eq!(errs[0].err, LexerErr::UnexpectedToken('²'));
+    }
+
     #[test]
     fn tokenize_number() {
-        let code: String = "12 25. 26.345".into();
+        let code: String = "12 25. 26.345 (1.5) 2.,".into();
         let mut lexer = Lexer::new(); 
         let tokens = lexer.tokenize(&code).unwrap();
 
@@ -516,15 +865,84 @@ mod tests {
 
         assert_eq!(
             tk_type,
-            vec![TokenKind::Int, TokenKind::Real, TokenKind::Real, TokenKind::Eof]
+            vec![
+                TokenKind::Int,
+                TokenKind::Real,
+                TokenKind::Real,
+                TokenKind::OpenParen,
+                TokenKind::Real,
+                TokenKind::CloseParen,
+                TokenKind::Real,
+                TokenKind::Comma,
+                TokenKind::Eof
+            ]
         );
 
         assert_eq!(
-            tk_value,
-            vec!["12".to_string(), "25.".to_string(), "26.345".to_string(), "eof".to_string()]
+            tk_value[..3],
+            vec!["12".to_string(), "25.".to_string(), "26.345".to_string()]
         );
     }
 
+    #[test]
+    fn tokenize_exponent() {
+        let code: String = "1e5 2.5E-3 3.e+2 4e2i 5e 6e-".into();
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(&code).unwrap();
+
+        let tk_type: Vec<TokenKind> = tokens.iter().map(|tk| tk.kind.clone()).collect();
+        let tk_value: Vec<EcoString> = tokens.iter().map(|tk| tk.value.clone()).collect();
+
+        assert_eq!(tk_type[..4], [TokenKind::Real, TokenKind::Real, TokenKind::Real, TokenKind::Imaginary]);
+        assert_eq!(tk_value[..4], ["1e5", "2.5E-3", "3.e+2", "4e2"]);
+
+        // Without digits, the 'e' isn't an exponent
+        assert_eq!(tk_type[4..6], [TokenKind::Int, TokenKind::Identifier]);
+        assert_eq!(tk_type[6..9], [TokenKind::Int, TokenKind::Identifier, TokenKind::Minus]);
+    }
+
+    #[test]
+    fn real_round_trip() {
+        let reals = [0.1 + 0.2, 1. / 3., 2.5, 1e21, 1.5e-7, 5e-324, f64::MAX];
+
+        for real in reals {
+            let written = format_real(real);
+            let mut lexer = Lexer::new();
+            let tokens = lexer.tokenize(&written).unwrap();
+
+            assert_eq!(tokens.len(), 2, "{written}");
+            assert_eq!(parse_real(&tokens[0].value).map(f64::to_bits), Some(real.to_bits()));
+        }
+    }
+
+    #[test]
+    fn tokenize_imaginary() {
+        let code: String = "4i 2.5i 3.i 4in".into();
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(&code).unwrap();
+
+        let tk_type: Vec<TokenKind> = tokens.iter().map(|tk| tk.kind.clone()).collect();
+        let tk_value: Vec<EcoString> = tokens.iter().map(|tk| tk.value.clone()).collect();
+
+        assert_eq!(tk_type[..3], vec![TokenKind::Imaginary; 3]);
+        assert_eq!(tk_type[3..5], vec![TokenKind::Int, TokenKind::In]);
+        assert_eq!(tk_value[..3], vec!["4", "2.5", "3."]);
+        assert_eq!(tokens[0].loc, Loc::new(0, 2));
+    }
+
+    #[test]
+    fn tokenize_doc_comment() {
+        let code: String = "/// Doc\nvar a = 1 /// not doc\n// plain".into();
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(&code).unwrap();
+
+        let tk_type: Vec<TokenKind> = tokens.iter().map(|tk| tk.kind.clone()).collect();
+
+        assert_eq!(tk_type[..2], [TokenKind::DocComment, TokenKind::NewLine]);
+        assert_eq!(tokens[0].value, "Doc");
+        assert_eq!(tk_type.iter().filter(|k| **k == TokenKind::DocComment).count(), 1);
+    }
+
     #[test]
     fn tokenize_range() {
         let code: String = "2..5".into();
@@ -566,6 +984,19 @@ mod tests {
             tokens.err().unwrap()[0].err,
             LexerErr::NonNumericDecimal(..)
         ));
+
+        // Reported on the literal, the rest is still lexed
+        let code: String = "a = 9223372036854775808 + 1e309 + 2e-400i".into();
+        let errs = Lexer::new().tokenize(&code).err().unwrap();
+
+        assert_eq!(errs[0].err, LexerErr::IntTooLarge("9223372036854775808".into()));
+        assert_eq!(errs[0].loc, Some(Loc::new(4, 23)));
+        assert_eq!(errs[1].err, LexerErr::RealOutOfRange("1e309".into()));
+        assert_eq!(errs[2].err, LexerErr::RealOutOfRange("2e-400".into()));
+        assert_eq!(errs[2].loc, Some(Loc::new(34, 41)));
+
+        let code: String = "9223372036854775807 0e-400 5e-324 -9223372036854775808".into();
+        assert!(Lexer::new().tokenize(&code).is_ok());
     }
 
     #[test]
@@ -613,4 +1044,23 @@ break 45+7".into();
             ]
         );
     }
+
+    #[test]
+    fn location_offset() {
+        let code: String = "var a\n".into();
+        let mut lexer = Lexer::with_offset(10);
+        let tokens = lexer.tokenize(&code).unwrap();
+
+        let tk_loc: Vec<&Loc> = tokens.iter().map(|tk| &tk.loc).collect();
+
+        assert_eq!(
+            tk_loc,
+            vec![
+                &Loc::new(10, 13),
+                &Loc::new(14, 15),
+                &Loc::new(15, 16),
+                &Loc::new(16, 17),
+            ]
+        );
+    }
 }
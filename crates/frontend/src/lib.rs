@@ -1,5 +1,12 @@
 pub mod lexer;
 pub mod parser;
 pub mod ast;
+pub mod cfg;
+pub mod inline;
+pub mod optimize;
+pub mod highlight;
+pub mod include;
+pub mod macros;
+pub mod strict;
 
 extern crate tools;
@@ -0,0 +1,330 @@
+use std::{collections::HashMap, rc::Rc};
+
+use ecow::EcoString;
+use tools::results::Loc;
+
+use crate::ast::{
+    expr::{
+        AssignExpr, AwaitExpr, BinaryExpr, CallExpr, ComprehensionExpr, Expr, GetExpr, GroupingExpr,
+        IdentifierExpr, IndexExpr, ListExpr, LogicalExpr, SetExpr, SpawnExpr, UnaryExpr,
+    },
+    node_id::NodeId,
+    stmt::{BlockStmt, ExprStmt, FnDeclStmt, ForStmt, IfStmt, ReturnStmt, Stmt, VarDeclStmt, WhileStmt},
+};
+use crate::parser::ParserErr;
+
+// Code template declared with 'macro name(params) { body }' and expanded
+// where 'name!(args)' is used, with the trailing block of the invocation
+// given to the last parameter:
+//   macro times(n, body) { for i in 0..n { body } }
+//   times!(3) { print("hello") }
+// Each use of an expression parameter is replaced by the argument, which
+// is evaluated each time. The names declared in the template get a suffix
+// that can't be written in a script, so they never capture or shadow the
+// user's variables (hygiene)
+#[derive(Debug, Clone)]
+pub struct MacroDef {
+    pub name: EcoString,
+    pub params: Vec<EcoString>,
+    pub body: Vec<Stmt>,
+    pub loc: Loc,
+}
+
+impl MacroDef {
+    // Expansion number 'id' makes the renamed declarations unique. Each
+    // parameter must get an argument, the block counting as the last one
+    pub fn expand(
+        &self,
+        args: Vec<Expr>,
+        block: Option<Vec<Stmt>>,
+        id: usize,
+        loc: Loc,
+    ) -> Result<Stmt, ParserErr> {
+        let found = args.len() + block.is_some() as usize;
+        if found != self.params.len() {
+            return Err(ParserErr::MacroArity(self.name.to_string(), self.params.len(), found));
+        }
+
+        let mut expander = Expander::default();
+        expander.args.extend(self.params.iter().cloned().zip(args));
+
+        if let (Some(param), Some(block)) = (self.params.last(), block) {
+            expander.block = Some((param.clone(), block));
+        }
+
+        let mut declared = vec![];
+        self.body.iter().for_each(|s| declared_names(s, &mut declared));
+        for name in declared {
+            let renamed = format!("{}#{}", name, id);
+            expander.renames.insert(name, renamed.into());
+        }
+
+        Ok(Stmt::Block(BlockStmt {
+            id: NodeId::default(),
+            stmts: self.body.iter().map(|s| expander.stmt(s)).collect(),
+            loc,
+        }))
+    }
+}
+
+// Names declared by the template itself, at any depth
+fn declared_names(stmt: &Stmt, names: &mut Vec<EcoString>) {
+    match stmt {
+        Stmt::VarDecl(s) => names.push(s.name.clone()),
+        Stmt::Block(s) => s.stmts.iter().for_each(|s| declared_names(s, names)),
+        Stmt::If(s) => {
+            for b in [&s.then_branch, &s.else_branch].into_iter().flatten() {
+                declared_names(b, names);
+            }
+        }
+        Stmt::While(s) => declared_names(&s.body, names),
+        Stmt::For(s) => {
+            names.extend(s.placeholders().map(|p| p.name.clone()));
+            declared_names(&s.body, names);
+        }
+        Stmt::FnDecl(s) => {
+            names.push(s.name.clone());
+            names.extend(s.params.iter().cloned());
+            s.body.iter().for_each(|s| declared_names(s, names));
+        }
+        Stmt::Expr(_) | Stmt::Return(_) | Stmt::Import(_) => {}
+    }
+}
+
+#[derive(Default)]
+struct Expander {
+    args: HashMap<EcoString, Expr>,
+    block: Option<(EcoString, Vec<Stmt>)>,
+    renames: HashMap<EcoString, EcoString>,
+}
+
+impl Expander {
+    fn name(&self, name: &EcoString) -> EcoString {
+        self.renames.get(name).cloned().unwrap_or_else(|| name.clone())
+    }
+
+    fn boxed(&self, stmt: &Stmt) -> Box<Stmt> {
+        Box::new(self.stmt(stmt))
+    }
+
+    fn stmt(&self, stmt: &Stmt) -> Stmt {
+        match stmt {
+            // The block parameter used as a statement
+            Stmt::Expr(ExprStmt { expr: Expr::Identifier(i), loc, .. })
+                if self.block.as_ref().is_some_and(|(p, _)| *p == i.name) =>
+            {
+                Stmt::Block(BlockStmt {
+                    id: NodeId::default(),
+                    stmts: self.block.as_ref().unwrap().1.clone(),
+                    loc: loc.clone(),
+                })
+            }
+            Stmt::Expr(s) => Stmt::Expr(ExprStmt {
+                id: NodeId::default(),
+                expr: self.expr(&s.expr),
+                loc: s.loc.clone(),
+            }),
+            Stmt::VarDecl(s) => Stmt::VarDecl(self.var_decl(s)),
+            Stmt::Block(s) => Stmt::Block(BlockStmt {
+                id: NodeId::default(),
+                stmts: s.stmts.iter().map(|s| self.stmt(s)).collect(),
+                loc: s.loc.clone(),
+            }),
+            Stmt::If(s) => Stmt::If(IfStmt {
+                id: NodeId::default(),
+                condition: self.expr(&s.condition),
+                then_branch: s.then_branch.as_ref().map(|b| self.boxed(b)),
+                else_branch: s.else_branch.as_ref().map(|b| self.boxed(b)),
+                loc: s.loc.clone(),
+            }),
+            Stmt::While(s) => Stmt::While(WhileStmt {
+                id: NodeId::default(),
+                condition: self.expr(&s.condition),
+                body: self.boxed(&s.body),
+                loc: s.loc.clone(),
+            }),
+            Stmt::For(s) => Stmt::For(ForStmt {
+                id: NodeId::default(),
+                placeholder: self.var_decl(&s.placeholder),
+                rest: s.rest.iter().map(|p| self.var_decl(p)).collect(),
+                range: s.range.clone().map(|e| self.expr(&e)),
+                body: self.boxed(&s.body),
+                loc: s.loc.clone(),
+            }),
+            Stmt::FnDecl(s) => Stmt::FnDecl(FnDeclStmt {
+                name: self.name(&s.name),
+                params: Rc::new(s.params.iter().map(|p| self.name(p)).collect()),
+                requires: Rc::new(s.requires.iter().map(|e| self.expr(e)).collect()),
+                ensures: Rc::new(s.ensures.iter().map(|e| self.expr(e)).collect()),
+                body: Rc::new(s.body.iter().map(|s| self.stmt(s)).collect()),
+                ..s.clone()
+            }),
+            Stmt::Return(s) => Stmt::Return(ReturnStmt {
+                id: NodeId::default(),
+                value: s.value.as_ref().map(|v| self.expr(v)),
+                loc: s.loc.clone(),
+            }),
+            Stmt::Import(s) => Stmt::Import(s.clone()),
+        }
+    }
+
+    fn var_decl(&self, s: &VarDeclStmt) -> VarDeclStmt {
+        VarDeclStmt {
+            name: self.name(&s.name),
+            value: s.value.as_ref().map(|v| self.expr(v)),
+            ..s.clone()
+        }
+    }
+
+    fn expr(&self, expr: &Expr) -> Expr {
+        let boxed = |e: &Expr| Box::new(self.expr(e));
+
+        match expr {
+            // Grouped to keep the precedence of the argument
+            Expr::Identifier(i) => match self.args.get(&i.name) {
+                Some(arg) => Expr::Grouping(GroupingExpr {
+                    id: NodeId::default(),
+                    expr: Box::new(arg.clone()),
+                    loc: i.loc.clone(),
+                }),
+                None => Expr::Identifier(IdentifierExpr {
+                    id: NodeId::default(),
+                    name: self.name(&i.name),
+                    loc: i.loc.clone(),
+                }),
+            },
+            Expr::Binary(e) => Expr::Binary(BinaryExpr {
+                left: boxed(&e.left),
+                right: boxed(&e.right),
+                ..e.clone()
+            }),
+            Expr::Grouping(e) => Expr::Grouping(GroupingExpr {
+                id: NodeId::default(),
+                expr: boxed(&e.expr),
+                loc: e.loc.clone(),
+            }),
+            Expr::Unary(e) => Expr::Unary(UnaryExpr {
+                right: boxed(&e.right),
+                ..e.clone()
+            }),
+            // Assigning a parameter assigns the variable given as argument
+            Expr::Assign(e) => Expr::Assign(AssignExpr {
+                id: NodeId::default(),
+                name: match self.args.get(&e.name) {
+                    Some(Expr::Identifier(arg)) => arg.name.clone(),
+                    _ => self.name(&e.name),
+                },
+                value: boxed(&e.value),
+                name_loc: e.name_loc.clone(),
+                loc: e.loc.clone(),
+            }),
+            Expr::Logical(e) => Expr::Logical(LogicalExpr {
+                left: boxed(&e.left),
+                right: boxed(&e.right),
+                ..e.clone()
+            }),
+            Expr::Call(e) => Expr::Call(CallExpr {
+                id: NodeId::default(),
+                callee: boxed(&e.callee),
+                args: e.args.iter().map(|a| self.expr(a)).collect(),
+                loc: e.loc.clone(),
+            }),
+            Expr::Get(e) => Expr::Get(GetExpr {
+                object: boxed(&e.object),
+                ..e.clone()
+            }),
+            Expr::Set(e) => Expr::Set(SetExpr {
+                object: boxed(&e.object),
+                value: boxed(&e.value),
+                ..e.clone()
+            }),
+            Expr::Index(e) => Expr::Index(IndexExpr {
+                object: boxed(&e.object),
+                start: e.start.as_deref().map(boxed),
+                end: e.end.as_deref().map(boxed),
+                ..e.clone()
+            }),
+            Expr::List(e) => Expr::List(ListExpr {
+                id: NodeId::default(),
+                items: e.items.iter().map(|i| self.expr(i)).collect(),
+                loc: e.loc.clone(),
+            }),
+            Expr::Comprehension(e) => Expr::Comprehension(ComprehensionExpr {
+                value: boxed(&e.value),
+                name: self.name(&e.name),
+                source: boxed(&e.source),
+                end: e.end.as_deref().map(boxed),
+                cond: e.cond.as_deref().map(boxed),
+                ..e.clone()
+            }),
+            Expr::Spawn(e) => Expr::Spawn(SpawnExpr {
+                id: NodeId::default(),
+                call: boxed(&e.call),
+                loc: e.loc.clone(),
+            }),
+            Expr::Await(e) => Expr::Await(AwaitExpr {
+                id: NodeId::default(),
+                task: boxed(&e.task),
+                loc: e.loc.clone(),
+            }),
+            literal => literal.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tools::results::Loc;
+
+    use crate::parser::{utils::*, ParserErr};
+
+    #[test]
+    fn expansion() {
+        let code = "
+macro times(n, body) {
+    for i in 0..n { body }
+}
+macro count(n, body) {
+    var i = 0
+    while i < n {
+        body
+        i = i + 1
+    }
+}
+macro swap(a, b) {
+    var tmp = a
+    a = b
+    b = tmp
+}
+times!(1 + 2) { print(i) }
+count!(2) { print(i) }
+swap!(x, y)
+";
+        assert_eq!(
+            print_sexpr(code),
+            [
+                "(block (for i#1 in .. 0 (group (+ 1 2)) (block (block (call print i)))))",
+                "(block (var i#2 0) (while (< i#2 (group 2)) (block (block (call print i)) (assign i#2 (+ i#2 1)))))",
+                "(block (var tmp#3 (group x)) (assign x (group y)) (assign y tmp#3))",
+            ]
+        );
+
+        // Errors
+        let code = "
+macro m(a) { print(a) }
+other!(1)
+m!(1, 2)
+m!(1) { print(2) }
+{
+    macro n() {}
+}
+";
+        let errs = lex_and_parse(code).err().unwrap();
+        let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
+        assert_eq!(e[0], &ParserErr::UnknownMacro("other".into()));
+        assert_eq!(e[1], &ParserErr::MacroArity("m".into(), 1, 2));
+        assert_eq!(errs[1].loc, Some(Loc::new(35, 43)));
+        assert_eq!(e[2], &ParserErr::MacroArity("m".into(), 1, 2));
+        assert_eq!(e[3], &ParserErr::MacroNotTopLevel);
+    }
+}
@@ -0,0 +1,704 @@
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use ecow::EcoString;
+use tools::results::Loc;
+
+use crate::ast::{
+    expr::{AssignExpr, BinaryExpr, Expr, GroupingExpr, IdentifierExpr, IndexExpr, ListExpr, LogicalExpr, UnaryExpr},
+    node_id::NodeId,
+    stmt::{
+        BlockStmt, ExprStmt, FnDeclStmt, ForRange, ForStmt, IfStmt, ReturnStmt, Stmt, VarDeclStmt,
+        WhileStmt,
+    },
+};
+
+// Passes of '--opt-level 2', over the pure expressions: operators on
+// literals and names. Their values are stored in variables whose names
+// start with '$', that scripts can't write. An error raised by a moved
+// expression is raised before the ones of the statements around it
+
+// The pure expressions of a loop over names it doesn't assign are computed
+// once before it. Only the ones evaluated at each iteration are moved, and
+// only when the loop runs at least once. The loops calling functions are
+// left as is, like the whole script if something else can run while they
+// do: tasks, watches, evaluated code or imported modules
+pub fn hoist_invariants(stmts: Vec<Stmt>) -> Vec<Stmt> {
+    if stmts.iter().any(|s| stmt_any(s, &mut may_run_concurrently)) {
+        return stmts;
+    }
+
+    let mut next = 0;
+    rewrite_lists(stmts, &mut |list| {
+        list.into_iter().map(|s| hoist_loop(s, &mut next)).collect()
+    })
+}
+
+// The pure expressions computed more than once by a statement are computed
+// once before it. Statements calling functions or assigning inside an
+// expression are left as is
+pub fn eliminate_common_subexprs(stmts: Vec<Stmt>) -> Vec<Stmt> {
+    let mut next = 0;
+    rewrite_lists(stmts, &mut |list| {
+        list.into_iter().flat_map(|s| reuse_subexprs(s, &mut next)).collect()
+    })
+}
+
+// --------------
+//  Hoisting
+// --------------
+fn hoist_loop(stmt: Stmt, next: &mut usize) -> Stmt {
+    match stmt {
+        Stmt::While(w) => {
+            if stmt_any(&w.body, &mut has_call) || expr_any(&w.condition, &mut has_call) {
+                return Stmt::While(w);
+            }
+
+            let mut assigned = HashSet::new();
+            assigned_names(&w.body, &mut assigned);
+            expr_any(&w.condition, &mut |e| {
+                if let Expr::Assign(a) = e {
+                    assigned.insert(a.name.clone());
+                }
+                false
+            });
+
+            let mut in_cond = vec![];
+            invariant_parts(&w.condition, &assigned, &mut in_cond);
+            let mut in_body = vec![];
+            each_iteration_exprs(&w.body).for_each(|e| invariant_parts(e, &assigned, &mut in_body));
+
+            if in_cond.is_empty() && in_body.is_empty() {
+                return Stmt::While(w);
+            }
+
+            let mut temps = Temps::new("inv", next);
+            let cond_decls = temps.declare(in_cond);
+            // Before the ones of the body, not declared yet where it is
+            let guard = temps.replace(w.condition.clone());
+            let body_decls = temps.declare(in_body);
+
+            let loc = w.loc;
+            let looped = Stmt::While(WhileStmt {
+                id: w.id,
+                condition: temps.replace(w.condition),
+                body: Box::new(temps.replace_in_stmt(*w.body)),
+                loc: loc.clone(),
+            });
+
+            // The values used by the body are only computed if it runs
+            let guarded = match body_decls.is_empty() {
+                true => looped,
+                false => Stmt::If(IfStmt {
+                    id: NodeId::default(),
+                    condition: guard,
+                    then_branch: Some(Box::new(block(body_decls, looped, &loc))),
+                    else_branch: None,
+                    loc: loc.clone(),
+                }),
+            };
+
+            block(cond_decls, guarded, &loc)
+        }
+        Stmt::For(f) => {
+            // Only the literal ranges are known to run
+            let iterations = match f.range {
+                ForRange::Ints(start, Some(end)) => end - start,
+                ForRange::Ints(end, None) => end,
+                _ => 0,
+            };
+
+            if iterations <= 0 || stmt_any(&f.body, &mut has_call) {
+                return Stmt::For(f);
+            }
+
+            let mut assigned = f.placeholders().map(|p| p.name.clone()).collect::<HashSet<_>>();
+            assigned_names(&f.body, &mut assigned);
+
+            let mut in_body = vec![];
+            each_iteration_exprs(&f.body).for_each(|e| invariant_parts(e, &assigned, &mut in_body));
+
+            if in_body.is_empty() {
+                return Stmt::For(f);
+            }
+
+            let mut temps = Temps::new("inv", next);
+            let decls = temps.declare(in_body);
+            let body = Box::new(temps.replace_in_stmt(*f.body));
+            let loc = f.loc.clone();
+
+            block(decls, Stmt::For(ForStmt { body, ..f }), &loc)
+        }
+        s => s,
+    }
+}
+
+// Expressions of the statements of the body that run at each iteration
+fn each_iteration_exprs(body: &Stmt) -> impl Iterator<Item = &Expr> {
+    let stmts = match body {
+        Stmt::Block(b) => b.stmts.as_slice(),
+        s => std::slice::from_ref(s),
+    };
+
+    stmts.iter().filter_map(|s| match s {
+        Stmt::Expr(ExprStmt { expr: Expr::Assign(a), .. }) => Some(a.value.as_ref()),
+        Stmt::Expr(s) => Some(&s.expr),
+        Stmt::VarDecl(v) => v.value.as_ref(),
+        _ => None,
+    })
+}
+
+// Largest pure parts not reading the assigned names, in the parts of the
+// expression always evaluated
+fn invariant_parts<'a>(expr: &'a Expr, assigned: &HashSet<EcoString>, found: &mut Vec<&'a Expr>) {
+    if size(expr) > 1 && is_pure(expr) && !expr_any(expr, &mut |e| reads(e, assigned)) {
+        found.push(expr);
+        return;
+    }
+
+    match expr {
+        Expr::Binary(b) => {
+            invariant_parts(&b.left, assigned, found);
+            invariant_parts(&b.right, assigned, found);
+        }
+        Expr::Grouping(g) => invariant_parts(&g.expr, assigned, found),
+        Expr::Unary(u) => invariant_parts(&u.right, assigned, found),
+        // The right side isn't always evaluated
+        Expr::Logical(l) => invariant_parts(&l.left, assigned, found),
+        Expr::Assign(a) => invariant_parts(&a.value, assigned, found),
+        Expr::Get(g) => invariant_parts(&g.object, assigned, found),
+        Expr::Index(i) => {
+            invariant_parts(&i.object, assigned, found);
+            i.bounds().for_each(|b| invariant_parts(b, assigned, found));
+        }
+        Expr::List(l) => l.items.iter().for_each(|i| invariant_parts(i, assigned, found)),
+        Expr::Set(s) => {
+            invariant_parts(&s.object, assigned, found);
+            invariant_parts(&s.value, assigned, found);
+        }
+        _ => {}
+    }
+}
+
+fn reads(expr: &Expr, names: &HashSet<EcoString>) -> bool {
+    matches!(expr, Expr::Identifier(i) if names.contains(&i.name))
+}
+
+// Names declared or assigned anywhere in the statement
+fn assigned_names(stmt: &Stmt, names: &mut HashSet<EcoString>) {
+    stmt_any(stmt, &mut |e| {
+        if let Expr::Assign(a) = e {
+            names.insert(a.name.clone());
+        }
+        false
+    });
+
+    match stmt {
+        Stmt::VarDecl(v) => {
+            names.insert(v.name.clone());
+        }
+        Stmt::FnDecl(f) => {
+            names.insert(f.name.clone());
+        }
+        Stmt::For(f) => {
+            names.extend(f.placeholders().map(|p| p.name.clone()));
+            assigned_names(&f.body, names);
+        }
+        Stmt::Block(b) => b.stmts.iter().for_each(|s| assigned_names(s, names)),
+        Stmt::If(i) => i.then_branch.iter().chain(&i.else_branch).for_each(|s| assigned_names(s, names)),
+        Stmt::While(w) => assigned_names(&w.body, names),
+        Stmt::Import(i) => {
+            names.insert(i.name().clone());
+        }
+        _ => {}
+    }
+}
+
+fn has_call(expr: &Expr) -> bool {
+    matches!(expr, Expr::Call(_) | Expr::Spawn(_) | Expr::Await(_))
+}
+
+fn may_run_concurrently(expr: &Expr) -> bool {
+    match expr {
+        Expr::Spawn(_) => true,
+        Expr::Identifier(i) => i.name == "watch" || i.name == "eval",
+        _ => false,
+    }
+}
+
+// --------------------------------
+//  Common subexpression elimination
+// --------------------------------
+fn reuse_subexprs(stmt: Stmt, next: &mut usize) -> Vec<Stmt> {
+    let expr = match &stmt {
+        Stmt::Expr(ExprStmt { expr: Expr::Assign(a), .. }) => a.value.as_ref(),
+        Stmt::Expr(s) => &s.expr,
+        Stmt::VarDecl(VarDeclStmt { value: Some(v), .. }) => v,
+        Stmt::Return(ReturnStmt { value: Some(v), .. }) => v,
+        _ => return vec![stmt],
+    };
+
+    if expr_any(expr, &mut |e| has_call(e) || matches!(e, Expr::Assign(_) | Expr::Set(_))) {
+        return vec![stmt];
+    }
+
+    let mut temps = Temps::new("cse", next);
+    let mut stmt = stmt;
+    let mut decls = vec![];
+
+    // The largest repeated part first, the smaller ones it contains are
+    // computed once with it
+    loop {
+        let mut counts: HashMap<String, (usize, bool)> = HashMap::new();
+        let mut parts = vec![];
+        stmt_exprs(&stmt).for_each(|e| pure_parts(e, true, &mut parts));
+
+        for (part, always) in &parts {
+            let count = counts.entry(key(part)).or_default();
+            count.0 += 1;
+            count.1 |= *always;
+        }
+
+        let repeated = parts
+            .iter()
+            .filter(|(p, _)| size(p) >= 3 && counts[&key(p)].0 >= 2 && counts[&key(p)].1)
+            .max_by_key(|(p, _)| size(p));
+
+        let Some((part, _)) = repeated else {
+            break;
+        };
+
+        decls.extend(temps.declare(vec![part]));
+        stmt = temps.replace_in_stmt(stmt);
+    }
+
+    decls.push(stmt);
+    decls
+}
+
+fn stmt_exprs(stmt: &Stmt) -> impl Iterator<Item = &Expr> {
+    match stmt {
+        Stmt::Expr(s) => Some(&s.expr),
+        Stmt::VarDecl(v) => v.value.as_ref(),
+        Stmt::Return(r) => r.value.as_ref(),
+        _ => None,
+    }
+    .into_iter()
+}
+
+// Every pure part of the expression, with whether it is always evaluated
+fn pure_parts<'a>(expr: &'a Expr, always: bool, found: &mut Vec<(&'a Expr, bool)>) {
+    if is_pure(expr) && !matches!(expr, Expr::Grouping(_)) {
+        found.push((expr, always));
+    }
+
+    match expr {
+        Expr::Binary(b) => {
+            pure_parts(&b.left, always, found);
+            pure_parts(&b.right, always, found);
+        }
+        Expr::Logical(l) => {
+            pure_parts(&l.left, always, found);
+            pure_parts(&l.right, false, found);
+        }
+        Expr::Grouping(g) => pure_parts(&g.expr, always, found),
+        Expr::Unary(u) => pure_parts(&u.right, always, found),
+        Expr::Assign(a) => pure_parts(&a.value, always, found),
+        Expr::Get(g) => pure_parts(&g.object, always, found),
+        Expr::Index(i) => {
+            pure_parts(&i.object, always, found);
+            i.bounds().for_each(|b| pure_parts(b, always, found));
+        }
+        Expr::List(l) => l.items.iter().for_each(|i| pure_parts(i, always, found)),
+        _ => {}
+    }
+}
+
+// --------------
+//  Temporaries
+// --------------
+
+// Variables holding the moved expressions, replacing them in the code
+struct Temps<'a> {
+    prefix: &'static str,
+    next: &'a mut usize,
+    // Name of the variable holding each expression, by key
+    names: HashMap<String, EcoString>,
+}
+
+impl<'a> Temps<'a> {
+    fn new(prefix: &'static str, next: &'a mut usize) -> Self {
+        Self {
+            prefix,
+            next,
+            names: HashMap::new(),
+        }
+    }
+
+    // A declaration for each expression not declared yet
+    fn declare(&mut self, exprs: Vec<&Expr>) -> Vec<Stmt> {
+        let mut decls = vec![];
+
+        for expr in exprs {
+            let key = key(expr);
+            if self.names.contains_key(&key) {
+                continue;
+            }
+
+            // Computed from the ones declared before
+            let value = self.replace(expr.clone());
+            let name = EcoString::from(format!("${}{}", self.prefix, self.next));
+            *self.next += 1;
+            self.names.insert(key, name.clone());
+
+            let loc = expr.get_loc();
+            decls.push(Stmt::VarDecl(VarDeclStmt {
+                id: NodeId::default(),
+                name,
+                value: Some(value),
+                ty: None,
+                doc: None,
+                is_pub: false,
+                name_loc: loc.clone(),
+                loc,
+            }));
+        }
+
+        decls
+    }
+
+    fn replace(&self, expr: Expr) -> Expr {
+        if is_pure(&expr) {
+            if let Some(name) = self.names.get(&key(&expr)) {
+                return Expr::Identifier(IdentifierExpr {
+                    id: expr.get_id(),
+                    name: name.clone(),
+                    loc: expr.get_loc(),
+                });
+            }
+        }
+
+        map_children(expr, &mut |e| self.replace(e))
+    }
+
+    // Functions declared in the statement keep their own body
+    fn replace_in_stmt(&self, stmt: Stmt) -> Stmt {
+        match stmt {
+            Stmt::Expr(s) => Stmt::Expr(ExprStmt {
+                expr: self.replace(s.expr),
+                ..s
+            }),
+            Stmt::VarDecl(s) => Stmt::VarDecl(VarDeclStmt {
+                value: s.value.map(|v| self.replace(v)),
+                ..s
+            }),
+            Stmt::Return(s) => Stmt::Return(ReturnStmt {
+                id: s.id,
+                value: s.value.map(|v| self.replace(v)),
+                loc: s.loc,
+            }),
+            Stmt::Block(s) => Stmt::Block(BlockStmt {
+                id: s.id,
+                stmts: s.stmts.into_iter().map(|s| self.replace_in_stmt(s)).collect(),
+                loc: s.loc,
+            }),
+            Stmt::If(s) => Stmt::If(IfStmt {
+                id: s.id,
+                condition: self.replace(s.condition),
+                then_branch: s.then_branch.map(|b| Box::new(self.replace_in_stmt(*b))),
+                else_branch: s.else_branch.map(|b| Box::new(self.replace_in_stmt(*b))),
+                loc: s.loc,
+            }),
+            Stmt::While(s) => Stmt::While(WhileStmt {
+                id: s.id,
+                condition: self.replace(s.condition),
+                body: Box::new(self.replace_in_stmt(*s.body)),
+                loc: s.loc,
+            }),
+            Stmt::For(s) => Stmt::For(ForStmt {
+                body: Box::new(self.replace_in_stmt(*s.body)),
+                ..s
+            }),
+            s => s,
+        }
+    }
+}
+
+fn block(mut stmts: Vec<Stmt>, last: Stmt, loc: &Loc) -> Stmt {
+    if stmts.is_empty() {
+        return last;
+    }
+
+    stmts.push(last);
+    Stmt::Block(BlockStmt {
+        id: NodeId::default(),
+        stmts,
+        loc: loc.clone(),
+    })
+}
+
+// --------------
+//  Expressions
+// --------------
+fn is_pure(expr: &Expr) -> bool {
+    match expr {
+        Expr::Binary(b) => is_pure(&b.left) && is_pure(&b.right),
+        Expr::Logical(l) => is_pure(&l.left) && is_pure(&l.right),
+        Expr::Grouping(g) => is_pure(&g.expr),
+        Expr::Unary(u) => is_pure(&u.right),
+        Expr::IntLiteral(_)
+        | Expr::RealLiteral(_)
+        | Expr::ImaginaryLiteral(_)
+        | Expr::StrLiteral(_)
+        | Expr::CharLiteral(_)
+        | Expr::UnitLiteral(_)
+        | Expr::Identifier(_) => true,
+        _ => false,
+    }
+}
+
+// Number of nodes of a pure expression
+fn size(expr: &Expr) -> usize {
+    match expr {
+        Expr::Binary(b) => 1 + size(&b.left) + size(&b.right),
+        Expr::Logical(l) => 1 + size(&l.left) + size(&l.right),
+        Expr::Grouping(g) => size(&g.expr),
+        Expr::Unary(u) => 1 + size(&u.right),
+        _ => 1,
+    }
+}
+
+// Same for the expressions computing the same value, whatever their location
+fn key(expr: &Expr) -> String {
+    match expr {
+        Expr::Binary(b) => format!("({} {} {})", b.operator, key(&b.left), key(&b.right)),
+        Expr::Logical(l) => format!("({} {} {})", l.operator, key(&l.left), key(&l.right)),
+        Expr::Grouping(g) => key(&g.expr),
+        Expr::Unary(u) => format!("({} {})", u.operator, key(&u.right)),
+        Expr::StrLiteral(s) => format!("{:?}", s.value),
+        Expr::RealLiteral(r) => format!("{:?}", r.value),
+        e => e.to_string(),
+    }
+}
+
+fn map_children(expr: Expr, f: &mut impl FnMut(Expr) -> Expr) -> Expr {
+    match expr {
+        Expr::Binary(b) => Expr::Binary(BinaryExpr {
+            left: Box::new(f(*b.left)),
+            right: Box::new(f(*b.right)),
+            ..b
+        }),
+        Expr::Logical(l) => Expr::Logical(LogicalExpr {
+            left: Box::new(f(*l.left)),
+            right: Box::new(f(*l.right)),
+            ..l
+        }),
+        Expr::Grouping(g) => Expr::Grouping(GroupingExpr {
+            id: g.id,
+            expr: Box::new(f(*g.expr)),
+            loc: g.loc,
+        }),
+        Expr::Unary(u) => Expr::Unary(UnaryExpr {
+            right: Box::new(f(*u.right)),
+            ..u
+        }),
+        Expr::Assign(a) => Expr::Assign(AssignExpr {
+            value: Box::new(f(*a.value)),
+            ..a
+        }),
+        Expr::Index(i) => Expr::Index(IndexExpr {
+            object: Box::new(f(*i.object)),
+            start: i.start.map(|s| Box::new(f(*s))),
+            end: i.end.map(|e| Box::new(f(*e))),
+            ..i
+        }),
+        Expr::List(l) => Expr::List(ListExpr {
+            items: l.items.into_iter().map(f).collect(),
+            ..l
+        }),
+        e => e,
+    }
+}
+
+// Whether an expression of the statement, outside of the functions it
+// declares, matches
+fn stmt_any(stmt: &Stmt, pred: &mut impl FnMut(&Expr) -> bool) -> bool {
+    match stmt {
+        Stmt::Expr(s) => expr_any(&s.expr, pred),
+        Stmt::VarDecl(v) => v.value.as_ref().is_some_and(|e| expr_any(e, pred)),
+        Stmt::Block(b) => b.stmts.iter().any(|s| stmt_any(s, pred)),
+        Stmt::If(i) => {
+            expr_any(&i.condition, pred)
+                || i.then_branch.iter().chain(&i.else_branch).any(|s| stmt_any(s, pred))
+        }
+        Stmt::While(w) => expr_any(&w.condition, pred) || stmt_any(&w.body, pred),
+        Stmt::For(f) => f.range.exprs().any(|e| expr_any(e, pred)) || stmt_any(&f.body, pred),
+        Stmt::Return(r) => r.value.as_ref().is_some_and(|e| expr_any(e, pred)),
+        Stmt::FnDecl(f) => f.body.iter().any(|s| stmt_any(s, pred)),
+        // Imported modules run their own code
+        Stmt::Import(_) => true,
+    }
+}
+
+fn expr_any(expr: &Expr, pred: &mut impl FnMut(&Expr) -> bool) -> bool {
+    if pred(expr) {
+        return true;
+    }
+
+    match expr {
+        Expr::Binary(b) => expr_any(&b.left, pred) || expr_any(&b.right, pred),
+        Expr::Logical(l) => expr_any(&l.left, pred) || expr_any(&l.right, pred),
+        Expr::Grouping(g) => expr_any(&g.expr, pred),
+        Expr::Unary(u) => expr_any(&u.right, pred),
+        Expr::Assign(a) => expr_any(&a.value, pred),
+        Expr::Call(c) => expr_any(&c.callee, pred) || c.args.iter().any(|a| expr_any(a, pred)),
+        Expr::Get(g) => expr_any(&g.object, pred),
+        Expr::Index(i) => expr_any(&i.object, pred) || i.bounds().any(|b| expr_any(b, pred)),
+        Expr::Set(s) => expr_any(&s.object, pred) || expr_any(&s.value, pred),
+        Expr::List(l) => l.items.iter().any(|i| expr_any(i, pred)),
+        Expr::Comprehension(c) => c.sub_exprs().any(|e| expr_any(e, pred)),
+        Expr::Spawn(s) => expr_any(&s.call, pred),
+        Expr::Await(a) => expr_any(&a.task, pred),
+        _ => false,
+    }
+}
+
+// Applies the rewrite to every list of statements, the nested ones first
+fn rewrite_lists(stmts: Vec<Stmt>, f: &mut impl FnMut(Vec<Stmt>) -> Vec<Stmt>) -> Vec<Stmt> {
+    let stmts = stmts.into_iter().map(|s| rewrite_nested(s, f)).collect();
+    f(stmts)
+}
+
+fn rewrite_nested(stmt: Stmt, f: &mut impl FnMut(Vec<Stmt>) -> Vec<Stmt>) -> Stmt {
+    match stmt {
+        Stmt::Block(s) => Stmt::Block(BlockStmt {
+            id: s.id,
+            stmts: rewrite_lists(s.stmts, f),
+            loc: s.loc,
+        }),
+        Stmt::If(s) => Stmt::If(IfStmt {
+            then_branch: s.then_branch.map(|b| Box::new(rewrite_nested(*b, f))),
+            else_branch: s.else_branch.map(|b| Box::new(rewrite_nested(*b, f))),
+            ..s
+        }),
+        Stmt::While(s) => Stmt::While(WhileStmt {
+            body: Box::new(rewrite_nested(*s.body, f)),
+            ..s
+        }),
+        Stmt::For(s) => Stmt::For(ForStmt {
+            body: Box::new(rewrite_nested(*s.body, f)),
+            ..s
+        }),
+        Stmt::FnDecl(s) => {
+            // The body is only shared once the function value exists
+            let body = match Rc::try_unwrap(s.body) {
+                Ok(body) => Rc::new(rewrite_lists(body, f)),
+                Err(body) => body,
+            };
+
+            Stmt::FnDecl(FnDeclStmt { body, ..s })
+        }
+        s => s,
+    }
+}
+#[cfg(test)]
+mod tests {
+    use crate::{ast::ast_pretty_print::AstPrinter, parser::utils::lex_and_parse};
+
+    use super::{eliminate_common_subexprs, hoist_invariants};
+
+    fn print(code: &str, level: u8) -> Vec<String> {
+        let mut nodes = lex_and_parse(code).unwrap();
+        if level & 1 != 0 {
+            nodes = hoist_invariants(nodes);
+        }
+        if level & 2 != 0 {
+            nodes = eliminate_common_subexprs(nodes);
+        }
+        let mut printer = AstPrinter::default();
+
+        nodes.iter().map(|n| printer.print(n).unwrap()).collect()
+    }
+
+    #[test]
+    fn hoist_while() {
+        let code = "
+while i < n * 2 {
+    var a = -k * x
+    x = x + a * dt
+    if x > 0 { y = k * 2 }
+    i = i + 1
+}
+";
+        assert_eq!(
+            print(code, 1),
+            ["(block (var $inv0 (* n 2)) (if (< i $inv0) (block (var $inv1 (- k)) \
+(while (< i $inv0) (block (var a (* $inv1 x)) (assign x (+ x (* a dt))) (if (> x 0) (assign y (* k 2))) (assign i (+ i 1)))))))"]
+        );
+    }
+
+    #[test]
+    fn hoist_for() {
+        let code = "
+for i in 0..10 { total = total + i * (rate * 2) }
+for i in 3..3 { total = total + rate * 2 }
+";
+        assert_eq!(
+            print(code, 1),
+            [
+                "(block (var $inv0 (group (* rate 2))) (for i in 0..10 (block (assign total (+ total (* i $inv0))))))",
+                "(for i in 3..3 (block (assign total (+ total (* rate 2)))))",
+            ]
+        );
+    }
+
+    #[test]
+    fn not_hoisted() {
+        // Calls can change the names read
+        let code = "
+while i < 10 {
+    print(k * 2)
+    i = i + 1
+}
+";
+        assert_eq!(print(code, 1), ["(while (< i 10) (block (call print (* k 2)) (assign i (+ i 1))))"]);
+
+        // Tasks can run at each iteration
+        let code = "
+spawn f()
+while i < 10 { i = i + k * 2 }
+";
+        assert_eq!(print(code, 1)[1], "(while (< i 10) (block (assign i (+ i (* k 2)))))");
+    }
+
+    #[test]
+    fn common_subexprs() {
+        let code = "
+var d = (x - y) * (x - y) + z * (x - y)
+fn f(a) {
+    return a * 2 > 0 and a * 2 < 10
+}
+r = x + 1 > 0 or (x + 1) * (x + 1) > 2
+print((x - y) * (x - y))
+";
+        assert_eq!(
+            print(code, 2),
+            [
+                "(var $cse1 (- x y))",
+                "(var d (+ (* $cse1 $cse1) (* z $cse1)))",
+                "(fn f(a) (var $cse0 (* a 2)) (return (and (> $cse0 0) (< $cse0 10))))",
+                "(var $cse2 (+ x 1))",
+                "(assign r (or (> $cse2 0) (> (* $cse2 $cse2) 2)))",
+                "(call print (* (group (- x y)) (group (- x y))))",
+            ]
+        );
+
+        // Only repeated in the parts not always evaluated
+        assert_eq!(
+            print("r = a or (x + 1) * (x + 1) > 2", 2),
+            ["(assign r (or a (> (* (group (+ x 1)) (group (+ x 1))) 2)))"]
+        );
+    }
+}
@@ -1,16 +1,24 @@
-use std::rc::Rc;
+use std::{collections::HashMap, rc::Rc};
 
 use colored::*;
 use ecow::EcoString;
 use thiserror::Error;
 
 use crate::ast::expr::{
-    AssignExpr, BinaryExpr, CallExpr, Expr, GroupingExpr, IdentifierExpr, IntLiteralExpr, LogicalExpr, RealLiteralExpr, StrLiteralExpr, UnaryExpr
+    AssignExpr, AwaitExpr, BinaryExpr, CallExpr, CharLiteralExpr, ComprehensionExpr, Expr, GetExpr,
+    GroupingExpr, IdentifierExpr, ImaginaryLiteralExpr, IndexExpr, IntLiteralExpr, ListExpr,
+    LogicalExpr, RealLiteralExpr, SetExpr, SpawnExpr, StrLiteralExpr, UnaryExpr, UnitLiteralExpr,
 };
-use crate::lexer::{Token, TokenKind};
+use crate::lexer::{Token, TokenKind, MIN_INT_MAGNITUDE};
+use crate::macros::MacroDef;
+use crate::ast::types::TypeAnn;
+use crate::ast::node_id::{number_nodes, NodeId};
 use crate::ast::stmt::{
-    BlockStmt, ExprStmt, FnDeclStmt, ForRange, ForStmt, IfStmt, PrintStmt, ReturnStmt, Stmt, VarDeclStmt, WhileStmt
+    Attribute, BlockStmt, ExprStmt, FnDeclStmt, ForRange, ForStmt, IfStmt, ImportStmt, ReturnStmt,
+    Stmt, VarDeclStmt, WhileStmt,
 };
+use tools::messages::{localize, text, Arg, Locale, Translate};
+use tools::real_format::parse_real;
 use tools::results::{PhyReport, PhyResult, Loc};
 
 
@@ -36,12 +44,22 @@ pub enum ParserErr {
     #[error("error parsing int")]
     ParsingInt,
 
+    #[error("int literal {0} is larger than the maximum int {}, only its negation is an int", i64::MAX)]
+    IntTooLarge(String),
+
     #[error("error parsing real")]
     ParsingReal,
 
     #[error("parenthesis group is never closed")]
     ParenNeverClosed,
 
+    // Units
+    #[error("expected unit symbol after '{0}' in unit literal")]
+    MissingUnitSymbol(String),
+
+    #[error("expected int exponent after '^' in unit literal")]
+    WrongUnitExponent,
+
     // Variables
     #[error("missing variable name after 'var' keyword in declaration")]
     VarDeclNoName,
@@ -108,7 +126,7 @@ pub enum ParserErr {
     #[error("missing range in 'for' loop")]
     MissingForRange,
 
-    #[error("only 'ints' are supported as 'for' ranges")]
+    #[error("only ints, lists and iterators are supported as 'for' ranges")]
     NonIntForRange,
 
     #[error("range can only be positive")]
@@ -136,6 +154,37 @@ pub enum ParserErr {
     #[error("missing comma to seperate arguments")]
     MissingArgsComma,
 
+    // Member access
+    #[error("expected member name after '.'")]
+    MissingMemberName,
+
+    // Indexing
+    #[error("expected index or range between '[' and ']'")]
+    MissingIndex,
+
+    #[error("missing ']' after index")]
+    MissingCloseBracket,
+
+    // List
+    #[error("missing comma to separate the items of the list")]
+    MissingListComma,
+
+    #[error("missing ']' at the end of the list")]
+    ListNeverClosed,
+
+    // Comprehension
+    #[error("expected 'for' after the value of a comprehension, or ',' after the first item of a list")]
+    MissingForComprehension,
+
+    #[error("missing variable name in comprehension")]
+    MissingVarNameComprehension,
+
+    #[error("missing 'in' after variable name in comprehension")]
+    MissingInComprehension,
+
+    #[error("missing ']' at the end of the comprehension")]
+    ComprehensionNeverClosed,
+
     // Function declaration
     #[error("missing function name after 'fn' keyword")]
     MissingFnName,
@@ -152,7 +201,96 @@ pub enum ParserErr {
     #[error("missing '{{' before function body")]
     MissingFnOpenBrace,
 
+    // Types
+    #[error("expected a type after '{0}'")]
+    MissingType(String),
+
+    #[error("expected parameter types between parenthesis in function type")]
+    MissingFnTypeParen,
+
+    #[error("missing ']' after list element type")]
+    MissingListTypeBracket,
+
+    #[error("expected type parameter names between '<' and '>'")]
+    WrongTypeParams,
+
+    // Contracts
+    #[error("expected a condition after 'requires' or 'ensures'")]
+    MissingContractCond,
+
+    // Tasks
+    #[error("'spawn' must be followed by a function call")]
+    SpawnWithoutCall,
+
+    // Attributes
+    #[error("expected attribute name after '@'")]
+    MissingAttributeName,
+
+    #[error("attributes must be followed by a function declaration")]
+    AttributeWithoutFn,
+
+    #[error("file attributes, like '@!allow(...)', must be at the top level")]
+    FileAttributeNotTopLevel,
+
+    // Macros
+    #[error("macros can only be declared at the top level")]
+    MacroNotTopLevel,
+
+    #[error("unknown macro '{0}'")]
+    UnknownMacro(String),
+
+    #[error("missing '(' after macro name")]
+    MissingMacroOpenParen,
+
+    #[error("macro '{0}' expects {1} arguments, found {2}")]
+    MacroArity(String, usize, usize),
+
+    // Operators
+    #[error("operators can only be declared at the top level")]
+    OperatorNotTopLevel,
+
+    #[error("expected operator symbol after 'operator', like '<+>'")]
+    MissingOperatorSymbol,
+
+    #[error("operator precedence must be an integer from 3 to 7, found '{0}'")]
+    WrongPrecedence(String),
+
+    #[error("operator '{0}' must take 2 parameters, found {1}")]
+    OperatorArity(String, usize),
+
+    #[error("unknown operator '{0}', operators must be declared before their use")]
+    UnknownOperator(String),
+
+    // Actors
+    #[error("missing actor name after 'actor' keyword")]
+    MissingActorName,
+
+    #[error("actor '{0}' has no 'on msg {{ ... }}' handler")]
+    MissingActorHandler(String),
+
+    #[error("an actor can only have one 'on' handler")]
+    DuplicateActorHandler,
+
+    #[error("missing '{{' before the body of the 'on' handler")]
+    MissingHandlerOpenBrace,
+
+    // Modules
+    #[error("expected module name after 'import'")]
+    MissingImportPath,
+
+    #[error("modules can only be imported at the top level")]
+    ImportNotTopLevel,
+
+    #[error("'pub' must be followed by a variable or function declaration")]
+    PubWithoutDecl,
+
+    #[error("only top level declarations can be 'pub'")]
+    PubNotTopLevel,
+
     // Others
+    #[error("expected identifier, found keyword '{0}'")]
+    KeywordAsIdentifier(String),
+
     #[error("unexpected end of file")]
     UnexpectedEof,
 
@@ -161,8 +299,123 @@ pub enum ParserErr {
 }
 
 impl PhyReport for ParserErr {
-    fn get_err_msg(&self) -> String {
-        format!("{} {}", "Parser error:".red(), self)
+    fn get_err_msg(&self, locale: &dyn Locale) -> String {
+        format!(
+            "{} {}",
+            text(locale, "label.parser", "Parser error:").red(),
+            localize(locale, self)
+        )
+    }
+}
+
+impl Translate for ParserErr {
+    fn category(&self) -> &'static str {
+        "parser"
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            ParserErr::UnexpectedEol => "UnexpectedEol",
+            ParserErr::MissingLhsInBinop => "MissingLhsInBinop",
+            ParserErr::UnexpectedToken(..) => "UnexpectedToken",
+            ParserErr::ParsingInt => "ParsingInt",
+            ParserErr::IntTooLarge(..) => "IntTooLarge",
+            ParserErr::ParsingReal => "ParsingReal",
+            ParserErr::ParenNeverClosed => "ParenNeverClosed",
+            ParserErr::MissingUnitSymbol(..) => "MissingUnitSymbol",
+            ParserErr::WrongUnitExponent => "WrongUnitExponent",
+            ParserErr::VarDeclNoName => "VarDeclNoName",
+            ParserErr::IncorrectVarDeclVal(..) => "IncorrectVarDeclVal",
+            ParserErr::WrongRhsVarDecl => "WrongRhsVarDecl",
+            ParserErr::NoExprAssign => "NoExprAssign",
+            ParserErr::InvalidAssignTarget => "InvalidAssignTarget",
+            ParserErr::UnclosedBlock => "UnclosedBlock",
+            ParserErr::MissingIfOpenBrace => "MissingIfOpenBrace",
+            ParserErr::MissingIfCloseBrace => "MissingIfCloseBrace",
+            ParserErr::MissingElseOpenBrace => "MissingElseOpenBrace",
+            ParserErr::MissingElseCloseBrace => "MissingElseCloseBrace",
+            ParserErr::IfWithNoCond => "IfWithNoCond",
+            ParserErr::OrWithNoCond => "OrWithNoCond",
+            ParserErr::AndWithNoCond => "AndWithNoCond",
+            ParserErr::VarDeclInIf => "VarDeclInIf",
+            ParserErr::ElseWithCond => "ElseWithCond",
+            ParserErr::WhileWithNoCond => "WhileWithNoCond",
+            ParserErr::MissingWhileOpenBrace => "MissingWhileOpenBrace",
+            ParserErr::MissingVarNameFor => "MissingVarNameFor",
+            ParserErr::MissingInFor => "MissingInFor",
+            ParserErr::MissingForRange => "MissingForRange",
+            ParserErr::NonIntForRange => "NonIntForRange",
+            ParserErr::NegativeForRange => "NegativeForRange",
+            ParserErr::LesserEndForRange => "LesserEndForRange",
+            ParserErr::MissingStartForRange => "MissingStartForRange",
+            ParserErr::MissingEndForRange => "MissingEndForRange",
+            ParserErr::MissingForOpenBrace => "MissingForOpenBrace",
+            ParserErr::MissingCallCloseParen => "MissingCallCloseParen",
+            ParserErr::TooManyCallArgs => "TooManyCallArgs",
+            ParserErr::MissingArgsComma => "MissingArgsComma",
+            ParserErr::MissingMemberName => "MissingMemberName",
+            ParserErr::MissingIndex => "MissingIndex",
+            ParserErr::MissingCloseBracket => "MissingCloseBracket",
+            ParserErr::MissingListComma => "MissingListComma",
+            ParserErr::ListNeverClosed => "ListNeverClosed",
+            ParserErr::MissingForComprehension => "MissingForComprehension",
+            ParserErr::MissingVarNameComprehension => "MissingVarNameComprehension",
+            ParserErr::MissingInComprehension => "MissingInComprehension",
+            ParserErr::ComprehensionNeverClosed => "ComprehensionNeverClosed",
+            ParserErr::MissingFnName => "MissingFnName",
+            ParserErr::NoOpenParenAfterFnName => "NoOpenParenAfterFnName",
+            ParserErr::MaxFnArgs => "MaxFnArgs",
+            ParserErr::WrongFnArgType => "WrongFnArgType",
+            ParserErr::MissingFnOpenBrace => "MissingFnOpenBrace",
+            ParserErr::MissingType(..) => "MissingType",
+            ParserErr::MissingFnTypeParen => "MissingFnTypeParen",
+            ParserErr::MissingListTypeBracket => "MissingListTypeBracket",
+            ParserErr::WrongTypeParams => "WrongTypeParams",
+            ParserErr::MissingContractCond => "MissingContractCond",
+            ParserErr::SpawnWithoutCall => "SpawnWithoutCall",
+            ParserErr::MissingAttributeName => "MissingAttributeName",
+            ParserErr::AttributeWithoutFn => "AttributeWithoutFn",
+            ParserErr::FileAttributeNotTopLevel => "FileAttributeNotTopLevel",
+            ParserErr::MacroNotTopLevel => "MacroNotTopLevel",
+            ParserErr::UnknownMacro(..) => "UnknownMacro",
+            ParserErr::MissingMacroOpenParen => "MissingMacroOpenParen",
+            ParserErr::MacroArity(..) => "MacroArity",
+            ParserErr::OperatorNotTopLevel => "OperatorNotTopLevel",
+            ParserErr::MissingOperatorSymbol => "MissingOperatorSymbol",
+            ParserErr::WrongPrecedence(..) => "WrongPrecedence",
+            ParserErr::OperatorArity(..) => "OperatorArity",
+            ParserErr::UnknownOperator(..) => "UnknownOperator",
+            ParserErr::MissingActorName => "MissingActorName",
+            ParserErr::MissingActorHandler(..) => "MissingActorHandler",
+            ParserErr::DuplicateActorHandler => "DuplicateActorHandler",
+            ParserErr::MissingHandlerOpenBrace => "MissingHandlerOpenBrace",
+            ParserErr::MissingImportPath => "MissingImportPath",
+            ParserErr::ImportNotTopLevel => "ImportNotTopLevel",
+            ParserErr::PubWithoutDecl => "PubWithoutDecl",
+            ParserErr::PubNotTopLevel => "PubNotTopLevel",
+            ParserErr::KeywordAsIdentifier(..) => "KeywordAsIdentifier",
+            ParserErr::UnexpectedEof => "UnexpectedEof",
+            ParserErr::ExpectedToken(..) => "ExpectedToken",
+        }
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        match self {
+            ParserErr::UnexpectedToken(v)
+            | ParserErr::IntTooLarge(v)
+            | ParserErr::MissingUnitSymbol(v)
+            | ParserErr::IncorrectVarDeclVal(v)
+            | ParserErr::MissingType(v)
+            | ParserErr::UnknownMacro(v)
+            | ParserErr::WrongPrecedence(v)
+            | ParserErr::UnknownOperator(v)
+            | ParserErr::MissingActorHandler(v)
+            | ParserErr::KeywordAsIdentifier(v) => vec![Arg::text(v)],
+            ParserErr::MacroArity(a, b, c) => vec![Arg::text(a), Arg::text(b), Arg::text(c)],
+            ParserErr::OperatorArity(a, b) => vec![Arg::text(a), Arg::text(b)],
+            ParserErr::ExpectedToken(a, b) => vec![Arg::text(a), Arg::text(b)],
+            _ => vec![],
+        }
     }
 }
 
@@ -172,8 +425,29 @@ pub(crate) type ParserExprRes = Result<Expr, PhyResParser>;
 
 enum FnKind {
     Fn,
+    Macro,
+    Operator,
+    Actor,
 }
 
+// Handler of the messages in the function made from an 'actor' declaration
+const ACTOR_HANDLER: &str = "on#";
+
+// Symbols read as the unit of the number before them, the ones of the
+// runtime unit table. Other names after a number start a new expression
+pub const UNIT_SYMBOLS: [&str; 22] = [
+    "kg", "m", "s", "A", "K", "mol", "cd", "g", "km", "cm", "mm", "ms", "min", "h", "N", "J", "W",
+    "Pa", "Hz", "C", "V", "ohm",
+];
+
+// Precedences of the binary operators, a custom one takes the level of the
+// built-in operators it's declared with
+const PREC_EQUALITY: u8 = 3;
+const PREC_COMPARISON: u8 = 4;
+const PREC_TERM: u8 = 5;
+const PREC_FACTOR: u8 = 6;
+const PREC_CUSTOM: u8 = 7;
+
 // ---------
 //  Parsing
 // ---------
@@ -182,6 +456,17 @@ pub struct Parser<'a> {
     tokens: &'a [Token],
     start_loc: usize,
     current: usize,
+    // Lines of the doc comments waiting for the declaration they document
+    doc: Vec<EcoString>,
+    // Attributes waiting for the function they apply to
+    attributes: Vec<Attribute>,
+    // '@!name' attributes, applying to the whole file
+    file_attributes: Vec<Attribute>,
+    // Macros declared so far and number of expansions made
+    macros: HashMap<EcoString, MacroDef>,
+    expansions: usize,
+    // Custom operators declared so far and their precedence
+    operators: HashMap<EcoString, u8>,
 }
 
 // TODO: Faire des localisation plus specifique. PAr exemple, si on parse :
@@ -204,7 +489,30 @@ impl<'a> Parser<'a> {
                 break;
             }
 
-            match self.parse_declarations() {
+            // Macros are only used by the parser, they aren't in the AST
+            if self.is_at(TokenKind::Macro) {
+                if let Err(e) = self.parse_macro_decl() {
+                    errors.push(e);
+                }
+
+                continue;
+            }
+
+            if self.is_at_file_attribute() {
+                match self.parse_attribute() {
+                    Ok(attr) => self.file_attributes.push(attr),
+                    Err(e) => errors.push(e),
+                }
+
+                continue;
+            }
+
+            let stmt = match self.is_at(TokenKind::Operator) {
+                true => self.parse_operator_decl(),
+                false => self.parse_declarations(),
+            };
+
+            match stmt {
                 Ok(stmt) => stmts.push(stmt),
                 Err(e) => errors.push(e),
             }
@@ -214,24 +522,151 @@ impl<'a> Parser<'a> {
             return Err(errors);
         }
 
+        // After the macros expansions, which copy the nodes of their body
+        number_nodes(&mut stmts);
+
         Ok(stmts)
     }
 
     fn parse_declarations(&mut self) -> ParserStmtRes {
         match self.at().kind {
             TokenKind::Var => self.parse_var_declaration(),
-            _ => self.parse_stmt(),
+            TokenKind::Fn | TokenKind::Actor => self.parse_stmt(),
+            TokenKind::At => self.parse_attributes(),
+            TokenKind::Pub => self.parse_pub_decl(),
+            TokenKind::Import => self.parse_import_stmt(),
+            TokenKind::Macro => Err(self.trigger_error(ParserErr::MacroNotTopLevel, true)),
+            TokenKind::Operator => Err(self.trigger_error(ParserErr::OperatorNotTopLevel, true)),
+            // Doc comments only document declarations
+            _ => {
+                self.doc.clear();
+                self.parse_stmt()
+            }
+        }
+    }
+
+    // Attributes of the file returned by the last parse
+    pub fn file_attributes(&self) -> &[Attribute] {
+        &self.file_attributes
+    }
+
+    fn parse_attributes(&mut self) -> ParserStmtRes {
+        while self.is_at(TokenKind::At) {
+            // The top level ones are parsed before reaching here
+            if self.is_at_file_attribute() {
+                self.attributes.clear();
+                return Err(self.trigger_error(ParserErr::FileAttributeNotTopLevel, true));
+            }
+
+            let attr = self.parse_attribute()?;
+            self.attributes.push(attr);
+        }
+
+        match self.at().kind {
+            TokenKind::Fn => self.parse_stmt(),
+            TokenKind::Pub => self.parse_pub_decl(),
+            _ => {
+                self.attributes.clear();
+                Err(self.trigger_error(ParserErr::AttributeWithoutFn, true))
+            }
+        }
+    }
+
+    // '@name' or '@!name', followed by arguments like the ones of a call
+    fn parse_attribute(&mut self) -> Result<Attribute, PhyResParser> {
+        let start = self.at().loc.start;
+        self.eat()?;
+
+        if self.is_stuck_at(TokenKind::Bang) {
+            self.eat()?;
+        }
+
+        let name = self.expect_identifier(ParserErr::MissingAttributeName)?.value;
+
+        let mut args = vec![];
+        if self.is_at(TokenKind::OpenParen) {
+            self.eat()?;
+
+            let callee = Expr::Identifier(IdentifierExpr {
+                id: NodeId::default(),
+                name: name.clone(),
+                loc: self.get_loc(),
+            });
+            if let Expr::Call(call) = self.finish_call(callee)? {
+                args = call.args;
+            }
+        }
+
+        let loc = Loc::new(start, self.at().loc.start);
+        self.skip_new_lines();
+
+        Ok(Attribute { name, args, loc })
+    }
+
+    fn is_at_file_attribute(&self) -> bool {
+        self.is_at(TokenKind::At)
+            && self
+                .tokens
+                .get(self.current + 1)
+                .is_some_and(|t| t.kind == TokenKind::Bang && t.loc.start == self.at().loc.end)
+    }
+
+    fn parse_pub_decl(&mut self) -> ParserStmtRes {
+        self.eat()?;
+
+        let mut stmt = match self.at().kind {
+            TokenKind::Var => self.parse_var_declaration()?,
+            TokenKind::Fn | TokenKind::Actor => self.parse_stmt()?,
+            _ => return Err(self.trigger_error(ParserErr::PubWithoutDecl, true)),
+        };
+
+        match &mut stmt {
+            Stmt::VarDecl(s) => s.is_pub = true,
+            Stmt::FnDecl(s) => s.is_pub = true,
+            _ => unreachable!("only declarations can be exported"),
+        }
+
+        Ok(stmt)
+    }
+
+    // import a.b.c
+    fn parse_import_stmt(&mut self) -> ParserStmtRes {
+        self.doc.clear();
+        self.eat()?;
+
+        let mut path = vec![];
+        let name_loc = loop {
+            let tk = self.expect_identifier(ParserErr::MissingImportPath)?;
+
+            path.push(tk.value);
+
+            if !self.is_at(TokenKind::Dot) {
+                break tk.loc;
+            }
+
+            self.eat()?;
+        };
+
+        let loc = self.get_loc();
+        self.skip_new_lines();
+
+        Ok(Stmt::Import(ImportStmt { id: NodeId::default(), path, name_loc, loc }))
+    }
+
+    fn take_doc(&mut self) -> Option<EcoString> {
+        match self.doc.is_empty() {
+            true => None,
+            false => Some(std::mem::take(&mut self.doc).join("\n").into()),
         }
     }
 
     fn parse_var_declaration(&mut self) -> ParserStmtRes {
+        let doc = self.take_doc();
         self.expect(TokenKind::Var)?;
-        let name = self
-            .expect(TokenKind::Identifier)
-            .map_err(|_| self.trigger_error(ParserErr::VarDeclNoName, true))?
-            .value
-            .clone();
+        let Token { value: name, loc: name_loc, .. } = self
+            .expect_identifier(ParserErr::VarDeclNoName)?;
 
+        let ty = self.parse_type_annotation(TokenKind::Colon)?;
         let mut value: Option<Expr> = None;
 
         match self.at().kind {
@@ -258,24 +693,32 @@ impl<'a> Parser<'a> {
             _ => return Err(self.trigger_error(ParserErr::WrongRhsVarDecl, true)),
         }
 
+        // Taken before skipping the new lines, which moves the start
+        let loc = self.get_loc();
         self.skip_new_lines();
 
         Ok(Stmt::VarDecl(VarDeclStmt {
+            id: NodeId::default(),
             name,
             value,
-            loc: self.get_loc(),
+            ty,
+            doc,
+            is_pub: false,
+            name_loc,
+            loc,
         }))
     }
 
     fn parse_stmt(&mut self) -> ParserStmtRes {
         let stmt = match self.at().kind {
-            TokenKind::Print => self.parse_print_stmt(),
             TokenKind::OpenBrace => self.parse_block_stmt(),
             TokenKind::If => self.parse_if_stmt(),
             TokenKind::While => self.parse_while_stmt(),
             TokenKind::For => self.parse_for_stmt(),
             TokenKind::Fn => self.parse_fn_decl_stmt(FnKind::Fn),
+            TokenKind::Actor => self.parse_fn_decl_stmt(FnKind::Actor),
             TokenKind::Return => self.parse_return_stmt(),
+            TokenKind::Identifier if self.is_at_macro_call() => self.parse_macro_call(),
             _ => self.parse_expr_stmt(),
         };
 
@@ -284,25 +727,105 @@ impl<'a> Parser<'a> {
         stmt
     }
 
-    fn parse_print_stmt(&mut self) -> ParserStmtRes {
-        self.expect(TokenKind::Print)?;
+    // Same syntax as a function declaration
+    fn parse_macro_decl(&mut self) -> Result<(), PhyResParser> {
+        if let Stmt::FnDecl(decl) = self.parse_fn_decl_stmt(FnKind::Macro)? {
+            let def = MacroDef {
+                name: decl.name.clone(),
+                params: decl.params.to_vec(),
+                body: decl.body.to_vec(),
+                loc: decl.loc,
+            };
+
+            self.macros.insert(decl.name, def);
+        }
 
-        let expr = self.parse_expr()?;
+        self.skip_new_lines();
 
-        Ok(Stmt::Print(PrintStmt {
-            expr,
+        Ok(())
+    }
+
+    // 'operator <+> prec 6 (a, b) { ... }' declares the function '<+>',
+    // called by 'x <+> y' in the code that follows. The precedence is the
+    // one of '==' (3), '<' (4), '+' (5, the default) or '*' (6), and 7
+    // binds tighter than all of them
+    fn parse_operator_decl(&mut self) -> ParserStmtRes {
+        let stmt = self.parse_fn_decl_stmt(FnKind::Operator)?;
+        self.skip_new_lines();
+
+        Ok(stmt)
+    }
+
+    fn parse_precedence(&mut self) -> Result<u8, PhyResParser> {
+        if !(self.is_at(TokenKind::Identifier) && self.at().value == "prec") {
+            return Ok(PREC_TERM);
+        }
+
+        self.eat()?;
+        let value = self.eat()?.value.clone();
+
+        let is_int = self.prev().kind == TokenKind::Int;
+
+        match value.parse::<u8>() {
+            Ok(p) if is_int && (PREC_EQUALITY..=PREC_CUSTOM).contains(&p) => Ok(p),
+            _ => Err(self.trigger_error(ParserErr::WrongPrecedence(value.into()), true)),
+        }
+    }
+
+    fn is_at_macro_call(&self) -> bool {
+        self.tokens
+            .get(self.current + 1)
+            .is_some_and(|t| t.kind == TokenKind::Bang && t.loc.start == self.at().loc.end)
+    }
+
+    // name!(args) with an optional trailing block given as last argument
+    fn parse_macro_call(&mut self) -> ParserStmtRes {
+        let start = self.start_loc;
+        let name = self.eat()?.value.clone();
+        self.eat()?;
+
+        let Some(def) = self.macros.get(&name).cloned() else {
+            return Err(self.trigger_error(ParserErr::UnknownMacro(name.into()), true));
+        };
+
+        self.expect(TokenKind::OpenParen)
+            .map_err(|_| self.trigger_error(ParserErr::MissingMacroOpenParen, true))?;
+
+        let callee = Expr::Identifier(IdentifierExpr {
+            id: NodeId::default(),
+            name: name.clone(),
             loc: self.get_loc(),
-        }))
+        });
+        let Expr::Call(call) = self.finish_call(callee)? else {
+            unreachable!("finish_call always returns a call")
+        };
+
+        let mut block = None;
+        if self.is_at(TokenKind::OpenBrace) {
+            self.expect_and_skip(TokenKind::OpenBrace)?;
+            block = Some(self.parse_block()?);
+        }
+
+        self.expansions += 1;
+        let loc = Loc::new(start, self.at().loc.start);
+
+        // The whole call is parsed, the error points at it
+        def.expand(call.args, block, self.expansions, loc.clone())
+            .map_err(|e| PhyResult::new(e, Some(loc)))
     }
 
     fn parse_block_stmt(&mut self) -> ParserStmtRes {
+        // Skipping the new lines moves the start of the statement, the
+        // block starts at its brace
+        let start = self.at().loc.start;
         self.expect_and_skip(TokenKind::OpenBrace)?;
 
         let stmts = self.parse_block()?;
 
         Ok(Stmt::Block(BlockStmt {
+            id: NodeId::default(),
             stmts,
-            loc: self.get_loc(),
+            loc: Loc::new(start, self.at().loc.start),
         }))
     }
 
@@ -310,6 +833,14 @@ impl<'a> Parser<'a> {
         let mut stmts: Vec<Stmt> = vec![];
 
         while !self.is_at(TokenKind::CloseBrace) && !self.eof() {
+            match self.at().kind {
+                TokenKind::Pub => return Err(self.trigger_error(ParserErr::PubNotTopLevel, true)),
+                TokenKind::Import => {
+                    return Err(self.trigger_error(ParserErr::ImportNotTopLevel, true))
+                }
+                _ => {}
+            }
+
             stmts.push(self.parse_declarations()?);
             self.skip_new_lines();
         }
@@ -366,6 +897,7 @@ impl<'a> Parser<'a> {
         }
 
         Ok(Stmt::If(IfStmt {
+            id: NodeId::default(),
             condition,
             then_branch,
             else_branch,
@@ -387,6 +919,7 @@ impl<'a> Parser<'a> {
         let body = Box::new(self.parse_stmt()?);
 
         Ok(Stmt::While(WhileStmt {
+            id: NodeId::default(),
             condition,
             body,
             loc: self.get_loc(),
@@ -396,12 +929,13 @@ impl<'a> Parser<'a> {
     fn parse_for_stmt(&mut self) -> ParserStmtRes {
         self.eat()?;
 
-        let var_name = self
-            .expect(TokenKind::Identifier)
-            .map_err(|_| self.trigger_error(ParserErr::MissingVarNameFor, true))?
-            .value;
+        let placeholder = self.parse_loop_var()?;
+        let mut rest = vec![];
 
-        let placeholder = VarDeclStmt { name: var_name, value: None, loc: self.get_loc() };
+        while self.is_at(TokenKind::Comma) {
+            self.eat()?;
+            rest.push(self.parse_loop_var()?);
+        }
 
         self.expect(TokenKind::In)
             .map_err(|_| self.trigger_error(ParserErr::MissingInFor, true))?;
@@ -411,36 +945,19 @@ impl<'a> Parser<'a> {
         if self.is_at(TokenKind::DotDot) {
             return Err(self.trigger_error(ParserErr::MissingStartForRange, true))
         }
-        else if self.is_at(TokenKind::Minus) {
-            return Err(self.trigger_error(ParserErr::NegativeForRange, true))
-        }
 
-        let start = self
-            .expect(TokenKind::Int)
-            .map_err(|_| self.trigger_error(ParserErr::NonIntForRange, true))?
-            .value
-            .parse::<i64>()
-            .map_err(|_| self.trigger_error(ParserErr::ParsingInt, true))?;
+        let start = self.parse_expr()?;
 
         let mut end = None;
         if self.is_at(TokenKind::DotDot) {
             self.eat()?;
 
             self.is_at_brace_or_end_of(ParserErr::MissingEndForRange)?;
-
-            end = Some(
-                self.expect(TokenKind::Int)
-                    .map_err(|_| self.trigger_error(ParserErr::NonIntForRange, true))?
-                    .value
-                    .parse::<i64>()
-                    .map_err(|_| self.trigger_error(ParserErr::ParsingInt, true))?
-            );
-
-            if Some(start) > end {
-                return Err(self.trigger_error(ParserErr::LesserEndForRange, true))
-            }
+            end = Some(self.parse_expr()?);
         }
 
+        let range = self.for_range(start, end)?;
+
         self.skip_new_lines();
         if !self.is_at(TokenKind::OpenBrace) {
             return Err(self.trigger_error(ParserErr::MissingForOpenBrace, true))
@@ -449,19 +966,84 @@ impl<'a> Parser<'a> {
         let body = Box::new(self.parse_stmt()?);
 
         Ok(Stmt::For(ForStmt {
+            id: NodeId::default(),
             placeholder,
-            range: ForRange { start, end },
+            rest,
+            range,
             body,
             loc: self.get_loc(),
         }))
     }
 
+    fn parse_loop_var(&mut self) -> Result<VarDeclStmt, PhyResParser> {
+        let Token { value: name, loc: name_loc, .. } = self
+            .expect_loop_var(ParserErr::MissingVarNameFor)?;
+
+        Ok(VarDeclStmt {
+            id: NodeId::default(),
+            name,
+            value: None,
+            ty: None,
+            doc: None,
+            is_pub: false,
+            name_loc,
+            loc: self.get_loc(),
+        })
+    }
+
+    // Literal ranges are checked here, the other ones when they are
+    // evaluated. Literals that are neither ints nor lists can't be ranges
+    fn for_range(&mut self, start: Expr, end: Option<Expr>) -> Result<ForRange, PhyResParser> {
+        let non_int = |e: &Expr| matches!(
+            e,
+            Expr::RealLiteral(_) | Expr::ImaginaryLiteral(_) | Expr::StrLiteral(_)
+                | Expr::CharLiteral(_) | Expr::UnitLiteral(_)
+        );
+
+        if non_int(&start) || end.as_ref().is_some_and(non_int) {
+            return Err(self.trigger_error(ParserErr::NonIntForRange, true))
+        }
+
+        match (start, end) {
+            (Expr::IntLiteral(s), None) if s.value < 0 => {
+                Err(self.trigger_error(ParserErr::NegativeForRange, true))
+            }
+            (Expr::Unary(u), None)
+                if u.operator == "-" && matches!(*u.right, Expr::IntLiteral(_)) =>
+            {
+                Err(self.trigger_error(ParserErr::NegativeForRange, true))
+            }
+            (Expr::IntLiteral(s), None) => Ok(ForRange::Ints(s.value, None)),
+            (Expr::IntLiteral(s), Some(Expr::IntLiteral(e))) => match s.value > e.value {
+                true => Err(self.trigger_error(ParserErr::LesserEndForRange, true)),
+                false => Ok(ForRange::Ints(s.value, Some(e.value))),
+            },
+            (start, Some(end)) => Ok(ForRange::Bounds(Box::new(start), Box::new(end))),
+            (items, None) => Ok(ForRange::Items(Box::new(items))),
+        }
+    }
+
     fn parse_fn_decl_stmt(&mut self, kind: FnKind) -> ParserStmtRes {
+        // Same as for calls, parameters and body can span multiple lines
+        let start = self.start_loc;
+        let doc = self.take_doc();
+        let attributes = std::mem::take(&mut self.attributes);
         self.eat()?;
 
-        let name = self.expect(TokenKind::Identifier)
-            .map_err(|_| self.trigger_error(ParserErr::MissingFnName, true))?
-            .value;
+        let Token { value: name, loc: name_loc, .. } = match kind {
+            FnKind::Operator => self
+                .expect(TokenKind::CustomOp)
+                .map_err(|_| self.trigger_error(ParserErr::MissingOperatorSymbol, true))?,
+            FnKind::Actor => self.expect_identifier(ParserErr::MissingActorName)?,
+            _ => self.expect_identifier(ParserErr::MissingFnName)?,
+        };
+
+        let precedence = match kind {
+            FnKind::Operator => Some(self.parse_precedence()?),
+            _ => None,
+        };
+
+        let type_params = self.parse_type_params()?;
 
         self.expect(TokenKind::OpenParen)
             .map_err(|_| self.trigger_error(ParserErr::NoOpenParenAfterFnName, true))?;
@@ -469,16 +1051,18 @@ impl<'a> Parser<'a> {
         self.skip_new_lines();
 
         let mut params: Vec<EcoString> = vec![];
+        let mut param_types: Vec<Option<TypeAnn>> = vec![];
+        let mut param_locs: Vec<Loc> = vec![];
         if !self.is_at(TokenKind::CloseParen) {
             loop {
                 if params.len() >= 255 {
                     return Err(self.trigger_error(ParserErr::MaxFnArgs, true))
                 }
 
-                params.push(self.expect(TokenKind::Identifier)
-                    .map_err(|_| self.trigger_error(ParserErr::WrongFnArgType, true))?
-                    .value
-                );
+                let param = self.expect_identifier(ParserErr::WrongFnArgType)?;
+                params.push(param.value);
+                param_locs.push(param.loc);
+                param_types.push(self.parse_type_annotation(TokenKind::Colon)?);
 
                 self.skip_new_lines();
 
@@ -496,8 +1080,21 @@ impl<'a> Parser<'a> {
         }
 
         self.eat()?;
+
+        // Known from here, so that the body can use it
+        if let Some(prec) = precedence {
+            if params.len() != 2 {
+                return Err(self.trigger_error(ParserErr::OperatorArity(name.into(), params.len()), true))
+            }
+
+            self.operators.insert(name.clone(), prec);
+        }
+
+        let return_type = self.parse_type_annotation(TokenKind::Arrow)?;
         self.skip_new_lines();
 
+        let (requires, ensures) = self.parse_contracts()?;
+
         if !self.is_at(TokenKind::OpenBrace) {
             return Err(self.trigger_error(ParserErr::MissingFnOpenBrace, true))
         }
@@ -505,31 +1102,268 @@ impl<'a> Parser<'a> {
         self.eat()?;
         self.skip_new_lines();
 
-        let body = Rc::new(self.parse_block()?);
+        let body = match kind {
+            FnKind::Actor => self.parse_actor_body(&name, &name_loc)?,
+            _ => self.parse_block()?,
+        };
+        let body = Rc::new(body);
 
         Ok(Stmt::FnDecl(FnDeclStmt {
+            id: NodeId::default(),
             name,
             params: Rc::new(params),
+            type_params,
+            param_types,
+            return_type,
+            requires: Rc::new(requires),
+            ensures: Rc::new(ensures),
             body,
-            loc: self.get_loc(),
+            doc,
+            attributes,
+            is_pub: false,
+            name_loc,
+            param_locs,
+            loc: Loc::new(start, self.at().loc.start),
         }))
     }
 
+    // 'actor Name(params) { ... on msg { ... } }' declares the function
+    // making the instances. The state is its variables, only seen by the
+    // handler, which becomes the function 'on#' given to the native
+    // 'actor'. The handler gets the messages sent with 'send(instance, msg)'
+    // once the interpreter is idle. 'on' isn't a keyword outside of this
+    // place
+    fn parse_actor_body(&mut self, name: &EcoString, name_loc: &Loc) -> Result<Vec<Stmt>, PhyResParser> {
+        let mut stmts: Vec<Stmt> = vec![];
+        let mut handler: Option<Loc> = None;
+
+        while !self.is_at(TokenKind::CloseBrace) && !self.eof() {
+            if !self.is_at_actor_handler() {
+                match self.at().kind {
+                    TokenKind::Pub => return Err(self.trigger_error(ParserErr::PubNotTopLevel, true)),
+                    TokenKind::Import => {
+                        return Err(self.trigger_error(ParserErr::ImportNotTopLevel, true))
+                    }
+                    _ => {}
+                }
+
+                stmts.push(self.parse_declarations()?);
+                self.skip_new_lines();
+                continue;
+            }
+
+            if handler.is_some() {
+                return Err(self.trigger_error(ParserErr::DuplicateActorHandler, true));
+            }
+
+            let start = self.at().loc.start;
+            self.eat()?;
+            let param = self.eat()?.clone();
+
+            self.skip_new_lines();
+            self.expect_and_skip(TokenKind::OpenBrace)
+                .map_err(|_| self.trigger_error(ParserErr::MissingHandlerOpenBrace, true))?;
+
+            let body = self.parse_block()?;
+            let loc = Loc::new(start, self.prev().loc.end);
+
+            stmts.push(Stmt::FnDecl(FnDeclStmt {
+                id: NodeId::default(),
+                name: ACTOR_HANDLER.into(),
+                params: Rc::new(vec![param.value]),
+                type_params: vec![],
+                param_types: vec![None],
+                return_type: None,
+                requires: Rc::new(vec![]),
+                ensures: Rc::new(vec![]),
+                body: Rc::new(body),
+                doc: None,
+                attributes: vec![],
+                is_pub: false,
+                name_loc: Loc::new(start, start + 2),
+                param_locs: vec![param.loc],
+                loc: loc.clone(),
+            }));
+            handler = Some(loc);
+        }
+
+        self.expect_and_skip(TokenKind::CloseBrace)
+            .map_err(|_| self.trigger_error(ParserErr::UnclosedBlock, true))?;
+
+        let Some(loc) = handler else {
+            return Err(PhyResult::new(ParserErr::MissingActorHandler(name.into()), Some(name_loc.clone())));
+        };
+
+        let identifier = |name: &str| {
+            Expr::Identifier(IdentifierExpr { id: NodeId::default(), name: name.into(), loc: loc.clone() })
+        };
+        let make = Expr::Call(CallExpr {
+            callee: Box::new(identifier("actor")),
+            args: vec![
+                Expr::StrLiteral(StrLiteralExpr { id: NodeId::default(), value: name.clone(), loc: loc.clone() }),
+                identifier(ACTOR_HANDLER),
+            ],
+            id: NodeId::default(),
+            loc: loc.clone(),
+        });
+
+        stmts.push(Stmt::Return(ReturnStmt { value: Some(make), id: NodeId::default(), loc }));
+
+        Ok(stmts)
+    }
+
+    fn is_at_actor_handler(&self) -> bool {
+        self.is_at(TokenKind::Identifier)
+            && self.at().value == "on"
+            && self
+                .tokens
+                .get(self.current + 1)
+                .is_some_and(|t| t.kind == TokenKind::Identifier)
+    }
+
+    // 'requires' and 'ensures' clauses before the body, one per line. They
+    // aren't keywords outside of this place
+    fn parse_contracts(&mut self) -> Result<(Vec<Expr>, Vec<Expr>), PhyResParser> {
+        let (mut requires, mut ensures) = (vec![], vec![]);
+
+        while self.is_at(TokenKind::Identifier) {
+            let clauses = match self.at().value.as_str() {
+                "requires" => &mut requires,
+                "ensures" => &mut ensures,
+                _ => break,
+            };
+            self.eat()?;
+
+            if self.is_at(TokenKind::OpenBrace) || self.is_at(TokenKind::NewLine) {
+                return Err(self.trigger_error(ParserErr::MissingContractCond, true));
+            }
+
+            // The location of the condition is reported on violation
+            self.start_loc = self.at().loc.start;
+            clauses.push(self.parse_expr()?);
+            self.skip_new_lines();
+        }
+
+        Ok((requires, ensures))
+    }
+
+    // The type following the separator, if it is here
+    fn parse_type_annotation(&mut self, separator: TokenKind) -> Result<Option<TypeAnn>, PhyResParser> {
+        if !self.is_at(separator) {
+            return Ok(None);
+        }
+
+        self.eat()?;
+        self.parse_type().map(Some)
+    }
+
+    fn parse_type(&mut self) -> Result<TypeAnn, PhyResParser> {
+        let start = self.at().loc.start;
+
+        if self.is_at(TokenKind::Fn) {
+            return self.parse_fn_type();
+        }
+
+        let mut ty = match self.at().kind {
+            TokenKind::Identifier | TokenKind::Null => {
+                let name = self.eat()?.value.clone();
+                TypeAnn::Named(name, Loc::new(start, self.prev().loc.end))
+            }
+            TokenKind::OpenBracket => {
+                self.eat()?;
+                let elem = self.parse_type()?;
+
+                self.expect(TokenKind::CloseBracket)
+                    .map_err(|_| self.trigger_error(ParserErr::MissingListTypeBracket, true))?;
+
+                TypeAnn::List(Box::new(elem), Loc::new(start, self.prev().loc.end))
+            }
+            _ => {
+                let after = self.prev().value.to_string();
+                return Err(self.trigger_error(ParserErr::MissingType(after), true));
+            }
+        };
+
+        while self.is_at(TokenKind::Question) {
+            let end = self.eat()?.loc.end;
+            ty = TypeAnn::Nullable(Box::new(ty), Loc::new(start, end));
+        }
+
+        Ok(ty)
+    }
+
+    // 'fn first<T, U>(...)', no names without the '<'
+    fn parse_type_params(&mut self) -> Result<Vec<EcoString>, PhyResParser> {
+        let mut names = vec![];
+
+        if !self.is_at(TokenKind::Less) {
+            return Ok(names);
+        }
+        self.eat()?;
+
+        loop {
+            let name = self.expect_identifier(ParserErr::WrongTypeParams)?;
+            names.push(name.value);
+
+            if !self.is_at(TokenKind::Comma) {
+                break;
+            }
+            self.eat()?;
+        }
+
+        self.expect(TokenKind::Greater)
+            .map_err(|_| self.trigger_error(ParserErr::WrongTypeParams, true))?;
+
+        Ok(names)
+    }
+
+    // The '?' after a function type applies to its return type
+    fn parse_fn_type(&mut self) -> Result<TypeAnn, PhyResParser> {
+        let start = self.at().loc.start;
+        self.eat()?;
+
+        self.expect(TokenKind::OpenParen)
+            .map_err(|_| self.trigger_error(ParserErr::MissingFnTypeParen, true))?;
+
+        let mut params = vec![];
+        while !self.is_at(TokenKind::CloseParen) {
+            params.push(self.parse_type()?);
+
+            if !self.is_at(TokenKind::Comma) {
+                break;
+            }
+            self.eat()?;
+        }
+
+        self.expect(TokenKind::CloseParen)
+            .map_err(|_| self.trigger_error(ParserErr::MissingFnTypeParen, true))?;
+
+        let ret = self.parse_type_annotation(TokenKind::Arrow)?.map(Box::new);
+
+        Ok(TypeAnn::Fn(params, ret, Loc::new(start, self.prev().loc.end)))
+    }
+
     fn parse_return_stmt(&mut self) -> ParserStmtRes {
-        let _ = self.eat();
+        // The value can move the start of the statement, like a call does
+        let start = self.eat()?.loc.start;
 
         let mut value = None;
         if !self.is_at(TokenKind::NewLine) {
             value = Some(self.parse_expr()?);
         }
         
-        Ok(Stmt::Return(ReturnStmt { value, loc: self.get_loc() }))
+        Ok(Stmt::Return(ReturnStmt {
+            id: NodeId::default(),
+            value,
+            loc: Loc::new(start, self.at().loc.start),
+        }))
     }
 
     fn parse_expr_stmt(&mut self) -> ParserStmtRes {
         let expr = self.parse_expr()?;
 
         Ok(Stmt::Expr(ExprStmt {
+            id: NodeId::default(),
             expr,
             loc: self.get_loc(),
         }))
@@ -546,14 +1380,26 @@ impl<'a> Parser<'a> {
             self.eat()?;
             let value = self.parse_assign()?;
 
-            if let Expr::Identifier(e) = assigne {
-                return Ok(Expr::Assign(AssignExpr {
-                    name: e.name.clone(),
-                    value: Box::new(value),
-                    loc: self.get_loc(),
-                }));
-            } else {
-                return Err(self.trigger_error(ParserErr::InvalidAssignTarget, true));
+            match assigne {
+                Expr::Identifier(e) => {
+                    return Ok(Expr::Assign(AssignExpr {
+                        id: NodeId::default(),
+                        name: e.name.clone(),
+                        value: Box::new(value),
+                        name_loc: e.loc,
+                        loc: self.get_loc(),
+                    }));
+                }
+                Expr::Get(e) => {
+                    return Ok(Expr::Set(SetExpr {
+                        id: NodeId::default(),
+                        object: e.object,
+                        name: e.name,
+                        value: Box::new(value),
+                        loc: self.get_loc(),
+                    }));
+                }
+                _ => return Err(self.trigger_error(ParserErr::InvalidAssignTarget, true)),
             }
         }
 
@@ -576,6 +1422,7 @@ impl<'a> Parser<'a> {
             let right = self.parse_and()?;
 
             return Ok(Expr::Logical(LogicalExpr {
+                id: NodeId::default(),
                 left: Box::new(left),
                 operator: EcoString::from("or"),
                 right: Box::new(right),
@@ -602,6 +1449,7 @@ impl<'a> Parser<'a> {
             let right = self.parse_equality()?;
 
             return Ok(Expr::Logical(LogicalExpr {
+                id: NodeId::default(),
                 left: Box::new(left),
                 operator: EcoString::from("and"),
                 right: Box::new(right),
@@ -615,15 +1463,13 @@ impl<'a> Parser<'a> {
     fn parse_equality(&mut self) -> ParserExprRes {
         let mut expr = self.parse_comparison()?;
 
-        while self.is_at(TokenKind::EqualEqual) || self.is_at(TokenKind::BangEqual) {
-            let operator = self.eat()?.value.clone();
+        while self.is_at(TokenKind::EqualEqual)
+            || self.is_at(TokenKind::BangEqual)
+            || self.is_at_operator(PREC_EQUALITY)
+        {
+            let operator = self.eat()?.clone();
             let right = self.parse_comparison()?;
-            expr = Expr::Binary(BinaryExpr {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-                loc: self.get_loc(),
-            });
+            expr = self.binary(expr, operator, right);
         }
 
         Ok(expr)
@@ -636,15 +1482,11 @@ impl<'a> Parser<'a> {
             || self.is_at(TokenKind::LessEqual)
             || self.is_at(TokenKind::Greater)
             || self.is_at(TokenKind::GreaterEqual)
+            || self.is_at_operator(PREC_COMPARISON)
         {
-            let operator = self.eat()?.value.clone();
+            let operator = self.eat()?.clone();
             let right = self.parse_term()?;
-            expr = Expr::Binary(BinaryExpr {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-                loc: self.get_loc(),
-            });
+            expr = self.binary(expr, operator, right);
         }
 
         Ok(expr)
@@ -653,46 +1495,145 @@ impl<'a> Parser<'a> {
     fn parse_term(&mut self) -> ParserExprRes {
         let mut expr = self.parse_factor()?;
 
-        while self.is_at(TokenKind::Minus) || self.is_at(TokenKind::Plus) {
-            let operator = self.eat()?.value.clone();
+        while self.is_at(TokenKind::Minus)
+            || self.is_at(TokenKind::Plus)
+            || self.is_at_operator(PREC_TERM)
+        {
+            let operator = self.eat()?.clone();
             let right = self.parse_factor()?;
-            expr = Expr::Binary(BinaryExpr {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-                loc: self.get_loc(),
-            });
+            expr = self.binary(expr, operator, right);
         }
 
         Ok(expr)
     }
 
     fn parse_factor(&mut self) -> ParserExprRes {
-        let mut expr = self.parse_unary()?;
+        let mut expr = self.parse_custom()?;
 
         while self.is_at(TokenKind::Star)
             || self.is_at(TokenKind::Slash)
             || self.is_at(TokenKind::Modulo)
+            || self.is_at_operator(PREC_FACTOR)
         {
-            let operator = self.eat()?.value.clone();
+            let operator = self.eat()?.clone();
+            let right = self.parse_custom()?;
+            expr = self.binary(expr, operator, right);
+        }
+
+        Ok(expr)
+    }
+
+    // Custom operators binding tighter than the built-in ones. Operators
+    // used without a declaration are reported here, the innermost level
+    fn parse_custom(&mut self) -> ParserExprRes {
+        let mut expr = self.parse_unary()?;
+
+        loop {
+            if self.is_at(TokenKind::CustomOp) && !self.operators.contains_key(&self.at().value) {
+                let name = self.at().value.to_string();
+                return Err(self.trigger_error(ParserErr::UnknownOperator(name), true));
+            }
+
+            if !self.is_at_operator(PREC_CUSTOM) {
+                break
+            }
+
+            let operator = self.eat()?.clone();
             let right = self.parse_unary()?;
-            expr = Expr::Binary(BinaryExpr {
-                left: Box::new(expr),
-                operator,
+            expr = self.binary(expr, operator, right);
+        }
+
+        Ok(expr)
+    }
+
+    fn is_at_operator(&self, precedence: u8) -> bool {
+        self.is_at(TokenKind::CustomOp) && self.operators.get(&self.at().value) == Some(&precedence)
+    }
+
+    // Custom operators are calls of the function they declared
+    // Located from the operands, the start of the statement moves when an
+    // operand spans several lines
+    fn binary(&self, left: Expr, operator: Token, right: Expr) -> Expr {
+        let loc = Loc::new(left.get_loc().start, right.get_loc().end);
+
+        if operator.kind != TokenKind::CustomOp {
+            return Expr::Binary(BinaryExpr {
+                id: NodeId::default(),
+                left: Box::new(left),
+                operator: operator.value,
                 right: Box::new(right),
-                loc: self.get_loc(),
+                loc,
             });
         }
 
-        Ok(expr)
+        let callee = Expr::Identifier(IdentifierExpr {
+            id: NodeId::default(),
+            name: operator.value,
+            loc: operator.loc,
+        });
+
+        Expr::Call(CallExpr {
+            id: NodeId::default(),
+            callee: Box::new(callee),
+            args: vec![left, right],
+            loc,
+        })
     }
 
     fn parse_unary(&mut self) -> ParserExprRes {
+        if self.is_at(TokenKind::Spawn) {
+            let start = self.eat()?.loc.start;
+            let call = self.parse_call()?;
+
+            if !matches!(call, Expr::Call(_)) {
+                return Err(self.trigger_error(ParserErr::SpawnWithoutCall, true));
+            }
+
+            return Ok(Expr::Spawn(SpawnExpr {
+                id: NodeId::default(),
+                call: Box::new(call),
+                loc: Loc::new(start, self.prev().loc.end),
+            }));
+        }
+
+        if self.is_at(TokenKind::Await) {
+            let start = self.eat()?.loc.start;
+            let task = self.parse_unary()?;
+
+            return Ok(Expr::Await(AwaitExpr {
+                id: NodeId::default(),
+                task: Box::new(task),
+                loc: Loc::new(start, self.prev().loc.end),
+            }));
+        }
+
+        // The magnitude of the smallest int isn't an int, the literal is
+        // the negation as a whole
+        let min_int = self.tokens.get(self.current + 1).is_some_and(|t| {
+            t.kind == TokenKind::Int && t.value == MIN_INT_MAGNITUDE
+        });
+
+        if self.is_at(TokenKind::Minus) && min_int {
+            self.eat()?;
+            self.eat()?;
+
+            if self.is_at(TokenKind::Identifier) {
+                return self.parse_unit_literal(i64::MIN as f64);
+            }
+
+            return Ok(Expr::IntLiteral(IntLiteralExpr {
+                id: NodeId::default(),
+                value: i64::MIN,
+                loc: self.get_loc(),
+            }));
+        }
+
         if self.is_at(TokenKind::Bang) || self.is_at(TokenKind::Minus) {
             let operator = self.eat()?.value.clone();
             let right = self.parse_primary()?;
 
             return Ok(Expr::Unary(UnaryExpr {
+                id: NodeId::default(),
                 operator,
                 right: Box::new(right),
                 loc: self.get_loc(),
@@ -711,6 +1652,23 @@ impl<'a> Parser<'a> {
                 self.skip_new_lines();
 
                 expr = self.finish_call(expr)?;
+            } else if self.is_at(TokenKind::Dot) {
+                self.eat()?;
+
+                let name = self.expect(TokenKind::Identifier)
+                    .map_err(|_| self.trigger_error(ParserErr::MissingMemberName, true))?
+                    .value;
+
+                expr = Expr::Get(GetExpr {
+                    id: NodeId::default(),
+                    loc: Loc::new(expr.get_loc().start, self.at().loc.start),
+                    object: Box::new(expr),
+                    name,
+                });
+            } else if self.is_at(TokenKind::OpenBracket) {
+                self.eat()?;
+
+                expr = self.finish_index(expr)?;
             } else {
                 break
             }
@@ -719,7 +1677,44 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
+    // After the '[': 'i]', 'i..j]', 'i..]', '..j]' or '..]'
+    fn finish_index(&mut self, object: Expr) -> ParserExprRes {
+        if self.is_at(TokenKind::CloseBracket) {
+            return Err(self.trigger_error(ParserErr::MissingIndex, true));
+        }
+
+        let start = match self.is_at(TokenKind::DotDot) {
+            true => None,
+            false => Some(Box::new(self.parse_expr()?)),
+        };
+
+        let mut end = None;
+        let is_slice = self.is_at(TokenKind::DotDot);
+        if is_slice {
+            self.eat()?;
+
+            if !self.is_at(TokenKind::CloseBracket) {
+                end = Some(Box::new(self.parse_expr()?));
+            }
+        }
+
+        self.expect(TokenKind::CloseBracket)
+            .map_err(|_| self.trigger_error(ParserErr::MissingCloseBracket, true))?;
+
+        Ok(Expr::Index(IndexExpr {
+            id: NodeId::default(),
+            loc: Loc::new(object.get_loc().start, self.prev().loc.end),
+            object: Box::new(object),
+            start,
+            end,
+            is_slice,
+        }))
+    }
+
     fn finish_call(&mut self, callee: Expr) -> ParserExprRes {
+        // Skipping new lines between arguments moves the start location,
+        // the call span has to begin at the callee
+        let start = callee.get_loc().start;
         let mut args: Vec<Expr> = vec![];
 
         if !self.is_at(TokenKind::CloseParen) {
@@ -752,9 +1747,10 @@ impl<'a> Parser<'a> {
             .map_err(|_| self.trigger_error(ParserErr::MissingCallCloseParen, true))?;
 
         Ok(Expr::Call(CallExpr {
+            id: NodeId::default(),
             callee: Box::new(callee),
             args,
-            loc: self.get_loc()
+            loc: Loc::new(start, self.at().loc.start),
         }))
     }
 
@@ -762,14 +1758,18 @@ impl<'a> Parser<'a> {
         match &self.eat()?.kind {
             TokenKind::Identifier | TokenKind::True | TokenKind::False | TokenKind::Null => {
                 Ok(Expr::Identifier(IdentifierExpr {
+                    id: NodeId::default(),
                     name: self.prev().value.clone(),
-                    loc: self.get_loc(),
+                    loc: self.prev().loc.clone(),
                 }))
             }
             TokenKind::Int => self.parse_int_literal(),
             TokenKind::Real => self.parse_real_literal(),
+            TokenKind::Imaginary => self.parse_imaginary_literal(),
             TokenKind::String => self.parse_str_literal(),
+            TokenKind::Char => self.parse_char_literal(),
             TokenKind::OpenParen => self.parse_grouping(),
+            TokenKind::OpenBracket => self.parse_list(),
             TokenKind::NewLine => Err(self.trigger_error(ParserErr::UnexpectedEol, false)),
             tk => {
                 match tk {
@@ -785,26 +1785,120 @@ impl<'a> Parser<'a> {
 
     fn parse_int_literal(&mut self) -> ParserExprRes {
         let tk = self.prev();
+        if tk.value == MIN_INT_MAGNITUDE {
+            let err = ParserErr::IntTooLarge(tk.value.to_string());
+            return Err(self.trigger_error(err, true));
+        }
+
         let value = tk
             .value
             .parse::<i64>()
             .map_err(|_| self.trigger_error(ParserErr::ParsingInt, true))?;
 
+        if self.is_at_unit() {
+            return self.parse_unit_literal(value as f64);
+        }
+
         Ok(Expr::IntLiteral(IntLiteralExpr {
+            id: NodeId::default(),
             value,
             loc: self.get_loc(),
         }))
     }
 
     fn parse_real_literal(&mut self) -> ParserExprRes {
-        let tk = self.prev();
-        let value = tk
-            .value
-            .parse::<f64>()
-            .map_err(|_| self.trigger_error(ParserErr::ParsingReal, true))?;
+        let value = parse_real(&self.prev().value)
+            .ok_or_else(|| self.trigger_error(ParserErr::ParsingReal, true))?;
+
+        if self.is_at_unit() {
+            return self.parse_unit_literal(value);
+        }
+
+        Ok(Expr::RealLiteral(RealLiteralExpr {
+            id: NodeId::default(),
+            value,
+            loc: self.get_loc(),
+        }))
+    }
+
+    fn parse_imaginary_literal(&mut self) -> ParserExprRes {
+        let value = parse_real(&self.prev().value)
+            .ok_or_else(|| self.trigger_error(ParserErr::ParsingReal, true))?;
+
+        Ok(Expr::ImaginaryLiteral(ImaginaryLiteralExpr {
+            id: NodeId::default(),
+            value,
+            loc: self.get_loc(),
+        }))
+    }
+
+    // Statements can follow each other on a line, in 'var t = 3 s = 4' the
+    // 's' starts an assignment. A symbol followed by what only continues a
+    // name isn't a unit
+    fn is_at_unit(&self) -> bool {
+        let continues_name = self.tokens.get(self.current + 1).is_some_and(|t| {
+            matches!(
+                t.kind,
+                TokenKind::Equal | TokenKind::OpenParen | TokenKind::OpenBracket | TokenKind::Dot
+            )
+        });
+
+        self.is_at(TokenKind::Identifier)
+            && UNIT_SYMBOLS.contains(&self.at().value.as_str())
+            && !continues_name
+    }
+
+    // Unit after a number, like '9.81 m/s^2'. Operators are part of the
+    // unit only when there is no space around them, '3 m / s' divides by
+    // the variable 's'
+    fn parse_unit_literal(&mut self, value: f64) -> ParserExprRes {
+        let mut unit: Vec<(EcoString, i32)> = vec![];
+        let mut sign = 1;
+
+        loop {
+            let after = self.prev().to_string();
+            let symbol = self
+                .expect(TokenKind::Identifier)
+                .map_err(|_| self.trigger_error(ParserErr::MissingUnitSymbol(after), true))?
+                .value;
+
+            let mut exp = 1;
+            if self.is_stuck_at(TokenKind::Caret) {
+                self.eat()?;
+
+                let negative = self.is_at(TokenKind::Minus);
+                if negative {
+                    self.eat()?;
+                }
+
+                exp = self
+                    .expect(TokenKind::Int)
+                    .ok()
+                    .and_then(|tk| tk.value.parse::<i32>().ok())
+                    .ok_or_else(|| self.trigger_error(ParserErr::WrongUnitExponent, true))?;
+
+                if negative {
+                    exp = -exp;
+                }
+            }
+
+            unit.push((symbol, sign * exp));
+
+            if self.is_stuck_at(TokenKind::Slash) {
+                sign = -1;
+            } else if self.is_stuck_at(TokenKind::Star) {
+                sign = 1;
+            } else {
+                break;
+            }
+
+            self.eat()?;
+        }
 
-        Ok(Expr::RealLiteral(RealLiteralExpr {
+        Ok(Expr::UnitLiteral(UnitLiteralExpr {
+            id: NodeId::default(),
             value,
+            unit,
             loc: self.get_loc(),
         }))
     }
@@ -813,11 +1907,23 @@ impl<'a> Parser<'a> {
         let tk = self.prev();
 
         Ok(Expr::StrLiteral(StrLiteralExpr {
+            id: NodeId::default(),
             value: tk.value.clone(),
             loc: self.get_loc(),
         }))
     }
 
+    // The lexer already checked that the token holds a single char
+    fn parse_char_literal(&self) -> ParserExprRes {
+        let tk = self.prev();
+
+        Ok(Expr::CharLiteral(CharLiteralExpr {
+            id: NodeId::default(),
+            value: tk.value.chars().next().unwrap(),
+            loc: self.get_loc(),
+        }))
+    }
+
     fn parse_grouping(&mut self) -> ParserExprRes {
         let expr = match self.parse_expr() {
             Ok(expr) => expr,
@@ -836,11 +1942,100 @@ impl<'a> Parser<'a> {
             .map_err(|_| PhyResult::new(ParserErr::ParenNeverClosed, Some(self.get_loc())))?;
 
         Ok(Expr::Grouping(GroupingExpr {
+            id: NodeId::default(),
             expr: Box::new(expr),
             loc: self.get_loc(),
         }))
     }
 
+    // '[value for name in source(..end)? (if cond)?]'
+    // A list literal, or a comprehension when its first item is followed
+    // by 'for'. The items may be on several lines, with a trailing comma
+    fn parse_list(&mut self) -> ParserExprRes {
+        let start = self.prev().loc.start;
+        let mut items = vec![];
+
+        self.skip_new_lines();
+
+        if !self.is_at(TokenKind::CloseBracket) {
+            loop {
+                items.push(self.parse_expr()?);
+                self.skip_new_lines();
+
+                if items.len() == 1 && self.is_at(TokenKind::For) {
+                    return self.parse_comprehension(start, items.remove(0));
+                }
+
+                if self.is_at(TokenKind::Comma) {
+                    let _ = self.eat();
+                    self.skip_new_lines();
+
+                    if self.is_at(TokenKind::CloseBracket) { break }
+                }
+                else if self.is_at(TokenKind::CloseBracket) || self.eof() {
+                    break
+                }
+                // The first item may be the value of a comprehension
+                else if items.len() == 1 {
+                    return Err(self.trigger_error(ParserErr::MissingForComprehension, true))
+                }
+                else {
+                    return Err(self.trigger_error(ParserErr::MissingListComma, true))
+                }
+            }
+        }
+
+        self.expect(TokenKind::CloseBracket)
+            .map_err(|_| self.trigger_error(ParserErr::ListNeverClosed, true))?;
+
+        Ok(Expr::List(ListExpr {
+            id: NodeId::default(),
+            items,
+            loc: Loc::new(start, self.prev().loc.end),
+        }))
+    }
+
+    fn parse_comprehension(&mut self, start: usize, value: Expr) -> ParserExprRes {
+        let value = Box::new(value);
+
+        self.expect(TokenKind::For)
+            .map_err(|_| self.trigger_error(ParserErr::MissingForComprehension, true))?;
+
+        let Token { value: name, loc: name_loc, .. } = self
+            .expect_loop_var(ParserErr::MissingVarNameComprehension)?;
+
+        self.expect(TokenKind::In)
+            .map_err(|_| self.trigger_error(ParserErr::MissingInComprehension, true))?;
+
+        let source = Box::new(self.parse_expr()?);
+
+        let mut end = None;
+        if self.is_at(TokenKind::DotDot) {
+            self.eat()?;
+            end = Some(Box::new(self.parse_expr()?));
+        }
+
+        let mut cond = None;
+        if self.is_at(TokenKind::If) {
+            self.eat()?;
+            cond = Some(Box::new(self.parse_expr()?));
+        }
+
+        self.expect(TokenKind::CloseBracket)
+            .map_err(|_| self.trigger_error(ParserErr::ComprehensionNeverClosed, true))?;
+
+        Ok(Expr::Comprehension(ComprehensionExpr {
+            id: NodeId::default(),
+            value,
+            name,
+            name_loc,
+            source,
+            end,
+            cond,
+            loc: Loc::new(start, self.prev().loc.end),
+        }))
+    }
+
     fn at(&self) -> &Token {
         self.tokens.get(self.current).unwrap()
     }
@@ -869,6 +2064,28 @@ impl<'a> Parser<'a> {
         }
     }
 
+    // Name of a declaration. Keywords are reserved, so 'var if' is reported
+    // on the keyword rather than as a missing name
+    fn expect_identifier(&mut self, err: ParserErr) -> Result<Token, PhyResParser> {
+        if self.at().kind.is_keyword() {
+            let Token { value, loc, .. } = self.eat()?.clone();
+            self.synchronize();
+
+            return Err(PhyResult::new(ParserErr::KeywordAsIdentifier(value.into()), Some(loc)));
+        }
+
+        self.expect(TokenKind::Identifier)
+            .map_err(|_| self.trigger_error(err, true))
+    }
+
+    // In 'for in l', the name is missing rather than being 'in'
+    fn expect_loop_var(&mut self, err: ParserErr) -> Result<Token, PhyResParser> {
+        match self.is_at(TokenKind::In) {
+            true => self.expect(TokenKind::Identifier).map_err(|_| self.trigger_error(err, true)),
+            false => self.expect_identifier(err),
+        }
+    }
+
     fn expect_and_skip(&mut self, kind: TokenKind) -> Result<(), PhyResParser> {
         self.expect(kind)?;
         self.skip_new_lines();
@@ -888,6 +2105,11 @@ impl<'a> Parser<'a> {
         self.at().kind == kind
     }
 
+    // Token kind with no space from the previous token
+    fn is_stuck_at(&self, kind: TokenKind) -> bool {
+        self.is_at(kind) && self.at().loc.start == self.prev().loc.end
+    }
+
     fn prev(&self) -> &Token {
         self.tokens.get(self.current - 1).unwrap()
     }
@@ -899,7 +2121,11 @@ impl<'a> Parser<'a> {
     fn skip_new_lines(&mut self) {
         // If we have a new line to begin a statement/expr parsing,
         // we skip it. There are important only in parsing steps
-        while !self.eof() && self.is_at(TokenKind::NewLine) {
+        while !self.eof() && (self.is_at(TokenKind::NewLine) || self.is_at(TokenKind::DocComment)) {
+            if self.is_at(TokenKind::DocComment) {
+                self.doc.push(self.at().value.clone());
+            }
+
             self.current += 1;
         }
 
@@ -963,7 +2189,6 @@ impl<'a> Parser<'a> {
                 //| TokenKind::For
                 //| TokenKind::If
                 //| TokenKind::While
-                //| TokenKind::Print
                 //| TokenKind::Return => return,
                 _ => {
                     let _ = self.eat();
@@ -980,7 +2205,9 @@ impl<'a> Parser<'a> {
 #[cfg(test)]
 mod tests {
     use tools::results::Loc;
-    use crate::parser::{ParserErr, utils::*};
+    use crate::ast::{expr::Expr, stmt::Stmt};
+    use crate::lexer::Lexer;
+    use crate::parser::{Parser, ParserErr, utils::*};
     use ecow::EcoString;
 
     #[test]
@@ -989,6 +2216,7 @@ mod tests {
 24.
 54.678
 \"foo bar! 5-{6}\"
+'c'
 (true)
 ( (null ))";
 
@@ -999,6 +2227,7 @@ mod tests {
             infos.get_str_values(),
             vec![EcoString::from("foo bar! 5-{6}")]
         );
+        assert_eq!(infos.get_char_values(), vec!['c']);
 
         assert_eq!(
             infos.get_grp_values()[0].get_ident_values(),
@@ -1110,6 +2339,14 @@ mod tests {
                 &ParserErr::MissingLhsInBinop,
             ]
         );
+
+        // The smallest int is a single literal, its magnitude isn't an int
+        let infos = get_expr_nodes_infos("-9223372036854775808");
+        assert_eq!(infos.get_int_values(), vec![&i64::MIN]);
+        assert!(infos.unary.is_empty());
+
+        let errs = lex_and_parse("1 - 9223372036854775808").err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::IntTooLarge("9223372036854775808".into()));
     }
 
     #[test]
@@ -1207,7 +2444,7 @@ foo_b4r = 65 % 6.";
         let code = "
 {
     var a = 3
-    print 8
+    print(8)
 }
 ";
         let infos = get_stmt_nodes_infos(code);
@@ -1222,7 +2459,9 @@ foo_b4r = 65 % 6.";
             block.var_decl[0].1.as_ref().unwrap().get_int_values()[0],
             &3
         );
-        assert_eq!(&block.print[0], &String::from("8"));
+        let print = &block.expr.call[0];
+        assert_eq!(print.callee.get_ident_values()[0], EcoString::from("print"));
+        assert_eq!(print.args[0].get_int_values()[0], &8);
 
         let code = "
 {
@@ -1352,7 +2591,7 @@ while a or true
 
 {
     a = 1
-    print a
+    print(a)
 }
 ";
         // 0
@@ -1370,8 +2609,8 @@ while a or true
         assert_eq!(body.name, EcoString::from("a"));
         assert_eq!(body.expr.get_int_values()[0], &1);
 
-        let print = &while_stmt.body.block[0].print[0];
-        assert_eq!(print, &String::from("a"));
+        let print = &while_stmt.body.block[0].expr.call[0];
+        assert_eq!(print.args[0].get_ident_values()[0], EcoString::from("a"));
 
         // Errors
         let code = "
@@ -1393,7 +2632,7 @@ for a in 5 {}
 for foo_b4r in 5..10
 
 {
-    print a
+    print(a)
 
 }
 ";
@@ -1403,27 +2642,51 @@ for foo_b4r in 5..10
         assert_eq!(for_stmt.placeholder, EcoString::from("a"));
         assert_eq!(
             for_stmt.range,
-            (5, None)
+            Some((5, None))
         );
 
         let for_stmt = &infos.for_stmt[1];
         assert_eq!(for_stmt.placeholder, EcoString::from("foo_b4r"));
         assert_eq!(
             for_stmt.range,
-            (5, Some(10))
+            Some((5, Some(10)))
         );
 
+        // Bounds and items that aren't literals, with several names
+        let code = "
+for i in a..n + 1 {}
+for x in [1, 2] {}
+for i, v in enumerate(l) {}
+for i in -2..2 {}
+";
+        let infos = get_stmt_nodes_infos(code);
+        let for_stmt = &infos.for_stmt[0];
+        assert!(for_stmt.range.is_none());
+        assert_eq!(for_stmt.range_exprs.len(), 2);
+        assert_eq!(for_stmt.range_exprs[1].binop.len(), 1);
+
+        let for_stmt = &infos.for_stmt[1];
+        assert_eq!(for_stmt.range_exprs[0].list.len(), 1);
+
+        let for_stmt = &infos.for_stmt[2];
+        assert_eq!(for_stmt.placeholder, EcoString::from("i"));
+        assert_eq!(for_stmt.rest, vec![EcoString::from("v")]);
+        assert_eq!(for_stmt.range_exprs[0].call.len(), 1);
+
+        assert_eq!(infos.for_stmt[3].range_exprs.len(), 2);
+
         // Errors
         let code = "
 for in 5 {}
 for a 5 {}
 for a in {}
-for a in 5 print a
+for a in 5 print(a)
 for a in ..5 {}
 for a in 5.. {}
 for a in -5 {}
 for a in 3.14 {}
 for a in 5..0 {}
+for a, in l {}
 ";
         // 0
         let errs = lex_and_parse(code).err().unwrap();
@@ -1437,6 +2700,7 @@ for a in 5..0 {}
         assert!(e[6] == &ParserErr::NegativeForRange, "{}", e[6]);
         assert!(e[7] == &ParserErr::NonIntForRange);
         assert!(e[8] == &ParserErr::LesserEndForRange);
+        assert!(e[9] == &ParserErr::MissingVarNameFor);
     }
 
     #[test]
@@ -1470,6 +2734,8 @@ c,
         assert_eq!(call.args[0].get_ident_values()[0], EcoString::from("a"));
         assert_eq!(call.args[1].get_ident_values()[0], EcoString::from("b"));
         assert_eq!(call.args[2].get_ident_values()[0], EcoString::from("c"));
+        // Trailing comma and new lines don't shrink the call span
+        assert_eq!(call.loc, Loc::new(20, 34));
 
         // Errors
         let code = "
@@ -1480,14 +2746,230 @@ foo(a b)
         assert!(e[0] == &ParserErr::MissingArgsComma);
     }
 
+    #[test]
+    fn imaginary_literal() {
+        let infos = get_expr_nodes_infos("3 + 4.5i");
+        let (lhs, op, rhs) = &infos.get_binop_values()[0];
+        assert_eq!(lhs.get_int_values(), vec![&3]);
+        assert_eq!(op, &EcoString::from("+"));
+        assert_eq!(rhs.imaginary[0].value, 4.5);
+    }
+
+    #[test]
+    fn unit_literal() {
+        let code = "
+3 m
+9.81 m/s^2
+2 kg*m^2/s^-1
+4 m / s
+";
+        let infos = get_stmt_nodes_infos(code);
+        let units = &infos.expr.unit;
+        assert_eq!(units[0].value, 3.);
+        assert_eq!(units[0].unit, vec![("m".into(), 1)]);
+        assert_eq!(units[1].value, 9.81);
+        assert_eq!(units[1].unit, vec![("m".into(), 1), ("s".into(), -2)]);
+        assert_eq!(
+            units[2].unit,
+            vec![("kg".into(), 1), ("m".into(), 2), ("s".into(), 1)]
+        );
+
+        // With spaces, it's a division
+        assert_eq!(infos.expr.binop[0].left.unit[0].unit, vec![("m".into(), 1)]);
+        assert_eq!(infos.expr.binop[0].right.get_ident_values()[0], EcoString::from("s"));
+
+        // Other names after a number start the next statement
+        let code = "
+var x = 10 var y = 5 print(x + y)
+while a > 1 { s = 3 print(s) }
+var t = 2 s = 4
+var u = 2 m s = 4
+";
+        let nodes = lex_and_parse(code).unwrap();
+        assert_eq!(nodes.len(), 8);
+        let value = |i: usize| match &nodes[i] {
+            Stmt::VarDecl(v) => v.value.clone(),
+            _ => None,
+        };
+        assert!(matches!(value(4), Some(Expr::IntLiteral(_))));
+        assert!(matches!(value(6), Some(Expr::UnitLiteral(_))));
+
+        // Errors
+        let code = "
+3 m/4
+3 m^x
+";
+        let errs = lex_and_parse(code).err().unwrap();
+        let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
+        assert_eq!(e[0], &ParserErr::MissingUnitSymbol("/".into()));
+        assert_eq!(e[1], &ParserErr::WrongUnitExponent);
+    }
+
+    #[test]
+    fn get() {
+        let code = "
+math.sqrt(4)
+a.b.c
+";
+        let infos = get_stmt_nodes_infos(code);
+        let call = &infos.expr.call[0];
+        let get = &call.callee.get[0];
+        assert_eq!(get.object.get_ident_values()[0], EcoString::from("math"));
+        assert_eq!(get.name, EcoString::from("sqrt"));
+        assert_eq!(get.loc, Loc::new(1, 10));
+
+        let get = &infos.expr.get[0];
+        assert_eq!(get.name, EcoString::from("c"));
+        assert_eq!(get.object.get[0].name, EcoString::from("b"));
+
+        // Errors
+        let code = "
+math.
+math.4
+";
+        let errs = lex_and_parse(code).err().unwrap();
+        let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
+        assert!(e[0] == &ParserErr::MissingMemberName);
+        assert!(e[1] == &ParserErr::MissingMemberName);
+    }
+
+    #[test]
+    fn index() {
+        let code = "
+a[-1]
+a[1..n][..2]
+f()[..]
+";
+        let infos = get_stmt_nodes_infos(code);
+        let index = &infos.expr.index[0];
+        assert_eq!(index.object.get_ident_values()[0], EcoString::from("a"));
+        assert!(!index.is_slice);
+        assert_eq!(index.start.as_ref().unwrap().unary.len(), 1);
+        assert_eq!(index.loc, Loc::new(1, 6));
+
+        let slice = &infos.expr.index[1];
+        assert!(slice.is_slice && slice.start.is_none());
+        assert_eq!(slice.end.as_ref().unwrap().get_int_values(), vec![&2]);
+        let inner = &slice.object.index[0];
+        assert_eq!(inner.start.as_ref().unwrap().get_int_values(), vec![&1]);
+        assert_eq!(inner.end.as_ref().unwrap().get_ident_values()[0], EcoString::from("n"));
+
+        let slice = &infos.expr.index[2];
+        assert!(slice.is_slice && slice.start.is_none() && slice.end.is_none());
+        assert_eq!(slice.object.call.len(), 1);
+
+        // Errors
+        let code = "
+a[]
+a[1
+";
+        let errs = lex_and_parse(code).err().unwrap();
+        let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
+        assert!(e[0] == &ParserErr::MissingIndex);
+        assert!(e[1] == &ParserErr::MissingCloseBracket);
+    }
+
+    #[test]
+    fn comprehension() {
+        let code = "
+[x * x for x in 0..10 if x % 2 == 0]
+[f(c) for c in s]
+";
+        let infos = get_stmt_nodes_infos(code);
+        let comp = &infos.expr.comprehension[0];
+        assert_eq!(comp.name, EcoString::from("x"));
+        assert_eq!(comp.value.binop.len(), 1);
+        assert_eq!(comp.source.get_int_values(), vec![&0]);
+        assert_eq!(comp.end.as_ref().unwrap().get_int_values(), vec![&10]);
+        assert_eq!(comp.cond.as_ref().unwrap().binop.len(), 1);
+        assert_eq!(comp.loc, Loc::new(1, 37));
+
+        let comp = &infos.expr.comprehension[1];
+        assert_eq!(comp.value.call.len(), 1);
+        assert_eq!(comp.source.get_ident_values()[0], EcoString::from("s"));
+        assert!(comp.end.is_none() && comp.cond.is_none());
+
+        // Errors
+        let code = "
+[x x in l]
+[x for in l]
+[x for x l]
+[x for x in l
+";
+        let errs = lex_and_parse(code).err().unwrap();
+        let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
+        assert!(e[0] == &ParserErr::MissingForComprehension);
+        assert!(e[1] == &ParserErr::MissingVarNameComprehension);
+        assert!(e[2] == &ParserErr::MissingInComprehension);
+        assert!(e[3] == &ParserErr::ComprehensionNeverClosed);
+    }
+
+    #[test]
+    fn list() {
+        let code = "
+[1, x + 2, [3]]
+[]
+[
+    f(a),
+    b,
+]
+";
+        let infos = get_stmt_nodes_infos(code);
+        let list = &infos.expr.list[0];
+        assert_eq!(list.items.len(), 3);
+        assert_eq!(list.items[0].get_int_values(), vec![&1]);
+        assert_eq!(list.items[1].binop.len(), 1);
+        assert_eq!(list.items[2].list[0].items.len(), 1);
+        assert_eq!(list.loc, Loc::new(1, 16));
+
+        assert!(infos.expr.list[1].items.is_empty());
+        assert_eq!(infos.expr.list[2].items.len(), 2);
+        assert_eq!(infos.expr.list[2].items[0].call.len(), 1);
+        assert_eq!(infos.expr.list[2].loc, Loc::new(20, 40));
+
+        // Errors
+        let code = "
+[1, ,]
+[1, 2 3]
+[1, 2
+";
+        let errs = lex_and_parse(code).err().unwrap();
+        let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
+        // Only one trailing comma
+        assert!(e[0] == &ParserErr::UnexpectedToken(",".into()));
+        assert!(e[1] == &ParserErr::MissingListComma);
+        assert!(e[2] == &ParserErr::ListNeverClosed);
+    }
+
+    #[test]
+    fn set() {
+        let code = "
+player.x = 5
+a.b.c = d = 1
+";
+        let infos = get_stmt_nodes_infos(code);
+        let set = &infos.expr.set[0];
+        assert_eq!(set.object.get_ident_values()[0], EcoString::from("player"));
+        assert_eq!(set.name, EcoString::from("x"));
+        assert_eq!(set.value.get_int_values(), vec![&5]);
+
+        let set = &infos.expr.set[1];
+        assert_eq!(set.object.get[0].name, EcoString::from("b"));
+        assert_eq!(set.value.get_assign_values()[0].0, EcoString::from("d"));
+    }
+
     #[test]
     fn fn_decl() {
         let code = "
 fn add() {}
 fn add(a, b,)
 {
-    print a
+    print(a)
 }
+fn sub(
+    a,
+    b,
+) {}
 ";
         // 0
         let infos = get_stmt_nodes_infos(code);
@@ -1501,15 +2983,19 @@ fn add(a, b,)
         assert_eq!(decl.name, EcoString::from("add"));
         assert_eq!(decl.params, vec![EcoString::from("a"), EcoString::from("b")]);
 
-        assert_eq!(decl.body[0].print[0], String::from("a"));
+        assert_eq!(decl.body[0].expr.call[0].args[0].get_ident_values()[0], EcoString::from("a"));
+
+        // 2
+        let decl = &infos.fn_decl[2];
+        assert_eq!(decl.params, vec![EcoString::from("a"), EcoString::from("b")]);
 
         // Errors
         let code = "
 fn (a, b) {}
 fn add a, b {}
 fn add(a b) {}
-fn add(a, b) print a
-fn add(1, a, b) print a
+fn add(a, b) print(a)
+fn add(1, a, b) print(a)
 ";
         let errs = lex_and_parse(code).err().unwrap();
         let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
@@ -1531,4 +3017,277 @@ return 4
         assert_eq!(&infos.return_stmt[0], &None);
         assert_eq!(&infos.return_stmt[1].as_ref().unwrap().get_int_values()[0], &&4);
     }
+    #[test]
+    fn attributes() {
+        let code = "
+@inline
+@deprecated(\"use add2\", 2)
+fn add(a, b) {}
+fn sub(a, b) {}
+";
+        let nodes = lex_and_parse(code).unwrap();
+        let Stmt::FnDecl(add) = &nodes[0] else { panic!("expected a function") };
+        assert_eq!(add.attributes.len(), 2);
+        assert_eq!(add.attributes[0].name, EcoString::from("inline"));
+        assert!(add.attributes[0].args.is_empty());
+        assert_eq!(add.get_attribute("deprecated").unwrap().args.len(), 2);
+
+        let Stmt::FnDecl(sub) = &nodes[1] else { panic!("expected a function") };
+        assert!(sub.attributes.is_empty());
+
+        // Errors
+        let code = "
+@inline
+var a = 1
+@
+fn add() {}
+";
+        let errs = lex_and_parse(code).err().unwrap();
+        let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
+        assert_eq!(e[0], &ParserErr::AttributeWithoutFn);
+        assert_eq!(e[1], &ParserErr::MissingAttributeName);
+    }
+
+    #[test]
+    fn type_annotations() {
+        let code = "
+var a: int? = null
+var b: str
+fn add(x: int, y, z: real?) -> real {}
+fn sub(x, y) {}
+fn apply(f: fn(int, str?) -> bool, g: fn()) -> fn(int) -> int {}
+fn first<T, U>(l: [T], other: [U?]?) -> T? {}
+";
+        let nodes = lex_and_parse(code).unwrap();
+        let Stmt::VarDecl(a) = &nodes[0] else { panic!("expected a variable") };
+        assert_eq!(a.ty.as_ref().unwrap().to_string(), "int?");
+
+        let Stmt::VarDecl(b) = &nodes[1] else { panic!("expected a variable") };
+        assert!(b.value.is_none() && b.ty.is_some());
+
+        let Stmt::FnDecl(add) = &nodes[2] else { panic!("expected a function") };
+        assert_eq!(add.signature(), "fn add(x: int, y, z: real?) -> real");
+
+        let Stmt::FnDecl(sub) = &nodes[3] else { panic!("expected a function") };
+        assert!(sub.param_types.iter().all(|t| t.is_none()) && sub.return_type.is_none());
+
+        let Stmt::FnDecl(apply) = &nodes[4] else { panic!("expected a function") };
+        assert_eq!(apply.signature(), "fn apply(f: fn(int, str?) -> bool, g: fn()) -> fn(int) -> int");
+
+        let Stmt::FnDecl(first) = &nodes[5] else { panic!("expected a function") };
+        assert_eq!(first.signature(), "fn first<T, U>(l: [T], other: [U?]?) -> T?");
+
+        // Errors
+        let code = "
+var a: = 1
+fn add(x:) {}
+fn sub() -> {}
+var f: fn int
+var l: [int
+fn g<>(x) {}
+fn h<T(x) {}
+";
+        let errs = lex_and_parse(code).err().unwrap();
+        let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
+        assert_eq!(e[0], &ParserErr::MissingType(":".into()));
+        assert_eq!(e[1], &ParserErr::MissingType(":".into()));
+        assert_eq!(e[2], &ParserErr::MissingType("->".into()));
+        assert_eq!(e[3], &ParserErr::MissingFnTypeParen);
+        assert_eq!(e[4], &ParserErr::MissingListTypeBracket);
+        assert_eq!(e[5], &ParserErr::WrongTypeParams);
+        assert_eq!(e[6], &ParserErr::WrongTypeParams);
+    }
+
+    #[test]
+    fn contracts() {
+        let code = "
+fn div(a, b) -> int
+    requires b != 0
+    requires a >= 0
+    ensures result >= 0
+{
+    return a / b
+}
+fn id(x) ensures result == x { return x }
+var requires = 1
+";
+        let nodes = lex_and_parse(code).unwrap();
+        let Stmt::FnDecl(div) = &nodes[0] else { panic!("expected a function") };
+        assert_eq!(div.requires.len(), 2);
+        assert_eq!(div.ensures.len(), 1);
+
+        let Stmt::FnDecl(id) = &nodes[1] else { panic!("expected a function") };
+        assert!(id.requires.is_empty() && id.ensures.len() == 1);
+
+        let errs = lex_and_parse("fn f(x) requires { return x }").err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::MissingContractCond);
+    }
+
+    #[test]
+    fn tasks() {
+        let nodes = lex_and_parse("var t = spawn m.f(1, 2)\nawait t").unwrap();
+        let Stmt::VarDecl(t) = &nodes[0] else { panic!("expected a variable") };
+        assert!(matches!(t.value.as_ref().unwrap(), Expr::Spawn(s) if matches!(*s.call, Expr::Call(_))));
+
+        let Stmt::Expr(e) = &nodes[1] else { panic!("expected an expression") };
+        assert!(matches!(&e.expr, Expr::Await(_)));
+
+        let errs = lex_and_parse("spawn f").err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::SpawnWithoutCall);
+    }
+
+    #[test]
+    fn actors() {
+        let code = "
+actor Counter(start) {
+    var count = start
+    on msg { count = count + msg }
+}
+var on = 1
+";
+        let nodes = lex_and_parse(code).unwrap();
+        let Stmt::FnDecl(counter) = &nodes[0] else { panic!("expected a function") };
+        assert_eq!(counter.params.as_slice(), ["start"]);
+
+        // State, handler and the instance returned
+        let [Stmt::VarDecl(_), Stmt::FnDecl(handler), Stmt::Return(ret)] = counter.body.as_slice() else {
+            panic!("expected the state, the handler and a return")
+        };
+        assert_eq!(handler.name, "on#");
+        assert_eq!(handler.params.as_slice(), ["msg"]);
+        assert!(matches!(&ret.value, Some(Expr::Call(c)) if c.args.len() == 2));
+
+        let errs = lex_and_parse("actor A() { var x = 1 }").err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::MissingActorHandler("A".into()));
+
+        let errs = lex_and_parse("actor A() { on m {}\n on n {} }").err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::DuplicateActorHandler);
+
+        let errs = lex_and_parse("actor () { on m {} }").err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::MissingActorName);
+    }
+
+    #[test]
+    fn keyword_as_identifier() {
+        for (code, keyword, loc) in [
+            ("var if = 1", "if", Loc::new(4, 6)),
+            ("fn while() {}", "while", Loc::new(3, 8)),
+            ("fn f(a, in) {}", "in", Loc::new(8, 10)),
+            ("for null in 0..2 {}", "null", Loc::new(4, 8)),
+            ("actor return { on m {} }", "return", Loc::new(6, 12)),
+        ] {
+            let errs = lex_and_parse(code).err().unwrap();
+            assert_eq!(errs[0].err, ParserErr::KeywordAsIdentifier(keyword.into()));
+            assert_eq!(errs[0].loc, Some(loc));
+        }
+
+        // Names that only start like a keyword are fine
+        assert!(lex_and_parse("var iffy = 1").is_ok());
+    }
+
+    #[test]
+    fn custom_operators() {
+        let code = "
+operator <+> (a, b) { return a }
+operator <^> prec 7 (a, b) { return a }
+1 <+> 2 * 3 <^> 4
+";
+        let nodes = lex_and_parse(code).unwrap();
+        assert!(matches!(&nodes[0], Stmt::FnDecl(f) if f.name == "<+>"));
+
+        // Calls of the operator functions, with their precedence
+        let Stmt::Expr(e) = &nodes[2] else { panic!("expected an expression") };
+        assert_eq!(e.expr.to_string(), "<+>(1, 2 * <^>(3, 4))");
+        assert_eq!(e.expr.get_loc(), Loc::new(74, 91));
+
+        let errs = lex_and_parse("1 <+> 2").err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::UnknownOperator("<+>".into()));
+
+        let errs = lex_and_parse("operator <+> prec 1 (a, b) {}").err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::WrongPrecedence("1".into()));
+
+        let errs = lex_and_parse("operator <+> (a) {}").err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::OperatorArity("<+>".into(), 1));
+
+        let errs = lex_and_parse("operator add (a, b) {}").err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::MissingOperatorSymbol);
+
+        let errs = lex_and_parse("{\noperator <+> (a, b) {}\n}").err().unwrap();
+        assert_eq!(errs[0].err, ParserErr::OperatorNotTopLevel);
+    }
+
+    #[test]
+    fn file_attributes() {
+        let code = "
+@!allow(unused_variable)
+@!deny(empty_block)
+@inline
+fn add(a, b) {}
+";
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(code).unwrap();
+        let mut parser = Parser::default();
+        let nodes = parser.parse(tokens).unwrap();
+
+        let names = parser.file_attributes().iter().map(|a| a.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["allow", "deny"]);
+        assert_eq!(parser.file_attributes()[1].args.len(), 1);
+
+        let Stmt::FnDecl(add) = &nodes[0] else { panic!("expected a function") };
+        assert_eq!(add.attributes.len(), 1);
+
+        // Errors
+        let code = "
+fn add(a, b) { @!allow(empty_block) }
+@inline
+@!deny(empty_block)
+fn sub() {}
+";
+        let errs = lex_and_parse(code).err().unwrap();
+        let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
+        assert_eq!(e[0], &ParserErr::FileAttributeNotTopLevel);
+        assert_eq!(e[1], &ParserErr::FileAttributeNotTopLevel);
+    }
+
+    #[test]
+    fn modules() {
+        let code = "
+import std.list
+import utils
+@inline
+pub fn add(a, b) {}
+pub var a = 1
+var b = 2
+";
+        let nodes = lex_and_parse(code).unwrap();
+        let Stmt::Import(list) = &nodes[0] else { panic!("expected an import") };
+        assert_eq!(list.path, [EcoString::from("std"), EcoString::from("list")]);
+        assert_eq!(list.name(), "list");
+
+        let Stmt::FnDecl(add) = &nodes[2] else { panic!("expected a function") };
+        assert!(add.is_pub && add.get_attribute("inline").is_some());
+
+        let Stmt::VarDecl(a) = &nodes[3] else { panic!("expected a variable") };
+        let Stmt::VarDecl(b) = &nodes[4] else { panic!("expected a variable") };
+        assert!(a.is_pub && !b.is_pub);
+
+        // Errors
+        let code = "
+import
+pub a = 1
+fn f() { import utils }
+fn g() { pub var a = 1 }
+";
+        let errs = lex_and_parse(code).err().unwrap();
+        let e = errs.iter().map(|e| &e.err).collect::<Vec<&ParserErr>>();
+        assert_eq!(
+            e,
+            [
+                &ParserErr::MissingImportPath,
+                &ParserErr::PubWithoutDecl,
+                &ParserErr::ImportNotTopLevel,
+                &ParserErr::PubNotTopLevel,
+            ]
+        );
+    }
 }
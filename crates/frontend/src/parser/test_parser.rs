@@ -2,21 +2,25 @@ use ecow::EcoString;
 
 use crate::ast::{
     expr::{
-        AssignExpr, BinaryExpr, CallExpr, GroupingExpr, IdentifierExpr, IntLiteralExpr,
-        LogicalExpr, RealLiteralExpr, StrLiteralExpr, UnaryExpr, VisitExpr,
+        AssignExpr, AwaitExpr, BinaryExpr, CallExpr, CharLiteralExpr, ComprehensionExpr, GetExpr,
+        GroupingExpr, IdentifierExpr, ImaginaryLiteralExpr, IndexExpr, IntLiteralExpr, ListExpr,
+        LogicalExpr, RealLiteralExpr, SetExpr, SpawnExpr, StrLiteralExpr, UnaryExpr,
+        UnitLiteralExpr, VisitExpr,
     },
     stmt::{
-        BlockStmt, ExprStmt, FnDeclStmt, ForStmt, IfStmt, PrintStmt, ReturnStmt, Stmt, VarDeclStmt, VisitStmt, WhileStmt
+        BlockStmt, ExprStmt, FnDeclStmt, ForRange, ForStmt, IfStmt, ImportStmt, ReturnStmt, Stmt,
+        VarDeclStmt, VisitStmt, WhileStmt,
     },
 };
 
+use tools::messages::Locale;
 use tools::results::{PhyReport, PhyResult, Loc};
 
 #[derive(Debug)]
 pub enum ParserTestErr {}
 
 impl PhyReport for ParserTestErr {
-    fn get_err_msg(&self) -> String {
+    fn get_err_msg(&self, _: &dyn Locale) -> String {
         String::from("")
     }
 }
@@ -29,7 +33,6 @@ type PhyResParserTestErr = PhyResult<ParserTestErr>;
 #[derive(Default, Debug, PartialEq, Clone)]
 pub struct StmtInfos {
     pub expr: ExprInfos,
-    pub print: Vec<String>,
     pub var_decl: Vec<(EcoString, Option<ExprInfos>)>,
     pub block: Vec<StmtInfos>,
     pub if_stmt: Vec<IfInfos>,
@@ -37,6 +40,7 @@ pub struct StmtInfos {
     pub for_stmt: Vec<ForInfos>,
     pub fn_decl: Vec<FnDeclInfos>,
     pub return_stmt: Vec<Option<ExprInfos>>,
+    pub import: Vec<Vec<EcoString>>,
 }
 
 #[derive(Clone, Debug, PartialEq, Default)]
@@ -55,7 +59,11 @@ pub struct WhileInfos {
 #[derive(Clone, Debug, PartialEq, Default)]
 pub struct ForInfos {
     pub placeholder: EcoString,
-    pub range: (i64, Option<i64>),
+    pub rest: Vec<EcoString>,
+    // Literal range
+    pub range: Option<(i64, Option<i64>)>,
+    // Bounds or items
+    pub range_exprs: Vec<ExprInfos>,
     pub body: StmtInfos,
 }
 
@@ -69,7 +77,6 @@ pub struct FnDeclInfos {
 impl StmtInfos {
     fn concat(&mut self, other: &mut StmtInfos) {
         self.expr.concat(&mut other.expr);
-        self.print.append(&mut other.print);
         self.var_decl.append(&mut other.var_decl);
         self.block.append(&mut other.block);
         self.if_stmt.append(&mut other.if_stmt);
@@ -77,6 +84,7 @@ impl StmtInfos {
         self.for_stmt.append(&mut other.for_stmt);
         self.fn_decl.append(&mut other.fn_decl);
         self.return_stmt.append(&mut other.return_stmt);
+        self.import.append(&mut other.import);
     }
 }
 
@@ -89,24 +97,6 @@ impl VisitStmt<StmtInfos, ParserTestErr> for TestParser {
         })
     }
 
-    fn visit_print_stmt(&mut self, stmt: &PrintStmt) -> Result<StmtInfos, PhyResult<ParserTestErr>> {
-        let expr = stmt.expr.accept(self)?;
-        let mut infos = StmtInfos::default();
-
-        // Only one field cannot be empty
-        if let Some(v) = expr.get_int_values().first() {
-            infos.print = vec![format!("{}", v)];
-        } else if let Some(v) = expr.get_real_values().first() {
-            infos.print = vec![format!("{}", v)];
-        } else if let Some(v) = expr.get_str_values().first() {
-            infos.print = vec![format!("{}", v)];
-        } else if let Some(v) = expr.get_ident_values().first() {
-            infos.print = vec![format!("{}", v)];
-        }
-
-        Ok(infos)
-    }
-
     fn visit_var_decl_stmt(
         &mut self,
         stmt: &VarDeclStmt,
@@ -172,13 +162,20 @@ impl VisitStmt<StmtInfos, ParserTestErr> for TestParser {
 
     fn visit_for_stmt(&mut self, stmt: &ForStmt) -> Result<StmtInfos, PhyResult<ParserTestErr>> {
         let placeholder = stmt.placeholder.name.clone();
-        let range = (stmt.range.start, stmt.range.end);
+        let rest = stmt.rest.iter().map(|p| p.name.clone()).collect();
+        let range = match stmt.range {
+            ForRange::Ints(start, end) => Some((start, end)),
+            _ => None,
+        };
+        let range_exprs = stmt.range.exprs().map(|e| e.accept(self)).collect::<Result<_, _>>()?;
         let body = stmt.body.accept(self)?;
 
         Ok(StmtInfos {
             for_stmt: vec![ForInfos {
                 placeholder,
+                rest,
                 range,
+                range_exprs,
                 body,
             }],
             ..Default::default()
@@ -211,13 +208,20 @@ impl VisitStmt<StmtInfos, ParserTestErr> for TestParser {
 
         Ok(StmtInfos { return_stmt: vec![value], ..Default::default() })
     }
+
+    fn visit_import_stmt(&mut self, stmt: &ImportStmt) -> Result<StmtInfos, PhyResult<ParserTestErr>> {
+        Ok(StmtInfos { import: vec![stmt.path.clone()], ..Default::default() })
+    }
 }
 
 #[derive(Default, Debug, PartialEq, Clone)]
 pub struct ExprInfos {
     pub int: Vec<IntInfo>,
     pub real: Vec<RealInfo>,
+    pub imaginary: Vec<RealInfo>,
     pub str: Vec<StrInfo>,
+    pub char: Vec<CharInfo>,
+    pub unit: Vec<UnitInfo>,
     pub bool: Vec<BoolInfo>,
     pub binop: Vec<BinopInfo>,
     pub grouping: Vec<GroupingInfo>,
@@ -226,6 +230,11 @@ pub struct ExprInfos {
     pub assign: Vec<AssignInfo>,
     pub logical: Vec<LogicalInfo>,
     pub call: Vec<CallInfo>,
+    pub get: Vec<GetInfo>,
+    pub set: Vec<SetInfo>,
+    pub index: Vec<IndexInfo>,
+    pub list: Vec<ListInfo>,
+    pub comprehension: Vec<ComprehensionInfo>,
 }
 
 impl ExprInfos {
@@ -241,6 +250,10 @@ impl ExprInfos {
         self.str.iter().map(|i| i.value.clone()).collect()
     }
 
+    pub fn get_char_values(&self) -> Vec<char> {
+        self.char.iter().map(|i| i.value).collect()
+    }
+
     pub fn get_grp_values(&self) -> Vec<&ExprInfos> {
         self.grouping.iter().map(|g| &g.expr).collect()
     }
@@ -277,13 +290,21 @@ impl ExprInfos {
     fn concat(&mut self, other: &mut ExprInfos) {
         self.int.append(&mut other.int);
         self.real.append(&mut other.real);
+        self.imaginary.append(&mut other.imaginary);
         self.str.append(&mut other.str);
+        self.char.append(&mut other.char);
+        self.unit.append(&mut other.unit);
         self.binop.append(&mut other.binop);
         self.grouping.append(&mut other.grouping);
         self.unary.append(&mut other.unary);
         self.assign.append(&mut other.assign);
         self.logical.append(&mut other.logical);
         self.call.append(&mut other.call);
+        self.get.append(&mut other.get);
+        self.set.append(&mut other.set);
+        self.index.append(&mut other.index);
+        self.list.append(&mut other.list);
+        self.comprehension.append(&mut other.comprehension);
     }
 }
 
@@ -313,6 +334,19 @@ pub struct StrInfo {
     pub loc: Loc,
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct CharInfo {
+    pub value: char,
+    pub loc: Loc,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct UnitInfo {
+    pub value: f64,
+    pub unit: Vec<(EcoString, i32)>,
+    pub loc: Loc,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct BoolInfo {
     pub value: bool,
@@ -359,6 +393,46 @@ pub struct CallInfo {
     pub loc: Loc,
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct GetInfo {
+    pub object: ExprInfos,
+    pub name: EcoString,
+    pub loc: Loc,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SetInfo {
+    pub object: ExprInfos,
+    pub name: EcoString,
+    pub value: ExprInfos,
+    pub loc: Loc,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct IndexInfo {
+    pub object: ExprInfos,
+    pub start: Option<ExprInfos>,
+    pub end: Option<ExprInfos>,
+    pub is_slice: bool,
+    pub loc: Loc,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ListInfo {
+    pub items: Vec<ExprInfos>,
+    pub loc: Loc,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ComprehensionInfo {
+    pub value: ExprInfos,
+    pub name: EcoString,
+    pub source: ExprInfos,
+    pub end: Option<ExprInfos>,
+    pub cond: Option<ExprInfos>,
+    pub loc: Loc,
+}
+
 #[derive(Default, Debug, PartialEq, Clone)]
 pub struct TestParser {
     pub infos: StmtInfos,
@@ -428,6 +502,33 @@ impl VisitExpr<ExprInfos, ParserTestErr> for TestParser {
         Ok(infos)
     }
 
+    fn visit_unit_literal_expr(
+        &mut self,
+        expr: &UnitLiteralExpr,
+    ) -> Result<ExprInfos, PhyResParserTestErr> {
+        let mut infos = ExprInfos::default();
+        infos.unit.push(UnitInfo {
+            value: expr.value,
+            unit: expr.unit.clone(),
+            loc: expr.loc.clone(),
+        });
+
+        Ok(infos)
+    }
+
+    fn visit_imaginary_literal_expr(
+        &mut self,
+        expr: &ImaginaryLiteralExpr,
+    ) -> Result<ExprInfos, PhyResParserTestErr> {
+        let mut infos = ExprInfos::default();
+        infos.imaginary.push(RealInfo {
+            value: expr.value,
+            loc: expr.loc.clone(),
+        });
+
+        Ok(infos)
+    }
+
     fn visit_str_literal_expr(
         &mut self,
         expr: &StrLiteralExpr,
@@ -442,6 +543,19 @@ impl VisitExpr<ExprInfos, ParserTestErr> for TestParser {
         Ok(infos)
     }
 
+    fn visit_char_literal_expr(
+        &mut self,
+        expr: &CharLiteralExpr,
+    ) -> Result<ExprInfos, PhyResParserTestErr> {
+        let mut infos = ExprInfos::default();
+        infos.char.push(CharInfo {
+            value: expr.value,
+            loc: expr.loc.clone(),
+        });
+
+        Ok(infos)
+    }
+
     fn visit_identifier_expr(
         &mut self,
         expr: &IdentifierExpr,
@@ -511,4 +625,80 @@ impl VisitExpr<ExprInfos, ParserTestErr> for TestParser {
 
         Ok(infos)
     }
+
+    fn visit_get_expr(&mut self, expr: &GetExpr) -> Result<ExprInfos, PhyResult<ParserTestErr>> {
+        let mut infos = ExprInfos::default();
+
+        infos.get.push(GetInfo {
+            object: expr.object.accept(self)?,
+            name: expr.name.clone(),
+            loc: expr.loc.clone(),
+        });
+
+        Ok(infos)
+    }
+
+    fn visit_set_expr(&mut self, expr: &SetExpr) -> Result<ExprInfos, PhyResult<ParserTestErr>> {
+        let mut infos = ExprInfos::default();
+
+        infos.set.push(SetInfo {
+            object: expr.object.accept(self)?,
+            name: expr.name.clone(),
+            value: expr.value.accept(self)?,
+            loc: expr.loc.clone(),
+        });
+
+        Ok(infos)
+    }
+
+    fn visit_index_expr(&mut self, expr: &IndexExpr) -> Result<ExprInfos, PhyResult<ParserTestErr>> {
+        let mut infos = ExprInfos::default();
+
+        infos.index.push(IndexInfo {
+            object: expr.object.accept(self)?,
+            start: expr.start.as_ref().map(|s| s.accept(self)).transpose()?,
+            end: expr.end.as_ref().map(|e| e.accept(self)).transpose()?,
+            is_slice: expr.is_slice,
+            loc: expr.loc.clone(),
+        });
+
+        Ok(infos)
+    }
+
+    fn visit_list_expr(&mut self, expr: &ListExpr) -> Result<ExprInfos, PhyResult<ParserTestErr>> {
+        let mut infos = ExprInfos::default();
+
+        infos.list.push(ListInfo {
+            items: expr.items.iter().map(|i| i.accept(self)).collect::<Result<_, _>>()?,
+            loc: expr.loc.clone(),
+        });
+
+        Ok(infos)
+    }
+
+    fn visit_comprehension_expr(
+        &mut self,
+        expr: &ComprehensionExpr,
+    ) -> Result<ExprInfos, PhyResult<ParserTestErr>> {
+        let mut infos = ExprInfos::default();
+
+        infos.comprehension.push(ComprehensionInfo {
+            value: expr.value.accept(self)?,
+            name: expr.name.clone(),
+            source: expr.source.accept(self)?,
+            end: expr.end.as_ref().map(|e| e.accept(self)).transpose()?,
+            cond: expr.cond.as_ref().map(|c| c.accept(self)).transpose()?,
+            loc: expr.loc.clone(),
+        });
+
+        Ok(infos)
+    }
+
+    fn visit_spawn_expr(&mut self, expr: &SpawnExpr) -> Result<ExprInfos, PhyResult<ParserTestErr>> {
+        expr.call.accept(self)
+    }
+
+    fn visit_await_expr(&mut self, expr: &AwaitExpr) -> Result<ExprInfos, PhyResult<ParserTestErr>> {
+        expr.task.accept(self)
+    }
 }
@@ -1,5 +1,8 @@
 use crate::{
-    ast::stmt::Stmt,
+    ast::{
+        ast_pretty_print::{AstFormat, AstPrinter},
+        stmt::Stmt,
+    },
     lexer::Lexer,
     parser::{Parser, PhyResParser},
 };
@@ -30,3 +33,18 @@ pub fn get_expr_nodes_infos(code: &str) -> ExprInfos {
     let mut test_parser = TestParser::default();
     test_parser.get_all_infos(&nodes).unwrap().expr.clone()
 }
+
+// Each statement as a s-expression, like (+ 1 (* 2 3))
+pub fn print_sexpr(code: &str) -> Vec<String> {
+    print_nodes(code, AstFormat::SExpr)
+}
+
+pub fn print_tree(code: &str) -> Vec<String> {
+    print_nodes(code, AstFormat::Tree)
+}
+
+fn print_nodes(code: &str, format: AstFormat) -> Vec<String> {
+    let nodes = lex_and_parse(code).unwrap();
+    let mut printer = AstPrinter::new(format);
+    nodes.iter().map(|n| printer.print(n).unwrap()).collect()
+}
@@ -0,0 +1,197 @@
+use colored::Colorize;
+use ecow::EcoString;
+use thiserror::Error;
+use tools::{
+    messages::{localize, text, Arg, Locale, Translate},
+    results::{Loc, PhyReport, PhyResult},
+};
+
+use crate::ast::{expr::Expr, stmt::Stmt};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum StrictErr {
+    #[error("variable '{0}' must be initialized")]
+    MissingInitializer(EcoString),
+
+    #[error("'{0}' can't be assigned null")]
+    NullAssignment(EcoString),
+}
+
+impl PhyReport for StrictErr {
+    fn get_err_msg(&self, locale: &dyn Locale) -> String {
+        format!(
+            "{} {}",
+            text(locale, "label.strict", "Strict error:").red(),
+            localize(locale, self)
+        )
+    }
+}
+
+impl Translate for StrictErr {
+    fn category(&self) -> &'static str {
+        "strict"
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            StrictErr::MissingInitializer(..) => "MissingInitializer",
+            StrictErr::NullAssignment(..) => "NullAssignment",
+        }
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        match self {
+            StrictErr::MissingInitializer(v) | StrictErr::NullAssignment(v) => vec![Arg::text(v)],
+        }
+    }
+}
+
+pub type PhyResStrict = PhyResult<StrictErr>;
+
+// Strict mode, enabled with '--strict' or '@!strict' in the file. Every
+// variable is declared with a value and null is never assigned to one,
+// so reading an uninitialized value can't happen at runtime
+pub fn check_strict(stmts: &[Stmt]) -> Vec<PhyResStrict> {
+    let mut errs = vec![];
+    stmts.iter().for_each(|s| check_stmt(s, &mut errs));
+
+    errs
+}
+
+fn check_stmt(stmt: &Stmt, errs: &mut Vec<PhyResStrict>) {
+    match stmt {
+        Stmt::VarDecl(s) => match &s.value {
+            Some(v) => {
+                check_value(&s.name, v, &s.name_loc, errs);
+                check_expr(v, errs);
+            }
+            None => errs.push(PhyResult::new(
+                StrictErr::MissingInitializer(s.name.clone()),
+                Some(s.name_loc.clone()),
+            )),
+        },
+        Stmt::Expr(s) => check_expr(&s.expr, errs),
+        Stmt::Return(s) => {
+            if let Some(v) = &s.value {
+                check_expr(v, errs);
+            }
+        }
+        Stmt::Block(s) => s.stmts.iter().for_each(|s| check_stmt(s, errs)),
+        Stmt::If(s) => {
+            check_expr(&s.condition, errs);
+            [&s.then_branch, &s.else_branch]
+                .into_iter()
+                .flatten()
+                .for_each(|b| check_stmt(b, errs));
+        }
+        Stmt::While(s) => {
+            check_expr(&s.condition, errs);
+            check_stmt(&s.body, errs);
+        }
+        // The placeholders are declared with the range values
+        Stmt::For(s) => {
+            s.range.exprs().for_each(|e| check_expr(e, errs));
+            check_stmt(&s.body, errs);
+        }
+        Stmt::FnDecl(s) => s.body.iter().for_each(|s| check_stmt(s, errs)),
+        Stmt::Import(_) => {}
+    }
+}
+
+fn check_expr(expr: &Expr, errs: &mut Vec<PhyResStrict>) {
+    match expr {
+        Expr::Assign(e) => {
+            check_value(&e.name, &e.value, &e.name_loc, errs);
+            check_expr(&e.value, errs);
+        }
+        Expr::Set(e) => {
+            check_value(&e.name, &e.value, &e.loc, errs);
+            check_expr(&e.object, errs);
+            check_expr(&e.value, errs);
+        }
+        Expr::Binary(e) => {
+            check_expr(&e.left, errs);
+            check_expr(&e.right, errs);
+        }
+        Expr::Logical(e) => {
+            check_expr(&e.left, errs);
+            check_expr(&e.right, errs);
+        }
+        Expr::Grouping(e) => check_expr(&e.expr, errs),
+        Expr::Unary(e) => check_expr(&e.right, errs),
+        Expr::Call(e) => {
+            check_expr(&e.callee, errs);
+            e.args.iter().for_each(|a| check_expr(a, errs));
+        }
+        Expr::Get(e) => check_expr(&e.object, errs),
+        Expr::Index(e) => {
+            check_expr(&e.object, errs);
+            e.bounds().for_each(|b| check_expr(b, errs));
+        }
+        Expr::List(e) => e.items.iter().for_each(|i| check_expr(i, errs)),
+        Expr::Comprehension(e) => e.sub_exprs().for_each(|s| check_expr(s, errs)),
+        Expr::Spawn(e) => check_expr(&e.call, errs),
+        Expr::Await(e) => check_expr(&e.task, errs),
+        Expr::IntLiteral(_)
+        | Expr::RealLiteral(_)
+        | Expr::ImaginaryLiteral(_)
+        | Expr::StrLiteral(_)
+        | Expr::CharLiteral(_)
+        | Expr::UnitLiteral(_)
+        | Expr::Identifier(_) => {}
+    }
+}
+
+fn check_value(name: &EcoString, value: &Expr, loc: &Loc, errs: &mut Vec<PhyResStrict>) {
+    if is_null(value) {
+        errs.push(PhyResult::new(StrictErr::NullAssignment(name.clone()), Some(loc.clone())));
+    }
+}
+
+fn is_null(expr: &Expr) -> bool {
+    match expr {
+        Expr::Identifier(e) => e.name == "null",
+        Expr::Grouping(e) => is_null(&e.expr),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::utils::lex_and_parse;
+
+    use super::{check_strict, StrictErr};
+
+    #[test]
+    fn strict() {
+        let code = "
+var a
+var b = null
+var c = 1
+c = (null)
+fn f(x) {
+    var d
+    x.field = null
+    return c = null
+}
+for i in 0..2 { c = i }
+";
+        let nodes = lex_and_parse(code).unwrap();
+        let errs = check_strict(&nodes).into_iter().map(|e| e.err).collect::<Vec<_>>();
+
+        assert_eq!(
+            errs,
+            [
+                StrictErr::MissingInitializer("a".into()),
+                StrictErr::NullAssignment("b".into()),
+                StrictErr::NullAssignment("c".into()),
+                StrictErr::MissingInitializer("d".into()),
+                StrictErr::NullAssignment("field".into()),
+                StrictErr::NullAssignment("c".into()),
+            ]
+        );
+
+        let nodes = lex_and_parse("var a = 1\nfn f(x) { return x }\na = f(2)").unwrap();
+        assert!(check_strict(&nodes).is_empty());
+    }
+}
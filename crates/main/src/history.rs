@@ -0,0 +1,279 @@
+use ecow::EcoString;
+
+use frontend::{
+    ast::stmt::Stmt,
+    lexer::{Lexer, Token, TokenKind},
+};
+use tools::source_map::SourceMap;
+
+// File attribute letting a script define a function again, like the
+// interactive mode does
+pub const REDEFINE_ATTRIBUTE: &str = "redefine";
+
+// Inputs of the interactive session that ran without errors, saved by
+// ':save' as a script that gives back the same globals when it is run:
+//  - The inputs keep their order. A function defined again is declared a
+//    second time, after the inputs that used the first definition. The
+//    file then starts with '@!redefine'
+//  - The results bound to '_1', '_2'... and '_' are declared with the
+//    expression that gave them, so that the next inputs can use them.
+//    Results that don't come from an expression can't be, the inputs
+//    using them are left out
+#[derive(Default)]
+pub struct History {
+    // Top level statements of the inputs, with the comments and the
+    // attributes above them
+    chunks: Vec<String>,
+    // If each result is declared in the chunks, the first one at 0
+    results: Vec<bool>,
+    // Functions declared by the chunks, to know if one is defined again
+    fns: Vec<EcoString>,
+    redefines: bool,
+}
+
+impl History {
+    // The nodes are the ones parsed from the code, their locations are
+    // read in the sources. The result is its number when it was bound
+    pub fn push(
+        &mut self,
+        code: &str,
+        nodes: &[Stmt],
+        sources: &SourceMap,
+        result: Option<usize>,
+    ) {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(code).map(|t| t.as_slice()).unwrap_or_default();
+
+        if self.uses_unbound_result(tokens) {
+            self.results.extend(result.map(|_| false));
+            return;
+        }
+
+        let chars: Vec<char> = code.chars().collect();
+        let text = |start: usize, end: usize| chars[start.min(end)..end].iter().collect::<String>();
+
+        // Local ends of the statements of the input itself, not the ones
+        // of the included files
+        let ends: Vec<(usize, &Stmt)> = nodes
+            .iter()
+            .filter_map(|n| {
+                let loc = n.get_loc();
+                let file = sources.lookup(&loc).filter(|f| &*f.code == code)?;
+
+                Some((file.local_loc(&loc).end.min(chars.len()), n))
+            })
+            .collect();
+
+        let mut chunks = vec![];
+        let mut prev = 0;
+
+        for (i, (end, node)) in ends.iter().enumerate() {
+            let end = match i == ends.len() - 1 {
+                true => chars.len(),
+                false => *end,
+            };
+
+            let mut chunk = text(prev, end).trim().to_string();
+
+            if let (Some(nb), true, Stmt::Expr(_)) = (result, i == ends.len() - 1, node) {
+                // The location of a call starts at its parenthesis, the
+                // expression starts at the first token after the comments
+                let start = tokens
+                    .iter()
+                    .find(|t| t.loc.start >= prev && t.kind != TokenKind::NewLine)
+                    .map_or(prev, |t| t.loc.start);
+                let above = text(prev, start);
+                let decl = match self.results.iter().any(|r| *r) {
+                    true => "",
+                    false => "var ",
+                };
+
+                chunk = format!(
+                    "{}var _{nb} = {}\n{decl}_ = _{nb}",
+                    above.trim_start(),
+                    text(start, end).trim()
+                );
+            }
+
+            if let Stmt::FnDecl(f) = node {
+                match self.fns.contains(&f.name) {
+                    true => self.redefines = true,
+                    false => self.fns.push(f.name.clone()),
+                }
+            }
+
+            chunks.push(chunk);
+            prev = end;
+        }
+
+        if ends.is_empty() && !code.trim().is_empty() {
+            chunks.push(code.trim().to_string());
+        }
+
+        let bound = matches!(ends.last(), Some((_, Stmt::Expr(_))));
+        self.results.extend(result.map(|_| bound));
+
+        self.chunks.extend(chunks);
+    }
+
+    pub fn to_code(&self) -> String {
+        let mut code = match self.redefines {
+            true => format!("@!{}\n", REDEFINE_ATTRIBUTE),
+            false => String::new(),
+        };
+
+        code.push_str(&self.chunks.join("\n"));
+        code.push('\n');
+
+        code
+    }
+
+    // '_' is the last result, the ones that aren't declared can't be used
+    fn uses_unbound_result(&self, tokens: &[Token]) -> bool {
+        tokens
+            .iter()
+            .filter(|t| t.kind == TokenKind::Identifier)
+            .filter_map(|t| match t.value.as_str() {
+                "_" => Some(self.results.len()),
+                name => name.strip_prefix('_')?.parse::<usize>().ok(),
+            })
+            .any(|nb| nb >= 1 && !self.results.get(nb - 1).copied().unwrap_or(true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use frontend::{lexer::Lexer, parser::Parser};
+    use runtime::{interpreter::Interpreter, values::RtVal};
+    use tools::source_map::SourceMap;
+
+    use super::{History, REDEFINE_ATTRIBUTE};
+
+    // Runs the inputs like the interactive mode and gives the saved
+    // session, checked by running it in a new interpreter that must end
+    // with the same globals
+    fn session(inputs: &[&str]) -> String {
+        let mut history = History::default();
+        let mut sources = SourceMap::default();
+        let mut interp = Interpreter::new();
+        interp.redefine_fns = true;
+        let mut results = 0;
+
+        for input in inputs {
+            let offset = sources.add("repl", input);
+            let mut lexer = Lexer::with_offset(offset);
+            let tokens = lexer.tokenize(input).unwrap();
+            let nodes = Parser::default().parse(tokens).unwrap();
+
+            let Ok(res) = interp.interpret(&nodes) else {
+                continue;
+            };
+
+            let result = match res == RtVal::Null {
+                true => None,
+                false => {
+                    results += 1;
+                    interp.set_global(&format!("_{}", results), res.clone());
+                    interp.set_global("_", res);
+                    Some(results)
+                }
+            };
+
+            history.push(input, &nodes, &sources, result);
+        }
+
+        let code = history.to_code();
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(&code).unwrap();
+        let mut parser = Parser::default();
+        let nodes = parser.parse(tokens).unwrap();
+
+        let mut replay = Interpreter::new();
+        replay.redefine_fns = parser.file_attributes().iter().any(|a| a.name == REDEFINE_ATTRIBUTE);
+        assert!(replay.interpret(&nodes).is_ok(), "can't load:\n{}", code);
+        // The results left out are missing, the others are the same
+        let expected = values(&interp);
+        for value in values(&replay) {
+            assert!(expected.contains(&value), "{:?} differs after:\n{}", value, code);
+        }
+
+        code
+    }
+
+    // Functions are compared by the values they gave
+    fn values(interp: &Interpreter) -> Vec<(String, String)> {
+        interp
+            .get_visible_vars()
+            .into_iter()
+            .filter(|(_, v)| !matches!(v, RtVal::FuncVal(_) | RtVal::NativeFnVal(_)))
+            .map(|(name, v)| (name.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn redefined_fns() {
+        let inputs = [
+            "fn f() { return 1 }",
+            "/// Doubles\nfn g(x) { return x * 2 }\nvar a = f()",
+            "fn f() { return 2 } // fixed",
+            "var b = g(f())",
+        ];
+
+        assert_eq!(
+            session(&inputs),
+            "@!redefine
+fn f() { return 1 }
+/// Doubles
+fn g(x) { return x * 2 }
+var a = f()
+fn f() { return 2 } // fixed
+var b = g(f())
+"
+        );
+
+        // The results keep the definition that gave them
+        let inputs = ["fn f() { return 1 }", "f()", "fn f() { return 2 }", "f()", "_1 + _2"];
+        assert_eq!(
+            session(&inputs),
+            "@!redefine
+fn f() { return 1 }
+var _1 = f()
+var _ = _1
+fn f() { return 2 }
+var _2 = f()
+_ = _2
+var _3 = _1 + _2
+_ = _3
+"
+        );
+    }
+
+    #[test]
+    fn results() {
+        // '_3' comes from an 'if', the inputs using it are left out and so
+        // are the ones using their results
+        let inputs = [
+            "1 + 2",
+            "_ * 2",
+            "if true { 5 }",
+            "_1 + _2",
+            "_3 + 1",
+            "_ + 1",
+            "max(_2, 1)",
+        ];
+
+        assert_eq!(
+            session(&inputs),
+            "var _1 = 1 + 2
+var _ = _1
+var _2 = _ * 2
+_ = _2
+if true { 5 }
+var _4 = _1 + _2
+_ = _4
+var _7 = max(_2, 1)
+_ = _7
+"
+        );
+    }
+}
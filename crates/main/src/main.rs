@@ -1,16 +1,59 @@
 use clap::Parser as ClapParser;
+use ecow::EcoString;
 use std::{
+    collections::HashMap,
     error::Error,
+    fmt::{Arguments, Display},
     fs,
-    io::{self, Write},
+    io::{self, BufRead, Write},
+    path::Path,
     process,
 };
 use colored::*;
 
 use frontend::{
-    ast::ast_pretty_print::AstPrinter, lexer::Lexer, parser::Parser,
+    ast::{
+        ast_pretty_print::{AstFormat, AstPrinter},
+        doc_gen::render_markdown,
+        stmt::{ImportStmt, Stmt},
+    },
+    cfg::prune_disabled_branches,
+    inline::inline_functions,
+    optimize::{eliminate_common_subexprs, hoist_invariants},
+    include::lex_with_includes,
+    lexer::Lexer,
+    parser::Parser,
+    strict::check_strict,
 };
-use runtime::{interpreter::Interpreter, values::RtVal};
+use codegen_c::c_generator::CGenerator;
+use codegen_js::js_generator::JsGenerator;
+use resolver::{
+    checker::check_types,
+    lint::{check, LintLevel},
+    resolver::Resolver,
+    symbols::render_scopes,
+};
+use runtime::{
+    interpreter::{
+        quiet_guarded_panics, ContractClause, InterpErr, Interpreter, ErrorSite, OutputFailure,
+        PackageResolver,
+    },
+    trace::write_trace,
+    values::{DisplayConfig, RtVal},
+};
+use tools::{
+    color::ColorChoice,
+    messages::{localize, Catalog},
+    results::{set_explain_errors, PhyResult},
+    source_map::SourceMap,
+};
+
+mod history;
+mod package;
+mod replay;
+
+use history::{History, REDEFINE_ATTRIBUTE};
+use package::{parse_spec, Manifest};
 
 // --------
 //   Cli
@@ -28,6 +71,59 @@ struct Cli {
     #[arg(short, long)]
     inter: bool,
 
+    /// File whose definitions are loaded before starting the interactive mode
+    #[arg(long)]
+    init: Option<String>,
+
+    /// File replacing the default prelude, run before any other code
+    #[arg(long)]
+    prelude: Option<String>,
+
+    /// Forbids scripts to access the file system
+    #[arg(long)]
+    sandbox: bool,
+
+    /// Variables must be initialized and can't be assigned null. Also
+    /// enabled by '@!strict' in a file
+    #[arg(long)]
+    strict: bool,
+
+    /// Checks the type annotations, like 'var a: int? = null', before
+    /// running. Also enabled by '@!typed' in a file
+    #[arg(long)]
+    typed: bool,
+
+    /// Evaluates the 'requires' and 'ensures' clauses of the functions at
+    /// each call
+    #[arg(long)]
+    contracts: bool,
+
+    /// Maximum number of significant digits of the reals written by
+    /// 'print' and the interactive mode
+    #[arg(long, value_name = "DIGITS", value_parser = clap::value_parser!(u16).range(1..=17))]
+    real_digits: Option<u16>,
+
+    /// Writes the reals in scientific notation, like 1.5e-7, when their
+    /// exponent is at least this one or at most its opposite
+    #[arg(long, value_name = "EXPONENT")]
+    sci_threshold: Option<u32>,
+
+    /// Compile time constant, like DEBUG=true. The branches of 'if DEBUG'
+    /// that aren't taken are removed before running
+    #[arg(short = 'D', long = "define", value_name = "NAME[=true|false]")]
+    defines: Vec<String>,
+
+    /// Optimizations run before interpreting a file: 0 for none, 1 to
+    /// inline the calls of tiny functions, 2 to also compute the loop
+    /// invariants and the repeated expressions once
+    #[arg(short = 'O', long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(0..=2))]
+    opt_level: u8,
+
+    /// Size of the largest returned expression of the functions whose
+    /// calls are inlined, in nodes. The ones marked '@inline' always are
+    #[arg(long, default_value_t = 8)]
+    inline_threshold: usize,
+
     // Prints the tokens
     #[arg(long)]
     print_tokens: bool,
@@ -35,24 +131,234 @@ struct Cli {
     // Prints the AST tree
     #[arg(short, long)]
     print_ast: bool,
+
+    /// Prints the AST as an indented tree instead of s-expressions
+    #[arg(long)]
+    ast_tree: bool,
+
+    /// Prints the code translated to JavaScript instead of running it
+    #[arg(long)]
+    emit_js: bool,
+
+    /// Prints the code translated to C instead of running it, to build with
+    /// 'cc out.c -lm'. The file is type checked, only the programs using
+    /// ints, reals, bools and strings are supported
+    #[arg(long)]
+    emit_c: bool,
+
+    /// Prints the Markdown documentation of the file instead of running it
+    #[arg(long)]
+    doc: bool,
+
+    /// Prints the warnings of the file, like unused variables, instead of
+    /// running it. Their level is set in the code with '@allow(code)',
+    /// '@warn(code)' or '@deny(code)' on functions and '@!deny(code)' for
+    /// the whole file
+    #[arg(long)]
+    check: bool,
+
+    /// Calls the functions marked '@test' once the file ran. Each one
+    /// passes unless it fails with an error, the run fails if any does
+    #[arg(long)]
+    test: bool,
+
+    /// Prints the nesting of the scopes of the file, with the names
+    /// declared in each one and the variables captured by the closures,
+    /// instead of running it
+    #[arg(long)]
+    scopes: bool,
+
+    /// Adds a dependency to the arc.toml of the current project and
+    /// fetches it. The source is a path or a git URL, with an optional
+    /// '@rev' for the later
+    #[arg(long, value_name = "NAME=SOURCE")]
+    add: Option<String>,
+
+    /// Writes the statements run and the variables set by the file, even
+    /// if it fails, to step through them later with '--replay'
+    #[arg(long, value_name = "TRACE")]
+    record: Option<String>,
+
+    /// Steps through a run written by '--record', starting from its last
+    /// statement and going back or forth
+    #[arg(long, value_name = "TRACE")]
+    replay: Option<String>,
+
+    /// When to color the messages: 'auto' when the errors go to a terminal
+    /// and NO_COLOR isn't set, 'always' or 'never'
+    #[arg(long, value_name = "WHEN", default_value = "auto")]
+    color: ColorChoice,
+
+    /// Catalog of translated diagnostics, one 'code = message' per line,
+    /// like 'parser.UnexpectedEof = fin de fichier inattendue'
+    #[arg(long, value_name = "CATALOG")]
+    messages: Option<String>,
+
+    /// Adds to common errors some advice and an example of code that
+    /// works, for those learning to program
+    #[arg(long)]
+    explain_errors: bool,
 }
 
 struct Repl {
     cli: Cli,
+    defines: HashMap<EcoString, bool>,
     ast_printer: AstPrinter,
     interpreter: Interpreter,
+    sources: SourceMap,
+    // Translations of the diagnostics, empty for the English ones
+    messages: Catalog,
+    // Inputs of the interactive session that ran without errors
+    history: History,
+    // Number of results bound to '_1', '_2'... in the interactive mode
+    results: usize,
 }
 
 fn main() {
+    let cli = Cli::parse();
+    cli.color.apply();
+    set_explain_errors(cli.explain_errors);
+
+    // The panics of the scripts are reported as internal errors, without
+    // the message of the default hook
+    quiet_guarded_panics();
+
+    let messages = match &cli.messages {
+        Some(f) => fs::read_to_string(f)
+            .map_err(|e| format!("can't read messages '{}': {}", f, e))
+            .and_then(|text| Catalog::parse(&text))
+            .unwrap_or_else(|e| {
+                print_err(e);
+                process::exit(1);
+            }),
+        None => Catalog::default(),
+    };
+
+    if let Some(spec) = &cli.add {
+        match add_package(spec) {
+            Ok(name) => println!("Added package '{}'", name),
+            Err(e) => {
+                print_err(e);
+                process::exit(1);
+            }
+        }
+
+        return;
+    }
+
+    if let Some(trace) = &cli.replay {
+        if let Err(e) = replay::replay(trace) {
+            print_err(e);
+            process::exit(1);
+        }
+
+        return;
+    }
+
+    let mut interpreter = match &cli.prelude {
+        Some(f) => match fs::read_to_string(f) {
+            Ok(code) => Interpreter::with_prelude(&code).unwrap_or_else(|e| {
+                print_err(localize(&messages, &e.err));
+                process::exit(1);
+            }),
+            Err(e) => {
+                print_err(format_args!("can't load prelude '{}': {}", f, e));
+                process::exit(1);
+            }
+        },
+        None => Interpreter::new(),
+    };
+    interpreter.sandboxed = cli.sandbox;
+    // Like the other tools, 'phy -f script.arc | head' ends when head does
+    interpreter.output_failure = OutputFailure::Stop;
+    interpreter.contracts = cli.contracts;
+    interpreter.display = DisplayConfig {
+        max_digits: cli.real_digits.map(usize::from),
+        sci_threshold: cli.sci_threshold,
+    };
+
+    let defines = parse_defines(&cli.defines).unwrap_or_else(|e| {
+        print_err(e);
+        process::exit(1);
+    });
+
+    // Also readable at runtime, for the uses that aren't pruned
+    for (name, value) in &defines {
+        interpreter.declare_const(name, (*value).into());
+    }
+
+    let ast_format = match cli.ast_tree {
+        true => AstFormat::Tree,
+        false => AstFormat::SExpr,
+    };
+
     let mut repl = Repl {
-        cli: Cli::parse(),
-        ast_printer: AstPrinter {},
-        interpreter: Interpreter::new(),
+        cli,
+        defines,
+        ast_printer: AstPrinter::new(ast_format),
+        interpreter,
+        sources: SourceMap::default(),
+        messages,
+        history: History::default(),
+        results: 0,
     };
 
     repl.run();
 }
 
+fn add_package(spec: &str) -> Result<String, String> {
+    let cwd = std::env::current_dir().map_err(|e| e.to_string())?;
+    let mut manifest = Manifest::find(&cwd)?.unwrap_or_else(|| Manifest::new(&cwd));
+
+    let (name, source) = parse_spec(spec)?;
+    manifest.add(&name, source)?;
+    if let Some(Err(e)) = manifest.resolve(&name) {
+        return Err(e);
+    }
+    manifest.save()?;
+
+    Ok(name)
+}
+
+// Output of the commands printing the file, like '--emit-js'. Ends the
+// process when it can't be written, quietly if the reader of the pipe
+// exited
+fn print_out(text: &str) {
+    write_out(format_args!("{}", text));
+}
+
+// Same for the interactive mode, flushed for the prompt
+fn write_out(args: Arguments) {
+    let mut stdout = io::stdout().lock();
+
+    match stdout.write_fmt(args).and_then(|_| stdout.flush()) {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => process::exit(0),
+        Err(_) => process::exit(1),
+    }
+}
+
+// Errors of the command line and of the interactive commands, on stderr
+// like the diagnostics
+fn print_err(msg: impl Display) {
+    let _ = writeln!(io::stderr(), "{} {}", "Error:".red(), msg);
+}
+
+fn parse_defines(defines: &[String]) -> Result<HashMap<EcoString, bool>, String> {
+    defines
+        .iter()
+        .map(|d| match d.split_once('=') {
+            None => Ok((EcoString::from(d.as_str()), true)),
+            Some((name, "true")) => Ok((EcoString::from(name), true)),
+            Some((name, "false")) => Ok((EcoString::from(name), false)),
+            Some((name, value)) => Err(format!(
+                "constant '{}' must be true or false, found '{}'",
+                name, value
+            )),
+        })
+        .collect()
+}
+
 impl Repl {
     pub fn run(&mut self) {
         let _ = match &self.cli.file {
@@ -62,25 +368,119 @@ impl Repl {
     }
 
     fn run_file(&mut self, file_path: String) -> Result<(), Box<dyn Error>> {
-        let code = fs::read_to_string(file_path)?;
-        self.sequence(code);
+        let code = fs::read_to_string(&file_path)?;
+
+        // Modules are imported relative to the script, packages are the
+        // ones of the project it belongs to
+        if let Some(dir) = Path::new(&file_path).parent() {
+            self.interpreter.module_paths = vec![dir.to_path_buf()];
+
+            let root = match dir.as_os_str().is_empty() {
+                true => Path::new("."),
+                false => dir,
+            };
+
+            // Fetched on their first import, so that the scripts not using
+            // them run without the network
+            match Manifest::find(root) {
+                Ok(manifest) => {
+                    self.interpreter.package_resolver =
+                        manifest.map(|m| Box::new(m) as Box<dyn PackageResolver>)
+                }
+                Err(e) => {
+                    print_err(e);
+                    return Ok(());
+                }
+            }
+        }
+
+        if self.cli.record.is_some() {
+            self.interpreter.recording = Some(vec![]);
+        }
+
+        // Timers scheduled by the script run once it ended, unless its
+        // output was closed
+        let ran = self.sequence(&file_path, code);
+
+        if ran.is_some() && !self.interpreter.output_closed() {
+            if let Err(e) = self.interpreter.run_until_idle() {
+                self.report_interp_err(&e);
+            }
+        }
+
+        let tests_failed = match (&ran, self.cli.test) {
+            (Some((_, nodes)), true) => self.run_tests(nodes),
+            _ => false,
+        };
+
+        if let (Some(trace), Some(events)) = (&self.cli.record, self.interpreter.recording.take()) {
+            let res = fs::File::create(trace)
+                .and_then(|f| write_trace(&events, &self.sources, &mut io::BufWriter::new(f)));
+
+            if let Err(e) = res {
+                print_err(format_args!("can't write trace '{}': {}", trace, e));
+            }
+        }
+
+        if tests_failed {
+            process::exit(1);
+        }
 
         Ok(())
     }
 
+    // Functions of the file marked '@test', called in their order without
+    // arguments. True if any failed
+    fn run_tests(&mut self, nodes: &[Stmt]) -> bool {
+        let tests: Vec<&EcoString> = nodes
+            .iter()
+            .filter_map(|n| match n {
+                Stmt::FnDecl(f) if f.get_attribute("test").is_some() => Some(&f.name),
+                _ => None,
+            })
+            .collect();
+
+        let mut failed = 0;
+
+        for name in &tests {
+            match self.interpreter.call_function(name.as_str(), vec![]) {
+                Ok(_) => write_out(format_args!("test {} ... ok\n", name)),
+                Err(e) => {
+                    write_out(format_args!("test {} ... FAILED\n", name));
+                    self.report_interp_err(&e);
+                    failed += 1;
+                }
+            }
+        }
+
+        write_out(format_args!("\n{} tests, {} failed\n", tests.len(), failed));
+
+        failed > 0
+    }
+
     fn run_repl(&mut self) -> Result<(), Box<dyn Error>> {
-        let stdin = io::stdin();
-        let mut stdout = io::stdout();
         let mut input = String::new();
 
-        println!("\n  {}", "Phy language interpreter v0.0".yellow());
+        write_out(format_args!("\n  {}\n", "Phy language interpreter v0.0".yellow()));
+
+        // Running a corrected function again replaces it
+        self.interpreter.redefine_fns = true;
+
+        if let Some(f) = self.cli.init.clone() {
+            if let Err(e) = self.run_file(f.clone()) {
+                print_err(format_args!("can't load '{}': {}", f, e));
+            }
+        }
 
         loop {
             input.clear();
-            print!("\n> ");
-            stdout.flush().unwrap();
+            write_out(format_args!("\n> "));
+
+            // End of input
+            if self.interpreter.input.read_line(&mut input)? == 0 {
+                return Ok(());
+            }
 
-            stdin.read_line(&mut input)?;
             let trimmed_input = input.trim();
 
             if trimmed_input == "quit" {
@@ -91,51 +491,286 @@ impl Repl {
                 continue;
             }
 
-            // Execute interpreter
-            self.sequence(trimmed_input.to_string());
+            if let Some(cmd) = trimmed_input.strip_prefix(':') {
+                self.run_command(cmd);
+                continue;
+            }
+
+            // Execute interpreter and echo the result
+            let code = trimmed_input.to_string();
+
+            if let Some((res, nodes)) = self.sequence("repl", code.clone()) {
+                // The last result is '_' and each one is also numbered, in
+                // the order they are shown
+                let result = match res == RtVal::Null {
+                    true => None,
+                    false => {
+                        self.results += 1;
+                        let name = format!("_{}", self.results);
+
+                        write_out(format_args!(
+                            "{} {} {} : {}\n",
+                            name.dimmed(),
+                            "=>".green(),
+                            res.display(&self.interpreter.display),
+                            res.get_type_name().cyan()
+                        ));
+
+                        self.interpreter.set_global(&name, res.clone());
+                        self.interpreter.set_global("_", res);
+                        Some(self.results)
+                    }
+                };
+
+                self.history.push(&code, &nodes, &self.sources, result);
+            }
+        }
+    }
+
+    // Commands of the interactive mode:
+    //  :save <file>  writes the inputs of the session that ran without errors
+    //  :load <file>  runs the file in the session
+    //  :complete <prefix>  lists keywords and visible names starting with prefix
+    //  :doc <name>  shows the signature and doc comment of a function
+    //  :env  lists the visible variables and functions, natives excepted
+    fn run_command(&mut self, cmd: &str) {
+        let (name, arg) = cmd.split_once(' ').unwrap_or((cmd, ""));
+        let arg = arg.trim();
+
+        match (name, arg.is_empty()) {
+            ("save", false) => {
+                match fs::write(arg, self.history.to_code()) {
+                    Ok(_) => write_out(format_args!("Session saved to '{}'\n", arg)),
+                    Err(e) => print_err(format_args!("can't save session: {}", e)),
+                }
+            }
+            ("load", false) => match fs::read_to_string(arg) {
+                Ok(code) => {
+                    if let Some((_, nodes)) = self.sequence(arg, code.clone()) {
+                        self.history.push(&code, &nodes, &self.sources, None);
+                    }
+                }
+                Err(e) => print_err(format_args!("can't load '{}': {}", arg, e)),
+            },
+            ("complete", _) => write_out(format_args!("{}\n", self.complete(arg).join("  "))),
+            ("env", _) => self.print_env(),
+            // Same as calling 'help', members like 'math.sqrt' work too
+            ("doc", false) => {
+                self.sequence("repl", format!("help({})", arg));
+            }
+            ("doc", true) => print_err("missing function name after ':doc'"),
+            ("save" | "load", true) => {
+                print_err(format_args!("missing file path after ':{}'", name))
+            }
+            _ => print_err(format_args!("unknown command ':{}'", name)),
         }
     }
 
-    fn sequence(&mut self, code: String) {
-        let mut lexer = Lexer::new();
+    fn print_env(&self) {
+        let mut vars = self.interpreter.get_visible_vars();
+        vars.retain(|(_, v)| !matches!(v, RtVal::NativeFnVal(..) | RtVal::ModuleVal(..)));
+        vars.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (name, value) in vars {
+            let value_str = value.display(&self.interpreter.display);
+            write_out(format_args!("{} = {} : {}\n", name, value_str, value.get_type_name().cyan()));
+        }
+    }
+
+    fn complete(&self, prefix: &str) -> Vec<String> {
+        let lexer = Lexer::new();
+        let mut candidates: Vec<String> = lexer
+            .get_keywords()
+            .into_iter()
+            .cloned()
+            .chain(self.interpreter.get_visible_names().iter().map(|n| n.to_string()))
+            .filter(|n| n.starts_with(prefix))
+            .collect();
+
+        candidates.sort();
+        candidates.dedup();
+        candidates
+    }
+
+    // Gives the result of the code and the nodes it ran
+    fn sequence(&mut self, name: &str, code: String) -> Option<(RtVal, Vec<Stmt>)> {
         let mut parser = Parser::default();
+        let loader = |path: &str| fs::read_to_string(path);
 
-        let tokens = match lexer.tokenize(&code) {
+        let tokens = match lex_with_includes(name, &code, &mut self.sources, &loader) {
             Ok(tk) => tk,
             Err(e) => {
                 e.iter()
-                    .for_each(|e| e.report(&"placeholder.rz".into(), &code));
+                    .for_each(|e| e.report(&self.sources, &self.messages));
 
-                return;
+                return None;
             }
         };
 
         if self.cli.print_tokens {
-            println!("Tokens: {:#?}", tokens);
+            print_out(&format!("Tokens: {:#?}\n", tokens));
         }
 
-        let nodes = match parser.parse(tokens) {
+        let nodes = match parser.parse(&tokens) {
             Ok(n) => n,
             Err(e) => {
                 e.iter()
-                    .for_each(|e| e.report(&"placeholder.rz".into(), &code));
-                return;
+                    .for_each(|e| e.report(&self.sources, &self.messages));
+                return None;
             }
         };
 
-        if self.cli.print_ast {
+        let has_attribute = |name: &str| parser.file_attributes().iter().any(|a| a.name == name);
+
+        if self.cli.strict || has_attribute("strict") {
+            let errs = check_strict(&nodes);
+
+            if !errs.is_empty() {
+                errs.iter().for_each(|e| e.report(&self.sources, &self.messages));
+                return None;
+            }
+        }
+
+        // The C code relies on the types being checked
+        if self.cli.typed || self.cli.emit_c || has_attribute("typed") {
+            // Modules are registered in the source map for the errors
+            // pointing in their code. The ones that can't be read are
+            // reported when running
+            let (interpreter, sources) = (&mut self.interpreter, &mut self.sources);
+            let mut load = |import: &ImportStmt| {
+                let (file, code) = interpreter.module_source(import).ok()?;
+                let offset = sources.add(&file.display().to_string(), &code);
+                let mut lexer = Lexer::with_offset(offset);
+                let tokens = lexer.tokenize(&code).ok()?;
+
+                Parser::default().parse(tokens).ok()
+            };
+
+            let errs = check_types(&nodes, &mut load);
+
+            if !errs.is_empty() {
+                errs.iter().for_each(|e| e.report(&self.sources, &self.messages));
+                return None;
+            }
+        }
+
+        let nodes = prune_disabled_branches(nodes, &self.defines);
+
+        if self.cli.print_ast || self.cli.ast_tree {
             for n in &nodes {
-                println!("{}", self.ast_printer.print(n).unwrap());
+                print_out(&format!("{}\n", self.ast_printer.print(n).unwrap()));
             }
         }
 
-        match self.interpreter.interpret(&nodes) {
-            Ok(res) => {
-                if res != RtVal::Null {
-                    println!("{}", res);
+        if self.cli.doc {
+            print_out(&render_markdown(name, &self.sources, &nodes));
+
+            return None;
+        }
+
+        if self.cli.check {
+            match check(&nodes, parser.file_attributes()) {
+                Ok(lints) => {
+                    lints.iter().for_each(|l| l.report(&self.sources, &self.messages));
+
+                    // Denied warnings fail the check, for scripts and CI
+                    if lints.iter().any(|l| l.err.level == LintLevel::Deny) {
+                        process::exit(1);
+                    }
                 }
+                Err(e) => e.report(&self.sources, &self.messages),
             }
-            Err(e) => e.report(&"placeholder.rz".into(), &code),
+
+            return None;
+        }
+
+        if self.cli.scopes {
+            let mut resolver = Resolver::default();
+
+            match resolver.resolve(&nodes) {
+                Ok(_) => print_out(&render_scopes(&resolver.into_symbols(), &self.sources)),
+                Err(e) => e.report(&self.sources, &self.messages),
+            }
+
+            return None;
+        }
+
+        // Misplaced control flow is reported before running anything
+        if let Err(e) = Resolver::default().resolve(&nodes) {
+            e.report(&self.sources, &self.messages);
+            return None;
+        }
+
+        // Saved sessions define their functions again like the interactive
+        // mode did
+        let redefines = has_attribute(REDEFINE_ATTRIBUTE);
+        if redefines {
+            self.interpreter.redefine_fns = true;
+        }
+
+        // The functions of a file can't be redefined once it ran, unlike
+        // in interactive mode
+        let opt_level = match self.cli.file.is_some() && !self.cli.inter && !redefines {
+            true => self.cli.opt_level,
+            false => 0,
+        };
+
+        let mut nodes = nodes;
+        if opt_level >= 1 {
+            nodes = inline_functions(nodes, self.cli.inline_threshold);
+        }
+        if opt_level >= 2 {
+            nodes = eliminate_common_subexprs(hoist_invariants(nodes));
+        }
+
+        if self.cli.emit_js {
+            match JsGenerator::default().generate(&nodes) {
+                Ok(js) => print_out(&js),
+                Err(e) => e.iter().for_each(|e| e.report(&self.sources, &self.messages)),
+            }
+
+            return None;
+        }
+
+        if self.cli.emit_c {
+            match CGenerator::default().generate(&nodes) {
+                Ok(c) => print_out(&c),
+                Err(e) => e.iter().for_each(|e| e.report(&self.sources, &self.messages)),
+            }
+
+            return None;
+        }
+
+        match self.interpreter.interpret(&nodes) {
+            Ok(res) => Some((res, nodes)),
+            Err(e) => {
+                self.report_interp_err(&e);
+                None
+            }
+        }
+    }
+
+    // Followed by the notes pointing to the violated contract and to where
+    // the errors of the calls were raised
+    fn report_interp_err(&self, e: &PhyResult<InterpErr>) {
+        e.report(&self.sources, &self.messages);
+
+        let mut err = e;
+
+        loop {
+            let inner = match (&err.err, &err.cause) {
+                (InterpErr::InNative(_, inner), _) => inner.as_ref(),
+                (_, Some(cause)) => cause.as_ref(),
+                _ => break,
+            };
+
+            err = inner.root_cause();
+            PhyResult::new(ErrorSite, err.loc.clone()).report(&self.sources, &self.messages);
+        }
+
+        if let InterpErr::ContractViolation { clause_loc, .. } = &err.err {
+            PhyResult::new(ContractClause, Some(clause_loc.clone()))
+                .report(&self.sources, &self.messages);
         }
     }
 }
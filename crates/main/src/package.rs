@@ -0,0 +1,425 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use frontend::lexer::{is_ident_continue, is_ident_start, KEYWORDS};
+use runtime::interpreter::PackageResolver;
+
+// Dependencies of a project, declared in the 'arc.toml' at its root:
+//
+//   [dependencies]
+//   geo = { path = "../geometry" }
+//   utils = { git = "https://github.com/user/utils", rev = "v1.0" }
+//
+// Only this subset of TOML is read. Once resolved, 'import geo' loads
+// 'lib.arc' at the root of the package and 'import geo.shapes' loads
+// 'shapes.arc'
+pub const MANIFEST: &str = "arc.toml";
+
+// Where the git packages are cloned, next to the manifest
+const CACHE_DIR: &str = ".arc/packages";
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Source {
+    // Relative to the manifest directory
+    Path(PathBuf),
+    Git { url: String, rev: Option<String> },
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Manifest {
+    pub dir: PathBuf,
+    pub dependencies: BTreeMap<String, Source>,
+    // The file as written, so that adding a dependency keeps the rest of
+    // it, like the comments and the other tables
+    text: String,
+}
+
+impl Manifest {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            dependencies: BTreeMap::new(),
+            text: String::new(),
+        }
+    }
+
+    // Looks for the manifest in the directory and then in its parents
+    pub fn find(start: &Path) -> Result<Option<Manifest>, String> {
+        for dir in start.ancestors() {
+            let file = dir.join(MANIFEST);
+
+            if file.is_file() {
+                let text = fs::read_to_string(&file)
+                    .map_err(|e| format!("can't read '{}': {}", file.display(), e))?;
+
+                return Manifest::parse(dir, &text).map(Some);
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub fn parse(dir: &Path, text: &str) -> Result<Manifest, String> {
+        let mut manifest = Manifest::new(dir);
+        manifest.text = text.to_string();
+        let mut in_deps = false;
+
+        for (i, line) in text.lines().enumerate() {
+            let err = |msg: &str| format!("{}:{}: {}", MANIFEST, i + 1, msg);
+            let line = split_unquoted(line, '#')[0].trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if line.starts_with('[') {
+                in_deps = line == "[dependencies]";
+                continue;
+            }
+
+            // Other tables are left to other tools
+            if !in_deps {
+                continue;
+            }
+
+            let (name, value) = line.split_once('=').ok_or_else(|| err("expected 'name = { ... }'"))?;
+            let name = name.trim();
+            let table = value
+                .trim()
+                .strip_prefix('{')
+                .and_then(|v| v.strip_suffix('}'))
+                .ok_or_else(|| err("dependencies must be inline tables"))?;
+
+            let mut keys = HashMap::new();
+            for entry in split_unquoted(table, ',').into_iter().filter(|e| !e.trim().is_empty()) {
+                let (key, value) = entry.split_once('=').ok_or_else(|| err("expected 'key = \"value\"'"))?;
+                let value = value
+                    .trim()
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .ok_or_else(|| err("values must be strings"))?;
+
+                keys.insert(key.trim(), value.to_string());
+            }
+
+            let source = match (keys.remove("path"), keys.remove("git")) {
+                (Some(path), None) => Source::Path(path.into()),
+                (None, Some(url)) => Source::Git { url, rev: keys.remove("rev") },
+                _ => return Err(err("expected either 'path' or 'git'")),
+            };
+
+            if let Some(key) = keys.keys().next() {
+                return Err(err(&format!("unknown key '{}'", key)));
+            }
+
+            check_dependency(name, &source).map_err(|e| err(&e))?;
+            manifest.dependencies.insert(name.to_string(), source);
+        }
+
+        Ok(manifest)
+    }
+
+    // The line of the dependency goes at the end of the '[dependencies]'
+    // table, which is created at the end of the file if there is none
+    pub fn add(&mut self, name: &str, source: Source) -> Result<(), String> {
+        check_dependency(name, &source)?;
+
+        if self.dependencies.contains_key(name) {
+            return Err(format!("package '{}' is already a dependency", name));
+        }
+
+        let table = match &source {
+            Source::Path(p) => format!("path = \"{}\"", p.display()),
+            Source::Git { url, rev: None } => format!("git = \"{}\"", url),
+            Source::Git { url, rev: Some(r) } => format!("git = \"{}\", rev = \"{}\"", url, r),
+        };
+        let entry = format!("{} = {{ {} }}", name, table);
+
+        let mut lines: Vec<&str> = self.text.lines().collect();
+        let header = lines
+            .iter()
+            .position(|l| split_unquoted(l, '#')[0].trim() == "[dependencies]");
+
+        match header {
+            Some(h) => {
+                let end = lines[h + 1..]
+                    .iter()
+                    .position(|l| l.trim_start().starts_with('['))
+                    .map_or(lines.len(), |i| h + 1 + i);
+                let last = lines[h + 1..end]
+                    .iter()
+                    .rposition(|l| !l.trim().is_empty())
+                    .map_or(h, |i| h + 1 + i);
+
+                lines.insert(last + 1, &entry);
+            }
+            None => {
+                if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                    lines.push("");
+                }
+                lines.extend(["[dependencies]", &entry]);
+            }
+        }
+
+        self.text = lines.join("\n") + "\n";
+        self.dependencies.insert(name.to_string(), source);
+
+        Ok(())
+    }
+
+    pub fn to_toml(&self) -> &str {
+        &self.text
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let file = self.dir.join(MANIFEST);
+
+        fs::write(&file, self.to_toml())
+            .map_err(|e| format!("can't write '{}': {}", file.display(), e))
+    }
+
+    // Root directory of the package, None if it isn't a dependency. The
+    // git ones missing from the cache are cloned first, so a package is
+    // only resolved once a script imports it
+    pub fn resolve(&self, name: &str) -> Option<Result<PathBuf, String>> {
+        let source = self.dependencies.get(name)?;
+
+        let root = match source {
+            Source::Path(p) => Ok(self.dir.join(p)),
+            Source::Git { url, rev } => self.fetch(name, url, rev.as_deref()),
+        };
+
+        Some(root.and_then(|root| match root.is_dir() {
+            true => Ok(root),
+            false => Err(format!("package '{}' not found at '{}'", name, root.display())),
+        }))
+    }
+
+    fn fetch(&self, name: &str, url: &str, rev: Option<&str>) -> Result<PathBuf, String> {
+        // Checked when read but the manifest may have been built by hand,
+        // and these end up in the arguments of git
+        check_dependency(name, &Source::Git { url: url.into(), rev: rev.map(String::from) })?;
+
+        let dest = self.dir.join(CACHE_DIR).join(name);
+
+        if dest.is_dir() {
+            return Ok(dest);
+        }
+
+        let mut cmd = Command::new("git");
+        cmd.args(["clone", "--quiet", "--depth", "1"]);
+        if let Some(rev) = rev {
+            cmd.args(["--branch", rev]);
+        }
+
+        let output = cmd
+            .arg("--")
+            .arg(url)
+            .arg(&dest)
+            .output()
+            .map_err(|e| format!("can't run git to fetch '{}': {}", name, e))?;
+
+        match output.status.success() {
+            true => Ok(dest),
+            false => Err(format!(
+                "can't fetch '{}': {}",
+                name,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+        }
+    }
+}
+
+impl PackageResolver for Manifest {
+    fn resolve(&mut self, name: &str) -> Option<Result<PathBuf, String>> {
+        Manifest::resolve(self, name)
+    }
+}
+
+// The name is imported and is the directory of the package in the cache,
+// so it must be an identifier. The url and the revision are arguments of
+// git, which would read them as options if they started with a '-'
+fn check_dependency(name: &str, source: &Source) -> Result<(), String> {
+    let mut chars = name.chars();
+    let is_ident = chars.next().is_some_and(is_ident_start) && chars.all(is_ident_continue);
+
+    if !is_ident || KEYWORDS.iter().any(|(k, _)| *k == name) {
+        return Err(format!("invalid package name '{}', expected an identifier", name));
+    }
+
+    match source {
+        Source::Git { url, .. } if url.starts_with('-') => Err(format!("invalid git url '{}'", url)),
+        Source::Git { rev: Some(rev), .. } if rev.starts_with('-') => {
+            Err(format!("invalid revision '{}'", rev))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Splits on the separators outside of the strings, like the '#' of the
+// comments which may also be in a url
+fn split_unquoted(text: &str, sep: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            _ if c == sep && !quoted => {
+                parts.push(&text[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+
+    parts.push(&text[start..]);
+    parts
+}
+
+// NAME=PATH or NAME=GIT_URL[@REV], git URLs being recognized by their
+// scheme or their '.git' extension
+pub fn parse_spec(spec: &str) -> Result<(String, Source), String> {
+    let invalid = || format!("invalid package '{}', expected NAME=PATH or NAME=GIT_URL[@REV]", spec);
+
+    let (name, source) = spec.split_once('=').ok_or_else(invalid)?;
+    if name.is_empty() || source.is_empty() {
+        return Err(invalid());
+    }
+
+    let is_git = ["https://", "http://", "git@", "ssh://"].iter().any(|s| source.starts_with(s))
+        || source.split('@').next().is_some_and(|s| s.ends_with(".git"));
+
+    let source = match is_git {
+        true => match source.rsplit_once('@') {
+            // The '@' of 'git@host:path' isn't a revision
+            Some((url, rev)) if !rev.contains([':', '/']) => Source::Git {
+                url: url.into(),
+                rev: Some(rev.into()),
+            },
+            _ => Source::Git { url: source.into(), rev: None },
+        },
+        false => Source::Path(source.into()),
+    };
+
+    check_dependency(name, &source)?;
+
+    Ok((name.to_string(), source))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{parse_spec, Manifest, Source};
+
+    #[test]
+    fn manifest() {
+        let text = "
+# Project
+[package]
+name = \"demo\"
+
+[dependencies]
+geo = { path = \"../geometry\" } # Shapes
+utils = { git = \"https://example.com/utils.git#main\", rev = \"v1\" }
+
+[tools]
+fmt = true
+";
+        let mut manifest = Manifest::parse(Path::new("proj"), text).unwrap();
+        assert_eq!(manifest.dependencies["geo"], Source::Path("../geometry".into()));
+        assert_eq!(
+            manifest.dependencies["utils"],
+            Source::Git { url: "https://example.com/utils.git#main".into(), rev: Some("v1".into()) }
+        );
+
+        // Added after the other dependencies, the rest is kept as written
+        manifest.add("num", Source::Path("num".into())).unwrap();
+        assert_eq!(
+            manifest.to_toml(),
+            text.replace("rev = \"v1\" }\n", "rev = \"v1\" }\nnum = { path = \"num\" }\n")
+        );
+        assert_eq!(Manifest::parse(Path::new("proj"), manifest.to_toml()).unwrap(), manifest);
+        assert!(manifest.add("geo", Source::Path("geo".into())).is_err());
+
+        let mut manifest = Manifest::parse(Path::new("."), "[package]\nname = \"demo\"").unwrap();
+        manifest.add("geo", Source::Path("../geo".into())).unwrap();
+        assert_eq!(
+            manifest.to_toml(),
+            "[package]\nname = \"demo\"\n\n[dependencies]\ngeo = { path = \"../geo\" }\n"
+        );
+
+        assert_eq!(
+            Manifest::parse(Path::new("."), "[dependencies]\ngeo = \"../geo\"").err().unwrap(),
+            "arc.toml:2: dependencies must be inline tables"
+        );
+        assert_eq!(
+            Manifest::parse(Path::new("."), "[dependencies]\ngeo = { url = \"x\" }").err().unwrap(),
+            "arc.toml:2: expected either 'path' or 'git'"
+        );
+        assert_eq!(
+            Manifest::parse(Path::new("."), "[dependencies]\n\"../x\" = { path = \"x\" }").err().unwrap(),
+            "arc.toml:2: invalid package name '\"../x\"', expected an identifier"
+        );
+    }
+
+    #[test]
+    fn specs() {
+        assert_eq!(parse_spec("geo=../geometry").unwrap(), ("geo".into(), Source::Path("../geometry".into())));
+        assert_eq!(
+            parse_spec("utils=https://example.com/utils@v2").unwrap().1,
+            Source::Git { url: "https://example.com/utils".into(), rev: Some("v2".into()) }
+        );
+        assert_eq!(
+            parse_spec("utils=git@example.com:user/utils.git").unwrap().1,
+            Source::Git { url: "git@example.com:user/utils.git".into(), rev: None }
+        );
+        assert!(parse_spec("geo").is_err());
+
+        // Names are directories of the cache, urls and revisions are
+        // arguments of git
+        assert!(parse_spec("../geo=../geometry").is_err());
+        assert!(parse_spec("fn=../geometry").is_err());
+        assert_eq!(
+            parse_spec("utils=--upload-pack=x.git").err().unwrap(),
+            "invalid git url '--upload-pack=x.git'"
+        );
+        assert_eq!(
+            parse_spec("utils=https://example.com/utils@-v2").err().unwrap(),
+            "invalid revision '-v2'"
+        );
+    }
+
+    #[test]
+    fn resolve_paths() {
+        let dir = std::env::temp_dir().join("phy_packages");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("app")).unwrap();
+        std::fs::create_dir_all(dir.join("geometry")).unwrap();
+        std::fs::write(dir.join("app/arc.toml"), "[dependencies]\ngeo = { path = \"../geometry\" }\n").unwrap();
+
+        // Found from a sub directory of the project
+        std::fs::create_dir_all(dir.join("app/src")).unwrap();
+        let manifest = Manifest::find(&dir.join("app/src")).unwrap().unwrap();
+        assert_eq!(
+            manifest.resolve("geo").unwrap().unwrap(),
+            dir.join("app").join(PathBuf::from("../geometry"))
+        );
+        assert!(manifest.resolve("std").is_none());
+
+        std::fs::write(dir.join("app/arc.toml"), "[dependencies]\nnone = { path = \"../none\" }\n").unwrap();
+        let manifest = Manifest::find(&dir.join("app")).unwrap().unwrap();
+        assert!(manifest.resolve("none").unwrap().is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
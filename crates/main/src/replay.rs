@@ -0,0 +1,89 @@
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+};
+
+use colored::*;
+
+use runtime::trace::{read_trace, vars_before, TraceStep};
+
+use crate::print_err;
+
+// Viewer of the runs recorded with '--record'. It starts at the last
+// statement, the one that failed if the run did, and moves between them:
+//  n or nothing  next statement
+//  b  previous statement
+//  g <step>  goes to the statement of that number
+//  v  lists the variables with their value before the statement
+//  q  quits
+pub fn replay(path: &str) -> Result<(), String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("can't read '{}': {}", path, e))?;
+    let steps = read_trace(&text)?;
+
+    // Index in the steps of each statement
+    let stmts = steps
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| matches!(s, TraceStep::Stmt { .. }))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    if stmts.is_empty() {
+        println!("No statement recorded");
+        return Ok(());
+    }
+
+    let mut stdin = io::stdin().lock();
+    let mut input = String::new();
+    let mut current = stmts.len() - 1;
+
+    loop {
+        show_stmt(&steps, &stmts, current);
+
+        print!("\n(replay) ");
+        io::stdout().flush().map_err(|e| e.to_string())?;
+
+        input.clear();
+        if stdin.read_line(&mut input).map_err(|e| e.to_string())? == 0 {
+            return Ok(());
+        }
+
+        let (cmd, arg) = input.trim().split_once(' ').unwrap_or((input.trim(), ""));
+
+        match cmd {
+            "" | "n" if current + 1 < stmts.len() => current += 1,
+            "" | "n" => println!("End of the run"),
+            "b" if current > 0 => current -= 1,
+            "b" => println!("Beginning of the run"),
+            "g" => match arg.trim().parse::<usize>() {
+                Ok(n) if (1..=stmts.len()).contains(&n) => current = n - 1,
+                _ => print_err(format_args!("step must be between 1 and {}", stmts.len())),
+            },
+            "v" => {
+                for (name, value) in vars_before(&steps, stmts[current]) {
+                    println!("{} = {}", name, value);
+                }
+            }
+            "q" => return Ok(()),
+            _ => print_err(format_args!("unknown command '{}', use n, b, g <step>, v or q", cmd)),
+        }
+    }
+}
+
+// With the variables the statement set, up to the next one
+fn show_stmt(steps: &[TraceStep], stmts: &[usize], current: usize) {
+    let index = stmts[current];
+
+    if let TraceStep::Stmt { file, line, code } = &steps[index] {
+        let step = format!("[{}/{}]", current + 1, stmts.len());
+        println!("\n{} {}:{}  {}", step.dimmed(), file, line, code);
+    }
+
+    let end = stmts.get(current + 1).copied().unwrap_or(steps.len());
+
+    for step in &steps[index + 1..end] {
+        if let TraceStep::Set { name, value } = step {
+            println!("  {} = {}", name.cyan(), value);
+        }
+    }
+}
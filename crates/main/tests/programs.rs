@@ -0,0 +1,86 @@
+// Golden-file tests: every .arc program in tests/programs is run by the phy
+// binary, like 'phy -f program.arc', and what it prints followed by its
+// diagnostics is compared to the .expected file next to it. A first line
+// like '// phy: --contracts' gives the options of the run. To write the
+// .expected files from the current behaviour:
+//   cargo test -p phy --test programs -- --bless
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+fn main() {
+    let bless = env::args().any(|a| a == "--bless");
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/programs");
+
+    let mut programs: Vec<PathBuf> = fs::read_dir(&dir)
+        .expect("can't read tests/programs")
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == "arc"))
+        .collect();
+    programs.sort();
+
+    let mut failed = vec![];
+
+    for program in &programs {
+        let actual = run(&dir, program);
+        let expected_path = program.with_extension("expected");
+
+        if bless {
+            fs::write(&expected_path, &actual).unwrap();
+            println!("blessed {}", name(program));
+            continue;
+        }
+
+        match fs::read_to_string(&expected_path) {
+            Ok(expected) if expected == actual => println!("test {} ... ok", name(program)),
+            Ok(expected) => {
+                println!("test {} ... FAILED", name(program));
+                println!("--- expected\n{}--- actual\n{}", expected, actual);
+                failed.push(name(program));
+            }
+            Err(_) => {
+                println!("test {} ... FAILED (missing .expected file)", name(program));
+                failed.push(name(program));
+            }
+        }
+    }
+
+    if !failed.is_empty() {
+        println!("\nfailed programs: {}", failed.join(", "));
+        process::exit(1);
+    }
+
+    println!("\ntest result: ok. {} programs", programs.len());
+}
+
+fn name(program: &Path) -> String {
+    program.file_stem().unwrap().to_string_lossy().into()
+}
+
+// Output of the program then its diagnostics. It runs from the directory
+// of the programs, so the reports show the file name alone
+fn run(dir: &Path, program: &Path) -> String {
+    let code = fs::read_to_string(program).unwrap();
+    let options: Vec<&str> = code
+        .lines()
+        .next()
+        .and_then(|l| l.strip_prefix("// phy:"))
+        .map(|o| o.split_whitespace().collect())
+        .unwrap_or_default();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_phy"))
+        .current_dir(dir)
+        .args(["--color", "never"])
+        .args(options)
+        .arg("-f")
+        .arg(program.file_name().unwrap())
+        .env_remove("NO_COLOR")
+        .output()
+        .expect("can't run phy");
+
+    let mut res = String::from_utf8_lossy(&output.stdout).into_owned();
+    res.push_str(&String::from_utf8_lossy(&output.stderr));
+    res
+}
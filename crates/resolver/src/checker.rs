@@ -0,0 +1,1482 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
+    rc::Rc,
+};
+
+use colored::Colorize;
+use ecow::EcoString;
+use thiserror::Error;
+use tools::{
+    messages::{localize, text, Arg, Locale, Translate},
+    results::{Loc, PhyReport, PhyResult},
+};
+
+use frontend::ast::{
+    expr::Expr,
+    stmt::{FnDeclStmt, ForRange, IfStmt, ImportStmt, Stmt, VarDeclStmt, WhileStmt},
+    types::TypeAnn,
+};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TypeErr {
+    #[error("expected type '{0}', found '{1}'")]
+    Mismatch(String, String),
+
+    #[error("'{0}' may be null, check it with 'if {0} != null' first")]
+    PossiblyNull(String),
+
+    #[error("unknown type '{0}'")]
+    UnknownType(String),
+
+    #[error("variable '{0}' of type '{1}' must be initialized")]
+    Uninitialized(EcoString, String),
+
+    #[error("can't infer the type of '{0}' from '{1}', add an annotation")]
+    CannotInfer(EcoString, String),
+
+    #[error("function '{0}' must return a value of type '{1}'")]
+    MissingReturn(EcoString, String),
+
+    #[error("expected {0} arguments, found {1}")]
+    ArgCount(usize, usize),
+
+    #[error("expected a function taking {0} arguments, found one taking {1}")]
+    CallbackArity(usize, usize),
+
+    #[error("operator '{0}' can't be used on values of generic type '{1}'")]
+    GenericOperand(EcoString, String),
+
+    // Reported after a mismatch, at the annotation of the parameter
+    #[error("type '{0}' expected because of this annotation")]
+    ExpectedHere(String),
+}
+
+impl PhyReport for TypeErr {
+    fn get_err_msg(&self, locale: &dyn Locale) -> String {
+        match self {
+            TypeErr::ExpectedHere(_) => {
+                format!("{} {}", text(locale, "label.note", "Note:").blue(), localize(locale, self))
+            }
+            e => format!(
+                "{} {}",
+                text(locale, "label.type", "Type error:").red(),
+                localize(locale, e)
+            ),
+        }
+    }
+}
+
+impl Translate for TypeErr {
+    fn category(&self) -> &'static str {
+        "type"
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            TypeErr::Mismatch(..) => "Mismatch",
+            TypeErr::PossiblyNull(..) => "PossiblyNull",
+            TypeErr::UnknownType(..) => "UnknownType",
+            TypeErr::Uninitialized(..) => "Uninitialized",
+            TypeErr::CannotInfer(..) => "CannotInfer",
+            TypeErr::MissingReturn(..) => "MissingReturn",
+            TypeErr::ArgCount(..) => "ArgCount",
+            TypeErr::CallbackArity(..) => "CallbackArity",
+            TypeErr::GenericOperand(..) => "GenericOperand",
+            TypeErr::ExpectedHere(..) => "ExpectedHere",
+        }
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        match self {
+            TypeErr::Mismatch(a, b) => vec![Arg::text(a), Arg::text(b)],
+            TypeErr::PossiblyNull(v)
+            | TypeErr::UnknownType(v)
+            | TypeErr::ExpectedHere(v) => vec![Arg::text(v)],
+            TypeErr::Uninitialized(a, b)
+            | TypeErr::CannotInfer(a, b)
+            | TypeErr::MissingReturn(a, b)
+            | TypeErr::GenericOperand(a, b) => vec![Arg::text(a), Arg::text(b)],
+            TypeErr::ArgCount(a, b)
+            | TypeErr::CallbackArity(a, b) => vec![Arg::text(a), Arg::text(b)],
+        }
+    }
+}
+
+pub type PhyResType = PhyResult<TypeErr>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Int,
+    Real,
+    Bool,
+    Str,
+    Char,
+    Null,
+    // Values that aren't followed, like the ones of the code without
+    // annotations, of the natives or of the modules
+    Any,
+    Nullable(Box<Type>),
+    List(Box<Type>),
+    // Type parameter of a generic function, only equal to itself in its
+    // body as it can be any type
+    Param(EcoString),
+    Fn(Rc<FnType>),
+    // Types of the public members
+    Module(Rc<BTreeMap<EcoString, Type>>),
+}
+
+#[derive(Debug, Clone)]
+pub struct FnType {
+    // Bound at each call by the arguments
+    pub type_params: Vec<EcoString>,
+    pub params: Vec<Type>,
+    pub ret: Type,
+    // Where each parameter type is written, to show why an argument is
+    // expected to be of this type
+    pub param_locs: Vec<Option<Loc>>,
+}
+
+impl PartialEq for FnType {
+    fn eq(&self, other: &Self) -> bool {
+        self.type_params == other.type_params && self.params == other.params && self.ret == other.ret
+    }
+}
+
+impl FnType {
+    // Signature with the type parameters replaced by the types of the
+    // arguments. The ones not found in the arguments are 'any'
+    pub fn instantiate(&self, args: &[Type]) -> FnType {
+        let mut bindings = HashMap::new();
+        for (param, arg) in self.params.iter().zip(args) {
+            param.bind(arg, &mut bindings);
+        }
+
+        bindings.retain(|name, _| self.type_params.contains(name));
+        for name in &self.type_params {
+            bindings.entry(name.clone()).or_insert(Type::Any);
+        }
+
+        FnType {
+            type_params: vec![],
+            params: self.params.iter().map(|p| p.substitute(&bindings)).collect(),
+            ret: self.ret.substitute(&bindings),
+            param_locs: self.param_locs.clone(),
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Real => write!(f, "real"),
+            Type::Bool => write!(f, "bool"),
+            Type::Str => write!(f, "str"),
+            Type::Char => write!(f, "char"),
+            Type::Null => write!(f, "null"),
+            Type::Any => write!(f, "any"),
+            Type::Nullable(t) => write!(f, "{}?", t),
+            Type::List(t) => write!(f, "[{}]", t),
+            Type::Param(name) => write!(f, "{}", name),
+            Type::Fn(func) => {
+                let params = func.params.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+
+                match func.type_params.is_empty() {
+                    true => write!(f, "fn({}) -> {}", params.join(", "), func.ret),
+                    false => write!(
+                        f,
+                        "fn<{}>({}) -> {}",
+                        func.type_params.join(", "),
+                        params.join(", "),
+                        func.ret
+                    ),
+                }
+            }
+            Type::Module(_) => write!(f, "module"),
+        }
+    }
+}
+
+impl Type {
+    pub fn nullable(self) -> Type {
+        match self {
+            Type::Any | Type::Null | Type::Nullable(_) => self,
+            t => Type::Nullable(Box::new(t)),
+        }
+    }
+
+    pub fn non_null(&self) -> Type {
+        match self {
+            Type::Nullable(t) => *t.clone(),
+            t => t.clone(),
+        }
+    }
+
+    pub fn is_nullable(&self) -> bool {
+        matches!(self, Type::Nullable(_) | Type::Null)
+    }
+
+    fn contains_any(&self) -> bool {
+        match self {
+            Type::Any => true,
+            Type::Nullable(t) | Type::List(t) => t.contains_any(),
+            Type::Fn(f) => f.params.iter().any(|p| p.contains_any()) || f.ret.contains_any(),
+            _ => false,
+        }
+    }
+
+    fn mentions(&self, param: &str) -> bool {
+        match self {
+            Type::Param(name) => name == param,
+            Type::Nullable(t) | Type::List(t) => t.mentions(param),
+            Type::Fn(f) => f.params.iter().any(|p| p.mentions(param)) || f.ret.mentions(param),
+            _ => false,
+        }
+    }
+
+    // If a value of type 'other' can be stored where 'self' is expected
+    pub fn accepts(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Any, _) | (_, Type::Any) => true,
+            (Type::Nullable(_), Type::Null) => true,
+            (Type::Nullable(t), Type::Nullable(o)) => t.accepts(o),
+            (Type::Nullable(t), o) => t.accepts(o),
+            (Type::Real, Type::Int) => true,
+            (Type::List(t), Type::List(o)) => t.accepts(o),
+            // A generic function is given the types of the expected one
+            (Type::Fn(f1), Type::Fn(f2)) if !f2.type_params.is_empty() => {
+                self.accepts(&Type::Fn(Rc::new(f2.instantiate(&f1.params))))
+            }
+            // Any argument given to the expected function must be accepted
+            (Type::Fn(f1), Type::Fn(f2)) => {
+                f1.params.len() == f2.params.len()
+                    && f1.params.iter().zip(&f2.params).all(|(a, b)| b.accepts(a))
+                    && f1.ret.accepts(&f2.ret)
+            }
+            (a, b) => a == b,
+        }
+    }
+
+    // Finds the types of the parameters in 'self' from the matching parts
+    // of 'arg'. A parameter found several times gets a type accepting all
+    // of them if there is one
+    fn bind(&self, arg: &Type, bindings: &mut HashMap<EcoString, Type>) {
+        match (self, arg) {
+            (Type::Param(name), a) => {
+                let ty = match bindings.get(name) {
+                    Some(b) => b.join(a).unwrap_or(b.clone()),
+                    None => a.clone(),
+                };
+                bindings.insert(name.clone(), ty);
+            }
+            (Type::Nullable(_), Type::Null) => {}
+            (Type::Nullable(t), Type::Nullable(a)) => t.bind(a, bindings),
+            (Type::Nullable(t), a) => t.bind(a, bindings),
+            (Type::List(t), Type::List(a)) => t.bind(a, bindings),
+            (Type::Fn(f), Type::Fn(a)) => {
+                for (p, a) in f.params.iter().zip(&a.params) {
+                    p.bind(a, bindings);
+                }
+                f.ret.bind(&a.ret, bindings);
+            }
+            _ => {}
+        }
+    }
+
+    fn substitute(&self, bindings: &HashMap<EcoString, Type>) -> Type {
+        match self {
+            Type::Param(name) => bindings.get(name).cloned().unwrap_or(self.clone()),
+            Type::Nullable(t) => t.substitute(bindings).nullable(),
+            Type::List(t) => Type::List(Box::new(t.substitute(bindings))),
+            Type::Fn(f) => Type::Fn(Rc::new(FnType {
+                type_params: f.type_params.clone(),
+                params: f.params.iter().map(|p| p.substitute(bindings)).collect(),
+                ret: f.ret.substitute(bindings),
+                param_locs: f.param_locs.clone(),
+            })),
+            t => t.clone(),
+        }
+    }
+
+    // Type of a value that is either of them, None if they have nothing
+    // in common
+    fn join(&self, other: &Type) -> Option<Type> {
+        match (self, other) {
+            (Type::Any, _) | (_, Type::Any) => Some(Type::Any),
+            (a, b) if a.accepts(b) => Some(a.clone()),
+            (a, b) if b.accepts(a) => Some(b.clone()),
+            (Type::Null, t) | (t, Type::Null) => Some(t.clone().nullable()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Var {
+    declared: Type,
+    // Narrowed by the assignments and the null checks
+    current: Type,
+}
+
+type Scopes = Vec<HashMap<EcoString, Var>>;
+
+// Variables and the type they have after a condition
+type Narrowing = Vec<(EcoString, Type)>;
+
+// Static check of the annotated code. A variable declared as 'int?' may be
+// null and can't be used in operations, calls or field accesses until a
+// check narrows it to 'int':
+//
+//   if x != null { x + 1 }
+//   if x == null { return 0 }  // x is an int after it
+//
+// Code without annotations is of type 'any' and never reported. The
+// signatures of the public functions of the imported modules are read from
+// the code given by the loader, the members of the modules it can't load
+// are of type 'any'
+pub fn check_types(
+    stmts: &[Stmt],
+    load: &mut dyn FnMut(&ImportStmt) -> Option<Vec<Stmt>>,
+) -> Vec<PhyResType> {
+    let mut checker = Checker { scopes: vec![HashMap::new()], ..Default::default() };
+    stmts.iter().for_each(|s| fn_assigns(s, false, &mut checker.fn_assigned));
+
+    // Imports are only allowed at the top level
+    for stmt in stmts {
+        if let Stmt::Import(i) = stmt {
+            if let Some(code) = load(i) {
+                checker.modules.insert(i.path.clone(), module_type(&code));
+            }
+        }
+    }
+
+    checker.block(stmts);
+
+    checker.errs
+}
+
+// The errors in the module are reported when checking it
+fn module_type(stmts: &[Stmt]) -> Type {
+    let mut checker = Checker::default();
+    let mut members = BTreeMap::new();
+
+    for stmt in stmts {
+        match stmt {
+            Stmt::FnDecl(f) if f.is_pub => {
+                members.insert(f.name.clone(), checker.signature(f));
+            }
+            Stmt::VarDecl(v) if v.is_pub => {
+                members.insert(v.name.clone(), checker.resolve_opt_ann(&v.ty));
+            }
+            _ => {}
+        }
+    }
+
+    Type::Module(Rc::new(members))
+}
+
+#[derive(Default)]
+struct Checker {
+    scopes: Scopes,
+    modules: HashMap<Vec<EcoString>, Type>,
+    // Return type of the functions being checked
+    returns: Vec<Type>,
+    // Type parameters of the functions being checked
+    generics: Vec<EcoString>,
+    // Last generic call returning a type parameter that isn't in its
+    // parameters, the cause of an 'any' in an inferred type
+    ambiguous: Option<(String, Loc)>,
+    // Names assigned in the body of a function, which a call may run
+    fn_assigned: HashSet<EcoString>,
+    errs: Vec<PhyResType>,
+}
+
+impl Checker {
+    fn error(&mut self, err: TypeErr, loc: Loc) {
+        self.errs.push(PhyResult::new(err, Some(loc)));
+    }
+
+    fn declare(&mut self, name: &EcoString, declared: Type, current: Type) {
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.clone(), Var { declared, current });
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Var> {
+        self.scopes.iter().rev().find_map(|s| s.get(name))
+    }
+
+    fn lookup_mut(&mut self, name: &str) -> Option<&mut Var> {
+        self.scopes.iter_mut().rev().find_map(|s| s.get_mut(name))
+    }
+
+    fn narrow(&mut self, narrowed: &[(EcoString, Type)]) {
+        for (name, ty) in narrowed {
+            if let Some(var) = self.lookup_mut(name) {
+                var.current = ty.clone();
+            }
+        }
+    }
+
+    // A call may run any function, and with it the assignments of their
+    // bodies to the variables they see, the globals and the captured ones.
+    // What is known about these variables is lost: 'a' may be null again
+    // after the call if 'g' assigns it
+    //
+    //   if a != null {
+    //       {
+    //           g()
+    //           a + 1
+    //       }
+    //   }
+    fn called(&mut self) {
+        for (name, var) in self.scopes.iter_mut().flat_map(|s| s.iter_mut()) {
+            if self.fn_assigned.contains(name) {
+                var.current = var.declared.clone();
+            }
+        }
+    }
+
+    // State after either the current one or the other one
+    fn join(&mut self, other: &Scopes) {
+        for (scope, other) in self.scopes.iter_mut().zip(other) {
+            for (name, var) in scope.iter_mut() {
+                if let Some(o) = other.get(name) {
+                    var.current = var.current.join(&o.current).unwrap_or(var.declared.clone());
+                }
+            }
+        }
+    }
+
+    fn resolve_ann(&mut self, ann: &TypeAnn) -> Type {
+        match ann {
+            TypeAnn::Named(name, loc) => match name.as_str() {
+                "int" => Type::Int,
+                "real" => Type::Real,
+                "bool" => Type::Bool,
+                "str" => Type::Str,
+                "char" => Type::Char,
+                "null" => Type::Null,
+                "any" => Type::Any,
+                name if self.generics.iter().any(|g| g == name) => Type::Param(name.into()),
+                _ => {
+                    self.error(TypeErr::UnknownType(name.to_string()), loc.clone());
+                    Type::Any
+                }
+            },
+            TypeAnn::Nullable(ty, _) => self.resolve_ann(ty).nullable(),
+            TypeAnn::List(ty, _) => Type::List(Box::new(self.resolve_ann(ty))),
+            TypeAnn::Fn(params, ret, _) => {
+                let ret = match ret {
+                    Some(ret) => self.resolve_ann(ret),
+                    None => Type::Any,
+                };
+
+                Type::Fn(Rc::new(FnType {
+                    type_params: vec![],
+                    params: params.iter().map(|p| self.resolve_ann(p)).collect(),
+                    ret,
+                    param_locs: params.iter().map(|p| Some(p.get_loc())).collect(),
+                }))
+            }
+        }
+    }
+
+    fn resolve_opt_ann(&mut self, ann: &Option<TypeAnn>) -> Type {
+        match ann {
+            Some(ann) => self.resolve_ann(ann),
+            None => Type::Any,
+        }
+    }
+
+    fn expect(&mut self, expected: &Type, found: &Type, loc: Loc) {
+        if !expected.accepts(found) {
+            self.error(TypeErr::Mismatch(expected.to_string(), found.to_string()), loc);
+        }
+    }
+
+    // Functions can be called before their declaration in the block
+    fn block(&mut self, stmts: &[Stmt]) -> bool {
+        for stmt in stmts {
+            if let Stmt::FnDecl(f) = stmt {
+                let sig = self.signature(f);
+                self.declare(&f.name, sig.clone(), sig);
+            }
+        }
+
+        // All the statements are checked, even after a return
+        let mut returns = false;
+        for stmt in stmts {
+            returns |= self.stmt(stmt);
+        }
+
+        returns
+    }
+
+    fn signature(&mut self, stmt: &FnDeclStmt) -> Type {
+        let outer = self.generics.len();
+        self.generics.extend(stmt.type_params.iter().cloned());
+
+        let func = FnType {
+            type_params: stmt.type_params.clone(),
+            params: stmt.param_types.iter().map(|t| self.resolve_opt_ann(t)).collect(),
+            ret: self.resolve_opt_ann(&stmt.return_type),
+            param_locs: stmt.param_types.iter().map(|t| t.as_ref().map(|t| t.get_loc())).collect(),
+        };
+
+        self.generics.truncate(outer);
+
+        Type::Fn(Rc::new(func))
+    }
+
+    // True if the statement always returns
+    fn stmt(&mut self, stmt: &Stmt) -> bool {
+        match stmt {
+            Stmt::Expr(s) => {
+                self.expr(&s.expr);
+                false
+            }
+            Stmt::VarDecl(s) => {
+                self.var_decl(s);
+                false
+            }
+            Stmt::Block(s) => {
+                self.scopes.push(HashMap::new());
+                let returns = self.block(&s.stmts);
+                self.scopes.pop();
+
+                returns
+            }
+            Stmt::If(s) => self.if_stmt(s),
+            Stmt::While(s) => {
+                self.while_stmt(s);
+                false
+            }
+            Stmt::For(s) => {
+                let item = self.for_item(&s.range);
+                // Each name of 'for i, v in pairs' is given an item of the pair
+                let ty = match (s.rest.is_empty(), item) {
+                    (true, item) => item,
+                    (false, Type::List(t)) => *t,
+                    (false, _) => Type::Any,
+                };
+
+                self.scopes.push(HashMap::new());
+                s.placeholders().for_each(|p| self.declare(&p.name, ty.clone(), ty.clone()));
+                self.loop_body(&s.body, None);
+                self.scopes.pop();
+
+                false
+            }
+            Stmt::FnDecl(s) => {
+                self.fn_decl(s);
+                false
+            }
+            Stmt::Return(s) => {
+                let ty = match &s.value {
+                    Some(v) => self.expr(v),
+                    None => Type::Null,
+                };
+
+                if let Some(expected) = self.returns.last().cloned() {
+                    let loc = s.value.as_ref().map(|v| v.get_loc()).unwrap_or(s.loc.clone());
+                    self.expect(&expected, &ty, loc);
+                }
+
+                true
+            }
+            Stmt::Import(s) => {
+                let ty = self.modules.get(&s.path).cloned().unwrap_or(Type::Any);
+                self.declare(s.name(), ty.clone(), ty);
+
+                false
+            }
+        }
+    }
+
+    fn var_decl(&mut self, stmt: &VarDeclStmt) {
+        let declared = match (&stmt.ty, &stmt.value) {
+            (Some(ann), _) => self.resolve_ann(ann),
+            (None, Some(v)) => return self.infer(stmt, v),
+            (None, None) => Type::Any,
+        };
+
+        let current = match &stmt.value {
+            Some(v) => {
+                let ty = self.expr(v);
+                self.expect(&declared, &ty, v.get_loc());
+
+                // Narrowed to the value, 'var a: int? = 1' is an int
+                match declared.accepts(&ty) && ty != Type::Any {
+                    true => ty,
+                    false => declared.clone(),
+                }
+            }
+            None => {
+                if !declared.accepts(&Type::Null) {
+                    self.error(
+                        TypeErr::Uninitialized(stmt.name.clone(), declared.to_string()),
+                        stmt.name_loc.clone(),
+                    );
+                }
+
+                Type::Null
+            }
+        };
+
+        self.declare(&stmt.name, declared, current);
+    }
+
+    // The variable is of the type of its value, 'var a = 1' is an int that
+    // can't be assigned a string later
+    fn infer(&mut self, stmt: &VarDeclStmt, value: &Expr) {
+        self.ambiguous = None;
+        let ty = self.expr(value);
+
+        let cause = match (&ty, self.ambiguous.take()) {
+            (Type::Null, _) => Some((value.to_string(), value.get_loc())),
+            (t, Some(cause)) if t.contains_any() => Some(cause),
+            _ => None,
+        };
+
+        match cause {
+            Some((expr, loc)) => {
+                self.error(TypeErr::CannotInfer(stmt.name.clone(), expr), loc);
+                self.declare(&stmt.name, Type::Any, ty);
+            }
+            None => self.declare(&stmt.name, ty.clone(), ty),
+        }
+    }
+
+    fn if_stmt(&mut self, stmt: &IfStmt) -> bool {
+        let (when_true, when_false) = self.condition(&stmt.condition);
+        let before = self.scopes.clone();
+
+        self.narrow(&when_true);
+        let then_returns = stmt.then_branch.as_ref().is_some_and(|b| self.stmt(b));
+        let after_then = std::mem::replace(&mut self.scopes, before);
+
+        self.narrow(&when_false);
+        let else_returns = stmt.else_branch.as_ref().is_some_and(|b| self.stmt(b));
+
+        // The code after only runs after the branches that don't return
+        match (then_returns, else_returns) {
+            (true, _) => {}
+            (false, true) => self.scopes = after_then,
+            (false, false) => self.join(&after_then),
+        }
+
+        then_returns && else_returns
+    }
+
+    // Type of the values taken by the placeholder, the range is evaluated
+    // once before the loop
+    fn for_item(&mut self, range: &ForRange) -> Type {
+        match range {
+            ForRange::Ints(..) => Type::Int,
+            ForRange::Bounds(start, end) => {
+                for bound in [start, end] {
+                    let ty = self.used(bound);
+                    self.expect(&Type::Int, &ty, bound.get_loc());
+                }
+
+                Type::Int
+            }
+            ForRange::Items(items) => {
+                let ty = self.used(items);
+                self.expect(&Type::List(Box::new(Type::Any)), &ty, items.get_loc());
+
+                match ty {
+                    Type::List(t) => *t,
+                    _ => Type::Any,
+                }
+            }
+        }
+    }
+
+    fn while_stmt(&mut self, stmt: &WhileStmt) {
+        let when_false = self.loop_body(&stmt.body, Some(&stmt.condition));
+        self.narrow(&when_false);
+    }
+
+    // Each iteration starts with the state before the loop or after any
+    // previous iteration. It is found by checking the loop until the state
+    // doesn't change anymore, before reporting the errors. Returns the
+    // variables narrowed when leaving a 'while'
+    fn loop_body(&mut self, body: &Stmt, condition: Option<&Expr>) -> Narrowing {
+        let errs = self.errs.len();
+
+        loop {
+            let before = self.scopes.clone();
+            self.iteration(body, condition);
+
+            let after = std::mem::replace(&mut self.scopes, before.clone());
+            self.join(&after);
+
+            if self.scopes.iter().zip(&before).all(|(a, b)| {
+                a.iter().all(|(name, var)| b.get(name).is_some_and(|v| v.current == var.current))
+            }) {
+                break;
+            }
+        }
+
+        self.errs.truncate(errs);
+
+        let before = self.scopes.clone();
+        let when_false = self.iteration(body, condition);
+        self.scopes = before;
+
+        when_false
+    }
+
+    fn iteration(&mut self, body: &Stmt, condition: Option<&Expr>) -> Narrowing {
+        let (when_true, when_false) = match condition {
+            Some(c) => self.condition(c),
+            None => (vec![], vec![]),
+        };
+
+        self.narrow(&when_true);
+        self.stmt(body);
+
+        when_false
+    }
+
+    fn fn_decl(&mut self, stmt: &FnDeclStmt) {
+        let Some(Type::Fn(func)) = self.lookup(&stmt.name).map(|v| v.declared.clone()) else {
+            return;
+        };
+
+        // The outer variables can change before the function is called
+        let outer = self.scopes.clone();
+        for var in self.scopes.iter_mut().flat_map(|s| s.values_mut()) {
+            var.current = var.declared.clone();
+        }
+
+        self.scopes.push(HashMap::new());
+        for (name, ty) in stmt.params.iter().zip(&func.params) {
+            self.declare(name, ty.clone(), ty.clone());
+        }
+
+        for cond in stmt.requires.iter() {
+            let ty = self.expr(cond);
+            self.expect(&Type::Bool, &ty, cond.get_loc());
+        }
+
+        // 'result' is only known in the postconditions
+        self.scopes.push(HashMap::new());
+        self.declare(&"result".into(), func.ret.clone(), func.ret.clone());
+        for cond in stmt.ensures.iter() {
+            let ty = self.expr(cond);
+            self.expect(&Type::Bool, &ty, cond.get_loc());
+        }
+        self.scopes.pop();
+
+        let generics = self.generics.len();
+        self.generics.extend(stmt.type_params.iter().cloned());
+
+        self.returns.push(func.ret.clone());
+        let returns = self.block(&stmt.body);
+        self.returns.pop();
+
+        self.generics.truncate(generics);
+
+        if !returns && !func.ret.accepts(&Type::Null) {
+            self.error(
+                TypeErr::MissingReturn(stmt.name.clone(), func.ret.to_string()),
+                stmt.name_loc.clone(),
+            );
+        }
+
+        self.scopes = outer;
+    }
+
+    // Checks the condition and returns the variables narrowed when it is
+    // true and when it is false
+    fn condition(&mut self, cond: &Expr) -> (Narrowing, Narrowing) {
+        let ty = self.expr(cond);
+        self.expect(&Type::Bool, &ty, cond.get_loc());
+
+        self.narrowing(cond)
+    }
+
+    fn narrowing(&self, cond: &Expr) -> (Narrowing, Narrowing) {
+        match cond {
+            Expr::Grouping(e) => self.narrowing(&e.expr),
+            Expr::Unary(e) if e.operator == "!" => {
+                let (t, f) = self.narrowing(&e.right);
+                (f, t)
+            }
+            Expr::Logical(e) => {
+                let (lt, lf) = self.narrowing(&e.left);
+                let (rt, rf) = self.narrowing(&e.right);
+
+                match e.operator.as_str() {
+                    "and" => ([lt, rt].concat(), vec![]),
+                    _ => (vec![], [lf, rf].concat()),
+                }
+            }
+            Expr::Binary(e) if e.operator == "==" || e.operator == "!=" => {
+                let name = match (&*e.left, &*e.right) {
+                    (Expr::Identifier(v), n) | (n, Expr::Identifier(v)) if is_null(n) => &v.name,
+                    _ => return (vec![], vec![]),
+                };
+
+                let Some(var) = self.lookup(name).filter(|v| v.current.is_nullable()) else {
+                    return (vec![], vec![]);
+                };
+
+                // Checking a variable known to be null only makes sense
+                // in code reached after some assignments
+                let ty = match var.current {
+                    Type::Null => var.declared.non_null(),
+                    _ => var.current.non_null(),
+                };
+
+                let not_null = vec![(name.clone(), ty)];
+                let null = vec![(name.clone(), Type::Null)];
+
+                match e.operator.as_str() {
+                    "!=" => (not_null, null),
+                    _ => (null, not_null),
+                }
+            }
+            _ => (vec![], vec![]),
+        }
+    }
+
+    // On mismatch, the argument is reported along with the annotation of
+    // the parameter, which can be far from the call
+    fn argument(&mut self, func: &FnType, idx: usize, arg: &Type, expr: &Expr) {
+        let param = &func.params[idx];
+        if param.accepts(arg) {
+            return;
+        }
+
+        let err = match (param, arg) {
+            (Type::Fn(p), Type::Fn(a)) if p.params.len() != a.params.len() => {
+                TypeErr::CallbackArity(p.params.len(), a.params.len())
+            }
+            _ => TypeErr::Mismatch(param.to_string(), arg.to_string()),
+        };
+        self.error(err, expr.get_loc());
+
+        if let Some(loc) = &func.param_locs[idx] {
+            self.error(TypeErr::ExpectedHere(param.to_string()), loc.clone());
+        }
+    }
+
+    // Nothing is known about the values of a type parameter
+    fn generic_operand(&mut self, operator: &EcoString, ty: &Type, expr: &Expr) {
+        if matches!(ty, Type::Param(_)) {
+            self.error(TypeErr::GenericOperand(operator.clone(), ty.to_string()), expr.get_loc());
+        }
+    }
+
+    // Non null type of a value that is used
+    fn used(&mut self, expr: &Expr) -> Type {
+        let ty = self.expr(expr);
+
+        if ty.is_nullable() {
+            self.error(TypeErr::PossiblyNull(expr.to_string()), expr.get_loc());
+        }
+
+        ty.non_null()
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Type {
+        match expr {
+            Expr::IntLiteral(_) => Type::Int,
+            Expr::RealLiteral(_) => Type::Real,
+            Expr::StrLiteral(_) => Type::Str,
+            Expr::CharLiteral(_) => Type::Char,
+            Expr::ImaginaryLiteral(_) | Expr::UnitLiteral(_) => Type::Any,
+            Expr::Identifier(e) => match e.name.as_str() {
+                "true" | "false" => Type::Bool,
+                "null" => Type::Null,
+                name => self.lookup(name).map(|v| v.current.clone()).unwrap_or(Type::Any),
+            },
+            Expr::Grouping(e) => self.expr(&e.expr),
+            Expr::Unary(e) => match e.operator.as_str() {
+                "!" => {
+                    self.expr(&e.right);
+                    Type::Bool
+                }
+                _ => {
+                    let ty = self.used(&e.right);
+                    self.generic_operand(&e.operator, &ty, &e.right);
+
+                    ty
+                }
+            },
+            Expr::Binary(e) => {
+                if e.operator == "==" || e.operator == "!=" {
+                    self.expr(&e.left);
+                    self.expr(&e.right);
+
+                    return Type::Bool;
+                }
+
+                let left = self.used(&e.left);
+                let right = self.used(&e.right);
+                self.generic_operand(&e.operator, &left, &e.left);
+                self.generic_operand(&e.operator, &right, &e.right);
+
+                match (e.operator.as_str(), left, right) {
+                    ("<" | "<=" | ">" | ">=", _, _) => Type::Bool,
+                    (_, Type::Int, Type::Int) => Type::Int,
+                    (_, Type::Int | Type::Real, Type::Int | Type::Real) => Type::Real,
+                    ("+", Type::Str, Type::Str) | ("*", Type::Str, Type::Int) => Type::Str,
+                    ("*", Type::List(t), Type::Int) | ("*", Type::Int, Type::List(t)) => Type::List(t),
+                    _ => Type::Any,
+                }
+            }
+            Expr::Logical(e) => {
+                let left = self.expr(&e.left);
+                self.expect(&Type::Bool, &left, e.left.get_loc());
+
+                // The right side is only evaluated if the left one is true
+                // for 'and', false for 'or'
+                let (when_true, when_false) = self.narrowing(&e.left);
+                let before = self.scopes.clone();
+                match e.operator.as_str() {
+                    "and" => self.narrow(&when_true),
+                    _ => self.narrow(&when_false),
+                }
+
+                let right = self.expr(&e.right);
+                self.expect(&Type::Bool, &right, e.right.get_loc());
+
+                let after = std::mem::replace(&mut self.scopes, before);
+                self.join(&after);
+
+                Type::Bool
+            }
+            Expr::Assign(e) => {
+                let ty = self.expr(&e.value);
+
+                let Some(declared) = self.lookup(&e.name).map(|v| v.declared.clone()) else {
+                    return Type::Null;
+                };
+
+                self.expect(&declared, &ty, e.value.get_loc());
+
+                let current = match declared.accepts(&ty) && ty != Type::Any {
+                    true => ty.clone(),
+                    false => declared,
+                };
+                self.lookup_mut(&e.name).unwrap().current = current;
+
+                // Like at runtime
+                Type::Null
+            }
+            Expr::Call(e) => {
+                let callee = self.used(&e.callee);
+                let args = e.args.iter().map(|a| self.expr(a)).collect::<Vec<_>>();
+
+                self.called();
+
+                let Type::Fn(mut func) = callee else {
+                    return Type::Any;
+                };
+
+                if !func.type_params.is_empty() {
+                    let unknown = func.type_params.iter().any(|p| {
+                        func.ret.mentions(p) && !func.params.iter().any(|t| t.mentions(p))
+                    });
+
+                    if unknown {
+                        self.ambiguous = Some((expr.to_string(), e.loc.clone()));
+                    }
+
+                    func = Rc::new(func.instantiate(&args));
+                }
+
+                if func.params.len() != args.len() {
+                    self.error(TypeErr::ArgCount(func.params.len(), args.len()), e.loc.clone());
+                    return func.ret.clone();
+                }
+
+                for (i, (arg, expr)) in args.iter().zip(&e.args).enumerate() {
+                    self.argument(&func, i, arg, expr);
+                }
+
+                func.ret.clone()
+            }
+            Expr::Get(e) => match self.used(&e.object) {
+                Type::Module(members) => members.get(&e.name).cloned().unwrap_or(Type::Any),
+                _ => Type::Any,
+            },
+            Expr::Set(e) => {
+                self.used(&e.object);
+                self.expr(&e.value)
+            }
+            Expr::Index(e) => {
+                let object = self.used(&e.object);
+                for bound in e.bounds() {
+                    let ty = self.used(bound);
+                    self.expect(&Type::Int, &ty, bound.get_loc());
+                }
+
+                match (object, e.is_slice) {
+                    (Type::Str, true) => Type::Str,
+                    (Type::Str, false) => Type::Char,
+                    (Type::List(t), true) => Type::List(t),
+                    (Type::List(t), false) => *t,
+                    _ => Type::Any,
+                }
+            }
+            // Items of different types make a list of 'any'
+            Expr::List(e) => {
+                let item = e.items.iter().map(|i| self.expr(i)).try_fold(None, |acc, ty| {
+                    match acc {
+                        None => Some(Some(ty)),
+                        Some(acc) => Type::join(&acc, &ty).map(Some),
+                    }
+                });
+
+                Type::List(Box::new(item.flatten().unwrap_or(Type::Any)))
+            }
+            Expr::Comprehension(e) => {
+                let source = self.used(&e.source);
+                let item = match &e.end {
+                    Some(end) => {
+                        self.expect(&Type::Int, &source, e.source.get_loc());
+                        let end_ty = self.used(end);
+                        self.expect(&Type::Int, &end_ty, end.get_loc());
+
+                        Type::Int
+                    }
+                    None => {
+                        let list = Type::List(Box::new(Type::Any));
+                        self.expect(&list, &source, e.source.get_loc());
+
+                        match source {
+                            Type::List(t) => *t,
+                            _ => Type::Any,
+                        }
+                    }
+                };
+
+                // The value and the condition may never be evaluated
+                let before = self.scopes.clone();
+                self.scopes.push(HashMap::new());
+                self.declare(&e.name, item.clone(), item);
+
+                if let Some(cond) = &e.cond {
+                    let (when_true, _) = self.condition(cond);
+                    self.narrow(&when_true);
+                }
+                let value = self.expr(&e.value);
+
+                self.scopes.pop();
+                let after = std::mem::replace(&mut self.scopes, before);
+                self.join(&after);
+
+                Type::List(Box::new(value))
+            }
+            // Task handles and their results aren't followed. Other tasks
+            // may run while waiting for one
+            Expr::Spawn(e) => {
+                self.expr(&e.call);
+                Type::Any
+            }
+            Expr::Await(e) => {
+                self.expr(&e.task);
+                self.called();
+                Type::Any
+            }
+        }
+    }
+}
+
+// Names assigned in the bodies of the functions, nested ones included
+fn fn_assigns(stmt: &Stmt, in_fn: bool, names: &mut HashSet<EcoString>) {
+    match stmt {
+        Stmt::Expr(s) => expr_assigns(&s.expr, in_fn, names),
+        Stmt::VarDecl(s) => s.value.iter().for_each(|v| expr_assigns(v, in_fn, names)),
+        Stmt::Block(s) => s.stmts.iter().for_each(|s| fn_assigns(s, in_fn, names)),
+        Stmt::If(s) => {
+            expr_assigns(&s.condition, in_fn, names);
+            s.then_branch.iter().chain(&s.else_branch).for_each(|b| fn_assigns(b, in_fn, names));
+        }
+        Stmt::While(s) => {
+            expr_assigns(&s.condition, in_fn, names);
+            fn_assigns(&s.body, in_fn, names);
+        }
+        Stmt::For(s) => {
+            s.range.exprs().for_each(|e| expr_assigns(e, in_fn, names));
+            fn_assigns(&s.body, in_fn, names);
+        }
+        Stmt::FnDecl(s) => s.body.iter().for_each(|s| fn_assigns(s, true, names)),
+        Stmt::Return(s) => s.value.iter().for_each(|v| expr_assigns(v, in_fn, names)),
+        Stmt::Import(_) => {}
+    }
+}
+
+fn expr_assigns(expr: &Expr, in_fn: bool, names: &mut HashSet<EcoString>) {
+    match expr {
+        Expr::Assign(a) => {
+            if in_fn {
+                names.insert(a.name.clone());
+            }
+            expr_assigns(&a.value, in_fn, names);
+        }
+        Expr::Binary(b) => {
+            expr_assigns(&b.left, in_fn, names);
+            expr_assigns(&b.right, in_fn, names);
+        }
+        Expr::Logical(l) => {
+            expr_assigns(&l.left, in_fn, names);
+            expr_assigns(&l.right, in_fn, names);
+        }
+        Expr::Grouping(g) => expr_assigns(&g.expr, in_fn, names),
+        Expr::Unary(u) => expr_assigns(&u.right, in_fn, names),
+        Expr::Call(c) => {
+            expr_assigns(&c.callee, in_fn, names);
+            c.args.iter().for_each(|a| expr_assigns(a, in_fn, names));
+        }
+        Expr::Get(g) => expr_assigns(&g.object, in_fn, names),
+        Expr::Set(s) => {
+            expr_assigns(&s.object, in_fn, names);
+            expr_assigns(&s.value, in_fn, names);
+        }
+        Expr::Index(i) => {
+            expr_assigns(&i.object, in_fn, names);
+            i.bounds().for_each(|b| expr_assigns(b, in_fn, names));
+        }
+        Expr::List(l) => l.items.iter().for_each(|i| expr_assigns(i, in_fn, names)),
+        Expr::Comprehension(c) => c.sub_exprs().for_each(|e| expr_assigns(e, in_fn, names)),
+        Expr::Spawn(s) => expr_assigns(&s.call, in_fn, names),
+        Expr::Await(a) => expr_assigns(&a.task, in_fn, names),
+        _ => {}
+    }
+}
+
+fn is_null(expr: &Expr) -> bool {
+    match expr {
+        Expr::Identifier(e) => e.name == "null",
+        Expr::Grouping(e) => is_null(&e.expr),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use frontend::parser::utils::lex_and_parse;
+
+    use super::{check_types, TypeErr};
+
+    fn errors(code: &str) -> Vec<TypeErr> {
+        let nodes = lex_and_parse(code).unwrap();
+        check_types(&nodes, &mut |_| None).into_iter().map(|e| e.err).collect()
+    }
+
+    fn null(name: &str) -> TypeErr {
+        TypeErr::PossiblyNull(name.into())
+    }
+
+    #[test]
+    fn annotations() {
+        let code = "
+var a: int = 1
+var b: real = a
+var c: str = a
+var d: int
+var e: int? = null
+var f: vec = 1
+fn add(x: int, y: int) -> int { return x + y }
+add(1, \"2\")
+add(1)
+var g: str = add(1, 2)
+fn half(x: int) -> int {
+    if x > 0 { return x / 2 }
+}
+var h = add
+h = 2
+";
+        assert_eq!(
+            errors(code),
+            [
+                TypeErr::Mismatch("str".into(), "int".into()),
+                TypeErr::Uninitialized("d".into(), "int".into()),
+                TypeErr::UnknownType("vec".into()),
+                TypeErr::Mismatch("int".into(), "str".into()),
+                TypeErr::ExpectedHere("int".into()),
+                TypeErr::ArgCount(2, 1),
+                TypeErr::Mismatch("str".into(), "int".into()),
+                TypeErr::MissingReturn("half".into(), "int".into()),
+                TypeErr::Mismatch("fn(int, int) -> int".into(), "int".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn null_checks() {
+        let code = "
+fn find(x: int) -> int? {
+    if x > 0 { return x }
+    return null
+}
+var a = find(1)
+var b: int? = 1
+a + 1
+b + 1
+if a != null { a + 1 }
+if a != null and a > 0 { a + 1 }
+if a == null or a > 0 { a + 1 }
+if a == null {} else { a + 1 }
+fn first(x: int?) -> int {
+    if x == null { return 0 }
+    return x + 1
+}
+while a == null { a = find(2) }
+a + 1
+";
+        assert_eq!(errors(code), [null("a"), null("a")]);
+    }
+
+    #[test]
+    fn flow() {
+        let code = "
+var a: int? = 1
+if a > 0 { a = null }
+a + 1
+var b: int? = 1
+while b > 0 {
+    print(b + 1)
+    b = null
+}
+var c: int? = 1
+fn f() { c + 1 }
+var d: int? = null
+for i in 0..3 { if d != null { d + i } }
+d.field
+d()
+";
+        assert_eq!(errors(code), [null("a"), null("b"), null("b"), null("c"), null("d"), null("d")]);
+
+        // Functions assigning the variables forget their checks when called
+        let code = "
+var a: int? = 3
+var q: int = 0
+fn g() { a = null }
+if a != null {
+    {
+        g()
+        q = a
+    }
+}
+var b: int? = 3
+fn h() {
+    fn reset() { b = null }
+    if b != null {
+        {
+            reset()
+            b + 1
+        }
+    }
+}
+var c: int? = 3
+fn keep() { var c = 1 }
+if c != null {
+    {
+        print(c)
+        c + 1
+    }
+}
+";
+        assert_eq!(
+            errors(code),
+            [TypeErr::Mismatch("int".into(), "int?".into()), null("b")]
+        );
+    }
+
+    #[test]
+    fn callbacks() {
+        let module = "
+pub fn map(l, f: fn(any) -> any) { return l }
+pub fn filter(l, keep: fn(int) -> bool) { return l }
+fn private(f: fn()) {}
+";
+        let code = "
+import utils
+fn double(x: int) -> int { return x * 2 }
+fn add(a: int, b: int) -> int { return a + b }
+fn is_even(x: int) -> bool { return x % 2 == 0 }
+fn name(x: int) -> str { return \"x\" }
+utils.map(1, double)
+utils.map(1, add)
+utils.filter(1, is_even)
+utils.filter(1, name)
+utils.private(1)
+fn apply(f: fn(int) -> int, x: int) -> int { return f(x) }
+apply(double, 1)
+apply(is_even, 1)
+var f: fn(int) -> int = add
+f(\"1\")
+";
+        let nodes = lex_and_parse(code).unwrap();
+        let errs = check_types(&nodes, &mut |import| {
+            assert_eq!(import.name(), "utils");
+            Some(lex_and_parse(module).unwrap())
+        });
+
+        let errs = errs.into_iter().map(|e| e.err).collect::<Vec<_>>();
+        assert_eq!(
+            errs,
+            [
+                TypeErr::CallbackArity(1, 2),
+                TypeErr::ExpectedHere("fn(any) -> any".into()),
+                TypeErr::Mismatch("fn(int) -> bool".into(), "fn(int) -> str".into()),
+                TypeErr::ExpectedHere("fn(int) -> bool".into()),
+                TypeErr::Mismatch("fn(int) -> int".into(), "fn(int) -> bool".into()),
+                TypeErr::ExpectedHere("fn(int) -> int".into()),
+                TypeErr::Mismatch("fn(int) -> int".into(), "fn(int, int) -> int".into()),
+                TypeErr::Mismatch("int".into(), "str".into()),
+                TypeErr::ExpectedHere("int".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn generics() {
+        let code = "
+fn first<T>(l: [T]) -> T { return list.get(l, 0) }
+fn pick<T>(a: T, b: T, left: bool) -> T {
+    if left { return a }
+    return b
+}
+fn apply<T, U>(x: T, f: fn(T) -> U) -> U { return f(x) }
+fn length(s: str) -> int { return 0 }
+var ints: [int] = list.new()
+var a: int = first(ints)
+var b: str = first(ints)
+var c: real = pick(1, 2.5, true)
+var d: int = pick(1, 2.5, true)
+var e: int = apply(\"abc\", length)
+apply(1, length)
+fn broken<T>(x: T) -> T {
+    var y: T = x
+    return x + 1
+}
+fn wrong<T>(x: T) -> T { return 0 }
+var f: fn(int) -> int = first
+var g: fn([str]) -> str = first
+";
+        assert_eq!(
+            errors(code),
+            [
+                TypeErr::Mismatch("str".into(), "int".into()),
+                TypeErr::Mismatch("int".into(), "real".into()),
+                TypeErr::Mismatch("fn(int) -> int".into(), "fn(str) -> int".into()),
+                TypeErr::ExpectedHere("fn(int) -> int".into()),
+                TypeErr::GenericOperand("+".into(), "T".into()),
+                TypeErr::Mismatch("T".into(), "int".into()),
+                TypeErr::Mismatch("fn(int) -> int".into(), "fn<T>([T]) -> T".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn inference() {
+        let code = "
+fn add(x: int, y: int) -> int { return x + y }
+fn empty<T>() -> [T] { return list.new() }
+fn id<T>(x: T) -> T { return x }
+var a = 3
+a = \"three\"
+var b = add
+b(1, 2.5)
+var c = add(1, 2) * 1.5
+c = 1
+var d = null
+d = 1
+var e = id(empty())
+var f: [int] = empty()
+var g = id(1)
+g = 2
+var h = list.new()
+h = 1
+";
+        assert_eq!(
+            errors(code),
+            [
+                TypeErr::Mismatch("int".into(), "str".into()),
+                TypeErr::Mismatch("int".into(), "real".into()),
+                TypeErr::ExpectedHere("int".into()),
+                TypeErr::CannotInfer("d".into(), "null".into()),
+                TypeErr::CannotInfer("e".into(), "empty()".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lists() {
+        let code = "
+var a: [real] = [1, 2.5]
+var b: [int?] = [1, null]
+var c: [int] = []
+var d: [int] = [1, \"a\"]
+var e: [str] = [1, 2]
+var f: [[int]] = [[1], [2.5]]
+var g: [int] = [0] * 3
+var h: [str] = 2 * [0]
+";
+        assert_eq!(
+            errors(code),
+            [
+                TypeErr::Mismatch("[str]".into(), "[int]".into()),
+                TypeErr::Mismatch("[[int]]".into(), "[[real]]".into()),
+                TypeErr::Mismatch("[str]".into(), "[int]".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn for_loops() {
+        let code = "
+var l: [str] = [\"a\"]
+var pairs: [[int]] = [[1, 2]]
+var k: str = \"3\"
+var m: int = 3
+for s in l { var n: int = s }
+for a, b in pairs { var t: str = b }
+for i, v in enumerate(l) { var u: str = v }
+for i in 0..k { var w: int = i }
+for x in m {}
+";
+        assert_eq!(
+            errors(code),
+            [
+                TypeErr::Mismatch("int".into(), "str".into()),
+                TypeErr::Mismatch("str".into(), "int".into()),
+                TypeErr::Mismatch("int".into(), "str".into()),
+                TypeErr::Mismatch("[any]".into(), "int".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn comprehensions() {
+        let code = "
+fn find(x: int) -> int? { return null }
+var squares: [int] = [x * x for x in 0..10]
+var names: [str] = [x for x in squares]
+var found: [int] = [x + 1 for x in [find(i) for i in 0..3] if x != null]
+var a = [x for x in 0..\"10\"]
+var b = [c for c in 5]
+var c = [x for x in squares if x]
+";
+        assert_eq!(
+            errors(code),
+            [
+                TypeErr::Mismatch("[str]".into(), "[int]".into()),
+                TypeErr::Mismatch("int".into(), "str".into()),
+                TypeErr::Mismatch("[any]".into(), "int".into()),
+                TypeErr::Mismatch("bool".into(), "int".into()),
+            ]
+        );
+    }
+}
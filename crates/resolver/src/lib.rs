@@ -1,4 +1,7 @@
 pub mod resolver;
+pub mod symbols;
+pub mod lint;
+pub mod checker;
 #[cfg(test)]
 mod utils;
 
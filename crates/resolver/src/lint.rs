@@ -0,0 +1,476 @@
+use std::collections::{HashMap, HashSet};
+
+use colored::Colorize;
+use ecow::EcoString;
+use thiserror::Error;
+use tools::{
+    messages::{localize, text, Arg, Locale, Translate},
+    results::{Loc, PhyReport, PhyResult},
+};
+
+use frontend::ast::{
+    expr::Expr,
+    stmt::{Attribute, FnDeclStmt, Stmt},
+};
+
+use crate::{
+    resolver::{Resolver, ResolverErr},
+    symbols::SymbolKind,
+};
+
+#[derive(Debug, Error, PartialEq, Clone)]
+pub enum LintWarn {
+    #[error("function '{0}' is never used")]
+    UnusedFunction(EcoString),
+
+    #[error("variable '{0}' is never used")]
+    UnusedVariable(EcoString),
+
+    #[error("condition is always the same")]
+    ConstantCondition,
+
+    #[error("empty block")]
+    EmptyBlock,
+
+    #[error("function '{0}' is deprecated")]
+    Deprecated(EcoString),
+
+    #[error("function '{0}' is deprecated: {1}")]
+    DeprecatedWith(EcoString, EcoString),
+
+    #[error("unknown attribute '{0}'")]
+    UnknownAttribute(EcoString),
+}
+
+impl Translate for LintWarn {
+    fn category(&self) -> &'static str {
+        "lint"
+    }
+
+    // Name used in the '@allow', '@warn' and '@deny' attributes
+    fn code(&self) -> &'static str {
+        match self {
+            LintWarn::UnusedFunction(_) => "unused_function",
+            LintWarn::UnusedVariable(_) => "unused_variable",
+            LintWarn::ConstantCondition => "constant_condition",
+            LintWarn::EmptyBlock => "empty_block",
+            LintWarn::Deprecated(_) | LintWarn::DeprecatedWith(..) => "deprecated",
+            LintWarn::UnknownAttribute(_) => "unknown_attribute",
+        }
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        match self {
+            LintWarn::UnusedFunction(name)
+            | LintWarn::UnusedVariable(name)
+            | LintWarn::Deprecated(name)
+            | LintWarn::UnknownAttribute(name) => vec![Arg::text(name)],
+            LintWarn::DeprecatedWith(name, msg) => vec![Arg::text(name), Arg::text(msg)],
+            _ => vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl LintLevel {
+    fn from_attribute(name: &str) -> Option<Self> {
+        match name {
+            "allow" => Some(LintLevel::Allow),
+            "warn" => Some(LintLevel::Warn),
+            "deny" => Some(LintLevel::Deny),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Lint {
+    pub warn: LintWarn,
+    pub level: LintLevel,
+}
+
+impl PhyReport for Lint {
+    fn get_err_msg(&self, locale: &dyn Locale) -> String {
+        let code = self.warn.code();
+        let prefix = match self.level {
+            LintLevel::Deny => {
+                format!("{} [{}]:", text(locale, "label.lint_error", "Error"), code).red()
+            }
+            _ => format!("{} [{}]:", text(locale, "label.lint_warning", "Warning"), code).yellow(),
+        };
+
+        format!("{} {}", prefix, localize(locale, &self.warn))
+    }
+}
+
+pub type PhyResLint = PhyResult<Lint>;
+
+// Attributes read by the passes, the other ones are reported
+const FILE_ATTRIBUTES: [&str; 6] = ["strict", "typed", "redefine", "allow", "warn", "deny"];
+const FN_ATTRIBUTES: [&str; 6] = ["allow", "warn", "deny", "inline", "deprecated", "test"];
+
+// Warnings of the program, in source order. Names starting with '_' and
+// the 'pub' declarations aren't reported as unused, each use of a function
+// marked '@deprecated' is. The level of each
+// warning is set with '@allow(code, ...)', '@warn(...)' or '@deny(...)'
+// on a function, for its whole body, or with '@!allow(...)' for the file.
+// The innermost one wins, allowed warnings aren't returned
+pub fn check(
+    stmts: &[Stmt],
+    file_attributes: &[Attribute],
+) -> Result<Vec<PhyResLint>, PhyResult<ResolverErr>> {
+    let mut resolver = Resolver::default();
+    resolver.resolve(stmts)?;
+    let symbols = resolver.into_symbols();
+
+    let mut linter = Linter::default();
+    linter.add_levels(&Loc::new(0, usize::MAX), file_attributes);
+    linter.check_attributes(file_attributes, &FILE_ATTRIBUTES);
+    stmts.iter().for_each(|s| linter.stmt(s));
+
+    for (id, symbol) in symbols.symbols().iter().enumerate() {
+        if symbol.kind == SymbolKind::Function {
+            if let Some(msg) = linter.deprecated.get(&symbol.decl.start) {
+                let warn = match msg {
+                    Some(msg) => LintWarn::DeprecatedWith(symbol.name.clone(), msg.clone()),
+                    None => LintWarn::Deprecated(symbol.name.clone()),
+                };
+
+                for loc in symbols.references_of(id) {
+                    linter.warn(warn.clone(), &loc);
+                }
+            }
+        }
+
+        // Macro expansions rename their declarations with a '#'
+        if symbol.name.starts_with('_')
+            || symbol.name.contains('#')
+            || linter.exported.contains(&symbol.decl.start)
+            || !symbols.references_of(id).is_empty()
+        {
+            continue;
+        }
+
+        let warn = match symbol.kind {
+            SymbolKind::Function => LintWarn::UnusedFunction(symbol.name.clone()),
+            SymbolKind::Variable | SymbolKind::Parameter => {
+                LintWarn::UnusedVariable(symbol.name.clone())
+            }
+            SymbolKind::Module => continue,
+        };
+
+        linter.warn(warn, &symbol.decl);
+    }
+
+    let mut lints: Vec<PhyResLint> = linter
+        .warns
+        .iter()
+        .map(|(warn, loc)| {
+            let lint = Lint { warn: warn.clone(), level: linter.level_of(warn, loc) };
+            PhyResult::new(lint, Some(loc.clone()))
+        })
+        .filter(|l| l.err.level != LintLevel::Allow)
+        .collect();
+
+    lints.sort_by_key(|l| l.loc.as_ref().map(|l| l.start));
+
+    Ok(lints)
+}
+
+#[derive(Default)]
+struct Linter {
+    warns: Vec<(LintWarn, Loc)>,
+    // Span, warning code and level, the outer scopes first
+    levels: Vec<(Loc, EcoString, LintLevel)>,
+    // Start of the names declared with 'pub'
+    exported: HashSet<usize>,
+    // Start of the names of the '@deprecated' functions, with their message
+    deprecated: HashMap<usize, Option<EcoString>>,
+}
+
+impl Linter {
+    fn warn(&mut self, warn: LintWarn, loc: &Loc) {
+        self.warns.push((warn, loc.clone()));
+    }
+
+    fn add_levels(&mut self, span: &Loc, attributes: &[Attribute]) {
+        for attr in attributes {
+            let Some(level) = LintLevel::from_attribute(&attr.name) else { continue };
+
+            for arg in &attr.args {
+                if let Expr::Identifier(i) = arg {
+                    self.levels.push((span.clone(), i.name.clone(), level));
+                }
+            }
+        }
+    }
+
+    fn check_attributes(&mut self, attributes: &[Attribute], known: &[&str]) {
+        for attr in attributes {
+            if !known.contains(&attr.name.as_str()) {
+                self.warn(LintWarn::UnknownAttribute(attr.name.clone()), &attr.loc);
+            }
+        }
+    }
+
+    fn level_of(&self, warn: &LintWarn, loc: &Loc) -> LintLevel {
+        self.levels
+            .iter()
+            .rev()
+            .find(|(span, code, _)| span.start <= loc.start && loc.start < span.end && code == warn.code())
+            .map(|(_, _, level)| *level)
+            .unwrap_or(LintLevel::Warn)
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block(s) => {
+                if s.stmts.is_empty() {
+                    self.warn(LintWarn::EmptyBlock, &s.loc);
+                }
+
+                s.stmts.iter().for_each(|s| self.stmt(s));
+            }
+            Stmt::If(s) => {
+                if is_constant(&s.condition) {
+                    self.warn(LintWarn::ConstantCondition, &s.condition.get_loc());
+                }
+
+                match &s.then_branch {
+                    Some(b) => self.stmt(b),
+                    None => self.warn(LintWarn::EmptyBlock, &s.loc),
+                }
+
+                if let Some(b) = &s.else_branch {
+                    self.stmt(b);
+                }
+            }
+            Stmt::While(s) => {
+                // 'while true' is the way to write an endless loop
+                let endless = matches!(&s.condition, Expr::Identifier(i) if i.name == "true");
+
+                if is_constant(&s.condition) && !endless {
+                    self.warn(LintWarn::ConstantCondition, &s.condition.get_loc());
+                }
+
+                self.stmt(&s.body);
+            }
+            Stmt::For(s) => self.stmt(&s.body),
+            Stmt::FnDecl(s) => self.fn_decl(s),
+            Stmt::VarDecl(s) if s.is_pub => {
+                self.exported.insert(s.name_loc.start);
+            }
+            Stmt::VarDecl(_) | Stmt::Expr(_) | Stmt::Return(_) | Stmt::Import(_) => {}
+        }
+    }
+
+    fn fn_decl(&mut self, stmt: &FnDeclStmt) {
+        if stmt.is_pub {
+            self.exported.insert(stmt.name_loc.start);
+        }
+
+        if stmt.body.is_empty() {
+            self.warn(LintWarn::EmptyBlock, &stmt.name_loc);
+        }
+
+        // The levels apply to the attributes too
+        let span = match stmt.attributes.first() {
+            Some(a) => Loc::new(a.loc.start.min(stmt.loc.start), stmt.loc.end),
+            None => stmt.loc.clone(),
+        };
+
+        self.add_levels(&span, &stmt.attributes);
+        self.check_attributes(&stmt.attributes, &FN_ATTRIBUTES);
+
+        if let Some(attr) = stmt.get_attribute("deprecated") {
+            let msg = match attr.args.first() {
+                Some(Expr::StrLiteral(msg)) => Some(msg.value.clone()),
+                _ => None,
+            };
+
+            self.deprecated.insert(stmt.name_loc.start, msg);
+        }
+
+        stmt.body.iter().for_each(|s| self.stmt(s));
+    }
+}
+
+// Only made of literals, true, false and null being parsed as identifiers
+fn is_constant(expr: &Expr) -> bool {
+    match expr {
+        Expr::IntLiteral(_)
+        | Expr::RealLiteral(_)
+        | Expr::ImaginaryLiteral(_)
+        | Expr::StrLiteral(_)
+        | Expr::CharLiteral(_)
+        | Expr::UnitLiteral(_) => true,
+        Expr::Identifier(i) => matches!(i.name.as_str(), "true" | "false" | "null"),
+        Expr::Grouping(g) => is_constant(&g.expr),
+        Expr::Unary(u) => is_constant(&u.right),
+        Expr::Binary(b) => is_constant(&b.left) && is_constant(&b.right),
+        Expr::Logical(l) => is_constant(&l.left) && is_constant(&l.right),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use frontend::{lexer::Lexer, parser::Parser};
+
+    use super::{check, LintLevel, LintWarn};
+
+    fn lints(code: &str) -> Vec<(LintWarn, LintLevel)> {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(code).unwrap();
+        let mut parser = Parser::default();
+        let nodes = parser.parse(tokens).unwrap();
+
+        check(&nodes, parser.file_attributes())
+            .unwrap()
+            .into_iter()
+            .map(|l| (l.err.warn, l.err.level))
+            .collect()
+    }
+
+    fn warns(code: &str) -> Vec<LintWarn> {
+        lints(code).into_iter().map(|(w, _)| w).collect()
+    }
+
+    #[test]
+    fn warnings() {
+        let code = "
+fn unused(a, _b) {
+    return 0
+}
+fn used() {}
+var x = 1
+var _y = 2
+pub var z = 3
+used()
+if 1 < 2 { print(x) }
+if x > 0 {
+} else {
+    print(x)
+}
+while true { x = x + 1 }
+while false { x = x + 1 }
+for i in 0..2 {
+    {}
+}
+";
+        assert_eq!(
+            warns(code),
+            [
+                LintWarn::UnusedFunction("unused".into()),
+                LintWarn::UnusedVariable("a".into()),
+                LintWarn::EmptyBlock,
+                LintWarn::ConstantCondition,
+                LintWarn::EmptyBlock,
+                LintWarn::ConstantCondition,
+                LintWarn::UnusedVariable("i".into()),
+                LintWarn::EmptyBlock,
+            ]
+        );
+    }
+
+    #[test]
+    fn allow() {
+        let code = "
+@allow(unused_variable, constant_condition)
+fn f(a) {
+    var b = 1
+    if true { return 0 }
+    {}
+}
+f(1)
+";
+        assert_eq!(warns(code), [LintWarn::EmptyBlock]);
+    }
+
+    #[test]
+    fn levels() {
+        let code = "
+@!deny(unused_variable)
+@!allow(empty_block)
+fn f(a) {
+    var b = 1
+    {}
+}
+@allow(unused_variable)
+@warn(empty_block)
+fn g(c) {
+    @warn(unused_variable)
+    fn h(d) {}
+    h(1)
+    {}
+}
+f(1)
+g(1)
+";
+        assert_eq!(
+            lints(code),
+            [
+                (LintWarn::UnusedVariable("a".into()), LintLevel::Deny),
+                (LintWarn::UnusedVariable("b".into()), LintLevel::Deny),
+                (LintWarn::EmptyBlock, LintLevel::Warn),
+                (LintWarn::UnusedVariable("d".into()), LintLevel::Warn),
+                (LintWarn::EmptyBlock, LintLevel::Warn),
+            ]
+        );
+    }
+
+    #[test]
+    fn deprecated() {
+        let code = "
+@deprecated(\"use g\")
+fn f() { return 1 }
+@deprecated
+fn g() { return f() }
+var a = g() + f()
+var b = a
+@allow(deprecated)
+fn h() { return g() }
+print(b, h)
+";
+        assert_eq!(
+            warns(code),
+            [
+                LintWarn::DeprecatedWith("f".into(), "use g".into()),
+                LintWarn::Deprecated("g".into()),
+                LintWarn::DeprecatedWith("f".into(), "use g".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_attributes() {
+        let code = "
+@!strict
+@!typo
+@!allow(empty_block)
+@inline
+@inlin
+@test
+fn f() {}
+@!redefine
+@deny(unknown_attribute)
+@example
+fn g() { return f() }
+print(g())
+";
+        assert_eq!(
+            lints(code),
+            [
+                (LintWarn::UnknownAttribute("typo".into()), LintLevel::Warn),
+                (LintWarn::UnknownAttribute("inlin".into()), LintLevel::Warn),
+                (LintWarn::UnknownAttribute("example".into()), LintLevel::Deny),
+            ]
+        );
+    }
+}
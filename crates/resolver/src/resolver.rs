@@ -3,28 +3,66 @@ use std::collections::HashMap;
 use colored::Colorize;
 use ecow::EcoString;
 use thiserror::Error;
-use tools::results::{PhyReport, PhyResult};
+use tools::{
+    messages::{localize, text, Arg, Locale, Translate},
+    results::{Loc, PhyReport, PhyResult},
+};
+
+use crate::symbols::{Capture, ScopeId, ScopeKind, SymbolId, SymbolKind, SymbolTable};
 
 use frontend::ast::{
     expr::{
-        AssignExpr, BinaryExpr, CallExpr, Expr, GroupingExpr, IdentifierExpr, IntLiteralExpr,
-        LogicalExpr, RealLiteralExpr, StrLiteralExpr, UnaryExpr, VisitExpr,
+        AssignExpr, AwaitExpr, BinaryExpr, CallExpr, CharLiteralExpr, ComprehensionExpr, Expr,
+        GetExpr, GroupingExpr, IdentifierExpr, ImaginaryLiteralExpr, IndexExpr, IntLiteralExpr,
+        ListExpr, LogicalExpr, RealLiteralExpr, SetExpr, SpawnExpr, StrLiteralExpr, UnaryExpr,
+        UnitLiteralExpr, VisitExpr,
     },
     stmt::{
-        BlockStmt, ExprStmt, FnDeclStmt, ForStmt, IfStmt, PrintStmt, ReturnStmt, Stmt, VarDeclStmt,
+        BlockStmt, ExprStmt, FnDeclStmt, ForStmt, IfStmt, ImportStmt, ReturnStmt, Stmt, VarDeclStmt,
         VisitStmt, WhileStmt,
     },
 };
 
 #[derive(Error, Debug, PartialEq)]
 pub enum ResolverErr {
-    #[error("local variable initializer is shadoweding global variable")]
-    LocalVarInOwnInit,
+    #[error("variable '{0}' is read in its own initializer")]
+    VarInOwnInit(EcoString),
+
+    #[error("parameter '{0}' is declared more than once")]
+    DuplicateParam(EcoString),
+
+    #[error("return outside of function")]
+    ReturnOutsideFn,
 }
 
 impl PhyReport for ResolverErr {
-    fn get_err_msg(&self) -> String {
-        format!("{}: ", "Resolver error".red())
+    fn get_err_msg(&self, locale: &dyn Locale) -> String {
+        format!(
+            "{} {}",
+            text(locale, "label.resolver", "Resolver error:").red(),
+            localize(locale, self)
+        )
+    }
+}
+
+impl Translate for ResolverErr {
+    fn category(&self) -> &'static str {
+        "resolver"
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            ResolverErr::VarInOwnInit(..) => "VarInOwnInit",
+            ResolverErr::DuplicateParam(..) => "DuplicateParam",
+            ResolverErr::ReturnOutsideFn => "ReturnOutsideFn",
+        }
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        match self {
+            ResolverErr::VarInOwnInit(v) | ResolverErr::DuplicateParam(v) => vec![Arg::text(v)],
+            _ => vec![],
+        }
     }
 }
 
@@ -40,6 +78,20 @@ pub type ResolverRes = Result<(), PhyResult<ResolverErr>>;
 pub struct Resolver {
     scopes: Vec<HashMap<EcoString, bool>>,
     locals: HashMap<EcoString, usize>,
+    // Same as the scopes with the globals at the bottom, to link each use
+    // of a name to its declaration
+    symbol_scopes: Vec<HashMap<EcoString, SymbolId>>,
+    // Scope in the table of each of the symbol scopes
+    scope_ids: Vec<ScopeId>,
+    symbols: SymbolTable,
+    // Uses of names not declared yet, globals can be declared after the
+    // functions using them
+    pending: Vec<(EcoString, Loc)>,
+    // Number of functions around the code being resolved
+    fn_depth: usize,
+    // Variables whose initializer is being resolved. Globals have no scope
+    // to track it
+    initializing: Vec<EcoString>,
 }
 
 // If we can’t find it in the stack of local scopes, we assume it must be global
@@ -92,27 +144,111 @@ impl Resolver {
             .insert(name.clone(), true);
     }
 
+    // Symbols of the resolved code. The remaining uses of undeclared names
+    // are linked to the globals declared after them
+    pub fn into_symbols(mut self) -> SymbolTable {
+        for (name, loc) in std::mem::take(&mut self.pending) {
+            if let Some(id) = self.symbol_scopes.first().and_then(|g| g.get(&name)) {
+                self.symbols.add_use(loc, *id);
+            }
+        }
+
+        self.symbols
+    }
+
+    fn declare_symbol(&mut self, name: &EcoString, kind: SymbolKind, loc: &Loc) {
+        self.open_global_scope();
+
+        let scope = *self.scope_ids.last().unwrap();
+        let id = self.symbols.declare(name.clone(), kind, loc.clone(), scope);
+        self.symbol_scopes.last_mut().unwrap().insert(name.clone(), id);
+    }
+
+    // A variable of an enclosing function or block used in a function is
+    // captured by it
+    fn use_symbol(&mut self, name: &EcoString, loc: &Loc) {
+        let found = self
+            .symbol_scopes
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(depth, s)| s.get(name).map(|id| (depth, *id)));
+
+        let Some((depth, id)) = found else {
+            self.pending.push((name.clone(), loc.clone()));
+            return;
+        };
+
+        self.symbols.add_use(loc.clone(), id);
+
+        let function = self.scope_ids.iter().rposition(|s| {
+            matches!(self.symbols.scopes()[*s].kind, ScopeKind::Function(_))
+        });
+
+        if let Some(f) = function.filter(|f| depth > 0 && depth < *f) {
+            self.symbols.add_capture(Capture {
+                symbol: id,
+                function: self.scope_ids[f],
+                loc: loc.clone(),
+            });
+        }
+    }
+
     fn resolve_fn(&mut self, stmt: &FnDeclStmt) -> ResolverRes {
-        self.begin_scope();
+        for (i, p) in stmt.params.iter().enumerate() {
+            if stmt.params[..i].contains(p) {
+                return Err(PhyResult::new(
+                    ResolverErr::DuplicateParam(p.clone()),
+                    Some(stmt.param_locs[i].clone()),
+                ));
+            }
+        }
+
+        self.begin_scope(ScopeKind::Function(stmt.name.clone()), &stmt.name_loc);
 
-        stmt.params.iter().for_each(|p| {
+        stmt.params.iter().zip(&stmt.param_locs).for_each(|(p, loc)| {
             self.declare(p.clone());
             self.define(p.clone());
+            self.declare_symbol(p, SymbolKind::Parameter, loc);
         });
 
-        self.resolve(&stmt.body.clone())?;
+        for cond in stmt.requires.iter().chain(stmt.ensures.iter()) {
+            self.resolve_expr(cond)?;
+        }
+
+        self.fn_depth += 1;
+        let res = self.resolve(&stmt.body.clone());
+        self.fn_depth -= 1;
+        res?;
 
         self.end_scope();
 
         Ok(())
     }
 
-    fn begin_scope(&mut self) {
+    fn open_global_scope(&mut self) {
+        if self.symbol_scopes.is_empty() {
+            let id = self.symbols.open_scope(ScopeKind::Global, None, Loc::default());
+
+            self.symbol_scopes.push(HashMap::new());
+            self.scope_ids.push(id);
+        }
+    }
+
+    fn begin_scope(&mut self, kind: ScopeKind, loc: &Loc) {
+        self.open_global_scope();
+
+        let id = self.symbols.open_scope(kind, self.scope_ids.last().copied(), loc.clone());
+
         self.scopes.push(HashMap::new());
+        self.symbol_scopes.push(HashMap::new());
+        self.scope_ids.push(id);
     }
 
     fn end_scope(&mut self) {
         self.scopes.pop();
+        self.symbol_scopes.pop();
+        self.scope_ids.pop();
     }
 }
 
@@ -123,24 +259,24 @@ impl VisitStmt<(), ResolverErr> for Resolver {
         Ok(())
     }
 
-    fn visit_print_stmt(&mut self, stmt: &PrintStmt) -> ResolverRes {
-        self.resolve_expr(&stmt.expr)
-    }
-
     fn visit_var_decl_stmt(&mut self, stmt: &VarDeclStmt) -> ResolverRes {
         self.declare(stmt.name.clone());
 
         if let Some(v) = &stmt.value {
-            self.resolve_expr(v)?;
+            self.initializing.push(stmt.name.clone());
+            let res = self.resolve_expr(v);
+            self.initializing.pop();
+            res?;
         }
 
         self.define(stmt.name.clone());
+        self.declare_symbol(&stmt.name, SymbolKind::Variable, &stmt.name_loc);
 
         Ok(())
     }
 
     fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> ResolverRes {
-        self.begin_scope();
+        self.begin_scope(ScopeKind::Block, &stmt.loc);
         self.resolve(&stmt.stmts)?;
         self.end_scope();
 
@@ -165,25 +301,48 @@ impl VisitStmt<(), ResolverErr> for Resolver {
         self.resolve_stmt(&stmt.body)
     }
 
+    // The placeholders only live in the loop, like in the interpreter. The
+    // range is resolved outside of their scope
     fn visit_for_stmt(&mut self, stmt: &ForStmt) -> ResolverRes {
-        self.resolve_stmt(&(&stmt.placeholder).into())?;
-        self.resolve_stmt(&stmt.body)
+        stmt.range.exprs().try_for_each(|e| self.resolve_expr(e))?;
+
+        self.begin_scope(ScopeKind::Loop, &stmt.placeholder.name_loc);
+        for placeholder in stmt.placeholders() {
+            self.resolve_stmt(&placeholder.into())?;
+        }
+        self.resolve_stmt(&stmt.body)?;
+        self.end_scope();
+
+        Ok(())
     }
 
     fn visit_fn_decl_stmt(&mut self, stmt: &FnDeclStmt) -> ResolverRes {
         self.declare(stmt.name.clone());
         self.define(stmt.name.clone());
+        self.declare_symbol(&stmt.name, SymbolKind::Function, &stmt.name_loc);
 
         self.resolve_fn(stmt)
     }
 
     fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> ResolverRes {
+        if self.fn_depth == 0 {
+            return Err(PhyResult::new(ResolverErr::ReturnOutsideFn, Some(stmt.loc.clone())));
+        }
+
         if let Some(v) = &stmt.value {
             self.resolve_expr(v)?;
         }
 
         Ok(())
     }
+
+    fn visit_import_stmt(&mut self, stmt: &ImportStmt) -> ResolverRes {
+        self.declare(stmt.name().clone());
+        self.define(stmt.name().clone());
+        self.declare_symbol(stmt.name(), SymbolKind::Module, &stmt.name_loc);
+
+        Ok(())
+    }
 }
 
 impl VisitExpr<(), ResolverErr> for Resolver {
@@ -206,21 +365,32 @@ impl VisitExpr<(), ResolverErr> for Resolver {
         Ok(())
     }
 
+    fn visit_imaginary_literal_expr(&mut self, _: &ImaginaryLiteralExpr) -> ResolverRes {
+        Ok(())
+    }
+
     fn visit_str_literal_expr(&mut self, _: &StrLiteralExpr) -> ResolverRes {
         Ok(())
     }
 
+    fn visit_char_literal_expr(&mut self, _: &CharLiteralExpr) -> ResolverRes {
+        Ok(())
+    }
+
+    fn visit_unit_literal_expr(&mut self, _: &UnitLiteralExpr) -> ResolverRes {
+        Ok(())
+    }
+
     fn visit_identifier_expr(&mut self, expr: &IdentifierExpr) -> ResolverRes {
-        if !self.scopes.is_empty()
-            && self.scopes.last().unwrap().get(&expr.name) == Some(&false)
-        {
+        if self.initializing.last() == Some(&expr.name) {
             return Err(PhyResult::new(
-                ResolverErr::LocalVarInOwnInit,
+                ResolverErr::VarInOwnInit(expr.name.clone()),
                 Some(expr.loc.clone()),
             ));
         }
 
         self.resolve_local(&expr.name);
+        self.use_symbol(&expr.name, &expr.loc);
 
         Ok(())
     }
@@ -230,8 +400,9 @@ impl VisitExpr<(), ResolverErr> for Resolver {
     }
 
     fn visit_assign_expr(&mut self, expr: &AssignExpr) -> ResolverRes {
-        self.resolve_expr(&expr.into())?;
+        self.resolve_expr(&expr.value)?;
         self.resolve_local(&expr.name);
+        self.use_symbol(&expr.name, &expr.name_loc);
 
         Ok(())
     }
@@ -250,11 +421,59 @@ impl VisitExpr<(), ResolverErr> for Resolver {
 
         Ok(())
     }
+
+    fn visit_get_expr(&mut self, expr: &GetExpr) -> ResolverRes {
+        self.resolve_expr(&expr.object)
+    }
+
+    fn visit_set_expr(&mut self, expr: &SetExpr) -> ResolverRes {
+        self.resolve_expr(&expr.value)?;
+        self.resolve_expr(&expr.object)
+    }
+
+    fn visit_index_expr(&mut self, expr: &IndexExpr) -> ResolverRes {
+        self.resolve_expr(&expr.object)?;
+        expr.bounds().try_for_each(|b| self.resolve_expr(b))
+    }
+
+    fn visit_list_expr(&mut self, expr: &ListExpr) -> ResolverRes {
+        expr.items.iter().try_for_each(|i| self.resolve_expr(i))
+    }
+
+    // The source is resolved outside of the placeholder scope
+    fn visit_comprehension_expr(&mut self, expr: &ComprehensionExpr) -> ResolverRes {
+        self.resolve_expr(&expr.source)?;
+        expr.end.iter().try_for_each(|e| self.resolve_expr(e))?;
+
+        self.begin_scope(ScopeKind::Comprehension, &expr.name_loc);
+        self.declare(expr.name.clone());
+        self.define(expr.name.clone());
+        self.declare_symbol(&expr.name, SymbolKind::Variable, &expr.name_loc);
+
+        let res = expr
+            .cond
+            .iter()
+            .try_for_each(|c| self.resolve_expr(c))
+            .and_then(|_| self.resolve_expr(&expr.value));
+        self.end_scope();
+
+        res
+    }
+
+    fn visit_spawn_expr(&mut self, expr: &SpawnExpr) -> ResolverRes {
+        self.resolve_expr(&expr.call)
+    }
+
+    fn visit_await_expr(&mut self, expr: &AwaitExpr) -> ResolverRes {
+        self.resolve_expr(&expr.task)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{resolver::ResolverErr, utils::lex_parse_resolve};
+    use tools::source_map::SourceMap;
+
+    use crate::{resolver::ResolverErr, symbols::render_scopes, utils::lex_parse_resolve};
 
     #[test]
     fn depth() {
@@ -272,7 +491,7 @@ var a
     }
 }
 ";
-        let resolver = lex_parse_resolve(code);
+        assert!(lex_parse_resolve(code).is_ok());
     }
 
     #[test]
@@ -285,6 +504,138 @@ var a
 ";
         let resolver = lex_parse_resolve(code);
         let err = resolver.err().unwrap().err;
-        assert_eq!(err, ResolverErr::LocalVarInOwnInit);
+        assert_eq!(err, ResolverErr::VarInOwnInit("a".into()));
+
+        let code = "
+var b = 1
+var c = b + c * 2
+";
+        let err = lex_parse_resolve(code).err().unwrap();
+        assert_eq!(err.err, ResolverErr::VarInOwnInit("c".into()));
+        assert_eq!(err.loc.map(|l| l.start), code.rfind("c"));
+
+        assert!(lex_parse_resolve("var d = 1\nd = d + 1").is_ok());
+    }
+
+    #[test]
+    fn duplicate_param() {
+        let code = "fn area(w, h, w) { return w * h }";
+        let err = lex_parse_resolve(code).err().unwrap();
+        assert_eq!(err.err, ResolverErr::DuplicateParam("w".into()));
+        assert_eq!(err.loc.map(|l| l.start), code.find("w)"));
+    }
+
+    #[test]
+    fn return_outside_fn() {
+        let code = "
+fn add(a, b) {
+    if a > b { return a + b }
+    return b + a
+}
+{
+    return add(1, 2)
+}
+";
+        let err = lex_parse_resolve(code).err().unwrap();
+        assert_eq!(err.err, ResolverErr::ReturnOutsideFn);
+        assert_eq!(err.loc.map(|l| l.start), code.find("return add"));
+    }
+
+    #[test]
+    fn symbols() {
+        let code = "
+fn area(r) {
+    return scale * r * r
+}
+var scale = 3
+var r = area(2)
+{
+    var r = 1
+    r = r + 1
+}
+for i in 0..3 {
+    print(i, r)
+}
+";
+        // Offset of the nth occurrence of the text
+        let at = |text: &str, n: usize| code.match_indices(text).nth(n).unwrap().0;
+        let symbols = lex_parse_resolve(code).unwrap().into_symbols();
+
+        // Globals used before their declaration
+        let scale = symbols.definition_at(at("scale", 0)).unwrap();
+        assert_eq!(scale.decl.start, at("scale", 1));
+
+        // Parameters, locals and shadowing
+        let param = symbols.symbol_at(at("r", 1)).unwrap();
+        assert_eq!(symbols.get(param).decl.start, at("(r)", 0) + 1);
+        assert_eq!(symbols.references_of(param).len(), 2);
+
+        let local = symbols.symbol_at(at("r = r", 0)).unwrap();
+        assert_eq!(symbols.get(local).decl.start, at("var r = 1", 0) + 4);
+        assert_eq!(
+            symbols.references_of(local).iter().map(|l| l.start).collect::<Vec<usize>>(),
+            [at("r = r", 0), at("r = r", 0) + 4]
+        );
+
+        let global = symbols.symbol_at(at("var r = area", 0) + 4).unwrap();
+        let refs = symbols.references_of(global);
+        assert_eq!(refs.len(), 1);
+        assert_eq!(refs[0].start..refs[0].end, at("r)", 1)..at("r)", 1) + 1);
+
+        let i = symbols.symbol_at(at("i,", 0)).unwrap();
+        assert_eq!(symbols.get(i).decl.start, at("i in", 0));
+
+        // Natives and keywords have no symbol
+        assert!(symbols.definition_at(at("print", 0)).is_none());
+        assert!(symbols.definition_at(at("return", 0)).is_none());
+    }
+
+    #[test]
+    fn scopes() {
+        let code = "var total = 0
+fn counter(start) {
+    var count = start
+    fn next() {
+        count = count + 1
+        total = total + 1
+        return count
+    }
+    return next
+}
+{
+    var step = 2
+    fn by_step(l) {
+        return [x * step for x in l]
+    }
+}
+";
+        let symbols = lex_parse_resolve(code).unwrap().into_symbols();
+
+        // Globals aren't captured, each use of the others is
+        let captures = symbols.captures();
+        assert_eq!(captures.len(), 4);
+        assert!(captures.iter().all(|c| symbols.get(c.symbol).name != "total"));
+
+        let mut sources = SourceMap::default();
+        sources.add("counter.arc", code);
+
+        assert_eq!(
+            render_scopes(&symbols, &sources),
+            "global
+  var total  line 1
+  fn counter  line 2
+    param start  line 2
+    var count  line 3
+    fn next  line 4
+      captures count (line 3)  lines 5, 7
+  block  line 11
+    var step  line 12
+    fn by_step  line 13
+      captures step (line 12)  line 14
+      param l  line 13
+      comprehension  line 14
+        var x  line 14
+"
+        );
     }
 }
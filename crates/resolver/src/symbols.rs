@@ -0,0 +1,256 @@
+use ecow::EcoString;
+use tools::{results::Loc, source_map::SourceMap};
+
+// Index of a symbol in its table
+pub type SymbolId = usize;
+
+// Index of a scope in its table, the globals are in the first one
+pub type ScopeId = usize;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SymbolKind {
+    Function,
+    Variable,
+    Parameter,
+    Module,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Symbol {
+    pub name: EcoString,
+    pub kind: SymbolKind,
+    // Span of the name in its declaration
+    pub decl: Loc,
+    pub scope: ScopeId,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ScopeKind {
+    Global,
+    Function(EcoString),
+    Block,
+    Loop,
+    Comprehension,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    pub parent: Option<ScopeId>,
+    // Span of what opens it: the function name, the loop variable or the
+    // block
+    pub loc: Loc,
+}
+
+// Use in a function of a variable declared in an enclosing function or
+// block, kept alive by the closure. Globals aren't captured
+#[derive(Debug, PartialEq, Clone)]
+pub struct Capture {
+    pub symbol: SymbolId,
+    // Scope of the capturing function
+    pub function: ScopeId,
+    pub loc: Loc,
+}
+
+// Declarations of the program and the spans where each one is used, filled
+// by the resolver. Names declared outside of the program, like the natives,
+// have no symbol
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+    uses: Vec<(Loc, SymbolId)>,
+    scopes: Vec<Scope>,
+    captures: Vec<Capture>,
+}
+
+impl SymbolTable {
+    pub(crate) fn declare(
+        &mut self,
+        name: EcoString,
+        kind: SymbolKind,
+        decl: Loc,
+        scope: ScopeId,
+    ) -> SymbolId {
+        self.symbols.push(Symbol { name, kind, decl, scope });
+        self.symbols.len() - 1
+    }
+
+    pub(crate) fn open_scope(&mut self, kind: ScopeKind, parent: Option<ScopeId>, loc: Loc) -> ScopeId {
+        self.scopes.push(Scope { kind, parent, loc });
+        self.scopes.len() - 1
+    }
+
+    pub(crate) fn add_capture(&mut self, capture: Capture) {
+        self.captures.push(capture);
+    }
+
+    pub(crate) fn add_use(&mut self, loc: Loc, id: SymbolId) {
+        self.uses.push((loc, id));
+    }
+
+    pub fn get(&self, id: SymbolId) -> &Symbol {
+        &self.symbols[id]
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    pub fn scopes(&self) -> &[Scope] {
+        &self.scopes
+    }
+
+    pub fn captures(&self) -> &[Capture] {
+        &self.captures
+    }
+
+    // Symbol declared or used at the offset
+    pub fn symbol_at(&self, offset: usize) -> Option<SymbolId> {
+        let contains = |loc: &Loc| loc.start <= offset && offset < loc.end;
+
+        self.uses
+            .iter()
+            .find(|(loc, _)| contains(loc))
+            .map(|(_, id)| *id)
+            .or_else(|| self.symbols.iter().position(|s| contains(&s.decl)))
+    }
+
+    pub fn definition_at(&self, offset: usize) -> Option<&Symbol> {
+        self.symbol_at(offset).map(|id| self.get(id))
+    }
+
+    // Spans of the uses, in source order. The declaration isn't included
+    pub fn references_of(&self, id: SymbolId) -> Vec<Loc> {
+        let mut refs: Vec<Loc> = self
+            .uses
+            .iter()
+            .filter(|(_, s)| *s == id)
+            .map(|(loc, _)| loc.clone())
+            .collect();
+
+        refs.sort_by_key(|l| l.start);
+        refs
+    }
+}
+
+// Tree of the scopes with the names declared in each one and, under the
+// functions, the variables they capture, for '--scopes':
+//
+// global
+//   fn counter  line 1
+//     var count  line 2
+//     fn next  line 3
+//       captures count (line 2)  lines 4, 5
+pub fn render_scopes(table: &SymbolTable, sources: &SourceMap) -> String {
+    let mut out = String::new();
+
+    if !table.scopes.is_empty() {
+        render_scope(table, sources, 0, 0, &mut out);
+    }
+
+    out
+}
+
+fn render_scope(table: &SymbolTable, sources: &SourceMap, id: ScopeId, depth: usize, out: &mut String) {
+    let scope = &table.scopes[id];
+    let indent = "  ".repeat(depth + 1);
+
+    let title = match &scope.kind {
+        ScopeKind::Global => "global".to_string(),
+        ScopeKind::Function(name) => format!("fn {}", name),
+        ScopeKind::Block => "block".to_string(),
+        ScopeKind::Loop => "for".to_string(),
+        ScopeKind::Comprehension => "comprehension".to_string(),
+    };
+
+    match scope.kind {
+        ScopeKind::Global => out.push_str(&format!("{}\n", title)),
+        _ => out.push_str(&format!("{}{}  {}\n", &indent[2..], title, line(sources, &scope.loc))),
+    }
+
+    // One line per captured variable, with the lines of its uses
+    let mut captured: Vec<(SymbolId, Vec<usize>)> = vec![];
+
+    for capture in table.captures.iter().filter(|c| c.function == id) {
+        let use_line = sources.lookup(&capture.loc).map(|f| f.line_of(&capture.loc));
+
+        match captured.iter_mut().find(|(s, _)| *s == capture.symbol) {
+            Some((_, lines)) => lines.extend(use_line),
+            None => captured.push((capture.symbol, use_line.into_iter().collect())),
+        }
+    }
+
+    for (symbol, mut lines) in captured {
+        let symbol = &table.symbols[symbol];
+        lines.dedup();
+
+        let lines = lines.iter().map(|l| l.to_string()).collect::<Vec<_>>().join(", ");
+        let used = match lines.contains(',') {
+            true => format!("lines {}", lines),
+            false => format!("line {}", lines),
+        };
+
+        out.push_str(&format!(
+            "{}captures {} ({})  {}\n",
+            indent,
+            symbol.name,
+            line(sources, &symbol.decl),
+            used
+        ));
+    }
+
+    // Declarations and nested scopes in the order of the code. Functions
+    // are shown by their scope, which opens at their name
+    let mut entries: Vec<(usize, Entry)> = table
+        .symbols
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.scope == id && s.kind != SymbolKind::Function)
+        .map(|(i, s)| (s.decl.start, Entry::Symbol(i)))
+        .chain(
+            table
+                .scopes
+                .iter()
+                .enumerate()
+                .filter(|(_, s)| s.parent == Some(id))
+                .map(|(i, s)| (s.loc.start, Entry::Scope(i))),
+        )
+        .collect();
+
+    entries.sort_by_key(|(start, _)| *start);
+
+    for (_, entry) in entries {
+        match entry {
+            Entry::Symbol(symbol) => {
+                let symbol = &table.symbols[symbol];
+                let kind = match symbol.kind {
+                    SymbolKind::Function => "fn",
+                    SymbolKind::Variable => "var",
+                    SymbolKind::Parameter => "param",
+                    SymbolKind::Module => "import",
+                };
+
+                out.push_str(&format!(
+                    "{}{} {}  {}\n",
+                    indent,
+                    kind,
+                    symbol.name,
+                    line(sources, &symbol.decl)
+                ));
+            }
+            Entry::Scope(scope) => render_scope(table, sources, scope, depth + 1, out),
+        }
+    }
+}
+
+enum Entry {
+    Symbol(SymbolId),
+    Scope(ScopeId),
+}
+
+fn line(sources: &SourceMap, loc: &Loc) -> String {
+    match sources.lookup(loc) {
+        Some(file) => format!("line {}", file.line_of(loc)),
+        None => String::new(),
+    }
+}
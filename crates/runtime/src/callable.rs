@@ -9,5 +9,18 @@ pub trait Callable<T: PhyReport> {
         args: Vec<RtVal>,
     ) -> Result<RtVal, PhyResult<T>>;
 
+    // Minimum number of arguments
     fn arity(&self) -> usize;
+
+    // Maximum number of arguments, None if there is no limit
+    fn max_arity(&self) -> Option<usize> {
+        Some(self.arity())
+    }
+
+    // Expected type name of each parameter, checked before the call.
+    // Alternatives are separated by '|' and "any" or a missing entry
+    // accepts every type
+    fn params_type(&self) -> &[&'static str] {
+        &[]
+    }
 }
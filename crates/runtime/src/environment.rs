@@ -1,13 +1,14 @@
 use ecow::EcoString;
 use std::{
-    cell::RefCell,
+    cell::{Ref, RefCell, RefMut},
     collections::{
         hash_map::Entry::{Occupied, Vacant},
-        HashMap,
+        HashMap, HashSet,
     },
     rc::Rc,
 };
 use thiserror::Error;
+use tools::messages::{Arg, Translate};
 
 use crate::values::RtVal;
 
@@ -21,9 +22,39 @@ pub enum EnvErr {
 
     #[error("undeclared variable '{0}'")]
     UndeclaredVar(String),
+
+    #[error("can't assign to constant '{0}'")]
+    ConstAssign(String),
+
+    #[error("environment already in use while accessing '{0}'")]
+    BorrowConflict(String),
+}
+
+impl Translate for EnvErr {
+    fn category(&self) -> &'static str {
+        "env"
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            EnvErr::AlreadyDeclaredVar(..) => "AlreadyDeclaredVar",
+            EnvErr::UndeclaredVar(..) => "UndeclaredVar",
+            EnvErr::ConstAssign(..) => "ConstAssign",
+            EnvErr::BorrowConflict(..) => "BorrowConflict",
+        }
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        match self {
+            EnvErr::AlreadyDeclaredVar(v)
+            | EnvErr::UndeclaredVar(v)
+            | EnvErr::ConstAssign(v)
+            | EnvErr::BorrowConflict(v) => vec![Arg::text(v)],
+        }
+    }
 }
 
-type EnvWrapper = Rc<RefCell<Env>>;
+pub type EnvWrapper = Rc<RefCell<Env>>;
 
 // -------------
 //  Environment
@@ -32,6 +63,12 @@ type EnvWrapper = Rc<RefCell<Env>>;
 pub struct Env {
     pub enclosing: Option<Rc<RefCell<Env>>>,
     pub vars: HashMap<EcoString, RtVal>,
+    // Names of the variables that can't be assigned
+    pub consts: HashSet<EcoString>,
+    // Callbacks run after the assignments of the variables of this scope
+    pub watches: HashMap<EcoString, Vec<RtVal>>,
+    // Globals of the interpreter or the builtins they shadow
+    pub global: bool,
 }
 
 impl Env {
@@ -39,9 +76,25 @@ impl Env {
         Self {
             enclosing,
             vars: HashMap::new(),
+            consts: HashSet::new(),
+            watches: HashMap::new(),
+            global: false,
         }
     }
 
+    // Borrows of a shared environment. An environment can already be borrowed
+    // when a native or a host object calls back into the interpreter, so a
+    // conflict is an error instead of a panic
+    pub fn read<'a>(env: &'a EnvWrapper, var_name: &str) -> Result<Ref<'a, Env>, EnvErr> {
+        env.try_borrow()
+            .map_err(|_| EnvErr::BorrowConflict(var_name.into()))
+    }
+
+    pub fn write<'a>(env: &'a EnvWrapper, var_name: &str) -> Result<RefMut<'a, Env>, EnvErr> {
+        env.try_borrow_mut()
+            .map_err(|_| EnvErr::BorrowConflict(var_name.into()))
+    }
+
     pub fn declare_var(&mut self, var_name: EcoString, value: RtVal) -> Result<(), EnvErr> {
         if let Vacant(v) = self.vars.entry(var_name.clone()) {
             v.insert(value);
@@ -52,12 +105,19 @@ impl Env {
         Ok(())
     }
 
+    pub fn declare_const(&mut self, name: EcoString, value: RtVal) -> Result<(), EnvErr> {
+        self.declare_var(name.clone(), value)?;
+        self.consts.insert(name);
+
+        Ok(())
+    }
+
     pub fn get_var(&self, var_name: EcoString) -> Result<RtVal, EnvErr> {
         match self.vars.get(&var_name) {
             Some(v) => Ok(v.clone()),
             None => {
                 if let Some(enclo) = &self.enclosing {
-                    enclo.borrow().get_var(var_name)
+                    Env::read(enclo, &var_name)?.get_var(var_name)
                 } else {
                     Err(EnvErr::UndeclaredVar(var_name.into()))
                 }
@@ -65,12 +125,72 @@ impl Env {
         }
     }
 
+    // Also tells if the variable was found in a global environment, the
+    // one of the globals or of the builtins
+    pub fn find_var(&self, var_name: &EcoString) -> Result<(RtVal, bool), EnvErr> {
+        match self.vars.get(var_name) {
+            Some(v) => Ok((v.clone(), self.global)),
+            None => match &self.enclosing {
+                Some(enclo) => Env::read(enclo, var_name)?.find_var(var_name),
+                None => Err(EnvErr::UndeclaredVar(var_name.into())),
+            },
+        }
+    }
+
+    // Names visible from this environment, the closest scope first and sorted
+    // inside each scope to be reproducible. Shadowed names are only listed once
+    pub fn get_visible_names(&self) -> Vec<EcoString> {
+        let mut names: Vec<EcoString> = self.vars.keys().cloned().collect();
+        names.sort();
+
+        if let Some(enclo) = &self.enclosing {
+            for name in enclo.borrow().get_visible_names() {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+
+        names
+    }
+
+    // Approximate memory used by the variables of this scope only
+    pub fn approx_size(&self) -> usize {
+        let mut seen = HashSet::new();
+        self.vars.values().map(|v| v.approx_size_in(&mut seen)).sum()
+    }
+
+    // The watch is kept in the scope declaring the variable, so it follows
+    // the binding and not the name
+    pub fn watch(&mut self, var_name: EcoString, callback: RtVal) -> Result<(), EnvErr> {
+        if self.vars.contains_key(&var_name) {
+            self.watches.entry(var_name).or_default().push(callback);
+            Ok(())
+        } else if let Some(enclo) = &self.enclosing {
+            Env::write(enclo, &var_name)?.watch(var_name, callback)
+        } else {
+            Err(EnvErr::UndeclaredVar(var_name.into()))
+        }
+    }
+
+    pub fn get_watches(&self, var_name: &str) -> Result<Vec<RtVal>, EnvErr> {
+        if self.vars.contains_key(var_name) {
+            Ok(self.watches.get(var_name).cloned().unwrap_or_default())
+        } else if let Some(enclo) = &self.enclosing {
+            Env::read(enclo, var_name)?.get_watches(var_name)
+        } else {
+            Err(EnvErr::UndeclaredVar(var_name.into()))
+        }
+    }
+
     pub fn assign(&mut self, var_name: EcoString, value: RtVal) -> Result<(), EnvErr> {
-        if let Occupied(mut v) = self.vars.entry(var_name.clone()) {
+        if self.consts.contains(&var_name) {
+            Err(EnvErr::ConstAssign(var_name.into()))
+        } else if let Occupied(mut v) = self.vars.entry(var_name.clone()) {
             v.insert(value);
             Ok(())
         } else if let Some(enclo) = &self.enclosing {
-            enclo.borrow_mut().assign(var_name, value)
+            Env::write(enclo, &var_name)?.assign(var_name, value)
         } else {
             Err(EnvErr::UndeclaredVar(var_name.into()))
         }
@@ -82,6 +202,7 @@ mod tests {
     use super::Env;
     use crate::{environment::EnvErr, values::RtVal};
     use ecow::EcoString;
+    use std::{cell::RefCell, rc::Rc};
 
     #[test]
     fn var_declaration() {
@@ -107,4 +228,94 @@ mod tests {
             EnvErr::UndeclaredVar { .. }
         ));
     }
+
+    #[test]
+    fn constants() {
+        let global = Rc::new(RefCell::new(Env::default()));
+        let _ = global.borrow_mut().declare_const(EcoString::from("PI"), 3.into());
+
+        let mut local = Env::new(Some(global));
+        assert!(matches!(
+            local.assign(EcoString::from("PI"), 3.into()).err().unwrap(),
+            EnvErr::ConstAssign { .. }
+        ));
+
+        // Shadowing in a local scope is still allowed
+        assert!(local.declare_var(EcoString::from("PI"), 3.into()).is_ok());
+        assert!(local.assign(EcoString::from("PI"), 4.into()).is_ok());
+    }
+
+    #[test]
+    fn watches() {
+        let global = Rc::new(RefCell::new(Env::default()));
+        let _ = global.borrow_mut().declare_var(EcoString::from("x"), 0.into());
+
+        // Kept in the scope declaring the variable, not shared by a shadowing one
+        let mut local = Env::new(Some(global.clone()));
+        assert!(local.watch(EcoString::from("x"), 1.into()).is_ok());
+        assert_eq!(global.borrow().get_watches("x").unwrap(), vec![1.into()]);
+
+        let _ = local.declare_var(EcoString::from("x"), 0.into());
+        assert!(local.get_watches("x").unwrap().is_empty());
+
+        assert!(matches!(
+            local.watch(EcoString::from("y"), 1.into()).err().unwrap(),
+            EnvErr::UndeclaredVar { .. }
+        ));
+    }
+
+    #[test]
+    fn visible_names() {
+        let global = Rc::new(RefCell::new(Env::default()));
+        let _ = global.borrow_mut().declare_var(EcoString::from("foo"), 3.into());
+        let _ = global.borrow_mut().declare_var(EcoString::from("bar"), 3.into());
+
+        let mut local = Env::new(Some(global));
+        let _ = local.declare_var(EcoString::from("foo"), 4.into());
+        let _ = local.declare_var(EcoString::from("baz"), 4.into());
+
+        let mut names = local.get_visible_names();
+        names.sort();
+        assert_eq!(names, vec!["bar", "baz", "foo"]);
+    }
+
+    #[test]
+    fn borrow_conflict() {
+        let global = Rc::new(RefCell::new(Env::default()));
+        let _ = global.borrow_mut().declare_var(EcoString::from("foo"), 3.into());
+        let mut local = Env::new(Some(global.clone()));
+
+        // Enclosing environment already borrowed by the host
+        let guard = global.borrow_mut();
+        assert!(matches!(
+            local.get_var(EcoString::from("foo")),
+            Err(EnvErr::BorrowConflict(..))
+        ));
+        assert!(matches!(
+            local.assign(EcoString::from("foo"), 4.into()),
+            Err(EnvErr::BorrowConflict(..))
+        ));
+        drop(guard);
+
+        assert!(local.assign(EcoString::from("foo"), 4.into()).is_ok());
+        assert_eq!(local.get_var(EcoString::from("foo")).unwrap(), 4.into());
+    }
+
+    #[test]
+    fn visible_names_order() {
+        let global = Rc::new(RefCell::new(Env::default()));
+        for name in ["zeta", "alpha", "mid"] {
+            let _ = global.borrow_mut().declare_var(EcoString::from(name), 3.into());
+        }
+
+        let mut local = Env::new(Some(global.clone()));
+        for name in ["mid", "beta", "alpha2"] {
+            let _ = local.declare_var(EcoString::from(name), 3.into());
+        }
+
+        assert_eq!(
+            local.get_visible_names(),
+            ["alpha2", "beta", "mid", "alpha", "zeta"]
+        );
+    }
 }
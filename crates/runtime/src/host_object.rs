@@ -0,0 +1,282 @@
+use std::fmt;
+
+use colored::*;
+use thiserror::Error;
+use tools::{
+    messages::{localize, text, Arg, Locale, Translate},
+    results::{PhyReport, PhyResult},
+};
+
+use crate::{interpreter::Interpreter, values::RtVal};
+
+// -----------------
+//  Error managment
+// -----------------
+#[derive(Debug, Error, PartialEq)]
+pub enum HostErr {
+    #[error("no member '{0}'")]
+    UnknownMember(String),
+
+    #[error("member '{0}' is read-only")]
+    ReadOnly(String),
+
+    #[error("member '{0}' expected a value of type {1}")]
+    WrongType(String, String),
+
+    #[error("{0}")]
+    Custom(String),
+}
+
+impl PhyReport for HostErr {
+    fn get_err_msg(&self, locale: &dyn Locale) -> String {
+        format!(
+            "{}: {}",
+            text(locale, "label.host", "Host object error").red(),
+            localize(locale, self)
+        )
+    }
+}
+
+impl Translate for HostErr {
+    fn category(&self) -> &'static str {
+        "host"
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            HostErr::UnknownMember(..) => "UnknownMember",
+            HostErr::ReadOnly(..) => "ReadOnly",
+            HostErr::WrongType(..) => "WrongType",
+            HostErr::Custom(..) => "Custom",
+        }
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        match self {
+            HostErr::UnknownMember(v)
+            | HostErr::ReadOnly(v)
+            | HostErr::Custom(v) => vec![Arg::text(v)],
+            HostErr::WrongType(a, b) => vec![Arg::text(a), Arg::text(b)],
+        }
+    }
+}
+
+pub type HostRes<T> = Result<T, PhyResult<HostErr>>;
+
+// -------------
+//  Host object
+// -------------
+// Rust value exposed to scripts. Field access and method calls on it
+// are routed back to the host, which owns the actual data. Methods
+// take '&self' because the object is shared, mutations have to go
+// through interior mutability
+pub trait HostObject {
+    // Name used in error messages and as the type name of the value
+    fn type_name(&self) -> &str;
+
+    fn get(&self, name: &str) -> HostRes<RtVal>;
+
+    fn set(&self, name: &str, _value: RtVal) -> HostRes<()> {
+        Err(PhyResult::new(HostErr::ReadOnly(name.into()), None))
+    }
+
+    fn call_method(&self, _interpreter: &mut Interpreter, name: &str, _args: Vec<RtVal>) -> HostRes<RtVal> {
+        Err(PhyResult::new(HostErr::UnknownMember(name.into()), None))
+    }
+
+    // Fields and methods listed by 'dir', none by default
+    fn members(&self) -> Vec<&str> {
+        vec![]
+    }
+}
+
+impl fmt::Debug for dyn HostObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{} object>", self.type_name())
+    }
+}
+
+// Host objects are only equal to themselves
+impl PartialEq for dyn HostObject {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::addr_eq(self, other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use frontend::parser::utils::lex_and_parse;
+    use tools::results::{Loc, PhyResult};
+
+    use crate::{
+        interpreter::{InterpErr, Interpreter},
+        utils::lex_parse_interp_with,
+        values::RtVal,
+    };
+
+    use super::{HostErr, HostObject, HostRes};
+
+    // Game entity owned by the host, moved by the scripts
+    struct Entity {
+        name: &'static str,
+        pos: RefCell<(f64, f64)>,
+    }
+
+    impl HostObject for Entity {
+        fn type_name(&self) -> &str {
+            "Entity"
+        }
+
+        fn get(&self, name: &str) -> HostRes<RtVal> {
+            match name {
+                "name" => Ok(ecow::EcoString::from(self.name).into()),
+                "x" => Ok(self.pos.borrow().0.into()),
+                "y" => Ok(self.pos.borrow().1.into()),
+                _ => Err(PhyResult::new(HostErr::UnknownMember(name.into()), None)),
+            }
+        }
+
+        fn set(&self, name: &str, value: RtVal) -> HostRes<()> {
+            let RtVal::RealVal(v) = value else {
+                return Err(PhyResult::new(HostErr::WrongType(name.into(), "real".into()), None));
+            };
+
+            match name {
+                "x" => self.pos.borrow_mut().0 = v.borrow().value,
+                "y" => self.pos.borrow_mut().1 = v.borrow().value,
+                _ => return Err(PhyResult::new(HostErr::ReadOnly(name.into()), None)),
+            }
+
+            Ok(())
+        }
+
+        fn call_method(&self, interp: &mut Interpreter, name: &str, args: Vec<RtVal>) -> HostRes<RtVal> {
+            match (name, args.as_slice()) {
+                ("move", [RtVal::RealVal(dx), RtVal::RealVal(dy)]) => {
+                    let mut pos = self.pos.borrow_mut();
+                    pos.0 += dx.borrow().value;
+                    pos.1 += dy.borrow().value;
+
+                    Ok(RtVal::new_null())
+                }
+                ("move", _) => Err(PhyResult::new(
+                    HostErr::Custom("move expects two reals".into()),
+                    None,
+                )),
+                ("crash", _) => panic!("entity crashed"),
+                // Calls back into the script while holding the globals
+                ("on_hit", [callback]) => {
+                    let globals = interp.globals.clone();
+                    let _guard = globals.borrow();
+                    interp
+                        .call_function(callback.clone(), vec![])
+                        .map_err(|e| PhyResult::new(HostErr::Custom(e.err.to_string()), None))
+                }
+                _ => Err(PhyResult::new(HostErr::UnknownMember(name.into()), None)),
+            }
+        }
+
+        fn members(&self) -> Vec<&str> {
+            vec!["x", "y", "name", "move", "crash", "on_hit"]
+        }
+    }
+
+    fn new_player() -> Rc<Entity> {
+        Rc::new(Entity {
+            name: "player",
+            pos: RefCell::new((0., 0.)),
+        })
+    }
+
+    #[test]
+    fn host_object() {
+        let player = new_player();
+        let code = "
+player.x = 2.
+player.move(1., 3.)
+player.y
+";
+        let res = lex_parse_interp_with(code, |interp| {
+            interp.declare_host_object("player", player.clone())
+        });
+        assert_eq!(res.unwrap(), 3f64.into());
+        assert_eq!(*player.pos.borrow(), (3., 3.));
+
+        let res = lex_parse_interp_with("player.name", |interp| {
+            interp.declare_host_object("player", new_player())
+        });
+        assert_eq!(res.unwrap(), ecow::EcoString::from("player").into());
+
+        let res = lex_parse_interp_with("dir(player)", |interp| {
+            interp.declare_host_object("player", new_player())
+        });
+        assert_eq!(
+            res.unwrap().to_string(),
+            "[\"crash\", \"move\", \"name\", \"on_hit\", \"x\", \"y\"]"
+        );
+
+        // Errors
+        let codes = [
+            "player.z",
+            "player.name = 1.",
+            "player.x = 1",
+            "player.jump()",
+            "player.move(1)",
+        ];
+        let errs = [
+            "no member 'z'",
+            "member 'name' is read-only",
+            "member 'x' expected a value of type real",
+            "no member 'jump'",
+            "move expects two reals",
+        ];
+
+        for (code, err) in codes.iter().zip(errs) {
+            let res = lex_parse_interp_with(code, |interp| {
+                interp.declare_host_object("player", new_player())
+            });
+            assert_eq!(res.err().unwrap().err, InterpErr::Host("Entity".into(), err.into()));
+        }
+    }
+
+    #[test]
+    fn host_panic() {
+        let code = "
+var a = 1
+player.crash()
+";
+        let res = lex_parse_interp_with(code, |interp| {
+            interp.declare_host_object("player", new_player())
+        });
+        let err = res.err().unwrap();
+        assert_eq!(err.err, InterpErr::Internal("entity crashed".into()));
+        assert_eq!(err.loc, Some(Loc { start: 11, end: 25 }));
+
+        // A panic in the prelude is located at the call of the script
+        let mut interp = Interpreter::with_prelude("fn poke(e) { e.crash() }").unwrap();
+        interp.declare_host_object("player", new_player());
+        let code = "var a = 1\npoke(player)";
+        let err = interp.interpret(&lex_and_parse(code).unwrap()).err().unwrap();
+        assert_eq!(err.err, InterpErr::Internal("entity crashed".into()));
+        assert_eq!(err.loc, Some(Loc { start: 10, end: 22 }));
+    }
+
+    #[test]
+    fn host_reentrancy() {
+        let code = "
+var hits = 0
+fn hit() { hits = hits + 1 }
+player.on_hit(hit)
+";
+        let res = lex_parse_interp_with(code, |interp| {
+            interp.declare_host_object("player", new_player())
+        });
+        // Reported as an error instead of a borrow panic
+        let InterpErr::Host(_, msg) = res.err().unwrap().err else {
+            panic!("expected a host error");
+        };
+        assert!(msg.to_string().contains("environment already in use while accessing 'hits'"));
+    }
+}
@@ -1,22 +1,61 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fs;
+use std::hash::{BuildHasherDefault, Hasher};
+use std::io::{self, BufRead, BufReader, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
 use std::rc::Rc;
+use std::sync::Once;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use colored::Colorize;
+use corosensei::stack::{DefaultStack, Stack};
+use corosensei::{Coroutine, CoroutineResult, Yielder};
 use ecow::EcoString;
 use thiserror::Error;
-use tools::results::{PhyReport, PhyResult};
+use tools::{
+    messages::{localize, localize_hint, text, Arg, Locale, Message, Translate},
+    results::{Hint, Loc, PhyReport, PhyResult},
+};
 
 use crate::callable::Callable;
-use crate::environment::Env;
-use crate::native_functions::{NativeClock, PhyNativeFn};
-use crate::values::RtVal;
+use crate::environment::{Env, EnvErr, EnvWrapper};
+use crate::native_functions::{
+    NativeAbs, NativeActor, NativeArg, NativeBytes, NativeBytesGet, NativeBytesLen,
+    NativeBytesSlice, NativeBytesToStr, NativeCeil, NativeChan, NativeChr, NativeClearTimer,
+    NativeClock, NativeConj, NativeCross, NativeDir, NativeDot, NativeEmit, NativeEval, NativeFloor,
+    NativeFromBase64, NativeFromHex, NativeFnArity, NativeFnErr, NativeFnName, NativeFnParams,
+    NativeGlobals, NativeHelp, NativeListGet, NativeListLen, NativeListNew, NativeListPush,
+    NativeListPop, NativeListInsert, NativeListRemove, NativeListClear, NativeEnumerate, NativeZip,
+    NativeIterRange, NativeIterMap, NativeIterFilter, NativeIterTake, NativeIterSkip,
+    NativeIterCollect, NativeIterSum, NativeLocals, NativeListDir, NativeLower, NativeMat,
+    NativeNorm, NativeOn, NativeOrd, NativeParseInt, NativeParseReal, NativePathBasename,
+    NativePathExists, NativePathExt, NativePathJoin, NativePow, NativePrint, NativeReadFile,
+    NativeReadFileBytes, NativeRecv, NativeRepeat, NativeRound, NativeSend, NativeSetInterval,
+    NativeSetTimeout, NativeStdinLines, NativeStdinReadAll, NativeSqrt, NativeStrChars,
+    NativeStrGet, NativeStrLen, NativeStrReverse, NativeStrSlice, NativeToBase64, NativeToFixed,
+    NativeToHex, NativeToStr, NativeTrunc, NativeUpper, NativeVec2, NativeVec3, NativeWatch,
+    NativeWriteFile, NativeWriteFileBytes, PhyNativeFn,
+};
+use crate::host_object::{HostErr, HostObject};
+use crate::trace::TraceEvent;
+use crate::units::Unit;
+use crate::values::{
+    Actor, Complex, DisplayConfig, Function, Iter, Module, Quantity, RtVal, RtValErr, Task,
+    TaskState, Vector,
+};
 use frontend::ast::expr::{
-    AssignExpr, BinaryExpr, CallExpr, Expr, GroupingExpr, IdentifierExpr, IntLiteralExpr, LogicalExpr, RealLiteralExpr, StrLiteralExpr, UnaryExpr, VisitExpr
+    AssignExpr, AwaitExpr, BinaryExpr, CallExpr, CharLiteralExpr, ComprehensionExpr, Expr, GetExpr,
+    GroupingExpr, IdentifierExpr, ImaginaryLiteralExpr, IndexExpr, IntLiteralExpr, ListExpr,
+    LogicalExpr, RealLiteralExpr, SetExpr, SpawnExpr, StrLiteralExpr, UnaryExpr, UnitLiteralExpr,
+    VisitExpr,
 };
+use frontend::{lexer::Lexer, parser::Parser};
 use frontend::ast::stmt::{
-    BlockStmt, ExprStmt, FnDeclStmt, ForStmt, IfStmt, PrintStmt, ReturnStmt, Stmt, VarDeclStmt,
-    VisitStmt, WhileStmt,
+    BlockStmt, ExprStmt, FnDeclStmt, ForRange, ForStmt, IfStmt, ImportStmt, ReturnStmt, Stmt,
+    VarDeclStmt, VisitStmt, WhileStmt,
 };
 
 // ----------------
@@ -26,7 +65,7 @@ use frontend::ast::stmt::{
 pub enum InterpErr {
     // Binop
     #[error("{0}")]
-    OperationEvaluation(String),
+    OperationEvaluation(Message),
 
     // Negate
     #[error("can't use '!' token on anything other than a bool value")]
@@ -36,17 +75,21 @@ pub enum InterpErr {
     NegateNonNumeric,
 
     #[error("{0}")]
-    Negation(String),
+    Negation(Message),
+
+    // Units
+    #[error("{0}")]
+    Unit(Message),
 
     // Variables
     #[error("{0}")]
-    VarDeclEnv(String),
+    VarDeclEnv(Message),
 
     #[error("{0}")]
-    GetVarEnv(String),
+    GetVarEnv(Message),
 
     #[error("{0}")]
-    AssignEnv(String),
+    AssignEnv(Message),
 
     #[error("uninitialized variable")]
     UninitializedValue,
@@ -61,7 +104,27 @@ pub enum InterpErr {
 
     // For
     #[error("{0}")]
-    ForLoop(String),
+    ForLoop(Message),
+
+    #[error("expected a list of {0} values to split between the names of the loop, found {1}")]
+    ForDestructuring(usize, String),
+
+    // Comprehension
+    #[error("can't take the values of {0}, expected a list, an iterator or an int range")]
+    NotIterable(String),
+
+    #[error("range bounds must be of type int, found {0}")]
+    NonIntRange(String),
+
+    #[error("comprehension condition is not a boolean")]
+    NonBoolComprehensionCond,
+
+    // Iterators
+    #[error("filter function must return a bool, found {0}")]
+    NonBoolFilter(String),
+
+    #[error("can't read stdin: {0}")]
+    ReadInput(String),
 
     // Call
     #[error("only functions and structures are callable")]
@@ -70,678 +133,5277 @@ pub enum InterpErr {
     #[error("wrong arguments number: expected {0} but got {1}")]
     WrongArgsNb(usize, usize),
 
+    #[error("wrong arguments number: expected at least {0} but got {1}")]
+    TooFewArgs(usize, usize),
+
+    #[error("wrong arguments number: expected between {0} and {1} but got {2}")]
+    WrongArgsRange(usize, usize, usize),
+
+    #[error("argument {0} must be of type {1}, found {2}")]
+    WrongArgType(usize, String, String),
+
+    #[error("{0}")]
+    FnCall(Message),
+
+    // Contracts
+    #[error("{kind} of '{func}' violated: {clause}")]
+    ContractViolation {
+        kind: &'static str,
+        func: EcoString,
+        clause: String,
+        clause_loc: Loc,
+    },
+
+    #[error("contract condition is not a boolean")]
+    NonBoolContract,
+
+    // Failure in a call made by a native, like a task run by 'recv'. The
+    // inner error keeps its location
+    #[error("in a call made by '{0}': {}", .1.err)]
+    InNative(String, Box<PhyResult<InterpErr>>),
+
+    // Tasks
+    #[error("only tasks can be awaited, found {0}")]
+    AwaitNonTask(String),
+
+    #[error("a task can't await itself")]
+    AwaitRunningTask,
+
+    #[error("every task left is blocked, none of them can wake the others")]
+    Deadlock,
+
+    #[error("can't allocate the stack of the task: {0}")]
+    TaskStack(String),
+
+    // Watches
+    #[error("in watch of '{0}': {1}")]
+    Watch(String, Message),
+
+    // Member access
+    #[error("module '{1}' has no member '{0}'")]
+    UnknownMember(String, String),
+
+    #[error("values of type {0} have no members")]
+    NoMembers(String),
+
+    #[error("{1} has no component '{0}'")]
+    UnknownComponent(String, String),
+
+    #[error("vector components must be of type int or real, found {0}")]
+    WrongComponentType(String),
+
+    #[error("can't assign to members of values of type {0}")]
+    ImmutableMembers(String),
+
+    #[error("member '{0}' of module '{1}' is private")]
+    PrivateMember(String, String),
+
+    #[error("{0} object: {1}")]
+    Host(String, Message),
+
+    // Indexing
     #[error("{0}")]
-    FnCall(String),
+    Indexing(Message),
+
+    #[error("index must be of type int, found {0}")]
+    NonIntIndex(String),
+
+    // Imports
+    #[error("module '{0}' not found")]
+    ModuleNotFound(String),
+
+    #[error("can't import modules in sandboxed mode")]
+    SandboxedImport,
+
+    #[error("in module '{0}': {1}")]
+    Module(String, String),
+
+    #[error("circular import: {0}")]
+    CircularImport(String),
+
+    #[error("can't load package '{0}': {1}")]
+    Package(String, String),
+
+    // Prelude
+    #[error("in prelude: {0}")]
+    Prelude(String),
+
+    // Limits
+    #[error("memory limit of {0} bytes exceeded")]
+    MemoryLimit(usize),
+
+    #[error("too many nested calls, stopped after {0}")]
+    CallDepth(usize),
+
+    #[error("can't allocate the stack of the run: {0}")]
+    RunStack(String),
+
+    #[error("stepped run stopped before its end")]
+    Stopped,
+
+    // Panics
+    #[error("internal error: {0}")]
+    Internal(String),
 
     // Results
+    #[error("return outside of function")]
+    ReturnOutsideFn,
+
     #[error("return: {0}")]
     Return(RtVal),
 }
 
 impl PhyReport for InterpErr {
-    fn get_err_msg(&self) -> String {
-        format!("{} {}", "Interpreter error:".red(), self)
+    fn get_err_msg(&self, locale: &dyn Locale) -> String {
+        format!(
+            "{} {}",
+            text(locale, "label.interpreter", "Interpreter error:").red(),
+            localize(locale, self)
+        )
+    }
+
+    fn get_hint(&self, locale: &dyn Locale) -> Option<Hint> {
+        let (advice, example) = match self {
+            InterpErr::NonBoolIfCond => (
+                "conditions, and the operands of 'and' and 'or', must be true or false. \
+                Compare the value to get a bool"
+                    .to_string(),
+                "if count > 0 {\n    print(count)\n}",
+            ),
+            InterpErr::WrongArgsNb(expected, found) => (
+                format!(
+                    "the function takes {} arguments and the call gives {}, \
+                    pass one value for each parameter",
+                    expected, found
+                ),
+                "fn add(a, b) {\n    return a + b\n}\nprint(add(1, 2))",
+            ),
+            InterpErr::UninitializedValue => (
+                "the variable was declared without a value, give it one before reading it"
+                    .to_string(),
+                "var total = 0\nprint(total + 1)",
+            ),
+            _ => return None,
+        };
+
+        Some(localize_hint(locale, self, advice, example))
+    }
+}
+
+impl Translate for InterpErr {
+    fn category(&self) -> &'static str {
+        "interpreter"
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            InterpErr::OperationEvaluation(..) => "OperationEvaluation",
+            InterpErr::BangOpOnNonBool => "BangOpOnNonBool",
+            InterpErr::NegateNonNumeric => "NegateNonNumeric",
+            InterpErr::Negation(..) => "Negation",
+            InterpErr::Unit(..) => "Unit",
+            InterpErr::VarDeclEnv(..) => "VarDeclEnv",
+            InterpErr::GetVarEnv(..) => "GetVarEnv",
+            InterpErr::AssignEnv(..) => "AssignEnv",
+            InterpErr::UninitializedValue => "UninitializedValue",
+            InterpErr::NonBoolIfCond => "NonBoolIfCond",
+            InterpErr::NonBoolWhileCond => "NonBoolWhileCond",
+            InterpErr::ForLoop(..) => "ForLoop",
+            InterpErr::ForDestructuring(..) => "ForDestructuring",
+            InterpErr::NotIterable(..) => "NotIterable",
+            InterpErr::NonIntRange(..) => "NonIntRange",
+            InterpErr::NonBoolComprehensionCond => "NonBoolComprehensionCond",
+            InterpErr::NonBoolFilter(..) => "NonBoolFilter",
+            InterpErr::ReadInput(..) => "ReadInput",
+            InterpErr::NonFnCall => "NonFnCall",
+            InterpErr::WrongArgsNb(..) => "WrongArgsNb",
+            InterpErr::TooFewArgs(..) => "TooFewArgs",
+            InterpErr::WrongArgsRange(..) => "WrongArgsRange",
+            InterpErr::WrongArgType(..) => "WrongArgType",
+            InterpErr::FnCall(..) => "FnCall",
+            InterpErr::ContractViolation { .. } => "ContractViolation",
+            InterpErr::NonBoolContract => "NonBoolContract",
+            InterpErr::InNative(..) => "InNative",
+            InterpErr::AwaitNonTask(..) => "AwaitNonTask",
+            InterpErr::AwaitRunningTask => "AwaitRunningTask",
+            InterpErr::Deadlock => "Deadlock",
+            InterpErr::TaskStack(..) => "TaskStack",
+            InterpErr::Watch(..) => "Watch",
+            InterpErr::UnknownMember(..) => "UnknownMember",
+            InterpErr::NoMembers(..) => "NoMembers",
+            InterpErr::UnknownComponent(..) => "UnknownComponent",
+            InterpErr::WrongComponentType(..) => "WrongComponentType",
+            InterpErr::ImmutableMembers(..) => "ImmutableMembers",
+            InterpErr::PrivateMember(..) => "PrivateMember",
+            InterpErr::Host(..) => "Host",
+            InterpErr::Indexing(..) => "Indexing",
+            InterpErr::NonIntIndex(..) => "NonIntIndex",
+            InterpErr::ModuleNotFound(..) => "ModuleNotFound",
+            InterpErr::SandboxedImport => "SandboxedImport",
+            InterpErr::Module(..) => "Module",
+            InterpErr::CircularImport(..) => "CircularImport",
+            InterpErr::Package(..) => "Package",
+            InterpErr::Prelude(..) => "Prelude",
+            InterpErr::MemoryLimit(..) => "MemoryLimit",
+            InterpErr::CallDepth(..) => "CallDepth",
+            InterpErr::RunStack(..) => "RunStack",
+            InterpErr::Stopped => "Stopped",
+            InterpErr::Internal(..) => "Internal",
+            InterpErr::ReturnOutsideFn => "ReturnOutsideFn",
+            InterpErr::Return(..) => "Return",
+        }
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        match self {
+            InterpErr::NotIterable(v)
+            | InterpErr::NonIntRange(v)
+            | InterpErr::NonBoolFilter(v)
+            | InterpErr::ReadInput(v)
+            | InterpErr::AwaitNonTask(v)
+            | InterpErr::TaskStack(v)
+            | InterpErr::NoMembers(v)
+            | InterpErr::WrongComponentType(v)
+            | InterpErr::ImmutableMembers(v)
+            | InterpErr::NonIntIndex(v)
+            | InterpErr::ModuleNotFound(v)
+            | InterpErr::CircularImport(v)
+            | InterpErr::Prelude(v)
+            | InterpErr::RunStack(v)
+            | InterpErr::Internal(v) => vec![Arg::text(v)],
+            InterpErr::ForDestructuring(a, b) => vec![Arg::text(a), Arg::text(b)],
+            InterpErr::WrongArgsNb(a, b)
+            | InterpErr::TooFewArgs(a, b) => vec![Arg::text(a), Arg::text(b)],
+            InterpErr::WrongArgsRange(a, b, c) => vec![Arg::text(a), Arg::text(b), Arg::text(c)],
+            InterpErr::WrongArgType(a, b, c) => vec![Arg::text(a), Arg::text(b), Arg::text(c)],
+            InterpErr::ContractViolation { kind, func, clause, .. } => {
+                vec![Arg::text(kind), Arg::text(func), Arg::text(clause)]
+            }
+            InterpErr::InNative(name, e) => vec![Arg::text(name), Arg::message(&e.err)],
+            InterpErr::Watch(name, msg) => vec![Arg::text(name), msg.clone().into()],
+            InterpErr::Host(name, msg) => vec![Arg::text(name), msg.clone().into()],
+            InterpErr::UnknownMember(a, b)
+            | InterpErr::UnknownComponent(a, b)
+            | InterpErr::PrivateMember(a, b)
+            | InterpErr::Module(a, b)
+            | InterpErr::Package(a, b) => vec![Arg::text(a), Arg::text(b)],
+            InterpErr::MemoryLimit(v) | InterpErr::CallDepth(v) => vec![Arg::text(v)],
+            InterpErr::Return(v) => vec![Arg::text(v)],
+            _ => vec![],
+        }
+    }
+
+    fn wrapped(&self) -> Option<&Message> {
+        match self {
+            InterpErr::OperationEvaluation(msg)
+            | InterpErr::Negation(msg)
+            | InterpErr::Unit(msg)
+            | InterpErr::VarDeclEnv(msg)
+            | InterpErr::GetVarEnv(msg)
+            | InterpErr::AssignEnv(msg)
+            | InterpErr::ForLoop(msg)
+            | InterpErr::FnCall(msg)
+            | InterpErr::Indexing(msg) => Some(msg),
+            _ => None,
+        }
+    }
+}
+
+// Condition of a violated contract, reported after the violation
+pub struct ContractClause;
+
+impl PhyReport for ContractClause {
+    fn get_err_msg(&self, locale: &dyn Locale) -> String {
+        format!(
+            "{} {}",
+            text(locale, "label.note", "Note:").blue(),
+            text(locale, "note.contract_clause", "condition declared here")
+        )
+    }
+}
+
+// Location where an error raised inside a call, in a called function or in
+// a call made by a native, reported after it
+pub struct ErrorSite;
+
+impl PhyReport for ErrorSite {
+    fn get_err_msg(&self, locale: &dyn Locale) -> String {
+        format!(
+            "{} {}",
+            text(locale, "label.note", "Note:").blue(),
+            text(locale, "note.error_site", "error raised here")
+        )
     }
 }
 
 pub(crate) type PhyResInterp = PhyResult<InterpErr>;
 pub(crate) type InterpRes = Result<RtVal, PhyResInterp>;
 
+// Function called from the host, either by its global name or as a value
+pub enum FnRef<'a> {
+    Name(&'a str),
+    Value(RtVal),
+}
+
+impl<'a> From<&'a str> for FnRef<'a> {
+    fn from(value: &'a str) -> Self {
+        FnRef::Name(value)
+    }
+}
+
+impl From<RtVal> for FnRef<'_> {
+    fn from(value: RtVal) -> Self {
+        FnRef::Value(value)
+    }
+}
+
 // --------------
 //  Interpreting
 // --------------
 pub struct Interpreter {
+    // Natives, constants and prelude, in the enclosing environment of the
+    // globals so that the scripts can shadow them
+    pub builtins: Rc<RefCell<Env>>,
     // In RefCell because visitor methods only borrow (&) so we must be
     // able to mutate thanks to RefCell and it can be multiple owners
     pub globals: Rc<RefCell<Env>>,
     pub env: Rc<RefCell<Env>>,
     pub locals: HashMap<Rc<Expr>, usize>,
+    // Where natives like print write, stdout by default. Embedders can
+    // replace it to capture the script's output
+    pub output: Box<dyn Write>,
+    pub output_failure: OutputFailure,
+    // The last run stopped because its output was closed
+    output_closed: bool,
+    // Code is running, called by the host
+    in_run: bool,
+    // Where stdin natives and the REPL read, stdin by default
+    pub input: Box<dyn BufRead>,
+    // Forbids natives to access the file system
+    pub sandboxed: bool,
+    // Approximate bytes used by the values bound in the environments. The
+    // script is aborted if it goes above the limit. Without a limit nothing
+    // is counted, a limit set later ignores the values already bound
+    pub memory_used: usize,
+    pub memory_limit: Option<usize>,
+    // Calls nested deeper fail, like a recursion without end. They also
+    // fail once the stack is close to its end, whatever their number
+    pub max_call_depth: usize,
+    call_depth: usize,
+    // Lowest address the calls can reach on the stack running the code,
+    // the one of the run or of the task. Zero if it's unknown
+    stack_limit: usize,
+    // Stack of the runs, kept between them
+    run_stack: Option<DefaultStack>,
+    // Whether a function of the prelude is running, called from the
+    // script at 'prelude_call' or from the host
+    in_prelude: bool,
+    prelude_call: Option<Loc>,
+    // Operations left to the stepped run, if this is one
+    stepping: Option<Stepping>,
+    // Evaluates the 'requires' and 'ensures' clauses of the functions
+    pub contracts: bool,
+    // A function declared again replaces the previous one of the same
+    // scope instead of failing, for the interactive mode
+    pub redefine_fns: bool,
+    // How 'print' writes the reals
+    pub display: DisplayConfig,
+    // Statements run and variables set, kept for '--record' when Some
+    pub recording: Option<Vec<TraceEvent>>,
+    metrics: Metrics,
+    env_depth: usize,
+    // Spawned tasks that didn't end, in the order they run next
+    tasks: VecDeque<Scheduled>,
+    // Task whose code is running, None for the main code
+    running: Option<Running>,
+    // Handlers of each event, in the order they subscribed
+    events: HashMap<EcoString, Vec<RtVal>>,
+    // Messages sent to the actors and not handled yet, in order
+    mailbox: VecDeque<(Rc<Actor>, RtVal)>,
+    // Callbacks scheduled by 'set_timeout' and 'set_interval', run by
+    // 'run_until_idle'
+    timers: Vec<Timer>,
+    next_timer_id: i64,
+    // Assignments only look for watches once one was declared. They don't
+    // trigger while a watch runs, so that it can correct the value
+    has_watches: bool,
+    in_watch: bool,
+    // Global functions and natives called by each call site, by address of
+    // its callee, so that calls in loops don't look them up each time.
+    // Cleared when one of their names is declared or assigned, which could
+    // shadow or replace them, and when a new tree runs as addresses are
+    // reused
+    callee_cache: HashMap<*const IdentifierExpr, RtVal, BuildHasherDefault<AddrHasher>>,
+    cached_callees: Vec<EcoString>,
+    // Directories searched in order for the imported modules
+    pub module_paths: Vec<PathBuf>,
+    // Root directory of the packages, by the name they are imported with
+    pub packages: HashMap<EcoString, PathBuf>,
+    // Finds the packages missing from 'packages' when they are imported
+    pub package_resolver: Option<Box<dyn PackageResolver>>,
+    // Modules already loaded, by file, so that each one runs only once
+    modules: HashMap<PathBuf, Rc<Module>>,
+    // Modules being loaded, the last one is the one running
+    importing: Vec<ImportFrame>,
+    // Location of the last statement or call evaluated, reported if
+    // the interpreter panics
+    last_loc: Loc,
 }
 
-impl Interpreter {
-    pub fn new() -> Self {
-        let globals = Rc::new(RefCell::new(Env::new(None)));
-
-        let _ = globals.borrow_mut().declare_var(
-            EcoString::from("clock"),
-            RtVal::NativeFnVal(Rc::new(PhyNativeFn {
-                name: EcoString::from("clock"),
-                func: Rc::new(NativeClock),
-            })),
-        );
+// Root directory of a package from its name, None if there is no such
+// package. Called once per package, on its first import, so that the
+// hosts only fetch the ones used
+pub trait PackageResolver {
+    fn resolve(&mut self, name: &str) -> Option<Result<PathBuf, String>>;
+}
 
-        let env = globals.clone();
+// What a failed write to the output does, like when the reader of the
+// pipe exited in 'phy -f script.arc | head'
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum OutputFailure {
+    // The write raises a runtime error, reported like the others
+    #[default]
+    Error,
+    // A closed output ends the run as if it completed, the other write
+    // errors still raise
+    Stop,
+}
 
-        Self { globals, env, locals: HashMap::new() }
-    }
+// Counters of what the scripts did, for the hosts monitoring them. They
+// add up over the runs until reset
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metrics {
+    pub statements: usize,
+    pub calls: usize,
+    // Values stored in variables, parameters included, by type name
+    pub allocations: BTreeMap<&'static str, usize>,
+    // Deepest nesting of the scopes run, blocks and function bodies
+    pub max_env_depth: usize,
 }
 
-impl Interpreter {
-    pub fn interpret(&mut self, nodes: &Vec<Stmt>) -> InterpRes {
-        let mut res: RtVal = RtVal::new_null();
+// Module being loaded and the import that asked for it, located in the
+// code of the previous frame
+struct ImportFrame {
+    name: String,
+    file: PathBuf,
+    code: Rc<str>,
+    loc: Loc,
+}
 
-        for node in nodes {
-            match node.accept(self) {
-                Ok(r) => res = r,
-                Err(e) => return Err(e),
-            }
-        }
+// Addresses are already unique, only spread for the buckets
+#[derive(Default)]
+struct AddrHasher(u64);
 
-        Ok(res)
+impl Hasher for AddrHasher {
+    fn finish(&self) -> u64 {
+        self.0
     }
-}
 
-impl VisitStmt<RtVal, InterpErr> for Interpreter {
-    fn visit_expr_stmt(&mut self, stmt: &ExprStmt) -> InterpRes {
-        stmt.expr.accept(self)
+    fn write(&mut self, bytes: &[u8]) {
+        bytes.iter().for_each(|b| self.write_u64(*b as u64));
     }
 
-    fn visit_print_stmt(&mut self, stmt: &PrintStmt) -> InterpRes {
-        let value = stmt.expr.accept(self)?;
-        println!("{}", value);
+    fn write_u64(&mut self, n: u64) {
+        self.0 = (self.0 ^ n).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    }
 
-        Ok(RtVal::new_null())
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
     }
-    fn visit_var_decl_stmt(&mut self, stmt: &VarDeclStmt) -> InterpRes {
-        let value = match &stmt.value {
-            Some(v) => v.accept(self)?,
-            None => RtVal::new_null(),
-        };
+}
 
-        self.env
-            .borrow_mut()
-            .declare_var(stmt.name.clone(), value)
-            .map_err(|e| {
-                PhyResult::new(InterpErr::VarDeclEnv(e.to_string()), Some(stmt.loc.clone()))
-            })?;
+// Values taken by a 'for' loop, evaluated before it starts
+enum ForValues {
+    Range(std::ops::Range<i64>),
+    List(Vec<RtVal>),
+    Iter(Rc<Iter>),
+}
 
-        Ok(RtVal::new_null())
-    }
+// Stack of each task, as big as the one of the main thread. The pages are
+// only allocated once used
+const TASK_STACK_SIZE: usize = 8 << 20;
 
-    fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> InterpRes {
-        let new_env = Env::new(Some(self.env.clone()));
-        self.execute_block_stmt(&stmt.stmts, new_env)?;
+// Stack the code runs on, instead of the one of the host whose size isn't
+// known. Allocated once used too
+const RUN_STACK_SIZE: usize = 256 << 20;
 
-        Ok(RtVal::new_null())
-    }
+// Room kept at the end of the stacks for the natives and the errors, a
+// call nearer to the end fails
+const STACK_RED_ZONE: usize = 256 << 10;
 
-    fn visit_if_stmt(&mut self, stmt: &IfStmt) -> InterpRes {
-        let cond = stmt.condition.accept(self)?;
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 10_000;
 
-        match cond {
-            RtVal::BoolVal(b) => match b.borrow().value {
-                true => {
-                    if let Some(t) = &stmt.then_branch {
-                        t.accept(self)
-                    } else {
-                        Ok(RtVal::new_null())
-                    }
-                }
-                false => {
-                    if let Some(e) = &stmt.else_branch {
-                        e.accept(self)
-                    } else {
-                        Ok(RtVal::new_null())
-                    }
-                }
-            },
-            _ => Err(PhyResult::new(
-                InterpErr::NonBoolIfCond,
-                Some(stmt.loc.clone()),
-            )),
-        }
-    }
+// Why a task gave the control back to the main code
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Suspend {
+    // At a yield point
+    Yield,
+    // Waiting for a value or a task, nothing changed since the last time
+    Blocked,
+}
 
-    fn visit_while_stmt(&mut self, stmt: &WhileStmt) -> InterpRes {
-        loop {
-            let cond = stmt.condition.accept(self)?;
+// Resumed with the interpreter, so that the compiler sees that the task
+// may change it
+type TaskStack = Coroutine<*mut Interpreter, Suspend, InterpRes, DefaultStack>;
 
-            match cond {
-                RtVal::BoolVal(b) => match b.borrow().value {
-                    true => {
-                        stmt.body.accept(self)?;
-                    }
-                    false => break,
-                },
-                _ => {
-                    return Err(PhyResult::new(
-                        InterpErr::NonBoolWhileCond,
-                        Some(stmt.loc.clone()),
-                    ))
-                }
-            }
-        }
+// Task in the queue, with its stack and its environment once started
+struct Scheduled {
+    task: Rc<Task>,
+    stack: Option<TaskStack>,
+    context: Context,
+}
 
-        Ok(RtVal::new_null())
-    }
+// Where the interpreter is in the code of the main program or of a task,
+// swapped when the control goes from one to the other
+struct Context {
+    env: Rc<RefCell<Env>>,
+    env_depth: usize,
+    call_depth: usize,
+    stack_limit: usize,
+    in_prelude: bool,
+    prelude_call: Option<Loc>,
+    in_watch: bool,
+    running: Option<Running>,
+}
 
-    fn visit_for_stmt(&mut self, stmt: &ForStmt) -> InterpRes {
-        let new_env = Env::new(Some(self.env.clone()));
-        let prev_env = std::mem::replace(&mut self.env, Rc::new(RefCell::new(new_env)));
+struct Running {
+    task: Rc<Task>,
+    // Valid as long as the stack of the task, which only runs while the
+    // interpreter isn't moved
+    yielder: *const Yielder<*mut Interpreter, Suspend>,
+}
 
-        self.visit_var_decl_stmt(&stmt.placeholder)?;
-        let mut range = 0..stmt.range.start;
+// Intervals are scheduled again each time they run, until cleared
+struct Timer {
+    id: i64,
+    due: Instant,
+    callback: RtVal,
+    interval: Option<Duration>,
+}
 
-        if let Some(i) = stmt.range.end {
-            range = stmt.range.start..i;
-        }
+struct Stepping {
+    ops: usize,
+    // Valid while the run is resumed, the interpreter is only reached
+    // through it then
+    yielder: *const Yielder<Option<usize>, ()>,
+    // By the host, the operations left fail until the run ends
+    stopped: bool,
+}
 
-        for i in range {
-            self.env
-                .borrow_mut()
-                .assign(stmt.placeholder.name.clone(), i.into())
-                .map_err(|e| {
-                    PhyResult::new(InterpErr::ForLoop(e.to_string()), Some(stmt.loc.clone()))
-                })?;
+// Resumed with the operations of the step, None to stop the run
+type StepStack = Coroutine<Option<usize>, (), InterpRes, DefaultStack>;
 
-            stmt.body.accept(self)?;
-        }
+// What a step of a stepped run did
+#[derive(Debug, PartialEq)]
+pub enum Step {
+    // Used all its operations, the next step goes on from there
+    Paused,
+    // The program ended, with the value of its last statement
+    Done(RtVal),
+}
 
-        let _ = std::mem::replace(&mut self.env, prev_env);
+// Program run a few operations at a time, like a bit at each frame of a
+// game. It owns the interpreter, kept on the heap where the paused run
+// points to, and gives it back once the run ended or was stopped
+pub struct SteppedRun {
+    interp: *mut Interpreter,
+    run: Option<StepStack>,
+}
 
-        Ok(RtVal::new_null())
-    }
+// Arc code run at the creation of the interpreter, defining the
+// convenience functions shared by all programs
+pub const DEFAULT_PRELUDE: &str = include_str!("prelude.arc");
 
-    fn visit_fn_decl_stmt(&mut self, stmt: &FnDeclStmt) -> Result<RtVal, PhyResult<InterpErr>> {
-        let func = RtVal::new_fn(stmt, self.env.clone());
+// Standard library written in arc, imported with 'import std.name'
+const STD_MODULES: [(&str, &str); 4] = [
+    ("list", include_str!("std/list.arc")),
+    ("string", include_str!("std/string.arc")),
+    ("math", include_str!("std/math.arc")),
+    ("functional", include_str!("std/functional.arc")),
+];
 
-        self.env
-            .borrow_mut()
-            .declare_var(stmt.name.clone(), func)
-            .map_err(|_| {
-                PhyResult::new(
-                    InterpErr::VarDeclEnv(stmt.name.to_string()),
-                    Some(stmt.loc.clone()),
-                )
-            })?;
+impl Interpreter {
+    pub fn new() -> Self {
+        let mut interp = Self::without_prelude();
+        interp
+            .load_prelude(DEFAULT_PRELUDE)
+            .expect("default prelude must be valid");
 
-        Ok(RtVal::new_null())
+        interp
     }
 
-    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> Result<RtVal, PhyResult<InterpErr>> {
-        let mut value = RtVal::new_null();
-
-        if let Some(v) = &stmt.value {
-            value = v.accept(self)?;
-        }
+    // Uses a custom prelude instead of the default one
+    pub fn with_prelude(prelude: &str) -> Result<Self, PhyResInterp> {
+        let mut interp = Self::without_prelude();
+        interp.load_prelude(prelude)?;
 
-        Err(PhyResult::new(InterpErr::Return(value), None))
+        Ok(interp)
     }
-}
 
-impl Interpreter {
-    pub fn execute_block_stmt(&mut self, stmts: &Vec<Stmt>, env: Env) -> InterpRes {
-        let prev_env = std::mem::replace(&mut self.env, Rc::new(RefCell::new(env)));
+    // Only natives are declared
+    pub fn without_prelude() -> Self {
+        let builtins = Rc::new(RefCell::new(Env { global: true, ..Env::new(None) }));
+        let globals = Env { global: true, ..Env::new(Some(builtins.clone())) };
+        let globals = Rc::new(RefCell::new(globals));
+        let env = globals.clone();
 
-        let mut res = Ok(RtVal::new_null());
-        for s in stmts {
-            res = s.accept(self);
+        let interp = Self {
+            builtins,
+            globals,
+            env,
+            locals: HashMap::new(),
+            output: Box::new(io::stdout()),
+            output_failure: OutputFailure::default(),
+            output_closed: false,
+            in_run: false,
+            input: Box::new(BufReader::new(io::stdin())),
+            sandboxed: false,
+            memory_used: 0,
+            memory_limit: None,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            call_depth: 0,
+            stack_limit: 0,
+            run_stack: None,
+            in_prelude: false,
+            prelude_call: None,
+            stepping: None,
+            contracts: false,
+            redefine_fns: false,
+            display: DisplayConfig::default(),
+            tasks: VecDeque::new(),
+            running: None,
+            events: HashMap::new(),
+            mailbox: VecDeque::new(),
+            timers: vec![],
+            next_timer_id: 0,
+            has_watches: false,
+            in_watch: false,
+            callee_cache: HashMap::default(),
+            cached_callees: vec![],
+            module_paths: vec![PathBuf::from(".")],
+            packages: HashMap::new(),
+            package_resolver: None,
+            modules: HashMap::new(),
+            importing: vec![],
+            last_loc: Loc::default(),
+            recording: None,
+            metrics: Metrics::default(),
+            env_depth: 0,
+        };
 
-            if res.is_err() {
-                break;
-            }
-        }
+        interp.declare_native("clock", Rc::new(NativeClock));
+        interp.declare_native("print", Rc::new(NativePrint));
+        interp.declare_native("globals", Rc::new(NativeGlobals));
+        interp.declare_native("locals", Rc::new(NativeLocals));
+        interp.declare_native("dir", Rc::new(NativeDir));
+        interp.declare_native("eval", Rc::new(NativeEval));
+        interp.declare_native("fn_name", Rc::new(NativeFnName));
+        interp.declare_native("fn_arity", Rc::new(NativeFnArity));
+        interp.declare_native("fn_params", Rc::new(NativeFnParams));
+        interp.declare_native("vec2", Rc::new(NativeVec2));
+        interp.declare_native("vec3", Rc::new(NativeVec3));
+        interp.declare_native("mat", Rc::new(NativeMat));
+        interp.declare_native("dot", Rc::new(NativeDot));
+        interp.declare_native("cross", Rc::new(NativeCross));
+        interp.declare_native("norm", Rc::new(NativeNorm));
+        interp.declare_native("round", Rc::new(NativeRound));
+        interp.declare_native("floor", Rc::new(NativeFloor));
+        interp.declare_native("ceil", Rc::new(NativeCeil));
+        interp.declare_native("trunc", Rc::new(NativeTrunc));
+        interp.declare_native("to_fixed", Rc::new(NativeToFixed));
+        interp.declare_native("to_str", Rc::new(NativeToStr));
+        interp.declare_native("repeat", Rc::new(NativeRepeat));
+        interp.declare_native("enumerate", Rc::new(NativeEnumerate));
+        interp.declare_native("zip", Rc::new(NativeZip));
+        interp.declare_native("parse_int", Rc::new(NativeParseInt));
+        interp.declare_native("parse_real", Rc::new(NativeParseReal));
+        interp.declare_native("ord", Rc::new(NativeOrd));
+        interp.declare_native("chr", Rc::new(NativeChr));
+        interp.declare_native("bytes", Rc::new(NativeBytes));
+        interp.declare_native("stdin_read_all", Rc::new(NativeStdinReadAll));
+        interp.declare_native("stdin_lines", Rc::new(NativeStdinLines));
+        interp.declare_native("chan", Rc::new(NativeChan));
+        interp.declare_native("send", Rc::new(NativeSend));
+        interp.declare_native("actor", Rc::new(NativeActor));
+        interp.declare_native("recv", Rc::new(NativeRecv));
+        interp.declare_native("set_timeout", Rc::new(NativeSetTimeout));
+        interp.declare_native("set_interval", Rc::new(NativeSetInterval));
+        interp.declare_native("clear_timer", Rc::new(NativeClearTimer));
+        interp.declare_native("watch", Rc::new(NativeWatch));
+        interp.declare_native("on", Rc::new(NativeOn));
+        interp.declare_native("emit", Rc::new(NativeEmit));
+        interp.declare_native("help", Rc::new(NativeHelp));
 
-        let _ = std::mem::replace(&mut self.env, prev_env);
+        interp.declare_const("PI", std::f64::consts::PI.into());
+        interp.declare_const("TAU", std::f64::consts::TAU.into());
+        interp.declare_const("E", std::f64::consts::E.into());
+        interp.declare_const("INF", f64::INFINITY.into());
+        interp.declare_const("NAN", f64::NAN.into());
 
-        res
-    }
-}
+        interp.declare_module("math", vec![
+            ("sqrt", Rc::new(NativeSqrt)),
+            ("abs", Rc::new(NativeAbs)),
+            ("pow", Rc::new(NativePow)),
+            ("arg", Rc::new(NativeArg)),
+            ("conj", Rc::new(NativeConj)),
+        ]);
+        interp.declare_module("str", vec![
+            ("upper", Rc::new(NativeUpper)),
+            ("lower", Rc::new(NativeLower)),
+            ("len", Rc::new(NativeStrLen)),
+            ("slice", Rc::new(NativeStrSlice)),
+            ("get", Rc::new(NativeStrGet)),
+            ("chars", Rc::new(NativeStrChars)),
+            ("reverse", Rc::new(NativeStrReverse)),
+        ]);
+        interp.declare_module("list", vec![
+            ("new", Rc::new(NativeListNew)),
+            ("len", Rc::new(NativeListLen)),
+            ("get", Rc::new(NativeListGet)),
+            ("push", Rc::new(NativeListPush)),
+            ("pop", Rc::new(NativeListPop)),
+            ("insert", Rc::new(NativeListInsert)),
+            ("remove", Rc::new(NativeListRemove)),
+            ("clear", Rc::new(NativeListClear)),
+        ]);
+        interp.declare_module("iter", vec![
+            ("range", Rc::new(NativeIterRange)),
+            ("map", Rc::new(NativeIterMap)),
+            ("filter", Rc::new(NativeIterFilter)),
+            ("take", Rc::new(NativeIterTake)),
+            ("skip", Rc::new(NativeIterSkip)),
+            ("collect", Rc::new(NativeIterCollect)),
+            ("sum", Rc::new(NativeIterSum)),
+        ]);
+        interp.declare_module("bin", vec![
+            ("len", Rc::new(NativeBytesLen)),
+            ("get", Rc::new(NativeBytesGet)),
+            ("slice", Rc::new(NativeBytesSlice)),
+            ("to_str", Rc::new(NativeBytesToStr)),
+            ("to_hex", Rc::new(NativeToHex)),
+            ("from_hex", Rc::new(NativeFromHex)),
+            ("to_base64", Rc::new(NativeToBase64)),
+            ("from_base64", Rc::new(NativeFromBase64)),
+        ]);
+        interp.declare_module("fs", vec![
+            ("read", Rc::new(NativeReadFile)),
+            ("write", Rc::new(NativeWriteFile)),
+            ("read_bytes", Rc::new(NativeReadFileBytes)),
+            ("write_bytes", Rc::new(NativeWriteFileBytes)),
+            ("path_join", Rc::new(NativePathJoin)),
+            ("path_exists", Rc::new(NativePathExists)),
+            ("path_basename", Rc::new(NativePathBasename)),
+            ("path_ext", Rc::new(NativePathExt)),
+            ("list_dir", Rc::new(NativeListDir)),
+        ]);
 
-impl VisitExpr<RtVal, InterpErr> for Interpreter {
-    fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> InterpRes {
-        let lhs = expr.left.accept(self)?;
-        if lhs == RtVal::new_null() {
-            return Err(PhyResult::new(
-                InterpErr::UninitializedValue,
-                Some(expr.left.get_loc()),
-            ));
-        }
+        interp
+    }
 
-        let rhs = expr.right.accept(self)?;
-        if rhs == RtVal::new_null() {
-            return Err(PhyResult::new(
-                InterpErr::UninitializedValue,
-                Some(expr.right.get_loc()),
-            ));
-        }
+    // Executes the code in the builtins, its declarations are then visible
+    // from every program run by this interpreter
+    pub fn load_prelude(&mut self, code: &str) -> Result<(), PhyResInterp> {
+        let to_err = |msgs: Vec<String>| PhyResult::new(InterpErr::Prelude(msgs.join(", ")), None);
 
-        match lhs.operate(&rhs, &expr.operator) {
-            Ok(res) => Ok(res),
-            Err(e) => Err(PhyResult::new(
-                InterpErr::OperationEvaluation(e.to_string()),
-                Some(expr.loc.clone()),
-            )),
-        }
-    }
+        let mut lexer = Lexer::new();
+        let tokens = lexer
+            .tokenize(code)
+            .map_err(|e| to_err(e.iter().map(|e| e.err.to_string()).collect()))?;
 
-    fn visit_assign_expr(&mut self, expr: &AssignExpr) -> InterpRes {
-        let value = expr.value.accept(self)?;
+        let mut parser = Parser::default();
+        let nodes = parser
+            .parse(tokens)
+            .map_err(|e| to_err(e.iter().map(|e| e.err.to_string()).collect()))?;
 
-        self.env
-            .borrow_mut()
-            .assign(expr.name.clone(), value)
-            .map_err(|e| {
-                PhyResult::new(InterpErr::AssignEnv(e.to_string()), Some(expr.loc.clone()))
-            })?;
+        // Not counted in the metrics, the scripts didn't run it
+        let metrics = self.metrics.clone();
+        let prev_env = std::mem::replace(&mut self.env, self.builtins.clone());
+        let res = self.interpret(&nodes);
+        self.env = prev_env;
+        self.metrics = metrics;
 
-        Ok(RtVal::new_null())
+        res.map(|_| ()).map_err(|e| to_err(vec![e.err.to_string()]))
     }
 
-    fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> InterpRes {
-        expr.expr.accept(self)
+    // Variables, functions and natives visible from the current environment
+    pub fn get_visible_names(&self) -> Vec<EcoString> {
+        self.env.borrow().get_visible_names()
     }
 
-    fn visit_int_literal_expr(&mut self, expr: &IntLiteralExpr) -> InterpRes {
-        Ok(expr.value.into())
+    // Visible names with their value, in the order of get_visible_names
+    pub fn get_visible_vars(&self) -> Vec<(EcoString, RtVal)> {
+        let env = self.env.borrow();
+
+        env.get_visible_names()
+            .into_iter()
+            .filter_map(|n| env.get_var(n.clone()).ok().map(|v| (n, v)))
+            .collect()
     }
 
-    fn visit_real_literal_expr(&mut self, expr: &RealLiteralExpr) -> InterpRes {
-        Ok(expr.value.into())
+    fn declare_native(&self, name: &str, func: Rc<dyn Callable<NativeFnErr>>) {
+        let _ = self.builtins.borrow_mut().declare_var(
+            EcoString::from(name),
+            RtVal::NativeFnVal(Rc::new(PhyNativeFn {
+                name: EcoString::from(name),
+                func,
+            })),
+        );
     }
 
-    fn visit_str_literal_expr(&mut self, expr: &StrLiteralExpr) -> InterpRes {
-        Ok(expr.value.clone().into())
+    // Builtin that scripts can't assign, embedders can register their own
+    pub fn declare_const(&self, name: &str, value: RtVal) {
+        let _ = self
+            .builtins
+            .borrow_mut()
+            .declare_const(EcoString::from(name), value);
     }
 
-    fn visit_identifier_expr(&mut self, expr: &IdentifierExpr) -> InterpRes {
-        match expr.name.as_str() {
-            "true" => Ok(true.into()),
-            "false" => Ok(false.into()),
-            "null" => Ok(RtVal::new_null()),
-            _ => self
-                .env
-                .borrow()
-                .get_var(expr.name.clone())
-                .map_err(|e| {
-                    PhyResult::new(InterpErr::GetVarEnv(e.to_string()), Some(expr.loc.clone()))
-                }),
+    // Global variable declared or assigned from the host, like the results
+    // of the interactive mode
+    pub fn set_global(&mut self, name: &str, value: RtVal) {
+        let name = EcoString::from(name);
+        let mut globals = self.globals.borrow_mut();
+
+        if globals.declare_var(name.clone(), value.clone()).is_err() {
+            let _ = globals.assign(name.clone(), value);
         }
+        drop(globals);
+
+        self.rebound(&name);
     }
 
-    fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> InterpRes {
-        let value = expr.right.accept(self)?;
+    // The handler, a function or a native, is called with the payload of
+    // each event of that name, the ones emitted by the scripts too
+    pub fn subscribe(&mut self, event: &str, handler: RtVal) {
+        self.events.entry(event.into()).or_default().push(handler);
+    }
 
-        match (&value, expr.operator.as_str()) {
-            (RtVal::IntVal(..) | RtVal::RealVal(..), "!") => {
-                return Err(PhyResult::new(
-                    InterpErr::BangOpOnNonBool,
-                    Some(expr.loc.clone()),
-                ))
+    // Calls the handlers of the event in the order they subscribed and
+    // returns how many there were. The ones subscribed while it runs wait
+    // for the next event
+    pub fn emit(&mut self, event: &str, payload: RtVal) -> Result<usize, PhyResInterp> {
+        let handlers = self.events.get(event).cloned().unwrap_or_default();
+
+        self.guarded(|interp| {
+            for handler in &handlers {
+                interp.call_value(handler.clone(), vec![payload.clone()], None)?;
             }
-            (RtVal::BoolVal(..) | RtVal::StrVal(..) | RtVal::Null, "-") => {
-                return Err(PhyResult::new(
-                    InterpErr::NegateNonNumeric,
-                    Some(expr.loc.clone()),
-                ))
+
+            Ok(handlers.len())
+        })
+    }
+
+    // Exposes a Rust value to the scripts under the given global name
+    pub fn declare_host_object(&self, name: &str, object: Rc<dyn HostObject>) {
+        let _ = self
+            .globals
+            .borrow_mut()
+            .declare_var(EcoString::from(name), RtVal::HostVal(object));
+    }
+
+    // Natives grouped in a namespace, accessed with 'module.native'
+    fn declare_module(&self, name: &str, natives: Vec<(&str, Rc<dyn Callable<NativeFnErr>>)>) {
+        let members = natives
+            .into_iter()
+            .map(|(n, func)| {
+                let native = PhyNativeFn {
+                    name: format!("{}.{}", name, n).into(),
+                    func,
+                };
+
+                (EcoString::from(n), RtVal::NativeFnVal(Rc::new(native)))
+            })
+            .collect();
+
+        let _ = self.builtins.borrow_mut().declare_var(
+            EcoString::from(name),
+            RtVal::ModuleVal(Rc::new(Module {
+                name: EcoString::from(name),
+                members,
+                private: BTreeSet::new(),
+            })),
+        );
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    // Any panic while interpreting is reported as an internal error so
+    // that it doesn't bring down the host
+    pub fn interpret(&mut self, nodes: &Vec<Stmt>) -> InterpRes {
+        self.forget_callees();
+
+        let res = self.guarded(|interp| interp.interpret_nodes(nodes));
+
+        match self.output_closed {
+            true => Ok(RtVal::new_null()),
+            false => res,
+        }
+    }
+
+    // Event loop: runs the waiting tasks and the timers in the order they
+    // are due, sleeping until the next one. Returns when nothing is left,
+    // so never while an interval isn't cleared
+    pub fn run_until_idle(&mut self) -> Result<(), PhyResInterp> {
+        let res = self.guarded(|interp| loop {
+            interp.run_tasks()?;
+
+            // Handled before the timers, the messages they send too
+            if let Some((actor, msg)) = interp.mailbox.pop_front() {
+                interp.call_value(actor.handler.clone(), vec![msg], None)?;
+                continue;
             }
-            _ => {}
+
+            let next = interp
+                .timers
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, t)| (t.due, t.id))
+                .map(|(i, _)| i);
+
+            let Some(i) = next else {
+                return Ok(());
+            };
+
+            let mut timer = interp.timers.swap_remove(i);
+            thread::sleep(timer.due.saturating_duration_since(Instant::now()));
+
+            // Rescheduled before the call so that the callback can clear it
+            let callback = timer.callback.clone();
+
+            if let Some(period) = timer.interval {
+                timer.due += period;
+                interp.timers.push(timer);
+            }
+
+            interp.call_value(callback, vec![], None)?;
+        });
+
+        match self.output_closed {
+            true => Ok(()),
+            false => res,
         }
+    }
 
-        value.negate().map_err(|e| {
-            PhyResult::new(InterpErr::Negation(e.to_string()), Some(expr.loc.clone()))
-        })?;
+    // Runs the program a step at a time with 'SteppedRun::step', instead
+    // of all at once like 'interpret'
+    pub fn step_run(mut self, nodes: Vec<Stmt>) -> Result<SteppedRun, PhyResInterp> {
+        let stack = self.take_run_stack()?;
+        let stack_limit = stack.limit().get() + STACK_RED_ZONE;
+        let interp = Box::into_raw(Box::new(self));
 
-        Ok(value)
+        let run = Coroutine::with_stack(stack, move |yielder, ops: Option<usize>| {
+            // SAFETY: the run owns the interpreter and only resumes this
+            // code through it, the boxed interpreter doesn't move
+            let interp = unsafe { &mut *interp };
+            interp.stepping = Some(Stepping { ops: ops.unwrap_or(0), yielder, stopped: false });
+            interp.forget_callees();
+
+            let res = interp.run_protected(stack_limit, |interp| interp.interpret_nodes(&nodes));
+            interp.stepping = None;
+
+            match interp.output_closed {
+                true => Ok(RtVal::new_null()),
+                false => res,
+            }
+        });
+
+        Ok(SteppedRun { interp, run: Some(run) })
     }
 
-    fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> InterpRes {
-        let left = expr.left.accept(self)?;
-        let op = expr.operator.as_str();
+    // Operation of a stepped run: a statement, a yield point or a round of
+    // the tasks. Only the main code pauses, the tasks run in its operations
+    #[inline(never)]
+    fn count_op(&mut self) -> Result<(), PhyResInterp> {
+        let Some(stepping) = &mut self.stepping else {
+            return Ok(());
+        };
 
-        if op == "or" {
-            match &left {
-                RtVal::BoolVal(b) => {
-                    if b.borrow().value {
-                        return Ok(left);
-                    }
-                }
-                _ => {
-                    return Err(PhyResult::new(
-                        InterpErr::NonBoolIfCond,
-                        Some(expr.loc.clone()),
-                    ))
-                }
+        match (stepping.stopped, stepping.ops, &self.running) {
+            (true, ..) => Err(PhyResult::new(InterpErr::Stopped, Some(self.last_loc.clone()))),
+            (_, 0, None) => self.pause(),
+            (_, 0, Some(_)) => Ok(()),
+            _ => {
+                stepping.ops -= 1;
+                Ok(())
             }
-        } else if op == "and" {
-            match &left {
-                RtVal::BoolVal(b) => {
-                    if !b.borrow().value {
-                        return Ok(left);
-                    }
-                }
-                _ => {
-                    return Err(PhyResult::new(
-                        InterpErr::NonBoolIfCond,
-                        Some(expr.loc.clone()),
-                    ))
+        }
+    }
+
+    // Gives the control back to the host until a step lets the operation
+    // that paused run
+    fn pause(&mut self) -> Result<(), PhyResInterp> {
+        let Some(stepping) = &mut self.stepping else {
+            unreachable!("only stepped runs pause")
+        };
+
+        // The panics while paused are the ones of the host
+        GUARDED.with(|g| g.set(g.get() - 1));
+        let ops = loop {
+            // SAFETY: the yielder belongs to the stack that is running this code
+            match unsafe { (*stepping.yielder).suspend(()) } {
+                Some(0) => continue,
+                ops => break ops,
+            }
+        };
+        GUARDED.with(|g| g.set(g.get() + 1));
+
+        match ops {
+            Some(ops) => {
+                stepping.ops = ops - 1;
+                Ok(())
+            }
+            None => {
+                stepping.stopped = true;
+                Err(PhyResult::new(InterpErr::Stopped, Some(self.last_loc.clone())))
+            }
+        }
+    }
+
+    // After an error, like in the interactive mode, the statements that
+    // completed keep their effects and the failing one has none: the
+    // variable whose value fails isn't declared. The scopes entered are
+    // left, so the next run starts in the environment this one started
+    // in. Scheduled work is dropped
+    fn guarded<T>(
+        &mut self,
+        run: impl FnOnce(&mut Self) -> Result<T, PhyResInterp>,
+    ) -> Result<T, PhyResInterp> {
+        // Code called back by the host during a run, like in a method of a
+        // host object, is a part of it
+        if self.in_run {
+            return run(self);
+        }
+
+        let mut stack = self.take_run_stack()?;
+        let stack_limit = stack.limit().get() + STACK_RED_ZONE;
+        let res = corosensei::on_stack(&mut stack, || self.run_protected(stack_limit, run));
+        self.run_stack = Some(stack);
+
+        res
+    }
+
+    fn take_run_stack(&mut self) -> Result<DefaultStack, PhyResInterp> {
+        match self.run_stack.take() {
+            Some(stack) => Ok(stack),
+            None => DefaultStack::new(RUN_STACK_SIZE)
+                .map_err(|e| PhyResult::new(InterpErr::RunStack(e.to_string()), None)),
+        }
+    }
+
+    // The part of 'guarded' on the stack of the run
+    fn run_protected<T>(
+        &mut self,
+        stack_limit: usize,
+        run: impl FnOnce(&mut Self) -> Result<T, PhyResInterp>,
+    ) -> Result<T, PhyResInterp> {
+        let (prev_env, prev_depth) = (self.env.clone(), self.env_depth);
+        let prev_imports = self.importing.len();
+        self.output_closed = false;
+        self.in_run = true;
+        self.call_depth = 0;
+        self.stack_limit = stack_limit;
+
+        // The tasks that didn't end run before the run returns
+        GUARDED.with(|g| g.set(g.get() + 1));
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            let res = run(self)?;
+            self.finish_tasks()?;
+            Ok(res)
+        }));
+        GUARDED.with(|g| g.set(g.get() - 1));
+        self.in_run = false;
+        self.stack_limit = 0;
+
+        let res = match res {
+            Ok(res) => res,
+            Err(payload) => {
+                let msg = match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+                    (Some(s), _) => s.to_string(),
+                    (_, Some(s)) => s.clone(),
+                    _ => "unknown panic".into(),
+                };
+
+                // The panic may have happened inside a block, a watch, an
+                // import or a task, which didn't get to clean up
+                self.env = prev_env;
+                self.env_depth = prev_depth;
+                self.importing.truncate(prev_imports);
+                self.in_watch = false;
+                self.running = None;
+
+                // The prelude isn't a part of the script, a panic in it is
+                // located at the call the script made
+                let loc = match std::mem::take(&mut self.in_prelude) {
+                    true => self.prelude_call.take(),
+                    false => Some(self.last_loc.clone()),
+                };
+
+                Err(PhyResult::new(InterpErr::Internal(msg), loc))
+            }
+        };
+
+        if res.is_err() {
+            self.tasks.clear();
+            self.mailbox.clear();
+            self.timers.clear();
+        }
+
+        res
+    }
+
+    // Whether the last run was stopped by 'OutputFailure::Stop'
+    pub fn output_closed(&self) -> bool {
+        self.output_closed
+    }
+
+    // Line written by the natives. The error of a closed output under
+    // 'OutputFailure::Stop' unwinds the run, which then ends without error
+    pub(crate) fn write_line(&mut self, text: &str) -> Result<(), NativeFnErr> {
+        writeln!(self.output, "{}", text).map_err(|e| {
+            match e.kind() == io::ErrorKind::BrokenPipe && self.output_failure == OutputFailure::Stop {
+                true => {
+                    self.output_closed = true;
+                    NativeFnErr::OutputClosed
                 }
+                false => NativeFnErr::WriteOutput(e.to_string()),
             }
+        })
+    }
+
+    pub(crate) fn post(&mut self, actor: Rc<Actor>, msg: RtVal) {
+        self.mailbox.push_back((actor, msg));
+    }
+
+    // The callback gets the new value each time the variable is assigned
+    pub(crate) fn watch(&mut self, name: EcoString, callback: RtVal) -> Result<(), EnvErr> {
+        Env::write(&self.env, &name)?.watch(name, callback)?;
+        self.has_watches = true;
+
+        Ok(())
+    }
+
+    fn run_watches(&mut self, name: &EcoString, value: RtVal, loc: &Loc) -> Result<(), PhyResInterp> {
+        if !self.has_watches || self.in_watch {
+            return Ok(());
         }
 
-        expr.right.accept(self)
+        let watches = Env::read(&self.env, name)
+            .and_then(|env| env.get_watches(name))
+            .unwrap_or_default();
+
+        self.in_watch = true;
+        let res = watches
+            .into_iter()
+            .try_for_each(|w| self.call_value(w, vec![value.clone()], None).map(|_| ()));
+        self.in_watch = false;
+
+        res.map_err(|e| {
+            let err = InterpErr::Watch(name.to_string(), Message::new(&e.err));
+            PhyResult::new(err, Some(loc.clone())).with_cause(e)
+        })
     }
 
-    fn visit_call_expr(&mut self, expr: &CallExpr) -> InterpRes {
-        let callee = expr.callee.accept(self)?;
+    // Callback run by the event loop after the delay, returns the id used
+    // to clear it
+    pub(crate) fn schedule(&mut self, callback: RtVal, delay: Duration, repeat: bool) -> i64 {
+        self.next_timer_id += 1;
+
+        self.timers.push(Timer {
+            id: self.next_timer_id,
+            due: Instant::now() + delay,
+            callback,
+            interval: repeat.then_some(delay),
+        });
+
+        self.next_timer_id
+    }
+
+    // False if there was no timer with this id
+    pub(crate) fn clear_timer(&mut self, id: i64) -> bool {
+        let nb = self.timers.len();
+        self.timers.retain(|t| t.id != id);
+
+        self.timers.len() != nb
+    }
 
-        let mut args: Vec<RtVal> = vec![];
-        for a in &expr.args {
-            args.push(a.accept(self)?);
+    fn lookup_callee(&mut self, id: &IdentifierExpr) -> InterpRes {
+        if let Some(value) = self.callee_cache.get(&(id as *const IdentifierExpr)) {
+            return Ok(value.clone());
         }
 
-        if let RtVal::FuncVal(f) = callee {
-            if f.arity() != args.len() {
-                return Err(PhyResult::new(
-                    InterpErr::WrongArgsNb(f.arity(), args.len()),
-                    Some(expr.loc.clone()),
-                ));
+        self.find_callee(id)
+    }
+
+    // Out of the calls, which only need the cache most of the time
+    #[inline(never)]
+    fn find_callee(&mut self, id: &IdentifierExpr) -> InterpRes {
+        if matches!(id.name.as_str(), "true" | "false" | "null") {
+            return self.visit_identifier_expr(id);
+        }
+
+        let (value, global) = Env::read(&self.env, &id.name)
+            .and_then(|env| env.find_var(&id.name))
+            .map_err(|e| {
+                PhyResult::new(InterpErr::GetVarEnv(Message::new(&e)), Some(id.loc.clone()))
+            })?;
+
+        if global && matches!(value, RtVal::FuncVal(_) | RtVal::NativeFnVal(_)) {
+            self.callee_cache.insert(id, value.clone());
+            if !self.cached_callees.contains(&id.name) {
+                self.cached_callees.push(id.name.clone());
             }
+        }
 
-            f.call(self, args).map_err(|e| {
-                PhyResult::new(InterpErr::FnCall(e.err.to_string()), Some(expr.loc.clone()))
-            })
-        } else {
-            Err(PhyResult::new(InterpErr::NonFnCall, Some(expr.loc.clone())))
+        Ok(value)
+    }
+
+    // A name declared or assigned again may not be the cached callee anymore
+    fn rebound(&mut self, name: &EcoString) {
+        if self.cached_callees.contains(name) {
+            self.forget_callees();
+        }
+    }
+
+    // The code run from now on may reuse the addresses of freed nodes
+    pub(crate) fn forget_callees(&mut self) {
+        self.callee_cache.clear();
+        self.cached_callees.clear();
+    }
+
+    // Counters since the creation of the interpreter or the last reset
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    pub fn reset_metrics(&mut self) {
+        self.metrics = Metrics::default();
+    }
+
+    fn count_allocation(&mut self, value: &RtVal) {
+        *self.metrics.allocations.entry(value.get_type_name()).or_default() += 1;
+    }
+
+    fn enter_env(&mut self) {
+        self.env_depth += 1;
+        self.metrics.max_env_depth = self.metrics.max_env_depth.max(self.env_depth);
+    }
+
+    // Statements of the blocks and branches, counted and recorded
+    fn run_stmt(&mut self, stmt: &Stmt) -> InterpRes {
+        self.metrics.statements += 1;
+        if self.stepping.is_some() {
+            self.count_op()?;
+        }
+        if self.recording.is_some() {
+            self.record_stmt(stmt);
+        }
+
+        stmt.accept(self)
+    }
+
+    // Blocks aren't recorded, only the statements they contain. The other
+    // compound statements end after their body, they are located at their
+    // first line
+    #[inline(never)]
+    fn record_stmt(&mut self, stmt: &Stmt) {
+        let Some(events) = &mut self.recording else {
+            return;
+        };
+
+        let loc = match stmt {
+            Stmt::Block(_) => return,
+            Stmt::If(s) => s.condition.get_loc(),
+            Stmt::While(s) => s.condition.get_loc(),
+            Stmt::For(s) => s.placeholder.name_loc.clone(),
+            s => s.get_loc(),
+        };
+
+        events.push(TraceEvent::Stmt(loc));
+    }
+
+    fn record_set(&mut self, name: &EcoString, value: &RtVal) {
+        if let Some(events) = &mut self.recording {
+            events.push(TraceEvent::Set(name.clone(), value.display(&self.display).to_string()));
         }
     }
+
+    fn interpret_nodes(&mut self, nodes: &Vec<Stmt>) -> InterpRes {
+        let mut res: RtVal = RtVal::new_null();
+
+        for node in nodes {
+            match self.run_stmt(node) {
+                Ok(r) => res = r,
+                // Only function calls catch the returns
+                Err(PhyResult { err: InterpErr::Return(_), loc, .. }) => {
+                    return Err(PhyResult::new(InterpErr::ReturnOutsideFn, loc))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(res)
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use ecow::EcoString;
+impl SteppedRun {
+    // Runs at most 'max_ops' operations, see 'count_op'. The errors end the
+    // run like with 'interpret'. Panics if the run already ended
+    pub fn step(&mut self, max_ops: usize) -> Result<Step, PhyResInterp> {
+        let Some(run) = &mut self.run else {
+            panic!("stepped run already ended")
+        };
 
-    use crate::{interpreter::InterpErr, utils::lex_parse_interp};
+        match run.resume(Some(max_ops)) {
+            CoroutineResult::Yield(()) => Ok(Step::Paused),
+            CoroutineResult::Return(res) => {
+                self.end();
+                res.map(Step::Done)
+            }
+        }
+    }
 
-    #[test]
-    fn interp_literals() {
-        let code = "1";
-        assert_eq!(lex_parse_interp(code).unwrap(), 1.into());
+    pub fn is_done(&self) -> bool {
+        self.run.is_none()
+    }
 
-        let code = "-45.";
-        assert_eq!(lex_parse_interp(code).unwrap(), (-45f64).into());
+    // Stops the run if it didn't end, its effects so far are kept
+    pub fn into_interpreter(mut self) -> Interpreter {
+        self.stop();
 
-        let code = "\"hello world!\"";
-        assert_eq!(
-            lex_parse_interp(code).unwrap(),
-            EcoString::from("hello world!").into()
-        );
+        // SAFETY: the run ended, nothing points to the interpreter anymore
+        let interp = unsafe { Box::from_raw(self.interp) };
+        std::mem::forget(self);
+
+        *interp
     }
 
-    #[test]
-    fn interp_binop() {
-        let code = "1 +2";
-        assert_eq!(lex_parse_interp(code).unwrap(), 3.into());
+    // The paused code fails with 'InterpErr::Stopped', which unwinds it
+    // like any other error
+    fn stop(&mut self) {
+        if let Some(run) = &mut self.run {
+            if run.started() {
+                let _ = run.resume(None);
+            }
 
-        let code = "1. + -2 *24";
-        assert_eq!(lex_parse_interp(code).unwrap(), (-47f64).into());
+            self.end();
+        }
+    }
 
-        let code = "5 + (6 * (2+3)) - (((6)))";
-        assert_eq!(lex_parse_interp(code).unwrap(), 29.into());
+    // The stack of the run goes back to the interpreter, for its next runs
+    fn end(&mut self) {
+        if let Some(mut run) = self.run.take() {
+            // Only drops the program if the run didn't start
+            run.force_unwind();
+
+            let stack = run.into_stack();
+            // SAFETY: the run ended, nothing else points to the interpreter
+            unsafe { (*self.interp).run_stack = Some(stack) };
+        }
     }
+}
 
-    #[test]
-    fn interp_str_op() {
-        let code = "\"foo\" * 4";
-        assert_eq!(
-            lex_parse_interp(code).unwrap(),
-            EcoString::from("foofoofoofoo").into()
+impl Drop for SteppedRun {
+    fn drop(&mut self) {
+        self.stop();
+        // SAFETY: the run ended, nothing points to the interpreter anymore
+        drop(unsafe { Box::from_raw(self.interp) });
+    }
+}
+
+impl VisitStmt<RtVal, InterpErr> for Interpreter {
+    fn visit_expr_stmt(&mut self, stmt: &ExprStmt) -> InterpRes {
+        self.last_loc = stmt.loc.clone();
+        stmt.expr.accept(self)
+    }
+
+    fn visit_var_decl_stmt(&mut self, stmt: &VarDeclStmt) -> InterpRes {
+        self.last_loc = stmt.loc.clone();
+        let value = match &stmt.value {
+            Some(v) => v.accept(self)?,
+            None => RtVal::new_null(),
+        };
+
+        let size = self.size_of(&value);
+        self.record_set(&stmt.name, &value);
+        self.count_allocation(&value);
+
+        Env::write(&self.env, &stmt.name)
+            .and_then(|mut env| env.declare_var(stmt.name.clone(), value))
+            .map_err(|e| {
+                PhyResult::new(InterpErr::VarDeclEnv(Message::new(&e)), Some(stmt.loc.clone()))
+            })?;
+
+        self.rebound(&stmt.name);
+        self.charge_memory(size, &stmt.loc)?;
+
+        Ok(RtVal::new_null())
+    }
+
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> InterpRes {
+        let new_env = Env::new(Some(self.env.clone()));
+        self.execute_block_stmt(&stmt.stmts, Rc::new(RefCell::new(new_env)))?;
+
+        Ok(RtVal::new_null())
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) -> InterpRes {
+        let cond = stmt.condition.accept(self)?;
+
+        match cond {
+            RtVal::BoolVal(b) => match b.borrow().value {
+                true => {
+                    if let Some(t) = &stmt.then_branch {
+                        self.run_stmt(t)
+                    } else {
+                        Ok(RtVal::new_null())
+                    }
+                }
+                false => {
+                    if let Some(e) = &stmt.else_branch {
+                        self.run_stmt(e)
+                    } else {
+                        Ok(RtVal::new_null())
+                    }
+                }
+            },
+            _ => Err(PhyResult::new(
+                InterpErr::NonBoolIfCond,
+                Some(stmt.loc.clone()),
+            )),
+        }
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) -> InterpRes {
+        loop {
+            let cond = stmt.condition.accept(self)?;
+
+            match cond {
+                RtVal::BoolVal(b) => match b.borrow().value {
+                    true => {
+                        stmt.body.accept(self)?;
+                        self.yield_now()?;
+                    }
+                    false => break,
+                },
+                _ => {
+                    return Err(PhyResult::new(
+                        InterpErr::NonBoolWhileCond,
+                        Some(stmt.loc.clone()),
+                    ))
+                }
+            }
+        }
+
+        Ok(RtVal::new_null())
+    }
+
+    // The range is evaluated before the placeholders are declared
+    fn visit_for_stmt(&mut self, stmt: &ForStmt) -> InterpRes {
+        let values = self.for_values(&stmt.range)?;
+
+        let new_env = Env::new(Some(self.env.clone()));
+        let prev_env = std::mem::replace(&mut self.env, Rc::new(RefCell::new(new_env)));
+        self.enter_env();
+
+        // The previous environment is restored even if the loop fails
+        let res = self.execute_for_loop(stmt, values);
+
+        self.release_scope_memory();
+        let _ = std::mem::replace(&mut self.env, prev_env);
+        self.env_depth -= 1;
+
+        res
+    }
+
+    fn visit_fn_decl_stmt(&mut self, stmt: &FnDeclStmt) -> Result<RtVal, PhyResult<InterpErr>> {
+        let func = RtVal::new_fn(stmt, self.env.clone());
+
+        Env::write(&self.env, &stmt.name)
+            .and_then(|mut env| match env.declare_var(stmt.name.clone(), func.clone()) {
+                Err(EnvErr::AlreadyDeclaredVar(_))
+                    if self.redefine_fns
+                        && matches!(env.get_var(stmt.name.clone()), Ok(RtVal::FuncVal(_))) =>
+                {
+                    env.assign(stmt.name.clone(), func)
+                }
+                res => res,
+            })
+            .map_err(|e| {
+                PhyResult::new(InterpErr::VarDeclEnv(Message::new(&e)), Some(stmt.loc.clone()))
+            })?;
+
+        self.rebound(&stmt.name);
+
+        Ok(RtVal::new_null())
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> Result<RtVal, PhyResult<InterpErr>> {
+        let mut value = RtVal::new_null();
+
+        if let Some(v) = &stmt.value {
+            value = v.accept(self)?;
+        }
+
+        Err(PhyResult::new(InterpErr::Return(value), Some(stmt.loc.clone())))
+    }
+
+    fn visit_import_stmt(&mut self, stmt: &ImportStmt) -> InterpRes {
+        self.last_loc = stmt.loc.clone();
+        let module = self
+            .import_module(stmt)
+            .map_err(|e| PhyResult::new(e, Some(stmt.loc.clone())))?;
+
+        // A module replaces the one of the same name, like the std modules
+        // extending the native ones
+        let (name, value) = (stmt.name().clone(), RtVal::ModuleVal(module));
+        Env::write(&self.env, &name)
+            .and_then(|mut env| match env.declare_var(name.clone(), value.clone()) {
+                Err(EnvErr::AlreadyDeclaredVar(_))
+                    if matches!(env.get_var(name.clone()), Ok(RtVal::ModuleVal(_))) =>
+                {
+                    env.assign(name.clone(), value)
+                }
+                res => res,
+            })
+            .map_err(|e| {
+                PhyResult::new(InterpErr::VarDeclEnv(Message::new(&e)), Some(stmt.loc.clone()))
+            })?;
+
+        self.rebound(stmt.name());
+
+        Ok(RtVal::new_null())
+    }
+}
+
+impl Interpreter {
+    // File and code of the module, as it would be imported. Used by the
+    // tools reading the modules without running them
+    pub fn module_source(&mut self, stmt: &ImportStmt) -> Result<(PathBuf, Rc<str>), InterpErr> {
+        let dotted = stmt.path.join(".");
+        let (file, std_code) = self.module_file(stmt, &dotted)?;
+
+        Ok((file.clone(), self.read_module(&file, std_code, &dotted)?))
+    }
+
+    // 'a.b' is the file 'a/b.arc' in the first module path that has it,
+    // except for the standard library modules which are embedded
+    fn module_file(
+        &mut self,
+        stmt: &ImportStmt,
+        dotted: &str,
+    ) -> Result<(PathBuf, Option<&'static str>), InterpErr> {
+        match stmt.path.as_slice() {
+            [root, name] if root == "std" => {
+                let (_, code) = STD_MODULES
+                    .iter()
+                    .find(|(n, _)| n == name)
+                    .ok_or_else(|| InterpErr::ModuleNotFound(dotted.into()))?;
+
+                Ok((PathBuf::from(format!("<std>/{}.arc", name)), Some(*code)))
+            }
+            _ => Ok((self.find_module_file(stmt, dotted)?, None)),
+        }
+    }
+
+    fn read_module(
+        &self,
+        file: &PathBuf,
+        std_code: Option<&'static str>,
+        dotted: &str,
+    ) -> Result<Rc<str>, InterpErr> {
+        match std_code {
+            Some(code) => Ok(code.into()),
+            None => fs::read_to_string(file)
+                .map(|c| c.into())
+                .map_err(|e| InterpErr::Module(dotted.into(), e.to_string())),
+        }
+    }
+
+    fn import_module(&mut self, stmt: &ImportStmt) -> Result<Rc<Module>, InterpErr> {
+        let dotted = stmt.path.join(".");
+        let (file, std_code) = self.module_file(stmt, &dotted)?;
+
+        if let Some(module) = self.modules.get(&file) {
+            return Ok(module.clone());
+        }
+
+        // The module isn't cached until it is fully loaded, importing it
+        // again before that would never end
+        if let Some(idx) = self.importing.iter().position(|f| f.file == file) {
+            return Err(InterpErr::CircularImport(self.import_chain(idx, &dotted, &stmt.loc)));
+        }
+
+        let code = self.read_module(&file, std_code, &dotted)?;
+
+        self.importing.push(ImportFrame {
+            name: dotted.clone(),
+            file: file.clone(),
+            code: code.clone(),
+            loc: stmt.loc.clone(),
+        });
+        let res = self.load_module(&dotted, stmt.name(), &code);
+        self.importing.pop();
+
+        let module = res?;
+        self.modules.insert(file, module.clone());
+
+        Ok(module)
+    }
+
+    fn find_module_file(&mut self, stmt: &ImportStmt, dotted: &str) -> Result<PathBuf, InterpErr> {
+        if self.sandboxed {
+            return Err(InterpErr::SandboxedImport);
+        }
+
+        // 'pkg' is the 'lib.arc' of the package and 'pkg.a' its 'a.arc'
+        if let Some(root) = self.package_root(&stmt.path[0])? {
+            let file = match stmt.path.len() {
+                1 => root.join("lib.arc"),
+                _ => stmt.path[1..]
+                    .iter()
+                    .fold(root, |f, p| f.join(p.as_str()))
+                    .with_extension("arc"),
+            };
+
+            return match file.is_file() {
+                true => Ok(file),
+                false => Err(InterpErr::ModuleNotFound(dotted.into())),
+            };
+        }
+
+        let file = stmt
+            .path
+            .iter()
+            .map(|p| p.as_str())
+            .collect::<PathBuf>()
+            .with_extension("arc");
+
+        self.module_paths
+            .iter()
+            .map(|dir| dir.join(&file))
+            .find(|f| f.is_file())
+            .ok_or_else(|| InterpErr::ModuleNotFound(dotted.into()))
+    }
+
+    fn package_root(&mut self, name: &EcoString) -> Result<Option<PathBuf>, InterpErr> {
+        if let Some(root) = self.packages.get(name) {
+            return Ok(Some(root.clone()));
+        }
+
+        let Some(res) = self.package_resolver.as_mut().and_then(|r| r.resolve(name)) else {
+            return Ok(None);
+        };
+        let root = res.map_err(|e| InterpErr::Package(name.to_string(), e))?;
+        self.packages.insert(name.clone(), root.clone());
+
+        Ok(Some(root))
+    }
+
+    // 'a -> b -> a' followed by the place where each module imports the next
+    fn import_chain(&self, start: usize, name: &str, loc: &Loc) -> String {
+        let frames = &self.importing[start..];
+
+        let mut names: Vec<&str> = frames.iter().map(|f| f.name.as_str()).collect();
+        names.push(name);
+
+        let sites: Vec<String> = frames
+            .iter()
+            .enumerate()
+            .map(|(i, frame)| {
+                let (next, at) = match frames.get(i + 1) {
+                    Some(next) => (next.name.as_str(), &next.loc),
+                    None => (name, loc),
+                };
+                let line = frame.code.get(..at.start).unwrap_or("").matches('\n').count() + 1;
+
+                format!("{} imported at {}:{}", next, frame.file.display(), line)
+            })
+            .collect();
+
+        format!("{} ({})", names.join(" -> "), sites.join(", "))
+    }
+
+    // Runs the module in its own environment, child of the globals. Only
+    // its 'pub' declarations become members, with the value they have at
+    // the end of the module's execution
+    fn load_module(&mut self, dotted: &str, name: &EcoString, code: &str) -> Result<Rc<Module>, InterpErr> {
+        let to_err = |msgs: Vec<String>| InterpErr::Module(dotted.into(), msgs.join(", "));
+
+        let mut lexer = Lexer::new();
+        let tokens = lexer
+            .tokenize(code)
+            .map_err(|e| to_err(e.iter().map(|e| e.err.to_string()).collect()))?;
+
+        let mut parser = Parser::default();
+        let nodes = parser
+            .parse(tokens)
+            .map_err(|e| to_err(e.iter().map(|e| e.err.to_string()).collect()))?;
+
+        let module_env = Rc::new(RefCell::new(Env::new(Some(self.globals.clone()))));
+        let prev_env = std::mem::replace(&mut self.env, module_env.clone());
+        let res = self.interpret_nodes(&nodes);
+        self.env = prev_env;
+        // Its top level code is dropped once loaded
+        self.forget_callees();
+
+        // The cycle is reported once, with the whole chain
+        res.map_err(|e| match e.err {
+            InterpErr::CircularImport(chain) => InterpErr::CircularImport(chain),
+            e => to_err(vec![e.to_string()]),
+        })?;
+
+        let env = module_env.borrow();
+        let mut members = BTreeMap::new();
+        let mut private = BTreeSet::new();
+
+        for node in &nodes {
+            let (decl, is_pub) = match node {
+                Stmt::VarDecl(s) => (&s.name, s.is_pub),
+                Stmt::FnDecl(s) => (&s.name, s.is_pub),
+                Stmt::Import(s) => (s.name(), false),
+                _ => continue,
+            };
+
+            match env.get_var(decl.clone()) {
+                Ok(value) if is_pub => {
+                    members.insert(decl.clone(), value);
+                }
+                _ => {
+                    private.insert(decl.clone());
+                }
+            }
+        }
+
+        Ok(Rc::new(Module { name: name.clone(), members, private }))
+    }
+}
+
+impl Interpreter {
+    fn check_args<T: PhyReport>(
+        &self,
+        callee: &dyn Callable<T>,
+        args: &[RtVal],
+        expr: Option<&CallExpr>,
+    ) -> Result<(), PhyResInterp> {
+        let (min, nb) = (callee.arity(), args.len());
+
+        let err = match callee.max_arity() {
+            Some(max) if min == max && nb != min => Some(InterpErr::WrongArgsNb(min, nb)),
+            Some(max) if nb < min || nb > max => Some(InterpErr::WrongArgsRange(min, max, nb)),
+            None if nb < min => Some(InterpErr::TooFewArgs(min, nb)),
+            _ => None,
+        };
+
+        if let Some(e) = err {
+            return Err(PhyResult::new(e, expr.map(|e| e.loc.clone())));
+        }
+
+        for (i, (arg, expected)) in args.iter().zip(callee.params_type()).enumerate() {
+            let found = arg.get_type_name();
+
+            if *expected != "any" && !expected.split('|').any(|t| t == found) {
+                return Err(PhyResult::new(
+                    InterpErr::WrongArgType(i + 1, expected.replace('|', " or "), found.into()),
+                    expr.map(|e| e.args[i].get_loc()),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Calls a script function from the host, like an 'on_update(dt)' hook.
+    // Arguments are checked the same way as in a script call
+    pub fn call_function<'a>(
+        &mut self,
+        callee: impl Into<FnRef<'a>>,
+        args: Vec<RtVal>,
+    ) -> Result<RtVal, PhyResInterp> {
+        let callee = match callee.into() {
+            FnRef::Name(name) => Env::read(&self.globals, name)
+                .and_then(|env| env.get_var(EcoString::from(name)))
+                .map_err(|e| PhyResult::new(InterpErr::GetVarEnv(Message::new(&e)), None))?,
+            FnRef::Value(v) => v,
+        };
+
+        self.guarded(|interp| interp.call_value(callee, args, None))
+    }
+
+    // Location of the errors is taken from the call expression if the call
+    // comes from a script
+    fn call_value(&mut self, callee: RtVal, args: Vec<RtVal>, expr: Option<&CallExpr>) -> InterpRes {
+        let loc = expr.map(|e| e.loc.clone());
+
+        if let Some(l) = &loc {
+            self.last_loc = l.clone();
+        }
+
+        self.yield_now()?;
+        self.metrics.calls += 1;
+
+        match callee {
+            RtVal::FuncVal(f) => {
+                self.check_args(f.as_ref(), &args, expr)?;
+                args.iter().for_each(|a| self.count_allocation(a));
+
+                if self.call_depth >= self.max_call_depth || stack_address() < self.stack_limit {
+                    return Err(PhyResult::new(InterpErr::CallDepth(self.call_depth), loc));
+                }
+
+                // Outermost call into the prelude, remembered for the errors
+                // that can't be located in it
+                let enters_prelude = !self.in_prelude && Rc::ptr_eq(&f.closure, &self.builtins);
+                if enters_prelude {
+                    self.in_prelude = true;
+                    self.prelude_call = loc.clone();
+                }
+
+                self.call_depth += 1;
+                let res = match self.contracts && (!f.requires.is_empty() || !f.ensures.is_empty()) {
+                    true => self.call_contracted(&f, args, &loc),
+                    false => f.call(self, args).map_err(|e| call_err(e.err, &loc)),
+                };
+                self.call_depth -= 1;
+
+                if enters_prelude {
+                    self.in_prelude = false;
+                }
+
+                res
+            }
+            RtVal::NativeFnVal(f) => self.call_native(&f, args, expr, loc),
+            _ => Err(PhyResult::new(InterpErr::NonFnCall, loc)),
+        }
+    }
+
+    // Arguments are kept for the postconditions
+    #[inline(never)]
+    fn call_contracted(&mut self, f: &Function, args: Vec<RtVal>, loc: &Option<Loc>) -> InterpRes {
+        self.check_contract(f, "precondition", &f.requires, &args, None, loc)?;
+        let res = f.call(self, args.clone()).map_err(|e| call_err(e.err, loc))?;
+        self.check_contract(f, "postcondition", &f.ensures, &args, Some(&res), loc)?;
+
+        Ok(res)
+    }
+
+    // Out of 'call_value', which is on the path of every script call
+    #[inline(never)]
+    fn call_native(
+        &mut self,
+        f: &PhyNativeFn,
+        args: Vec<RtVal>,
+        expr: Option<&CallExpr>,
+        loc: Option<Loc>,
+    ) -> InterpRes {
+        self.check_args(f.func.as_ref(), &args, expr)?;
+
+        // A failing call made by the native is framed with its name
+        f.func.call(self, args).map_err(|e| match e.err {
+            NativeFnErr::Callback(inner) => {
+                PhyResult::new(InterpErr::InNative(f.name.to_string(), inner), loc)
+            }
+            e => PhyResult::new(InterpErr::FnCall(Message::new(&e)), loc),
+        })
+    }
+
+    // Yield point, at the calls and at the end of each loop iteration. A
+    // task gives the control back to the main code, which lets each task
+    // run until its next yield point
+    fn yield_now(&mut self) -> Result<(), PhyResInterp> {
+        if self.stepping.is_some() {
+            self.count_op()?;
+        }
+
+        match self.running {
+            Some(_) => {
+                self.suspend(Suspend::Yield);
+                Ok(())
+            }
+            None if self.tasks.is_empty() => Ok(()),
+            None => self.run_round().map(|_| ()),
+        }
+    }
+
+    // Blocks the code running until 'ready' gives a value. A task is
+    // suspended and checks again at its next turn, the main code runs the
+    // tasks meanwhile. None if they are all blocked too, the value can't
+    // come
+    pub(crate) fn wait_for<T>(
+        &mut self,
+        mut ready: impl FnMut() -> Option<T>,
+    ) -> Result<Option<T>, PhyResInterp> {
+        loop {
+            if let Some(value) = ready() {
+                return Ok(Some(value));
+            }
+
+            match self.running.is_some() {
+                true => self.suspend(Suspend::Blocked),
+                false if !self.run_round()? => return Ok(None),
+                false => {}
+            }
+        }
+    }
+
+    // Gives the control back to the main code, which resumes the task later
+    fn suspend(&mut self, reason: Suspend) {
+        let Some(running) = &self.running else {
+            unreachable!("only tasks are suspended")
+        };
+
+        // SAFETY: the yielder belongs to the stack that is running this code
+        unsafe { (*running.yielder).suspend(reason) };
+    }
+
+    // Each task of the queue runs until its next yield point, the ones
+    // spawned meanwhile wait for the next round. False if no task did
+    // anything, because they were all blocked
+    fn run_round(&mut self) -> Result<bool, PhyResInterp> {
+        if self.stepping.is_some() {
+            self.count_op()?;
+        }
+
+        let mut progress = false;
+
+        for _ in 0..self.tasks.len() {
+            let Some(task) = self.tasks.pop_front() else {
+                break;
+            };
+
+            progress |= self.resume(task)?;
+        }
+
+        Ok(progress)
+    }
+
+    // Rounds until the tasks ended or are all blocked
+    fn run_tasks(&mut self) -> Result<(), PhyResInterp> {
+        while self.run_round()? {}
+
+        Ok(())
+    }
+
+    // At the end of a run. The stacks of the tasks can't outlive it, the
+    // interpreter may move, so the blocked ones are a deadlock
+    fn finish_tasks(&mut self) -> Result<(), PhyResInterp> {
+        self.run_tasks()?;
+
+        match self.tasks.front() {
+            Some(s) => Err(PhyResult::new(InterpErr::Deadlock, Some(s.task.call.loc.clone()))),
+            None => Ok(()),
+        }
+    }
+
+    // Runs the task in its own context until it's suspended, and queues
+    // it again. False if it was blocked. Errors of the task stop the run
+    fn resume(&mut self, mut scheduled: Scheduled) -> Result<bool, PhyResInterp> {
+        let mut stack = match scheduled.stack.take() {
+            Some(stack) => stack,
+            None => self.start_task(&scheduled.task)?,
+        };
+
+        self.swap_context(&mut scheduled.context);
+        let res = stack.resume(self);
+        self.swap_context(&mut scheduled.context);
+
+        match res {
+            CoroutineResult::Yield(reason) => {
+                scheduled.stack = Some(stack);
+                self.tasks.push_back(scheduled);
+
+                Ok(reason == Suspend::Yield)
+            }
+            CoroutineResult::Return(res) => {
+                let value = res.as_ref().ok().cloned().unwrap_or(RtVal::Null);
+                scheduled.task.state.replace(TaskState::Done(value));
+
+                res.map(|_| true)
+            }
+        }
+    }
+
+    fn start_task(&mut self, task: &Rc<Task>) -> Result<TaskStack, PhyResInterp> {
+        let TaskState::Waiting(callee, args) = task.state.replace(TaskState::Running) else {
+            unreachable!("only the tasks waiting to start have no stack")
+        };
+
+        let stack = DefaultStack::new(TASK_STACK_SIZE).map_err(|e| {
+            PhyResult::new(InterpErr::TaskStack(e.to_string()), Some(task.call.loc.clone()))
+        })?;
+        let stack_limit = stack.limit().get() + STACK_RED_ZONE;
+        let task = task.clone();
+
+        Ok(Coroutine::with_stack(stack, move |yielder, interp: *mut Interpreter| {
+            // SAFETY: the task only runs while the interpreter resumes it,
+            // and its stack is dropped at the end of the run
+            let interp = unsafe { &mut *interp };
+            interp.running = Some(Running { task: task.clone(), yielder });
+            interp.stack_limit = stack_limit;
+
+            interp.call_value(callee, args, Some(&task.call))
+        }))
+    }
+
+    fn swap_context(&mut self, context: &mut Context) {
+        std::mem::swap(&mut self.env, &mut context.env);
+        std::mem::swap(&mut self.env_depth, &mut context.env_depth);
+        std::mem::swap(&mut self.call_depth, &mut context.call_depth);
+        std::mem::swap(&mut self.stack_limit, &mut context.stack_limit);
+        std::mem::swap(&mut self.in_prelude, &mut context.in_prelude);
+        std::mem::swap(&mut self.prelude_call, &mut context.prelude_call);
+        std::mem::swap(&mut self.in_watch, &mut context.in_watch);
+        std::mem::swap(&mut self.running, &mut context.running);
+    }
+
+    // Clauses are evaluated with the parameters, and 'result' for the
+    // postconditions. A violation is located at the call, or at the clause
+    // if the function is called by the host
+    #[inline(never)]
+    fn check_contract(
+        &mut self,
+        f: &Function,
+        kind: &'static str,
+        clauses: &[Expr],
+        args: &[RtVal],
+        result: Option<&RtVal>,
+        loc: &Option<Loc>,
+    ) -> Result<(), PhyResInterp> {
+        let mut env = Env::new(Some(f.closure.clone()));
+        for (p, v) in f.params.iter().zip(args) {
+            let _ = env.declare_var(p.clone(), v.clone());
+        }
+
+        if let Some(r) = result {
+            let _ = env.declare_var("result".into(), r.clone());
+        }
+
+        let prev_env = std::mem::replace(&mut self.env, Rc::new(RefCell::new(env)));
+        let res = clauses.iter().try_for_each(|clause| {
+            match clause.accept(self)? {
+                RtVal::BoolVal(b) if b.borrow().value => Ok(()),
+                RtVal::BoolVal(_) => Err(PhyResult::new(
+                    InterpErr::ContractViolation {
+                        kind,
+                        func: f.name.clone(),
+                        clause: clause.to_string(),
+                        clause_loc: clause.get_loc(),
+                    },
+                    loc.clone().or(Some(clause.get_loc())),
+                )),
+                _ => Err(PhyResult::new(InterpErr::NonBoolContract, Some(clause.get_loc()))),
+            }
+        });
+        self.env = prev_env;
+
+        res
+    }
+
+    // The environment comes shared, a scope built in the frame would make
+    // the frames of the recursive calls larger
+    pub fn execute_block_stmt(&mut self, stmts: &Vec<Stmt>, env: EnvWrapper) -> InterpRes {
+        // Variables declared before, like function parameters
+        if self.memory_limit.is_some() {
+            let size = env.borrow().approx_size();
+            self.charge_memory(size, &Loc::default())?;
+        }
+
+        let prev_env = std::mem::replace(&mut self.env, env);
+        self.enter_env();
+
+        let mut res = Ok(RtVal::new_null());
+        for s in stmts {
+            res = self.run_stmt(s);
+
+            if res.is_err() {
+                break;
+            }
+        }
+
+        self.release_scope_memory();
+        let _ = std::mem::replace(&mut self.env, prev_env);
+        self.env_depth -= 1;
+
+        res
+    }
+
+    // The items of a list are taken before the first iteration, pushing
+    // to the list in the body doesn't add items. An iterator runs along
+    // the loop
+    fn for_values(&mut self, range: &ForRange) -> Result<ForValues, PhyResInterp> {
+        match range {
+            ForRange::Ints(start, Some(end)) => Ok(ForValues::Range(*start..*end)),
+            ForRange::Ints(end, None) => Ok(ForValues::Range(0..*end)),
+            ForRange::Bounds(start, end) => {
+                Ok(ForValues::Range(self.range_bound(start)?..self.range_bound(end)?))
+            }
+            ForRange::Items(items) => match items.accept(self)? {
+                RtVal::ListVal(l) => Ok(ForValues::List(l.borrow().value.clone())),
+                RtVal::IterVal(i) => Ok(ForValues::Iter(i)),
+                v => Err(PhyResult::new(
+                    InterpErr::NotIterable(v.get_type_name().into()),
+                    Some(items.get_loc()),
+                )),
+            },
+        }
+    }
+
+    fn execute_for_loop(&mut self, stmt: &ForStmt, values: ForValues) -> InterpRes {
+        for placeholder in stmt.placeholders() {
+            self.visit_var_decl_stmt(placeholder)?;
+        }
+
+        match values {
+            ForValues::Range(range) => {
+                range.into_iter().try_for_each(|i| self.for_iteration(stmt, RtVal::from(i)))?
+            }
+            ForValues::List(items) => {
+                items.into_iter().try_for_each(|item| self.for_iteration(stmt, item))?
+            }
+            ForValues::Iter(iter) => iter.run(self, |interp, item| interp.for_iteration(stmt, item))?,
+        }
+
+        Ok(RtVal::new_null())
+    }
+
+    fn for_iteration(&mut self, stmt: &ForStmt, item: RtVal) -> Result<(), PhyResInterp> {
+        let values = match stmt.rest.is_empty() {
+            true => vec![item],
+            false => self.split_item(stmt, item)?,
+        };
+
+        for (placeholder, value) in stmt.placeholders().zip(values) {
+            Env::write(&self.env, &placeholder.name)
+                .and_then(|mut env| env.assign(placeholder.name.clone(), value.clone()))
+                .map_err(|e| {
+                    PhyResult::new(InterpErr::ForLoop(Message::new(&e)), Some(stmt.loc.clone()))
+                })?;
+            self.record_set(&placeholder.name, &value);
+            self.count_allocation(&value);
+        }
+
+        stmt.body.accept(self)?;
+        self.yield_now()
+    }
+
+    // Values of the names of 'for i, v in pairs', the item must be a list
+    // with one value per name
+    fn split_item(&self, stmt: &ForStmt, item: RtVal) -> Result<Vec<RtVal>, PhyResInterp> {
+        let names = stmt.rest.len() + 1;
+        let found = match &item {
+            RtVal::ListVal(l) if l.borrow().value.len() == names => {
+                return Ok(l.borrow().value.clone())
+            }
+            RtVal::ListVal(l) => format!("a list of {} values", l.borrow().value.len()),
+            v => v.get_type_name().to_string(),
+        };
+
+        let last = stmt.rest.last().unwrap_or(&stmt.placeholder);
+        let loc = Loc::new(stmt.placeholder.name_loc.start, last.name_loc.end);
+
+        Err(PhyResult::new(InterpErr::ForDestructuring(names, found), Some(loc)))
+    }
+
+    fn range_bound(&mut self, bound: &Expr) -> Result<i64, PhyResInterp> {
+        match bound.accept(self)? {
+            RtVal::IntVal(i) => Ok(i.borrow().value),
+            v => Err(PhyResult::new(
+                InterpErr::NonIntRange(v.get_type_name().into()),
+                Some(bound.get_loc()),
+            )),
+        }
+    }
+
+    // The items are taken before the first value, pushing to the source
+    // list in the value doesn't add items
+    fn comprehension_items(
+        &mut self,
+        expr: &ComprehensionExpr,
+    ) -> Result<Box<dyn Iterator<Item = RtVal>>, PhyResInterp> {
+        let Some(end) = &expr.end else {
+            return match expr.source.accept(self)? {
+                RtVal::ListVal(l) => Ok(Box::new(l.borrow().value.clone().into_iter())),
+                RtVal::IterVal(i) => Ok(Box::new(i.collect(self)?.into_iter())),
+                v => Err(PhyResult::new(
+                    InterpErr::NotIterable(v.get_type_name().into()),
+                    Some(expr.source.get_loc()),
+                )),
+            };
+        };
+
+        let start = self.range_bound(&expr.source)?;
+        let end = self.range_bound(end)?;
+
+        Ok(Box::new((start..end).map(RtVal::from)))
+    }
+
+    fn collect_comprehension(
+        &mut self,
+        expr: &ComprehensionExpr,
+        items: Box<dyn Iterator<Item = RtVal>>,
+    ) -> Result<Vec<RtVal>, PhyResInterp> {
+        let placeholder_err = |e: EnvErr| {
+            PhyResult::new(InterpErr::ForLoop(Message::new(&e)), Some(expr.loc.clone()))
+        };
+
+        Env::write(&self.env, &expr.name)
+            .and_then(|mut env| env.declare_var(expr.name.clone(), RtVal::new_null()))
+            .map_err(placeholder_err)?;
+        self.rebound(&expr.name);
+
+        let mut values = vec![];
+        for item in items {
+            Env::write(&self.env, &expr.name)
+                .and_then(|mut env| env.assign(expr.name.clone(), item))
+                .map_err(placeholder_err)?;
+
+            if let Some(cond) = &expr.cond {
+                match cond.accept(self)? {
+                    RtVal::BoolVal(b) if !b.borrow().value => continue,
+                    RtVal::BoolVal(_) => {}
+                    _ => {
+                        return Err(PhyResult::new(
+                            InterpErr::NonBoolComprehensionCond,
+                            Some(cond.get_loc()),
+                        ))
+                    }
+                }
+            }
+
+            values.push(expr.value.accept(self)?);
+        }
+
+        Ok(values)
+    }
+
+    fn charge_memory(&mut self, size: usize, loc: &Loc) -> Result<(), PhyResInterp> {
+        self.memory_used += size;
+
+        match self.memory_limit {
+            Some(limit) if self.memory_used > limit => {
+                Err(PhyResult::new(InterpErr::MemoryLimit(limit), Some(loc.clone())))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn release_memory(&mut self, size: usize) {
+        self.memory_used = self.memory_used.saturating_sub(size);
+    }
+
+    // The values are only measured with a limit: walking them costs their
+    // size, on every declaration, assignment and scope exit
+    fn size_of(&self, value: &RtVal) -> usize {
+        match self.memory_limit {
+            Some(_) => value.approx_size(),
+            None => 0,
+        }
+    }
+
+    fn release_scope_memory(&mut self) {
+        if self.memory_limit.is_some() {
+            let size = self.env.borrow().approx_size();
+            self.release_memory(size);
+        }
+    }
+}
+
+thread_local! {
+    // Runs in 'guarded' on this thread
+    static GUARDED: Cell<usize> = const { Cell::new(0) };
+}
+
+// The default hook prints the panics on stderr, the ones caught by
+// 'guarded' included although they are reported as internal errors. For
+// the hosts which want them silenced: installed once, on top of their own
+// hook which still gets the other panics
+pub fn quiet_guarded_panics() {
+    static INSTALL: Once = Once::new();
+
+    INSTALL.call_once(|| {
+        let prev = panic::take_hook();
+
+        panic::set_hook(Box::new(move |info| {
+            if GUARDED.with(|g| g.get()) == 0 {
+                prev(info);
+            }
+        }));
+    });
+}
+
+// Address in the frame of the caller, to know how far the stack went
+#[inline(always)]
+fn stack_address() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+// Error of a failed script call. The violations keep their own location
+// through the calls, the other errors are the cause of the failed call
+#[inline(never)]
+fn call_err(err: RtValErr, loc: &Option<Loc>) -> PhyResInterp {
+    match err {
+        RtValErr::Propagated(e) => *e,
+        RtValErr::FnExecution(e) => {
+            PhyResult::new(InterpErr::FnCall(Message::new(&e.err)), loc.clone()).with_cause(*e)
+        }
+        e => PhyResult::new(InterpErr::FnCall(Message::new(&e)), loc.clone()),
+    }
+}
+
+// Operations other than the ones on numbers, out of the visitor so that
+// the recursive calls don't carry their locals
+#[inline(never)]
+fn binary_result(lhs: RtVal, rhs: RtVal, expr: &BinaryExpr, equality: bool) -> InterpRes {
+    if rhs == RtVal::new_null() && !equality {
+        return Err(PhyResult::new(
+            InterpErr::UninitializedValue,
+            Some(expr.right.get_loc()),
+        ));
+    }
+
+    if equality && (lhs == RtVal::new_null() || rhs == RtVal::new_null()) {
+        return Ok(((lhs == rhs) == (expr.operator == "==")).into());
+    }
+
+    match lhs.operate(&rhs, &expr.operator) {
+        Ok(res) => Ok(res),
+        Err(e) => Err(PhyResult::new(
+            InterpErr::OperationEvaluation(Message::new(&e)),
+            Some(expr.loc.clone()),
+        )),
+    }
+}
+
+// Operations between ints and reals, the most frequent ones, without the
+// generic dispatch of 'RtVal::operate'. None for the other values and
+// operators, and for the int operations that overflow or divide by zero,
+// left to it to report
+#[inline]
+fn operate_numbers(lhs: &RtVal, rhs: &RtVal, operator: &str) -> Option<RtVal> {
+    let (l, r) = match (lhs, rhs) {
+        (RtVal::IntVal(l), RtVal::IntVal(r)) => {
+            let (l, r) = (l.borrow().value, r.borrow().value);
+
+            return Some(match operator {
+                "+" => l.checked_add(r)?.into(),
+                "-" => l.checked_sub(r)?.into(),
+                "*" => l.checked_mul(r)?.into(),
+                "/" => l.checked_div(r)?.into(),
+                "%" => l.checked_rem(r)?.into(),
+                "<" => (l < r).into(),
+                ">" => (l > r).into(),
+                "<=" => (l <= r).into(),
+                ">=" => (l >= r).into(),
+                "==" => (l == r).into(),
+                "!=" => (l != r).into(),
+                _ => return None,
+            });
+        }
+        (RtVal::RealVal(l), RtVal::RealVal(r)) => (l.borrow().value, r.borrow().value),
+        (RtVal::IntVal(l), RtVal::RealVal(r)) => (l.borrow().value as f64, r.borrow().value),
+        (RtVal::RealVal(l), RtVal::IntVal(r)) => (l.borrow().value, r.borrow().value as f64),
+        _ => return None,
+    };
+
+    Some(match operator {
+        "+" => (l + r).into(),
+        "-" => (l - r).into(),
+        "*" => (l * r).into(),
+        "/" => (l / r).into(),
+        "%" => (l % r).into(),
+        "<" => (l < r).into(),
+        ">" => (l > r).into(),
+        "<=" => (l <= r).into(),
+        ">=" => (l >= r).into(),
+        "==" => (l == r).into(),
+        "!=" => (l != r).into(),
+        _ => return None,
+    })
+}
+
+impl VisitExpr<RtVal, InterpErr> for Interpreter {
+    fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> InterpRes {
+        // Comparing to null is how a value is checked before being used
+        let equality = expr.operator == "==" || expr.operator == "!=";
+
+        let lhs = expr.left.accept(self)?;
+        if lhs == RtVal::new_null() && !equality {
+            return Err(PhyResult::new(
+                InterpErr::UninitializedValue,
+                Some(expr.left.get_loc()),
+            ));
+        }
+
+        let rhs = expr.right.accept(self)?;
+        if let Some(res) = operate_numbers(&lhs, &rhs, &expr.operator) {
+            return Ok(res);
+        }
+
+        binary_result(lhs, rhs, expr, equality)
+    }
+
+    fn visit_assign_expr(&mut self, expr: &AssignExpr) -> InterpRes {
+        let value = expr.value.accept(self)?;
+        let new_size = self.size_of(&value);
+        let old_size = match self.memory_limit {
+            Some(_) => Env::read(&self.env, &expr.name)
+                .and_then(|env| env.get_var(expr.name.clone()))
+                .map_or(0, |v| v.approx_size()),
+            None => 0,
+        };
+
+        Env::write(&self.env, &expr.name)
+            .and_then(|mut env| env.assign(expr.name.clone(), value.clone()))
+            .map_err(|e| {
+                PhyResult::new(InterpErr::AssignEnv(Message::new(&e)), Some(expr.loc.clone()))
+            })?;
+
+        self.rebound(&expr.name);
+        self.record_set(&expr.name, &value);
+        self.count_allocation(&value);
+
+        match new_size >= old_size {
+            true => self.charge_memory(new_size - old_size, &expr.loc)?,
+            false => self.release_memory(old_size - new_size),
+        }
+
+        self.run_watches(&expr.name, value, &expr.loc)?;
+
+        Ok(RtVal::new_null())
+    }
+
+    fn visit_imaginary_literal_expr(&mut self, expr: &ImaginaryLiteralExpr) -> InterpRes {
+        Ok(Complex { re: 0., im: expr.value }.into())
+    }
+
+    fn visit_unit_literal_expr(&mut self, expr: &UnitLiteralExpr) -> InterpRes {
+        let (unit, factor) = Unit::from_symbols(&expr.unit)
+            .map_err(|e| {
+                PhyResult::new(InterpErr::Unit(Message::new(&e)), Some(expr.loc.clone()))
+            })?;
+
+        Ok(Quantity::new_val(expr.value * factor, unit))
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> InterpRes {
+        expr.expr.accept(self)
+    }
+
+    fn visit_int_literal_expr(&mut self, expr: &IntLiteralExpr) -> InterpRes {
+        Ok(expr.value.into())
+    }
+
+    fn visit_real_literal_expr(&mut self, expr: &RealLiteralExpr) -> InterpRes {
+        Ok(expr.value.into())
+    }
+
+    fn visit_str_literal_expr(&mut self, expr: &StrLiteralExpr) -> InterpRes {
+        Ok(expr.value.clone().into())
+    }
+
+    fn visit_char_literal_expr(&mut self, expr: &CharLiteralExpr) -> InterpRes {
+        Ok(expr.value.into())
+    }
+
+    fn visit_identifier_expr(&mut self, expr: &IdentifierExpr) -> InterpRes {
+        match expr.name.as_str() {
+            "true" => Ok(true.into()),
+            "false" => Ok(false.into()),
+            "null" => Ok(RtVal::new_null()),
+            _ => Env::read(&self.env, &expr.name)
+                .and_then(|env| env.get_var(expr.name.clone()))
+                .map_err(|e| {
+                    PhyResult::new(InterpErr::GetVarEnv(Message::new(&e)), Some(expr.loc.clone()))
+                }),
+        }
+    }
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> InterpRes {
+        let value = expr.right.accept(self)?;
+
+        match (&value, expr.operator.as_str()) {
+            (
+                RtVal::IntVal(..)
+                | RtVal::RealVal(..)
+                | RtVal::QuantityVal(..)
+                | RtVal::ComplexVal(..),
+                "!",
+            ) => {
+                return Err(PhyResult::new(
+                    InterpErr::BangOpOnNonBool,
+                    Some(expr.loc.clone()),
+                ))
+            }
+            (RtVal::BoolVal(..) | RtVal::StrVal(..) | RtVal::CharVal(..) | RtVal::Null, "-") => {
+                return Err(PhyResult::new(
+                    InterpErr::NegateNonNumeric,
+                    Some(expr.loc.clone()),
+                ))
+            }
+            _ => {}
+        }
+
+        value.negate().map_err(|e| {
+            PhyResult::new(InterpErr::Negation(Message::new(&e)), Some(expr.loc.clone()))
+        })
+    }
+
+    fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> InterpRes {
+        let left = expr.left.accept(self)?;
+        let op = expr.operator.as_str();
+
+        if op == "or" {
+            match &left {
+                RtVal::BoolVal(b) => {
+                    if b.borrow().value {
+                        return Ok(left);
+                    }
+                }
+                _ => {
+                    return Err(PhyResult::new(
+                        InterpErr::NonBoolIfCond,
+                        Some(expr.loc.clone()),
+                    ))
+                }
+            }
+        } else if op == "and" {
+            match &left {
+                RtVal::BoolVal(b) => {
+                    if !b.borrow().value {
+                        return Ok(left);
+                    }
+                }
+                _ => {
+                    return Err(PhyResult::new(
+                        InterpErr::NonBoolIfCond,
+                        Some(expr.loc.clone()),
+                    ))
+                }
+            }
+        }
+
+        expr.right.accept(self)
+    }
+
+    fn visit_call_expr(&mut self, expr: &CallExpr) -> InterpRes {
+        // Method calls on host objects are routed to the host instead of
+        // looking up a callable member
+        let callee = match expr.callee.as_ref() {
+            Expr::Get(get) => match get.object.accept(self)? {
+                RtVal::HostVal(host) => return self.call_host_method(host, &get.name, expr),
+                object => self.get_member(object, get)?,
+            },
+            Expr::Identifier(id) => self.lookup_callee(id)?,
+            callee => callee.accept(self)?,
+        };
+
+        let args = self.eval_args(&expr.args)?;
+
+        self.call_value(callee, args, Some(expr))
+    }
+
+    fn visit_get_expr(&mut self, expr: &GetExpr) -> InterpRes {
+        let object = expr.object.accept(self)?;
+        self.get_member(object, expr)
+    }
+
+    fn visit_set_expr(&mut self, expr: &SetExpr) -> InterpRes {
+        let object = expr.object.accept(self)?;
+        let value = expr.value.accept(self)?;
+
+        match object {
+            RtVal::HostVal(host) => host
+                .set(&expr.name, value)
+                .map_err(|e| host_err(host.as_ref(), e.err, &expr.loc))?,
+            RtVal::VectorVal(v) => {
+                let idx = self.component_idx(&v.borrow(), expr.name.as_str(), &expr.loc)?;
+                let comp = value.get_real().ok_or_else(|| {
+                    PhyResult::new(
+                        InterpErr::WrongComponentType(value.get_type_name().into()),
+                        Some(expr.value.get_loc()),
+                    )
+                })?;
+
+                v.borrow_mut().value[idx] = comp;
+            }
+            v => {
+                return Err(PhyResult::new(
+                    InterpErr::ImmutableMembers(v.get_type_name().into()),
+                    Some(expr.loc.clone()),
+                ))
+            }
+        }
+
+        Ok(RtVal::new_null())
+    }
+
+    fn visit_index_expr(&mut self, expr: &IndexExpr) -> InterpRes {
+        let object = expr.object.accept(self)?;
+        let start = self.eval_index(&expr.start)?;
+        let end = self.eval_index(&expr.end)?;
+
+        let res = match expr.is_slice {
+            true => object.get_slice(start, end),
+            false => object.get_item(start.unwrap()),
+        };
+
+        res.map_err(|e| {
+            PhyResult::new(InterpErr::Indexing(Message::new(&e)), Some(expr.loc.clone()))
+        })
+    }
+
+    fn visit_list_expr(&mut self, expr: &ListExpr) -> InterpRes {
+        let items = expr.items.iter().map(|i| i.accept(self)).collect::<Result<Vec<_>, _>>()?;
+
+        Ok(RtVal::from(items))
+    }
+
+    fn visit_comprehension_expr(&mut self, expr: &ComprehensionExpr) -> InterpRes {
+        let items = self.comprehension_items(expr)?;
+
+        let new_env = Env::new(Some(self.env.clone()));
+        let prev_env = std::mem::replace(&mut self.env, Rc::new(RefCell::new(new_env)));
+        self.enter_env();
+
+        // The previous environment is restored even if a value fails
+        let res = self.collect_comprehension(expr, items);
+
+        self.release_scope_memory();
+        let _ = std::mem::replace(&mut self.env, prev_env);
+        self.env_depth -= 1;
+
+        res.map(RtVal::from)
+    }
+
+    fn visit_spawn_expr(&mut self, expr: &SpawnExpr) -> InterpRes {
+        let Expr::Call(call) = expr.call.as_ref() else {
+            unreachable!("'spawn' is always parsed with a call")
+        };
+
+        let callee = call.callee.accept(self)?;
+        let args = self.eval_args(&call.args)?;
+
+        let task = Rc::new(Task {
+            call: call.clone(),
+            state: RefCell::new(TaskState::Waiting(callee, args)),
+        });
+
+        // Runs from the globals, its call enters the scope of the function
+        self.tasks.push_back(Scheduled {
+            task: task.clone(),
+            stack: None,
+            context: Context {
+                env: self.globals.clone(),
+                env_depth: 0,
+                call_depth: 0,
+                stack_limit: 0,
+                in_prelude: false,
+                prelude_call: None,
+                in_watch: false,
+                running: None,
+            },
+        });
+
+        Ok(RtVal::TaskVal(task))
+    }
+
+    // Blocks until the task ended
+    fn visit_await_expr(&mut self, expr: &AwaitExpr) -> InterpRes {
+        let task = match expr.task.accept(self)? {
+            RtVal::TaskVal(t) => t,
+            v => {
+                return Err(PhyResult::new(
+                    InterpErr::AwaitNonTask(v.get_type_name().into()),
+                    Some(expr.loc.clone()),
+                ))
+            }
+        };
+
+        if self.running.as_ref().is_some_and(|r| Rc::ptr_eq(&r.task, &task)) {
+            return Err(PhyResult::new(InterpErr::AwaitRunningTask, Some(expr.loc.clone())));
+        }
+
+        let value = self.wait_for(|| match &*task.state.borrow() {
+            TaskState::Done(v) => Some(v.clone()),
+            _ => None,
+        })?;
+
+        value.ok_or_else(|| PhyResult::new(InterpErr::Deadlock, Some(expr.loc.clone())))
+    }
+}
+
+impl Interpreter {
+    #[inline(never)]
+    fn call_host_method(&mut self, host: Rc<dyn HostObject>, name: &str, expr: &CallExpr) -> InterpRes {
+        let args = self.eval_args(&expr.args)?;
+        self.last_loc = expr.loc.clone();
+
+        host.call_method(self, name, args)
+            .map_err(|e| host_err(host.as_ref(), e.err, &expr.loc))
+    }
+
+    fn component_idx(&self, vector: &Vector, name: &str, loc: &Loc) -> Result<usize, PhyResInterp> {
+        vector.component_idx(name).ok_or_else(|| {
+            PhyResult::new(
+                InterpErr::UnknownComponent(name.into(), format!("vec{}", vector.value.len())),
+                Some(loc.clone()),
+            )
+        })
+    }
+
+    fn eval_index(&mut self, index: &Option<Box<Expr>>) -> Result<Option<i64>, PhyResInterp> {
+        let Some(index) = index else { return Ok(None) };
+
+        match index.accept(self)? {
+            RtVal::IntVal(i) => Ok(Some(i.borrow().value)),
+            v => Err(PhyResult::new(
+                InterpErr::NonIntIndex(v.get_type_name().into()),
+                Some(index.get_loc()),
+            )),
+        }
+    }
+
+    fn eval_args(&mut self, args: &[Expr]) -> Result<Vec<RtVal>, PhyResInterp> {
+        args.iter().map(|a| a.accept(self)).collect()
+    }
+
+    fn get_member(&mut self, object: RtVal, expr: &GetExpr) -> InterpRes {
+        match object {
+            RtVal::HostVal(host) => host
+                .get(&expr.name)
+                .map_err(|e| host_err(host.as_ref(), e.err, &expr.loc)),
+            RtVal::ComplexVal(c) => match expr.name.as_str() {
+                "re" => Ok(c.borrow().re.into()),
+                "im" => Ok(c.borrow().im.into()),
+                name => Err(PhyResult::new(
+                    InterpErr::UnknownComponent(name.into(), "complex".into()),
+                    Some(expr.loc.clone()),
+                )),
+            },
+            RtVal::VectorVal(v) => {
+                let v = v.borrow();
+                let idx = self.component_idx(&v, expr.name.as_str(), &expr.loc)?;
+
+                Ok(v.value[idx].into())
+            }
+            RtVal::TaskVal(t) => match expr.name.as_str() {
+                "done" => Ok(matches!(*t.state.borrow(), TaskState::Done(_)).into()),
+                name => Err(PhyResult::new(
+                    InterpErr::UnknownComponent(name.into(), "task".into()),
+                    Some(expr.loc.clone()),
+                )),
+            },
+            RtVal::ModuleVal(m) => m.members.get(&expr.name).cloned().ok_or_else(|| {
+                let err = match m.private.contains(&expr.name) {
+                    true => InterpErr::PrivateMember(expr.name.to_string(), m.name.to_string()),
+                    false => InterpErr::UnknownMember(expr.name.to_string(), m.name.to_string()),
+                };
+
+                PhyResult::new(err, Some(expr.loc.clone()))
+            }),
+            v => Err(PhyResult::new(
+                InterpErr::NoMembers(v.get_type_name().into()),
+                Some(expr.loc.clone()),
+            )),
+        }
+    }
+}
+
+fn host_err(host: &dyn HostObject, err: HostErr, loc: &Loc) -> PhyResInterp {
+    PhyResult::new(
+        InterpErr::Host(host.type_name().to_string(), Message::new(&err)),
+        Some(loc.clone()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, path::PathBuf, rc::Rc};
+
+    use ecow::EcoString;
+    use frontend::parser::utils::lex_and_parse;
+    use tools::{
+        messages::{localize, Catalog, English},
+        results::{Loc, PhyReport},
+    };
+
+    use crate::{
+        interpreter::{
+            InterpErr, Interpreter, Metrics, OutputFailure, PackageResolver, Step,
+            DEFAULT_MAX_CALL_DEPTH,
+        },
+        trace::TraceEvent,
+        utils::{
+            lex_parse_interp, lex_parse_interp_input, lex_parse_interp_output, lex_parse_interp_with,
+            SharedOutput,
+        },
+        values::{DisplayConfig, RtVal},
+    };
+
+    #[test]
+    fn interp_literals() {
+        let code = "1";
+        assert_eq!(lex_parse_interp(code).unwrap(), 1.into());
+
+        let code = "-45.";
+        assert_eq!(lex_parse_interp(code).unwrap(), (-45f64).into());
+
+        let code = "\"hello world!\"";
+        assert_eq!(
+            lex_parse_interp(code).unwrap(),
+            EcoString::from("hello world!").into()
+        );
+    }
+
+    #[test]
+    fn interp_binop() {
+        let code = "1 +2";
+        assert_eq!(lex_parse_interp(code).unwrap(), 3.into());
+
+        let code = "1. + -2 *24";
+        assert_eq!(lex_parse_interp(code).unwrap(), (-47f64).into());
+
+        let code = "5 + (6 * (2+3)) - (((6)))";
+        assert_eq!(lex_parse_interp(code).unwrap(), 29.into());
+    }
+
+    #[test]
+    fn interp_str_op() {
+        let code = "\"foo\" * 4";
+        assert_eq!(
+            lex_parse_interp(code).unwrap(),
+            EcoString::from("foofoofoofoo").into()
+        );
+
+        let code = "4 * \"foo\"";
+        assert_eq!(
+            lex_parse_interp(code).unwrap(),
+            EcoString::from("foofoofoofoo").into()
+        );
+
+        let code = "\"foo\" + \" \" + \"bar\"";
+        assert_eq!(
+            lex_parse_interp(code).unwrap(),
+            EcoString::from("foo bar").into()
+        );
+
+        // Errors
+        let code = "\"foo\" * 3.5";
+        matches!(
+            lex_parse_interp(code).err().unwrap().err,
+            InterpErr::OperationEvaluation { .. }
+        );
+
+        let code = "\"foo\" + 56";
+        matches!(
+            lex_parse_interp(code).err().unwrap().err,
+            InterpErr::OperationEvaluation { .. }
+        );
+    }
+
+    #[test]
+    fn interp_negation() {
+        let code = "-3";
+        assert_eq!(lex_parse_interp(code).unwrap(), (-3).into());
+
+        let code = "-3.";
+        assert_eq!(lex_parse_interp(code).unwrap(), (-3f64).into());
+
+        let code = "!true";
+        assert_eq!(lex_parse_interp(code).unwrap(), false.into());
+
+        let code = "!false";
+        assert_eq!(lex_parse_interp(code).unwrap(), true.into());
+
+        // Errors
+        let code = "- \"foo\"";
+        assert_eq!(
+            lex_parse_interp(code).err().unwrap().err,
+            InterpErr::NegateNonNumeric
+        );
+
+        let code = "!8";
+        assert_eq!(
+            lex_parse_interp(code).err().unwrap().err,
+            InterpErr::BangOpOnNonBool
+        );
+    }
+
+    #[test]
+    fn variable() {
+        let code = "var a = -8
+a";
+        assert_eq!(lex_parse_interp(code).unwrap(), (-8).into());
+
+        let code = "var a = -8
+a = 4 + a*2
+a";
+        assert_eq!(lex_parse_interp(code).unwrap(), (-12).into());
+
+        // Errors
+        let code = "a = 5";
+        assert!(matches!(
+            lex_parse_interp(code).err().unwrap().err,
+            InterpErr::AssignEnv { .. }
+        ));
+
+        let code = "var b
+3 + b";
+        assert_eq!(
+            lex_parse_interp(code).err().unwrap().err,
+            InterpErr::UninitializedValue
+        );
+    }
+
+    #[test]
+    fn null_comparison() {
+        let code = "var a
+var b = 1
+var c = a == null and null == a
+var d = b != null and !(b == null)
+var e = c and d
+e";
+        assert_eq!(lex_parse_interp(code).unwrap(), true.into());
+
+        let code = "var a
+a != null";
+        assert_eq!(lex_parse_interp(code).unwrap(), false.into());
+    }
+
+    #[test]
+    fn block() {
+        let code = "var a = -8
+{
+    var b = 1
+    b = a + 9
+    a = b
+}
+a";
+        assert_eq!(lex_parse_interp(code).unwrap(), 1.into());
+    }
+
+    #[test]
+    fn if_stmt() {
+        let code = "
+var a = true
+var b = 0
+if a { b = 1 } else {}
+b
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 1.into());
+
+        let code = "
+var a = false
+var b = 0
+if a { b = 8 } else { b = 1 }
+b
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 1.into());
+
+        let code = "
+var a = false
+var b = 42
+if a {} else {}
+b
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 42.into());
+
+        // Errors
+        let code = "
+var a = 5
+if a {} else {}
+";
+        assert!(lex_parse_interp(code).err().unwrap().err == InterpErr::NonBoolIfCond);
+    }
+
+    #[test]
+    fn logical() {
+        let code = "
+var a = true
+var b = 0
+if a and b == 0 { b = 1 }
+b
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 1.into());
+
+        let code = "
+var a = true
+var b = 0
+if a and 2 + 2 == 5 { } else { b = 1 }
+b
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 1.into());
+
+        let code = "
+var a = true
+var b = 0
+if a or false { b = 1 }
+b
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 1.into());
+
+        let code = "
+var a = false
+var b = 0
+if a or false {} else { b = 1 }
+b
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 1.into());
+
+        let code = "
+var a = true
+var b = 42
+if a and b == 41 or b == 42 and false {} else { b = 45 }
+b
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 45.into());
+    }
+
+    #[test]
+    fn while_stmt() {
+        let code = "
+var a = 0
+while a < 5 {
+    a = a + 1
+}
+a
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 5.into());
+    }
+
+    #[test]
+    fn for_stmt() {
+        let code = "
+var a = 0
+for i in 5 { a = a + i }
+a
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 10.into());
+
+        let code = "
+var a = 0
+for i in 5..10 { a = a + i }
+a
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 35.into());
+    }
+
+    #[test]
+    fn for_ranges_and_items() {
+        let code = "
+var n = 3
+for i in n - 1..n * 2 {
+    n = 0
+    print(i)
+}
+var l = [\"a\", \"b\"]
+for x in l {
+    list.push(l, x)
+    print(x)
+}
+for i, v in enumerate(l) { print(i, v) }
+var y = [true]
+for y in y { print(y) }
+
+fn first_even(it) {
+    for v in it {
+        if v % 2 == 0 { return v }
+    }
+}
+var seen = list.new()
+fn see(v) {
+    list.push(seen, v)
+    return v
+}
+print(first_even(iter.map(iter.range(1, 1000000), see)), seen)
+";
+        let (res, output) = lex_parse_interp_output(code);
+        assert!(res.is_ok());
+        assert_eq!(
+            output,
+            "2\n3\n4\n5\na\nb\n0 a\n1 b\n2 a\n3 b\ntrue\n2 [1, 2]\n"
+        );
+
+        let errs = [
+            ("var n = 3\nfor i in n {}", InterpErr::NotIterable("int".into())),
+            ("var s = \"3\"\nfor i in 0..s {}", InterpErr::NonIntRange("str".into())),
+            (
+                "for i, v in [[1, 2], [3]] {}",
+                InterpErr::ForDestructuring(2, "a list of 1 values".into()),
+            ),
+            ("for a, b, c in [1] {}", InterpErr::ForDestructuring(3, "int".into())),
+        ];
+
+        for (code, err) in errs {
+            assert_eq!(lex_parse_interp(code).err().unwrap().err, err);
+        }
+    }
+
+    #[test]
+    fn functions() {
+        let code = "
+var res
+fn add(a, b) {
+    res = a + b
+}
+add(5, 6)
+res
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 11.into());
+    }
+
+    #[test]
+    fn first_class_fn() {
+        let code = "
+fn add(a, b) { return a+b }
+var c = add
+c(1, 2)
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 3.into());
+    }
+
+    #[test]
+    fn recurs_and_break_fn() {
+        let code = "
+fn fib(n) {
+    if n <= 1 { return n }
+
+    return fib(n-2) + fib(n-1)
+}
+
+fib(20)
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 6765.into());
+    }
+
+    #[test]
+    fn closure_env() {
+        let code = "
+fn makeCounter() {
+  var i = 0
+  fn count() {
+    i = i + 1
+    return i
+  }
+
+  return count
+}
+
+var counter = makeCounter()
+var a = 0
+a = counter()
+a = counter()
+a
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 2.into());
+    }
+
+    #[test]
+    fn print_native() {
+        let code = "
+var x = 3
+print(\"x =\", x, 1.5)
+print()
+var p = print
+p(\"foo\")
+";
+        let (res, output) = lex_parse_interp_output(code);
+        assert!(res.is_ok());
+        assert_eq!(output, "x = 3 1.5\n\nfoo\n");
+
+        let code = "clock(1)";
+        assert_eq!(
+            lex_parse_interp(code).err().unwrap().err,
+            InterpErr::WrongArgsNb(0, 1)
+        );
+    }
+
+    #[test]
+    fn type_names() {
+        let code = "
+fn f() {}
+f
+";
+        assert_eq!(lex_parse_interp(code).unwrap().get_type_name(), "fn");
+        assert_eq!(lex_parse_interp("clock").unwrap().get_type_name(), "native fn");
+        assert_eq!(lex_parse_interp("4 / 2.").unwrap().get_type_name(), "real");
+        assert_eq!(lex_parse_interp("\"a\" * 2").unwrap().get_type_name(), "str");
+    }
+
+    #[test]
+    fn introspection() {
+        let code = "
+var b = 1
+var a = 2
+globals()
+";
+        let RtVal::ListVal(names) = lex_parse_interp(code).unwrap() else {
+            panic!("globals() must return a list");
+        };
+        let names: Vec<String> = names.borrow().value.iter().map(|n| n.to_string()).collect();
+
+        assert_eq!(names[..3], ["\"E\"", "\"INF\"", "\"NAN\""]);
+        assert!(names.contains(&"\"a\"".into()) && names.contains(&"\"b\"".into()));
+        assert!(names.contains(&"\"globals\"".into()));
+        assert!(names.windows(2).all(|w| w[0] < w[1]));
+
+        let code = "
+var a = 2
+fn f(x) {
+    var y = 1
+    return locals()
+}
+f(a)
+";
+        assert_eq!(
+            lex_parse_interp(code).unwrap().to_string(),
+            "[\"x\", \"y\"]"
+        );
+
+        let codes = ["dir(vec3(1, 2, 3))", "dir(2 + 1i)", "dir(math)", "dir(1)"];
+        let dirs = ["[\"x\", \"y\", \"z\"]", "[\"im\", \"re\"]", "", "[]"];
+
+        for (code, dir) in codes.iter().zip(dirs) {
+            let res = lex_parse_interp(code).unwrap().to_string();
+
+            match dir.is_empty() {
+                true => assert!(res.contains("\"sqrt\"") && res.contains("\"pow\"")),
+                false => assert_eq!(res, dir),
+            }
+        }
+    }
+
+    #[test]
+    fn eval_native() {
+        let code = "
+var a = 2
+eval(\"var b = a * 3\")
+eval(\"b + 1\")
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 7.into());
+
+        // Isolated declarations don't leak
+        let code = "
+var a = 2
+eval(\"var b = 1\", true)
+eval(\"b\")
+";
+        assert!(matches!(
+            lex_parse_interp(code).err().unwrap().err,
+            InterpErr::FnCall { .. }
+        ));
+
+        // Syntax errors are runtime errors at the call
+        let code = "eval(\"var = 4\")";
+        let err = lex_parse_interp(code).err().unwrap().err;
+        assert_eq!(
+            err.to_string(),
+            "in evaluated code: missing variable name after 'var' keyword in declaration"
+        );
+
+        let code = "eval(4)";
+        assert_eq!(
+            lex_parse_interp(code).err().unwrap().err,
+            InterpErr::WrongArgType(1, "str".into(), "int".into())
+        );
+    }
+
+    #[test]
+    fn fn_reflection() {
+        let code = "
+fn add(a, b) { return a + b }
+var f = add
+fn_name(f)
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), EcoString::from("add").into());
+
+        let code = "
+fn add(a, b) { return a + b }
+fn_params(add)
+";
+        assert_eq!(lex_parse_interp(code).unwrap().to_string(), "[\"a\", \"b\"]");
+
+        assert_eq!(lex_parse_interp("fn_name(clock)").unwrap(), EcoString::from("clock").into());
+        assert_eq!(lex_parse_interp("fn_arity(eval)").unwrap(), 1.into());
+        assert_eq!(lex_parse_interp("fn_params(clock)").unwrap().to_string(), "[]");
+
+        let code = "fn_arity(3)";
+        let err = lex_parse_interp(code).err().unwrap().err;
+        assert_eq!(err.to_string(), "argument 1 must be of type fn or native fn, found int");
+    }
+
+    #[test]
+    fn native_args_check() {
+        let code = "eval(\"1\", true, 3)";
+        assert_eq!(
+            lex_parse_interp(code).err().unwrap().err,
+            InterpErr::WrongArgsRange(1, 2, 3)
+        );
+
+        let code = "eval(\"1\", 3)";
+        assert_eq!(
+            lex_parse_interp(code).err().unwrap().err,
+            InterpErr::WrongArgType(2, "bool".into(), "int".into())
+        );
+
+        let code = "fn_name()";
+        assert_eq!(
+            lex_parse_interp(code).err().unwrap().err,
+            InterpErr::WrongArgsNb(1, 0)
+        );
+    }
+
+    #[test]
+    fn native_modules() {
+        assert_eq!(lex_parse_interp("math.sqrt(16)").unwrap(), 4f64.into());
+        assert_eq!(lex_parse_interp("math.pow(2, 10)").unwrap(), 1024.into());
+        assert_eq!(lex_parse_interp("math.abs(-2.5)").unwrap(), 2.5f64.into());
+        assert_eq!(lex_parse_interp("math.pow(-1, 10000000001)").unwrap(), (-1).into());
+
+        // Ints that don't fit are reported like with the operators
+        assert_eq!(
+            lex_parse_interp("math.abs(-9223372036854775807 - 1)").err().unwrap().err,
+            InterpErr::FnCall("int overflow in operation 'abs'".into())
+        );
+        assert_eq!(
+            lex_parse_interp("math.pow(3, 41)").err().unwrap().err,
+            InterpErr::FnCall("int overflow in operation 'pow'".into())
+        );
+        assert_eq!(
+            lex_parse_interp("str.upper(\"foo\")").unwrap(),
+            EcoString::from("FOO").into()
+        );
+
+        let code = "
+var m = math
+var sqrt = m.sqrt
+fn_name(sqrt)
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), EcoString::from("math.sqrt").into());
+
+        // Errors
+        assert_eq!(
+            lex_parse_interp("math.foo").err().unwrap().err,
+            InterpErr::UnknownMember("foo".into(), "math".into())
+        );
+        assert_eq!(
+            lex_parse_interp("var a = 1\na.b").err().unwrap().err,
+            InterpErr::NoMembers("int".into())
+        );
+        assert_eq!(
+            lex_parse_interp("math.sqrt = 1").err().unwrap().err,
+            InterpErr::ImmutableMembers("module".into())
+        );
+    }
+
+    #[test]
+    fn call_function() {
+        let code = "
+var speed = 0
+fn on_update(dt) {
+    speed = speed + dt * 2
+    return speed
+}
+";
+        let nodes = lex_and_parse(code).unwrap();
+        let mut interp = Interpreter::new();
+        interp.interpret(&nodes).unwrap();
+
+        assert_eq!(interp.call_function("on_update", vec![1.into()]).unwrap(), 2.into());
+        assert_eq!(interp.call_function("on_update", vec![3.into()]).unwrap(), 8.into());
+
+        // By value
+        let f = interp.globals.borrow().get_var("on_update".into()).unwrap();
+        assert_eq!(interp.call_function(f, vec![1.into()]).unwrap(), 10.into());
+
+        // Errors
+        assert!(matches!(
+            interp.call_function("unknown", vec![]).err().unwrap().err,
+            InterpErr::GetVarEnv(_)
+        ));
+        assert_eq!(
+            interp.call_function("speed", vec![]).err().unwrap().err,
+            InterpErr::NonFnCall
+        );
+        assert_eq!(
+            interp.call_function("on_update", vec![]).err().unwrap().err,
+            InterpErr::WrongArgsNb(1, 0)
+        );
+    }
+
+    #[test]
+    fn prelude() {
+        assert_eq!(lex_parse_interp("abs(-3)").unwrap(), 3.into());
+        assert_eq!(lex_parse_interp("clamp(12, 0, 10)").unwrap(), 10.into());
+        assert_eq!(lex_parse_interp("clamp(-2.5, 0., 1.)").unwrap(), 0f64.into());
+        assert_eq!(lex_parse_interp("lerp(0., 10., 0.25)").unwrap(), 2.5f64.into());
+
+        // The scripts shadow the builtins, which keep using their own
+        let code = "
+var max = 3
+fn abs(x) { return 0 }
+var print = max + abs(-1)
+clamp(12, 0, 10) + print
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 13.into());
+        assert!(lex_parse_interp("PI = 3").is_err());
+
+        // Custom prelude
+        let mut interp = Interpreter::with_prelude("fn double(x) { return x * 2 }").unwrap();
+        let nodes = lex_and_parse("double(4)").unwrap();
+        assert_eq!(interp.interpret(&nodes).unwrap(), 8.into());
+        assert!(interp.call_function("lerp", vec![]).is_err());
+
+        // Errors
+        assert!(matches!(
+            Interpreter::with_prelude("fn f(").err().unwrap().err,
+            InterpErr::Prelude(_)
+        ));
+        assert_eq!(
+            Interpreter::with_prelude("var a = b").err().unwrap().err,
+            InterpErr::Prelude("undeclared variable 'b'".into())
+        );
+    }
+
+    #[test]
+    fn units() {
+        let code = "
+var g = 9.81 m/s^2
+var m = 2 kg
+m * g
+";
+        assert_eq!(lex_parse_interp(code).unwrap().to_string(), "19.62 kg*m/s^2");
+        assert_eq!(lex_parse_interp("1 km + 500 m").unwrap().to_string(), "1500 m");
+        assert_eq!(lex_parse_interp("10 m / 2").unwrap().to_string(), "5 m");
+        assert_eq!(lex_parse_interp("2 / 4 s").unwrap().to_string(), "0.5 s^-1");
+        assert_eq!(lex_parse_interp("-(3 N)").unwrap().to_string(), "-3 kg*m/s^2");
+        assert_eq!(lex_parse_interp("1 min > 50 s").unwrap(), true.into());
+
+        // Units cancelling each other give a real
+        assert_eq!(lex_parse_interp("6 m / 2 m").unwrap(), 3f64.into());
+        assert_eq!(lex_parse_interp("3 m").unwrap().get_type_name(), "quantity");
+
+        // Errors
+        let codes = ["1 m + 1 s", "1 m + 1", "2 > 1 s", "1 m/parsec"];
+        let errs = [
+            "incompatible units for operator '+': m and s",
+            "incompatible units for operator '+': m and unitless",
+            "incompatible units for operator '>': unitless and s",
+            "unknown unit 'parsec'",
+        ];
+
+        for (code, err) in codes.iter().zip(errs) {
+            assert_eq!(lex_parse_interp(code).err().unwrap().err.to_string(), err);
+        }
+    }
+
+    #[test]
+    fn vectors() {
+        let code = "
+var v = vec2(1, 2) + vec2(0.5, 1)
+v.y = v.x * 2
+v
+";
+        assert_eq!(lex_parse_interp(code).unwrap().to_string(), "vec2(1.5, 3)");
+        assert_eq!(lex_parse_interp("2 * vec3(1, 2, 3) - 1").unwrap().to_string(), "vec3(1, 3, 5)");
+        assert_eq!(lex_parse_interp("-(vec2(1, -2))").unwrap().to_string(), "vec2(-1, 2)");
+        assert_eq!(lex_parse_interp("vec3(1, 2, 3).z").unwrap(), 3f64.into());
+        assert_eq!(lex_parse_interp("dot(vec2(1, 2), vec2(3, 4))").unwrap(), 11f64.into());
+        assert_eq!(
+            lex_parse_interp("cross(vec3(1, 0, 0), vec3(0, 1, 0))").unwrap().to_string(),
+            "vec3(0, 0, 1)"
+        );
+        assert_eq!(lex_parse_interp("norm(vec2(3, 4))").unwrap(), 5f64.into());
+
+        // Matrices
+        let code = "
+var rot = mat(vec2(0, -1), vec2(1, 0))
+rot * vec2(1, 0)
+";
+        assert_eq!(lex_parse_interp(code).unwrap().to_string(), "vec2(0, 1)");
+
+        let code = "
+var m = mat(vec2(1, 2), vec2(3, 4))
+m * m + 1
+";
+        assert_eq!(lex_parse_interp(code).unwrap().to_string(), "mat([8, 11], [16, 23])");
+
+        // Errors
+        let codes = [
+            "vec2(1, 2) + vec3(1, 2, 3)",
+            "vec2(1, 2).z",
+            "var v = vec2(1, 2)\nv.x = \"a\"",
+            "cross(vec2(1, 0), vec2(0, 1))",
+            "mat(vec2(1, 2), vec3(1, 2, 3))",
+            "mat(vec2(1, 2)) * vec3(1, 2, 3)",
+            "1 - vec2(1, 2)",
+        ];
+        let errs = [
+            "dimension mismatch for operator '+': vec2 and vec3",
+            "vec2 has no component 'z'",
+            "vector components must be of type int or real, found str",
+            "cross product is only defined for vec3",
+            "matrix rows must have the same size",
+            "dimension mismatch for operator '*': mat1x2 and vec3",
+            "operation not supported",
+        ];
+
+        for (code, err) in codes.iter().zip(errs) {
+            assert_eq!(lex_parse_interp(code).err().unwrap().err.to_string(), err);
+        }
+    }
+
+    #[test]
+    fn complex() {
+        assert_eq!(lex_parse_interp("3 + 4i").unwrap().to_string(), "3+4i");
+        assert_eq!(lex_parse_interp("(1 + 2i) * (3 - 1i)").unwrap().to_string(), "5+5i");
+        assert_eq!(lex_parse_interp("(5 + 5i) / (3 - 1i)").unwrap().to_string(), "1+2i");
+        assert_eq!(lex_parse_interp("2i * 2i == -4 + 0i").unwrap(), true.into());
+        assert_eq!(lex_parse_interp("(3 - 4i).im").unwrap(), (-4f64).into());
+        assert_eq!(lex_parse_interp("math.abs(3 + 4i)").unwrap(), 5f64.into());
+        assert_eq!(lex_parse_interp("abs(3 + 4i)").unwrap(), 5f64.into());
+        assert_eq!(lex_parse_interp("math.conj(1 + 2i)").unwrap().to_string(), "1-2i");
+        assert_eq!(
+            lex_parse_interp("math.arg(1i)").unwrap(),
+            std::f64::consts::FRAC_PI_2.into()
+        );
+
+        // Errors
+        assert_eq!(
+            lex_parse_interp("1i < 2").err().unwrap().err.to_string(),
+            "operator '<' is not supported for operations on complex type"
+        );
+    }
+
+    #[test]
+    fn constants() {
+        assert_eq!(lex_parse_interp("PI").unwrap(), std::f64::consts::PI.into());
+        assert_eq!(lex_parse_interp("1 / INF").unwrap(), 0f64.into());
+        assert_eq!(lex_parse_interp("NAN == NAN").unwrap(), false.into());
+
+        // Negation doesn't modify the constant
+        assert_eq!(lex_parse_interp("-E\nE").unwrap(), std::f64::consts::E.into());
+
+        let code = "
+fn f() {
+    var PI = 3
+    return PI
+}
+f()
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 3.into());
+
+        // Errors
+        assert_eq!(
+            lex_parse_interp("PI = 3").err().unwrap().err,
+            InterpErr::AssignEnv("can't assign to constant 'PI'".into())
+        );
+    }
+
+    #[test]
+    fn rounding() {
+        // Ints are returned without digits, reals with digits
+        assert_eq!(lex_parse_interp("round(2.5)").unwrap(), 3.into());
+        assert_eq!(lex_parse_interp("round(-2.5)").unwrap(), (-3).into());
+        assert_eq!(lex_parse_interp("round(7)").unwrap(), 7.into());
+        assert_eq!(lex_parse_interp("round(7, 2)").unwrap(), 7.into());
+        assert_eq!(lex_parse_interp("round(2.71828, 2)").unwrap(), 2.72f64.into());
+        assert_eq!(lex_parse_interp("round(2., 0)").unwrap(), 2f64.into());
+
+        assert_eq!(lex_parse_interp("floor(-1.5)").unwrap(), (-2).into());
+        assert_eq!(lex_parse_interp("ceil(1.2)").unwrap(), 2.into());
+        assert_eq!(lex_parse_interp("trunc(-1.7)").unwrap(), (-1).into());
+        assert_eq!(lex_parse_interp("floor(4)").unwrap(), 4.into());
+
+        assert_eq!(
+            lex_parse_interp("to_fixed(PI, 3)").unwrap(),
+            EcoString::from("3.142").into()
+        );
+        assert_eq!(
+            lex_parse_interp("to_fixed(2, 2)").unwrap(),
+            EcoString::from("2.00").into()
+        );
+        assert_eq!(
+            lex_parse_interp("to_str(vec2(0.1 + 0.2, 1e21))").unwrap(),
+            EcoString::from("vec2(0.30000000000000004, 1e21)").into()
+        );
+        assert_eq!(
+            lex_parse_interp("parse_real(to_str(1. / 3.)) == 1. / 3.").unwrap(),
+            true.into()
+        );
+
+        // Errors
+        assert_eq!(
+            lex_parse_interp("floor(INF)").err().unwrap().err,
+            InterpErr::FnCall("can't convert inf to int".into())
+        );
+        assert_eq!(
+            lex_parse_interp("to_fixed(1.5, -1)").err().unwrap().err,
+            InterpErr::FnCall("number of digits must be between 0 and 100, found -1".into())
+        );
+        assert_eq!(
+            lex_parse_interp("to_fixed(1.5, 1000000000)").err().unwrap().err,
+            InterpErr::FnCall("number of digits must be between 0 and 100, found 1000000000".into())
+        );
+        assert_eq!(
+            lex_parse_interp("round(1.5, 400)").err().unwrap().err,
+            InterpErr::FnCall("number of digits must be between 0 and 17, found 400".into())
+        );
+        assert_eq!(lex_parse_interp("round(1e300, 17)").unwrap(), 1e300.into());
+    }
+
+    #[test]
+    fn parse_numbers() {
+        assert_eq!(lex_parse_interp("parse_int(\" 42 \")").unwrap(), 42.into());
+        assert_eq!(lex_parse_interp("parse_int(\"-17\")").unwrap(), (-17).into());
+        assert_eq!(lex_parse_interp("parse_int(\"ff\", 16)").unwrap(), 255.into());
+        assert_eq!(lex_parse_interp("parse_int(\"101\", 2)").unwrap(), 5.into());
+        assert_eq!(lex_parse_interp("parse_real(\"2.5\")").unwrap(), 2.5f64.into());
+        assert_eq!(lex_parse_interp("parse_real(\"1e3\")").unwrap(), 1000f64.into());
+
+        // Errors
+        let codes = [
+            "parse_int(\"4.2\")",
+            "parse_int(\"12\", 2)",
+            "parse_int(\"12\", 40)",
+            "parse_real(\"abc\")",
+        ];
+        let errs = [
+            "can't parse '4.2' as int",
+            "can't parse '12' as int in base 2",
+            "base must be between 2 and 36, found 40",
+            "can't parse 'abc' as real",
+        ];
+
+        for (code, err) in codes.iter().zip(errs) {
+            assert_eq!(
+                lex_parse_interp(code).err().unwrap().err,
+                InterpErr::FnCall(err.into())
+            );
+        }
+    }
+
+    #[test]
+    fn stdin_natives() {
+        let input = "first\nsecond\r\nthird";
+
+        assert_eq!(
+            lex_parse_interp_input("stdin_read_all()", input).unwrap(),
+            EcoString::from(input).into()
+        );
+        assert_eq!(
+            lex_parse_interp_input("iter.collect(stdin_lines())", input).unwrap().to_string(),
+            "[\"first\", \"second\", \"third\"]"
+        );
+
+        // Input is consumed
+        let code = "
+stdin_read_all()
+iter.collect(stdin_lines())
+";
+        assert_eq!(lex_parse_interp_input(code, input).unwrap().to_string(), "[]");
+
+        // Lines are read as the loop goes, the rest stays for later reads
+        let code = "
+for line in stdin_lines() {
+    print(line)
+}
+";
+        let mut interp = Interpreter::new();
+        let output = SharedOutput::default();
+        interp.output = Box::new(output.clone());
+        interp.input = Box::new(input.as_bytes());
+
+        let nodes = lex_and_parse(code).unwrap();
+        assert!(interp.interpret(&nodes).is_ok());
+        assert_eq!(output.text(), "first\nsecond\nthird\n");
+
+        let code = "
+var first = iter.collect(iter.take(stdin_lines(), 1))
+[first, stdin_read_all()]
+";
+        assert_eq!(
+            lex_parse_interp_input(code, input).unwrap().to_string(),
+            "[[\"first\"], \"second\r\nthird\"]"
+        );
+    }
+
+    #[test]
+    fn path_natives() {
+        assert_eq!(
+            lex_parse_interp("fs.path_join(\"data\", \"runs\", \"a.csv\")").unwrap(),
+            EcoString::from("data/runs/a.csv").into()
+        );
+        assert_eq!(
+            lex_parse_interp("fs.path_basename(\"data/a.csv\")").unwrap(),
+            EcoString::from("a.csv").into()
+        );
+        assert_eq!(
+            lex_parse_interp("fs.path_ext(\"data/a.tar.gz\")").unwrap(),
+            EcoString::from("gz").into()
+        );
+        assert_eq!(lex_parse_interp("fs.path_ext(\"data\")").unwrap(), RtVal::Null);
+
+        let dir = std::env::temp_dir().join("phy_path_natives");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("b.txt"), "").unwrap();
+
+        let code = format!(
+            "
+var dir = \"{}\"
+print(fs.path_exists(fs.path_join(dir, \"b.txt\")), fs.path_exists(dir + \"/c\"))
+fs.list_dir(dir)
+",
+            dir.display()
+        );
+        let (res, output) = lex_parse_interp_output(&code);
+        assert_eq!(res.unwrap().to_string(), "[\"b.txt\", \"sub\"]");
+        assert_eq!(output, "true false\n");
+
+        // Sandbox
+        let res = lex_parse_interp_with("fs.path_exists(\"/\")", |interp| interp.sandboxed = true);
+        assert_eq!(
+            res.err().unwrap().err,
+            InterpErr::FnCall("file system access is disabled in sandbox mode".into())
+        );
+
+        let res = lex_parse_interp_with("fs.path_ext(\"a.txt\")", |interp| interp.sandboxed = true);
+        assert!(res.is_ok());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn fn_redefinition() {
+        let code = "
+fn f() { return 1 }
+fn g() { return f() }
+fn f() { return 2 }
+g()
+";
+        assert_eq!(
+            lex_parse_interp(code).err().unwrap().err,
+            InterpErr::VarDeclEnv("variable 'f' is already declared".into())
+        );
+        assert_eq!(lex_parse_interp_with(code, |interp| interp.redefine_fns = true).unwrap(), 2.into());
+
+        // Only functions are replaced
+        let res = lex_parse_interp_with("var f = 1\nfn f() {}", |interp| interp.redefine_fns = true);
+        assert_eq!(
+            res.err().unwrap().err,
+            InterpErr::VarDeclEnv("variable 'f' is already declared".into())
+        );
+    }
+
+    #[test]
+    fn set_global() {
+        let mut interp = Interpreter::new();
+        interp.set_global("_", 1.into());
+        interp.set_global("_", 2.into());
+
+        let vars = interp.get_visible_vars();
+        assert!(vars.contains(&("_".into(), 2.into())));
+    }
+
+    #[test]
+    fn metrics() {
+        let code = "
+fn fact(n) {
+    if n <= 1 { return 1 }
+    return n * fact(n - 1)
+}
+var s = \"a\"
+var f = fact(3)
+";
+        let nodes = lex_and_parse(code).unwrap();
+        let mut interp = Interpreter::new();
+        interp.interpret(&nodes).unwrap();
+
+        let metrics = interp.metrics();
+        assert_eq!(metrics.statements, 9);
+        assert_eq!(metrics.calls, 3);
+        assert_eq!(metrics.allocations.get("int"), Some(&4));
+        assert_eq!(metrics.allocations.get("str"), Some(&1));
+        // The body of each call
+        assert_eq!(metrics.max_env_depth, 3);
+
+        interp.reset_metrics();
+        assert_eq!(interp.metrics(), &Metrics::default());
+    }
+
+    #[test]
+    fn recording() {
+        let code = "var a = 1\nfor i in 0..2 { a = a + i }\nprint(a / 0)";
+        let nodes = lex_and_parse(code).unwrap();
+        let mut interp = Interpreter::new();
+        interp.recording = Some(vec![]);
+        assert!(interp.interpret(&nodes).is_err());
+
+        let events = interp.recording.unwrap();
+        let stmts = events.iter().filter(|e| matches!(e, TraceEvent::Stmt(_))).count();
+        let sets = events
+            .iter()
+            .filter_map(|e| match e {
+                TraceEvent::Set(name, value) => Some(format!("{}={}", name, value)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        // The failing statement is the last one
+        assert_eq!(stmts, 5);
+        assert_eq!(events.last(), Some(&TraceEvent::Stmt(Loc::new(49, 50))));
+        assert_eq!(sets, ["a=1", "i=null", "i=0", "a=1", "i=1", "a=2"]);
+    }
+
+    #[test]
+    fn contracts() {
+        let code = "
+fn div(a, b)
+    requires b != 0
+    ensures result * b <= a
+{
+    return a / b
+}
+fn wrong(n) ensures result > n { return n }
+fn avg(x, y) { return div(x + y, 0) }
+";
+        let nodes = lex_and_parse(code).unwrap();
+        let mut interp = Interpreter::new();
+        interp.contracts = true;
+        interp.interpret(&nodes).unwrap();
+
+        assert_eq!(interp.call_function("div", vec![7.into(), 2.into()]).unwrap(), 3.into());
+
+        // Located at the clause when called by the host
+        let err = interp.call_function("div", vec![1.into(), 0.into()]).err().unwrap();
+        let InterpErr::ContractViolation { kind, func, clause, clause_loc } = &err.err else {
+            panic!("expected a contract violation")
+        };
+        assert_eq!((*kind, func.as_str(), clause.as_str()), ("precondition", "div", "b != 0"));
+        assert_eq!(err.loc.as_ref(), Some(clause_loc));
+
+        assert!(matches!(
+            interp.call_function("wrong", vec![1.into()]).err().unwrap().err,
+            InterpErr::ContractViolation { kind: "postcondition", .. }
+        ));
+
+        // Kept through the calls, at the inner call
+        let err = interp.call_function("avg", vec![1.into(), 2.into()]).err().unwrap();
+        assert!(matches!(err.err, InterpErr::ContractViolation { kind: "precondition", .. }));
+        assert_ne!(err.loc, Some(clause_loc.clone()));
+
+        let code = "
+fn half(n) requires n % 2 == 0 { return n / 2 }
+half(3)
+";
+        // Not checked by default
+        assert_eq!(lex_parse_interp(code).unwrap(), 1.into());
+
+        let res = lex_parse_interp_with("fn f(n) requires n { return n }\nf(1)", |interp| {
+            interp.contracts = true
+        });
+        assert_eq!(res.err().unwrap().err, InterpErr::NonBoolContract);
+    }
+
+    #[test]
+    fn tasks() {
+        let code = "
+fn worker(name, n) {
+    var i = 0
+    while i < n {
+        print(name, i)
+        i = i + 1
+    }
+    return name
+}
+var a = spawn worker(\"a\", 2)
+var b = spawn worker(\"b\", 2)
+print(a.done, b.done)
+print(await b, await a, a.done)
+var c = spawn worker(\"c\", 1)
+";
+        // Each task runs until its next yield point when the main code
+        // reaches one, 'c' runs at the end
+        let (res, output) = lex_parse_interp_output(code);
+        assert!(res.is_ok());
+        assert_eq!(output, "false false\na 0\nb 0\na 1\nb 1\nb a true\nc 0\n");
+
+        // Each player waits for its turn in the middle of its loop
+        let code = "
+var turn = \"ping\"
+fn player(me, other, n) {
+    for i in 0..n {
+        while turn != me {}
+        print(me, i)
+        turn = other
+    }
+}
+var a = spawn player(\"ping\", \"pong\", 2)
+var b = spawn player(\"pong\", \"ping\", 2)
+await b
+";
+        let (res, output) = lex_parse_interp_output(code);
+        assert!(res.is_ok());
+        assert_eq!(output, "ping 0\npong 0\nping 1\npong 1\n");
+
+        // Awaiting a task before any yield point runs it
+        let code = "
+fn double(x) { return x * 2 }
+var t = spawn double(21)
+await t
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 42.into());
+
+        assert_eq!(
+            lex_parse_interp("await 1").err().unwrap().err,
+            InterpErr::AwaitNonTask("int".into())
+        );
+
+        let code = "
+fn wait() { return await t }
+var t = spawn wait()
+print(1)
+";
+        assert_eq!(
+            lex_parse_interp(code).err().unwrap().err,
+            InterpErr::FnCall("a task can't await itself".into())
+        );
+
+        // Tasks awaiting each other never end
+        let code = "
+fn wait_b() { return await b }
+fn wait_a() { return await a }
+var a = spawn wait_b()
+var b = spawn wait_a()
+";
+        let err = lex_parse_interp(code).err().unwrap();
+        assert_eq!(err.err, InterpErr::Deadlock);
+        assert_eq!(err.loc, Some(Loc::new(77, 85)));
+    }
+
+    #[test]
+    fn channels() {
+        let code = "
+fn producer(ch, n) {
+    var i = 0
+    while i < n {
+        send(ch, i * 10)
+        i = i + 1
+    }
+    send(ch, null)
+}
+fn consumer(ch, out) {
+    var total = 0
+    var v = recv(ch)
+    while v != null {
+        total = total + v
+        v = recv(ch)
+    }
+    send(out, total)
+}
+var ch = chan()
+var out = chan()
+spawn consumer(ch, out)
+spawn producer(ch, 4)
+print(recv(out))
+";
+        // The consumer blocks on 'recv' and lets the producer run
+        let (res, output) = lex_parse_interp_output(code);
+        assert!(res.is_ok());
+        assert_eq!(output, "60\n");
+
+        // Each player waits for the ball in the middle of its loop
+        let code = "
+fn player(name, inbox, outbox, n) {
+    for i in 0..n {
+        var ball = recv(inbox)
+        print(name, ball)
+        send(outbox, ball + 1)
+    }
+    return name
+}
+var ping = chan()
+var pong = chan()
+var a = spawn player(\"ping\", ping, pong, 3)
+var b = spawn player(\"pong\", pong, ping, 3)
+send(ping, 0)
+print(await a, await b, recv(ping))
+";
+        let (res, output) = lex_parse_interp_output(code);
+        assert!(res.is_ok());
+        assert_eq!(output, "ping 0\npong 1\nping 2\npong 3\nping 4\npong 5\nping pong 6\n");
+
+        // A task still waiting at the end of the run
+        let code = "
+fn consumer(ch) { return recv(ch) }
+var t = spawn consumer(chan())
+";
+        assert_eq!(lex_parse_interp(code).err().unwrap().err, InterpErr::Deadlock);
+
+        assert_eq!(
+            lex_parse_interp("recv(chan())").err().unwrap().err,
+            InterpErr::FnCall("receiving from an empty channel, no task left to send a value".into())
+        );
+        assert_eq!(
+            lex_parse_interp("send(1, 2)").err().unwrap().err,
+            InterpErr::WrongArgType(1, "chan or actor".into(), "int".into())
+        );
+    }
+
+    #[test]
+    fn stepped_run() {
+        let code = "
+var n = 0
+while n < 100 {
+    n = n + 1
+}
+n
+";
+        // Each iteration is two operations, the loop and its statement
+        let interp = Interpreter::new();
+        let mut run = interp.step_run(lex_and_parse(code).unwrap()).unwrap();
+        let mut steps = 1;
+
+        while run.step(20).unwrap() == Step::Paused {
+            steps += 1;
+        }
+        assert!(run.is_done());
+        assert!((10..=12).contains(&steps));
+
+        // The run ends in the first step with enough operations, its
+        // effects stay in the interpreter
+        let mut run = Interpreter::new().step_run(lex_and_parse(code).unwrap()).unwrap();
+        assert_eq!(run.step(1000).unwrap(), Step::Done(100.into()));
+
+        let mut interp = run.into_interpreter();
+        assert_eq!(interp.interpret(&lex_and_parse("n * 2").unwrap()).unwrap(), 200.into());
+
+        // Stopped in the middle, the loop never ends otherwise
+        let code = "
+var n = 0
+while true {
+    n = n + 1
+}
+";
+        let mut run = Interpreter::new().step_run(lex_and_parse(code).unwrap()).unwrap();
+        for _ in 0..5 {
+            assert_eq!(run.step(10).unwrap(), Step::Paused);
+        }
+
+        // 50 operations, the declaration and the loop then 24 iterations
+        let mut interp = run.into_interpreter();
+        assert_eq!(interp.interpret(&lex_and_parse("n").unwrap()).unwrap(), 24.into());
+
+        // Dropped while paused or before its first step
+        let mut run = Interpreter::new().step_run(lex_and_parse(code).unwrap()).unwrap();
+        assert_eq!(run.step(3).unwrap(), Step::Paused);
+        drop(run);
+        drop(Interpreter::new().step_run(lex_and_parse(code).unwrap()).unwrap());
+
+        // Errors end the run
+        let mut run = Interpreter::new().step_run(lex_and_parse("var a = 1\nb").unwrap()).unwrap();
+        assert_eq!(run.step(1).unwrap(), Step::Paused);
+        assert!(run.step(1).is_err());
+        assert!(run.is_done());
+
+        // The tasks run within the operations of the main code
+        let code = "
+fn worker(name, n) {
+    for i in 0..n {
+        print(name, i)
+    }
+    return n
+}
+var a = spawn worker(\"a\", 3)
+var b = spawn worker(\"b\", 2)
+print(await a + await b)
+";
+        let output = SharedOutput::default();
+        let mut interp = Interpreter::new();
+        interp.output = Box::new(output.clone());
+
+        let mut run = interp.step_run(lex_and_parse(code).unwrap()).unwrap();
+        let mut steps = 1;
+
+        while run.step(2).unwrap() == Step::Paused {
+            steps += 1;
+        }
+        assert!(steps > 3);
+        assert_eq!(output.text(), "a 0\nb 0\na 1\nb 1\na 2\n5\n");
+    }
+
+    #[test]
+    fn timers() {
+        let code = "
+var n = 0
+fn tick() {
+    n = n + 1
+    print(\"tick\", n)
+    if n == 3 { clear_timer(id) }
+}
+fn first() { print(\"first\") }
+fn last() { print(\"last\") }
+var id = set_interval(tick, 2)
+set_timeout(last, 20)
+set_timeout(first, 0)
+var cleared = set_timeout(last, 1)
+print(clear_timer(cleared), clear_timer(cleared))
+";
+        let nodes = lex_and_parse(code).unwrap();
+        let output = SharedOutput::default();
+        let mut interp = Interpreter::new();
+        interp.output = Box::new(output.clone());
+
+        // Nothing runs before the event loop
+        interp.interpret(&nodes).unwrap();
+        assert_eq!(output.text(), "true false\n");
+
+        interp.run_until_idle().unwrap();
+        assert_eq!(output.text(), "true false\nfirst\ntick 1\ntick 2\ntick 3\nlast\n");
+
+        // Errors of the callbacks stop the loop and drop the other timers
+        let code = "
+fn fail() { return 1 / \"a\" }
+set_timeout(fail, 0)
+set_timeout(print, 0)
+";
+        let nodes = lex_and_parse(code).unwrap();
+        let mut interp = Interpreter::new();
+        interp.interpret(&nodes).unwrap();
+        assert!(interp.run_until_idle().is_err());
+        assert!(interp.run_until_idle().is_ok());
+    }
+
+    #[test]
+    fn actors() {
+        let code = "
+actor Account(owner) {
+    var balance = 0
+    on amount {
+        balance = balance + amount
+        print(owner, balance)
+        if amount > 10 { send(bank, amount) }
+    }
+}
+actor Bank() {
+    on amount { print(\"large\", amount) }
+}
+var bank = Bank()
+var a = Account(\"a\")
+var b = Account(\"b\")
+send(a, 5)
+send(b, 20)
+send(a, 1)
+print(a)
+";
+        let nodes = lex_and_parse(code).unwrap();
+        let output = SharedOutput::default();
+        let mut interp = Interpreter::new();
+        interp.output = Box::new(output.clone());
+
+        // The messages wait for the event loop, and are handled in order
+        interp.interpret(&nodes).unwrap();
+        assert_eq!(output.text(), "<actor Account>\n");
+
+        interp.run_until_idle().unwrap();
+        assert_eq!(output.text(), "<actor Account>\na 5\nb 20\na 6\nlarge 20\n");
+
+        // The state is private
+        let err = lex_parse_interp("actor A() { var s = 1\n on m {} }\nvar a = A()\nprint(s)").err().unwrap();
+        assert_eq!(err.err.to_string(), "undeclared variable 's'");
+    }
+
+    #[test]
+    fn events() {
+        let code = "
+var score = 0
+fn add(points) { score = score + points }
+fn log(points) {
+    print(\"hit\", points)
+    on(\"hit\", log)
+}
+on(\"hit\", add)
+on(\"hit\", log)
+print(emit(\"hit\", 10), emit(\"miss\", null))
+";
+        let nodes = lex_and_parse(code).unwrap();
+        let output = SharedOutput::default();
+        let mut interp = Interpreter::new();
+        interp.output = Box::new(output.clone());
+        interp.interpret(&nodes).unwrap();
+        assert_eq!(output.text(), "hit 10\n2 0\n");
+
+        // From the host, the handlers subscribed by a handler run from the
+        // next event
+        assert_eq!(interp.emit("hit", 5.into()).unwrap(), 3);
+        assert_eq!(output.text(), "hit 10\n2 0\nhit 5\nhit 5\n");
+        assert!(interp.get_visible_vars().contains(&("score".into(), 15.into())));
+
+        let err = lex_parse_interp("fn f(p) { return p + \"a\" }\non(\"e\", f)\nemit(\"e\", 1)")
+            .err()
+            .unwrap();
+        assert!(err.err.to_string().starts_with("in a call made by 'emit'"));
+    }
+
+    #[test]
+    fn state_after_errors() {
+        let mut interp = Interpreter::new();
+        let run = |interp: &mut Interpreter, code: &str| {
+            let nodes = lex_and_parse(code).unwrap();
+            interp.interpret(&nodes)
+        };
+
+        // The declarations before the error are kept, not the failing one
+        assert!(run(&mut interp, "var a = 1\nvar b = a + \"x\" - 1\nvar c = 3").is_err());
+        let names = interp.get_visible_names();
+        assert!(names.contains(&"a".into()) && !names.contains(&"b".into()) && !names.contains(&"c".into()));
+
+        // Failing deep in nested calls, loops and blocks leaves all of them
+        let code = "
+fn inner(i) {
+    var local = i
+    if i == 2 { return local + \"x\" - 1 }
+    return local
+}
+fn outer() {
+    for i in 0..5 {
+        var step = inner(i)
+        a = a + step
+    }
+}
+";
+        run(&mut interp, code).unwrap();
+        let memory = interp.memory_used;
+        assert!(run(&mut interp, "outer()").is_err());
+        assert!(Rc::ptr_eq(&interp.env, &interp.globals));
+        assert_eq!(interp.memory_used, memory);
+        assert_eq!(run(&mut interp, "a").unwrap(), 2.into());
+
+        // The names of the left scopes are free again
+        run(&mut interp, "var i = 0\nvar local = 1\nvar step = 2").unwrap();
+
+        // Errors raised in a watch too, and watches still run after one
+        let output = SharedOutput::default();
+        interp.output = Box::new(output.clone());
+        run(&mut interp, "fn check(v) { print(10 / v) }\nwatch(\"a\", check)").unwrap();
+        let err = run(&mut interp, "{ var x = 1\n a = 0 }").err().unwrap();
+        assert_eq!(err.err, InterpErr::Watch("a".into(), "division by zero".into()));
+        assert!(Rc::ptr_eq(&interp.env, &interp.globals));
+        run(&mut interp, "a = 3").unwrap();
+        assert_eq!(output.text(), "3\n");
+    }
+
+    #[test]
+    fn closed_output() {
+        struct ClosedPipe;
+
+        impl std::io::Write for ClosedPipe {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::BrokenPipe.into())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let code = "
+var a = 1
+fn show(x) { print(x) }
+show(a)
+var b = 2
+";
+        let nodes = lex_and_parse(code).unwrap();
+
+        let mut interp = Interpreter::new();
+        interp.output = Box::new(ClosedPipe);
+        let err = interp.interpret(&nodes).err().unwrap();
+        assert!(err.err.to_string().contains("failed to write to output"));
+        assert!(!interp.output_closed());
+
+        // Stops where the output closed, without error
+        let mut interp = Interpreter::new();
+        interp.output = Box::new(ClosedPipe);
+        interp.output_failure = OutputFailure::Stop;
+        assert_eq!(interp.interpret(&nodes).unwrap(), RtVal::Null);
+        assert!(interp.output_closed());
+
+        let names = interp.get_visible_names();
+        assert!(names.contains(&"a".into()) && !names.contains(&"b".into()));
+
+        // Reset by the next run
+        let nodes = lex_and_parse("var c = 3").unwrap();
+        interp.interpret(&nodes).unwrap();
+        assert!(!interp.output_closed());
+    }
+
+    #[test]
+    fn hints() {
+        let code = "
+fn f(a) {}
+f(1, 2)
+";
+        let hint = lex_parse_interp(code).err().unwrap().err.get_hint(&English).unwrap();
+        assert!(hint.advice.contains("takes 1 arguments and the call gives 2"));
+        assert!(hint.example.contains("add(1, 2)"));
+
+        let code = "var a\na + 1";
+        let hint = lex_parse_interp(code).err().unwrap().err.get_hint(&English);
+        assert!(hint.unwrap().example.contains("var total = 0"));
+
+        let code = "1 + true";
+        assert!(lex_parse_interp(code).err().unwrap().err.get_hint(&English).is_none());
+    }
+
+    #[test]
+    fn localized_errors() {
+        let mut catalog = Catalog::default();
+        catalog.insert("value.OpStrInt", "opérateur invalide entre une string et un int");
+        catalog.insert("env.UndeclaredVar", "variable '{0}' non déclarée");
+        catalog.insert("interpreter.InNative", "dans un appel fait par '{0}' : {1}");
+
+        // The failures of the operations and of the environment are
+        // translated with the error wrapping them
+        let err = lex_parse_interp("\"a\" + 1").err().unwrap().err;
+        assert_eq!(localize(&catalog, &err), "opérateur invalide entre une string et un int");
+        assert_eq!(
+            localize(&English, &err),
+            "can't use this operator for operations on string and int types"
+        );
+
+        let err = lex_parse_interp("print(b)").err().unwrap().err;
+        assert_eq!(localize(&catalog, &err), "variable 'b' non déclarée");
+
+        let code = "
+fn producer(ch) {
+    send(ch, \"a\" + 1)
+}
+var ch = chan()
+spawn producer(ch)
+recv(ch)
+";
+        let err = lex_parse_interp(code).err().unwrap().err;
+        assert_eq!(
+            localize(&catalog, &err),
+            "dans un appel fait par 'recv' : opérateur invalide entre une string et un int"
+        );
+    }
+
+    #[test]
+    fn watches() {
+        let code = "
+var x = 0
+fn changed(v) { print(\"x changed to\", v) }
+fn cap(v) {
+    if v > 10 { x = 10 }
+}
+watch(\"x\", changed)
+watch(\"x\", cap)
+x = 5
+fn f() {
+    var x = 1
+    x = 2
+    return 0
+}
+f()
+x = 20
+x
+";
+        // Shadowing variables aren't watched, assignments in a watch don't
+        // trigger the watches again
+        let (res, output) = lex_parse_interp_output(code);
+        assert_eq!(res.unwrap(), 10.into());
+        assert_eq!(output, "x changed to 5\nx changed to 20\n");
+
+        assert_eq!(
+            lex_parse_interp("watch(\"y\", print)").err().unwrap().err,
+            InterpErr::FnCall("can't watch variable: undeclared variable 'y'".into())
+        );
+
+        let code = "
+var x = 0
+fn fail(v) { return v / \"a\" }
+watch(\"x\", fail)
+x = 1
+";
+        assert!(matches!(
+            lex_parse_interp(code).err().unwrap().err,
+            InterpErr::Watch(name, _) if name == "x"
+        ));
+    }
+
+    #[test]
+    fn custom_operators() {
+        let code = "
+operator <+> (a, b) { return vec2(a.x + b.x, a.y - b.y) }
+operator <.> prec 6 (a, b) { return a.x * b.x + a.y * b.y }
+var u = vec2(1, 2) <+> vec2(3, 1)
+u <.> u + 1
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 18.0.into());
+    }
+
+    #[test]
+    fn help() {
+        let code = "
+/// Adds two numbers
+fn add(a: int, b) -> int { return a + b }
+fn sub(a, b) { return a - b }
+help(add)
+help(sub)
+help(math.sqrt)
+help(print)
+";
+        let (res, output) = lex_parse_interp_output(code);
+        assert!(res.is_ok());
+        assert_eq!(
+            output,
+            "fn add(a: int, b) -> int\n\nAdds two numbers\n\
+             fn sub(a, b)\n\nNo documentation\n\
+             native fn math.sqrt(int|real)\n\nNo documentation\n\
+             native fn print(...)\n\nNo documentation\n"
+        );
+    }
+
+    #[test]
+    fn native_callbacks() {
+        // 'starter' and 'producer' run while 'recv' waits in the main code,
+        // the error of 'producer' is framed by it
+        let code = "
+fn producer(ch) {
+    send(ch, 1 / \"a\")
+}
+fn starter(ch) {
+    spawn producer(ch)
+}
+fn consumer(ch) {
+    spawn starter(ch)
+    return recv(ch)
+}
+consumer(chan())
+";
+        let err = lex_parse_interp(code).err().unwrap();
+        let InterpErr::InNative(name, inner) = &err.err else {
+            panic!("expected an error framed by the native, found {:?}", err.err)
+        };
+        assert_eq!(name, "recv");
+        assert_eq!(err.loc, Some(Loc::new(136, 144)));
+        assert_eq!(inner.loc, Some(Loc::new(70, 82)));
+        assert_eq!(
+            err.err.to_string(),
+            "in a call made by 'recv': can't use this operator for operations on string and int types"
+        );
+    }
+
+    #[test]
+    fn error_causes() {
+        let code = "
+fn g(x) {
+    var y = -\"a\"
+}
+fn f(x) { return g(x) + 1 }
+f(2)
+";
+        // The error of each call is caused by the one raised in its body
+        let err = lex_parse_interp(code).err().unwrap();
+        let msg = "can't use '-' token on anything other than an int or a real value";
+        assert_eq!(err.err, InterpErr::FnCall(msg.into()));
+        assert_eq!(err.loc, Some(Loc::new(58, 62)));
+
+        let cause = err.cause.as_ref().unwrap();
+        assert_eq!(cause.err, InterpErr::FnCall(msg.into()));
+        assert_eq!(cause.loc, Some(Loc::new(47, 52)));
+
+        let root = err.root_cause();
+        assert_eq!(root.err, InterpErr::NegateNonNumeric);
+        assert_eq!(root.loc, Some(Loc::new(15, 27)));
+        assert!(root.cause.is_none());
+
+        let code = "
+var x = 0
+fn fail(v) { var y = -\"a\" }
+watch(\"x\", fail)
+x = 1
+";
+        let err = lex_parse_interp(code).err().unwrap();
+        assert_eq!(err.root_cause().err, InterpErr::NegateNonNumeric);
+    }
+
+    #[test]
+    fn memory_limit() {
+        let code = "
+var s = \"a\" * 100
+s = s * 100
+";
+        let res = lex_parse_interp_with(code, |interp| interp.memory_limit = Some(5_000));
+        assert_eq!(res.err().unwrap().err, InterpErr::MemoryLimit(5_000));
+
+        // Memory of the function scopes is given back at their end
+        let code = "
+fn f() {
+    var s = \"a\" * 1000
+    return 0
+}
+for i in 0..100 {
+    f()
+}
+";
+        let res = lex_parse_interp_with(code, |interp| interp.memory_limit = Some(5_000));
+        assert!(res.is_ok());
+
+        let code = "
+fn f(s) {
+    var t = s * 10
+    return 0
+}
+f(\"a\" * 1000)
+";
+        let res = lex_parse_interp_with(code, |interp| interp.memory_limit = Some(5_000));
+        assert_eq!(
+            res.err().unwrap().err,
+            InterpErr::FnCall("memory limit of 5000 bytes exceeded".into())
+        );
+
+        // A list holding itself is measured once
+        let code = "
+var l = [1]
+list.push(l, l)
+var k = l
+k = 0
+";
+        let res = lex_parse_interp_with(code, |interp| interp.memory_limit = Some(5_000));
+        assert!(res.is_ok());
+
+        // Nothing is counted without a limit
+        let mut interp = Interpreter::new();
+        interp.interpret(&lex_and_parse("var s = \"a\" * 1000").unwrap()).unwrap();
+        assert_eq!(interp.memory_used, 0);
+    }
+
+    #[test]
+    fn call_depth() {
+        let code = "
+fn rec(n) {
+    if n == 0 { return 0 }
+    return 1 + rec(n - 1)
+}
+rec(5000)
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), RtVal::from(5000));
+
+        // Fails with the location of the last call instead of aborting
+        let code = "
+fn rec(n) {
+    if n == 0 { return 0 }
+    return 1 + rec(n - 1)
+}
+rec(100000)
+";
+        let err = lex_parse_interp(code).err().unwrap();
+        assert_eq!(err.err, InterpErr::CallDepth(DEFAULT_MAX_CALL_DEPTH));
+        assert_eq!(err.loc.map(|l| l.start), code.find("rec(n - 1)"));
+
+        let res = lex_parse_interp_with(code, |interp| interp.max_call_depth = 50);
+        assert_eq!(res.err().unwrap().err, InterpErr::CallDepth(50));
+
+        // Tasks have smaller stacks, the limit of the stack comes first
+        let code = "
+fn rec(n) {
+    if n == 0 { return 0 }
+    return 1 + rec(n - 1)
+}
+await spawn rec(100000)
+";
+        let res = lex_parse_interp_with(code, |interp| interp.max_call_depth = 1_000_000);
+        assert!(matches!(res.err().unwrap().err, InterpErr::CallDepth(n) if n < 100000));
+    }
+
+    #[test]
+    fn macros() {
+        let code = "
+macro times(n, body) {
+    var i = 0
+    while i < n {
+        body
+        i = i + 1
+    }
+}
+var i = 10
+var count = 0
+times!(3) { count = count + i }
+count
+";
+        assert_eq!(lex_parse_interp(code).unwrap(), 30.into());
+    }
+
+    #[test]
+    fn imports() {
+        let dir = std::env::temp_dir().join("phy_imports");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("geo")).unwrap();
+        std::fs::write(
+            dir.join("geo/shapes.arc"),
+            "
+var count = 0
+fn scale(x) { return x * 2 }
+pub fn square(x) {
+    count = count + 1
+    return scale(x) * scale(x) / 4
+}
+pub var sides = 4
+",
+        )
+        .unwrap();
+
+        let run = |code: &str| {
+            lex_parse_interp_with(code, |interp| interp.module_paths = vec![dir.clone()])
+        };
+
+        let code = "
+import geo.shapes
+shapes.square(3) + shapes.sides
+";
+        assert_eq!(run(code).unwrap(), 13.into());
+
+        // Private declarations stay in the module
+        let res = run("import geo.shapes
+shapes.scale(1)");
+        assert_eq!(
+            res.err().unwrap().err,
+            InterpErr::PrivateMember("scale".into(), "shapes".into())
+        );
+        assert!(run("import geo.shapes
+scale(1)").is_err());
+
+        assert_eq!(
+            run("import geo.circles").err().unwrap().err,
+            InterpErr::ModuleNotFound("geo.circles".into())
+        );
+
+        let res = lex_parse_interp_with("import geo.shapes", |interp| interp.sandboxed = true);
+        assert_eq!(res.err().unwrap().err, InterpErr::SandboxedImport);
+
+        std::fs::write(dir.join("broken.arc"), "pub var a = b").unwrap();
+        assert!(matches!(
+            run("import broken").err().unwrap().err,
+            InterpErr::Module(m, _) if m == "broken"
+        ));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn circular_imports() {
+        let dir = std::env::temp_dir().join("phy_circular_imports");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.arc"), "pub var x = 1\nimport b\n").unwrap();
+        std::fs::write(dir.join("b.arc"), "import c\n\nimport a\n").unwrap();
+        std::fs::write(dir.join("c.arc"), "pub var y = 2\n").unwrap();
+
+        let res = lex_parse_interp_with("import a", |interp| {
+            interp.module_paths = vec![dir.clone()]
+        });
+        let expected = format!(
+            "a -> b -> a (b imported at {0}:2, a imported at {1}:3)",
+            dir.join("a.arc").display(),
+            dir.join("b.arc").display(),
+        );
+        assert_eq!(res.err().unwrap().err, InterpErr::CircularImport(expected));
+
+        // Importing the same module twice isn't a cycle
+        std::fs::write(dir.join("d.arc"), "import c\npub var z = c.y + 1\n").unwrap();
+        let res = lex_parse_interp_with("import c\nimport d\nc.y + d.z", |interp| {
+            interp.module_paths = vec![dir.clone()]
+        });
+        assert_eq!(res.unwrap(), 5.into());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn list_natives() {
+        let code = "
+var l = list.new()
+list.push(l, 1)
+list.push(l, \"a\")
+print(list.len(l), list.get(l, 1), l)
+list.get(l, 2)
+";
+        let (res, output) = lex_parse_interp_output(code);
+        assert_eq!(output, "2 a [1, \"a\"]\n");
+        assert_eq!(
+            res.err().unwrap().err,
+            InterpErr::FnCall("index 2 out of range for length 2".into())
+        );
+
+        assert_eq!(lex_parse_interp("str.len(\"héhé\")").unwrap(), 4.into());
+        assert_eq!(
+            lex_parse_interp("str.slice(\"abcdef\", 1, 3) + str.slice(\"abc\", 2, 10)").unwrap(),
+            EcoString::from("bcc").into()
+        );
+    }
+
+    #[test]
+    fn list_mutation() {
+        let code = "
+var l = list.new()
+list.push(l, 1)
+list.push(l, 2)
+list.insert(l, 0, 0)
+list.insert(l, 3, 4)
+list.insert(l, -1, 3)
+print(l)
+print(list.pop(l), list.remove(l, 0), list.remove(l, -1), l)
+
+fn add(l, v) { list.push(l, v) }
+var alias = l
+add(alias, 5)
+var copy = l[..]
+list.clear(copy)
+print(l, alias, copy)
+list.clear(l)
+print(alias)
+list.pop(l)
+";
+        let (res, output) = lex_parse_interp_output(code);
+        assert_eq!(output, "[0, 1, 2, 3, 4]\n4 0 3 [1, 2]\n[1, 2, 5] [1, 2, 5] []\n[]\n");
+        assert_eq!(res.err().unwrap().err, InterpErr::FnCall("can't pop from an empty list".into()));
+
+        assert_eq!(
+            lex_parse_interp("var l = list.new()\nlist.insert(l, 1, 0)").err().unwrap().err,
+            InterpErr::FnCall("index 1 out of range for length 0".into())
+        );
+        assert_eq!(
+            lex_parse_interp("var l = list.new()\nlist.remove(l, 0)").err().unwrap().err,
+            InterpErr::FnCall("index 0 out of range for length 0".into())
+        );
+    }
+
+    #[test]
+    fn list_literals() {
+        let code = "
+var i = 0
+fn next() {
+    i = i + 1
+    return i
+}
+var l = [next(), next() * 10, [\"a\", 'b'], []]
+list.push(l[2], null)
+print(l, list.len([]), [x for x in [1, 2, 3] if x != 2])
+";
+        let (res, output) = lex_parse_interp_output(code);
+        assert!(res.is_ok());
+        assert_eq!(output, "[1, 20, [\"a\", 'b', null], []] 0 [1, 3]\n");
+    }
+
+    #[test]
+    fn list_equality() {
+        let code = "
+var a = [1, [2, \"b\"], null]
+print([1] == [1], [1] == [1.0], [1] != [2], [1] == [1, 2], a == [1, [2, \"b\"], null])
+print([1] == [\"1\"], [] == [], [[]] != [[1]])
+";
+        let (res, output) = lex_parse_interp_output(code);
+        assert!(res.is_ok());
+        assert_eq!(output, "true true true false true\nfalse true true\n");
+
+        assert_eq!(
+            lex_parse_interp("[1] < [2]").err().unwrap().err,
+            InterpErr::OperationEvaluation(
+                "operator '<' is not supported for operations on list type".into()
+            )
+        );
+    }
+
+    #[test]
+    fn cyclic_lists() {
+        let code = "
+var l = [1]
+list.push(l, l)
+var m = [1]
+list.push(m, m)
+print(l, [l, 2], l == m, l == [1, [1]])
+";
+        let (res, output) = lex_parse_interp_output(code);
+        assert!(res.is_ok());
+        assert_eq!(output, "[1, [...]] [[1, [...]], 2] true false\n");
+    }
+
+    #[test]
+    fn comprehensions() {
+        let code = "
+var x = \"outer\"
+var n = 5
+var squares = [x * x for x in 0..n if x % 2 == 0]
+var codes = [ord(c) for c in str.chars(\"ab\")]
+var pairs = [[i * 10 + j for j in 0..i] for i in 1..4]
+print(squares, codes, pairs, [x for x in 3..1], x)
+
+fn grow(l) {
+    return [list.push(l, v) for v in l]
+}
+var l = list.new()
+list.push(l, 1)
+print(list.len(grow(l)), l)
+[x for x in 1..\"3\"]
+";
+        let (res, output) = lex_parse_interp_output(code);
+        assert_eq!(
+            output,
+            "[0, 4, 16] [97, 98] [[10], [20, 21], [30, 31, 32]] [] outer\n1 [1, 1]\n"
         );
+        let err = res.err().unwrap();
+        assert_eq!(err.err, InterpErr::NonIntRange("str".into()));
 
-        let code = "4 * \"foo\"";
         assert_eq!(
-            lex_parse_interp(code).unwrap(),
-            EcoString::from("foofoofoofoo").into()
+            lex_parse_interp("[c for c in \"abc\"]").err().unwrap().err,
+            InterpErr::NotIterable("str".into())
         );
-
-        let code = "\"foo\" + \" \" + \"bar\"";
         assert_eq!(
-            lex_parse_interp(code).unwrap(),
-            EcoString::from("foo bar").into()
+            lex_parse_interp("[x for x in 0..3 if x]").err().unwrap().err,
+            InterpErr::NonBoolComprehensionCond
         );
+    }
 
-        // Errors
-        let code = "\"foo\" * 3.5";
-        matches!(
-            lex_parse_interp(code).err().unwrap().err,
-            InterpErr::OperationEvaluation { .. }
+    #[test]
+    fn pairs() {
+        let code = "
+var names = list.new()
+list.push(names, \"a\")
+list.push(names, \"b\")
+var scores = [x * 10 for x in 1..4]
+print(enumerate(names), zip(names, scores), zip(scores, list.new()))
+print([p[0] + p[1] for p in zip(scores, scores)])
+print([p[1] * p[0] for p in enumerate(scores) if p[0] > 0])
+zip(names, 1)
+";
+        let (res, output) = lex_parse_interp_output(code);
+        assert_eq!(
+            output,
+            "[[0, \"a\"], [1, \"b\"]] [[\"a\", 10], [\"b\", 20]] []\n[20, 40, 60]\n[20, 60]\n"
         );
-
-        let code = "\"foo\" + 56";
-        matches!(
-            lex_parse_interp(code).err().unwrap().err,
-            InterpErr::OperationEvaluation { .. }
+        assert_eq!(
+            res.err().unwrap().err,
+            InterpErr::WrongArgType(2, "list".into(), "int".into())
         );
     }
 
     #[test]
-    fn interp_negation() {
-        let code = "-3";
-        assert_eq!(lex_parse_interp(code).unwrap(), (-3).into());
-
-        let code = "-3.";
-        assert_eq!(lex_parse_interp(code).unwrap(), (-3f64).into());
+    fn iterators() {
+        let code = "
+var calls = 0
+fn even(x) {
+    calls = calls + 1
+    return x % 2 == 0
+}
+fn sq(x) { return x * x }
 
-        let code = "!true";
-        assert_eq!(lex_parse_interp(code).unwrap(), false.into());
+var evens = iter.filter(iter.range(0, 1000000), even)
+print(calls, evens)
+print(iter.collect(iter.take(iter.map(evens, sq), 3)), calls)
+print(iter.sum(iter.skip(iter.take(evens, 5), 2)), iter.sum(iter.range(3, 1)))
 
-        let code = "!false";
-        assert_eq!(lex_parse_interp(code).unwrap(), true.into());
+var l = list.new()
+list.push(l, 1)
+fn double(x) { return 2 * x }
+var doubled = iter.map(l, double)
+list.push(l, 2)
+print(iter.collect(doubled), [x + 1 for x in doubled])
+iter.collect(iter.filter(l, sq))
+";
+        let (res, output) = lex_parse_interp_output(code);
+        assert_eq!(output, "0 <iter>\n[0, 4, 16] 5\n18 0\n[2, 4] [3, 5]\n");
 
-        // Errors
-        let code = "- \"foo\"";
         assert_eq!(
-            lex_parse_interp(code).err().unwrap().err,
-            InterpErr::NegateNonNumeric
+            res.err().unwrap().err.to_string(),
+            "in a call made by 'iter.collect': filter function must return a bool, found int"
         );
 
-        let code = "!8";
         assert_eq!(
-            lex_parse_interp(code).err().unwrap().err,
-            InterpErr::BangOpOnNonBool
+            lex_parse_interp("iter.take(iter.range(0, 3), -1)").err().unwrap().err,
+            InterpErr::FnCall("count must be positive, found -1".into())
         );
     }
 
     #[test]
-    fn variable() {
-        let code = "var a = -8
-a";
-        assert_eq!(lex_parse_interp(code).unwrap(), (-8).into());
-
-        let code = "var a = -8
-a = 4 + a*2
-a";
-        assert_eq!(lex_parse_interp(code).unwrap(), (-12).into());
-
-        // Errors
-        let code = "a = 5";
-        assert!(matches!(
-            lex_parse_interp(code).err().unwrap().err,
-            InterpErr::AssignEnv { .. }
-        ));
+    fn chars() {
+        let code = "
+var s = \"héllo\"
+print(str.get(s, 1), 'a' < 'b', ord('a'), chr(98), s + '!', str.chars(\"ab\"))
+print('\\n' == chr(10), '\\'' == str.get(\"'\", 0))
+str.get(s, 5)
+";
+        let (res, output) = lex_parse_interp_output(code);
+        assert_eq!(output, "é true 97 b héllo! ['a', 'b']\ntrue true\n");
+        assert_eq!(
+            res.err().unwrap().err,
+            InterpErr::FnCall("index 5 out of range for length 5".into())
+        );
 
-        let code = "var b
-3 + b";
+        assert_eq!(lex_parse_interp("'x'").unwrap(), 'x'.into());
         assert_eq!(
-            lex_parse_interp(code).err().unwrap().err,
-            InterpErr::UninitializedValue
+            lex_parse_interp("chr(-1)").err().unwrap().err,
+            InterpErr::FnCall("-1 is not a valid char code point".into())
         );
     }
 
     #[test]
-    fn block() {
-        let code = "var a = -8
-{
-    var b = 1
-    b = a + 9
-    a = b
-}
-a";
-        assert_eq!(lex_parse_interp(code).unwrap(), 1.into());
+    fn indexing() {
+        let code = "
+var l = list.new()
+list.push(l, 1)
+list.push(l, 2)
+list.push(l, 3)
+print(l[0], l[-1], l[1..], l[..-1], l[-10..10], l[2..1], list.get(l, -3))
+var s = \"héllo\"
+print(s[1], s[-1], s[1..3], s[..], str.slice(s, -3, 5), bytes(\"ab\")[-1], bytes(\"abc\")[1..])
+l[3]
+";
+        let (res, output) = lex_parse_interp_output(code);
+        assert_eq!(
+            output,
+            "1 3 [2, 3] [1, 2] [1, 2, 3] [] 1\né o él héllo llo 98 bytes([98, 99])\n"
+        );
+        let err = res.err().unwrap();
+        assert_eq!(err.err, InterpErr::Indexing("index 3 out of range for length 3".into()));
+        assert_eq!(err.loc, Some(Loc::new(250, 254)));
+
+        assert_eq!(
+            lex_parse_interp("\"abc\"[-4]").err().unwrap().err,
+            InterpErr::Indexing("index -4 out of range for length 3".into())
+        );
+        assert_eq!(
+            lex_parse_interp("\"abc\"[1.5]").err().unwrap().err,
+            InterpErr::NonIntIndex("real".into())
+        );
+        assert_eq!(
+            lex_parse_interp("5[0]").err().unwrap().err,
+            InterpErr::Indexing("values of type int can't be indexed".into())
+        );
     }
 
     #[test]
-    fn if_stmt() {
+    fn repetition() {
         let code = "
-var a = true
-var b = 0
-if a { b = 1 } else {}
-b
+var l = [1]
+print(\"ab\" * 2, 2 * \"c\", l * 3, 0 * l, repeat(0, 3), repeat(\"ab\", 2), repeat(l, 2))
+print([0] * 3, 2 * [1, 2], [] * 4)
+var n = [l] * 2
+list.push(list.get(n, 0), 2)
+print(n)
+\"a\" * -1
 ";
-        assert_eq!(lex_parse_interp(code).unwrap(), 1.into());
+        let (res, output) = lex_parse_interp_output(code);
+        assert_eq!(
+            output,
+            "abab cc [1, 1, 1] [] [0, 0, 0] abab [1, 1]\n[0, 0, 0] [1, 2, 1, 2] []\n[[1, 2], [1, 2]]\n"
+        );
+        assert_eq!(
+            res.err().unwrap().err,
+            InterpErr::OperationEvaluation(
+                "can't repeat a value a negative number of times, found -1".into()
+            )
+        );
+        assert_eq!(
+            lex_parse_interp("repeat(1, -2)").err().unwrap().err,
+            InterpErr::FnCall("can't repeat a value a negative number of times, found -2".into())
+        );
 
-        let code = "
-var a = false
-var b = 0
-if a { b = 8 } else { b = 1 }
-b
-";
-        assert_eq!(lex_parse_interp(code).unwrap(), 1.into());
+        // Too large to be allocated, located at the whole expression
+        let too_large =
+            |n: &str| format!("can't repeat a value {} times, the result would be too large", n);
+        for (code, n) in [
+            ("\"ab\" * 9223372036854775807", "9223372036854775807"),
+            ("4611686018427387904 * \"a\"", "4611686018427387904"),
+            ("[1, 2] * 4611686018427387904", "4611686018427387904"),
+        ] {
+            let err = lex_parse_interp(code).err().unwrap();
+            assert_eq!(err.err, InterpErr::OperationEvaluation(too_large(n).into()));
+            assert_eq!(err.loc, Some(Loc::new(0, code.len())));
+        }
 
-        let code = "
-var a = false
-var b = 42
-if a {} else {}
-b
-";
-        assert_eq!(lex_parse_interp(code).unwrap(), 42.into());
+        let code = "repeat([1, 2], 4611686018427387904)";
+        let err = lex_parse_interp(code).err().unwrap();
+        assert_eq!(err.err, InterpErr::FnCall(too_large("4611686018427387904").into()));
+        assert_eq!(err.loc, Some(Loc::new(0, code.len())));
+        assert!(lex_parse_interp("repeat(0, 4611686018427387904)").is_err());
+        assert_eq!(lex_parse_interp("str.len(\"\" * 4611686018427387904)").unwrap(), 0.into());
+    }
 
-        // Errors
+    #[test]
+    fn unicode_strings() {
+        // An emoji is one char of 4 bytes, the accent of 'e\u{301}' is a
+        // char of its own
         let code = "
-var a = 5
-if a {} else {}
+var s = \"a😀e\u{301}\"
+print(str.len(s), bin.len(bytes(s)), str.get(s, 1), str.get(s, 2), str.slice(s, 1, 2))
+print(str.reverse(\"ab😀\"), str.len(str.reverse(s)), str.upper(\"straße\"), str.lower(\"ÉTÉ\"))
 ";
-        assert!(lex_parse_interp(code).err().unwrap().err == InterpErr::NonBoolIfCond);
+        let (res, output) = lex_parse_interp_output(code);
+        assert!(res.is_ok());
+        assert_eq!(output, "4 8 😀 e 😀\n😀ba 4 STRASSE été\n");
     }
 
     #[test]
-    fn logical() {
+    fn bytes() {
         let code = "
-var a = true
-var b = 0
-if a and b == 0 { b = 1 }
-b
+var l = list.new()
+list.push(l, 104)
+list.push(l, 105)
+list.push(l, 255)
+var b = bytes(l)
+print(b, bin.len(b), bin.get(b, 2), bin.slice(b, 1, 10), bin.to_hex(b), bin.to_base64(b))
+print(bin.to_str(bytes(\"hé\")), bytes(2) + bin.from_hex(\"0A\"), bin.from_base64(\"aGk=\") == bytes(\"hi\"))
+list.push(l, 256)
+bytes(l)
 ";
-        assert_eq!(lex_parse_interp(code).unwrap(), 1.into());
+        let (res, output) = lex_parse_interp_output(code);
+        assert_eq!(
+            output,
+            "bytes([104, 105, 255]) 3 255 bytes([105, 255]) 6869ff aGn/\nhé bytes([0, 0, 10]) true\n"
+        );
+        assert_eq!(
+            res.err().unwrap().err,
+            InterpErr::FnCall("bytes must be ints between 0 and 255, found 256".into())
+        );
+
+        assert_eq!(
+            lex_parse_interp("bin.from_hex(\"abc\")").err().unwrap().err,
+            InterpErr::FnCall("can't parse 'abc' as hex".into())
+        );
+        assert_eq!(
+            lex_parse_interp("bin.to_str(bin.from_hex(\"ff\"))").err().unwrap().err,
+            InterpErr::FnCall("bytes aren't valid utf-8 text".into())
+        );
+
+        let path = std::env::temp_dir().join("phy_bytes_natives.bin");
+        let code = format!(
+            "fs.write_bytes(\"{0}\", bin.from_hex(\"000102\"))\nfs.read_bytes(\"{0}\")",
+            path.display()
+        );
+        assert_eq!(lex_parse_interp(&code).unwrap(), vec![0u8, 1, 2].into());
+
+        let _ = std::fs::remove_file(&path);
+    }
 
+    #[test]
+    fn std_modules() {
         let code = "
-var a = true
-var b = 0
-if a and 2 + 2 == 5 { } else { b = 1 }
-b
+import std.list
+import std.functional
+fn double(x) { return x * 2 }
+fn even(x) { return x % 2 == 0 }
+var l = list.map(list.filter(list.range(0, 7), even), double)
+print(l, list.sum(l), list.contains(l, 8), list.reverse(l))
+print(functional.repeat(functional.compose(double, double), 2, 1))
+fn key(x) { return x % 7 }
+print(list.sort_by(list.map(list.range(10, 14), double), key))
 ";
-        assert_eq!(lex_parse_interp(code).unwrap(), 1.into());
+        let (res, output) = lex_parse_interp_output(code);
+        assert!(res.is_ok());
+        assert_eq!(output, "[0, 4, 8, 12] 24 true [12, 8, 4, 0]\n16\n[22, 24, 26, 20]\n");
 
         let code = "
-var a = true
-var b = 0
-if a or false { b = 1 }
-b
+import std.string
+string.capitalize(string.pad_left(\"7\", 3, \"0\")) + string.capitalize(\"arc\")
 ";
-        assert_eq!(lex_parse_interp(code).unwrap(), 1.into());
+        assert_eq!(lex_parse_interp(code).unwrap(), EcoString::from("007Arc").into());
 
+        // std.math extends the native math module
         let code = "
-var a = false
-var b = 0
-if a or false {} else { b = 1 }
-b
+import std.math
+math.gcd(12, 18) + math.lcm(4, 6) + math.factorial(4) + math.sqrt(16)
 ";
-        assert_eq!(lex_parse_interp(code).unwrap(), 1.into());
+        assert_eq!(lex_parse_interp(code).unwrap(), 46.0.into());
+
+        // Std modules don't need the file system
+        let res = lex_parse_interp_with("import std.list\nlist.sum(list.range(0, 4))", |interp| {
+            interp.sandboxed = true
+        });
+        assert_eq!(res.unwrap(), 6.into());
+
+        assert_eq!(
+            lex_parse_interp("import std.net").err().unwrap().err,
+            InterpErr::ModuleNotFound("std.net".into())
+        );
+    }
+
+    #[test]
+    fn package_imports() {
+        let dir = std::env::temp_dir().join("phy_package_imports");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("lib.arc"), "pub var version = 2\n").unwrap();
+        std::fs::write(dir.join("shapes.arc"), "pub fn square(x) { return x * x }\n").unwrap();
 
         let code = "
-var a = true
-var b = 42
-if a and b == 41 or b == 42 and false {} else { b = 45 }
-b
+import geo
+import geo.shapes
+shapes.square(geo.version)
 ";
-        assert_eq!(lex_parse_interp(code).unwrap(), 45.into());
+        let res = lex_parse_interp_with(code, |interp| {
+            interp.packages.insert("geo".into(), dir.clone());
+        });
+        assert_eq!(res.unwrap(), 4.into());
+
+        let res = lex_parse_interp_with("import geo.circles", |interp| {
+            interp.packages.insert("geo".into(), dir.clone());
+        });
+        assert_eq!(res.err().unwrap().err, InterpErr::ModuleNotFound("geo.circles".into()));
+
+        // Resolved once, and only when imported
+        struct Resolver(PathBuf, Rc<Cell<usize>>);
+
+        impl PackageResolver for Resolver {
+            fn resolve(&mut self, name: &str) -> Option<Result<PathBuf, String>> {
+                self.1.set(self.1.get() + 1);
+
+                match name {
+                    "geo" => Some(Ok(self.0.clone())),
+                    "net" => Some(Err("offline".into())),
+                    _ => None,
+                }
+            }
+        }
+
+        let calls = Rc::new(Cell::new(0));
+        let resolver = Resolver(dir.clone(), calls.clone());
+        let res = lex_parse_interp_with(code, |interp| interp.package_resolver = Some(Box::new(resolver)));
+        assert_eq!(res.unwrap(), 4.into());
+        assert_eq!(calls.get(), 1);
+
+        let resolver = Resolver(dir.clone(), calls.clone());
+        let res = lex_parse_interp_with("import net", |interp| interp.package_resolver = Some(Box::new(resolver)));
+        assert_eq!(res.err().unwrap().err, InterpErr::Package("net".into(), "offline".into()));
+
+        let resolver = Resolver(dir.clone(), calls.clone());
+        let res = lex_parse_interp_with("1", |interp| interp.package_resolver = Some(Box::new(resolver)));
+        assert_eq!(res.unwrap(), 1.into());
+        assert_eq!(calls.get(), 2);
+
+        let _ = std::fs::remove_dir_all(&dir);
     }
 
     #[test]
-    fn while_stmt() {
+    fn return_outside_fn() {
         let code = "
-var a = 0
-while a < 5 {
-    a = a + 1
+fn double(x) { return x * 2 }
+var a = double(3)
+if a > 5 {
+    return a
 }
-a
 ";
-        assert_eq!(lex_parse_interp(code).unwrap(), 5.into());
+        let err = lex_parse_interp(code).err().unwrap();
+        assert_eq!(err.err, InterpErr::ReturnOutsideFn);
+        assert_eq!(err.loc.map(|l| l.start), code.find("return a"));
     }
 
     #[test]
-    fn for_stmt() {
-        let code = "
-var a = 0
-for i in 5 { a = a + i }
-a
-";
-        assert_eq!(lex_parse_interp(code).unwrap(), 10.into());
+    fn numeric_operations() {
+        assert_eq!(lex_parse_interp("7 / 2 + 7 % 3 * 2").unwrap(), 5.into());
+        assert_eq!(lex_parse_interp("7 / 2.0 - 1 % 0.75").unwrap(), 3.25.into());
+        assert_eq!(lex_parse_interp("2 < 3.5 == (1.0 >= 1)").unwrap(), true.into());
 
-        let code = "
-var a = 0
-for i in 5..10 { a = a + i }
-a
-";
-        assert_eq!(lex_parse_interp(code).unwrap(), 35.into());
+        // Not numbers and failing int operations take the generic path,
+        // which reports them on the operation
+        assert_eq!(lex_parse_interp("\"ab\" * 2").unwrap(), String::from("abab").into());
+        assert_eq!(lex_parse_interp("1.0 / 0").unwrap(), f64::INFINITY.into());
+
+        let code = "var a = 1\nprint(a % 0)";
+        let err = lex_parse_interp(code).err().unwrap();
+        assert_eq!(err.err, InterpErr::OperationEvaluation("division by zero".into()));
+        assert_eq!(err.loc, Some(Loc::new(16, 21)));
+
+        let code = "var big = 9223372036854775807\nbig + 1";
+        let err = lex_parse_interp(code).err().unwrap();
+        assert_eq!(err.err, InterpErr::OperationEvaluation("int overflow in operation '+'".into()));
+        assert_eq!(err.loc, Some(Loc::new(30, 37)));
+
+        assert_eq!(lex_parse_interp("-9223372036854775808 + 1").unwrap(), (i64::MIN + 1).into());
+
+        assert_eq!(
+            lex_parse_interp("-(-9223372036854775808)").err().unwrap().err,
+            InterpErr::Negation("int overflow in operation '-'".into())
+        );
+
+        for code in ["1 / 0", "-9223372036854775807 - 2", "4611686018427387904 * 2"] {
+            assert!(matches!(
+                lex_parse_interp(code).err().unwrap().err,
+                InterpErr::OperationEvaluation(_)
+            ));
+        }
     }
 
     #[test]
-    fn functions() {
+    fn cached_callees() {
+        // Declared closer to the call site after it was cached
         let code = "
-var res
-fn add(a, b) {
-    res = a + b
+fn twice(x) { return x * 2 }
+var res = 0
+{
+    fn apply(x) { return twice(x) }
+    res = apply(1)
+    fn twice(x) { return x * 3 }
+    res = res + apply(1)
 }
-add(5, 6)
 res
 ";
-        assert_eq!(lex_parse_interp(code).unwrap(), 11.into());
-    }
+        assert_eq!(lex_parse_interp(code).unwrap(), 5.into());
 
-    #[test]
-    fn first_class_fn() {
+        // Assigned, and shadowed by a parameter
         let code = "
-fn add(a, b) { return a+b }
-var c = add
-c(1, 2)
+fn one() { return 1 }
+fn two() { return 2 }
+fn call(f) { return f() }
+var res = 0
+var i = 0
+while i < 2 {
+    res = res * 10 + one() + call(two)
+    one = two
+    i = i + 1
+}
+res
 ";
-        assert_eq!(lex_parse_interp(code).unwrap(), 3.into());
+        assert_eq!(lex_parse_interp(code).unwrap(), 34.into());
     }
 
     #[test]
-    fn recurs_and_break_fn() {
-        let code = "
-fn fib(n) {
-    if n <= 1 { return n }
+    fn display_config() {
+        let code = "print(0.1 + 0.2, 1. / 3., 123456.789, 0.000001234, 2., -0.5)";
+        let print = |config: DisplayConfig| {
+            let output = SharedOutput::default();
+            lex_parse_interp_with(code, |interp| {
+                interp.output = Box::new(output.clone());
+                interp.display = config;
+            })
+            .unwrap();
+            output.text()
+        };
 
-    return fib(n-2) + fib(n-1)
-}
+        assert_eq!(
+            print(DisplayConfig::default()),
+            "0.30000000000000004 0.3333333333333333 123456.789 0.000001234 2 -0.5\n"
+        );
 
-fib(20)
-";
-        assert_eq!(lex_parse_interp(code).unwrap(), 6765.into());
-    }
+        let digits = DisplayConfig { max_digits: Some(6), sci_threshold: None };
+        assert_eq!(print(digits), "0.3 0.333333 123457 0.000001234 2 -0.5\n");
 
-    #[test]
-    fn closure_env() {
-        let code = "
-fn makeCounter() {
-  var i = 0
-  fn count() {
-    i = i + 1
-    return i
-  }
+        let sci = DisplayConfig { max_digits: None, sci_threshold: Some(5) };
+        assert_eq!(print(sci), "0.30000000000000004 0.3333333333333333 1.23456789e5 1.234e-6 2 -0.5\n");
 
-  return count
-}
+        let both = DisplayConfig { max_digits: Some(3), sci_threshold: Some(4) };
+        assert_eq!(print(both), "0.3 0.333 1.23e5 1.23e-6 2 -0.5\n");
 
-var counter = makeCounter()
-var a = 0
-a = counter()
-a = counter()
-a
-";
-        assert_eq!(lex_parse_interp(code).unwrap(), 2.into());
+        // Rounding can change the exponent
+        assert_eq!(both.format_real(99999.), "1e5");
+        assert_eq!(digits.format_real(f64::NAN), "NaN");
     }
 }
@@ -3,7 +3,11 @@ pub mod values;
 pub mod environment;
 pub mod callable;
 pub mod native_functions;
+pub mod host_object;
+pub mod units;
+pub mod trace;
 
+#[cfg(test)]
 mod utils;
 
 extern crate frontend;
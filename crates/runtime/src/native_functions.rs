@@ -1,14 +1,30 @@
 use colored::*;
 use ecow::EcoString;
-use std::{fmt, rc::Rc, time::{SystemTime, UNIX_EPOCH}};
+use std::{
+    cell::RefCell,
+    fmt, fs,
+    io::Read,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 use thiserror::Error;
 
 use crate::{
     callable::Callable,
-    interpreter::Interpreter,
-    values::RtVal,
+    environment::Env,
+    interpreter::{InterpErr, Interpreter},
+    values::{
+        item_index, slice_range, Actor, Channel, Complex, DisplayConfig, Iter, IterSource, IterStage, List, Matrix, RtVal,
+        Vector,
+    },
+};
+use frontend::{lexer::Lexer, parser::Parser};
+use tools::{
+    encoding::{decode_base64, decode_hex, encode_base64, encode_hex},
+    messages::{localize, text, Arg, Locale, Message, Translate},
+    results::{PhyReport, PhyResult},
 };
-use tools::results::{PhyReport, PhyResult};
 
 
 pub type NativeFnRes = Result<RtVal, PhyResult<NativeFnErr>>;
@@ -18,6 +34,31 @@ pub struct PhyNativeFn {
     pub func: Rc<dyn Callable<NativeFnErr>>,
 }
 
+impl PhyNativeFn {
+    // Natives have no parameter names, only their types. Optional ones are
+    // followed by '?' and variadic natives end with '...'
+    pub fn signature(&self) -> String {
+        let (min, max) = (self.func.arity(), self.func.max_arity());
+
+        let mut params: Vec<String> = (0..max.unwrap_or(min))
+            .map(|i| {
+                let ty = self.func.params_type().get(i).copied().unwrap_or("any");
+
+                match i < min {
+                    true => ty.to_string(),
+                    false => format!("{}?", ty),
+                }
+            })
+            .collect();
+
+        if max.is_none() {
+            params.push("...".into());
+        }
+
+        format!("native fn {}({})", self.name, params.join(", "))
+    }
+}
+
 impl fmt::Debug for PhyNativeFn {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "<native fn>")
@@ -41,11 +82,139 @@ impl fmt::Display for PhyNativeFn {
 pub enum NativeFnErr {
     #[error("time access failed")]
     GetTime,
+
+    #[error("failed to write to output: {0}")]
+    WriteOutput(String),
+
+    #[error("output closed")]
+    OutputClosed,
+
+    #[error("in evaluated code: {0}")]
+    Eval(String),
+
+    #[error("{0}")]
+    Io(String),
+
+    #[error("file system access is disabled in sandbox mode")]
+    Sandboxed,
+
+    #[error("{0}")]
+    Dimension(String),
+
+    #[error("can't convert {0} to int")]
+    NonFiniteToInt(String),
+
+    #[error("int overflow in operation '{0}'")]
+    IntOverflow(String),
+
+    #[error("number of digits must be between 0 and {1}, found {0}")]
+    DigitsRange(i64, usize),
+
+    #[error("can't parse '{0}' as {1}")]
+    Parse(String, String),
+
+    #[error("base must be between 2 and 36, found {0}")]
+    WrongBase(i64),
+
+    #[error("{0}")]
+    Repeat(String),
+
+    #[error("length must be positive, found {0}")]
+    NegativeLength(i64),
+
+    #[error("index {0} out of range for length {1}")]
+    IndexOutOfRange(i64, usize),
+
+    #[error("can't pop from an empty list")]
+    PopEmpty,
+
+    #[error("count must be positive, found {0}")]
+    NegativeCount(i64),
+
+    #[error("{0} is not a valid char code point")]
+    InvalidCodePoint(i64),
+
+    #[error("bytes must be ints between 0 and 255, found {0}")]
+    ByteValue(String),
+
+    #[error("bytes aren't valid utf-8 text")]
+    InvalidUtf8,
+
+    #[error("receiving from an empty channel, no task left to send a value")]
+    Deadlock,
+
+    // Failure of arc code called by the native, reported with its location
+    #[error("{}", .0.err)]
+    Callback(Box<PhyResult<InterpErr>>),
+
+    #[error("can't watch variable: {0}")]
+    Watch(String),
 }
 
 impl PhyReport for NativeFnErr {
-    fn get_err_msg(&self) -> String {
-        format!("{} {}", "Function error:".red(), self)
+    fn get_err_msg(&self, locale: &dyn Locale) -> String {
+        format!(
+            "{} {}",
+            text(locale, "label.native", "Function error:").red(),
+            localize(locale, self)
+        )
+    }
+}
+
+impl Translate for NativeFnErr {
+    fn category(&self) -> &'static str {
+        "native"
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            NativeFnErr::GetTime => "GetTime",
+            NativeFnErr::WriteOutput(..) => "WriteOutput",
+            NativeFnErr::OutputClosed => "OutputClosed",
+            NativeFnErr::Eval(..) => "Eval",
+            NativeFnErr::Io(..) => "Io",
+            NativeFnErr::Sandboxed => "Sandboxed",
+            NativeFnErr::Dimension(..) => "Dimension",
+            NativeFnErr::NonFiniteToInt(..) => "NonFiniteToInt",
+            NativeFnErr::IntOverflow(..) => "IntOverflow",
+            NativeFnErr::DigitsRange(..) => "DigitsRange",
+            NativeFnErr::Parse(..) => "Parse",
+            NativeFnErr::WrongBase(..) => "WrongBase",
+            NativeFnErr::Repeat(..) => "Repeat",
+            NativeFnErr::NegativeLength(..) => "NegativeLength",
+            NativeFnErr::IndexOutOfRange(..) => "IndexOutOfRange",
+            NativeFnErr::PopEmpty => "PopEmpty",
+            NativeFnErr::NegativeCount(..) => "NegativeCount",
+            NativeFnErr::InvalidCodePoint(..) => "InvalidCodePoint",
+            NativeFnErr::ByteValue(..) => "ByteValue",
+            NativeFnErr::InvalidUtf8 => "InvalidUtf8",
+            NativeFnErr::Deadlock => "Deadlock",
+            NativeFnErr::Callback(..) => "Callback",
+            NativeFnErr::Watch(..) => "Watch",
+        }
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        match self {
+            NativeFnErr::WriteOutput(v)
+            | NativeFnErr::Eval(v)
+            | NativeFnErr::Io(v)
+            | NativeFnErr::Dimension(v)
+            | NativeFnErr::NonFiniteToInt(v)
+            | NativeFnErr::IntOverflow(v)
+            | NativeFnErr::Repeat(v)
+            | NativeFnErr::ByteValue(v)
+            | NativeFnErr::Watch(v) => vec![Arg::text(v)],
+            NativeFnErr::WrongBase(v)
+            | NativeFnErr::NegativeLength(v)
+            | NativeFnErr::NegativeCount(v)
+            | NativeFnErr::InvalidCodePoint(v) => vec![Arg::text(v)],
+            NativeFnErr::Parse(a, b) => vec![Arg::text(a), Arg::text(b)],
+            NativeFnErr::IndexOutOfRange(a, b) => vec![Arg::text(a), Arg::text(b)],
+            NativeFnErr::DigitsRange(a, b) => vec![Arg::text(a), Arg::text(b)],
+            NativeFnErr::Callback(e) => vec![Arg::message(&e.err)],
+            _ => vec![],
+        }
     }
 }
 
@@ -65,3 +234,1992 @@ impl Callable<NativeFnErr> for NativeClock {
         }
     }
 }
+
+// Print
+pub struct NativePrint;
+
+impl Callable<NativeFnErr> for NativePrint {
+    fn arity(&self) -> usize {
+        0
+    }
+
+    fn max_arity(&self) -> Option<usize> {
+        None
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let values: Vec<String> = args.iter().map(|a| a.to_print_string(&interpreter.display)).collect();
+
+        interpreter
+            .write_line(&values.join(" "))
+            .map_err(|e| PhyResult::new(e, None))?;
+
+        Ok(RtVal::new_null())
+    }
+}
+
+// Globals
+pub struct NativeGlobals;
+
+impl Callable<NativeFnErr> for NativeGlobals {
+    fn arity(&self) -> usize {
+        0
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, _: Vec<RtVal>) -> NativeFnRes {
+        Ok(sorted_names(interpreter.globals.borrow().get_visible_names().iter()))
+    }
+}
+
+// Locals
+pub struct NativeLocals;
+
+impl Callable<NativeFnErr> for NativeLocals {
+    fn arity(&self) -> usize {
+        0
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, _: Vec<RtVal>) -> NativeFnRes {
+        Ok(sorted_names(interpreter.env.borrow().vars.keys()))
+    }
+}
+
+// Members reachable with '.', the names of a module or the fields and
+// methods of a host object. Empty for the values without any
+pub struct NativeDir;
+
+impl Callable<NativeFnErr> for NativeDir {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let names: Vec<EcoString> = match &args[0] {
+            RtVal::ModuleVal(m) => m.members.keys().cloned().collect(),
+            RtVal::HostVal(h) => h.members().into_iter().map(EcoString::from).collect(),
+            RtVal::VectorVal(v) => Vector::COMPONENTS[..v.borrow().value.len()]
+                .iter()
+                .map(|c| EcoString::from(*c))
+                .collect(),
+            RtVal::ComplexVal(_) => vec!["re".into(), "im".into()],
+            RtVal::TaskVal(_) => vec!["done".into()],
+            _ => vec![],
+        };
+
+        Ok(sorted_names(names.iter()))
+    }
+}
+
+// List of strings from variable names, sorted to be reproducible
+fn sorted_names<'a>(names: impl Iterator<Item = &'a EcoString>) -> RtVal {
+    let mut names: Vec<&EcoString> = names.collect();
+    names.sort();
+
+    names.into_iter().map(|n| n.clone().into()).collect::<Vec<RtVal>>().into()
+}
+
+// Eval
+// Runs the code in the current environment, or in a child one if the
+// second argument is true so that the declarations don't leak
+pub struct NativeEval;
+
+impl Callable<NativeFnErr> for NativeEval {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn max_arity(&self) -> Option<usize> {
+        Some(2)
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str", "bool"]
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let code = match &args[0] {
+            RtVal::StrVal(s) => s.borrow().value.clone(),
+            _ => unreachable!(),
+        };
+
+        let isolated = match args.get(1) {
+            Some(RtVal::BoolVal(b)) => b.borrow().value,
+            _ => false,
+        };
+
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize(&code).map_err(|e| {
+            let msgs: Vec<String> = e.iter().map(|e| e.err.to_string()).collect();
+            PhyResult::new(NativeFnErr::Eval(msgs.join(", ")), None)
+        })?;
+
+        let mut parser = Parser::default();
+        let nodes = parser.parse(tokens).map_err(|e| {
+            let msgs: Vec<String> = e.iter().map(|e| e.err.to_string()).collect();
+            PhyResult::new(NativeFnErr::Eval(msgs.join(", ")), None)
+        })?;
+
+        let res = match isolated {
+            true => {
+                let env = Env::new(Some(interpreter.env.clone()));
+                interpreter.execute_block_stmt(&nodes, Rc::new(RefCell::new(env)))
+            }
+            false => interpreter.interpret(&nodes),
+        };
+        interpreter.forget_callees();
+
+        res.map_err(|e| PhyResult::new(NativeFnErr::Eval(e.err.to_string()), None))
+    }
+}
+
+// Function reflection
+pub struct NativeFnName;
+
+impl Callable<NativeFnErr> for NativeFnName {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["fn|native fn"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        match &args[0] {
+            RtVal::FuncVal(f) => Ok(f.name.clone().into()),
+            RtVal::NativeFnVal(f) => Ok(f.name.clone().into()),
+            _ => unreachable!(),
+        }
+    }
+}
+
+// For variadic natives, it is the minimum number of arguments
+pub struct NativeFnArity;
+
+impl Callable<NativeFnErr> for NativeFnArity {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["fn|native fn"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        match &args[0] {
+            RtVal::FuncVal(f) => Ok((f.arity() as i64).into()),
+            RtVal::NativeFnVal(f) => Ok((f.func.arity() as i64).into()),
+            _ => unreachable!(),
+        }
+    }
+}
+
+// Natives don't have named parameters, the list is empty for them
+pub struct NativeFnParams;
+
+impl Callable<NativeFnErr> for NativeFnParams {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["fn|native fn"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        match &args[0] {
+            RtVal::FuncVal(f) => Ok(f
+                .params
+                .iter()
+                .map(|p| p.clone().into())
+                .collect::<Vec<RtVal>>()
+                .into()),
+            RtVal::NativeFnVal(_) => Ok(Vec::<RtVal>::new().into()),
+            _ => unreachable!(),
+        }
+    }
+}
+
+
+// ------
+//  Math
+// ------
+pub struct NativeSqrt;
+
+impl Callable<NativeFnErr> for NativeSqrt {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["int|real"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        Ok(get_real(&args[0]).sqrt().into())
+    }
+}
+
+pub struct NativeAbs;
+
+impl Callable<NativeFnErr> for NativeAbs {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["int|real|complex"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        match &args[0] {
+            RtVal::IntVal(i) => i.borrow().value.checked_abs().map(RtVal::from).ok_or_else(|| {
+                PhyResult::new(NativeFnErr::IntOverflow("abs".into()), None)
+            }),
+            RtVal::ComplexVal(c) => Ok(c.borrow().abs().into()),
+            v => Ok(get_real(v).abs().into()),
+        }
+    }
+}
+
+// Int if both are ints and the exponent is positive, real otherwise
+pub struct NativePow;
+
+impl Callable<NativeFnErr> for NativePow {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["int|real", "int|real"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        match (&args[0], &args[1]) {
+            (RtVal::IntVal(x), RtVal::IntVal(e)) if e.borrow().value >= 0 => {
+                // Past 64, only 0, 1 and -1 don't overflow and the parity is enough
+                let e = e.borrow().value;
+                let e = if e > 64 { 64 + e % 2 } else { e };
+
+                x.borrow()
+                    .value
+                    .checked_pow(e as u32)
+                    .map(RtVal::from)
+                    .ok_or_else(|| PhyResult::new(NativeFnErr::IntOverflow("pow".into()), None))
+            }
+            (x, e) => Ok(get_real(x).powf(get_real(e)).into()),
+        }
+    }
+}
+
+// Argument of a complex number, in radians
+pub struct NativeArg;
+
+impl Callable<NativeFnErr> for NativeArg {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["int|real|complex"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        Ok(get_complex(&args[0]).arg().into())
+    }
+}
+
+pub struct NativeConj;
+
+impl Callable<NativeFnErr> for NativeConj {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["int|real|complex"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        Ok(get_complex(&args[0]).conj().into())
+    }
+}
+
+// Only called on int, real or complex values, checked by the parameters type
+fn get_complex(value: &RtVal) -> Complex {
+    match value {
+        RtVal::ComplexVal(c) => *c.borrow(),
+        v => Complex::from(get_real(v)),
+    }
+}
+
+// Only called on int or real values, checked by the parameters type
+fn get_real(value: &RtVal) -> f64 {
+    value.get_real().unwrap()
+}
+
+// ---------
+//  Strings
+// ---------
+// Lengths and indices count the Unicode scalar values, the chars, and
+// neither the utf-8 bytes nor the graphemes: an 'e' followed by a combining
+// accent is 2 chars. 'bytes(s)' gives the bytes
+pub struct NativeUpper;
+
+impl Callable<NativeFnErr> for NativeUpper {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        Ok(get_str(&args[0]).to_uppercase().into())
+    }
+}
+
+pub struct NativeLower;
+
+impl Callable<NativeFnErr> for NativeLower {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        Ok(get_str(&args[0]).to_lowercase().into())
+    }
+}
+
+// Number of characters
+pub struct NativeStrLen;
+
+impl Callable<NativeFnErr> for NativeStrLen {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        Ok((get_str(&args[0]).chars().count() as i64).into())
+    }
+}
+
+// Characters from start to end excluded, like 's[start..end]': the bounds
+// are clamped to the string and the negative ones count from the end,
+// slice("abc", 1, 10) == "bc" and slice("abc", -2, 3) == "bc"
+pub struct NativeStrSlice;
+
+impl Callable<NativeFnErr> for NativeStrSlice {
+    fn arity(&self) -> usize {
+        3
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str", "int", "int"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let chars: Vec<char> = get_str(&args[0]).chars().collect();
+        let range = slice_range(Some(get_int(&args[1])), Some(get_int(&args[2])), chars.len());
+
+        Ok(chars[range].iter().collect::<String>().into())
+    }
+}
+
+// Char at the index, counted in chars like 's[i]': str.get("abc", 1) == 'b'
+// and str.get("abc", -1) == 'c'
+pub struct NativeStrGet;
+
+impl Callable<NativeFnErr> for NativeStrGet {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str", "int"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let chars: Vec<char> = get_str(&args[0]).chars().collect();
+        let idx = get_int(&args[1]);
+
+        item_index(idx, chars.len())
+            .map(|i| chars[i].into())
+            .ok_or_else(|| PhyResult::new(NativeFnErr::IndexOutOfRange(idx, chars.len()), None))
+    }
+}
+
+pub struct NativeStrChars;
+
+impl Callable<NativeFnErr> for NativeStrChars {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        Ok(get_str(&args[0]).chars().map(RtVal::from).collect::<Vec<RtVal>>().into())
+    }
+}
+
+// Reverses the chars, the combining marks end up before their letter
+pub struct NativeStrReverse;
+
+impl Callable<NativeFnErr> for NativeStrReverse {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        Ok(get_str(&args[0]).chars().rev().collect::<String>().into())
+    }
+}
+
+// Only called on str values, checked by the parameters type
+fn get_str(value: &RtVal) -> EcoString {
+    match value {
+        RtVal::StrVal(s) => s.borrow().value.clone(),
+        _ => unreachable!(),
+    }
+}
+
+// -------
+//  Chars
+// -------
+// Unicode code point of the char: ord('a') == 97
+pub struct NativeOrd;
+
+impl Callable<NativeFnErr> for NativeOrd {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["char"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        match &args[0] {
+            RtVal::CharVal(c) => Ok((c.borrow().value as i64).into()),
+            _ => unreachable!(),
+        }
+    }
+}
+
+// Surrogates and values above 0x10FFFF aren't chars
+pub struct NativeChr;
+
+impl Callable<NativeFnErr> for NativeChr {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["int"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let code = get_int(&args[0]);
+
+        u32::try_from(code)
+            .ok()
+            .and_then(char::from_u32)
+            .map(RtVal::from)
+            .ok_or_else(|| PhyResult::new(NativeFnErr::InvalidCodePoint(code), None))
+    }
+}
+
+// -------
+//  Bytes
+// -------
+// From a list of ints, the utf-8 encoding of a str or a number of zeros:
+// bytes(list), bytes("abc") or bytes(16)
+pub struct NativeBytes;
+
+impl Callable<NativeFnErr> for NativeBytes {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["list|str|int"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let byte_err = |v: String| PhyResult::new(NativeFnErr::ByteValue(v), None);
+
+        match &args[0] {
+            RtVal::ListVal(l) => l
+                .borrow()
+                .value
+                .iter()
+                .map(|v| match v {
+                    RtVal::IntVal(i) => u8::try_from(i.borrow().value).map_err(|_| byte_err(v.to_string())),
+                    v => Err(byte_err(v.get_type_name().into())),
+                })
+                .collect::<Result<Vec<u8>, _>>()
+                .map(RtVal::from),
+            RtVal::StrVal(s) => Ok(s.borrow().value.as_bytes().to_vec().into()),
+            RtVal::IntVal(i) => {
+                let len = usize::try_from(i.borrow().value)
+                    .map_err(|_| PhyResult::new(NativeFnErr::NegativeLength(i.borrow().value), None))?;
+                Ok(vec![0u8; len].into())
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+pub struct NativeBytesLen;
+
+impl Callable<NativeFnErr> for NativeBytesLen {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["bytes"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        Ok((get_bytes(&args[0]).len() as i64).into())
+    }
+}
+
+// Byte at the index, as an int
+pub struct NativeBytesGet;
+
+impl Callable<NativeFnErr> for NativeBytesGet {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["bytes", "int"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let bytes = get_bytes(&args[0]);
+        let idx = get_int(&args[1]);
+
+        item_index(idx, bytes.len())
+            .map(|i| (bytes[i] as i64).into())
+            .ok_or_else(|| PhyResult::new(NativeFnErr::IndexOutOfRange(idx, bytes.len()), None))
+    }
+}
+
+// Bounds clamped like for str.slice
+pub struct NativeBytesSlice;
+
+impl Callable<NativeFnErr> for NativeBytesSlice {
+    fn arity(&self) -> usize {
+        3
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["bytes", "int", "int"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let bytes = get_bytes(&args[0]);
+        let range = slice_range(Some(get_int(&args[1])), Some(get_int(&args[2])), bytes.len());
+
+        Ok(bytes[range].to_vec().into())
+    }
+}
+
+// Decodes utf-8 text, the opposite of bytes(str)
+pub struct NativeBytesToStr;
+
+impl Callable<NativeFnErr> for NativeBytesToStr {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["bytes"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        String::from_utf8(get_bytes(&args[0]))
+            .map(|s| s.into())
+            .map_err(|_| PhyResult::new(NativeFnErr::InvalidUtf8, None))
+    }
+}
+
+// Lowercase, without separators: bin.to_hex(bytes("hi")) == "6869"
+pub struct NativeToHex;
+
+impl Callable<NativeFnErr> for NativeToHex {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["bytes"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        Ok(encode_hex(&get_bytes(&args[0])).into())
+    }
+}
+
+pub struct NativeFromHex;
+
+impl Callable<NativeFnErr> for NativeFromHex {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let s = get_str(&args[0]);
+
+        decode_hex(&s)
+            .map(RtVal::from)
+            .ok_or_else(|| PhyResult::new(NativeFnErr::Parse(s.to_string(), "hex".into()), None))
+    }
+}
+
+// Standard alphabet, with padding
+pub struct NativeToBase64;
+
+impl Callable<NativeFnErr> for NativeToBase64 {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["bytes"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        Ok(encode_base64(&get_bytes(&args[0])).into())
+    }
+}
+
+pub struct NativeFromBase64;
+
+impl Callable<NativeFnErr> for NativeFromBase64 {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let s = get_str(&args[0]);
+
+        decode_base64(&s)
+            .map(RtVal::from)
+            .ok_or_else(|| PhyResult::new(NativeFnErr::Parse(s.to_string(), "base64".into()), None))
+    }
+}
+
+// Only called on bytes values, checked by the parameters type
+fn get_bytes(value: &RtVal) -> Vec<u8> {
+    match value {
+        RtVal::BytesVal(b) => b.borrow().value.clone(),
+        _ => unreachable!(),
+    }
+}
+
+// -------
+//  Lists
+// -------
+// Lists are shared: assigning a list or passing it to a function doesn't
+// copy it, so mutating it is seen through all its references. A copy is made
+// with a full slice, l[..]
+pub struct NativeListNew;
+
+impl Callable<NativeFnErr> for NativeListNew {
+    fn arity(&self) -> usize {
+        0
+    }
+
+    fn call(&self, _: &mut Interpreter, _: Vec<RtVal>) -> NativeFnRes {
+        Ok(Vec::<RtVal>::new().into())
+    }
+}
+
+pub struct NativeListLen;
+
+impl Callable<NativeFnErr> for NativeListLen {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["list"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        Ok((get_list(&args[0]).borrow().value.len() as i64).into())
+    }
+}
+
+// Negative indices count from the end, like with l[i]
+pub struct NativeListGet;
+
+impl Callable<NativeFnErr> for NativeListGet {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["list", "int"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let list = get_list(&args[0]);
+        let list = list.borrow();
+        let idx = get_int(&args[1]);
+
+        item_index(idx, list.value.len())
+            .map(|i| list.value[i].clone())
+            .ok_or_else(|| {
+                PhyResult::new(NativeFnErr::IndexOutOfRange(idx, list.value.len()), None)
+            })
+    }
+}
+
+pub struct NativeListPush;
+
+impl Callable<NativeFnErr> for NativeListPush {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["list", "any"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        get_list(&args[0]).borrow_mut().value.push(args[1].clone());
+
+        Ok(RtVal::new_null())
+    }
+}
+
+// Removes and returns the last element
+pub struct NativeListPop;
+
+impl Callable<NativeFnErr> for NativeListPop {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["list"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        get_list(&args[0])
+            .borrow_mut()
+            .value
+            .pop()
+            .ok_or_else(|| PhyResult::new(NativeFnErr::PopEmpty, None))
+    }
+}
+
+// The value ends up at the index, which can be the length to insert at the
+// end. Negative indices count from the end, so -1 inserts before the last
+// element
+pub struct NativeListInsert;
+
+impl Callable<NativeFnErr> for NativeListInsert {
+    fn arity(&self) -> usize {
+        3
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["list", "int", "any"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let list = get_list(&args[0]);
+        let mut list = list.borrow_mut();
+        let idx = get_int(&args[1]);
+        let len = list.value.len();
+
+        let i = match idx == len as i64 {
+            true => len,
+            false => item_index(idx, len)
+                .ok_or_else(|| PhyResult::new(NativeFnErr::IndexOutOfRange(idx, len), None))?,
+        };
+        list.value.insert(i, args[2].clone());
+
+        Ok(RtVal::new_null())
+    }
+}
+
+// Removes and returns the element at the index
+pub struct NativeListRemove;
+
+impl Callable<NativeFnErr> for NativeListRemove {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["list", "int"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let list = get_list(&args[0]);
+        let mut list = list.borrow_mut();
+        let idx = get_int(&args[1]);
+        let len = list.value.len();
+
+        item_index(idx, len)
+            .map(|i| list.value.remove(i))
+            .ok_or_else(|| PhyResult::new(NativeFnErr::IndexOutOfRange(idx, len), None))
+    }
+}
+
+pub struct NativeListClear;
+
+impl Callable<NativeFnErr> for NativeListClear {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["list"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        get_list(&args[0]).borrow_mut().value.clear();
+
+        Ok(RtVal::new_null())
+    }
+}
+
+// Pairs are two elements lists, split by the loops, 'for i, v in
+// enumerate(l)', and taken apart by indexing in comprehensions:
+// [p[0] * p[1] for p in enumerate(l)]. enumerate(["a", "b"]) gives
+// [[0, "a"], [1, "b"]]
+pub struct NativeEnumerate;
+
+impl Callable<NativeFnErr> for NativeEnumerate {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["list"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let list = get_list(&args[0]);
+        let pairs = list
+            .borrow()
+            .value
+            .iter()
+            .enumerate()
+            .map(|(i, v)| vec![(i as i64).into(), v.clone()].into())
+            .collect::<Vec<RtVal>>();
+
+        Ok(pairs.into())
+    }
+}
+
+// Stops at the end of the shortest list
+pub struct NativeZip;
+
+impl Callable<NativeFnErr> for NativeZip {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["list", "list"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let (a, b) = (get_list(&args[0]), get_list(&args[1]));
+        let pairs = a
+            .borrow()
+            .value
+            .iter()
+            .zip(b.borrow().value.iter())
+            .map(|(x, y)| vec![x.clone(), y.clone()].into())
+            .collect::<Vec<RtVal>>();
+
+        Ok(pairs.into())
+    }
+}
+
+// Only called on list values, checked by the parameters type
+fn get_list(value: &RtVal) -> Rc<RefCell<List>> {
+    match value {
+        RtVal::ListVal(l) => l.clone(),
+        _ => unreachable!(),
+    }
+}
+
+// Only called on int values, checked by the parameters type
+fn get_int(value: &RtVal) -> i64 {
+    match value {
+        RtVal::IntVal(i) => i.borrow().value,
+        _ => unreachable!(),
+    }
+}
+
+// -----------
+//  Iterators
+// -----------
+// The stages take a list or an iterator and give a new iterator, nothing
+// runs before 'collect' or 'sum':
+// iter.sum(iter.take(iter.filter(iter.range(0, 1000000), even), 10))
+pub struct NativeIterRange;
+
+impl Callable<NativeFnErr> for NativeIterRange {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["int", "int"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let source = IterSource::Range(get_int(&args[0]), get_int(&args[1]));
+
+        Ok(RtVal::IterVal(Rc::new(Iter { source, stages: vec![] })))
+    }
+}
+
+pub struct NativeIterMap;
+
+impl Callable<NativeFnErr> for NativeIterMap {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["list|iter", "fn|native fn"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        Ok(with_stage(&args[0], IterStage::Map(args[1].clone())))
+    }
+}
+
+pub struct NativeIterFilter;
+
+impl Callable<NativeFnErr> for NativeIterFilter {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["list|iter", "fn|native fn"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        Ok(with_stage(&args[0], IterStage::Filter(args[1].clone())))
+    }
+}
+
+pub struct NativeIterTake;
+
+impl Callable<NativeFnErr> for NativeIterTake {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["list|iter", "int"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        Ok(with_stage(&args[0], IterStage::Take(get_count(&args[1])?)))
+    }
+}
+
+pub struct NativeIterSkip;
+
+impl Callable<NativeFnErr> for NativeIterSkip {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["list|iter", "int"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        Ok(with_stage(&args[0], IterStage::Skip(get_count(&args[1])?)))
+    }
+}
+
+pub struct NativeIterCollect;
+
+impl Callable<NativeFnErr> for NativeIterCollect {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["list|iter"]
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        get_iter(&args[0])
+            .collect(interpreter)
+            .map(RtVal::from)
+            .map_err(|e| PhyResult::new(NativeFnErr::Callback(Box::new(e)), None))
+    }
+}
+
+// The values are added with '+' starting from 0, the sum of nothing is 0
+pub struct NativeIterSum;
+
+impl Callable<NativeFnErr> for NativeIterSum {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["list|iter"]
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let mut sum = RtVal::from(0);
+
+        get_iter(&args[0])
+            .run(interpreter, |_, v| {
+                sum = sum.operate(&v, "+").map_err(|e| {
+                    PhyResult::new(InterpErr::OperationEvaluation(Message::new(&e)), None)
+                })?;
+
+                Ok(())
+            })
+            .map_err(|e| PhyResult::new(NativeFnErr::Callback(Box::new(e)), None))?;
+
+        Ok(sum)
+    }
+}
+
+// Only called on list and iter values, checked by the parameters type
+fn get_iter(value: &RtVal) -> Iter {
+    match value {
+        RtVal::ListVal(l) => Iter {
+            source: IterSource::List(l.clone()),
+            stages: vec![],
+        },
+        RtVal::IterVal(i) => i.as_ref().clone(),
+        _ => unreachable!(),
+    }
+}
+
+fn with_stage(value: &RtVal, stage: IterStage) -> RtVal {
+    RtVal::IterVal(Rc::new(get_iter(value).with_stage(stage)))
+}
+
+fn get_count(value: &RtVal) -> Result<usize, PhyResult<NativeFnErr>> {
+    let count = get_int(value);
+    usize::try_from(count).map_err(|_| PhyResult::new(NativeFnErr::NegativeCount(count), None))
+}
+
+// -------------
+//  File system
+// -------------
+pub struct NativeReadFile;
+
+impl Callable<NativeFnErr> for NativeReadFile {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str"]
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        check_fs_access(interpreter)?;
+        let path = get_str(&args[0]);
+
+        fs::read_to_string(path.as_str())
+            .map(|content| content.into())
+            .map_err(|e| PhyResult::new(NativeFnErr::Io(format!("can't read '{}': {}", path, e)), None))
+    }
+}
+
+pub struct NativeWriteFile;
+
+impl Callable<NativeFnErr> for NativeWriteFile {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str", "str"]
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        check_fs_access(interpreter)?;
+        let path = get_str(&args[0]);
+
+        fs::write(path.as_str(), get_str(&args[1]).as_str())
+            .map(|_| RtVal::new_null())
+            .map_err(|e| PhyResult::new(NativeFnErr::Io(format!("can't write '{}': {}", path, e)), None))
+    }
+}
+
+pub struct NativeReadFileBytes;
+
+impl Callable<NativeFnErr> for NativeReadFileBytes {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str"]
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        check_fs_access(interpreter)?;
+        let path = get_str(&args[0]);
+
+        fs::read(path.as_str())
+            .map(|content| content.into())
+            .map_err(|e| PhyResult::new(NativeFnErr::Io(format!("can't read '{}': {}", path, e)), None))
+    }
+}
+
+pub struct NativeWriteFileBytes;
+
+impl Callable<NativeFnErr> for NativeWriteFileBytes {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str", "bytes"]
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        check_fs_access(interpreter)?;
+        let path = get_str(&args[0]);
+
+        fs::write(path.as_str(), get_bytes(&args[1]))
+            .map(|_| RtVal::new_null())
+            .map_err(|e| PhyResult::new(NativeFnErr::Io(format!("can't write '{}': {}", path, e)), None))
+    }
+}
+
+// Paths manipulation only works on strings, it's allowed in sandbox mode
+pub struct NativePathJoin;
+
+impl Callable<NativeFnErr> for NativePathJoin {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn max_arity(&self) -> Option<usize> {
+        None
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let mut path = PathBuf::new();
+
+        for arg in &args {
+            let RtVal::StrVal(s) = arg else {
+                return Err(PhyResult::new(
+                    NativeFnErr::Io(format!("path parts must be of type str, found {}", arg.get_type_name())),
+                    None,
+                ));
+            };
+
+            path.push(s.borrow().value.as_str());
+        }
+
+        Ok(path.to_string_lossy().to_string().into())
+    }
+}
+
+// Last component of the path, null if there is none
+pub struct NativePathBasename;
+
+impl Callable<NativeFnErr> for NativePathBasename {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let path = get_str(&args[0]);
+
+        Ok(Path::new(path.as_str())
+            .file_name()
+            .map_or(RtVal::new_null(), |n| n.to_string_lossy().to_string().into()))
+    }
+}
+
+// Extension without the '.', null if there is none
+pub struct NativePathExt;
+
+impl Callable<NativeFnErr> for NativePathExt {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let path = get_str(&args[0]);
+
+        Ok(Path::new(path.as_str())
+            .extension()
+            .map_or(RtVal::new_null(), |e| e.to_string_lossy().to_string().into()))
+    }
+}
+
+pub struct NativePathExists;
+
+impl Callable<NativeFnErr> for NativePathExists {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str"]
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        check_fs_access(interpreter)?;
+
+        Ok(Path::new(get_str(&args[0]).as_str()).exists().into())
+    }
+}
+
+// Sorted names of the entries in the directory
+pub struct NativeListDir;
+
+impl Callable<NativeFnErr> for NativeListDir {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str"]
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        check_fs_access(interpreter)?;
+        let path = get_str(&args[0]);
+        let io_err = |e: std::io::Error| {
+            PhyResult::new(NativeFnErr::Io(format!("can't list '{}': {}", path, e)), None)
+        };
+
+        let mut names = fs::read_dir(path.as_str())
+            .map_err(io_err)?
+            .map(|entry| entry.map(|e| e.file_name().to_string_lossy().to_string()))
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(io_err)?;
+
+        names.sort();
+
+        Ok(names.into_iter().map(RtVal::from).collect::<Vec<RtVal>>().into())
+    }
+}
+
+fn check_fs_access(interpreter: &Interpreter) -> Result<(), PhyResult<NativeFnErr>> {
+    match interpreter.sandboxed {
+        true => Err(PhyResult::new(NativeFnErr::Sandboxed, None)),
+        false => Ok(()),
+    }
+}
+
+// ---------------------
+//  Vectors and matrices
+// ---------------------
+pub struct NativeVec2;
+
+impl Callable<NativeFnErr> for NativeVec2 {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["int|real", "int|real"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        Ok(Vector { value: args.iter().map(get_real).collect() }.into())
+    }
+}
+
+pub struct NativeVec3;
+
+impl Callable<NativeFnErr> for NativeVec3 {
+    fn arity(&self) -> usize {
+        3
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["int|real", "int|real", "int|real"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        Ok(Vector { value: args.iter().map(get_real).collect() }.into())
+    }
+}
+
+// Matrix built from its rows: mat(vec2(1, 0), vec2(0, 1))
+pub struct NativeMat;
+
+impl Callable<NativeFnErr> for NativeMat {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn max_arity(&self) -> Option<usize> {
+        None
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let mut rows: Vec<Vec<f64>> = vec![];
+
+        for (i, arg) in args.iter().enumerate() {
+            let RtVal::VectorVal(v) = arg else {
+                return Err(dimension_err(format!(
+                    "matrix row {} must be of type vector, found {}",
+                    i + 1,
+                    arg.get_type_name()
+                )));
+            };
+
+            rows.push(v.borrow().value.clone());
+        }
+
+        if rows.iter().any(|r| r.len() != rows[0].len()) {
+            return Err(dimension_err("matrix rows must have the same size".into()));
+        }
+
+        Ok(Matrix { value: rows }.into())
+    }
+}
+
+pub struct NativeDot;
+
+impl Callable<NativeFnErr> for NativeDot {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["vector", "vector"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let (v1, v2) = (get_vector(&args[0]), get_vector(&args[1]));
+
+        if v1.value.len() != v2.value.len() {
+            return Err(dimension_err("dot product of vectors of different sizes".into()));
+        }
+
+        Ok(v1.dot(&v2).into())
+    }
+}
+
+pub struct NativeCross;
+
+impl Callable<NativeFnErr> for NativeCross {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["vector", "vector"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let (a, b) = (get_vector(&args[0]).value, get_vector(&args[1]).value);
+
+        if a.len() != 3 || b.len() != 3 {
+            return Err(dimension_err("cross product is only defined for vec3".into()));
+        }
+
+        Ok(Vector {
+            value: vec![
+                a[1] * b[2] - a[2] * b[1],
+                a[2] * b[0] - a[0] * b[2],
+                a[0] * b[1] - a[1] * b[0],
+            ],
+        }
+        .into())
+    }
+}
+
+pub struct NativeNorm;
+
+impl Callable<NativeFnErr> for NativeNorm {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["vector"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let v = get_vector(&args[0]);
+        Ok(v.dot(&v).sqrt().into())
+    }
+}
+
+// Only called on vector values, checked by the parameters type
+fn get_vector(value: &RtVal) -> Vector {
+    match value {
+        RtVal::VectorVal(v) => v.borrow().clone(),
+        _ => unreachable!(),
+    }
+}
+
+fn dimension_err(msg: String) -> PhyResult<NativeFnErr> {
+    PhyResult::new(NativeFnErr::Dimension(msg), None)
+}
+
+// ----------
+//  Rounding
+// ----------
+// Without digits, the result is an int. With digits, it stays a real
+pub struct NativeRound;
+
+impl Callable<NativeFnErr> for NativeRound {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn max_arity(&self) -> Option<usize> {
+        Some(2)
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["int|real", "int"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        match (&args[0], args.get(1)) {
+            (RtVal::IntVal(_), _) => Ok(args[0].copy()),
+            (v, None) => to_int(get_real(v).round()),
+            (v, Some(RtVal::IntVal(d))) => {
+                let factor = 10f64.powi(get_digits(d.borrow().value, MAX_ROUND_DIGITS)? as i32);
+                let value = get_real(v);
+                let scaled = value * factor;
+
+                // A value this large has no decimals left to round
+                match scaled.is_finite() {
+                    true => Ok((scaled.round() / factor).into()),
+                    false => Ok(value.into()),
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+pub struct NativeFloor;
+
+impl Callable<NativeFnErr> for NativeFloor {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["int|real"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        to_int(get_real(&args[0]).floor())
+    }
+}
+
+pub struct NativeCeil;
+
+impl Callable<NativeFnErr> for NativeCeil {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["int|real"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        to_int(get_real(&args[0]).ceil())
+    }
+}
+
+pub struct NativeTrunc;
+
+impl Callable<NativeFnErr> for NativeTrunc {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["int|real"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        to_int(get_real(&args[0]).trunc())
+    }
+}
+
+// String with exactly n decimals: to_fixed(PI, 2) == "3.14"
+pub struct NativeToFixed;
+
+impl Callable<NativeFnErr> for NativeToFixed {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["int|real", "int"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let RtVal::IntVal(d) = &args[1] else {
+            unreachable!()
+        };
+        let digits = get_digits(d.borrow().value, MAX_FIXED_DIGITS)?;
+
+        Ok(format!("{:.*}", digits, get_real(&args[0])).into())
+    }
+}
+
+// Value as written by print, except that the reals always have the fewest
+// digits reading back as the same value: to_str(0.1) == "0.1"
+pub struct NativeToStr;
+
+impl Callable<NativeFnErr> for NativeToStr {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        Ok(args[0].to_print_string(&DisplayConfig::default()).into())
+    }
+}
+
+// Strings and lists are replicated like with '*', the other values give a
+// list of n copies: repeat(0, 3) gives [0, 0, 0]
+pub struct NativeRepeat;
+
+impl Callable<NativeFnErr> for NativeRepeat {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["any", "int"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        args[0]
+            .repeat(get_int(&args[1]))
+            .map_err(|e| PhyResult::new(NativeFnErr::Repeat(e.to_string()), None))
+    }
+}
+
+// Integral reals out of the int range (or not finite) can't be converted
+fn to_int(value: f64) -> NativeFnRes {
+    if !value.is_finite() || value < i64::MIN as f64 || value >= i64::MAX as f64 {
+        return Err(PhyResult::new(NativeFnErr::NonFiniteToInt(value.to_string()), None));
+    }
+
+    Ok((value as i64).into())
+}
+
+// A real has at most 17 significant digits, more can't change the rounding
+const MAX_ROUND_DIGITS: usize = 17;
+const MAX_FIXED_DIGITS: usize = 100;
+
+fn get_digits(digits: i64, max: usize) -> Result<usize, PhyResult<NativeFnErr>> {
+    usize::try_from(digits)
+        .ok()
+        .filter(|d| *d <= max)
+        .ok_or_else(|| PhyResult::new(NativeFnErr::DigitsRange(digits, max), None))
+}
+
+// ---------
+//  Parsing
+// ---------
+// Surrounding whitespaces are ignored, the base is 10 by default
+pub struct NativeParseInt;
+
+impl Callable<NativeFnErr> for NativeParseInt {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn max_arity(&self) -> Option<usize> {
+        Some(2)
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str", "int"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let s = get_str(&args[0]);
+        let base = match args.get(1) {
+            Some(RtVal::IntVal(b)) => b.borrow().value,
+            _ => 10,
+        };
+
+        if !(2..=36).contains(&base) {
+            return Err(PhyResult::new(NativeFnErr::WrongBase(base), None));
+        }
+
+        let kind = match base {
+            10 => "int".to_string(),
+            b => format!("int in base {}", b),
+        };
+
+        i64::from_str_radix(s.trim(), base as u32)
+            .map(|i| i.into())
+            .map_err(|_| PhyResult::new(NativeFnErr::Parse(s.to_string(), kind), None))
+    }
+}
+
+pub struct NativeParseReal;
+
+impl Callable<NativeFnErr> for NativeParseReal {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let s = get_str(&args[0]);
+
+        s.trim()
+            .parse::<f64>()
+            .map(|r| r.into())
+            .map_err(|_| PhyResult::new(NativeFnErr::Parse(s.to_string(), "real".into()), None))
+    }
+}
+
+// -------
+//  Stdin
+// -------
+pub struct NativeStdinReadAll;
+
+impl Callable<NativeFnErr> for NativeStdinReadAll {
+    fn arity(&self) -> usize {
+        0
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, _: Vec<RtVal>) -> NativeFnRes {
+        let mut content = String::new();
+
+        interpreter
+            .input
+            .read_to_string(&mut content)
+            .map(|_| content.into())
+            .map_err(|e| PhyResult::new(NativeFnErr::Io(format!("can't read stdin: {}", e)), None))
+    }
+}
+
+// Iterator on the remaining lines of the input, without their line
+// ending. Each one is read when the iterator gets to it, so a loop can
+// handle an endless input and stop in the middle
+pub struct NativeStdinLines;
+
+impl Callable<NativeFnErr> for NativeStdinLines {
+    fn arity(&self) -> usize {
+        0
+    }
+
+    fn call(&self, _: &mut Interpreter, _: Vec<RtVal>) -> NativeFnRes {
+        Ok(RtVal::IterVal(Rc::new(Iter { source: IterSource::Stdin, stages: vec![] })))
+    }
+}
+
+// ----------
+//  Channels
+// ----------
+pub struct NativeChan;
+
+impl Callable<NativeFnErr> for NativeChan {
+    fn arity(&self) -> usize {
+        0
+    }
+
+    fn call(&self, _: &mut Interpreter, _: Vec<RtVal>) -> NativeFnRes {
+        Ok(RtVal::ChanVal(Rc::new(Channel::default())))
+    }
+}
+
+// Channels are unbounded, sending never blocks. The messages of the actors
+// are handled once the interpreter is idle
+pub struct NativeSend;
+
+impl Callable<NativeFnErr> for NativeSend {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["chan|actor", "any"]
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        match &args[0] {
+            RtVal::ActorVal(a) => interpreter.post(a.clone(), args[1].clone()),
+            v => get_chan(v).queue.borrow_mut().push_back(args[1].clone()),
+        }
+
+        Ok(RtVal::new_null())
+    }
+}
+
+// Blocks the code running until a value is sent. A task is suspended and
+// the others run meanwhile, the OS thread is never blocked. If they are
+// all blocked the value can't come
+pub struct NativeRecv;
+
+impl Callable<NativeFnErr> for NativeRecv {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["chan"]
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let chan = get_chan(&args[0]);
+
+        interpreter
+            .wait_for(|| chan.queue.borrow_mut().pop_front())
+            .map_err(|e| PhyResult::new(NativeFnErr::Callback(Box::new(e)), None))?
+            .ok_or_else(|| PhyResult::new(NativeFnErr::Deadlock, None))
+    }
+}
+
+// Only called on chan values, checked by the parameters type
+fn get_chan(value: &RtVal) -> Rc<Channel> {
+    match value {
+        RtVal::ChanVal(c) => c.clone(),
+        _ => unreachable!(),
+    }
+}
+
+// Instance of an 'actor' declaration, made by the function the parser
+// generates for it. 'actor' is a keyword, scripts can't call it
+pub struct NativeActor;
+
+impl Callable<NativeFnErr> for NativeActor {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str", "fn"]
+    }
+
+    fn call(&self, _: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        Ok(RtVal::ActorVal(Rc::new(Actor { name: get_str(&args[0]), handler: args[1].clone() })))
+    }
+}
+
+// --------
+//  Timers
+// --------
+// The callbacks take no argument and run in 'run_until_idle', once the
+// script ended. Negative delays are treated as 0
+pub struct NativeSetTimeout;
+
+impl Callable<NativeFnErr> for NativeSetTimeout {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["fn|native fn", "int"]
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let delay = get_delay(&args[1]);
+
+        Ok(interpreter.schedule(args[0].clone(), delay, false).into())
+    }
+}
+
+pub struct NativeSetInterval;
+
+impl Callable<NativeFnErr> for NativeSetInterval {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["fn|native fn", "int"]
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let delay = get_delay(&args[1]);
+
+        Ok(interpreter.schedule(args[0].clone(), delay, true).into())
+    }
+}
+
+// Returns false if the timer already ran or was cleared
+pub struct NativeClearTimer;
+
+impl Callable<NativeFnErr> for NativeClearTimer {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["int"]
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        Ok(interpreter.clear_timer(get_int(&args[0])).into())
+    }
+}
+
+// In milliseconds
+fn get_delay(value: &RtVal) -> Duration {
+    Duration::from_millis(get_int(value).max(0) as u64)
+}
+
+// ---------
+//  Watches
+// ---------
+// The callback runs with the new value after each assignment of the
+// variable visible from the call, watch("x", f)
+pub struct NativeWatch;
+
+impl Callable<NativeFnErr> for NativeWatch {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str", "fn|native fn"]
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        interpreter
+            .watch(get_str(&args[0]), args[1].clone())
+            .map(|_| RtVal::new_null())
+            .map_err(|e| PhyResult::new(NativeFnErr::Watch(e.to_string()), None))
+    }
+}
+
+// --------
+//  Events
+// --------
+// The handler is called with the payload of each event of that name, in
+// the order the handlers subscribed, on("hit", f)
+pub struct NativeOn;
+
+impl Callable<NativeFnErr> for NativeOn {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str", "fn|native fn"]
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        interpreter.subscribe(&get_str(&args[0]), args[1].clone());
+
+        Ok(RtVal::new_null())
+    }
+}
+
+// Returns the number of handlers called, emit("hit", 10)
+pub struct NativeEmit;
+
+impl Callable<NativeFnErr> for NativeEmit {
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["str", "any"]
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        interpreter
+            .emit(&get_str(&args[0]), args[1].clone())
+            .map(|n| (n as i64).into())
+            .map_err(|e| PhyResult::new(NativeFnErr::Callback(Box::new(e)), None))
+    }
+}
+
+// ------
+//  Help
+// ------
+// Prints the signature of the function and its doc comment
+pub struct NativeHelp;
+
+impl Callable<NativeFnErr> for NativeHelp {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn params_type(&self) -> &[&'static str] {
+        &["fn|native fn"]
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<RtVal>) -> NativeFnRes {
+        let (signature, doc) = match &args[0] {
+            RtVal::FuncVal(f) => (f.signature.to_string(), f.doc.clone()),
+            RtVal::NativeFnVal(f) => (f.signature(), None),
+            _ => unreachable!(),
+        };
+
+        let doc = doc.map_or("No documentation".into(), |d| d.to_string());
+
+        interpreter
+            .write_line(&format!("{}\n\n{}", signature, doc))
+            .map_err(|e| PhyResult::new(e, None))?;
+
+        Ok(RtVal::new_null())
+    }
+}
@@ -0,0 +1,158 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+};
+
+use ecow::EcoString;
+use tools::{results::Loc, source_map::SourceMap};
+
+// ---------
+//   Trace
+// ---------
+// Steps of a run kept by the interpreter while recording, to replay it
+// later and go back from where it failed
+#[derive(Debug, PartialEq, Clone)]
+pub enum TraceEvent {
+    // Statement about to run
+    Stmt(Loc),
+    // Variable declared or assigned, with its displayed value
+    Set(EcoString, String),
+}
+
+// Step read back from a trace file. The statements keep their code, the
+// file may have changed since the run
+#[derive(Debug, PartialEq, Clone)]
+pub enum TraceStep {
+    Stmt { file: String, line: usize, code: String },
+    Set { name: String, value: String },
+}
+
+const HEADER: &str = "phy-trace 1";
+
+// One step per line, with tab separated fields
+pub fn write_trace(
+    events: &[TraceEvent],
+    sources: &SourceMap,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    writeln!(out, "{}", HEADER)?;
+
+    for event in events {
+        match event {
+            TraceEvent::Stmt(loc) => {
+                let Some(file) = sources.lookup(loc) else {
+                    continue;
+                };
+
+                let start = file.local_loc(loc).start;
+                let before = file.code.chars().take(start).collect::<String>();
+                let line = before.matches('\n').count() + 1;
+                let code = file.code.lines().nth(line - 1).unwrap_or("").trim();
+
+                writeln!(out, "stmt\t{}\t{}\t{}", escape(&file.name), line, escape(code))?;
+            }
+            TraceEvent::Set(name, value) => writeln!(out, "set\t{}\t{}", name, escape(value))?,
+        }
+    }
+
+    Ok(())
+}
+
+pub fn read_trace(text: &str) -> Result<Vec<TraceStep>, String> {
+    let mut lines = text.lines().enumerate();
+
+    if lines.next().map(|(_, l)| l) != Some(HEADER) {
+        return Err("not a trace recorded with '--record'".into());
+    }
+
+    lines
+        .map(|(i, l)| {
+            let step = match l.split('\t').collect::<Vec<_>>()[..] {
+                ["stmt", file, line, code] => line.parse().ok().map(|line| TraceStep::Stmt {
+                    file: unescape(file),
+                    line,
+                    code: unescape(code),
+                }),
+                ["set", name, value] => Some(TraceStep::Set {
+                    name: name.into(),
+                    value: unescape(value),
+                }),
+                _ => None,
+            };
+
+            step.ok_or_else(|| format!("invalid step at line {} of the trace", i + 1))
+        })
+        .collect()
+}
+
+// Values of the variables once the steps before the index ran. Variables
+// of different scopes with the same name are merged
+pub fn vars_before(steps: &[TraceStep], index: usize) -> BTreeMap<&str, &str> {
+    steps[..index]
+        .iter()
+        .filter_map(|s| match s {
+            TraceStep::Set { name, value } => Some((name.as_str(), value.as_str())),
+            TraceStep::Stmt { .. } => None,
+        })
+        .collect()
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+fn unescape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('t') => res.push('\t'),
+            Some('n') => res.push('\n'),
+            Some(c) => res.push(c),
+            None => res.push('\\'),
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use tools::{results::Loc, source_map::SourceMap};
+
+    use super::{read_trace, vars_before, write_trace, TraceEvent, TraceStep};
+
+    #[test]
+    fn round_trip() {
+        let mut sources = SourceMap::default();
+        sources.add("sim.arc", "var a = 1\n  a = a + 1\n");
+
+        let events = vec![
+            TraceEvent::Stmt(Loc::new(0, 9)),
+            TraceEvent::Set("a".into(), "1".into()),
+            TraceEvent::Stmt(Loc::new(12, 21)),
+            TraceEvent::Set("a".into(), "tab\there\nand \\".into()),
+        ];
+
+        let mut out = vec![];
+        write_trace(&events, &sources, &mut out).unwrap();
+        let steps = read_trace(&String::from_utf8(out).unwrap()).unwrap();
+
+        assert_eq!(
+            steps[2],
+            TraceStep::Stmt { file: "sim.arc".into(), line: 2, code: "a = a + 1".into() }
+        );
+        assert_eq!(vars_before(&steps, 2).get("a"), Some(&"1"));
+        assert_eq!(vars_before(&steps, 4).get("a"), Some(&"tab\there\nand \\"));
+        assert!(vars_before(&steps, 0).is_empty());
+
+        assert!(read_trace("var a = 1").is_err());
+        assert!(read_trace("phy-trace 1\nstmt\tsim.arc\tone\tcode").is_err());
+    }
+}
@@ -0,0 +1,191 @@
+use std::fmt::Display;
+
+use ecow::EcoString;
+use thiserror::Error;
+use tools::messages::{Arg, Translate};
+
+// -----------------
+//  Error managment
+// -----------------
+#[derive(Debug, Error, PartialEq)]
+pub enum UnitErr {
+    #[error("unknown unit '{0}'")]
+    UnknownUnit(String),
+}
+
+impl Translate for UnitErr {
+    fn category(&self) -> &'static str {
+        "unit"
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            UnitErr::UnknownUnit(..) => "UnknownUnit",
+        }
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        match self {
+            UnitErr::UnknownUnit(v) => vec![Arg::text(v)],
+        }
+    }
+}
+
+// --------
+//   Unit
+// --------
+// Exponent of each SI base unit, in the order of BASE_SYMBOLS
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Unit {
+    pub dims: [i32; 7],
+}
+
+const BASE_SYMBOLS: [&str; 7] = ["kg", "m", "s", "A", "K", "mol", "cd"];
+
+// Symbol, factor to convert to SI base units and dimensions
+const UNITS: [(&str, f64, [i32; 7]); 22] = [
+    // Base units
+    ("kg", 1., [1, 0, 0, 0, 0, 0, 0]),
+    ("m", 1., [0, 1, 0, 0, 0, 0, 0]),
+    ("s", 1., [0, 0, 1, 0, 0, 0, 0]),
+    ("A", 1., [0, 0, 0, 1, 0, 0, 0]),
+    ("K", 1., [0, 0, 0, 0, 1, 0, 0]),
+    ("mol", 1., [0, 0, 0, 0, 0, 1, 0]),
+    ("cd", 1., [0, 0, 0, 0, 0, 0, 1]),
+    // Scaled
+    ("g", 1e-3, [1, 0, 0, 0, 0, 0, 0]),
+    ("km", 1e3, [0, 1, 0, 0, 0, 0, 0]),
+    ("cm", 1e-2, [0, 1, 0, 0, 0, 0, 0]),
+    ("mm", 1e-3, [0, 1, 0, 0, 0, 0, 0]),
+    ("ms", 1e-3, [0, 0, 1, 0, 0, 0, 0]),
+    ("min", 60., [0, 0, 1, 0, 0, 0, 0]),
+    ("h", 3600., [0, 0, 1, 0, 0, 0, 0]),
+    // Derived
+    ("N", 1., [1, 1, -2, 0, 0, 0, 0]),
+    ("J", 1., [1, 2, -2, 0, 0, 0, 0]),
+    ("W", 1., [1, 2, -3, 0, 0, 0, 0]),
+    ("Pa", 1., [1, -1, -2, 0, 0, 0, 0]),
+    ("Hz", 1., [0, 0, -1, 0, 0, 0, 0]),
+    ("C", 1., [0, 0, 1, 1, 0, 0, 0]),
+    ("V", 1., [1, 2, -3, -1, 0, 0, 0]),
+    ("ohm", 1., [1, 2, -3, -2, 0, 0, 0]),
+];
+
+impl Unit {
+    // Builds the unit from its symbols and exponents. Also returns the
+    // factor to convert a value in this unit to SI base units
+    pub fn from_symbols(symbols: &[(EcoString, i32)]) -> Result<(Unit, f64), UnitErr> {
+        let mut unit = Unit::default();
+        let mut factor = 1.;
+
+        for (symbol, exp) in symbols {
+            let (_, scale, dims) = UNITS
+                .iter()
+                .find(|(s, _, _)| s == symbol)
+                .ok_or_else(|| UnitErr::UnknownUnit(symbol.to_string()))?;
+
+            factor *= scale.powi(*exp);
+            unit = unit.mul(&Unit { dims: *dims }.pow(*exp));
+        }
+
+        Ok((unit, factor))
+    }
+
+    pub fn is_dimensionless(&self) -> bool {
+        self.dims.iter().all(|d| *d == 0)
+    }
+
+    pub fn mul(&self, rhs: &Unit) -> Unit {
+        let mut dims = self.dims;
+        dims.iter_mut().zip(rhs.dims).for_each(|(d, r)| *d += r);
+
+        Unit { dims }
+    }
+
+    pub fn div(&self, rhs: &Unit) -> Unit {
+        self.mul(&rhs.pow(-1))
+    }
+
+    pub fn pow(&self, exp: i32) -> Unit {
+        Unit {
+            dims: self.dims.map(|d| d * exp),
+        }
+    }
+}
+
+// Positive exponents first, then each negative one as a division: kg*m/s^2
+impl Display for Unit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let factor = |symbol: &str, exp: i32| match exp {
+            1 => symbol.to_string(),
+            e => format!("{}^{}", symbol, e),
+        };
+
+        let num: Vec<String> = BASE_SYMBOLS
+            .iter()
+            .zip(self.dims)
+            .filter(|(_, d)| *d > 0)
+            .map(|(s, d)| factor(s, d))
+            .collect();
+
+        let den: Vec<String> = BASE_SYMBOLS
+            .iter()
+            .zip(self.dims)
+            .filter(|(_, d)| *d < 0)
+            .map(|(s, d)| factor(s, if num.is_empty() { d } else { -d }))
+            .collect();
+
+        match num.is_empty() {
+            true => write!(f, "{}", den.join("*")),
+            false if den.is_empty() => write!(f, "{}", num.join("*")),
+            false => write!(f, "{}/{}", num.join("*"), den.join("/")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ecow::EcoString;
+
+    use frontend::parser::UNIT_SYMBOLS;
+
+    use super::{Unit, UnitErr, UNITS};
+
+    fn unit(symbols: &[(&str, i32)]) -> (Unit, f64) {
+        let symbols: Vec<(EcoString, i32)> =
+            symbols.iter().map(|(s, e)| (EcoString::from(*s), *e)).collect();
+
+        Unit::from_symbols(&symbols).unwrap()
+    }
+
+    #[test]
+    fn units() {
+        let (accel, factor) = unit(&[("m", 1), ("s", -2)]);
+        assert_eq!(accel.to_string(), "m/s^2");
+        assert_eq!(factor, 1.);
+
+        let (force, _) = unit(&[("N", 1)]);
+        assert_eq!(force, accel.mul(&unit(&[("kg", 1)]).0));
+        assert_eq!(force.to_string(), "kg*m/s^2");
+
+        let (speed, factor) = unit(&[("km", 1), ("h", -1)]);
+        assert_eq!(speed.to_string(), "m/s");
+        assert_eq!(factor, 1000. / 3600.);
+
+        assert_eq!(unit(&[("Hz", 1)]).0.to_string(), "s^-1");
+        assert_eq!(unit(&[("Pa", 1)]).0.to_string(), "kg/m/s^2");
+        assert!(speed.div(&speed).is_dimensionless());
+
+        assert_eq!(
+            Unit::from_symbols(&[("parsec".into(), 1)]),
+            Err(UnitErr::UnknownUnit("parsec".into()))
+        );
+    }
+
+    // The parser only reads these symbols as units
+    #[test]
+    fn parser_symbols() {
+        let symbols: Vec<&str> = UNITS.iter().map(|(s, _, _)| *s).collect();
+        assert_eq!(symbols, UNIT_SYMBOLS);
+    }
+}
@@ -1,3 +1,5 @@
+use std::{cell::RefCell, io::Write, rc::Rc};
+
 use frontend::parser::utils::lex_and_parse;
 
 use crate::values::RtVal;
@@ -9,3 +11,52 @@ pub fn lex_parse_interp(code: &str) -> Result<RtVal, PhyResInterp> {
     let mut interp = Interpreter::new();
     interp.interpret(&nodes)
 }
+
+// Output sink shared with the test to read what the script printed
+#[derive(Clone, Default)]
+pub struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl SharedOutput {
+    pub fn text(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn lex_parse_interp_output(code: &str) -> (Result<RtVal, PhyResInterp>, String) {
+    let nodes = lex_and_parse(code).unwrap();
+    let output = SharedOutput::default();
+    let mut interp = Interpreter::new();
+    interp.output = Box::new(output.clone());
+
+    let res = interp.interpret(&nodes);
+    (res, output.text())
+}
+
+// Lets the test declare host values before running the code
+pub fn lex_parse_interp_with(
+    code: &str,
+    setup: impl FnOnce(&mut Interpreter),
+) -> Result<RtVal, PhyResInterp> {
+    let nodes = lex_and_parse(code).unwrap();
+    let mut interp = Interpreter::new();
+    setup(&mut interp);
+    interp.interpret(&nodes)
+}
+
+// Code reading the given input instead of stdin
+pub fn lex_parse_interp_input(code: &str, input: &'static str) -> Result<RtVal, PhyResInterp> {
+    let nodes = lex_and_parse(code).unwrap();
+    let mut interp = Interpreter::new();
+    interp.input = Box::new(input.as_bytes());
+    interp.interpret(&nodes)
+}
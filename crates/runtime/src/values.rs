@@ -1,15 +1,27 @@
 use colored::*;
 use ecow::EcoString;
-use frontend::ast::stmt::{FnDeclStmt, Stmt};
-use std::{cell::RefCell, fmt::Display, rc::Rc};
+use frontend::ast::{expr::{CallExpr, Expr}, stmt::{FnDeclStmt, Stmt}};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashSet, VecDeque},
+    fmt::Display,
+    ops::Range,
+    rc::Rc,
+};
 use thiserror::Error;
-use tools::results::{PhyReport, PhyResult};
+use tools::{
+    messages::{localize, text, Arg, Locale, Translate},
+    real_format::format_real_with,
+    results::{PhyReport, PhyResult},
+};
 
 use crate::{
     callable::Callable,
-    environment::Env,
+    environment::{Env, EnvWrapper},
+    host_object::HostObject,
     interpreter::{InterpErr, Interpreter},
     native_functions::PhyNativeFn,
+    units::Unit,
 };
 
 // -----------------
@@ -31,12 +43,45 @@ pub enum RtValErr {
     #[error("operator '{0}' is not supported for string manipulation")]
     StringManip(String),
 
+    #[error("division by zero")]
+    DivisionByZero,
+
+    #[error("int overflow in operation '{0}'")]
+    IntOverflow(String),
+
+    #[error("can't repeat a value a negative number of times, found {0}")]
+    NegativeRepeat(i64),
+
+    #[error("can't repeat a value {0} times, the result would be too large")]
+    RepeatTooLarge(i64),
+
+    // Indexing
+    #[error("index {0} out of range for length {1}")]
+    IndexOutOfRange(i64, usize),
+
+    #[error("values of type {0} can't be indexed")]
+    NotIndexable(String),
+
+    // Vectors and matrices
+    #[error("dimension mismatch for operator '{0}': {1} and {2}")]
+    DimensionMismatch(String, String, String),
+
+    // Units
+    #[error("incompatible units for operator '{0}': {1} and {2}")]
+    IncompatibleUnits(String, String, String),
+
     // Function
     #[error("function parameter declaration")]
     WrongFnParamDecl,
 
-    #[error("{0}")]
-    FnExecution(String),
+    // Error in the body, chained by the caller
+    #[error("{}", .0.err)]
+    FnExecution(Box<PhyResult<InterpErr>>),
+
+    // Errors kept as is for the caller, like the contract violations and
+    // the failures in the calls made by natives
+    #[error("{}", .0.err)]
+    Propagated(Box<PhyResult<InterpErr>>),
 
     // Others
     #[error("can't use a null value in a binary operation")]
@@ -47,8 +92,57 @@ pub enum RtValErr {
 }
 
 impl PhyReport for RtValErr {
-    fn get_err_msg(&self) -> String {
-        format!("{}: {}", "Function error".red(), self)
+    fn get_err_msg(&self, locale: &dyn Locale) -> String {
+        format!(
+            "{}: {}",
+            text(locale, "label.value", "Function error").red(),
+            localize(locale, self)
+        )
+    }
+}
+
+impl Translate for RtValErr {
+    fn category(&self) -> &'static str {
+        "value"
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            RtValErr::UnNegatable => "UnNegatable",
+            RtValErr::UnsupportedOpOnType(..) => "UnsupportedOpOnType",
+            RtValErr::OpStrInt => "OpStrInt",
+            RtValErr::StringManip(..) => "StringManip",
+            RtValErr::DivisionByZero => "DivisionByZero",
+            RtValErr::IntOverflow(..) => "IntOverflow",
+            RtValErr::NegativeRepeat(..) => "NegativeRepeat",
+            RtValErr::RepeatTooLarge(..) => "RepeatTooLarge",
+            RtValErr::IndexOutOfRange(..) => "IndexOutOfRange",
+            RtValErr::NotIndexable(..) => "NotIndexable",
+            RtValErr::DimensionMismatch(..) => "DimensionMismatch",
+            RtValErr::IncompatibleUnits(..) => "IncompatibleUnits",
+            RtValErr::WrongFnParamDecl => "WrongFnParamDecl",
+            RtValErr::FnExecution(..) => "FnExecution",
+            RtValErr::Propagated(..) => "Propagated",
+            RtValErr::OperationOnNull => "OperationOnNull",
+            RtValErr::UnknownOperation => "UnknownOperation",
+        }
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        match self {
+            RtValErr::UnsupportedOpOnType(a, b) => vec![Arg::text(a), Arg::text(b)],
+            RtValErr::StringManip(v)
+            | RtValErr::IntOverflow(v)
+            | RtValErr::NotIndexable(v) => vec![Arg::text(v)],
+            RtValErr::NegativeRepeat(v) | RtValErr::RepeatTooLarge(v) => vec![Arg::text(v)],
+            RtValErr::IndexOutOfRange(a, b) => vec![Arg::text(a), Arg::text(b)],
+            RtValErr::DimensionMismatch(a, b, c)
+            | RtValErr::IncompatibleUnits(a, b, c) => {
+                vec![Arg::text(a), Arg::text(b), Arg::text(c)]
+            }
+            RtValErr::FnExecution(e) | RtValErr::Propagated(e) => vec![Arg::message(&e.err)],
+            _ => vec![],
+        }
     }
 }
 
@@ -59,10 +153,23 @@ impl PhyReport for RtValErr {
 pub enum RtVal {
     IntVal(Rc<RefCell<Int>>),
     RealVal(Rc<RefCell<Real>>),
+    QuantityVal(Rc<RefCell<Quantity>>),
+    ComplexVal(Rc<RefCell<Complex>>),
     StrVal(Rc<RefCell<Str>>),
+    CharVal(Rc<RefCell<Char>>),
+    BytesVal(Rc<RefCell<Bytes>>),
     BoolVal(Rc<RefCell<Bool>>),
+    ListVal(Rc<RefCell<List>>),
+    VectorVal(Rc<RefCell<Vector>>),
+    MatrixVal(Rc<RefCell<Matrix>>),
     FuncVal(Rc<Function>),
     NativeFnVal(Rc<PhyNativeFn>),
+    ModuleVal(Rc<Module>),
+    HostVal(Rc<dyn HostObject>),
+    TaskVal(Rc<Task>),
+    ChanVal(Rc<Channel>),
+    ActorVal(Rc<Actor>),
+    IterVal(Rc<Iter>),
     Null,
 }
 
@@ -79,15 +186,174 @@ impl RtVal {
         RtVal::Null
     }
 
-    pub fn negate(&self) -> Result<(), RtValErr> {
-        match &self {
+    pub fn get_type_name(&self) -> &'static str {
+        match self {
+            RtVal::IntVal(_) => "int",
+            RtVal::RealVal(_) => "real",
+            RtVal::QuantityVal(_) => "quantity",
+            RtVal::ComplexVal(_) => "complex",
+            RtVal::StrVal(_) => "str",
+            RtVal::CharVal(_) => "char",
+            RtVal::BytesVal(_) => "bytes",
+            RtVal::BoolVal(_) => "bool",
+            RtVal::ListVal(_) => "list",
+            RtVal::VectorVal(_) => "vector",
+            RtVal::MatrixVal(_) => "matrix",
+            RtVal::FuncVal(_) => "fn",
+            RtVal::NativeFnVal(_) => "native fn",
+            RtVal::ModuleVal(_) => "module",
+            RtVal::HostVal(_) => "object",
+            RtVal::TaskVal(_) => "task",
+            RtVal::ChanVal(_) => "chan",
+            RtVal::ActorVal(_) => "actor",
+            RtVal::IterVal(_) => "iter",
+            RtVal::Null => "null",
+        }
+    }
+
+    // Approximate number of bytes used by the value, with its heap storage
+    pub fn approx_size(&self) -> usize {
+        self.approx_size_in(&mut HashSet::new())
+    }
+
+    // Lists and modules are shared, the ones already in 'seen' are only
+    // counted once. A list holding itself is then counted, not walked forever
+    pub fn approx_size_in(&self, seen: &mut HashSet<*const ()>) -> usize {
+        let heap = match self {
+            RtVal::StrVal(s) => s.borrow().value.len(),
+            RtVal::BytesVal(b) => b.borrow().value.len(),
+            RtVal::ListVal(l) if seen.insert(Rc::as_ptr(l) as *const ()) => {
+                l.borrow().value.iter().map(|v| v.approx_size_in(seen)).sum()
+            }
+            RtVal::VectorVal(v) => v.borrow().value.len() * size_of::<f64>(),
+            RtVal::MatrixVal(m) => m.borrow().value.iter().map(|r| r.len() * size_of::<f64>()).sum(),
+            RtVal::ModuleVal(m) if seen.insert(Rc::as_ptr(m) as *const ()) => {
+                m.members.values().map(|v| v.approx_size_in(seen)).sum()
+            }
+            _ => 0,
+        };
+
+        size_of::<RtVal>() + heap
+    }
+
+    // Value of int and real as f64
+    pub fn get_real(&self) -> Option<f64> {
+        match self {
+            RtVal::IntVal(i) => Some(i.borrow().value as f64),
+            RtVal::RealVal(r) => Some(r.borrow().value),
+            _ => None,
+        }
+    }
+
+    // Strings are printed without their surrounding quotes
+    pub fn to_print_string(&self, config: &DisplayConfig) -> String {
+        match self {
+            RtVal::StrVal(s) => s.borrow().value.to_string(),
+            RtVal::CharVal(c) => c.borrow().value.to_string(),
+            _ => self.display(config).to_string(),
+        }
+    }
+
+    // New value with its own storage, mutating it doesn't change the
+    // variables referencing the original one
+    pub fn copy(&self) -> RtVal {
+        match self {
+            RtVal::IntVal(i) => i.borrow().value.into(),
+            RtVal::RealVal(r) => r.borrow().value.into(),
+            RtVal::StrVal(s) => s.borrow().value.clone().into(),
+            RtVal::CharVal(c) => c.borrow().value.into(),
+            RtVal::BytesVal(b) => b.borrow().value.clone().into(),
+            RtVal::BoolVal(b) => b.borrow().value.into(),
+            RtVal::QuantityVal(q) => Quantity::new_val(q.borrow().value, q.borrow().unit),
+            RtVal::ComplexVal(c) => (*c.borrow()).into(),
+            RtVal::VectorVal(v) => v.borrow().clone().into(),
+            RtVal::MatrixVal(m) => m.borrow().clone().into(),
+            RtVal::ListVal(l) => l.borrow().value.clone().into(),
+            v => v.clone(),
+        }
+    }
+
+    // Same replication as the '*' operator for strings and lists. Any other
+    // value gives a list of n copies: repeat(0, 3) == [0, 0, 0]
+    pub fn repeat(&self, count: i64) -> Result<RtVal, RtValErr> {
+        match self {
+            RtVal::StrVal(s) => {
+                let s = &s.borrow().value;
+                Ok(s.as_str().repeat(repeat_count(count, s.len())?).into())
+            }
+            RtVal::ListVal(l) => l.borrow().operate(&Int { value: count }, "*"),
+            v => {
+                let n = repeat_count(count, size_of::<RtVal>())?;
+                Ok((0..n).map(|_| v.copy()).collect::<Vec<RtVal>>().into())
+            }
+        }
+    }
+
+    // Item of a list, char of a string or byte of bytes, see 'item_index'
+    pub fn get_item(&self, idx: i64) -> Result<RtVal, RtValErr> {
+        let out_of_range = |len: usize| RtValErr::IndexOutOfRange(idx, len);
+
+        match self {
+            RtVal::ListVal(l) => {
+                let l = l.borrow();
+                item_index(idx, l.value.len())
+                    .map(|i| l.value[i].clone())
+                    .ok_or_else(|| out_of_range(l.value.len()))
+            }
+            RtVal::StrVal(s) => {
+                let chars: Vec<char> = s.borrow().value.chars().collect();
+                item_index(idx, chars.len())
+                    .map(|i| chars[i].into())
+                    .ok_or_else(|| out_of_range(chars.len()))
+            }
+            RtVal::BytesVal(b) => {
+                let b = b.borrow();
+                item_index(idx, b.value.len())
+                    .map(|i| (b.value[i] as i64).into())
+                    .ok_or_else(|| out_of_range(b.value.len()))
+            }
+            v => Err(RtValErr::NotIndexable(v.get_type_name().into())),
+        }
+    }
+
+    // New list, string or bytes, see 'slice_range'
+    pub fn get_slice(&self, start: Option<i64>, end: Option<i64>) -> Result<RtVal, RtValErr> {
+        match self {
+            RtVal::ListVal(l) => {
+                let l = l.borrow();
+                Ok(l.value[slice_range(start, end, l.value.len())].to_vec().into())
+            }
+            RtVal::StrVal(s) => {
+                let chars: Vec<char> = s.borrow().value.chars().collect();
+                Ok(chars[slice_range(start, end, chars.len())].iter().collect::<String>().into())
+            }
+            RtVal::BytesVal(b) => {
+                let b = b.borrow();
+                Ok(b.value[slice_range(start, end, b.value.len())].to_vec().into())
+            }
+            v => Err(RtValErr::NotIndexable(v.get_type_name().into())),
+        }
+    }
+
+    // Negates a copy, the operand may be a variable
+    pub fn negate(&self) -> Result<RtVal, RtValErr> {
+        let value = self.copy();
+
+        match &value {
+            RtVal::IntVal(i) if i.borrow().value == i64::MIN => {
+                return Err(RtValErr::IntOverflow("-".into()))
+            }
             RtVal::IntVal(i) => i.borrow_mut().negate(),
             RtVal::RealVal(r) => r.borrow_mut().negate(),
+            RtVal::QuantityVal(q) => q.borrow_mut().negate(),
+            RtVal::ComplexVal(c) => c.borrow_mut().negate(),
+            RtVal::VectorVal(v) => v.borrow_mut().negate(),
+            RtVal::MatrixVal(m) => m.borrow_mut().negate(),
             RtVal::BoolVal(b) => b.borrow_mut().negate(),
             _ => return Err(RtValErr::UnNegatable),
         }
 
-        Ok(())
+        Ok(value)
     }
 
     // TODO: Error handling for other operation
@@ -99,9 +365,66 @@ impl RtVal {
             }
             (RtVal::IntVal(i1), RtVal::RealVal(r1)) => i1.borrow().operate(&*r1.borrow(), operator),
             (RtVal::RealVal(r1), RtVal::IntVal(i1)) => r1.borrow().operate(&*i1.borrow(), operator),
+            (RtVal::QuantityVal(q1), RtVal::QuantityVal(q2)) => {
+                q1.borrow().operate(&*q2.borrow(), operator)
+            }
+            (RtVal::QuantityVal(q), RtVal::IntVal(i)) => {
+                q.borrow().operate(&(i.borrow().value as f64), operator)
+            }
+            (RtVal::QuantityVal(q), RtVal::RealVal(r)) => {
+                q.borrow().operate(&r.borrow().value, operator)
+            }
+            (RtVal::IntVal(i), RtVal::QuantityVal(q)) => {
+                Quantity::from(i.borrow().value as f64).operate(&*q.borrow(), operator)
+            }
+            (RtVal::RealVal(r), RtVal::QuantityVal(q)) => {
+                Quantity::from(r.borrow().value).operate(&*q.borrow(), operator)
+            }
+            (RtVal::ComplexVal(c1), RtVal::ComplexVal(c2)) => {
+                c1.borrow().operate(&*c2.borrow(), operator)
+            }
+            (RtVal::ComplexVal(c), RtVal::IntVal(_) | RtVal::RealVal(_)) => {
+                c.borrow().operate(&Complex::from(rhs.get_real().unwrap()), operator)
+            }
+            (RtVal::IntVal(_) | RtVal::RealVal(_), RtVal::ComplexVal(c)) => {
+                Complex::from(self.get_real().unwrap()).operate(&*c.borrow(), operator)
+            }
+            (RtVal::VectorVal(v1), RtVal::VectorVal(v2)) => {
+                v1.borrow().operate(&*v2.borrow(), operator)
+            }
+            (RtVal::MatrixVal(m1), RtVal::MatrixVal(m2)) => {
+                m1.borrow().operate(&*m2.borrow(), operator)
+            }
+            (RtVal::MatrixVal(m), RtVal::VectorVal(v)) => m.borrow().operate(&*v.borrow(), operator),
+            (RtVal::VectorVal(v), RtVal::IntVal(_) | RtVal::RealVal(_)) => {
+                v.borrow().operate(&rhs.get_real().unwrap(), operator)
+            }
+            (RtVal::MatrixVal(m), RtVal::IntVal(_) | RtVal::RealVal(_)) => {
+                m.borrow().operate(&rhs.get_real().unwrap(), operator)
+            }
+            // Scalar on the left, only commutative operators
+            (RtVal::IntVal(_) | RtVal::RealVal(_), RtVal::VectorVal(_) | RtVal::MatrixVal(_))
+                if operator == "+" || operator == "*" =>
+            {
+                rhs.operate(self, operator)
+            }
             (RtVal::StrVal(s1), RtVal::StrVal(s2)) => s1.borrow().operate(&*s2.borrow(), operator),
             (RtVal::StrVal(s1), RtVal::IntVal(i1)) => s1.borrow().operate(&*i1.borrow(), operator),
             (RtVal::IntVal(i1), RtVal::StrVal(s1)) => i1.borrow().operate(&*s1.borrow(), operator),
+            (RtVal::CharVal(c1), RtVal::CharVal(c2)) => c1.borrow().operate(&*c2.borrow(), operator),
+            (RtVal::StrVal(s), RtVal::CharVal(c)) => s.borrow().operate(&*c.borrow(), operator),
+            (RtVal::CharVal(c), RtVal::StrVal(s)) => c.borrow().operate(&*s.borrow(), operator),
+            (RtVal::ListVal(l), RtVal::IntVal(i)) | (RtVal::IntVal(i), RtVal::ListVal(l)) => {
+                l.borrow().operate(&*i.borrow(), operator)
+            }
+            (RtVal::ListVal(l1), RtVal::ListVal(l2)) => match operator {
+                "==" => Ok(lists_eq(l1, l2, &mut vec![]).into()),
+                "!=" => Ok((!lists_eq(l1, l2, &mut vec![])).into()),
+                op => Err(RtValErr::UnsupportedOpOnType(op.to_string(), "list".into())),
+            },
+            (RtVal::BytesVal(b1), RtVal::BytesVal(b2)) => {
+                b1.borrow().operate(&*b2.borrow(), operator)
+            }
             (RtVal::BoolVal(b1), RtVal::BoolVal(b2)) => {
                 b1.borrow().operate(&*b2.borrow(), operator)
             }
@@ -111,6 +434,37 @@ impl RtVal {
     }
 }
 
+// ------------
+//   Indexing
+// ------------
+// Shared by 'a[i]', 'a[i..j]' and the natives on lists, strings and bytes.
+// Strings are indexed by chars. Negative indices count from the end, -1
+// being the last item: for a length of 3, -1 is 2 and -3 is 0
+pub fn item_index(idx: i64, len: usize) -> Option<usize> {
+    let idx = match idx < 0 {
+        true => idx + len as i64,
+        false => idx,
+    };
+
+    usize::try_from(idx).ok().filter(|i| *i < len)
+}
+
+// Unlike the items, the bounds of a slice are never out of range: they are
+// clamped to the sequence, and a start after the end gives an empty slice.
+// The start defaults to 0 and the end to the length. For "abcd", [1..3] is
+// "bc", [-2..] is "cd", [..10] is "abcd" and [3..1] is ""
+pub fn slice_range(start: Option<i64>, end: Option<i64>, len: usize) -> Range<usize> {
+    let bound = |b: i64| match b < 0 {
+        true => (b + len as i64).max(0) as usize,
+        false => (b as usize).min(len),
+    };
+
+    let end = end.map_or(len, bound);
+    let start = start.map_or(0, bound).min(end);
+
+    start..end
+}
+
 // -------
 //   Int
 // -------
@@ -127,12 +481,18 @@ impl Negate for Int {
 
 impl Operate<Int> for Int {
     fn operate(&self, rhs: &Int, operator: &str) -> Result<RtVal, RtValErr> {
+        let checked = |res: Option<i64>| {
+            res.map(RtVal::from).ok_or_else(|| RtValErr::IntOverflow(operator.into()))
+        };
+
         match operator {
-            "+" => Ok((self.value + rhs.value).into()),
-            "-" => Ok((self.value - rhs.value).into()),
-            "*" => Ok((self.value * rhs.value).into()),
-            "/" => Ok((self.value / rhs.value).into()),
-            "%" => Ok((self.value % rhs.value).into()),
+            "/" | "%" if rhs.value == 0 => Err(RtValErr::DivisionByZero),
+            "+" => checked(self.value.checked_add(rhs.value)),
+            "-" => checked(self.value.checked_sub(rhs.value)),
+            "*" => checked(self.value.checked_mul(rhs.value)),
+            // i64::MIN / -1 is the only other failure
+            "/" => checked(self.value.checked_div(rhs.value)),
+            "%" => checked(self.value.checked_rem(rhs.value)),
             "<" => Ok((self.value < rhs.value).into()),
             ">" => Ok((self.value > rhs.value).into()),
             "<=" => Ok((self.value <= rhs.value).into()),
@@ -166,7 +526,7 @@ impl Operate<Real> for Int {
 impl Operate<Str> for Int {
     fn operate(&self, rhs: &Str, operator: &str) -> Result<RtVal, RtValErr> {
         match operator {
-            "*" => Ok(rhs.value.repeat(self.value as usize).into()),
+            "*" => Ok(rhs.value.as_str().repeat(repeat_count(self.value, rhs.value.len())?).into()),
             _ => Err(RtValErr::OpStrInt),
         }
     }
@@ -224,6 +584,152 @@ impl Operate<Real> for Real {
     }
 }
 
+// ------------
+//   Quantity
+// ------------
+// Real value with a unit, always stored in SI base units
+#[derive(Debug, PartialEq)]
+pub struct Quantity {
+    pub value: f64,
+    pub unit: Unit,
+}
+
+impl Quantity {
+    // Units cancelling each other give back a real
+    pub fn new_val(value: f64, unit: Unit) -> RtVal {
+        match unit.is_dimensionless() {
+            true => value.into(),
+            false => RtVal::QuantityVal(Rc::new(RefCell::new(Quantity { value, unit }))),
+        }
+    }
+}
+
+// Numbers without unit are dimensionless quantities
+impl From<f64> for Quantity {
+    fn from(value: f64) -> Self {
+        Quantity {
+            value,
+            unit: Unit::default(),
+        }
+    }
+}
+
+impl Negate for Quantity {
+    fn negate(&mut self) {
+        self.value *= -1.;
+    }
+}
+
+impl Operate<Quantity> for Quantity {
+    fn operate(&self, rhs: &Quantity, operator: &str) -> Result<RtVal, RtValErr> {
+        match operator {
+            "*" => return Ok(Quantity::new_val(self.value * rhs.value, self.unit.mul(&rhs.unit))),
+            "/" => return Ok(Quantity::new_val(self.value / rhs.value, self.unit.div(&rhs.unit))),
+            _ => {}
+        }
+
+        // Other operations are only defined between same units
+        if self.unit != rhs.unit {
+            let unit_name = |u: &Unit| match u.is_dimensionless() {
+                true => "unitless".to_string(),
+                false => u.to_string(),
+            };
+
+            return Err(RtValErr::IncompatibleUnits(
+                operator.into(),
+                unit_name(&self.unit),
+                unit_name(&rhs.unit),
+            ));
+        }
+
+        match operator {
+            "+" => Ok(Quantity::new_val(self.value + rhs.value, self.unit)),
+            "-" => Ok(Quantity::new_val(self.value - rhs.value, self.unit)),
+            "%" => Ok(Quantity::new_val(self.value % rhs.value, self.unit)),
+            "<" => Ok((self.value < rhs.value).into()),
+            ">" => Ok((self.value > rhs.value).into()),
+            "<=" => Ok((self.value <= rhs.value).into()),
+            ">=" => Ok((self.value >= rhs.value).into()),
+            "==" => Ok((self.value == rhs.value).into()),
+            "!=" => Ok((self.value != rhs.value).into()),
+            op => Err(RtValErr::UnsupportedOpOnType(op.to_string(), "quantity".into())),
+        }
+    }
+}
+
+impl Operate<f64> for Quantity {
+    fn operate(&self, rhs: &f64, operator: &str) -> Result<RtVal, RtValErr> {
+        self.operate(&Quantity::from(*rhs), operator)
+    }
+}
+
+// -----------
+//   Complex
+// -----------
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn abs(&self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    pub fn arg(&self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn conj(&self) -> Complex {
+        Complex {
+            re: self.re,
+            im: -self.im,
+        }
+    }
+}
+
+impl From<f64> for Complex {
+    fn from(value: f64) -> Self {
+        Complex { re: value, im: 0. }
+    }
+}
+
+impl Negate for Complex {
+    fn negate(&mut self) {
+        self.re *= -1.;
+        self.im *= -1.;
+    }
+}
+
+impl Operate<Complex> for Complex {
+    fn operate(&self, rhs: &Complex, operator: &str) -> Result<RtVal, RtValErr> {
+        let (a, b, c, d) = (self.re, self.im, rhs.re, rhs.im);
+
+        match operator {
+            "+" => Ok(Complex { re: a + c, im: b + d }.into()),
+            "-" => Ok(Complex { re: a - c, im: b - d }.into()),
+            "*" => Ok(Complex {
+                re: a * c - b * d,
+                im: a * d + b * c,
+            }
+            .into()),
+            "/" => {
+                let den = c * c + d * d;
+
+                Ok(Complex {
+                    re: (a * c + b * d) / den,
+                    im: (b * c - a * d) / den,
+                }
+                .into())
+            }
+            "==" => Ok((self == rhs).into()),
+            "!=" => Ok((self != rhs).into()),
+            op => Err(RtValErr::UnsupportedOpOnType(op.to_string(), "complex".into())),
+        }
+    }
+}
+
 // ----------
 //   String
 // ----------
@@ -246,12 +752,76 @@ impl Operate<Str> for Str {
 impl Operate<Int> for Str {
     fn operate(&self, rhs: &Int, operator: &str) -> Result<RtVal, RtValErr> {
         match operator {
-            "*" => Ok(self.value.repeat(rhs.value as usize).into()),
+            "*" => {
+                let n = repeat_count(rhs.value, self.value.len())?;
+                Ok(self.value.as_str().repeat(n).into())
+            }
             _ => Err(RtValErr::OpStrInt),
         }
     }
 }
 
+impl Operate<Char> for Str {
+    fn operate(&self, rhs: &Char, operator: &str) -> Result<RtVal, RtValErr> {
+        match operator {
+            "+" => Ok(format!("{}{}", self.value, rhs.value).into()),
+            op => Err(RtValErr::StringManip(op.to_string())),
+        }
+    }
+}
+
+// --------
+//   Char
+// --------
+// Unicode scalar value, compared by code point
+#[derive(Debug, PartialEq)]
+pub struct Char {
+    pub value: char,
+}
+
+impl Operate<Char> for Char {
+    fn operate(&self, rhs: &Char, operator: &str) -> Result<RtVal, RtValErr> {
+        match operator {
+            "+" => Ok(format!("{}{}", self.value, rhs.value).into()),
+            "<" => Ok((self.value < rhs.value).into()),
+            ">" => Ok((self.value > rhs.value).into()),
+            "<=" => Ok((self.value <= rhs.value).into()),
+            ">=" => Ok((self.value >= rhs.value).into()),
+            "==" => Ok((self.value == rhs.value).into()),
+            "!=" => Ok((self.value != rhs.value).into()),
+            op => Err(RtValErr::UnsupportedOpOnType(op.to_string(), "char".into())),
+        }
+    }
+}
+
+impl Operate<Str> for Char {
+    fn operate(&self, rhs: &Str, operator: &str) -> Result<RtVal, RtValErr> {
+        match operator {
+            "+" => Ok(format!("{}{}", self.value, rhs.value).into()),
+            op => Err(RtValErr::StringManip(op.to_string())),
+        }
+    }
+}
+
+// ---------
+//   Bytes
+// ---------
+#[derive(Debug, PartialEq)]
+pub struct Bytes {
+    pub value: Vec<u8>,
+}
+
+impl Operate<Bytes> for Bytes {
+    fn operate(&self, rhs: &Bytes, operator: &str) -> Result<RtVal, RtValErr> {
+        match operator {
+            "+" => Ok([self.value.as_slice(), &rhs.value].concat().into()),
+            "==" => Ok((self.value == rhs.value).into()),
+            "!=" => Ok((self.value != rhs.value).into()),
+            op => Err(RtValErr::UnsupportedOpOnType(op.to_string(), "bytes".into())),
+        }
+    }
+}
+
 // --------
 //   Bool
 // --------
@@ -278,13 +848,498 @@ impl Operate<Bool> for Bool {
     }
 }
 
+// --------
+//   List
+// --------
+#[derive(Debug, PartialEq)]
+pub struct List {
+    pub value: Vec<RtVal>,
+}
+
+// The items are repeated, not copied: in 'l * 3' with lists in 'l', the
+// three occurrences of each of them are the same list
+impl Operate<Int> for List {
+    fn operate(&self, rhs: &Int, operator: &str) -> Result<RtVal, RtValErr> {
+        match operator {
+            "*" => {
+                let size = self.value.len() * size_of::<RtVal>();
+                let len = self.value.len() * repeat_count(rhs.value, size)?;
+                Ok(self.value.iter().cycle().take(len).cloned().collect::<Vec<RtVal>>().into())
+            }
+            op => Err(RtValErr::UnsupportedOpOnType(op.to_string(), "list".into())),
+        }
+    }
+}
+
+// Same length and equal items, compared like '=='. 'open' holds the pairs
+// of lists being compared, a pair met again inside them is taken as equal
+// so that the lists holding themselves are compared without end
+fn lists_eq(
+    l1: &Rc<RefCell<List>>,
+    l2: &Rc<RefCell<List>>,
+    open: &mut Vec<(*const RefCell<List>, *const RefCell<List>)>,
+) -> bool {
+    let pair = (Rc::as_ptr(l1), Rc::as_ptr(l2));
+    if Rc::ptr_eq(l1, l2) || open.contains(&pair) {
+        return true;
+    }
+
+    let (a, b) = (l1.borrow(), l2.borrow());
+    if a.value.len() != b.value.len() {
+        return false;
+    }
+
+    open.push(pair);
+    let eq = a.value.iter().zip(&b.value).all(|(x, y)| match (x, y) {
+        (RtVal::ListVal(x), RtVal::ListVal(y)) => lists_eq(x, y, open),
+        _ => match x.operate(y, "==") {
+            Ok(RtVal::BoolVal(b)) => b.borrow().value,
+            _ => x == y,
+        },
+    });
+    open.pop();
+
+    eq
+}
+
+// Repetitions giving more bytes fail, such a count is more likely a mistake
+// than a value meant to fill the memory
+const MAX_REPEAT_BYTES: usize = 1 << 30;
+
+// Number of repetitions of a value of 'size' bytes. The strings are then
+// repeated by 'str::repeat', which copies in doubling chunks where
+// 'EcoString::repeat' would loop over the count
+fn repeat_count(count: i64, size: usize) -> Result<usize, RtValErr> {
+    let n = usize::try_from(count).map_err(|_| RtValErr::NegativeRepeat(count))?;
+
+    match size.checked_mul(n) {
+        Some(total) if total <= MAX_REPEAT_BYTES => Ok(n),
+        _ => Err(RtValErr::RepeatTooLarge(count)),
+    }
+}
+
+// ----------
+//   Vector
+// ----------
+// 2 or 3 components, accessed with '.x', '.y' and '.z'
+#[derive(Debug, PartialEq, Clone)]
+pub struct Vector {
+    pub value: Vec<f64>,
+}
+
+impl Vector {
+    pub const COMPONENTS: [&'static str; 3] = ["x", "y", "z"];
+
+    pub fn component_idx(&self, name: &str) -> Option<usize> {
+        Self::COMPONENTS[..self.value.len()].iter().position(|c| *c == name)
+    }
+
+    pub fn dot(&self, rhs: &Vector) -> f64 {
+        self.value.iter().zip(&rhs.value).map(|(a, b)| a * b).sum()
+    }
+
+    fn type_name(&self) -> String {
+        format!("vec{}", self.value.len())
+    }
+}
+
+impl Negate for Vector {
+    fn negate(&mut self) {
+        self.value.iter_mut().for_each(|v| *v *= -1.);
+    }
+}
+
+impl Operate<Vector> for Vector {
+    fn operate(&self, rhs: &Vector, operator: &str) -> Result<RtVal, RtValErr> {
+        if self.value.len() != rhs.value.len() {
+            return Err(RtValErr::DimensionMismatch(
+                operator.into(),
+                self.type_name(),
+                rhs.type_name(),
+            ));
+        }
+
+        let zip = |f: fn(f64, f64) -> f64| -> RtVal {
+            Vector {
+                value: self.value.iter().zip(&rhs.value).map(|(a, b)| f(*a, *b)).collect(),
+            }
+            .into()
+        };
+
+        match operator {
+            "+" => Ok(zip(|a, b| a + b)),
+            "-" => Ok(zip(|a, b| a - b)),
+            "==" => Ok((self == rhs).into()),
+            "!=" => Ok((self != rhs).into()),
+            op => Err(RtValErr::UnsupportedOpOnType(op.to_string(), "vector".into())),
+        }
+    }
+}
+
+// Scalar broadcasted on every component
+impl Operate<f64> for Vector {
+    fn operate(&self, rhs: &f64, operator: &str) -> Result<RtVal, RtValErr> {
+        let map = |f: &dyn Fn(f64) -> f64| -> RtVal {
+            Vector {
+                value: self.value.iter().map(|v| f(*v)).collect(),
+            }
+            .into()
+        };
+
+        match operator {
+            "+" => Ok(map(&|v| v + rhs)),
+            "-" => Ok(map(&|v| v - rhs)),
+            "*" => Ok(map(&|v| v * rhs)),
+            "/" => Ok(map(&|v| v / rhs)),
+            op => Err(RtValErr::UnsupportedOpOnType(op.to_string(), "vector".into())),
+        }
+    }
+}
+
+// ----------
+//   Matrix
+// ----------
+// Rows of same length
+#[derive(Debug, PartialEq, Clone)]
+pub struct Matrix {
+    pub value: Vec<Vec<f64>>,
+}
+
+impl Matrix {
+    fn nb_cols(&self) -> usize {
+        self.value.first().map_or(0, |r| r.len())
+    }
+
+    fn type_name(&self) -> String {
+        format!("mat{}x{}", self.value.len(), self.nb_cols())
+    }
+
+    fn map(&self, f: impl Fn(f64) -> f64) -> Matrix {
+        Matrix {
+            value: self.value.iter().map(|r| r.iter().map(|v| f(*v)).collect()).collect(),
+        }
+    }
+}
+
+impl Negate for Matrix {
+    fn negate(&mut self) {
+        *self = self.map(|v| -v);
+    }
+}
+
+impl Operate<Matrix> for Matrix {
+    fn operate(&self, rhs: &Matrix, operator: &str) -> Result<RtVal, RtValErr> {
+        let mismatch = || {
+            RtValErr::DimensionMismatch(operator.into(), self.type_name(), rhs.type_name())
+        };
+
+        match operator {
+            "+" | "-" => {
+                if self.type_name() != rhs.type_name() {
+                    return Err(mismatch());
+                }
+
+                let sign = if operator == "+" { 1. } else { -1. };
+                let value = self
+                    .value
+                    .iter()
+                    .zip(&rhs.value)
+                    .map(|(r1, r2)| r1.iter().zip(r2).map(|(a, b)| a + sign * b).collect())
+                    .collect();
+
+                Ok(Matrix { value }.into())
+            }
+            "*" => {
+                if self.nb_cols() != rhs.value.len() {
+                    return Err(mismatch());
+                }
+
+                let value = self
+                    .value
+                    .iter()
+                    .map(|row| {
+                        (0..rhs.nb_cols())
+                            .map(|j| row.iter().zip(&rhs.value).map(|(a, r)| a * r[j]).sum())
+                            .collect()
+                    })
+                    .collect();
+
+                Ok(Matrix { value }.into())
+            }
+            "==" => Ok((self == rhs).into()),
+            "!=" => Ok((self != rhs).into()),
+            op => Err(RtValErr::UnsupportedOpOnType(op.to_string(), "matrix".into())),
+        }
+    }
+}
+
+impl Operate<Vector> for Matrix {
+    fn operate(&self, rhs: &Vector, operator: &str) -> Result<RtVal, RtValErr> {
+        match operator {
+            "*" if self.nb_cols() == rhs.value.len() => Ok(Vector {
+                value: self.value.iter().map(|row| Vector { value: row.clone() }.dot(rhs)).collect(),
+            }
+            .into()),
+            "*" => Err(RtValErr::DimensionMismatch(
+                operator.into(),
+                self.type_name(),
+                rhs.type_name(),
+            )),
+            op => Err(RtValErr::UnsupportedOpOnType(op.to_string(), "matrix".into())),
+        }
+    }
+}
+
+impl Operate<f64> for Matrix {
+    fn operate(&self, rhs: &f64, operator: &str) -> Result<RtVal, RtValErr> {
+        match operator {
+            "+" => Ok(self.map(|v| v + rhs).into()),
+            "-" => Ok(self.map(|v| v - rhs).into()),
+            "*" => Ok(self.map(|v| v * rhs).into()),
+            "/" => Ok(self.map(|v| v / rhs).into()),
+            op => Err(RtValErr::UnsupportedOpOnType(op.to_string(), "matrix".into())),
+        }
+    }
+}
+
+// ----------
+//   Module
+// ----------
+// Namespace grouping natives, like 'math' in 'math.sqrt'
+#[derive(Debug, PartialEq)]
+pub struct Module {
+    pub name: EcoString,
+    // Sorted so that listing the members is reproducible
+    pub members: BTreeMap<EcoString, RtVal>,
+    // Names declared by the module without 'pub', only reported in errors
+    pub private: BTreeSet<EcoString>,
+}
+
+// ----------
+//   Task
+// ----------
+// Call started by 'spawn'. It waits in the queue of the interpreter until
+// a yield point and then runs on its own stack, suspended at each yield
+// point to let the main code and the other tasks run
+#[derive(Debug)]
+pub struct Task {
+    pub call: CallExpr,
+    pub state: RefCell<TaskState>,
+}
+
+#[derive(Debug)]
+pub enum TaskState {
+    // Callee and arguments
+    Waiting(RtVal, Vec<RtVal>),
+    Running,
+    // Null if the task failed
+    Done(RtVal),
+}
+
+// Tasks are only equal to themselves
+impl PartialEq for Task {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// ----------
+//  Channel
+// ----------
+// Unbounded queue of values shared between tasks. Receiving from an empty
+// channel blocks the task, or the main code, until a value is sent
+#[derive(Debug, Default)]
+pub struct Channel {
+    pub queue: RefCell<VecDeque<RtVal>>,
+}
+
+// Channels are only equal to themselves
+impl PartialEq for Channel {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// ---------
+//  Actor
+// ---------
+// Instance of an 'actor' declaration. Its state is only seen by the
+// handler, which gets the messages sent to it one at a time, when the
+// interpreter is idle
+#[derive(Debug)]
+pub struct Actor {
+    pub name: EcoString,
+    pub handler: RtVal,
+}
+
+// Actors are only equal to themselves
+impl PartialEq for Actor {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// ----------
+//  Iterator
+// ----------
+// Lazy pipeline over a list or an int range, built by the 'iter' natives.
+// Nothing runs until it is consumed, by 'iter.collect', 'iter.sum', a
+// 'for' loop or a comprehension, and it runs again each time it is. Adding a stage gives
+// a new iterator, the previous one is unchanged
+#[derive(Debug, Clone)]
+pub struct Iter {
+    pub source: IterSource,
+    pub stages: Vec<IterStage>,
+}
+
+#[derive(Debug, Clone)]
+pub enum IterSource {
+    // Read when consumed, the elements pushed before are taken
+    List(Rc<RefCell<List>>),
+    // Start and excluded end
+    Range(i64, i64),
+    // Lines of the input of the interpreter, read one at a time
+    Stdin,
+}
+
+#[derive(Debug, Clone)]
+pub enum IterStage {
+    Map(RtVal),
+    Filter(RtVal),
+    Take(usize),
+    Skip(usize),
+}
+
+// Iterators are only equal to themselves
+impl PartialEq for Iter {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+enum Step {
+    Yield(RtVal),
+    Skip,
+}
+
+// Next line of the input without its line ending, None at its end
+fn read_line(interp: &mut Interpreter) -> Result<Option<EcoString>, PhyResult<InterpErr>> {
+    let mut line = String::new();
+
+    match interp.input.read_line(&mut line) {
+        Ok(0) => Ok(None),
+        Ok(_) => {
+            let line = line.strip_suffix('\n').unwrap_or(&line);
+            Ok(Some(line.strip_suffix('\r').unwrap_or(line).into()))
+        }
+        Err(e) => Err(PhyResult::new(InterpErr::ReadInput(e.to_string()), None)),
+    }
+}
+
+impl Iter {
+    pub fn with_stage(&self, stage: IterStage) -> Self {
+        let mut stages = self.stages.clone();
+        stages.push(stage);
+
+        Self {
+            source: self.source.clone(),
+            stages,
+        }
+    }
+
+    // Gives 'f' the values coming out of the last stage. Once a 'take'
+    // stage let all its values through, no more item is read
+    pub fn run(
+        &self,
+        interp: &mut Interpreter,
+        mut f: impl FnMut(&mut Interpreter, RtVal) -> Result<(), PhyResult<InterpErr>>,
+    ) -> Result<(), PhyResult<InterpErr>> {
+        let mut counts = vec![0; self.stages.len()];
+
+        for i in 0.. {
+            let done = self.stages.iter().zip(&counts).any(|(s, c)| matches!(s, IterStage::Take(n) if c >= n));
+            if done {
+                break;
+            }
+
+            let item = match &self.source {
+                IterSource::List(l) => match l.borrow().value.get(i) {
+                    Some(v) => v.clone(),
+                    None => break,
+                },
+                IterSource::Range(start, end) => match start.checked_add(i as i64) {
+                    Some(v) if v < *end => v.into(),
+                    _ => break,
+                },
+                IterSource::Stdin => match read_line(interp)? {
+                    Some(line) => line.into(),
+                    None => break,
+                },
+            };
+
+            if let Step::Yield(v) = self.pass(interp, item, &mut counts)? {
+                f(interp, v)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn collect(&self, interp: &mut Interpreter) -> Result<Vec<RtVal>, PhyResult<InterpErr>> {
+        let mut values = vec![];
+        self.run(interp, |_, v| {
+            values.push(v);
+            Ok(())
+        })?;
+
+        Ok(values)
+    }
+
+    fn pass(
+        &self,
+        interp: &mut Interpreter,
+        mut value: RtVal,
+        counts: &mut [usize],
+    ) -> Result<Step, PhyResult<InterpErr>> {
+        for (stage, count) in self.stages.iter().zip(counts) {
+            match stage {
+                IterStage::Map(f) => value = interp.call_function(f.clone(), vec![value])?,
+                IterStage::Filter(f) => match interp.call_function(f.clone(), vec![value.clone()])? {
+                    RtVal::BoolVal(b) if b.borrow().value => {}
+                    RtVal::BoolVal(_) => return Ok(Step::Skip),
+                    v => {
+                        return Err(PhyResult::new(
+                            InterpErr::NonBoolFilter(v.get_type_name().into()),
+                            None,
+                        ))
+                    }
+                },
+                IterStage::Take(_) => *count += 1,
+                IterStage::Skip(n) => {
+                    if *count < *n {
+                        *count += 1;
+                        return Ok(Step::Skip);
+                    }
+                }
+            }
+        }
+
+        Ok(Step::Yield(value))
+    }
+}
+
 // ------------
 //   Function
 // ------------
 #[derive(Debug)]
 pub struct Function {
     pub name: EcoString,
+    // Kept for 'help' and the ':doc' command of the REPL
+    pub doc: Option<EcoString>,
+    pub signature: EcoString,
     pub params: Rc<Vec<EcoString>>,
+    pub requires: Rc<Vec<Expr>>,
+    pub ensures: Rc<Vec<Expr>>,
     pub body: Rc<Vec<Stmt>>,
     pub closure: Rc<RefCell<Env>>,
 }
@@ -293,7 +1348,11 @@ impl RtVal {
     pub fn new_fn(value: &FnDeclStmt, closure: Rc<RefCell<Env>>) -> Self {
         RtVal::FuncVal(Rc::new(Function {
             name: value.name.clone(),
+            doc: value.doc.clone(),
+            signature: value.signature().into(),
             params: value.params.clone(),
+            requires: value.requires.clone(),
+            ensures: value.ensures.clone(),
             body: value.body.clone(),
             closure: closure.clone(),
         }))
@@ -306,29 +1365,33 @@ impl PartialEq for Function {
     }
 }
 
+impl Function {
+    // Scope of a call, with the parameters. Out of 'call', which is on the
+    // path of every script call
+    #[inline(never)]
+    fn call_env(&self, args: Vec<RtVal>) -> Result<EnvWrapper, PhyResult<RtValErr>> {
+        let mut env = Env::new(Some(self.closure.clone()));
+
+        for (p, v) in self.params.iter().zip(args) {
+            env.declare_var(p.clone(), v)
+                .map_err(|_| PhyResult::new(RtValErr::WrongFnParamDecl, None))?;
+        }
+
+        Ok(Rc::new(RefCell::new(env)))
+    }
+}
+
 impl Callable<RtValErr> for Function {
     fn call(
         &self,
         interpreter: &mut Interpreter,
         args: Vec<RtVal>,
     ) -> Result<RtVal, PhyResult<RtValErr>> {
-        let mut new_env = Env::new(Some(self.closure.clone()));
-
-        for (p, v) in self.params.iter().zip(args) {
-            new_env
-                .declare_var(p.clone(), v)
-                .map_err(|_| PhyResult::new(RtValErr::WrongFnParamDecl, None))?;
-        }
+        let new_env = self.call_env(args)?;
 
         match interpreter.execute_block_stmt(&self.body, new_env) {
             Ok(_) => Ok(RtVal::new_null()),
-            Err(e) => match e.err {
-                InterpErr::Return(v) => Ok(v),
-                _ => Err(PhyResult::new(
-                    RtValErr::FnExecution(e.err.to_string()),
-                    None,
-                )),
-            },
+            Err(e) => fn_result(e),
         }
     }
 
@@ -337,6 +1400,19 @@ impl Callable<RtValErr> for Function {
     }
 }
 
+// Value returned by the body, or its error as the one of the call. Out of
+// 'call', which is on the path of every script call
+#[inline(never)]
+fn fn_result(e: PhyResult<InterpErr>) -> Result<RtVal, PhyResult<RtValErr>> {
+    match e.err {
+        InterpErr::Return(v) => Ok(v),
+        InterpErr::ContractViolation { .. } | InterpErr::InNative(..) | InterpErr::CallDepth(..) => {
+            Err(PhyResult::new(RtValErr::Propagated(Box::new(e)), None))
+        }
+        _ => Err(PhyResult::new(RtValErr::FnExecution(Box::new(e)), None)),
+    }
+}
+
 // --------
 //   Into
 // --------
@@ -368,25 +1444,160 @@ impl From<String> for RtVal {
     }
 }
 
+impl From<char> for RtVal {
+    fn from(value: char) -> Self {
+        RtVal::CharVal(Rc::new(RefCell::new(Char { value })))
+    }
+}
+
+impl From<Vec<u8>> for RtVal {
+    fn from(value: Vec<u8>) -> Self {
+        RtVal::BytesVal(Rc::new(RefCell::new(Bytes { value })))
+    }
+}
+
 impl From<bool> for RtVal {
     fn from(value: bool) -> Self {
         RtVal::BoolVal(Rc::new(RefCell::new(Bool { value })))
     }
 }
 
+impl From<Complex> for RtVal {
+    fn from(value: Complex) -> Self {
+        RtVal::ComplexVal(Rc::new(RefCell::new(value)))
+    }
+}
+
+impl From<Vector> for RtVal {
+    fn from(value: Vector) -> Self {
+        RtVal::VectorVal(Rc::new(RefCell::new(value)))
+    }
+}
+
+impl From<Matrix> for RtVal {
+    fn from(value: Matrix) -> Self {
+        RtVal::MatrixVal(Rc::new(RefCell::new(value)))
+    }
+}
+
+impl From<Vec<RtVal>> for RtVal {
+    fn from(value: Vec<RtVal>) -> Self {
+        RtVal::ListVal(Rc::new(RefCell::new(List { value })))
+    }
+}
+
 // -----------
 //   Display
 // -----------
+// How the reals are written by 'print' and the REPL. By default, they are
+// written with the fewest digits giving back the same value, whatever the
+// platform or the locale, and in scientific notation when very large or
+// small
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DisplayConfig {
+    // Maximum number of significant digits
+    pub max_digits: Option<usize>,
+    // Reals whose exponent is at least this one, or at most its opposite,
+    // are written in scientific notation, like '1.5e-7'
+    pub sci_threshold: Option<u32>,
+}
+
+impl DisplayConfig {
+    pub fn format_real(&self, value: f64) -> String {
+        format_real_with(value, self.max_digits, self.sci_threshold)
+    }
+}
+
+// Value written with a display configuration
+pub struct Displayed<'a> {
+    value: &'a RtVal,
+    config: &'a DisplayConfig,
+}
+
+impl RtVal {
+    pub fn display<'a>(&'a self, config: &'a DisplayConfig) -> Displayed<'a> {
+        Displayed { value: self, config }
+    }
+}
+
 impl Display for RtVal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &self {
-            RtVal::IntVal(i) => write!(f, "{}", i.borrow().value),
-            RtVal::RealVal(r) => write!(f, "{}", r.borrow().value),
-            RtVal::BoolVal(b) => write!(f, "{}", b.borrow().value),
-            RtVal::StrVal(s) => write!(f, "\"{}\"", s.borrow().value),
-            RtVal::FuncVal(func) => write!(f, "<fn {}>", func.name),
-            RtVal::NativeFnVal(func) => write!(f, "{}", func),
-            RtVal::Null => write!(f, "null"),
+        write!(f, "{}", self.display(&DisplayConfig::default()))
+    }
+}
+
+impl Display for Displayed<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_value(f, self.value, self.config, &mut vec![])
+    }
+}
+
+// 'open' holds the lists being written, the ones containing the value. A
+// list found inside itself is written '[...]' instead of again
+fn write_value(
+    f: &mut std::fmt::Formatter<'_>,
+    value: &RtVal,
+    config: &DisplayConfig,
+    open: &mut Vec<*const RefCell<List>>,
+) -> std::fmt::Result {
+    let real = |r: f64| config.format_real(r);
+
+    match value {
+        RtVal::IntVal(i) => write!(f, "{}", i.borrow().value),
+        RtVal::RealVal(r) => write!(f, "{}", real(r.borrow().value)),
+        RtVal::QuantityVal(q) => write!(f, "{} {}", real(q.borrow().value), q.borrow().unit),
+        RtVal::ComplexVal(c) => {
+            let c = c.borrow();
+            match c.im < 0. {
+                true => write!(f, "{}-{}i", real(c.re), real(-c.im)),
+                false => write!(f, "{}+{}i", real(c.re), real(c.im)),
+            }
+        }
+        RtVal::BoolVal(b) => write!(f, "{}", b.borrow().value),
+        RtVal::StrVal(s) => write!(f, "\"{}\"", s.borrow().value),
+        RtVal::CharVal(c) => write!(f, "{:?}", c.borrow().value),
+        RtVal::BytesVal(b) => {
+            let items: Vec<String> = b.borrow().value.iter().map(|b| b.to_string()).collect();
+            write!(f, "bytes([{}])", items.join(", "))
+        }
+        RtVal::ListVal(l) if open.contains(&Rc::as_ptr(l)) => write!(f, "[...]"),
+        RtVal::ListVal(l) => {
+            open.push(Rc::as_ptr(l));
+            write!(f, "[")?;
+            for (i, v) in l.borrow().value.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write_value(f, v, config, open)?;
+            }
+            open.pop();
+            write!(f, "]")
+        }
+        RtVal::VectorVal(v) => {
+            let v = v.borrow();
+            let comps: Vec<String> = v.value.iter().map(|c| real(*c)).collect();
+            write!(f, "{}({})", v.type_name(), comps.join(", "))
+        }
+        RtVal::MatrixVal(m) => {
+            let rows: Vec<String> = m
+                .borrow()
+                .value
+                .iter()
+                .map(|r| {
+                    let cols: Vec<String> = r.iter().map(|c| real(*c)).collect();
+                    format!("[{}]", cols.join(", "))
+                })
+                .collect();
+            write!(f, "mat({})", rows.join(", "))
         }
+        RtVal::FuncVal(func) => write!(f, "<fn {}>", func.name),
+        RtVal::NativeFnVal(func) => write!(f, "{}", func),
+        RtVal::ModuleVal(m) => write!(f, "<module {}>", m.name),
+        RtVal::HostVal(h) => write!(f, "<{} object>", h.type_name()),
+        RtVal::TaskVal(t) => write!(f, "<task {}>", t.call.callee),
+        RtVal::ChanVal(_) => write!(f, "<chan>"),
+        RtVal::ActorVal(a) => write!(f, "<actor {}>", a.name),
+        RtVal::IterVal(_) => write!(f, "<iter>"),
+        RtVal::Null => write!(f, "null"),
     }
 }
@@ -0,0 +1,71 @@
+use std::{
+    env,
+    io::{self, IsTerminal},
+    str::FromStr,
+};
+
+// When the diagnostics and the other messages are colored. All the crates
+// color through 'colored', which follows the choice applied here once for
+// the whole process
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ColorChoice {
+    // When stderr, where the diagnostics go, is a terminal and NO_COLOR
+    // isn't set, so that CI logs and redirected errors stay clean
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled(self) -> bool {
+        // Any value but an empty one disables the colors, see no-color.org
+        let no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+
+        self.resolve(no_color, io::stderr().is_terminal())
+    }
+
+    pub fn apply(self) {
+        colored::control::set_override(self.enabled());
+    }
+
+    fn resolve(self, no_color: bool, terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => !no_color && terminal,
+        }
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!("expected 'auto', 'always' or 'never', found '{}'", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ColorChoice;
+
+    #[test]
+    fn choices() {
+        assert_eq!("always".parse(), Ok(ColorChoice::Always));
+        assert!("yes".parse::<ColorChoice>().is_err());
+
+        // Forced choices ignore the environment
+        assert!(ColorChoice::Always.resolve(true, false));
+        assert!(!ColorChoice::Never.resolve(false, true));
+
+        assert!(ColorChoice::Auto.resolve(false, true));
+        assert!(!ColorChoice::Auto.resolve(true, true));
+        assert!(!ColorChoice::Auto.resolve(false, false));
+    }
+}
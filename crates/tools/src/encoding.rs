@@ -0,0 +1,96 @@
+// Text encodings of binary data, used by the bytes natives. Encoding always
+// writes lowercase hex and padded base64, decoding returns None on any
+// invalid character or length
+
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Both cases are accepted, like 'ff' or 'FF'
+pub fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+pub fn encode_base64(bytes: &[u8]) -> String {
+    let mut res = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => res.push(BASE64_CHARS[(n >> (18 - 6 * i) & 0x3f) as usize] as char),
+                false => res.push('='),
+            }
+        }
+    }
+
+    res
+}
+
+// The padding is required, as written by 'encode_base64'
+pub fn decode_base64(base64: &str) -> Option<Vec<u8>> {
+    let base64 = base64.as_bytes();
+    if !base64.len().is_multiple_of(4) {
+        return None;
+    }
+
+    let mut res = Vec::with_capacity(base64.len() / 4 * 3);
+
+    for (idx, chunk) in base64.chunks(4).enumerate() {
+        let is_last = idx == base64.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|c| **c == b'=').count();
+        if padding > 2 || (padding > 0 && !is_last) {
+            return None;
+        }
+
+        let mut n = 0u32;
+        for (i, c) in chunk[..4 - padding].iter().enumerate() {
+            let value = BASE64_CHARS.iter().position(|b| b == c)? as u32;
+            n |= value << (18 - 6 * i);
+        }
+
+        res.extend(n.to_be_bytes()[1..4 - padding].iter());
+    }
+
+    Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_base64, decode_hex, encode_base64, encode_hex};
+
+    #[test]
+    fn round_trip() {
+        let data: Vec<&[u8]> = vec![b"", b"f", b"fo", b"foo", b"foob", &[0, 255, 16, 128]];
+
+        let hex: Vec<String> = data.iter().map(|d| encode_hex(d)).collect();
+        assert_eq!(hex, ["", "66", "666f", "666f6f", "666f6f62", "00ff1080"]);
+
+        let base64: Vec<String> = data.iter().map(|d| encode_base64(d)).collect();
+        assert_eq!(base64, ["", "Zg==", "Zm8=", "Zm9v", "Zm9vYg==", "AP8QgA=="]);
+
+        for (d, (h, b)) in data.iter().zip(hex.iter().zip(&base64)) {
+            assert_eq!(decode_hex(h).as_deref(), Some(*d));
+            assert_eq!(decode_base64(b).as_deref(), Some(*d));
+        }
+
+        assert_eq!(decode_hex("FF"), Some(vec![255]));
+        assert_eq!(decode_hex("f"), None);
+        assert_eq!(decode_hex("zz"), None);
+        assert_eq!(decode_hex("+f"), None);
+        assert_eq!(decode_base64("Zg="), None);
+        assert_eq!(decode_base64("Z=g="), None);
+        assert_eq!(decode_base64("Zg==Zg=="), None);
+        assert_eq!(decode_base64("Z*=="), None);
+    }
+}
@@ -1 +1,6 @@
-pub mod results;
\ No newline at end of file
+pub mod results;
+pub mod source_map;
+pub mod real_format;
+pub mod encoding;
+pub mod color;
+pub mod messages;
@@ -0,0 +1,333 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::results::Hint;
+
+// ----------
+//  Messages
+// ----------
+// Diagnostics are written in English next to their error enums. A locale
+// replaces them by code: the category of the error and the code of its
+// variant, like 'parser.MissingActorName', or the code of a label, like
+// 'label.parser' for the 'Parser error:' prefix. The templates refer to
+// the arguments of the error by position, '{0}', '{1}'... in the order
+// of the fields of the variant. Codes without a message keep the English
+// one, so a locale can be partial
+pub trait Locale {
+    fn message(&self, code: &str) -> Option<&str>;
+}
+
+// Messages as written next to the errors
+pub struct English;
+
+impl Locale for English {
+    fn message(&self, _: &str) -> Option<&str> {
+        None
+    }
+}
+
+// Error that a locale can translate. The code of a variant is part of the
+// catalogs, it doesn't change when the variant is renamed
+pub trait Translate: Display {
+    // Shared by the variants of the enum, like 'parser'
+    fn category(&self) -> &'static str;
+
+    // Like 'MissingActorName'
+    fn code(&self) -> &'static str;
+
+    fn args(&self) -> Vec<Arg> {
+        vec![]
+    }
+
+    // Message of a variant that only shows the error it wraps, translated
+    // in its place
+    fn wrapped(&self) -> Option<&Message> {
+        None
+    }
+}
+
+// Value of a placeholder. An error wrapped in another one is translated
+// with it
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arg {
+    Text(String),
+    Message(Box<Message>),
+}
+
+impl Arg {
+    pub fn text(value: impl Display) -> Self {
+        Arg::Text(value.to_string())
+    }
+
+    pub fn message(err: &impl Translate) -> Self {
+        Arg::Message(Box::new(Message::new(err)))
+    }
+
+    fn localize(&self, locale: &dyn Locale) -> String {
+        match self {
+            Arg::Text(t) => t.clone(),
+            Arg::Message(m) => m.localize(locale),
+        }
+    }
+}
+
+impl From<Message> for Arg {
+    fn from(msg: Message) -> Self {
+        Arg::Message(Box::new(msg))
+    }
+}
+
+// Message of an error kept by another one, like the failure of an
+// operation reported by the call that made it. The ones built from a text
+// have no code, they are the same in all the locales. The code and the
+// arguments are boxed to keep the errors holding a message small
+#[derive(Debug, Clone)]
+pub struct Message {
+    code: Option<Box<(String, Vec<Arg>)>>,
+    text: String,
+}
+
+impl Message {
+    pub fn new(err: &impl Translate) -> Self {
+        if let Some(msg) = err.wrapped() {
+            return msg.clone();
+        }
+
+        Self {
+            code: Some(Box::new((format!("{}.{}", err.category(), err.code()), err.args()))),
+            text: err.to_string(),
+        }
+    }
+
+    pub fn localize(&self, locale: &dyn Locale) -> String {
+        self.code
+            .as_ref()
+            .and_then(|c| translate(locale, &c.0, &c.1))
+            .unwrap_or_else(|| self.text.clone())
+    }
+}
+
+impl Display for Message {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+// Messages are the same if they read the same in English
+impl PartialEq for Message {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+impl From<String> for Message {
+    fn from(text: String) -> Self {
+        Self { code: None, text }
+    }
+}
+
+impl From<&str> for Message {
+    fn from(text: &str) -> Self {
+        text.to_string().into()
+    }
+}
+
+// Message of the error in the locale, or its own
+pub fn localize(locale: &dyn Locale, err: &impl Translate) -> String {
+    Message::new(err).localize(locale)
+}
+
+// Hint shown with the error in '--explain-errors' mode. Its advice and its
+// example are under the code of the error prefixed by 'hint.' and by
+// 'example.', like 'hint.interpreter.WrongArgsNb'
+pub fn localize_hint(
+    locale: &dyn Locale,
+    err: &impl Translate,
+    advice: String,
+    example: &str,
+) -> Hint {
+    let code = format!("{}.{}", err.category(), err.code());
+
+    Hint {
+        advice: translate(locale, &format!("hint.{}", code), &err.args()).unwrap_or(advice),
+        example: text(locale, &format!("example.{}", code), example),
+    }
+}
+
+// Fixed text, like the prefixes of the diagnostics
+pub fn text(locale: &dyn Locale, code: &str, default: &str) -> String {
+    translate(locale, code, &[]).unwrap_or_else(|| default.to_string())
+}
+
+fn translate(locale: &dyn Locale, code: &str, args: &[Arg]) -> Option<String> {
+    let template = locale.message(code)?;
+
+    Some(fill(template, &args.iter().map(|a| a.localize(locale)).collect::<Vec<_>>()))
+}
+
+// '{{' and '}}' are the braces themselves. Unknown placeholders are left
+// as they are, to be noticed by the translator
+fn fill(template: &str, args: &[String]) -> String {
+    let mut res = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(i) = rest.find(['{', '}']) {
+        res.push_str(&rest[..i]);
+        let tail = &rest[i..];
+
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            res.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+
+        let key = tail[1..].find('}').map(|end| &tail[1..end + 1]);
+        let value = key.and_then(|k| args.get(k.parse::<usize>().ok()?));
+
+        match (key, value) {
+            (Some(k), Some(arg)) => {
+                res.push_str(arg);
+                rest = &tail[k.len() + 2..];
+            }
+            _ => {
+                res.push_str(&tail[..1]);
+                rest = &tail[1..];
+            }
+        }
+    }
+
+    res.push_str(rest);
+    res
+}
+
+// ---------
+//  Catalog
+// ---------
+// Locale read from a file, one message per line: 'code = template'.
+// Empty lines and the ones starting with '#' are ignored
+#[derive(Debug, Default, Clone)]
+pub struct Catalog {
+    messages: HashMap<String, String>,
+}
+
+impl Catalog {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut catalog = Catalog::default();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match line.split_once('=') {
+                Some((code, template)) if !code.trim().is_empty() => {
+                    catalog.insert(code.trim(), template.trim())
+                }
+                _ => return Err(format!("expected 'code = message' at line {}", i + 1)),
+            }
+        }
+
+        Ok(catalog)
+    }
+
+    pub fn insert(&mut self, code: impl Into<String>, template: impl Into<String>) {
+        self.messages.insert(code.into(), template.into());
+    }
+}
+
+impl Locale for Catalog {
+    fn message(&self, code: &str) -> Option<&str> {
+        self.messages.get(code).map(|m| m.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use super::{fill, localize, text, Arg, Catalog, English, Locale, Message, Translate};
+
+    #[derive(Debug)]
+    enum TestErr {
+        Arity(String, usize),
+        Call(Message),
+        Native(Message),
+    }
+
+    impl fmt::Display for TestErr {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                TestErr::Arity(name, nb) => write!(f, "'{}' takes {} arguments", name, nb),
+                TestErr::Call(msg) => write!(f, "in call: {}", msg),
+                TestErr::Native(msg) => write!(f, "{}", msg),
+            }
+        }
+    }
+
+    impl Translate for TestErr {
+        fn category(&self) -> &'static str {
+            "test"
+        }
+
+        fn code(&self) -> &'static str {
+            match self {
+                TestErr::Arity(..) => "Arity",
+                TestErr::Call(_) => "Call",
+                TestErr::Native(_) => "Native",
+            }
+        }
+
+        fn args(&self) -> Vec<Arg> {
+            match self {
+                TestErr::Arity(name, nb) => vec![Arg::text(name), Arg::text(nb)],
+                TestErr::Call(msg) => vec![msg.clone().into()],
+                TestErr::Native(_) => vec![],
+            }
+        }
+
+        fn wrapped(&self) -> Option<&Message> {
+            match self {
+                TestErr::Native(msg) => Some(msg),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn templates() {
+        let args = ["a".to_string(), "2".to_string()];
+        assert_eq!(fill("'{0}' attend {1} arguments", &args), "'a' attend 2 arguments");
+        assert_eq!(fill("{{{1}}} {2} {name} {", &args), "{2} {2} {name} {");
+    }
+
+    #[test]
+    fn catalog() {
+        let catalog = Catalog::parse("# French\n\nlabel.parser = Erreur :\n").unwrap();
+        assert_eq!(catalog.message("label.parser"), Some("Erreur :"));
+        assert_eq!(catalog.message("label.lexer"), None);
+
+        assert!(Catalog::parse("label.parser").is_err());
+        assert!(Catalog::parse("= message").is_err());
+
+        let mut catalog = Catalog::default();
+        catalog.insert("test.Arity", "'{0}' attend {1} arguments");
+        catalog.insert("test.Call", "dans l'appel : {0}");
+
+        let err = TestErr::Arity("f".into(), 2);
+        assert_eq!(localize(&catalog, &err), "'f' attend 2 arguments");
+        assert_eq!(localize(&English, &err), "'f' takes 2 arguments");
+        assert_eq!(text(&catalog, "label.parser", "Parser error:"), "Parser error:");
+
+        // The wrapped errors are translated too, the texts stay the same
+        let err = TestErr::Call(Message::new(&err));
+        assert_eq!(localize(&catalog, &err), "dans l'appel : 'f' attend 2 arguments");
+
+        let err = TestErr::Native(Message::new(&TestErr::Arity("g".into(), 1)));
+        assert_eq!(localize(&catalog, &err), "'g' attend 1 arguments");
+
+        let err = TestErr::Call("file not found".into());
+        assert_eq!(localize(&catalog, &err), "dans l'appel : file not found");
+        assert_eq!(Message::new(&TestErr::Arity("f".into(), 2)), "'f' takes 2 arguments".into());
+    }
+}
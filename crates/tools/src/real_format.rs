@@ -0,0 +1,119 @@
+// Writing and reading of the reals, shared by the lexer, the interpreter
+// and the natives so that a written real reads back as the same value.
+// Neither depends on the locale: the decimal separator is always '.'
+
+// Reals whose exponent is in this range are written without one, like
+// 0.000001 or 100000000000000000000. The others like 1e21 or 1e-7
+const POSITIONAL_EXP: std::ops::Range<i32> = -6..21;
+
+// Fewest digits reading back as the same value, like 0.30000000000000004
+pub fn format_real(value: f64) -> String {
+    format_real_with(value, None, None)
+}
+
+// Real rounded to a maximum number of significant digits, in scientific
+// notation when the absolute value of its exponent is at least the
+// threshold. Without them, same as 'format_real'
+pub fn format_real_with(value: f64, max_digits: Option<usize>, sci_threshold: Option<u32>) -> String {
+    if !value.is_finite() {
+        return value.to_string();
+    }
+
+    // Exponent of the value once rounded to the maximum digits
+    let sci = match max_digits {
+        Some(d) => format!("{:.*e}", d.max(1) - 1, value),
+        None => format!("{:e}", value),
+    };
+    let (mantissa, exp) = sci.split_once('e').unwrap();
+    let exp: i32 = exp.parse().unwrap();
+
+    let scientific = value != 0.
+        && match sci_threshold {
+            Some(t) => exp.unsigned_abs() >= t,
+            None => !POSITIONAL_EXP.contains(&exp),
+        };
+
+    match (scientific, max_digits) {
+        (true, _) => format!("{}e{}", trim_decimals(mantissa), exp),
+        (false, Some(d)) => {
+            let decimals = (d.max(1) as i32 - 1 - exp).max(0) as usize;
+            trim_decimals(&format!("{:.*}", decimals, value)).to_string()
+        }
+        (false, None) => value.to_string(),
+    }
+}
+
+// Removes the trailing zeros of the decimal part, and the point if nothing
+// is left, as reals with no decimals are written like ints
+fn trim_decimals(real: &str) -> &str {
+    match real.contains('.') {
+        true => real.trim_end_matches('0').trim_end_matches('.'),
+        false => real,
+    }
+}
+
+// Reads the reals written by 'format_real' and the real literals, like
+// '2', '2.', '0.5' or '1.5e-7', with an optional '-'. Unlike the
+// standard parsing, 'inf', 'NaN', '+1' or '.5' aren't accepted
+pub fn parse_real(real: &str) -> Option<f64> {
+    let digits = real.strip_prefix('-').unwrap_or(real);
+    let (number, exp) = match digits.split_once(['e', 'E']) {
+        Some((number, exp)) => (number, Some(exp.strip_prefix(['+', '-']).unwrap_or(exp))),
+        None => (digits, None),
+    };
+    let (int, decimals) = number.split_once('.').unwrap_or((number, ""));
+
+    let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    let valid = !int.is_empty()
+        && is_digits(int)
+        && is_digits(decimals)
+        && exp.is_none_or(|e| !e.is_empty() && is_digits(e));
+
+    valid.then(|| real.parse().ok()).flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_real, format_real_with, parse_real};
+
+    #[test]
+    fn round_trip() {
+        let reals = [0.1 + 0.2, 1. / 3., 2., 0., 123456.789, 1e21, 1.5e-7, 0.000001, 5e-324, f64::MAX, -0.5];
+        let written = reals.map(format_real);
+
+        assert_eq!(
+            written,
+            [
+                "0.30000000000000004",
+                "0.3333333333333333",
+                "2",
+                "0",
+                "123456.789",
+                "1e21",
+                "1.5e-7",
+                "0.000001",
+                "5e-324",
+                "1.7976931348623157e308",
+                "-0.5",
+            ]
+        );
+        for (real, written) in reals.iter().zip(written) {
+            assert_eq!(parse_real(&written).map(f64::to_bits), Some(real.to_bits()));
+        }
+
+        assert_eq!(format_real_with(99999., Some(3), Some(4)), "1e5");
+        assert_eq!(format_real_with(0.1 + 0.2, Some(6), None), "0.3");
+        assert_eq!(format_real(f64::NAN), "NaN");
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(parse_real("2."), Some(2.));
+        assert_eq!(parse_real("1E+3"), Some(1000.));
+        assert_eq!(parse_real("-2.5e-1"), Some(-0.25));
+
+        for invalid in ["", "-", ".5", "+1", "1,5", "inf", "NaN", "1e", "1.2.3", " 1"] {
+            assert_eq!(parse_real(invalid), None, "{invalid}");
+        }
+    }
+}
@@ -1,6 +1,15 @@
-use std::collections::VecDeque;
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
 use colored::*;
 
+use crate::{
+    messages::{text, English, Locale},
+    source_map::SourceMap,
+};
+
 
 #[derive(Debug, PartialEq, Default, Clone)]
 pub struct Loc {
@@ -17,8 +26,28 @@ impl Loc {
     }
 }
 
+// Messages are written in the locale of the host
 pub trait PhyReport {
-    fn get_err_msg(&self) -> String;
+    fn get_err_msg(&self, locale: &dyn Locale) -> String;
+
+    fn get_hint(&self, _locale: &dyn Locale) -> Option<Hint> {
+        None
+    }
+}
+
+// Advice on a common error for beginners, with a small example of code
+// that works, shown with '--explain-errors'
+#[derive(Debug, PartialEq)]
+pub struct Hint {
+    pub advice: String,
+    pub example: String,
+}
+
+static EXPLAIN_ERRORS: AtomicBool = AtomicBool::new(false);
+
+// For the whole process, like the colors
+pub fn set_explain_errors(on: bool) {
+    EXPLAIN_ERRORS.store(on, Ordering::Relaxed);
 }
 
 struct ReportContext<'a> {
@@ -27,50 +56,102 @@ struct ReportContext<'a> {
     offset: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct PhyResult<T: PhyReport> {
     pub err: T,
     pub loc: Option<Loc>,
+    // Error this one comes from, like the failure inside a called function
+    pub cause: Option<Box<PhyResult<T>>>,
 }
 
 impl<'a, T: PhyReport> PhyResult<T> {
     pub fn new(err: T, loc: Option<Loc>) -> PhyResult<T> {
-        PhyResult { err, loc }
+        PhyResult { err, loc, cause: None }
     }
 
-    pub fn report(&self, file_name: &String, code: &str) {
-        // Error msg
-        println!("{}", self.err.get_err_msg());
+    pub fn with_cause(mut self, cause: PhyResult<T>) -> Self {
+        self.cause = Some(Box::new(cause));
+        self
+    }
+
+    // Innermost error of the chain, where it was raised
+    pub fn root_cause(&self) -> &PhyResult<T> {
+        match &self.cause {
+            Some(c) => c.root_cause(),
+            None => self,
+        }
+    }
+
+    // Diagnostics go to stderr, apart from the output of the script. One
+    // that can't be written, like when stderr is closed, is dropped
+    pub fn report(&self, sources: &SourceMap, locale: &dyn Locale) {
+        let _ = self.write_report(&mut io::stderr().lock(), sources, locale);
+    }
 
-        // Additional infos on location
-        if let Some(loc) = &self.loc {
-            let cx = self.get_context(code, loc);
-            let deco = self.get_decorators(&cx, loc);
+    pub fn write_report(
+        &self,
+        out: &mut dyn Write,
+        sources: &SourceMap,
+        locale: &dyn Locale,
+    ) -> io::Result<()> {
+        // Error msg
+        writeln!(out, "{}", self.err.get_err_msg(locale))?;
 
-            println!("  {} {} [line {}]", "-->".cyan(), file_name, cx.line);
+        self.write_snippet(out, sources)?;
 
-            for (i, line) in cx.snippets {
-                // If this line + 1 is % 10, the next one will be one digit
-                // longer, so we add a space before the smallest
-                let add_space = if (i + 1) % 10 == 0 { " " } else { "" };
+        let hint = self.err.get_hint(locale).filter(|_| EXPLAIN_ERRORS.load(Ordering::Relaxed));
+        if let Some(hint) = hint {
+            let label = format!("= {}", text(locale, "label.hint", "hint:"));
+            writeln!(out, "  {} {}", label.green(), hint.advice)?;
 
-                println!(" {} {}", format!("{}{} |", add_space, i).cyan(), line);
+            for line in hint.example.lines() {
+                writeln!(out, "      {}", line)?;
             }
+        }
+
+        Ok(())
+    }
+
+    fn write_snippet(&self, out: &mut dyn Write, sources: &SourceMap) -> io::Result<()> {
+        // Additional infos on location, with the code it comes from
+        let Some((file, loc)) = self
+            .loc
+            .as_ref()
+            .and_then(|l| sources.lookup(l).map(|f| (f, f.local_loc(l))))
+        else {
+            return Ok(());
+        };
+
+        let cx = self.get_context(&file.code, &loc);
+        let deco = self.get_decorators(&cx, &loc);
+
+        writeln!(out, "  {} {} [line {}]", "-->".cyan(), file.name, cx.line)?;
 
-            // Here, 4 is for space at the beginning and between line nb and '|' and space again
-            let margin = cx.line.to_string().len() + 4;
-            println!("{}{}", " ".repeat(margin), deco.red());
+        for (i, line) in cx.snippets {
+            // If this line + 1 is % 10, the next one will be one digit
+            // longer, so we add a space before the smallest
+            let add_space = if (i + 1) % 10 == 0 { " " } else { "" };
+
+            writeln!(out, " {} {}", format!("{}{} |", add_space, i).cyan(), line)?;
         }
+
+        // Here, 4 is for space at the beginning and between line nb and '|' and space again
+        let margin = cx.line.to_string().len() + 4;
+        writeln!(out, "{}{}", " ".repeat(margin), deco.red())
     }
 
-    fn get_context(&'a self, code: &'a str, loc: &Loc) -> ReportContext {
+    fn get_context(&self, code: &'a str, loc: &Loc) -> ReportContext<'a> {
         let mut offset: usize = 0;
         let mut lines: VecDeque<(usize, &'a str)> = VecDeque::new();
 
         for (i, line) in code.split('\n').enumerate() {
             lines.push_back((i + 1, line));
 
-            if loc.start >= offset && loc.start < offset + line.len() {
+            // Locations are in chars, not bytes. A location on the '\n'
+            // (or at the end of file) belongs to the line it ends
+            let len = line.chars().count();
+
+            if loc.start >= offset && loc.start <= offset + len {
                 return ReportContext {
                     line: i + 1,
                     snippets: lines,
@@ -82,13 +163,13 @@ impl<'a, T: PhyReport> PhyResult<T> {
                 }
 
                 // + 1 because we don't have '\n' anymore
-                offset += line.len() + 1;
+                offset += len + 1;
             }
         }
 
         panic!(
             "Code snippet not found while reporting error: {}",
-            self.err.get_err_msg()
+            self.err.get_err_msg(&English)
         )
     }
 
@@ -100,3 +181,54 @@ impl<'a, T: PhyReport> PhyResult<T> {
         decorators
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+
+    use super::{Loc, PhyReport, PhyResult};
+    use crate::{
+        messages::{English, Locale},
+        source_map::SourceMap,
+    };
+
+    #[derive(Debug)]
+    struct TestErr;
+
+    impl PhyReport for TestErr {
+        fn get_err_msg(&self, _: &dyn Locale) -> String {
+            "Test error: wrong".into()
+        }
+    }
+
+    struct ClosedPipe;
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn write_report() {
+        colored::control::set_override(false);
+
+        let mut sources = SourceMap::default();
+        sources.add("main.arc", "var a = 1\nvar b = c\n");
+        let err = PhyResult::new(TestErr, Some(Loc::new(18, 19)));
+
+        let mut out = vec![];
+        err.write_report(&mut out, &sources, &English).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Test error: wrong\n  --> main.arc [line 2]\n 1 | var a = 1\n 2 | var b = c\n             ^\n"
+        );
+
+        // The reader of the pipe exited, nothing to panic about
+        assert!(err.write_report(&mut ClosedPipe, &sources, &English).is_err());
+    }
+}
@@ -0,0 +1,85 @@
+use std::rc::Rc;
+
+use crate::results::Loc;
+
+
+// Every piece of code given to the lexer (a file, a REPL input, ...) is
+// registered here. Each one gets its own range of locations, so that any
+// Loc produced while lexing can be traced back to the text it comes from,
+// even long after it was lexed (errors in functions declared in a previous
+// REPL input for example)
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+#[derive(Debug)]
+pub struct SourceFile {
+    pub name: String,
+    pub code: Rc<str>,
+    // Location of the first char of the code in the map
+    pub start: usize,
+    pub len: usize,
+}
+
+impl SourceMap {
+    // Registers the code and returns the offset to give to the lexer
+    pub fn add(&mut self, name: &str, code: &str) -> usize {
+        // + 1 to keep a slot for the end of file token
+        let start = match self.files.last() {
+            Some(f) => f.start + f.len + 1,
+            None => 0,
+        };
+
+        self.files.push(SourceFile {
+            name: name.into(),
+            code: code.into(),
+            start,
+            len: code.chars().count(),
+        });
+
+        start
+    }
+
+    pub fn lookup(&self, loc: &Loc) -> Option<&SourceFile> {
+        self.files
+            .iter()
+            .rev()
+            .find(|f| loc.start >= f.start && loc.start <= f.start + f.len)
+    }
+}
+
+impl SourceFile {
+    // Location relative to the beginning of this file
+    pub fn local_loc(&self, loc: &Loc) -> Loc {
+        Loc::new(loc.start - self.start, loc.end - self.start)
+    }
+
+    // Line of the start of the location, from 1
+    pub fn line_of(&self, loc: &Loc) -> usize {
+        let start = self.local_loc(loc).start;
+        self.code.chars().take(start).filter(|c| *c == '\n').count() + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SourceMap;
+    use crate::results::Loc;
+
+    #[test]
+    fn lookup() {
+        let mut map = SourceMap::default();
+        assert_eq!(map.add("a", "var a = 1"), 0);
+        assert_eq!(map.add("b", "print(a)"), 10);
+
+        assert_eq!(map.lookup(&Loc::new(4, 5)).unwrap().name, "a");
+        assert_eq!(map.lookup(&Loc::new(9, 10)).unwrap().name, "a");
+        assert_eq!(map.lookup(&Loc::new(10, 15)).unwrap().name, "b");
+        assert_eq!(
+            map.lookup(&Loc::new(16, 17)).unwrap().local_loc(&Loc::new(16, 17)),
+            Loc::new(6, 7)
+        );
+        assert!(map.lookup(&Loc::new(25, 26)).is_none());
+    }
+}